@@ -0,0 +1,71 @@
+//! Throughput of result sanitization
+//!
+//! Every result passes through `sanitize_domain_result` before it reaches a
+//! sink, so sanitizing must stay cheap next to serializing the result:
+//!
+//! ```text
+//! cargo bench --bench sanitize
+//! ```
+//!
+//! Prints the time per result for sanitization and for JSON serialization,
+//! for results with and without error messages.
+
+use sentri::core::DomainResult;
+use sentri::sanitize::sanitize_domain_result;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Results sanitized per measurement
+const ITERATIONS: u32 = 200_000;
+
+fn result(error: Option<&str>) -> DomainResult {
+    DomainResult {
+        domain: "contoso.com".to_string(),
+        tenant: Some("contoso".to_string()),
+        federated_domains: vec![
+            "contoso.com".to_string(),
+            "contoso.onmicrosoft.com".to_string(),
+            "fabrikam.com".to_string(),
+        ],
+        mdi_instance: Some("contososensorapi.atp.azure.com".to_string()),
+        tags: [("owner".to_string(), "alice".to_string())].into(),
+        error: error.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+/// Average time of `f` over [`ITERATIONS`] runs
+fn measure(mut f: impl FnMut()) -> Duration {
+    // Warm up, which also compiles the lazily built patterns
+    for _ in 0..ITERATIONS / 10 {
+        f();
+    }
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    started.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let cases = [
+        ("success", result(None)),
+        (
+            "error",
+            result(Some(
+                "Failed to connect to 10.20.30.40:443 via [2001:db8::1]: see /var/log/sentri/scan.log or C:\\ProgramData\\sentri\\scan.log",
+            )),
+        ),
+    ];
+
+    println!("{:<10} {:>12} {:>12}", "result", "sanitize", "to_json");
+    for (name, result) in &cases {
+        let sanitize = measure(|| {
+            black_box(sanitize_domain_result(black_box(result)));
+        });
+        let to_json = measure(|| {
+            black_box(serde_json::to_string(black_box(result)).unwrap());
+        });
+        println!("{:<10} {:>12?} {:>12?}", name, sanitize, to_json);
+    }
+}
@@ -0,0 +1,42 @@
+//! Records the git commit and time of the build for `sentri::build_info`
+//!
+//! vergen sets `VERGEN_GIT_SHA` to the commit hash and
+//! `VERGEN_BUILD_TIMESTAMP` to an RFC 3339 timestamp. Outside a git checkout
+//! the commit is "unknown". Reproducible builds pin the timestamp with
+//! `SOURCE_DATE_EPOCH`; a value that is not a Unix timestamp is ignored with
+//! a warning instead of failing the build.
+
+use anyhow::Result;
+use std::env;
+use std::process::Command;
+use vergen_gitcl::{BuildBuilder, Emitter, GitclBuilder};
+
+fn main() -> Result<()> {
+    if let Ok(epoch) = env::var("SOURCE_DATE_EPOCH") {
+        if epoch.parse::<i64>().is_err() {
+            println!(
+                "cargo:warning=Ignoring SOURCE_DATE_EPOCH={:?}, which is not a Unix timestamp",
+                epoch
+            );
+            env::remove_var("SOURCE_DATE_EPOCH");
+        }
+    }
+    if env::var_os("VERGEN_GIT_SHA").is_none() && !in_git_checkout() {
+        env::set_var("VERGEN_GIT_SHA", "unknown");
+    }
+
+    let build = BuildBuilder::default().build_timestamp(true).build()?;
+    let git = GitclBuilder::default().sha(false).build()?;
+    Emitter::default()
+        .add_instructions(&build)?
+        .add_instructions(&git)?
+        .emit()
+}
+
+/// Whether git is installed and the crate is built from a checkout
+fn in_git_checkout() -> bool {
+    Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
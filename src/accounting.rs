@@ -0,0 +1,157 @@
+//! Per-customer usage accounting for batch runs
+//!
+//! MSSPs scanning on behalf of several customers can tag every domain of a
+//! batch input with the customer it belongs to, as a `customer=` tag or a
+//! bare second CSV column (see [`crate::tags`]):
+//!
+//! ```text
+//! domain,customer
+//! contoso.com,Acme Corp
+//! fabrikam.com,customer=Globex,bu=emea
+//! woodgrovebank.com
+//! ```
+//!
+//! With `sentri batch --usage-report usage.csv`, the requests and compute
+//! time of the run are attributed to these customers and written as one CSV
+//! row per customer when the batch ends (see
+//! [`UsageReportSink`](crate::sinks::usage_report::UsageReportSink)). A
+//! domain without a customer is billed as [`UNTAGGED`].
+//!
+//! A request is one domain check against Microsoft, the unit the rate limit
+//! of a batch is counted in. Results served from the cache and domains
+//! rejected by validation cost no request; their compute time is still
+//! counted.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::core::DomainResult;
+use crate::error_class::ErrorClass;
+use crate::tags::CUSTOMER_TAG;
+
+/// Customer of domains the input does not tag
+pub const UNTAGGED: &str = "(untagged)";
+
+/// Customer a result is billed to, [`UNTAGGED`] when its domain has none
+pub fn customer(result: &DomainResult) -> &str {
+    result
+        .tags
+        .get(CUSTOMER_TAG)
+        .map(String::as_str)
+        .unwrap_or(UNTAGGED)
+}
+
+/// Usage of one customer over a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerUsage {
+    /// Customer tag from the input
+    pub customer: String,
+    /// Results delivered for the customer's domains
+    pub domains: u64,
+    /// Domain checks sent to Microsoft
+    pub requests: u64,
+    /// Results served from the cache
+    pub cache_hits: u64,
+    /// Checks that ended in an error
+    pub failed: u64,
+    /// Processing time of all results in milliseconds
+    pub compute_ms: u64,
+    /// Start of the customer's first check
+    pub first_checked_at: DateTime<Utc>,
+    /// End of the customer's last check
+    pub last_completed_at: DateTime<Utc>,
+}
+
+impl CustomerUsage {
+    const CSV_HEADER: &'static str =
+        "customer,domains,requests,cache_hits,failed,compute_ms,first_checked_at,last_completed_at";
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{}",
+            crate::output::csv_cell(&self.customer),
+            self.domains,
+            self.requests,
+            self.cache_hits,
+            self.failed,
+            self.compute_ms,
+            self.first_checked_at.to_rfc3339(),
+            self.last_completed_at.to_rfc3339()
+        )
+    }
+}
+
+/// Usage of a run, aggregated per customer
+///
+/// # Examples
+///
+/// ```
+/// use sentri::accounting::UsageReport;
+/// use sentri::core::DomainResult;
+///
+/// let mut report = UsageReport::default();
+/// for (domain, customer) in [("contoso.com", Some("Acme")), ("fabrikam.com", Some("Acme")), ("woodgrovebank.com", None)] {
+///     let mut result = DomainResult { domain: domain.to_string(), processing_time_ms: 40, ..Default::default() };
+///     if let Some(customer) = customer {
+///         result.tags.insert("customer".to_string(), customer.to_string());
+///     }
+///     report.add(&result);
+/// }
+///
+/// let usage = report.usage();
+/// assert_eq!(usage[0].customer, "(untagged)");
+/// assert_eq!((usage[1].customer.as_str(), usage[1].requests, usage[1].compute_ms), ("Acme", 2, 80));
+/// ```
+#[derive(Debug, Default)]
+pub struct UsageReport {
+    customers: BTreeMap<String, CustomerUsage>,
+}
+
+impl UsageReport {
+    /// Attributes a result to the customer of its domain
+    pub fn add(&mut self, result: &DomainResult) {
+        let customer = customer(result);
+        let usage = self
+            .customers
+            .entry(customer.to_string())
+            .or_insert_with(|| CustomerUsage {
+                customer: customer.to_string(),
+                domains: 0,
+                requests: 0,
+                cache_hits: 0,
+                failed: 0,
+                compute_ms: 0,
+                first_checked_at: result.checked_at,
+                last_completed_at: result.completed_at,
+            });
+        usage.domains += 1;
+        if result.from_cache {
+            usage.cache_hits += 1;
+        } else if result.error_class != Some(ErrorClass::InvalidDomain) {
+            usage.requests += 1;
+        }
+        if result.error.is_some() {
+            usage.failed += 1;
+        }
+        usage.compute_ms += result.processing_time_ms;
+        usage.first_checked_at = usage.first_checked_at.min(result.checked_at);
+        usage.last_completed_at = usage.last_completed_at.max(result.completed_at);
+    }
+
+    /// Usage of every customer, sorted by customer
+    pub fn usage(&self) -> Vec<CustomerUsage> {
+        self.customers.values().cloned().collect()
+    }
+
+    /// Renders the report as CSV with a header row
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CustomerUsage::CSV_HEADER);
+        csv.push('\n');
+        for usage in self.customers.values() {
+            csv.push_str(&usage.csv_row());
+            csv.push('\n');
+        }
+        csv
+    }
+}
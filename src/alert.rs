@@ -0,0 +1,223 @@
+//! Incident alerting for watch mode
+//!
+//! Opens incidents in PagerDuty or Opsgenie when watch mode detects a change
+//! that needs human attention, such as a monitored domain losing its MDI
+//! instance. Every alert carries a deduplication key derived from the event
+//! kind and the domain, so a condition that persists across watch cycles
+//! updates one incident instead of paging repeatedly.
+//!
+//! # Security Considerations
+//!
+//! - Routing keys and API keys are only sent in request bodies/headers over HTTPS
+//!   (security:network:validate_ssl_certs)
+//! - Alert payloads are built from sanitized results only
+//!   (security:output:sanitize_all_output)
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde_json::json;
+use std::time::Duration;
+use tracing::info;
+
+use crate::cli::AlertArgs;
+use crate::http::HttpClient;
+use crate::watch::WatchEvent;
+
+/// PagerDuty Events API v2 endpoint
+pub const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Default Opsgenie alert API endpoint (US instance)
+pub const OPSGENIE_ALERTS_URL: &str = "https://api.opsgenie.com/v2/alerts";
+
+/// Destination for incidents raised by watch mode
+#[async_trait]
+pub trait Alerter: Send + Sync {
+    /// Short human-readable name used in logs and error context
+    fn name(&self) -> &str;
+
+    /// Opens (or updates) the incident for a single event
+    async fn trigger(&self, event: &WatchEvent) -> Result<()>;
+}
+
+/// Alerter opening incidents through the PagerDuty Events API v2
+pub struct PagerDutyAlerter {
+    client: HttpClient,
+    routing_key: String,
+}
+
+impl PagerDutyAlerter {
+    /// Creates a new PagerDuty alerter
+    ///
+    /// # Arguments
+    /// * `routing_key` - Integration key of the Events API v2 service
+    /// * `timeout` - Request timeout
+    pub fn new(routing_key: &str, timeout: Duration) -> Result<Self> {
+        crate::egress::allow_url(PAGERDUTY_EVENTS_URL);
+        Ok(Self {
+            client: alert_client(timeout)?,
+            routing_key: routing_key.to_string(),
+        })
+    }
+
+    /// Builds the Events API v2 payload for an event
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::alert::PagerDutyAlerter;
+    /// use sentri::watch::WatchEvent;
+    /// use std::time::Duration;
+    ///
+    /// let alerter = PagerDutyAlerter::new("routing-key", Duration::from_secs(5)).unwrap();
+    /// let payload = alerter.payload(&WatchEvent::MdiLost {
+    ///     domain: "contoso.com".to_string(),
+    ///     previous_instance: "contososensorapi.atp.azure.com".to_string(),
+    /// });
+    /// assert_eq!(payload["dedup_key"], "sentri:mdi-lost:contoso.com");
+    /// ```
+    pub fn payload(&self, event: &WatchEvent) -> serde_json::Value {
+        json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "dedup_key": event.dedup_key(),
+            "payload": {
+                "summary": event.summary(),
+                "source": event.domain(),
+                "severity": event.severity(),
+                "component": "sentri",
+                "custom_details": event,
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl Alerter for PagerDutyAlerter {
+    fn name(&self) -> &str {
+        "pagerduty"
+    }
+
+    async fn trigger(&self, event: &WatchEvent) -> Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        self.client
+            .post(
+                PAGERDUTY_EVENTS_URL,
+                headers,
+                &self.payload(event).to_string(),
+            )
+            .await
+            .context("Failed to trigger PagerDuty event")?;
+
+        info!("PagerDuty incident triggered: {}", event.dedup_key());
+        Ok(())
+    }
+}
+
+/// Alerter opening alerts through the Opsgenie Alert API
+pub struct OpsgenieAlerter {
+    client: HttpClient,
+    api_key: String,
+    url: String,
+}
+
+impl OpsgenieAlerter {
+    /// Creates a new Opsgenie alerter
+    ///
+    /// # Arguments
+    /// * `api_key` - Opsgenie API integration key
+    /// * `url` - Alert API endpoint, e.g. the EU instance URL
+    /// * `timeout` - Request timeout
+    pub fn new(api_key: &str, url: &str, timeout: Duration) -> Result<Self> {
+        crate::egress::allow_url(url);
+        Ok(Self {
+            client: alert_client(timeout)?,
+            api_key: api_key.to_string(),
+            url: url.to_string(),
+        })
+    }
+
+    /// Builds the Alert API payload for an event
+    ///
+    /// Opsgenie deduplicates open alerts sharing the same `alias`.
+    pub fn payload(&self, event: &WatchEvent) -> serde_json::Value {
+        let priority = match event.severity() {
+            "critical" => "P1",
+            "error" => "P2",
+            _ => "P3",
+        };
+
+        json!({
+            "message": event.summary(),
+            "alias": event.dedup_key(),
+            "description": event.summary(),
+            "source": "sentri",
+            "entity": event.domain(),
+            "priority": priority,
+            "details": {
+                "domain": event.domain(),
+                "event": event.kind(),
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl Alerter for OpsgenieAlerter {
+    fn name(&self) -> &str {
+        "opsgenie"
+    }
+
+    async fn trigger(&self, event: &WatchEvent) -> Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("GenieKey {}", self.api_key))?,
+        );
+
+        self.client
+            .post(&self.url, headers, &self.payload(event).to_string())
+            .await
+            .context("Failed to create Opsgenie alert")?;
+
+        info!("Opsgenie alert created: {}", event.dedup_key());
+        Ok(())
+    }
+}
+
+/// Creates an HTTP client suitable for third-party alerting APIs
+fn alert_client(timeout: Duration) -> Result<HttpClient> {
+    HttpClient::builder()
+        .timeout(timeout)
+        .http2_prior_knowledge(false)
+        .build()
+}
+
+/// Builds the alerters requested on the command line
+///
+/// # Arguments
+/// * `args` - Alerting options parsed from the command line
+/// * `timeout` - Request timeout for alerting calls
+///
+/// # Returns
+/// * `Result<Vec<Box<dyn Alerter>>>` - Configured alerters, possibly empty
+pub fn build_alerters(args: &AlertArgs, timeout: Duration) -> Result<Vec<Box<dyn Alerter>>> {
+    let mut alerters: Vec<Box<dyn Alerter>> = Vec::new();
+
+    if let Some(routing_key) = &args.pagerduty_routing_key {
+        alerters.push(Box::new(PagerDutyAlerter::new(routing_key, timeout)?));
+    }
+
+    if let Some(api_key) = &args.opsgenie_api_key {
+        alerters.push(Box::new(OpsgenieAlerter::new(
+            api_key,
+            &args.opsgenie_url,
+            timeout,
+        )?));
+    }
+
+    Ok(alerters)
+}
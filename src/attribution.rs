@@ -0,0 +1,286 @@
+//! Attribution of resolved IP addresses to Microsoft address space
+//!
+//! Federated domains normally receive mail through Exchange Online and
+//! publish their autodiscover endpoint on Microsoft infrastructure. A mail or
+//! identity endpoint that resolves outside Microsoft's address space is
+//! worth a second look: it may indicate a hybrid deployment, a third-party
+//! mail gateway, or a stale or hijacked record.
+//!
+//! Address ranges come from Microsoft's published service tags. A compact
+//! list of well-known Microsoft supernets is bundled as an offline fallback;
+//! a full `ServiceTags_Public` JSON file placed in the data directory (see
+//! `sentri update-data`) takes precedence.
+//!
+//! # Performance Considerations
+//!
+//! - Lookups use a longest-prefix scan over the loaded ranges; the bundled
+//!   list is small and a full service tag file holds a few tens of thousands
+//!   of prefixes, which is negligible next to the DNS round-trips
+//!   (performance:memory:avoid_unnecessary_allocations)
+
+use anyhow::{Context, Result};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::Path;
+use tracing::{debug, info};
+
+/// File name of the service tag JSON inside the data directory
+pub const SERVICE_TAGS_FILE: &str = "service-tags.json";
+
+/// Well-known Microsoft supernets bundled as an offline fallback
+///
+/// Deliberately conservative: only ranges registered to Microsoft are listed.
+/// The full published service tags are far more precise.
+const BUNDLED_RANGES: &[(&str, &str)] = &[
+    // Exchange Online / Exchange Online Protection
+    ("40.92.0.0/15", "ExchangeOnline"),
+    ("40.107.0.0/16", "ExchangeOnline"),
+    ("52.96.0.0/14", "ExchangeOnline"),
+    ("52.100.0.0/14", "ExchangeOnline"),
+    ("104.47.0.0/17", "ExchangeOnline"),
+    ("2a01:111:f400::/48", "ExchangeOnline"),
+    // Entra ID (Azure Active Directory)
+    ("20.190.128.0/18", "AzureActiveDirectory"),
+    ("40.126.0.0/18", "AzureActiveDirectory"),
+    // General Microsoft and Azure address space
+    ("13.64.0.0/11", "Microsoft"),
+    ("13.104.0.0/14", "Microsoft"),
+    ("20.33.0.0/16", "Microsoft"),
+    ("20.34.0.0/15", "Microsoft"),
+    ("20.36.0.0/14", "Microsoft"),
+    ("20.40.0.0/13", "Microsoft"),
+    ("20.48.0.0/12", "Microsoft"),
+    ("20.64.0.0/10", "Microsoft"),
+    ("20.128.0.0/16", "Microsoft"),
+    ("20.135.0.0/16", "Microsoft"),
+    ("20.136.0.0/16", "Microsoft"),
+    ("20.150.0.0/15", "Microsoft"),
+    ("20.152.0.0/15", "Microsoft"),
+    ("20.157.0.0/16", "Microsoft"),
+    ("20.160.0.0/12", "Microsoft"),
+    ("20.176.0.0/14", "Microsoft"),
+    ("20.180.0.0/14", "Microsoft"),
+    ("20.184.0.0/13", "Microsoft"),
+    ("20.192.0.0/10", "Microsoft"),
+    ("23.96.0.0/13", "Microsoft"),
+    ("40.64.0.0/10", "Microsoft"),
+    ("51.4.0.0/15", "Microsoft"),
+    ("51.8.0.0/16", "Microsoft"),
+    ("51.10.0.0/15", "Microsoft"),
+    ("51.103.0.0/16", "Microsoft"),
+    ("51.104.0.0/15", "Microsoft"),
+    ("51.116.0.0/16", "Microsoft"),
+    ("51.120.0.0/16", "Microsoft"),
+    ("51.124.0.0/16", "Microsoft"),
+    ("51.132.0.0/16", "Microsoft"),
+    ("51.136.0.0/15", "Microsoft"),
+    ("51.138.0.0/16", "Microsoft"),
+    ("51.140.0.0/14", "Microsoft"),
+    ("51.144.0.0/15", "Microsoft"),
+    ("52.96.0.0/12", "Microsoft"),
+    ("52.112.0.0/14", "Microsoft"),
+    ("52.120.0.0/14", "Microsoft"),
+    ("52.125.0.0/16", "Microsoft"),
+    ("52.126.0.0/15", "Microsoft"),
+    ("52.136.0.0/13", "Microsoft"),
+    ("52.148.0.0/14", "Microsoft"),
+    ("52.152.0.0/13", "Microsoft"),
+    ("52.160.0.0/11", "Microsoft"),
+    ("52.224.0.0/11", "Microsoft"),
+    ("65.52.0.0/14", "Microsoft"),
+    ("70.37.0.0/17", "Microsoft"),
+    ("94.245.64.0/18", "Microsoft"),
+    ("104.40.0.0/13", "Microsoft"),
+    ("104.146.0.0/15", "Microsoft"),
+    ("104.208.0.0/13", "Microsoft"),
+    ("131.253.0.0/16", "Microsoft"),
+    ("134.170.0.0/16", "Microsoft"),
+    ("137.116.0.0/15", "Microsoft"),
+    ("137.135.0.0/16", "Microsoft"),
+    ("138.91.0.0/16", "Microsoft"),
+    ("157.54.0.0/15", "Microsoft"),
+    ("157.56.0.0/14", "Microsoft"),
+    ("157.60.0.0/16", "Microsoft"),
+    ("168.61.0.0/16", "Microsoft"),
+    ("168.62.0.0/15", "Microsoft"),
+    ("191.232.0.0/13", "Microsoft"),
+    ("207.46.0.0/16", "Microsoft"),
+    ("2603:1000::/24", "Microsoft"),
+    ("2620:1ec::/36", "Microsoft"),
+    ("2a01:111::/32", "Microsoft"),
+];
+
+/// Kind of endpoint checked for a federated domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndpointKind {
+    /// Mail exchanger (MX record target)
+    Mx,
+    /// Autodiscover host (`autodiscover.<domain>`)
+    Autodiscover,
+}
+
+/// An endpoint of a federated domain that resolves outside Microsoft
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointAnomaly {
+    /// Federated domain the endpoint belongs to
+    pub domain: String,
+    /// Kind of endpoint
+    pub kind: EndpointKind,
+    /// Host name of the endpoint
+    pub host: String,
+    /// Resolved addresses outside Microsoft address space
+    pub addresses: Vec<IpAddr>,
+}
+
+/// Microsoft IP ranges used for attribution
+#[derive(Debug, Clone)]
+pub struct IpRanges {
+    ranges: Vec<(IpNet, String)>,
+}
+
+/// Top-level structure of Microsoft's service tag download
+#[derive(Deserialize)]
+struct ServiceTags {
+    values: Vec<ServiceTag>,
+}
+
+#[derive(Deserialize)]
+struct ServiceTag {
+    name: String,
+    properties: ServiceTagProperties,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServiceTagProperties {
+    #[serde(default)]
+    address_prefixes: Vec<String>,
+}
+
+impl IpRanges {
+    /// Returns the bundled fallback ranges
+    pub fn bundled() -> Self {
+        Self {
+            ranges: BUNDLED_RANGES
+                .iter()
+                .filter_map(|(prefix, tag)| Some((prefix.parse().ok()?, tag.to_string())))
+                .collect(),
+        }
+    }
+
+    /// Parses a Microsoft service tag JSON document (`ServiceTags_Public_*.json`)
+    ///
+    /// # Errors
+    /// * The document is not valid service tag JSON or contains no prefixes
+    pub fn from_service_tags_json(content: &str) -> Result<Self> {
+        let tags: ServiceTags =
+            serde_json::from_str(content).context("Invalid service tag document")?;
+
+        let ranges: Vec<(IpNet, String)> = tags
+            .values
+            .into_iter()
+            .flat_map(|tag| {
+                let name = tag.name;
+                tag.properties
+                    .address_prefixes
+                    .into_iter()
+                    .filter_map(move |prefix| Some((prefix.parse().ok()?, name.clone())))
+            })
+            .collect();
+
+        if ranges.is_empty() {
+            anyhow::bail!("Service tag document contains no address prefixes");
+        }
+        Ok(Self { ranges })
+    }
+
+    /// Loads the ranges from the data directory, falling back to the bundled list
+    ///
+    /// # Arguments
+    /// * `data_dir` - Directory that may contain a downloaded service tag file
+    pub fn load(data_dir: Option<&Path>) -> Result<Self> {
+        if let Some(path) = data_dir.map(|dir| dir.join(SERVICE_TAGS_FILE)) {
+            if path.exists() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let ranges = Self::from_service_tags_json(&content)
+                    .with_context(|| format!("Failed to load {}", path.display()))?;
+                info!(
+                    "Loaded {} Microsoft IP ranges from {}",
+                    ranges.len(),
+                    path.display()
+                );
+                return Ok(ranges);
+            }
+            debug!("{} not found, using bundled IP ranges", path.display());
+        }
+        Ok(Self::bundled())
+    }
+
+    /// Number of loaded prefixes
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Returns true if no prefixes are loaded
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Attributes an address to the most specific matching service tag
+    ///
+    /// # Returns
+    /// * `Option<&str>` - The service tag, or None for non-Microsoft addresses
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::attribution::IpRanges;
+    ///
+    /// let ranges = IpRanges::bundled();
+    /// assert_eq!(ranges.attribute("40.107.1.1".parse().unwrap()), Some("ExchangeOnline"));
+    /// assert_eq!(ranges.attribute("192.0.2.1".parse().unwrap()), None);
+    /// ```
+    pub fn attribute(&self, ip: IpAddr) -> Option<&str> {
+        self.ranges
+            .iter()
+            .filter(|(net, _)| net.contains(&ip))
+            .max_by_key(|(net, _)| net.prefix_len())
+            .map(|(_, tag)| tag.as_str())
+    }
+
+    /// Returns true if the address is in Microsoft address space
+    pub fn is_microsoft(&self, ip: IpAddr) -> bool {
+        self.attribute(ip).is_some()
+    }
+
+    /// Builds an anomaly for an endpoint if any of its addresses are external
+    ///
+    /// # Arguments
+    /// * `domain` - Federated domain owning the endpoint
+    /// * `kind` - Kind of endpoint
+    /// * `host` - Endpoint host name
+    /// * `addresses` - Resolved addresses of the host
+    pub fn check_endpoint(
+        &self,
+        domain: &str,
+        kind: EndpointKind,
+        host: &str,
+        addresses: &[IpAddr],
+    ) -> Option<EndpointAnomaly> {
+        let external: Vec<IpAddr> = addresses
+            .iter()
+            .copied()
+            .filter(|ip| !self.is_microsoft(*ip))
+            .collect();
+
+        (!external.is_empty()).then(|| EndpointAnomaly {
+            domain: domain.to_string(),
+            kind,
+            host: host.to_string(),
+            addresses: external,
+        })
+    }
+}
@@ -0,0 +1,256 @@
+//! Approved baseline snapshots and drift detection
+//!
+//! A baseline records the approved tenant, federation and MDI state of every
+//! domain in a scan. Later scans are compared against it and every deviation
+//! is reported as a [`Drift`], giving compliance workflows a simple
+//! "is anything different from what we signed off?" check.
+//!
+//! Domains whose scan failed are not compared, so transient errors never
+//! show up as drift.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::core::{DomainResult, MdiGeneration};
+
+/// Approved state of a single domain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainState {
+    /// Microsoft tenant, if any
+    pub tenant: Option<String>,
+    /// Federated domains, sorted
+    pub federated_domains: BTreeSet<String>,
+    /// MDI instance, if any
+    pub mdi_instance: Option<String>,
+    /// MDI sensor endpoint generation, if any
+    pub mdi_generation: Option<MdiGeneration>,
+}
+
+impl From<&DomainResult> for DomainState {
+    fn from(result: &DomainResult) -> Self {
+        Self {
+            tenant: result.tenant.clone(),
+            federated_domains: result.federated_domains.iter().cloned().collect(),
+            mdi_instance: result.mdi_instance.clone(),
+            mdi_generation: result.mdi_generation,
+        }
+    }
+}
+
+/// An approved snapshot of scan results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Baseline {
+    /// When the snapshot was taken
+    pub created_at: DateTime<Utc>,
+    /// Approved state per domain
+    pub domains: BTreeMap<String, DomainState>,
+}
+
+/// A difference between the baseline and a later scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "drift", rename_all = "snake_case")]
+pub enum Drift {
+    /// A baselined domain is absent from the scan (a tombstone for downstream stores)
+    DomainMissing {
+        /// Domain
+        domain: String,
+        /// When the domain was last seen, i.e. when the baseline was taken
+        last_seen: DateTime<Utc>,
+    },
+    /// A scanned domain is not part of the baseline
+    DomainAdded {
+        /// Domain
+        domain: String,
+    },
+    /// The domain resolves to a different tenant
+    TenantChanged {
+        /// Domain
+        domain: String,
+        /// Approved tenant
+        expected: Option<String>,
+        /// Observed tenant
+        actual: Option<String>,
+    },
+    /// Domains were added to or removed from the federation
+    FederationChanged {
+        /// Domain
+        domain: String,
+        /// Federated domains not in the baseline
+        added: Vec<String>,
+        /// Baselined federated domains no longer present
+        removed: Vec<String>,
+    },
+    /// The MDI instance appeared, disappeared or changed
+    MdiChanged {
+        /// Domain
+        domain: String,
+        /// Approved MDI instance
+        expected: Option<String>,
+        /// Observed MDI instance
+        actual: Option<String>,
+    },
+    /// The MDI sensor endpoint generation changed
+    GenerationChanged {
+        /// Domain
+        domain: String,
+        /// Approved generation
+        expected: Option<MdiGeneration>,
+        /// Observed generation
+        actual: Option<MdiGeneration>,
+    },
+}
+
+impl Baseline {
+    /// Creates a baseline taken now without any domains
+    pub fn empty() -> Self {
+        Self {
+            created_at: Utc::now(),
+            domains: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a baseline from scan results
+    ///
+    /// Failed results are left out, so they can neither be approved nor
+    /// later reported as missing.
+    pub fn from_results(results: &[DomainResult]) -> Self {
+        let mut baseline = Self::empty();
+        for result in results {
+            baseline.add(result);
+        }
+        baseline
+    }
+
+    /// Approves the state of a scanned domain, unless its scan failed
+    pub fn add(&mut self, result: &DomainResult) {
+        if result.error.is_none() {
+            self.domains
+                .insert(result.domain.clone(), DomainState::from(result));
+        }
+    }
+
+    /// Loads a baseline file
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid baseline file {}", path.display()))
+    }
+
+    /// Writes the baseline as pretty-printed JSON, suitable for review in version control
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        let content = format!("{}\n", serde_json::to_string_pretty(self)?);
+        tokio::fs::write(path, content)
+            .await
+            .with_context(|| format!("Failed to write baseline {}", path.display()))
+    }
+
+    /// Compares scan results against the baseline
+    ///
+    /// # Returns
+    /// * `Vec<Drift>` - Every deviation for baselined domains in domain order, followed
+    ///   by domains new to the scan; empty when the scan matches
+    pub fn check(&self, results: &[DomainResult]) -> Vec<Drift> {
+        let mut check = self.start_check();
+        for result in results {
+            check.add(result);
+        }
+        check.finish()
+    }
+
+    /// Starts comparing results read one at a time against the baseline
+    ///
+    /// Only the state of every scanned domain is kept, not the results.
+    pub fn start_check(&self) -> BaselineCheck<'_> {
+        BaselineCheck {
+            baseline: self,
+            scanned: BTreeMap::new(),
+        }
+    }
+}
+
+/// Comparison of streamed scan results against a [`Baseline`]
+///
+/// Created by [`Baseline::start_check`].
+pub struct BaselineCheck<'a> {
+    baseline: &'a Baseline,
+    /// State of every scanned domain; `None` if its scan failed
+    scanned: BTreeMap<String, Option<DomainState>>,
+}
+
+impl BaselineCheck<'_> {
+    /// Records a scan result; a later result of the same domain wins
+    pub fn add(&mut self, result: &DomainResult) {
+        let state = result.error.is_none().then(|| DomainState::from(result));
+        self.scanned.insert(result.domain.clone(), state);
+    }
+
+    /// Drifts of the recorded results, as returned by [`Baseline::check`]
+    pub fn finish(self) -> Vec<Drift> {
+        let baseline = self.baseline;
+        let mut drifts = Vec::new();
+
+        for (domain, expected) in &baseline.domains {
+            let Some(scanned) = self.scanned.get(domain) else {
+                drifts.push(Drift::DomainMissing {
+                    domain: domain.clone(),
+                    last_seen: baseline.created_at,
+                });
+                continue;
+            };
+            let Some(actual) = scanned else {
+                continue;
+            };
+
+            if actual.tenant != expected.tenant {
+                drifts.push(Drift::TenantChanged {
+                    domain: domain.clone(),
+                    expected: expected.tenant.clone(),
+                    actual: actual.tenant.clone(),
+                });
+            }
+            if actual.federated_domains != expected.federated_domains {
+                drifts.push(Drift::FederationChanged {
+                    domain: domain.clone(),
+                    added: actual
+                        .federated_domains
+                        .difference(&expected.federated_domains)
+                        .cloned()
+                        .collect(),
+                    removed: expected
+                        .federated_domains
+                        .difference(&actual.federated_domains)
+                        .cloned()
+                        .collect(),
+                });
+            }
+            if actual.mdi_instance != expected.mdi_instance {
+                drifts.push(Drift::MdiChanged {
+                    domain: domain.clone(),
+                    expected: expected.mdi_instance.clone(),
+                    actual: actual.mdi_instance.clone(),
+                });
+            } else if actual.mdi_generation != expected.mdi_generation {
+                drifts.push(Drift::GenerationChanged {
+                    domain: domain.clone(),
+                    expected: expected.mdi_generation,
+                    actual: actual.mdi_generation,
+                });
+            }
+        }
+
+        for (domain, state) in &self.scanned {
+            if state.is_some() && !baseline.domains.contains_key(domain) {
+                drifts.push(Drift::DomainAdded {
+                    domain: domain.clone(),
+                });
+            }
+        }
+
+        drifts
+    }
+}
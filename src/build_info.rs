@@ -0,0 +1,64 @@
+//! Build metadata identifying the running binary
+//!
+//! The version, the git commit the binary was built from and the build time
+//! are recorded at compile time, so a result can always be traced back to
+//! the exact code that produced it. They appear in `sentri --version`, the
+//! metadata of output formats that carry any (the grepable header, Parquet
+//! footers, Markdown summaries, traces) and the server's `/version`
+//! endpoint.
+//!
+//! Builds outside a git checkout, e.g. from a crates.io package, report no
+//! commit. Reproducible builds pin the build time with `SOURCE_DATE_EPOCH`.
+
+use serde::Serialize;
+
+/// Version of the crate
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Git commit hash of the build, or "unknown"
+pub const GIT_COMMIT: &str = env!("VERGEN_GIT_SHA");
+
+/// Time of the build, RFC 3339 in UTC
+pub const BUILD_DATE: &str = env!("VERGEN_BUILD_TIMESTAMP");
+
+/// Version with commit and build time, as printed by `sentri --version`
+pub const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (commit ",
+    env!("VERGEN_GIT_SHA"),
+    ", built ",
+    env!("VERGEN_BUILD_TIMESTAMP"),
+    ")"
+);
+
+/// Build metadata of the running binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    /// Version of the crate
+    pub version: &'static str,
+    /// Git commit hash, None if built outside a git checkout
+    pub commit: Option<&'static str>,
+    /// Time of the build, RFC 3339 in UTC
+    pub build_date: &'static str,
+}
+
+impl BuildInfo {
+    /// Metadata of this build
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::build_info::BuildInfo;
+    ///
+    /// let info = BuildInfo::current();
+    /// assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    /// assert!(chrono::DateTime::parse_from_rfc3339(info.build_date).is_ok());
+    /// ```
+    pub fn current() -> Self {
+        Self {
+            version: VERSION,
+            commit: (GIT_COMMIT != "unknown").then_some(GIT_COMMIT),
+            build_date: BUILD_DATE,
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Content-addressed capture of raw federation responses
+//!
+//! With `--capture-dir <DIR>`, every autodiscover federation response is kept
+//! as evidence next to the findings derived from it. Many domains of a tenant
+//! receive byte-identical responses, so responses are stored once under the
+//! SHA-256 of their body, and an index maps each domain to the hash of the
+//! response it received:
+//!
+//! ```text
+//! <DIR>/
+//!   index.jsonl                   {"domain": ..., "sha256": ..., "captured_at": ...} per response
+//!   responses/ab/abcdef...0123.xml  response bodies, named by their hash
+//! ```
+//!
+//! Evidence stays complete, since every domain has an index entry, while
+//! storage grows with the number of distinct responses only. The index is
+//! append-only, so one directory can collect several runs.
+//!
+//! With `--storage-key`, the index is an encrypted line file (see
+//! [`crate::encryption`]) and each body is encrypted on its own, bound to its
+//! hash, and stored as `<id>.xml.enc`, where `<id>` is a keyed hash of the
+//! body's hash, so file names do not reveal which responses were captured. A
+//! directory is either encrypted or not; runs with and without the key cannot
+//! share one.
+//!
+//! Results record the canonical hash of their response in `response_sha256`
+//! (see [`crate::xml::canonical_sha256`]). [`Evidence`] finds the captured
+//! response matching a result, which `sentri verify-evidence` does for a whole
+//! results file.
+//!
+//! # Security Considerations
+//!
+//! Responses are written as received; they are evidence and must not be
+//! altered. They are stored under names derived from their hash only, so no
+//! remote value influences a path (security:input:validate_all_input).
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::fs;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::encryption::{LineAppender, LineDecoder, StorageKey};
+use crate::xml::canonical_sha256;
+
+/// Name of the index file in a capture directory
+pub const INDEX_FILE: &str = "index.jsonl";
+
+/// Directory of response bodies in a capture directory
+pub const RESPONSES_DIR: &str = "responses";
+
+/// Index entry linking a domain to its captured response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureEntry {
+    /// Domain whose federation request produced the response
+    pub domain: String,
+    /// Lowercase hex SHA-256 of the response body
+    pub sha256: String,
+    /// When the response was received
+    pub captured_at: DateTime<Utc>,
+}
+
+/// Counts of a store's captures since it was opened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureStats {
+    /// Responses captured, one per index entry
+    pub captured: u64,
+    /// Captures whose body was already stored
+    pub deduplicated: u64,
+}
+
+/// Content-addressed store of federation responses
+pub struct ResponseStore {
+    dir: PathBuf,
+    key: Option<Arc<StorageKey>>,
+    index: Mutex<LineAppender>,
+    captured: AtomicU64,
+    deduplicated: AtomicU64,
+}
+
+impl ResponseStore {
+    /// Opens the capture directory `dir`, creating it if needed
+    ///
+    /// Responses are encrypted with `key` when given.
+    ///
+    /// # Errors
+    /// * The directory was created with a different key, or without one
+    pub async fn open(dir: &Path, key: Option<Arc<StorageKey>>) -> Result<Self> {
+        fs::create_dir_all(dir.join(RESPONSES_DIR))
+            .await
+            .with_context(|| format!("Failed to create capture directory {}", dir.display()))?;
+        let index = LineAppender::open(&dir.join(INDEX_FILE), key.clone())
+            .await
+            .with_context(|| format!("Failed to open capture index in {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            key,
+            index: Mutex::new(index),
+            captured: AtomicU64::new(0),
+            deduplicated: AtomicU64::new(0),
+        })
+    }
+
+    /// Stores the response `body` received for `domain`
+    ///
+    /// The body is only written if no identical response is stored yet; the
+    /// index entry is always appended.
+    ///
+    /// # Returns
+    /// * `Result<String>` - The hex SHA-256 of the body
+    pub async fn capture(&self, domain: &str, body: &str) -> Result<String> {
+        let sha256 = format!("{:x}", Sha256::digest(body.as_bytes()));
+        let path = self.response_path(&sha256);
+
+        if fs::try_exists(&path).await? {
+            self.deduplicated.fetch_add(1, Ordering::Relaxed);
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            let stored = match &self.key {
+                Some(key) => key.encrypt(body.as_bytes(), sha256.as_bytes())?,
+                None => body.as_bytes().to_vec(),
+            };
+            // Write under a unique name first so readers never see partial bodies
+            let partial = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+            fs::write(&partial, stored).await?;
+            fs::rename(&partial, &path)
+                .await
+                .with_context(|| format!("Failed to store response {}", sha256))?;
+        }
+
+        let entry = CaptureEntry {
+            domain: domain.to_string(),
+            sha256: sha256.clone(),
+            captured_at: Utc::now(),
+        };
+        let line = serde_json::to_string(&entry)?;
+        self.index.lock().await.append(&line).await?;
+        self.captured.fetch_add(1, Ordering::Relaxed);
+
+        Ok(sha256)
+    }
+
+    /// Path of the stored response with hash `sha256`
+    pub fn response_path(&self, sha256: &str) -> PathBuf {
+        stored_response_path(&self.dir, self.key.as_deref(), sha256)
+    }
+
+    /// Counts of captures since the store was opened
+    pub fn stats(&self) -> CaptureStats {
+        CaptureStats {
+            captured: self.captured.load(Ordering::Relaxed),
+            deduplicated: self.deduplicated.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Path of the response with hash `sha256` in the capture directory `dir`
+pub fn response_path(dir: &Path, sha256: &str) -> PathBuf {
+    let prefix = sha256.get(..2).unwrap_or(sha256);
+    dir.join(RESPONSES_DIR)
+        .join(prefix)
+        .join(format!("{}.xml", sha256))
+}
+
+/// Path of the encrypted response with hash `sha256` in the capture directory `dir`
+pub fn encrypted_response_path(dir: &Path, key: &StorageKey, sha256: &str) -> PathBuf {
+    let id = key.keyed_id(sha256);
+    dir.join(RESPONSES_DIR)
+        .join(&id[..2])
+        .join(format!("{}.xml.enc", id))
+}
+
+fn stored_response_path(dir: &Path, key: Option<&StorageKey>, sha256: &str) -> PathBuf {
+    match key {
+        Some(key) => encrypted_response_path(dir, key, sha256),
+        None => response_path(dir, sha256),
+    }
+}
+
+/// Reads every entry of the index of the capture directory `dir`
+///
+/// # Errors
+/// * The index is encrypted and `key` is not its key, or it was tampered with
+pub async fn read_index(dir: &Path, key: Option<&StorageKey>) -> Result<Vec<CaptureEntry>> {
+    let path = dir.join(INDEX_FILE);
+    let file = fs::File::open(&path)
+        .await
+        .with_context(|| format!("Failed to open capture index {}", path.display()))?;
+    let mut lines = BufReader::new(file).lines();
+    let mut decoder = LineDecoder::new();
+    let mut entries = Vec::new();
+    let mut line_number = 0;
+    while let Some(line) = lines.next_line().await? {
+        line_number += 1;
+        let line = decoder
+            .decode(key, &line)
+            .with_context(|| format!("Unreadable capture index entry on line {}", line_number))?;
+        let Some(line) = line.filter(|line| !line.trim().is_empty()) else {
+            continue;
+        };
+        let entry = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid capture index entry on line {}", line_number))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Captured responses of a capture directory, for verifying findings
+pub struct Evidence {
+    dir: PathBuf,
+    key: Option<Arc<StorageKey>>,
+    /// Hashes of the responses captured for each lowercase domain
+    responses: HashMap<String, Vec<String>>,
+    /// Canonical hashes of stored responses, computed on demand
+    canonical: HashMap<String, String>,
+}
+
+impl Evidence {
+    /// Loads the index of the capture directory `dir`, encrypted with `key` if given
+    pub async fn load(dir: &Path, key: Option<Arc<StorageKey>>) -> Result<Self> {
+        let mut responses: HashMap<String, Vec<String>> = HashMap::new();
+        for entry in read_index(dir, key.as_deref()).await? {
+            let hashes = responses
+                .entry(entry.domain.to_ascii_lowercase())
+                .or_default();
+            if !hashes.contains(&entry.sha256) {
+                hashes.push(entry.sha256);
+            }
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            key,
+            responses,
+            canonical: HashMap::new(),
+        })
+    }
+
+    /// Finds the response captured for `domain` whose canonical hash is `response_sha256`
+    ///
+    /// Stored responses that cannot be read or decrypted are skipped with a
+    /// warning, so one damaged file does not hide the domain's other captures.
+    ///
+    /// # Returns
+    /// * `Result<Option<PathBuf>>` - Path of the matching response; `None` if
+    ///   no captured response of the domain matches. An error if none of the
+    ///   domain's captured responses could be read
+    pub async fn find(&mut self, domain: &str, response_sha256: &str) -> Result<Option<PathBuf>> {
+        let Some(hashes) = self.responses.get(&domain.to_ascii_lowercase()) else {
+            return Ok(None);
+        };
+        let mut readable = 0;
+        let mut last_error = None;
+        for sha256 in hashes {
+            // Index entries come from disk; only well-formed hashes name files
+            if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+                continue;
+            }
+            let path = stored_response_path(&self.dir, self.key.as_deref(), sha256);
+            let canonical = match self.canonical.get(sha256) {
+                Some(canonical) => canonical.clone(),
+                None => match read_canonical(&path, self.key.as_deref(), sha256).await {
+                    Ok(canonical) => {
+                        self.canonical.insert(sha256.clone(), canonical.clone());
+                        canonical
+                    }
+                    Err(e) => {
+                        warn!("Skipping captured response of {}: {:#}", domain, e);
+                        last_error = Some(e);
+                        continue;
+                    }
+                },
+            };
+            readable += 1;
+            if canonical.eq_ignore_ascii_case(response_sha256) {
+                return Ok(Some(path));
+            }
+        }
+        match last_error {
+            Some(e) if readable == 0 => Err(e.context(format!(
+                "None of the responses captured for {} could be read",
+                domain
+            ))),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Reads the stored response at `path` and returns its canonical hash
+async fn read_canonical(path: &Path, key: Option<&StorageKey>, sha256: &str) -> Result<String> {
+    let stored = fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read response {}", path.display()))?;
+    let stored = match key {
+        Some(key) => key.decrypt(&stored, sha256.as_bytes()),
+        None => Ok(stored),
+    }
+    .with_context(|| format!("Failed to read response {}", path.display()))?;
+    let body = String::from_utf8(stored)
+        .with_context(|| format!("Response {} is not UTF-8", path.display()))?;
+    canonical_sha256(&body)
+}
@@ -60,8 +60,28 @@
 //!   and data validation issues
 //! - Propagation of underlying error information without leaking sensitive details
 
-use clap::{Parser, Subcommand};
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::data::DataSet;
+use crate::depth::Depth;
+use crate::dns::{DEFAULT_DNS_ATTEMPTS, DEFAULT_DNS_TIMEOUT_MS};
+use crate::dns_pipeline::DEFAULT_PIPELINE_DEPTH;
+use crate::dns_pool::{parse_upstream, Strategy};
+use crate::dns_privacy::{parse_ecs, Ecs};
+use crate::env_profile::{EnvProfile, Tuning};
+use crate::error_class::ErrorClass;
+use crate::graph::GraphFormat;
+use crate::http::{DEFAULT_ACCEPT_LANGUAGE, DEFAULT_MAX_RESPONSE_SIZE};
+use crate::output::compression::Compression;
+use crate::output::OutputFormat;
+use crate::rescan::RescanFilter;
+use crate::retention::parse_age;
+use crate::secrets::SecretResolver;
+use crate::upload::ServerSideEncryption;
 
 /// Main command-line interface structure for Sentri
 ///
@@ -145,20 +165,83 @@ use std::path::PathBuf;
 /// ## Creating a CLI instance programmatically
 ///
 /// ```no_run
-/// use sentri::cli::{Cli, Commands};
+/// use sentri::cli::{Cli, Commands, NotifyArgs, SinkArgs, UploadArgs};
 /// use std::path::PathBuf;
 /// use std::time::Duration;
 ///
 /// // Create a batch processing configuration with custom settings
 /// let cli_struct = Cli {
 ///     command: Commands::Batch {
-///         input_file: PathBuf::from("/path/to/domains.txt"),
+///         input_file: Some(PathBuf::from("/path/to/domains.txt")),
+///         from_results: None,
+///         only: None,
+///         retry_classes: vec![],
 ///         output_file: Some(PathBuf::from("/path/to/results.json")),
+///         append: false,
+///         index: false,
+///         compress: None,
+///         socket: None,
+///         policy: None,
+///         tenant_report: None,
+///         usage_report: None,
+///         rate_report: None,
+///         output_csv: None,
+///         output_parquet: None,
+///         output_sqlite: None,
+///         max_runtime: None,
+///         checkpoint_file: None,
 ///         chunk_size: 500,
 ///         rate_limit: 30,
+///         sinks: SinkArgs::default(),
+///         upload: UploadArgs::default(),
+///         notify: NotifyArgs::default(),
 ///     },
 ///     concurrent_requests: 50,
 ///     timeout_ms: 8000,
+///     attribute_ips: false,
+///     depth: Default::default(),
+///     data_dir: None,
+///     require_ownership: false,
+///     ownership_file: PathBuf::from("sentri-ownership.json"),
+///     engagement_id: None,
+///     operator: None,
+///     max_age: None,
+///     support_dir: None,
+///     seed: None,
+///     resolvers: vec![],
+///     resolver_strategy: Default::default(),
+///     ecs: None,
+///     dns_bind_address: None,
+///     dns_timeout_ms: 5000,
+///     dns_attempts: 2,
+///     dns_override: None,
+///     dnssec_negatives: false,
+///     dns_pipeline_workers: None,
+///     dns_pipeline_depth: 64,
+///     mdi_dns_rate_limit: None,
+///     mdi_dns_concurrency: None,
+///     roaming_retry: false,
+///     roaming_resolvers: vec![],
+///     offline: false,
+///     capture_dir: None,
+///     storage_key: None,
+///     strict_schema: false,
+///     max_response_size: 1024 * 1024,
+///     slow_host_threshold_ms: None,
+///     accept_language: "en-US".to_string(),
+///     expected_binary_sha256: None,
+///     strict_egress: false,
+///     egress_allow: vec![],
+///     trace_file: None,
+///     timestamp_bucket: None,
+///     fields: vec![],
+///     format: None,
+///     quiet: false,
+///     config: None,
+///     cache_size: None,
+///     env_profile: Default::default(),
+///     raise_fd_limit: false,
+///     tuning: None,
 /// };
 ///
 /// // These values would typically be passed to your core processing logic
@@ -188,7 +271,8 @@ use std::path::PathBuf;
 #[command(
     name = "sentri",
     about = "High-performance Microsoft Defender for Identity instance discovery tool",
-    version
+    version,
+    long_version = crate::build_info::LONG_VERSION
 )]
 pub struct Cli {
     /// Command to execute (single domain check or batch processing)
@@ -204,6 +288,253 @@ pub struct Cli {
     /// Increase this value when checking slow-responding domains
     #[arg(short = 't', long, default_value = "5000")]
     pub timeout_ms: u64,
+
+    /// Attribute federated mail/identity endpoints to Microsoft address space
+    /// Endpoints resolving outside Microsoft are reported as anomalies
+    #[arg(long, global = true)]
+    pub attribute_ips: bool,
+
+    /// Detectors run for every domain: `fast` (federation and tenant),
+    /// `standard` (+ MDI DNS lookups) or `deep` (+ endpoint attribution)
+    #[arg(long, global = true, value_enum, default_value_t = Depth::Standard, value_name = "LEVEL")]
+    pub depth: Depth,
+
+    /// Directory holding downloaded enrichment data (e.g. service tags)
+    /// Defaults to $XDG_DATA_HOME/sentri; bundled fallbacks are used for anything missing
+    #[arg(long, global = true)]
+    pub data_dir: Option<PathBuf>,
+
+    /// Only run intrusive detectors (e.g. --attribute-ips) against domains
+    /// verified with `sentri verify-ownership`
+    #[arg(long, global = true)]
+    pub require_ownership: bool,
+
+    /// File holding ownership tokens and verification state
+    #[arg(long, global = true, default_value = "sentri-ownership.json")]
+    pub ownership_file: PathBuf,
+
+    /// Identifier of the authorized engagement, recorded with every result
+    /// and audited request
+    #[arg(long, global = true)]
+    pub engagement_id: Option<String>,
+
+    /// Person or team performing the scan, recorded alongside the engagement
+    #[arg(long, global = true)]
+    pub operator: Option<String>,
+
+    /// Recheck domains whose cached result is older than this (e.g. 30m, 12h)
+    /// Cached results are reused for the lifetime of the process by default
+    #[arg(long, global = true, value_parser = parse_age)]
+    pub max_age: Option<Duration>,
+
+    /// Directory receiving diagnostic bundles when sentri crashes
+    /// Defaults to the `support` directory inside the data directory
+    #[arg(long, global = true)]
+    pub support_dir: Option<PathBuf>,
+
+    /// Seed for randomized behavior such as retry jitter, making runs reproducible
+    /// Meant for debugging; never affects tokens or keys
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    /// Upstream DNS resolver (IP or IP:port) used instead of the system configuration
+    /// Repeat to spread queries across several resolvers
+    #[arg(long = "resolver", global = true, value_parser = parse_upstream, value_delimiter = ',')]
+    pub resolvers: Vec<SocketAddr>,
+
+    /// How queries are distributed across several resolvers
+    #[arg(long, global = true, value_enum, default_value_t = Strategy::RoundRobin)]
+    pub resolver_strategy: Strategy,
+
+    /// EDNS Client Subnet sent with DNS queries: `off` or a subnet such as 198.51.100.0/24
+    /// `off` asks resolvers not to forward any part of this host's address
+    #[arg(long, global = true, value_parser = parse_ecs)]
+    pub ecs: Option<Ecs>,
+
+    /// Local address DNS queries are sent from; source ports stay randomized
+    #[arg(long, global = true)]
+    pub dns_bind_address: Option<IpAddr>,
+
+    /// Milliseconds to wait for a DNS answer before sending the query again
+    /// Raise it for slow corporate resolvers
+    #[arg(long, global = true, default_value_t = DEFAULT_DNS_TIMEOUT_MS,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    pub dns_timeout_ms: u64,
+
+    /// Times a DNS query is sent before the lookup fails
+    #[arg(long, global = true, default_value_t = DEFAULT_DNS_ATTEMPTS as u8,
+          value_parser = clap::value_parser!(u8).range(1..))]
+    pub dns_attempts: u8,
+
+    /// Hosts-style file of names answered with fixed addresses or NXDOMAIN
+    /// Consulted before real resolution, for DNS lookups and HTTP connections
+    #[arg(long, global = true)]
+    pub dns_override: Option<PathBuf>,
+
+    /// Confirm "no MDI" verdicts with DNSSEC: results of tenants whose signed zone proves
+    /// with NSEC records that no sensor hostname exists are marked `dnssec_confirmed`
+    /// Requires the `dnssec` feature
+    #[arg(long, global = true)]
+    pub dnssec_negatives: bool,
+
+    /// Resolve MDI sensor names through this many workers pipelining their queries over
+    /// one TCP connection per resolver, for batches of 100k+ tenants
+    /// Cannot be combined with --ecs or --dns-bind-address, which only apply to UDP
+    #[arg(long, global = true, value_parser = clap::value_parser!(u16).range(1..))]
+    pub dns_pipeline_workers: Option<u16>,
+
+    /// Names each pipeline worker sends at once
+    #[arg(long, global = true, default_value_t = DEFAULT_PIPELINE_DEPTH as u16,
+          value_parser = clap::value_parser!(u16).range(1..), requires = "dns_pipeline_workers")]
+    pub dns_pipeline_depth: u16,
+
+    /// DNS queries per minute of the MDI sensor lookups, which query Microsoft's zones
+    /// rather than the targets'; defaults to the --depth budget or [mdi_dns] in --config
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    pub mdi_dns_rate_limit: Option<u64>,
+
+    /// MDI sensor lookups in flight at once; defaults as --mdi-dns-rate-limit
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    pub mdi_dns_concurrency: Option<u64>,
+
+    /// Give domains failing for environmental reasons (timeouts, DNS, connections, throttling)
+    /// a last chance: once the rest is done, check them again through the alternate
+    /// autodiscover endpoint and the --roaming-resolver upstreams
+    #[arg(long, global = true)]
+    pub roaming_retry: bool,
+
+    /// Upstream DNS resolver (IP or IP:port) of --roaming-retry
+    /// Defaults to the system configuration with --resolver, to 1.1.1.1 and 9.9.9.9 otherwise
+    #[arg(
+        long = "roaming-resolver",
+        global = true,
+        value_parser = parse_upstream,
+        value_delimiter = ',',
+        requires = "roaming_retry"
+    )]
+    pub roaming_resolvers: Vec<SocketAddr>,
+
+    /// Fail every network operation immediately, for air-gapped analysis
+    /// Cached results and names listed in --dns-override are still answered
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    /// Keep every federation response in this directory as evidence
+    /// Identical responses are stored once; index.jsonl maps domains to them
+    #[arg(long, global = true)]
+    pub capture_dir: Option<PathBuf>,
+
+    /// Base64 AES-256 key encrypting stored data (or a secret reference)
+    /// Covers server results, --capture-dir, --output-sqlite and the audit log
+    #[arg(long, global = true)]
+    pub storage_key: Option<String>,
+
+    /// Validate federation responses against the bundled Autodiscover schema
+    /// Violations are reported as schema_warnings on each result
+    #[arg(long, global = true)]
+    pub strict_schema: bool,
+
+    /// Maximum size in bytes of an autodiscover response body
+    /// Larger responses are abandoned and reported as invalid_response failures
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_RESPONSE_SIZE)]
+    pub max_response_size: usize,
+
+    /// Flag hosts whose p95 latency exceeds this many milliseconds
+    /// Flagged hosts are time-boxed to this value for 5 minutes and reported in the batch summary
+    #[arg(long, global = true)]
+    pub slow_host_threshold_ms: Option<u64>,
+
+    /// Accept-Language sent with every request; fault strings follow it
+    /// Pinned so errors group the same regardless of the scanning host's locale
+    #[arg(long, global = true, default_value = DEFAULT_ACCEPT_LANGUAGE)]
+    pub accept_language: String,
+
+    /// Refuse to start unless the running binary has this SHA-256
+    /// Typically the hash published with a signed release; the check is audited by `serve`
+    #[arg(long, global = true, value_name = "HEX")]
+    pub expected_binary_sha256: Option<String>,
+
+    /// Refuse outbound connections to hosts outside the allowlist
+    /// Allowed are the Microsoft endpoints of the scan, configured sinks and --egress-allow
+    #[arg(long, global = true)]
+    pub strict_egress: bool,
+
+    /// Host to allow with --strict-egress; `*.example.com` allows subdomains
+    #[arg(
+        long = "egress-allow",
+        global = true,
+        value_name = "HOST",
+        value_delimiter = ','
+    )]
+    pub egress_allow: Vec<String>,
+
+    /// Write per-domain pipeline spans of `single` and `batch` to this file
+    /// Chrome trace-event format; open it in Perfetto to see where a scan spends time
+    #[arg(long, global = true, value_name = "PATH")]
+    pub trace_file: Option<PathBuf>,
+
+    /// Round timestamps of `single` and `batch` results down to this bucket (e.g. 1h, 1d)
+    /// Reduces the precision of operational metadata in result sets shared externally
+    #[arg(long, global = true, value_parser = parse_age, value_name = "AGE")]
+    pub timestamp_bucket: Option<Duration>,
+
+    /// Only write these fields of `single` and `batch` results in json/jsonl output,
+    /// e.g. domain,tenant,mdi_instance; see `sentri schema` for the field names
+    #[arg(long, global = true, value_delimiter = ',', value_name = "FIELDS")]
+    pub fields: Vec<String>,
+
+    /// Format of results printed by `single`, `batch`, `lookup` and `report`
+    /// Defaults to `json` on stdout and `jsonl` for output files; `junit`,
+    /// `grepable`, `parquet`, `markdown`, `xlsx`, `dot` and `graphml` write a single document
+    #[arg(long, global = true, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Write logs to stderr, so stdout only carries results
+    /// Combine with `--format ndjson` to pipe `batch` results into another tool
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// TOML file of settings without a flag, such as `[retry.http]`, `[windows]` and
+    /// `[[route]]`; `serve` and `watch` reload it on change or SIGHUP
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Maximum number of results kept in the result cache [default: unbounded]
+    /// Once full, further results are not cached
+    #[arg(long, global = true, value_name = "ENTRIES")]
+    pub cache_size: Option<usize>,
+
+    /// `auto` derives worker threads, concurrency, chunk and cache size from
+    /// the cores, memory and descriptor limit of the host; explicit options win
+    #[arg(long, global = true, value_enum, default_value_t = EnvProfile::Off, value_name = "PROFILE")]
+    pub env_profile: EnvProfile,
+
+    /// Raise the soft open file limit towards the hard limit when
+    /// --concurrent-requests needs more descriptors; concurrency is clamped otherwise
+    #[arg(long, global = true)]
+    pub raise_fd_limit: bool,
+
+    /// Defaults chosen by `--env-profile auto`, set while parsing
+    #[arg(skip)]
+    pub tuning: Option<Tuning>,
+}
+
+impl Cli {
+    /// Replaces secret references in credential options with the secrets
+    ///
+    /// # Errors
+    /// * A reference cannot be resolved
+    pub fn resolve_secrets(&mut self, resolver: &SecretResolver) -> Result<()> {
+        resolver.resolve_option(&mut self.storage_key)?;
+        match &mut self.command {
+            Commands::Batch { sinks, notify, .. } => {
+                sinks.resolve_secrets(resolver)?;
+                notify.resolve_secrets(resolver)
+            }
+            Commands::Watch { alerts, .. } => alerts.resolve_secrets(resolver),
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Available subcommands for the Sentri CLI
@@ -211,6 +542,16 @@ pub struct Cli {
 /// The tool supports two primary modes of operation, each optimized for different use cases:
 /// - `Single`: Checking a single domain interactively with detailed output
 /// - `Batch`: Processing multiple domains from a file with configurable parallelism and rate limiting
+/// - `Policy`: Asserting a policy over batch results for CI gates
+/// - `Report`: Summarizing batch results per tenant in Markdown
+/// - `Baseline`: Recording an approved snapshot and reporting drift from it
+/// - `Watch`: Periodically rescanning monitored domains and alerting on changes
+/// - `UpdateData`: Refreshing the enrichment data in the data directory
+/// - `Serve`: Running as a scanning service with scheduled scans
+/// - `Decrypt`: Reading result files stored with encryption at rest
+/// - `VerifyOwnership`: Proving control of a domain before intrusive checks
+/// - `Service`: Running `serve` and `watch` under systemd
+/// - `Purge`: Removing stored data past its retention age
 ///
 /// # Implementation Details
 ///
@@ -265,6 +606,7 @@ pub struct Cli {
 /// sentri batch --input-file domains.txt
 /// ```
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // Parsed once at startup, boxing buys nothing
 pub enum Commands {
     /// Check a single domain for MDI presence
     ///
@@ -279,6 +621,26 @@ pub enum Commands {
         #[arg(short, long)]
         domain: String,
     },
+    /// List the domains federated with a domain's tenant, without any DNS probing
+    ///
+    /// Sends only the GetFederationInformation request and prints the
+    /// federated domains one per line, for tenant domain enumeration with a
+    /// minimal footprint. With --format, the result is printed instead.
+    Federation {
+        /// Domain to enumerate (e.g., example.com)
+        #[arg(short, long)]
+        domain: String,
+    },
+    /// Check a tenant known by name, skipping federation discovery
+    ///
+    /// Checks that `<name>.onmicrosoft.com` exists, then looks up its MDI
+    /// sensor endpoints. Useful when the tenant name is already known, e.g.
+    /// from reconnaissance; the result has no federated domains.
+    Tenant {
+        /// Tenant name (e.g., contoso or contoso.onmicrosoft.com)
+        #[arg(short, long)]
+        name: String,
+    },
     /// Process multiple domains from file with parallel execution
     ///
     /// This mode reads domains from a file (one per line) and processes
@@ -287,16 +649,102 @@ pub enum Commands {
     /// object per line).
     ///
     /// Empty lines and those starting with '#' in the input file are skipped.
+    /// With `--from-results`, the domains of a previous results file are
+    /// scanned again instead, e.g. only those that failed.
     Batch {
         /// Input file containing domains (one per line)
-        #[arg(short, long)]
-        input_file: PathBuf,
+        /// Further CSV columns tag each domain with `key=value` pairs copied into its results;
+        /// a bare column is its `customer` tag for `--usage-report`
+        #[arg(short, long, required_unless_present = "from_results")]
+        input_file: Option<PathBuf>,
+
+        /// Results file of a previous batch whose domains are scanned again
+        #[arg(long, conflicts_with = "input_file")]
+        from_results: Option<PathBuf>,
+
+        /// Results of `--from-results` whose domains are scanned again [default: all]
+        #[arg(long, value_enum, conflicts_with = "input_file")]
+        only: Option<RescanFilter>,
+
+        /// Re-check only failures of these error classes from `--from-results`
+        /// The output merges the refreshed records with all other records
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            conflicts_with_all = ["input_file", "only"]
+        )]
+        retry_classes: Vec<ErrorClass>,
 
         /// Output file for results (JSON format, one result per line)
-        /// If not specified, results are printed to stdout
-        #[arg(short, long)]
+        /// Also takes s3://, azblob:// and gs:// URLs, streamed without local disk (object-store feature);
+        /// if not specified, results are printed to stdout
+        #[arg(short, long, alias = "output")]
         output_file: Option<PathBuf>,
 
+        /// Append to the output file under an exclusive lock instead of truncating it
+        /// Lets several sentri processes write to the same file without interleaving lines
+        #[arg(long, requires = "output_file")]
+        append: bool,
+
+        /// Also write `<output>.idx` mapping every domain to the byte offset of its result
+        /// Lets `sentri lookup` read single results without parsing the whole file
+        #[arg(long, requires = "output_file", conflicts_with = "append")]
+        index: bool,
+
+        /// Compress the output file while it is written: gzip or zstd
+        /// Supports the line formats json, jsonl, csv, table, cef and leef
+        #[arg(long, value_enum, requires = "output_file", conflicts_with_all = ["append", "index"])]
+        compress: Option<Compression>,
+
+        /// Unix domain socket or named pipe receiving results as NDJSON
+        /// Replaces the output file and stdout; a collector must be listening
+        #[arg(long, conflicts_with = "output_file")]
+        socket: Option<PathBuf>,
+
+        /// Policy file deciding which JUnit test cases fail
+        /// Replaces the default "MDI must be present" check for `--format junit`
+        #[arg(long, conflicts_with_all = ["compress", "socket", "append", "index"])]
+        policy: Option<PathBuf>,
+
+        /// Also write one JSON line per discovered tenant to this file
+        /// Summarizes the domain count and MDI status of every tenant
+        #[arg(long)]
+        tenant_report: Option<PathBuf>,
+
+        /// Write per-customer request counts and compute time as CSV to this file
+        /// Customers come from the `customer` tag of the input file; written when the batch ends
+        #[arg(long, value_name = "FILE")]
+        usage_report: Option<PathBuf>,
+
+        /// Write requests per minute against --rate-limit as JSON to this file
+        /// Evidence that the run stayed within its throttling; written when the batch ends
+        #[arg(long, value_name = "FILE")]
+        rate_report: Option<PathBuf>,
+
+        /// Also write every result as CSV to this file, columns as with `--format csv`
+        #[arg(long, value_name = "FILE")]
+        output_csv: Option<PathBuf>,
+
+        /// Also write every result to this Parquet file; requires the `parquet` feature
+        #[arg(long, value_name = "FILE")]
+        output_parquet: Option<PathBuf>,
+
+        /// Also upsert every result into the `results` table of this SQLite database
+        /// Re-scans replace the rows of their domains; requires the `sqlite` feature
+        #[arg(long, value_name = "FILE")]
+        output_sqlite: Option<PathBuf>,
+
+        /// Stop starting new chunks after this run time (e.g. 90m, 8h)
+        /// The chunk in flight is finished and the unprocessed domains are written to a checkpoint
+        #[arg(long, value_parser = parse_age, value_name = "AGE")]
+        max_runtime: Option<Duration>,
+
+        /// Checkpoint receiving the domains left unprocessed by --max-runtime
+        /// Defaults to `<output file>.remaining`, or sentri-remaining.txt without an output file
+        #[arg(long, value_name = "FILE", requires = "max_runtime")]
+        checkpoint_file: Option<PathBuf>,
+
         /// Chunk size for batch processing
         /// Controls memory usage and output frequency
         #[arg(long, default_value = "1000")]
@@ -306,5 +754,616 @@ pub enum Commands {
         /// Adjust to comply with Microsoft API rate limits
         #[arg(short, long, default_value = "50")]
         rate_limit: u64,
+
+        /// Additional destinations receiving every result
+        #[command(flatten)]
+        sinks: SinkArgs,
+
+        /// Upload of the completed output file to object storage
+        #[command(flatten)]
+        upload: UploadArgs,
+
+        /// Email notification when the batch completes or fails
+        #[command(flatten)]
+        notify: NotifyArgs,
+    },
+    /// Evaluate a policy file against batch results
+    ///
+    /// Reads results written by `batch` (JSONL) and checks them against the
+    /// rules of a TOML policy file and/or Rego policies. Every violation is printed as a JSON line
+    /// and the command exits with a non-zero status if any rule is violated.
+    Policy {
+        /// Policy file (TOML) with the rules to enforce
+        #[arg(short, long, required_unless_present = "rego_file")]
+        policy_file: Option<PathBuf>,
+
+        /// Rego policy file evaluated per result (requires the `rego` feature)
+        /// May be given multiple times
+        #[arg(long)]
+        rego_file: Vec<PathBuf>,
+
+        /// Rego rule producing the set of deny messages
+        #[arg(long, default_value = crate::policy::rego::DEFAULT_DENY_RULE)]
+        rego_query: String,
+
+        /// Results file produced by `sentri batch --output-file`
+        #[arg(short, long)]
+        results_file: PathBuf,
+    },
+    /// Export tenants, domains, federation and MDI instances as a graph
+    ///
+    /// Reads results written by `batch` (JSONL) and writes a graph in DOT,
+    /// GraphML or JSON Graph Format for visualization in Graphviz, Gephi or
+    /// Maltego-style tools.
+    Graph {
+        /// Results file produced by `sentri batch --output-file`
+        #[arg(short, long)]
+        results_file: PathBuf,
+
+        /// Graph format
+        #[arg(short = 'f', long, value_enum, default_value_t = GraphFormat::Dot)]
+        graph_format: GraphFormat,
+
+        /// File to write the graph to (stdout if omitted)
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+    },
+    /// Summarize a results file per tenant for tickets and wikis
+    ///
+    /// Aggregates the results written by `batch` (JSONL) per tenant, with
+    /// its domains, MDI status and the kinds of federated domains, into a
+    /// Markdown document. Only `--format markdown`, the default, is supported.
+    Report {
+        /// Results file produced by `sentri batch --output-file`
+        #[arg(short, long)]
+        results_file: PathBuf,
+
+        /// File to write the report to (stdout if omitted)
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+    },
+    /// Check that findings correspond to captured federation responses
+    ///
+    /// For every result with a `response_sha256`, looks up the response
+    /// captured for its domain with `--capture-dir` whose canonical hash
+    /// matches. Prints one JSON line per result and exits with a non-zero
+    /// status if any finding has no matching evidence.
+    VerifyEvidence {
+        /// Results file produced by `sentri batch --output-file`
+        #[arg(short, long)]
+        results_file: PathBuf,
+
+        /// Capture directory the responses were stored in
+        #[arg(long)]
+        capture_dir: PathBuf,
+    },
+    /// Print the results of specific domains from a results file
+    ///
+    /// Reads only the requested lines when the file has an index written by
+    /// `sentri batch --index`, and the whole file otherwise. Exits with a
+    /// non-zero status if any domain has no result.
+    Lookup {
+        /// Results file produced by `sentri batch --output-file`
+        #[arg(short, long)]
+        results_file: PathBuf,
+
+        /// Domain whose result is printed; may be given multiple times
+        #[arg(short, long = "domain", required = true)]
+        domains: Vec<String>,
+    },
+    /// Create or check an approved baseline snapshot
+    ///
+    /// `create` records the tenant, federation and MDI state from a results
+    /// file; `check` compares a later results file against it, prints every
+    /// drift as a JSON line and exits with a non-zero status if anything changed.
+    Baseline {
+        /// Baseline action to perform
+        #[command(subcommand)]
+        action: BaselineAction,
+    },
+    /// Periodically rescan monitored domains and alert on changes
+    ///
+    /// Each cycle rescans every domain in the input file, bypassing the
+    /// result cache, and compares the results with the previous cycle.
+    /// Detected changes are printed as JSON lines and, when configured,
+    /// raised as incidents in PagerDuty or Opsgenie. The first cycle only
+    /// establishes the baseline.
+    Watch {
+        /// Input file containing monitored domains (one per line)
+        #[arg(short, long)]
+        input_file: PathBuf,
+
+        /// Seconds to wait between scan cycles
+        #[arg(long, default_value = "3600")]
+        interval_secs: u64,
+
+        /// Rate limit (requests per minute)
+        #[arg(short, long, default_value = "50")]
+        rate_limit: u64,
+
+        /// Stop after this many cycles (runs until interrupted if omitted)
+        #[arg(long)]
+        max_iterations: Option<u64>,
+
+        /// Consecutive failed scans after which a domain is reported gone
+        #[arg(long, default_value = "3", value_parser = clap::value_parser!(u32).range(1..))]
+        tombstone_after: u32,
+
+        /// Incident alerting for detected changes
+        #[command(flatten)]
+        alerts: AlertArgs,
+    },
+
+    /// Download and verify enrichment data into the data directory
+    ///
+    /// Fetches Microsoft service tags, the public suffix list and Unicode
+    /// confusables. Failed downloads keep the previous or bundled data.
+    UpdateData {
+        /// Only update these data sets (all by default)
+        #[arg(long, value_enum)]
+        only: Vec<DataSet>,
+
+        /// Direct service tag JSON URL, skipping discovery via Microsoft's download page
+        #[arg(long)]
+        service_tags_url: Option<String>,
+    },
+
+    /// Run as a scanning service with an HTTP API and cron scheduler
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+
+        /// Directory for schedule definitions and scan results
+        #[arg(long, default_value = "sentri-state")]
+        state_dir: PathBuf,
+
+        /// Rate limit (requests per minute) shared by all scheduled scans and jobs
+        #[arg(short, long, default_value = "50")]
+        rate_limit: u64,
+
+        /// TOML file of API keys with per-key quotas; the API is open without it
+        #[arg(long)]
+        api_keys: Option<PathBuf>,
+
+        /// Delete stored results older than this age (e.g. 90d); kept forever if omitted
+        #[arg(long, value_parser = parse_age)]
+        retention: Option<Duration>,
+
+        /// Drop audit records older than this age (e.g. 365d); kept forever if omitted
+        #[arg(long, value_parser = parse_age)]
+        audit_retention: Option<Duration>,
+    },
+
+    /// Prove control of a domain with a DNS TXT token
+    VerifyOwnership {
+        #[command(subcommand)]
+        action: OwnershipAction,
+    },
+
+    /// Integrate `serve` and `watch` with a service manager
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+
+    /// Delete stored results and audit records past their retention age
+    Purge {
+        /// Server state directory to purge
+        #[arg(long, default_value = "sentri-state")]
+        state_dir: PathBuf,
+
+        /// Delete result files last written longer ago than this (e.g. 90d)
+        #[arg(long, value_parser = parse_age)]
+        older_than: Option<Duration>,
+
+        /// Drop audit records older than this (defaults to --older-than)
+        #[arg(long, value_parser = parse_age)]
+        audit_older_than: Option<Duration>,
+
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Print an encrypted JSONL file as plaintext JSONL
+    ///
+    /// Reads result files, capture indexes and audit logs written with
+    /// `--storage-key`, which must be the key they were encrypted with.
+    Decrypt {
+        /// File written with `--storage-key`
+        #[arg(short, long)]
+        input_file: PathBuf,
+    },
+
+    /// Print the JSON Schema of results for validating `json`/`jsonl` output
+    ///
+    /// The schema describes the version in the `schema_version` field of
+    /// results written by this build.
+    Schema,
+}
+
+/// Actions of the `verify-ownership` subcommand
+#[derive(Subcommand)]
+pub enum OwnershipAction {
+    /// Generate a token and print the TXT record to publish
+    Issue {
+        /// Domain to verify
+        #[arg(short, long)]
+        domain: String,
+    },
+    /// Look up the published TXT record and mark the domain verified
+    Check {
+        /// Domain a token was issued for
+        #[arg(short, long)]
+        domain: String,
+    },
+    /// Show the verification state of every domain
+    List,
+}
+
+/// Actions of the `service` subcommand
+#[derive(Subcommand)]
+pub enum ServiceAction {
+    /// Print a systemd unit (Type=notify) running the given sentri arguments
+    ///
+    /// e.g. `sentri service unit -- serve --state-dir /var/lib/sentri`
+    Unit {
+        /// Account the service runs as; a transient DynamicUser when omitted
+        #[arg(long)]
+        user: Option<String>,
+
+        /// Restart the service when it stops answering for this long (e.g. 2m)
+        #[arg(long, value_parser = parse_age, value_name = "AGE")]
+        watchdog: Option<Duration>,
+
+        /// Arguments of the service, after `--`
+        #[arg(last = true, required = true)]
+        args: Vec<String>,
+    },
+    /// Register a Windows service running the given sentri arguments
+    ///
+    /// e.g. `sentri service install -- serve --state-dir C:\ProgramData\sentri`
+    #[cfg(windows)]
+    Install {
+        /// Name of the service
+        #[arg(long, default_value = crate::service::windows::DEFAULT_SERVICE_NAME)]
+        name: String,
+
+        /// Account the service runs as, e.g. `NT AUTHORITY\LocalService`; LocalSystem when omitted
+        #[arg(long)]
+        account: Option<String>,
+
+        /// Arguments of the service, after `--`
+        #[arg(last = true, required = true)]
+        args: Vec<String>,
+    },
+    /// Remove a Windows service registered with `sentri service install`
+    #[cfg(windows)]
+    Uninstall {
+        /// Name of the service
+        #[arg(long, default_value = crate::service::windows::DEFAULT_SERVICE_NAME)]
+        name: String,
     },
+    /// Run the given sentri arguments as a Windows service; started by the
+    /// Service Control Manager
+    #[cfg(windows)]
+    #[command(hide = true)]
+    Run {
+        /// Name of the service
+        #[arg(long, default_value = crate::service::windows::DEFAULT_SERVICE_NAME)]
+        name: String,
+
+        /// Arguments of the service, after `--`
+        #[arg(last = true, required = true)]
+        args: Vec<String>,
+    },
+}
+
+/// Actions of the `baseline` subcommand
+#[derive(Subcommand)]
+pub enum BaselineAction {
+    /// Store an approved snapshot of a results file
+    Create {
+        /// Results file produced by `sentri batch --output-file`
+        #[arg(short, long)]
+        results_file: PathBuf,
+
+        /// Baseline file to write
+        #[arg(short, long)]
+        baseline_file: PathBuf,
+    },
+    /// Compare a results file against an approved snapshot
+    Check {
+        /// Results file produced by `sentri batch --output-file`
+        #[arg(short, long)]
+        results_file: PathBuf,
+
+        /// Previously created baseline file
+        #[arg(short, long)]
+        baseline_file: PathBuf,
+    },
+}
+
+/// Options for raising incidents from watch mode
+///
+/// Alerts use a stable deduplication key per event kind and domain, so a
+/// condition persisting across cycles updates a single incident.
+///
+/// # Examples
+///
+/// ```text
+/// sentri watch \
+///   --input-file monitored.txt \
+///   --pagerduty-routing-key <integration-key>
+/// ```
+#[derive(Args, Debug, Clone, Default)]
+pub struct AlertArgs {
+    /// PagerDuty Events API v2 routing (integration) key
+    #[arg(long)]
+    pub pagerduty_routing_key: Option<String>,
+
+    /// Opsgenie API integration key
+    #[arg(long)]
+    pub opsgenie_api_key: Option<String>,
+
+    /// Opsgenie alert API URL (use https://api.eu.opsgenie.com/v2/alerts for EU accounts)
+    #[arg(long, default_value = crate::alert::OPSGENIE_ALERTS_URL, requires = "opsgenie_api_key")]
+    pub opsgenie_url: String,
+}
+
+impl AlertArgs {
+    /// Resolves secret references in the integration keys
+    pub fn resolve_secrets(&mut self, resolver: &SecretResolver) -> Result<()> {
+        resolver.resolve_option(&mut self.pagerduty_routing_key)?;
+        resolver.resolve_option(&mut self.opsgenie_api_key)
+    }
+}
+
+/// Options for delivering batch results to external services
+///
+/// Every configured sink receives each sanitized result in addition to the
+/// primary output file or stdout.
+///
+/// # Security Considerations
+///
+/// Credentials passed here are only used to authenticate against the
+/// configured service and are never written to result output
+/// (security:output:error_info_control). They may be given as secret
+/// references (e.g. `file:/run/secrets/es`) instead of on the command line;
+/// see [`crate::secrets`].
+///
+/// # Examples
+///
+/// ```text
+/// sentri batch \
+///   --input-file domains.txt \
+///   --la-workspace-id 00000000-0000-0000-0000-000000000000 \
+///   --la-shared-key <base64-key>
+/// ```
+#[derive(Args, Debug, Clone, Default)]
+pub struct SinkArgs {
+    /// Log Analytics workspace ID for the HTTP Data Collector API
+    #[arg(long, requires = "la_shared_key")]
+    pub la_workspace_id: Option<String>,
+
+    /// Log Analytics workspace shared key (base64)
+    #[arg(long, requires = "la_workspace_id")]
+    pub la_shared_key: Option<String>,
+
+    /// Custom log type for the Data Collector API (table name without _CL)
+    #[arg(long)]
+    pub la_log_type: Option<String>,
+
+    /// Data collection endpoint URL for DCR-based ingestion
+    #[arg(long, requires_all = ["la_dcr_id", "la_token"])]
+    pub la_dce_url: Option<String>,
+
+    /// Immutable ID of the data collection rule
+    #[arg(long, requires = "la_dce_url")]
+    pub la_dcr_id: Option<String>,
+
+    /// Stream name declared in the data collection rule
+    #[arg(long)]
+    pub la_stream: Option<String>,
+
+    /// Bearer token for DCR-based ingestion (audience https://monitor.azure.com)
+    #[arg(long, requires = "la_dce_url")]
+    pub la_token: Option<String>,
+
+    /// Number of results sent to Log Analytics per request
+    #[arg(long)]
+    pub la_batch_size: Option<usize>,
+
+    /// Elasticsearch / OpenSearch cluster URL for bulk indexing
+    #[arg(long)]
+    pub es_url: Option<String>,
+
+    /// Target index, may contain strftime placeholders (e.g. sentri-%Y.%m.%d)
+    #[arg(long, requires = "es_url")]
+    pub es_index: Option<String>,
+
+    /// User name for Elasticsearch basic authentication
+    #[arg(long, requires_all = ["es_url", "es_password"], conflicts_with = "es_api_key")]
+    pub es_username: Option<String>,
+
+    /// Password for Elasticsearch basic authentication
+    #[arg(long, requires = "es_username")]
+    pub es_password: Option<String>,
+
+    /// Elasticsearch API key (base64-encoded id:api_key)
+    #[arg(long, requires = "es_url")]
+    pub es_api_key: Option<String>,
+
+    /// Number of results sent per bulk request
+    #[arg(long, requires = "es_url")]
+    pub es_batch_size: Option<usize>,
+
+    /// Splunk HTTP Event Collector URL (e.g. https://splunk.example.com:8088)
+    #[arg(long, requires = "splunk_token")]
+    pub splunk_hec_url: Option<String>,
+
+    /// Splunk HEC token
+    #[arg(long, requires = "splunk_hec_url")]
+    pub splunk_token: Option<String>,
+
+    /// Splunk index of the events; defaults to the index of the token
+    #[arg(long, requires = "splunk_hec_url")]
+    pub splunk_index: Option<String>,
+
+    /// Splunk source type of the events [default: sentri:result]
+    #[arg(long, requires = "splunk_hec_url")]
+    pub splunk_sourcetype: Option<String>,
+
+    /// Number of results sent to Splunk per request
+    #[arg(long, requires = "splunk_hec_url")]
+    pub splunk_batch_size: Option<usize>,
+
+    /// Kafka bootstrap brokers (host:port, comma-separated) to produce results to
+    #[arg(long, value_delimiter = ',', requires = "kafka_topic")]
+    pub kafka_brokers: Vec<String>,
+
+    /// Kafka topic receiving one message per result, keyed by domain
+    #[arg(long, requires = "kafka_brokers")]
+    pub kafka_topic: Option<String>,
+
+    /// Number of results produced to Kafka per request
+    #[arg(long, requires = "kafka_brokers")]
+    pub kafka_batch_size: Option<usize>,
+
+    /// Connect to the Kafka brokers over TLS, verifying their certificates
+    #[arg(long, requires = "kafka_brokers")]
+    pub kafka_tls: bool,
+
+    /// CA certificates (PEM) verifying the Kafka brokers instead of the system store
+    #[arg(long, requires = "kafka_tls")]
+    pub kafka_ca_file: Option<PathBuf>,
+
+    /// SASL mechanism authenticating to the Kafka brokers
+    #[arg(
+        long,
+        value_parser = ["PLAIN", "SCRAM-SHA-256", "SCRAM-SHA-512"],
+        requires_all = ["kafka_brokers", "kafka_sasl_username", "kafka_sasl_password"]
+    )]
+    pub kafka_sasl_mechanism: Option<String>,
+
+    /// SASL user name for the Kafka brokers
+    #[arg(long, requires = "kafka_sasl_mechanism")]
+    pub kafka_sasl_username: Option<String>,
+
+    /// SASL password for the Kafka brokers
+    #[arg(long, requires = "kafka_sasl_mechanism")]
+    pub kafka_sasl_password: Option<String>,
+
+    /// PostgreSQL DSN (e.g. postgres://sentri@db.internal/scans) to upsert results into
+    /// Requires the `postgres` feature; the table is created if missing
+    #[arg(long)]
+    pub pg_dsn: Option<String>,
+
+    /// PostgreSQL table receiving the results, optionally schema-qualified [default: sentri_results]
+    #[arg(long, requires = "pg_dsn")]
+    pub pg_table: Option<String>,
+
+    /// Number of results upserted into PostgreSQL per statement
+    #[arg(long, requires = "pg_dsn")]
+    pub pg_batch_size: Option<usize>,
+}
+
+impl SinkArgs {
+    /// Resolves secret references in the sink credentials
+    pub fn resolve_secrets(&mut self, resolver: &SecretResolver) -> Result<()> {
+        resolver.resolve_option(&mut self.la_shared_key)?;
+        resolver.resolve_option(&mut self.la_token)?;
+        resolver.resolve_option(&mut self.es_password)?;
+        resolver.resolve_option(&mut self.es_api_key)?;
+        resolver.resolve_option(&mut self.splunk_token)?;
+        resolver.resolve_option(&mut self.kafka_sasl_password)?;
+        resolver.resolve_option(&mut self.pg_dsn)
+    }
+}
+
+/// Options for uploading the completed output file to object storage
+///
+/// Requires the `object-store` cargo feature. Cloud credentials are read from
+/// the provider environment variables (`AWS_*`, `AZURE_*`, `GOOGLE_*`).
+///
+/// # Examples
+///
+/// ```text
+/// sentri batch \
+///   --input-file domains.txt \
+///   --output-file results.jsonl \
+///   --upload-to s3://scan-results/acme/ \
+///   --upload-sse kms --upload-kms-key-id alias/scan-results
+/// ```
+#[derive(Args, Debug, Clone, Default)]
+pub struct UploadArgs {
+    /// Object store URL or prefix (ending in /) to upload the output file to
+    /// Supports s3://, az://, abfs:// and gs:// destinations
+    #[arg(long, requires = "output_file")]
+    pub upload_to: Option<String>,
+
+    /// Server-side encryption for S3 uploads
+    #[arg(long, value_enum, default_value_t = ServerSideEncryption::Default, requires = "upload_to")]
+    pub upload_sse: ServerSideEncryption,
+
+    /// KMS key ID or ARN for kms/dsse-kms server-side encryption
+    #[arg(long, requires = "upload_to")]
+    pub upload_kms_key_id: Option<String>,
+}
+
+/// Options for emailing a report when a batch completes or fails
+///
+/// Requires the `email` cargo feature. Mail is submitted over STARTTLS on
+/// port 587 unless implicit TLS is requested.
+///
+/// # Examples
+///
+/// ```text
+/// sentri batch \
+///   --input-file domains.txt \
+///   --email-report ops@example.com \
+///   --smtp-host smtp.example.com \
+///   --email-attach-html
+/// ```
+#[derive(Args, Debug, Clone, Default)]
+pub struct NotifyArgs {
+    /// Recipient of the completion report (repeat or comma-separate for several)
+    #[arg(long, value_delimiter = ',', requires = "smtp_host")]
+    pub email_report: Vec<String>,
+
+    /// Sender address for report emails
+    #[arg(long, default_value = "sentri@localhost")]
+    pub email_from: String,
+
+    /// SMTP relay used to deliver report emails
+    #[arg(long)]
+    pub smtp_host: Option<String>,
+
+    /// SMTP port (defaults to 587, or 465 with implicit TLS)
+    #[arg(long)]
+    pub smtp_port: Option<u16>,
+
+    /// SMTP user name
+    #[arg(long, requires = "smtp_password")]
+    pub smtp_username: Option<String>,
+
+    /// SMTP password
+    #[arg(long, requires = "smtp_username")]
+    pub smtp_password: Option<String>,
+
+    /// Use implicit TLS instead of STARTTLS
+    #[arg(long)]
+    pub smtp_implicit_tls: bool,
+
+    /// Attach an HTML rendering of the report
+    #[arg(long)]
+    pub email_attach_html: bool,
+}
+
+impl NotifyArgs {
+    /// Resolves secret references in the SMTP password
+    pub fn resolve_secrets(&mut self, resolver: &SecretResolver) -> Result<()> {
+        resolver.resolve_option(&mut self.smtp_password)
+    }
 }
@@ -0,0 +1,434 @@
+//! Central configuration: command-line options with `SENTRI_*` environment fallbacks
+//!
+//! Every option of every subcommand can also be set through an environment
+//! variable named after its long flag, which suits container deployments
+//! where options come from a ConfigMap or Secret:
+//!
+//! | Flag                    | Variable                    |
+//! |-------------------------|-----------------------------|
+//! | `--rate-limit`          | `SENTRI_RATE_LIMIT`         |
+//! | `--listen`              | `SENTRI_LISTEN`             |
+//! | `--es-api-key`          | `SENTRI_ES_API_KEY`         |
+//! | `--concurrent-requests` | `SENTRI_CONCURRENT_REQUESTS` |
+//!
+//! Precedence, highest first:
+//!
+//! 1. The flag on the command line
+//! 2. The environment variable
+//! 3. The built-in default
+//!
+//! Credential options additionally read `SENTRI_<OPTION>_FILE`, and accept
+//! secret references such as `file:` or `env:`; see [`crate::secrets`].
+//!
+//! Options with the same name in several subcommands share one variable.
+//! Boolean flags accept `true`/`false`, `yes`/`no`, `on`/`off` and `1`/`0`;
+//! list options take comma-separated values where the flag does.
+//!
+//! With `--env-profile auto`, options left at their built-in default take
+//! values derived from the host instead; see [`crate::env_profile`].
+//!
+//! # Configuration File
+//!
+//! Settings too detailed for flags are read from a TOML file passed with
+//! `--config` (see [`ConfigFile`]). Retry policies are set per component,
+//! every key being optional and falling back to the component's default:
+//!
+//! ```toml
+//! # Autodiscover requests
+//! [retry.http]
+//! max_retries = 5
+//! initial_backoff_ms = 250
+//! backoff_factor = 2.0
+//! max_backoff_ms = 20000
+//! jitter = true
+//!
+//! # DNS lookups, on top of --dns-attempts
+//! [retry.dns]
+//! max_retries = 1
+//! ```
+//!
+//! MDI sensor lookups query Microsoft's zones rather than the targets', and
+//! can be throttled apart from the other DNS queries of the `--depth` budget
+//! (see [`crate::depth::DnsBudget`]). `--mdi-dns-rate-limit` and
+//! `--mdi-dns-concurrency` take precedence over the section:
+//!
+//! ```toml
+//! [mdi_dns]
+//! queries_per_minute = 600
+//! max_concurrent = 50
+//! ```
+//!
+//! `serve` and `watch` additionally read a scan budget and the times of day
+//! scans may start (see [`crate::scan_window`]):
+//!
+//! ```toml
+//! [rate_limit]
+//! requests_per_minute = 120
+//! max_concurrent = 20
+//!
+//! [windows]
+//! allowed = ["22:00-06:00"]
+//! ```
+//!
+//! These two sections are reloaded while running, when the file changes or
+//! on `SIGHUP`; see [`crate::reload`]. Everything else is read at startup.
+//!
+//! `batch` delivers results matching `[[route]]` rules to further webhooks
+//! and files (see [`crate::sinks::routing`]):
+//!
+//! ```toml
+//! [[route]]
+//! name = "emea-gaps"
+//! tags = { bu = "emea" }
+//! mdi = false
+//! webhook = "https://hooks.example.com/services/emea"
+//! ```
+//!
+//! # Security Considerations
+//!
+//! - **Credential Exposure**: `--help` lists the variable names but never their
+//!   values, as they may hold credentials (security:output:error_info_control)
+
+use anyhow::{bail, Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command, CommandFactory, FromArgMatches};
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::path::Path;
+
+use crate::cli::{Cli, Commands};
+use crate::depth::DnsBudget;
+use crate::env_profile::{EnvProfile, Tuning};
+use crate::retry::RetryConfig;
+use crate::scan_window::ScanWindows;
+use crate::secrets::{SecretResolver, SECRET_OPTIONS};
+use crate::sinks::routing::Route;
+
+/// Prefix of every environment variable read by sentri
+pub const ENV_PREFIX: &str = "SENTRI_";
+
+/// Returns the environment variable backing a long flag
+///
+/// # Examples
+///
+/// ```
+/// use sentri::config::env_var_name;
+///
+/// assert_eq!(env_var_name("rate-limit"), "SENTRI_RATE_LIMIT");
+/// ```
+pub fn env_var_name(long: &str) -> String {
+    format!("{}{}", ENV_PREFIX, long.replace('-', "_").to_uppercase())
+}
+
+/// Attaches environment fallbacks to every option of a command and its subcommands
+fn with_env(command: Command) -> Command {
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    let command = command.mut_args(|arg| {
+        let long = match arg.get_long() {
+            Some("help") | Some("version") | None => return arg,
+            Some(long) => long.to_string(),
+        };
+        arg.env(env_var_name(&long)).hide_env_values(true)
+    });
+    subcommands.iter().fold(command, |command, name| {
+        command.mut_subcommand(name, with_env)
+    })
+}
+
+/// The CLI definition with environment fallbacks applied
+pub fn command() -> Command {
+    with_env(Cli::command())
+}
+
+/// Parses the process arguments and environment and resolves secrets
+///
+/// Exits with usage information on invalid arguments.
+///
+/// # Errors
+/// * A secret reference cannot be resolved
+pub fn parse() -> Result<Cli> {
+    let mut cli = match try_parse_from(std::env::args_os()) {
+        Ok(cli) => cli,
+        Err(e) => e.exit(),
+    };
+    cli.resolve_secrets(&SecretResolver::default())?;
+    Ok(cli)
+}
+
+/// Parses the given arguments, falling back to `SENTRI_*` variables
+///
+/// Secret references are left unresolved; see [`Cli::resolve_secrets`].
+pub fn try_parse_from<I, T>(args: I) -> Result<Cli, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    args.extend(secret_file_args(&args));
+
+    let mut command = command();
+    let matches = command.try_get_matches_from_mut(args)?;
+    let mut cli = Cli::from_arg_matches(&matches).map_err(|e| e.format(&mut command))?;
+    if cli.env_profile == EnvProfile::Auto {
+        apply_tuning(&mut cli, &matches, Tuning::detect());
+    }
+    Ok(cli)
+}
+
+/// Replaces options left at their built-in default with the values of `tuning`
+///
+/// Options set on the command line or through their environment variable
+/// are kept.
+pub fn apply_tuning(cli: &mut Cli, matches: &ArgMatches, tuning: Tuning) {
+    let is_default = |matches: &ArgMatches, id: &str| {
+        matches
+            .value_source(id)
+            .is_none_or(|source| source == ValueSource::DefaultValue)
+    };
+    if is_default(matches, "concurrent_requests") {
+        cli.concurrent_requests = tuning.concurrent_requests;
+    }
+    if is_default(matches, "cache_size") {
+        cli.cache_size = Some(tuning.cache_size);
+    }
+    if let (Commands::Batch { chunk_size, .. }, Some(("batch", batch))) =
+        (&mut cli.command, matches.subcommand())
+    {
+        if is_default(batch, "chunk_size") {
+            *chunk_size = tuning.chunk_size;
+        }
+    }
+    cli.tuning = Some(tuning);
+}
+
+/// Returns `--<option>=file:<path>` for each global credential option and
+/// each of the invoked subcommand that is unset but has a
+/// `SENTRI_<OPTION>_FILE` variable
+///
+/// Passing the reference as an argument, rather than as a default, lets it
+/// satisfy options that require the credential.
+fn secret_file_args(args: &[OsString]) -> Vec<OsString> {
+    // A lenient first pass only identifies the subcommand and what is already set
+    let Ok(matches) = command().ignore_errors(true).try_get_matches_from(args) else {
+        return Vec::new();
+    };
+    let Some((name, sub_matches)) = matches.subcommand() else {
+        return Vec::new();
+    };
+    let root = command();
+    let Some(subcommand) = root.find_subcommand(name) else {
+        return Vec::new();
+    };
+
+    root.get_arguments()
+        .filter(|arg| arg.is_global_set())
+        .chain(subcommand.get_arguments())
+        .filter_map(|arg| {
+            let long = arg
+                .get_long()
+                .filter(|long| SECRET_OPTIONS.contains(long))?;
+            if sub_matches.value_source(arg.get_id().as_str()).is_some() {
+                return None;
+            }
+            let path = std::env::var_os(format!("{}_FILE", env_var_name(long)))?;
+            let mut value = OsString::from(format!("--{}=file:", long));
+            value.push(path);
+            Some(value)
+        })
+        .collect()
+}
+
+/// Settings read from the `--config` file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Retry policies per component
+    #[serde(default)]
+    pub retry: RetrySections,
+    /// `[rate_limit]`: scan budget of `serve` and `watch`
+    pub rate_limit: Option<RateLimitSettings>,
+    /// `[mdi_dns]`: DNS query budget of the MDI sensor lookups
+    pub mdi_dns: Option<DnsBudgetSettings>,
+    /// `[windows]`: times of day scheduled scans may start
+    #[serde(default)]
+    pub windows: ScanWindows,
+    /// `[[route]]`: destinations of batch results by tag or finding
+    #[serde(default, rename = "route")]
+    pub routes: Vec<Route>,
+}
+
+/// The `[rate_limit]` section; unset keys keep the command-line value
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitSettings {
+    /// Requests per minute, as `--rate-limit`
+    pub requests_per_minute: Option<u64>,
+    /// Checks in flight at once, as `--concurrent-requests`
+    pub max_concurrent: Option<usize>,
+}
+
+impl RateLimitSettings {
+    /// Returns the requests per minute and concurrency over the command-line values
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::config::RateLimitSettings;
+    ///
+    /// let settings = RateLimitSettings { requests_per_minute: Some(30), ..Default::default() };
+    /// assert_eq!(settings.apply(100, 10).unwrap(), (30, 10));
+    /// ```
+    ///
+    /// # Errors
+    /// * Either value is zero
+    pub fn apply(&self, requests_per_minute: u64, max_concurrent: usize) -> Result<(u64, usize)> {
+        let requests_per_minute = self.requests_per_minute.unwrap_or(requests_per_minute);
+        let max_concurrent = self.max_concurrent.unwrap_or(max_concurrent);
+        if requests_per_minute == 0 {
+            bail!("requests_per_minute must be at least 1");
+        }
+        if max_concurrent == 0 {
+            bail!("max_concurrent must be at least 1");
+        }
+        Ok((requests_per_minute, max_concurrent))
+    }
+}
+
+/// The `[mdi_dns]` section; unset keys keep the `--depth` budget
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DnsBudgetSettings {
+    /// DNS queries per minute, as `--mdi-dns-rate-limit`
+    pub queries_per_minute: Option<usize>,
+    /// DNS queries in flight at once, as `--mdi-dns-concurrency`
+    pub max_concurrent: Option<usize>,
+}
+
+impl DnsBudgetSettings {
+    /// Applies the settings over the `base` budget
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::config::DnsBudgetSettings;
+    /// use sentri::depth::Depth;
+    ///
+    /// let settings = DnsBudgetSettings { queries_per_minute: Some(600), ..Default::default() };
+    /// let budget = settings.apply(Depth::Standard.dns_budget()).unwrap();
+    /// assert_eq!(budget.queries_per_minute, 600);
+    /// assert_eq!(budget.max_concurrent, Depth::Standard.dns_budget().max_concurrent);
+    /// ```
+    ///
+    /// # Errors
+    /// * Either value is zero
+    pub fn apply(&self, base: DnsBudget) -> Result<DnsBudget> {
+        let budget = DnsBudget {
+            queries_per_minute: self.queries_per_minute.unwrap_or(base.queries_per_minute),
+            max_concurrent: self.max_concurrent.unwrap_or(base.max_concurrent),
+        };
+        if budget.queries_per_minute == 0 {
+            bail!("queries_per_minute must be at least 1");
+        }
+        if budget.max_concurrent == 0 {
+            bail!("max_concurrent must be at least 1");
+        }
+        Ok(budget)
+    }
+}
+
+/// The `[retry.*]` sections of the configuration file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetrySections {
+    /// `[retry.http]`: autodiscover requests
+    pub http: Option<RetrySettings>,
+    /// `[retry.dns]`: DNS lookups
+    pub dns: Option<RetrySettings>,
+}
+
+/// Retry settings of one component; unset keys keep the component's default
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetrySettings {
+    /// Maximum number of retry attempts
+    pub max_retries: Option<u32>,
+    /// Wait before the first retry in milliseconds
+    pub initial_backoff_ms: Option<u64>,
+    /// Multiplier of the wait for each subsequent retry
+    pub backoff_factor: Option<f64>,
+    /// Longest wait between retries in milliseconds
+    pub max_backoff_ms: Option<u64>,
+    /// Randomize waits so retries of many scans do not align
+    pub jitter: Option<bool>,
+}
+
+impl RetrySettings {
+    /// Applies the settings over the `base` configuration of a component
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::config::RetrySettings;
+    /// use sentri::retry::RetryConfig;
+    ///
+    /// let settings = RetrySettings { max_retries: Some(5), ..Default::default() };
+    /// let config = settings.apply(RetryConfig::default()).unwrap();
+    /// assert_eq!(config.max_retries, 5);
+    /// assert_eq!(config.initial_backoff_ms, RetryConfig::default().initial_backoff_ms);
+    /// ```
+    ///
+    /// # Errors
+    /// * The backoff factor is below 1 or not finite
+    /// * The initial backoff exceeds the maximum backoff
+    pub fn apply(&self, base: RetryConfig) -> Result<RetryConfig> {
+        let config = RetryConfig {
+            max_retries: self.max_retries.unwrap_or(base.max_retries),
+            initial_backoff_ms: self.initial_backoff_ms.unwrap_or(base.initial_backoff_ms),
+            backoff_factor: self.backoff_factor.unwrap_or(base.backoff_factor),
+            max_backoff_ms: self.max_backoff_ms.unwrap_or(base.max_backoff_ms),
+            add_jitter: self.jitter.unwrap_or(base.add_jitter),
+        };
+        if !config.backoff_factor.is_finite() || config.backoff_factor < 1.0 {
+            bail!(
+                "backoff_factor must be at least 1, got {}",
+                config.backoff_factor
+            );
+        }
+        if config.initial_backoff_ms > config.max_backoff_ms {
+            bail!(
+                "initial_backoff_ms ({}) exceeds max_backoff_ms ({})",
+                config.initial_backoff_ms,
+                config.max_backoff_ms
+            );
+        }
+        Ok(config)
+    }
+}
+
+impl ConfigFile {
+    /// Parses a configuration file from TOML
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::config::ConfigFile;
+    ///
+    /// let config = ConfigFile::from_toml("[retry.dns]\nmax_retries = 1").unwrap();
+    /// assert_eq!(config.retry.dns.unwrap().max_retries, Some(1));
+    /// assert!(config.retry.http.is_none());
+    /// ```
+    pub fn from_toml(content: &str) -> Result<Self> {
+        toml::from_str(content).context("Invalid configuration file")
+    }
+
+    /// Loads a configuration file from disk
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read configuration file {}", path.display()))?;
+        Self::from_toml(&content).with_context(|| format!("Failed to load {}", path.display()))
+    }
+}
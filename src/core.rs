@@ -10,23 +10,51 @@
 //! All operations respect the rate limits defined in `.windsurfrules` and
 //! implement proper error handling and backoff strategies.
 
-use anyhow::{Context, Result};
-use dashmap::DashMap;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::{
-    path::{Path, PathBuf},
-    sync::Arc,
-    time::{Duration, Instant},
+use std::path::PathBuf;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+use {
+    anyhow::{Context, Result},
+    dashmap::DashMap,
+    std::{
+        collections::HashSet,
+        path::Path,
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    tokio::{
+        fs::File,
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
+    },
+    tracing::{debug, error, info, warn},
 };
-use tokio::{
-    fs::{File, OpenOptions},
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-};
-use tracing::{debug, error, info};
 
 use crate::{
-    dns::DnsResolver, http::HttpClient, rate_limit::RateLimiter, sanitize::sanitize_domain_result,
-    validation::validate_domain, xml::XmlParser,
+    attribution::EndpointAnomaly, engagement::Engagement, error_class::ErrorClass,
+    latency::SlowHost, stats::DetectorSummary, tags::Tags, time::Stopwatch,
+    xml_schema::SchemaWarning,
+};
+// The scanning engine needs the whole networking stack
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+use crate::{
+    attribution::{EndpointKind, IpRanges},
+    capture::ResponseStore,
+    depth::Depth,
+    dns::{is_missing_record, DnsResolver},
+    dns_pipeline::{LookupPool, PipelineConfig},
+    http::HttpClient,
+    logging::LogSampler,
+    rate_limit::RateLimiter,
+    rate_report::RateRecorder,
+    sanitize::sanitize_domain_result,
+    sinks::{primary_sink, ResultSink},
+    stats::{Detector, DetectorStats},
+    tags::{parse_input_line, InputLine},
+    trace::{span, TraceRecorder},
+    validation::validate_domain,
+    xml::{canonical_sha256, XmlParser},
+    xml_schema::validate_federation_response,
 };
 
 /// Results from scanning a domain for MDI presence
@@ -36,35 +64,71 @@ use crate::{
 /// - The identified Microsoft tenant (if any)
 /// - All federated domains discovered
 /// - The MDI instance URL (if detected)
+/// - The MDI sensor endpoint generation (if detected)
+/// - Whether DNSSEC confirms that no MDI instance exists (with `--dnssec-negatives`)
+/// - Federated mail/identity endpoints hosted outside Microsoft (if IP attribution is enabled)
+/// - The engagement the scan was performed under (if declared)
+/// - The tags of the domain in the batch input (see [`crate::tags`])
 /// - Processing metrics and any errors encountered
+/// - Whether the result came from the cache and when the domain was checked
+///
+/// Timestamps serialize as RFC3339 in UTC (e.g. `2024-05-01T12:00:00.250Z`).
 ///
 /// # Examples
 ///
 /// ```
-/// use sentri::core::DomainResult;
+/// use sentri::core::{DomainResult, MdiGeneration};
+/// use sentri::error_class::ErrorClass;
 ///
 /// // Example of a successful scan result
 /// let success = DomainResult {
+///     schema_version: sentri::core::RESULT_SCHEMA_VERSION,
 ///     domain: "example.com".to_string(),
 ///     tenant: Some("examplecorp".to_string()),
 ///     federated_domains: vec!["example.com".to_string(), "example.net".to_string()],
 ///     mdi_instance: Some("https://contoso-corp.atp.azure.com".to_string()),
+///     mdi_generation: Some(MdiGeneration::Legacy),
+///     mdi_confidence: None,
+///     endpoint_anomalies: vec![],
+///     engagement: None,
+///     tags: Default::default(),
 ///     processing_time_ms: 1250,
+///     response_sha256: None,
+///     schema_warnings: vec![],
 ///     error: None,
+///     error_class: None,
+///     from_cache: false,
+///     checked_at: chrono::Utc::now(),
+///     completed_at: chrono::Utc::now(),
 /// };
 ///
 /// // Example of a scan result with error
 /// let error_result = DomainResult {
+///     schema_version: sentri::core::RESULT_SCHEMA_VERSION,
 ///     domain: "invalid.domain".to_string(),
 ///     tenant: None,
 ///     federated_domains: vec![],
 ///     mdi_instance: None,
+///     mdi_generation: None,
+///     mdi_confidence: None,
+///     endpoint_anomalies: vec![],
+///     engagement: None,
+///     tags: Default::default(),
 ///     processing_time_ms: 350,
+///     response_sha256: None,
+///     schema_warnings: vec![],
 ///     error: Some("Invalid domain format".to_string()),
+///     error_class: Some(ErrorClass::InvalidDomain),
+///     from_cache: false,
+///     checked_at: chrono::Utc::now(),
+///     completed_at: chrono::Utc::now(),
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DomainResult {
+    /// Version of this result format, see [`RESULT_SCHEMA_VERSION`]
+    #[serde(default = "unversioned_schema")]
+    pub schema_version: u32,
     /// The domain that was scanned
     pub domain: String,
     /// The Microsoft tenant identifier, if detected
@@ -73,12 +137,165 @@ pub struct DomainResult {
     pub federated_domains: Vec<String>,
     /// URL of the MDI instance if detected
     pub mdi_instance: Option<String>,
+    /// Which MDI sensor endpoint generation the tenant appears to use
+    pub mdi_generation: Option<MdiGeneration>,
+    /// How firmly the absence of an MDI instance is established
+    ///
+    /// Only set for tenants without an MDI instance, and only when the checker
+    /// validates negatives with DNSSEC (see [`MdiConfidence`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mdi_confidence: Option<MdiConfidence>,
+    /// Federated mail/identity endpoints resolving outside Microsoft address space
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub endpoint_anomalies: Vec<EndpointAnomaly>,
+    /// Engagement the scan was performed under, if one was declared
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engagement: Option<Engagement>,
+    /// Tags of the domain in the batch input, such as `bu` or `customer`
+    #[serde(default, skip_serializing_if = "Tags::is_empty")]
+    pub tags: Tags,
     /// Time taken to process this domain in milliseconds
     pub processing_time_ms: u64,
+    /// Canonical SHA-256 of the federation response the findings are based on
+    ///
+    /// Matches [`crate::xml::canonical_sha256`] of the response captured with
+    /// `--capture-dir`, tying the finding to its evidence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_sha256: Option<String>,
+    /// Deviations of the federation response from the Autodiscover schema
+    ///
+    /// Only checked with `--strict-schema`; see [`crate::xml_schema`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schema_warnings: Vec<SchemaWarning>,
     /// Error message if the scan failed
     pub error: Option<String>,
+    /// Class of the failure, if the scan failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_class: Option<ErrorClass>,
+    /// True if the result was served from the checker's cache
+    #[serde(default)]
+    pub from_cache: bool,
+    /// When the check of the domain started; older than the response if cached
+    #[serde(default)]
+    pub checked_at: DateTime<Utc>,
+    /// When the check of the domain finished
+    #[serde(default)]
+    pub completed_at: DateTime<Utc>,
+}
+
+/// Version of the [`DomainResult`] format written by this build
+///
+/// Adding an optional field keeps the version; renaming, removing or
+/// changing the type or meaning of a field increments it. `sentri schema`
+/// prints the JSON Schema of the current version.
+pub const RESULT_SCHEMA_VERSION: u32 = 1;
+
+/// Version of results written before the field existed
+fn unversioned_schema() -> u32 {
+    1
+}
+
+impl Default for DomainResult {
+    fn default() -> Self {
+        Self {
+            schema_version: RESULT_SCHEMA_VERSION,
+            domain: String::new(),
+            tenant: None,
+            federated_domains: Vec::new(),
+            mdi_instance: None,
+            mdi_generation: None,
+            mdi_confidence: None,
+            endpoint_anomalies: Vec::new(),
+            engagement: None,
+            tags: Tags::new(),
+            processing_time_ms: 0,
+            response_sha256: None,
+            schema_warnings: Vec::new(),
+            error: None,
+            error_class: None,
+            from_cache: false,
+            checked_at: DateTime::default(),
+            completed_at: DateTime::default(),
+        }
+    }
+}
+
+/// Generation of the MDI sensor endpoint namespace used by a tenant
+///
+/// Microsoft has started migrating tenants from the classic `*.atp.azure.com`
+/// sensor endpoints to Defender XDR-hosted endpoints. The generation is derived
+/// from whichever sensor API hostname resolves for the tenant.
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::MdiGeneration;
+///
+/// let generation = MdiGeneration::Unified;
+/// assert_eq!(serde_json::to_string(&generation).unwrap(), "\"unified\"");
+/// assert_eq!(generation.to_string(), "unified");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MdiGeneration {
+    /// Classic sensor endpoint under `atp.azure.com`
+    Legacy,
+    /// Defender XDR-hosted sensor endpoint under `security.microsoft.com`
+    Unified,
+}
+
+impl MdiGeneration {
+    /// Name of the generation, as serialized
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MdiGeneration::Legacy => "legacy",
+            MdiGeneration::Unified => "unified",
+        }
+    }
 }
 
+impl std::fmt::Display for MdiGeneration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Confidence in a "no MDI" verdict
+///
+/// A sensor hostname that does not resolve usually means the tenant has no
+/// MDI instance, but a plain NXDOMAIN can be spoofed or come from a broken
+/// resolver. With `--dnssec-negatives`, the hostnames are looked up again
+/// with DNSSEC validation: when the signed zone proves with NSEC records that
+/// none of them exist, the verdict is cryptographically confirmed.
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::MdiConfidence;
+///
+/// let confidence = MdiConfidence::DnssecConfirmed;
+/// assert_eq!(serde_json::to_string(&confidence).unwrap(), "\"dnssec_confirmed\"");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MdiConfidence {
+    /// No sensor hostname resolved, but their absence could not be proven
+    Unconfirmed,
+    /// DNSSEC proved that no sensor hostname of the tenant exists
+    DnssecConfirmed,
+}
+
+/// Sensor API hostname suffixes probed for each MDI generation
+///
+/// Entries are probed in order and probing stops at the first hostname that
+/// resolves. The documented legacy namespace comes first, so the unified
+/// namespace costs an extra query only for tenants without a legacy sensor
+/// endpoint.
+pub const MDI_SENSOR_ENDPOINTS: &[(MdiGeneration, &str)] = &[
+    (MdiGeneration::Legacy, "sensorapi.atp.azure.com"),
+    (MdiGeneration::Unified, "sensorapi.security.microsoft.com"),
+];
+
 /// Core engine for Microsoft Defender for Identity scanning
 ///
 /// The `MdiChecker` orchestrates the entire scanning process by coordinating:
@@ -115,6 +332,7 @@ pub struct DomainResult {
 /// # Ok(())
 /// # }
 /// ```
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
 pub struct MdiChecker {
     /// Client for making HTTP requests to autodiscover endpoints
     http_client: Arc<HttpClient>,
@@ -126,8 +344,57 @@ pub struct MdiChecker {
     concurrent_limit: usize,
     /// Cache of domain check results to avoid duplicate work
     results_cache: Arc<DashMap<String, DomainResult>>,
+    /// Microsoft IP ranges for endpoint attribution, if enabled
+    ip_ranges: Option<Arc<IpRanges>>,
+    /// Domains intrusive detectors may touch; all domains when None
+    verified_domains: Option<Arc<HashSet<String>>>,
+    /// Engagement stamped onto every result
+    engagement: Option<Engagement>,
+    /// Age after which cached results are rechecked; cached indefinitely when None
+    max_cache_age: Option<Duration>,
+    /// Maximum number of cached results; unbounded when None
+    cache_capacity: Option<usize>,
+    /// Sampler limiting repetitive failure logs, shared with the HTTP client and resolver
+    log_sampler: Arc<LogSampler>,
+    /// Store keeping every federation response as evidence, if capturing
+    capture: Option<Arc<ResponseStore>>,
+    /// Validate federation responses against the Autodiscover schema
+    strict_schema: bool,
+    /// Recorder of per-domain pipeline spans, if tracing
+    trace: Option<Arc<TraceRecorder>>,
+    /// Run time limit of batches, if any
+    deadline: Option<Deadline>,
+    /// Recorder of the requests granted to batches, if reporting
+    rate_recorder: Option<Arc<RateRecorder>>,
+    /// Detectors run for every domain
+    depth: Depth,
+    /// Outcomes and latencies of every detector run
+    detector_stats: Arc<DetectorStats>,
+    /// Alternate endpoint and resolver of the last-chance pass, if enabled
+    roaming: Option<Roaming>,
+    /// Workers pipelining the MDI sensor lookups, if enabled
+    lookup_pool: Option<Arc<LookupPool>>,
+    /// Budget of the MDI sensor lookups; the resolver's when None
+    mdi_limiter: Option<Arc<RateLimiter>>,
 }
 
+/// Run time limit of batches (see [`MdiChecker::with_max_runtime`])
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[derive(Debug, Clone)]
+struct Deadline {
+    max_runtime: Duration,
+    checkpoint: PathBuf,
+}
+
+/// Alternate components of the roaming retry (see [`MdiChecker::with_roaming`])
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[derive(Clone)]
+struct Roaming {
+    http_client: Arc<HttpClient>,
+    dns_resolver: Arc<DnsResolver>,
+}
+
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
 impl MdiChecker {
     /// Creates a new MDI checker with specified concurrency and timeout settings
     ///
@@ -154,15 +421,275 @@ impl MdiChecker {
     /// # }
     /// ```
     pub fn new(concurrent_requests: usize, timeout_ms: u64) -> Result<Self> {
+        let log_sampler = Arc::new(LogSampler::default());
         Ok(Self {
-            http_client: Arc::new(HttpClient::new(Duration::from_millis(timeout_ms))?),
-            dns_resolver: Arc::new(DnsResolver::new()?),
+            http_client: Arc::new(
+                HttpClient::new(Duration::from_millis(timeout_ms))?
+                    .with_log_sampler(Arc::clone(&log_sampler)),
+            ),
+            dns_resolver: Arc::new(DnsResolver::new()?.with_log_sampler(Arc::clone(&log_sampler))),
             xml_parser: Arc::new(XmlParser::new()),
             concurrent_limit: concurrent_requests,
             results_cache: Arc::new(DashMap::new()),
+            ip_ranges: None,
+            verified_domains: None,
+            engagement: None,
+            max_cache_age: None,
+            cache_capacity: None,
+            log_sampler,
+            capture: None,
+            strict_schema: false,
+            trace: None,
+            deadline: None,
+            rate_recorder: None,
+            depth: Depth::default(),
+            detector_stats: Arc::new(DetectorStats::new()),
+            roaming: None,
+            lookup_pool: None,
+            mdi_limiter: None,
         })
     }
 
+    /// Replaces the DNS resolver, e.g. with one balancing several upstreams
+    ///
+    /// The resolver shares the checker's log sampler.
+    pub fn with_dns_resolver(mut self, resolver: DnsResolver) -> Self {
+        self.dns_resolver = Arc::new(resolver.with_log_sampler(Arc::clone(&self.log_sampler)));
+        self
+    }
+
+    /// Replaces the HTTP client, e.g. with one applying DNS overrides
+    ///
+    /// The client shares the checker's log sampler.
+    pub fn with_http_client(mut self, client: HttpClient) -> Self {
+        self.http_client = Arc::new(client.with_log_sampler(Arc::clone(&self.log_sampler)));
+        self
+    }
+
+    /// Enables attribution of federated mail and identity endpoints
+    ///
+    /// For every federated domain, the MX hosts and `autodiscover.<domain>`
+    /// are resolved and any address outside the given Microsoft ranges is
+    /// reported in `DomainResult::endpoint_anomalies`. This adds several DNS
+    /// lookups per federated domain.
+    ///
+    /// # Arguments
+    /// * `ranges` - Microsoft IP ranges to attribute addresses against
+    ///
+    /// # Examples
+    /// ```
+    /// # use sentri::core::MdiChecker;
+    /// # use sentri::attribution::IpRanges;
+    /// # fn example() -> anyhow::Result<()> {
+    /// let checker = MdiChecker::new(5, 10_000)?.with_ip_attribution(IpRanges::bundled());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_ip_attribution(mut self, ranges: IpRanges) -> Self {
+        self.ip_ranges = Some(Arc::new(ranges));
+        self
+    }
+
+    /// Restricts intrusive detectors to domains with verified ownership
+    ///
+    /// Detectors that query the target's own infrastructure (currently IP
+    /// attribution) skip every domain not in the set; see [`crate::ownership`].
+    ///
+    /// # Arguments
+    /// * `domains` - Lowercase names of verified domains
+    pub fn with_verified_domains(mut self, domains: HashSet<String>) -> Self {
+        self.verified_domains = Some(Arc::new(domains));
+        self
+    }
+
+    /// Stamps every result with the engagement the scan is performed under
+    pub fn with_engagement(mut self, engagement: Engagement) -> Self {
+        self.engagement = Some(engagement);
+        self
+    }
+
+    /// Engagement stamped onto results, if any
+    pub fn engagement(&self) -> Option<&Engagement> {
+        self.engagement.as_ref()
+    }
+
+    /// Rechecks domains whose cached result is older than `max_age`
+    ///
+    /// By default cached results are served until [`MdiChecker::clear_cache`].
+    pub fn with_max_cache_age(mut self, max_age: Duration) -> Self {
+        self.max_cache_age = Some(max_age);
+        self
+    }
+
+    /// Caches at most `capacity` results
+    ///
+    /// Once the cache is full, further results are not cached; entries
+    /// already cached are served until they go stale.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache_capacity = Some(capacity);
+        self
+    }
+
+    /// Keeps every federation response in a content-addressed store
+    ///
+    /// See [`crate::capture`]. A response that cannot be stored is logged;
+    /// the check itself continues.
+    pub fn with_capture(mut self, store: Arc<ResponseStore>) -> Self {
+        self.capture = Some(store);
+        self
+    }
+
+    /// Validates every federation response against the Autodiscover schema
+    ///
+    /// Violations are reported in `DomainResult::schema_warnings`; see
+    /// [`crate::xml_schema`].
+    pub fn with_strict_schema(mut self) -> Self {
+        self.strict_schema = true;
+        self
+    }
+
+    /// Records the spans of every domain check; see [`crate::trace`]
+    ///
+    /// Results served from the cache are not traced.
+    pub fn with_trace(mut self, recorder: Arc<TraceRecorder>) -> Self {
+        self.trace = Some(recorder);
+        self
+    }
+
+    /// Selects the detectors run for every domain; see [`crate::depth`]
+    ///
+    /// The level does not configure the components: `deep` attribution needs
+    /// [`MdiChecker::with_ip_attribution`] and its DNS budget is applied
+    /// through the resolver given to [`MdiChecker::with_dns_resolver`].
+    pub fn with_depth(mut self, depth: Depth) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Detectors run for every domain
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+
+    /// Stops batches from starting new chunks once they have run for `max_runtime`
+    ///
+    /// The chunk in flight when the limit passes is finished and delivered as
+    /// usual. The domains not yet started are written to `checkpoint`, one per
+    /// line, so a later run can resume with `--input-file <checkpoint>`, and
+    /// the batch summary reports them in [`BatchSummary::truncated`].
+    ///
+    /// # Arguments
+    /// * `max_runtime` - Run time after which no new chunk is started
+    /// * `checkpoint` - File receiving the unprocessed domains
+    pub fn with_max_runtime(mut self, max_runtime: Duration, checkpoint: PathBuf) -> Self {
+        self.deadline = Some(Deadline {
+            max_runtime,
+            checkpoint,
+        });
+        self
+    }
+
+    /// Records the requests granted by the rate limiter of batches; see
+    /// [`crate::rate_report`]
+    pub fn with_rate_recorder(mut self, recorder: Arc<RateRecorder>) -> Self {
+        self.rate_recorder = Some(recorder);
+        self
+    }
+
+    /// Resolves MDI sensor names through a pool of pipelining lookup workers
+    ///
+    /// See [`crate::dns_pipeline`]. The workers use the checker's current
+    /// resolver, so this is called after
+    /// [`with_dns_resolver`](Self::with_dns_resolver), typically with a
+    /// resolver built [`with_pipelining`](DnsResolver::with_pipelining).
+    ///
+    /// # Panics
+    /// Panics when called outside of a Tokio runtime.
+    pub fn with_dns_pipeline(mut self, config: PipelineConfig) -> Self {
+        let pool = LookupPool::spawn(Arc::clone(&self.dns_resolver), config);
+        self.lookup_pool = Some(Arc::new(pool));
+        self
+    }
+
+    /// Gives the MDI sensor lookups a rate limiter of their own
+    ///
+    /// Sensor names live in Microsoft's zones rather than the targets', so
+    /// their throttling can be tuned apart from every other lookup of the
+    /// resolver (see [`crate::depth::DnsBudget`]). This includes the DNSSEC
+    /// proofs of [`MdiConfidence`], the lookups of the roaming retry and the
+    /// tenant lookups of [`MdiChecker::check_tenant`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use sentri::core::MdiChecker;
+    /// # use sentri::depth::DnsBudget;
+    /// # fn example() -> anyhow::Result<()> {
+    /// let budget = DnsBudget { queries_per_minute: 600, max_concurrent: 50 };
+    /// let checker = MdiChecker::new(10, 5000)?.with_mdi_rate_limiter(budget.limiter());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_mdi_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.mdi_limiter = Some(limiter);
+        self
+    }
+
+    /// Gives failed domains a last chance through another endpoint and resolver
+    ///
+    /// Results failing for a reason of [`ErrorClass::is_environmental`] are
+    /// held back by batches and their domains checked once more after the
+    /// last chunk, through `http_client` (typically configured with
+    /// [`crate::http::ALTERNATE_AUTODISCOVER_URL`]) and `dns_resolver`. The
+    /// result of that second check is the one recorded, and
+    /// [`BatchSummary::roaming`] counts the domains it recovered.
+    ///
+    /// # Arguments
+    /// * `http_client` - Client sending federation requests to the alternate endpoint
+    /// * `dns_resolver` - Resolver querying the alternate upstreams
+    pub fn with_roaming(mut self, http_client: HttpClient, dns_resolver: DnsResolver) -> Self {
+        let log_sampler = Arc::clone(&self.log_sampler);
+        self.roaming = Some(Roaming {
+            http_client: Arc::new(http_client.with_log_sampler(Arc::clone(&log_sampler))),
+            dns_resolver: Arc::new(dns_resolver.with_log_sampler(log_sampler)),
+        });
+        self
+    }
+
+    /// Checker sending every request through the roaming components, if configured
+    pub fn roaming(&self) -> Option<MdiChecker> {
+        let roaming = self.roaming.as_ref()?;
+        let mut checker = self.clone();
+        checker.http_client = Arc::clone(&roaming.http_client);
+        checker.dns_resolver = Arc::clone(&roaming.dns_resolver);
+        checker.roaming = None;
+        // The pool resolves through the primary resolver
+        checker.lookup_pool = None;
+        Some(checker)
+    }
+
+    /// Checks a domain, retrying an environmental failure once through the
+    /// roaming components
+    ///
+    /// Behaves like [`check_domain`](Self::check_domain) without
+    /// [`with_roaming`](Self::with_roaming).
+    pub async fn check_domain_with_roaming(&self, domain: &str) -> Result<DomainResult> {
+        let result = self.check_domain(domain).await?;
+        match self.roaming() {
+            Some(roaming) if needs_last_chance(&result) => {
+                info!(domain, error = ?result.error, "Retrying through the roaming endpoint and resolver");
+                roaming.check_domain(domain).await
+            }
+            _ => Ok(result),
+        }
+    }
+
+    /// Returns true if intrusive detectors may touch the domain
+    pub fn may_probe(&self, domain: &str) -> bool {
+        self.verified_domains
+            .as_ref()
+            .is_none_or(|verified| verified.contains(&domain.to_lowercase()))
+    }
+
     /// Checks a single domain for MDI presence with caching
     ///
     /// This method performs the complete MDI detection workflow:
@@ -199,17 +726,45 @@ impl MdiChecker {
     /// # }
     /// ```
     pub async fn check_domain(&self, domain: &str) -> Result<DomainResult> {
-        let start = Instant::now();
+        let stopwatch = Stopwatch::start();
 
-        if let Some(cached) = self.results_cache.get(domain) {
-            debug!("Cache hit for domain: {}", domain);
-            return Ok(cached.clone());
+        // Clone first so the map guard is released before a stale entry is removed
+        let cached = self.results_cache.get(domain).map(|entry| entry.clone());
+        if let Some(mut cached) = cached {
+            if self.is_fresh(&cached) {
+                debug!(domain, "Cache hit");
+                cached.from_cache = true;
+                return Ok(cached);
+            }
+            debug!(domain, checked_at = %cached.checked_at, "Cached result is stale, rechecking");
+            self.results_cache.remove(domain);
         }
 
-        let result = self.check_domain_impl(domain, start).await;
+        let result = match &self.trace {
+            Some(recorder) => {
+                recorder
+                    .domain(domain)
+                    .scope(async {
+                        let mut check = span("check_domain");
+                        let result = self.check_domain_impl(domain, &stopwatch).await;
+                        if let Ok(result) = &result {
+                            check.arg("mdi", result.mdi_instance.is_some());
+                            if let Some(class) = result.error_class {
+                                check.arg("error_class", class.as_str());
+                            }
+                        }
+                        result
+                    })
+                    .await
+            }
+            None => self.check_domain_impl(domain, &stopwatch).await,
+        };
 
         if let Ok(ref result) = result {
-            if result.error.is_none() {
+            let full = self
+                .cache_capacity
+                .is_some_and(|capacity| self.results_cache.len() >= capacity);
+            if result.error.is_none() && !full {
                 self.results_cache
                     .insert(domain.to_string(), result.clone());
             }
@@ -218,51 +773,292 @@ impl MdiChecker {
         result
     }
 
-    async fn check_domain_impl(&self, domain: &str, start: Instant) -> Result<DomainResult> {
-        debug!("Starting check for domain: {}", domain);
+    /// Enumerates the federated domains of a domain and nothing else
+    ///
+    /// Sends the single GetFederationInformation request of a check and
+    /// extracts the tenant from its answer, as [`Depth::Fast`] does: no MDI
+    /// sensor or endpoint lookups are made, whatever the depth of the
+    /// checker. The result cache is bypassed, as a cached result of a deeper
+    /// check would carry findings this call did not make.
+    ///
+    /// # Arguments
+    /// * `domain` - Domain to enumerate (e.g., "example.com")
+    ///
+    /// # Examples
+    /// ```
+    /// # use sentri::core::MdiChecker;
+    /// # async fn example() -> anyhow::Result<()> {
+    /// let checker = MdiChecker::new(5, 10_000)?;
+    /// let result = checker.check_federation("example.com").await?;
+    /// for domain in &result.federated_domains {
+    ///     println!("{}", domain);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn check_federation(&self, domain: &str) -> Result<DomainResult> {
+        let stopwatch = Stopwatch::start();
+        let mut fast = self.clone();
+        fast.depth = Depth::Fast;
+        fast.check_domain_impl(domain, &stopwatch).await
+    }
+
+    /// Checks a tenant known by name, skipping federation discovery
+    ///
+    /// The tenant exists if `<tenant>.onmicrosoft.com` has a mail exchanger,
+    /// which Microsoft publishes for every tenant; the MDI sensor names of an
+    /// existing tenant are then resolved as by [`MdiChecker::check_domain`].
+    ///
+    /// The result is reported for `<tenant>.onmicrosoft.com`, without
+    /// federated domains and with no tenant if it does not exist. Tenant
+    /// results are not cached, so they never answer a domain check.
+    ///
+    /// # Arguments
+    /// * `name` - Tenant name, e.g. "contoso", with or without `.onmicrosoft.com`
+    ///
+    /// # Examples
+    /// ```
+    /// # use sentri::core::MdiChecker;
+    /// # async fn example() -> anyhow::Result<()> {
+    /// let checker = MdiChecker::new(5, 10_000)?;
+    /// let result = checker.check_tenant("contoso").await?;
+    /// assert_eq!(result.domain, "contoso.onmicrosoft.com");
+    /// if result.tenant.is_none() && result.error.is_none() {
+    ///     println!("No such tenant");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn check_tenant(&self, name: &str) -> Result<DomainResult> {
+        let stopwatch = Stopwatch::start();
+        let name = name.trim().to_ascii_lowercase();
+        let tenant = name.strip_suffix(".onmicrosoft.com").unwrap_or(&name);
+        let domain = format!("{}.onmicrosoft.com", tenant);
+        debug!(tenant, "Starting tenant check");
+
+        let validated = match validate_domain(&domain) {
+            Ok(()) if tenant.contains('.') => Err(format!("Invalid tenant name: {}", name)),
+            validated => validated,
+        };
+        if let Err(validation_error) = validated {
+            return Ok(DomainResult {
+                domain,
+                engagement: self.engagement.clone(),
+                processing_time_ms: stopwatch.elapsed_ms(),
+                error: Some(validation_error),
+                error_class: Some(ErrorClass::InvalidDomain),
+                checked_at: stopwatch.started_at(),
+                completed_at: stopwatch.now(),
+                ..Default::default()
+            });
+        }
+
+        let started = Instant::now();
+        let exchanges = {
+            let _span = span("tenant");
+            match &self.mdi_limiter {
+                Some(limiter) => self.dns_resolver.resolve_mx_limited(&domain, limiter).await,
+                None => self.dns_resolver.resolve_mx(&domain).await,
+            }
+        };
+        self.detector_stats
+            .record(Detector::Tenant, exchanges.is_ok(), started.elapsed());
+        let tenant = match exchanges {
+            Ok(exchanges) if exchanges.is_empty() => {
+                debug!(tenant, "Tenant does not exist");
+                None
+            }
+            Ok(_) => Some(tenant.to_string()),
+            Err(e) => {
+                let message = format!("{:#}", e);
+                if let Some(occurrences) = self.log_sampler.sample("core.tenant_failed") {
+                    error!(tenant, error = %message, occurrences, "Failed to look up tenant");
+                }
+                return Ok(DomainResult {
+                    domain,
+                    engagement: self.engagement.clone(),
+                    processing_time_ms: stopwatch.elapsed_ms(),
+                    error: Some(message),
+                    error_class: Some(ErrorClass::classify(&e)),
+                    checked_at: stopwatch.started_at(),
+                    completed_at: stopwatch.now(),
+                    ..Default::default()
+                });
+            }
+        };
+
+        let (mdi_instance, mdi_generation, mdi_confidence) =
+            self.detect_mdi(tenant.as_deref()).await;
+
+        Ok(DomainResult {
+            schema_version: RESULT_SCHEMA_VERSION,
+            domain,
+            tenant,
+            mdi_instance,
+            mdi_generation,
+            mdi_confidence,
+            engagement: self.engagement.clone(),
+            processing_time_ms: stopwatch.elapsed_ms(),
+            checked_at: stopwatch.started_at(),
+            completed_at: stopwatch.now(),
+            ..Default::default()
+        })
+    }
+
+    /// Returns true if a cached result is young enough to be served
+    fn is_fresh(&self, cached: &DomainResult) -> bool {
+        self.max_cache_age.is_none_or(|max_age| {
+            chrono::Duration::from_std(max_age)
+                .is_ok_and(|max_age| Utc::now() - cached.checked_at <= max_age)
+        })
+    }
+
+    /// Discards all cached results
+    ///
+    /// Long-running callers such as watch mode call this before each cycle so
+    /// every domain is rescanned instead of served from the cache.
+    pub fn clear_cache(&self) {
+        self.results_cache.clear();
+    }
+
+    /// Checks that the autodiscover endpoint answers, returning its HTTP status
+    pub async fn probe_autodiscover(&self) -> Result<u16> {
+        Ok(self.http_client.probe_autodiscover().await?.as_u16())
+    }
+
+    /// Checks that DNS resolution works by resolving the autodiscover host
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of addresses resolved
+    pub async fn probe_dns(&self) -> Result<usize> {
+        let host = self
+            .http_client
+            .autodiscover_host()
+            .context("Autodiscover URL has no host")?;
+        let addresses = self.dns_resolver.resolve(&host).await?;
+        if addresses.is_empty() {
+            anyhow::bail!("{} resolved to no addresses", host);
+        }
+        Ok(addresses.len())
+    }
+
+    /// Outcomes and latencies of the detectors run by this checker and its clones
+    pub fn detector_stats(&self) -> &DetectorStats {
+        &self.detector_stats
+    }
+
+    /// Sampler limiting repetitive failure logs of this checker
+    pub fn log_sampler(&self) -> &LogSampler {
+        &self.log_sampler
+    }
+
+    /// Maximum number of domain checks run concurrently
+    pub fn concurrent_limit(&self) -> usize {
+        self.concurrent_limit
+    }
+
+    async fn check_domain_impl(&self, domain: &str, stopwatch: &Stopwatch) -> Result<DomainResult> {
+        debug!(domain, "Starting check");
 
-        if let Err(validation_error) = validate_domain(domain) {
-            error!("Domain validation failed: {}", validation_error);
+        let validated = {
+            let _span = span("validation");
+            validate_domain(domain)
+        };
+        if let Err(validation_error) = validated {
+            if let Some(occurrences) = self.log_sampler.sample("core.invalid_domain") {
+                error!(domain, error = %validation_error, occurrences, "Domain validation failed");
+            }
             return Ok(DomainResult {
                 domain: domain.to_string(),
-                tenant: None,
-                federated_domains: vec![],
-                mdi_instance: None,
-                processing_time_ms: start.elapsed().as_millis() as u64,
+                engagement: self.engagement.clone(),
+                processing_time_ms: stopwatch.elapsed_ms(),
                 error: Some(validation_error),
+                error_class: Some(ErrorClass::InvalidDomain),
+                checked_at: stopwatch.started_at(),
+                completed_at: stopwatch.now(),
+                ..Default::default()
             });
         }
 
-        let federation_info = match self.get_federation_info(domain).await {
-            Ok(info) => info,
+        let federation_started = Instant::now();
+        let federation = self.get_federation_info(domain).await;
+        // A rejected domain is an answer, not a failure of the service
+        let answered = match &federation {
+            Ok(_) => true,
+            Err(e) => ErrorClass::classify(e) == ErrorClass::InvalidDomain,
+        };
+        self.detector_stats
+            .record(Detector::Federation, answered, federation_started.elapsed());
+        let FederationResponse {
+            info: federation_info,
+            response_sha256,
+            schema_warnings,
+        } = match federation {
+            Ok(response) => response,
             Err(e) => {
-                error!("Failed to get federation info for {}: {}", domain, e);
+                let message = format!("{:#}", e);
+                if let Some(occurrences) = self.log_sampler.sample("core.federation_failed") {
+                    error!(domain, error = %message, occurrences, "Failed to get federation info");
+                }
                 return Ok(DomainResult {
                     domain: domain.to_string(),
-                    tenant: None,
-                    federated_domains: vec![],
-                    mdi_instance: None,
-                    processing_time_ms: start.elapsed().as_millis() as u64,
-                    error: Some(e.to_string()),
+                    engagement: self.engagement.clone(),
+                    processing_time_ms: stopwatch.elapsed_ms(),
+                    error: Some(message),
+                    error_class: Some(ErrorClass::classify(&e)),
+                    checked_at: stopwatch.started_at(),
+                    completed_at: stopwatch.now(),
+                    ..Default::default()
                 });
             }
         };
 
-        let tenant = self.extract_tenant(&federation_info.domains);
+        let tenant = {
+            let _span = span("tenant");
+            let started = Instant::now();
+            let tenant = self.extract_tenant(&federation_info.domains);
+            self.detector_stats
+                .record(Detector::Tenant, true, started.elapsed());
+            tenant
+        };
 
-        let mdi_instance = if let Some(ref tenant_name) = tenant {
-            self.check_mdi_instance(tenant_name).await
-        } else {
-            None
+        let (mdi_instance, mdi_generation, mdi_confidence) =
+            self.detect_mdi(tenant.as_deref()).await;
+
+        let endpoint_anomalies = match &self.ip_ranges {
+            Some(ranges) if self.depth > Depth::Fast => {
+                self.check_endpoints(ranges, &federation_info.domains).await
+            }
+            _ => Vec::new(),
         };
 
+        debug!(
+            domain,
+            tenant = tenant.as_deref(),
+            mdi_instance = mdi_instance.as_deref(),
+            elapsed_ms = stopwatch.elapsed_ms(),
+            "Check completed"
+        );
+
         Ok(DomainResult {
+            schema_version: RESULT_SCHEMA_VERSION,
             domain: domain.to_string(),
             tenant: tenant.clone(),
             federated_domains: federation_info.domains,
             mdi_instance,
-            processing_time_ms: start.elapsed().as_millis() as u64,
+            mdi_generation,
+            mdi_confidence,
+            endpoint_anomalies,
+            engagement: self.engagement.clone(),
+            tags: Tags::new(),
+            processing_time_ms: stopwatch.elapsed_ms(),
+            response_sha256,
+            schema_warnings,
             error: None,
+            error_class: None,
+            from_cache: false,
+            checked_at: stopwatch.started_at(),
+            completed_at: stopwatch.now(),
         })
     }
 
@@ -276,11 +1072,48 @@ impl MdiChecker {
     /// * `domain` - Domain to get federation information for
     ///
     /// # Returns
-    /// * `Result<FederationInfo>` - Federation info containing all federated domains
-    async fn get_federation_info(&self, domain: &str) -> Result<FederationInfo> {
+    /// * `Result<FederationResponse>` - Federation info containing all federated
+    ///   domains, with the canonical hash of the response and any schema warnings
+    async fn get_federation_info(&self, domain: &str) -> Result<FederationResponse> {
+        let _span = span("federation");
         let soap_body = self.xml_parser.create_federation_request(domain);
         let response_xml = self.http_client.post_soap_request(&soap_body).await?;
-        self.xml_parser.parse_federation_response(&response_xml)
+        if let Some(store) = &self.capture {
+            if let Err(e) = store.capture(domain, &response_xml).await {
+                if let Some(occurrences) = self.log_sampler.sample("core.capture_failed") {
+                    error!(domain, error = %e, occurrences, "Failed to capture federation response");
+                }
+            }
+        }
+        let schema_warnings = if self.strict_schema {
+            validate_federation_response(&response_xml)
+        } else {
+            Vec::new()
+        };
+        if !schema_warnings.is_empty() {
+            if let Some(occurrences) = self.log_sampler.sample("core.schema_violation") {
+                warn!(
+                    domain,
+                    warnings = schema_warnings.len(),
+                    first = %schema_warnings[0].message,
+                    occurrences,
+                    "Federation response violates the Autodiscover schema"
+                );
+            }
+        }
+        let info = self.xml_parser.parse_federation_response(&response_xml)?;
+        let response_sha256 = match canonical_sha256(&response_xml) {
+            Ok(hash) => Some(hash),
+            Err(e) => {
+                debug!(domain, error = %e, "Failed to canonicalize federation response");
+                None
+            }
+        };
+        Ok(FederationResponse {
+            info,
+            response_sha256,
+            schema_warnings,
+        })
     }
 
     /// Extracts Microsoft tenant identifier from federated domains
@@ -302,29 +1135,182 @@ impl MdiChecker {
             .map(String::from)
     }
 
+    /// Runs the MDI detector for a tenant if the depth level asks for it
+    ///
+    /// # Returns
+    /// * The MDI instance and its generation if found, otherwise the
+    ///   confidence in its absence
+    async fn detect_mdi(
+        &self,
+        tenant: Option<&str>,
+    ) -> (Option<String>, Option<MdiGeneration>, Option<MdiConfidence>) {
+        match tenant {
+            Some(tenant) if self.depth.checks_mdi() => {
+                match self.check_mdi_instance(tenant).await {
+                    Some((instance, generation)) => (Some(instance), Some(generation), None),
+                    None => (None, None, self.confirm_no_mdi(tenant).await),
+                }
+            }
+            _ => (None, None, None),
+        }
+    }
+
     /// Checks if an MDI instance exists for the given tenant
     ///
-    /// This method constructs the potential MDI instance URL based on the
-    /// tenant name and performs verification to determine if it exists.
-    /// Uses DNS resolution and HTTP probing with appropriate rate limiting.
+    /// This method constructs the potential MDI sensor hostnames for every
+    /// known endpoint generation (see [`MDI_SENSOR_ENDPOINTS`]) and resolves
+    /// them in order. The first hostname that resolves determines both the
+    /// reported instance and the generation the tenant appears to use.
     ///
     /// # Arguments
     /// * `tenant` - The tenant identifier to check for MDI
     ///
     /// # Returns
-    /// * `Option<String>` - The MDI instance URL if found, None otherwise
-    async fn check_mdi_instance(&self, tenant: &str) -> Option<String> {
-        let mdi_domain = format!("{}sensorapi.atp.azure.com", tenant);
-        match self.dns_resolver.resolve(&mdi_domain).await {
-            Ok(_) => {
-                debug!("MDI instance found for tenant: {}", tenant);
-                Some(mdi_domain)
+    /// * `Option<(String, MdiGeneration)>` - The MDI instance hostname and its
+    ///   endpoint generation if found, None otherwise
+    async fn check_mdi_instance(&self, tenant: &str) -> Option<(String, MdiGeneration)> {
+        let _span = span("mdi");
+        let started = Instant::now();
+        let mut failed = false;
+        let mut instance = None;
+        for (generation, suffix) in MDI_SENSOR_ENDPOINTS {
+            let mdi_domain = format!("{}{}", tenant, suffix);
+            let resolved = match (&self.lookup_pool, &self.mdi_limiter) {
+                (Some(pool), Some(limiter)) => {
+                    pool.resolve_limited(&mdi_domain, Arc::clone(limiter)).await
+                }
+                (Some(pool), None) => pool.resolve(&mdi_domain).await,
+                (None, Some(limiter)) => {
+                    self.dns_resolver
+                        .resolve_limited(&mdi_domain, limiter)
+                        .await
+                }
+                (None, None) => self.dns_resolver.resolve(&mdi_domain).await,
+            };
+            match resolved {
+                Ok(_) => {
+                    debug!(tenant, domain = %mdi_domain, generation = %generation, "MDI instance found");
+                    instance = Some((mdi_domain, *generation));
+                    break;
+                }
+                Err(e) => {
+                    debug!(tenant, domain = %mdi_domain, generation = %generation, error = %e, "No MDI endpoint");
+                    failed |= !is_missing_record(&e);
+                }
             }
-            Err(e) => {
-                debug!("No MDI instance for tenant {}: {}", tenant, e);
-                None
+        }
+
+        // Finding an instance answers the question despite earlier failed lookups
+        self.detector_stats.record(
+            Detector::Mdi,
+            instance.is_some() || !failed,
+            started.elapsed(),
+        );
+        if instance.is_none() {
+            debug!(tenant, "No MDI instance");
+        }
+        instance
+    }
+
+    /// Tries to prove with DNSSEC that a tenant has no MDI instance
+    ///
+    /// Only runs when the resolver validates negatives (see
+    /// [`DnsResolver::with_dnssec_negatives`]); every sensor hostname of
+    /// [`MDI_SENSOR_ENDPOINTS`] must be proven not to exist.
+    ///
+    /// # Returns
+    /// * `Option<MdiConfidence>` - None without validation, otherwise whether
+    ///   the absence is confirmed
+    async fn confirm_no_mdi(&self, tenant: &str) -> Option<MdiConfidence> {
+        if !self.dns_resolver.validates_negatives() {
+            return None;
+        }
+        let _span = span("mdi_dnssec");
+        for (_, suffix) in MDI_SENSOR_ENDPOINTS {
+            let mdi_domain = format!("{}{}", tenant, suffix);
+            let proven = match &self.mdi_limiter {
+                Some(limiter) => {
+                    self.dns_resolver
+                        .prove_nonexistent_limited(&mdi_domain, limiter)
+                        .await
+                }
+                None => self.dns_resolver.prove_nonexistent(&mdi_domain).await,
+            };
+            if !proven {
+                debug!(tenant, host = %mdi_domain, "No MDI instance, unconfirmed by DNSSEC");
+                return Some(MdiConfidence::Unconfirmed);
             }
         }
+        debug!(tenant, "No MDI instance, confirmed by DNSSEC");
+        Some(MdiConfidence::DnssecConfirmed)
+    }
+
+    /// Attributes the mail and identity endpoints of federated domains
+    ///
+    /// `*.onmicrosoft.com` domains are skipped since they are Microsoft-hosted
+    /// by definition, as are domains [`MdiChecker::may_probe`] rejects.
+    /// Endpoints that fail to resolve are ignored.
+    ///
+    /// # Arguments
+    /// * `ranges` - Microsoft IP ranges
+    /// * `domains` - Federated domains to check
+    ///
+    /// # Returns
+    /// * `Vec<EndpointAnomaly>` - Endpoints with addresses outside Microsoft
+    async fn check_endpoints(&self, ranges: &IpRanges, domains: &[String]) -> Vec<EndpointAnomaly> {
+        let _span = span("endpoints");
+        let started = Instant::now();
+        let mut failed = false;
+        let mut anomalies = Vec::new();
+
+        for domain in domains.iter().filter(|d| !d.ends_with(".onmicrosoft.com")) {
+            if !self.may_probe(domain) {
+                debug!(domain = %domain, "Skipping endpoint attribution of unverified domain");
+                continue;
+            }
+            let mut endpoints: Vec<(EndpointKind, String)> =
+                match self.dns_resolver.resolve_mx(domain).await {
+                    Ok(hosts) => hosts
+                        .into_iter()
+                        .map(|host| (EndpointKind::Mx, host))
+                        .collect(),
+                    Err(e) => {
+                        debug!(domain = %domain, error = %e, "MX lookup failed");
+                        failed = true;
+                        Vec::new()
+                    }
+                };
+            endpoints.push((
+                EndpointKind::Autodiscover,
+                format!("autodiscover.{}", domain),
+            ));
+
+            for (kind, host) in endpoints {
+                match self.dns_resolver.resolve(&host).await {
+                    Ok(addresses) => {
+                        if let Some(anomaly) =
+                            ranges.check_endpoint(domain, kind, &host, &addresses)
+                        {
+                            debug!(
+                                domain = %domain,
+                                host = %host,
+                                ?kind,
+                                "Endpoint resolves outside Microsoft"
+                            );
+                            anomalies.push(anomaly);
+                        }
+                    }
+                    Err(e) => {
+                        debug!(host = %host, error = %e, "Failed to resolve endpoint");
+                        failed |= !is_missing_record(&e);
+                    }
+                }
+            }
+        }
+
+        self.detector_stats
+            .record(Detector::Endpoints, !failed, started.elapsed());
+        anomalies
     }
 
     /// Processes a batch of domains from a file with rate limiting
@@ -372,127 +1358,429 @@ impl MdiChecker {
         chunk_size: usize,
         rate_limit: u64,
     ) -> Result<()> {
-        // Open output file for writing if specified
-        let mut output_writer = if let Some(path) = output_file {
-            Some(
-                OpenOptions::new()
-                    .create(true)
-                    .truncate(true)
-                    .write(true)
-                    .open(path)
-                    .await
-                    .context("Failed to create output file")?,
-            )
-        } else {
-            None
-        };
-
-        // Create rate limiter for this batch
-        let rate_limiter = Arc::new(RateLimiter::new(
-            rate_limit as usize,   // requests per minute
-            60_000,                // period of 60 seconds (1 minute)
-            self.concurrent_limit, // max concurrent requests
-        ));
+        let mut sinks = vec![primary_sink(output_file.map(PathBuf::as_path)).await?];
+        self.process_batch_with_sinks(input_file, &mut sinks, chunk_size, rate_limit)
+            .await?;
+        Ok(())
+    }
 
+    /// Processes a batch of domains from a file, delivering results to several sinks
+    ///
+    /// Behaves like `process_batch`, but every sanitized result is handed to each
+    /// of the given sinks in order. Sinks are flushed after every chunk and
+    /// closed once the batch completes.
+    ///
+    /// # Arguments
+    /// * `input_file` - Path to file containing domains to scan (one per line)
+    /// * `sinks` - Destinations receiving every sanitized result
+    /// * `chunk_size` - Number of domains to process in each chunk
+    /// * `rate_limit` - Maximum number of requests per minute
+    ///
+    /// # Returns
+    /// * `Result<BatchSummary>` - Aggregate counts for the run, or error if
+    ///   processing or delivery failed
+    ///
+    /// # Examples
+    /// ```
+    /// # use sentri::core::MdiChecker;
+    /// # use sentri::sinks::{primary_sink, ResultSink};
+    /// # use std::path::Path;
+    /// # use anyhow::Result;
+    /// #
+    /// # async fn example() -> Result<()> {
+    /// let checker = MdiChecker::new(10, 5000)?;
+    /// let mut sinks: Vec<Box<dyn ResultSink>> =
+    ///     vec![primary_sink(Some(Path::new("results.jsonl"))).await?];
+    ///
+    /// let summary = checker
+    ///     .process_batch_with_sinks(Path::new("domains.txt"), &mut sinks, 50, 30)
+    ///     .await?;
+    /// println!("{} domains with MDI", summary.mdi_instances);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn process_batch_with_sinks(
+        &self,
+        input_file: &Path,
+        sinks: &mut [Box<dyn ResultSink>],
+        chunk_size: usize,
+        rate_limit: u64,
+    ) -> Result<BatchSummary> {
         // Stream domains from file instead of loading all into memory
         // This implements the use_streaming_io rule from .windsurfrules
         let file = File::open(input_file)
             .await
             .context(format!("Failed to open domain file: {:?}", input_file))?;
 
-        // Use a generous buffer size for efficiency (64KB)
-        let mut reader = BufReader::with_capacity(64 * 1024, file);
-        let mut domains_processed = 0;
-        let mut current_chunk = Vec::with_capacity(chunk_size);
-        let mut line = String::new();
-
         info!(
             "Processing domains from {} in streaming mode",
             input_file.display()
         );
 
-        // Process domains in streaming fashion without loading entire file into memory
-        loop {
-            line.clear(); // Reuse the string to avoid allocations
-            let bytes_read = reader.read_line(&mut line).await?;
-            if bytes_read == 0 {
-                // End of file
-                break;
-            }
+        // Use a generous buffer size for efficiency (64KB)
+        let source = DomainSource::File {
+            reader: BufReader::with_capacity(64 * 1024, file),
+            line: String::new(),
+        };
+        self.process_source(source, sinks, chunk_size, rate_limit)
+            .await
+    }
 
-            let domain = line.trim();
-            if !domain.is_empty() && !domain.starts_with('#') {
-                current_chunk.push(domain.to_string());
-
-                // When we've collected enough domains, process the chunk
-                if current_chunk.len() >= chunk_size {
-                    domains_processed += current_chunk.len();
-                    info!(
-                        "Processing chunk of {} domains ({} total so far)",
-                        current_chunk.len(),
-                        domains_processed
-                    );
-
-                    let results = self.process_chunk(&current_chunk, &rate_limiter).await;
-
-                    // Stream results to output immediately as they're available
-                    for result in results {
-                        // Sanitize the result before outputting it (implements security:output:sanitize_all_output rule)
-                        let sanitized_result = sanitize_domain_result(&result);
-
-                        if let Some(ref mut writer) = output_writer {
-                            let json_line = format!(
-                                "{}
-",
-                                serde_json::to_string(&sanitized_result)?
-                            );
-                            writer.write_all(json_line.as_bytes()).await?;
-                        } else {
-                            println!("{}", serde_json::to_string_pretty(&sanitized_result)?);
-                        }
-                    }
+    /// Processes a list of domains in chunks, streaming results to sinks
+    ///
+    /// Behaves like [`process_batch_with_sinks`](Self::process_batch_with_sinks)
+    /// for domains that are already in memory, such as the domains of a
+    /// previous results file being re-scanned.
+    ///
+    /// # Arguments
+    /// * `domains` - Domains to process, in order, optionally with their tags
+    /// * `sinks` - Destinations receiving every result; all of them are closed at the end
+    /// * `chunk_size` - Number of domains to process in each chunk
+    /// * `rate_limit` - Maximum number of requests per minute
+    pub async fn process_domains_with_sinks(
+        &self,
+        domains: impl IntoIterator<Item = impl Into<InputLine>>,
+        sinks: &mut [Box<dyn ResultSink>],
+        chunk_size: usize,
+        rate_limit: u64,
+    ) -> Result<BatchSummary> {
+        let domains: Vec<InputLine> = domains.into_iter().map(Into::into).collect();
+        info!(domains = domains.len(), "Processing domains from list");
+        let source = DomainSource::List(domains.into_iter());
+        self.process_source(source, sinks, chunk_size, rate_limit)
+            .await
+    }
 
-                    // Flush after each chunk to avoid buffering too much data
-                    // This follows the streaming IO principle for large datasets
-                    if let Some(ref mut writer) = output_writer {
-                        writer.flush().await?;
-                    }
+    async fn process_source(
+        &self,
+        mut source: DomainSource,
+        sinks: &mut [Box<dyn ResultSink>],
+        chunk_size: usize,
+        rate_limit: u64,
+    ) -> Result<BatchSummary> {
+        let mut summary = BatchSummary::new();
 
-                    current_chunk.clear();
-                }
-            }
+        // Create rate limiter for this batch
+        let mut rate_limiter = RateLimiter::new(
+            rate_limit as usize,   // requests per minute
+            60_000,                // period of 60 seconds (1 minute)
+            self.concurrent_limit, // max concurrent requests
+        );
+        if let Some(recorder) = &self.rate_recorder {
+            rate_limiter = rate_limiter.with_recorder(Arc::clone(recorder));
         }
+        let rate_limiter = Arc::new(rate_limiter);
+
+        let mut domains_processed = 0;
+        let mut current_chunk = Vec::with_capacity(chunk_size);
+        // Failures waiting for the roaming retry
+        let mut held_back = Vec::new();
 
-        // Process any remaining domains in the final chunk
-        if !current_chunk.is_empty() {
-            info!("Processing final chunk of {} domains", current_chunk.len());
-            let results = self.process_chunk(&current_chunk, &rate_limiter).await;
-
-            for result in results {
-                // Sanitize the result before outputting it (implements security:output:sanitize_all_output rule)
-                let sanitized_result = sanitize_domain_result(&result);
-
-                if let Some(ref mut writer) = output_writer {
-                    let json_line = format!(
-                        "{}
-",
-                        serde_json::to_string(&sanitized_result)?
-                    );
-                    writer.write_all(json_line.as_bytes()).await?;
-                } else {
-                    println!("{}", serde_json::to_string_pretty(&sanitized_result)?);
+        // Process domains in streaming fashion without loading entire file into memory
+        while let Some(line) = source.next_line().await? {
+            current_chunk.push(line);
+
+            // When we've collected enough domains, process the chunk
+            if current_chunk.len() >= chunk_size {
+                if self.deadline_passed(&summary) {
+                    break;
                 }
-            }
+                domains_processed += current_chunk.len();
+                info!(
+                    chunk_size = current_chunk.len(),
+                    domains_processed, "Processing chunk"
+                );
 
-            if let Some(ref mut writer) = output_writer {
-                writer.flush().await?;
+                let results = self.process_lines(&current_chunk, &rate_limiter).await;
+                let results = self.hold_back(results, &mut held_back);
+                results.iter().for_each(|result| summary.record(result));
+                crate::crash::record_state("batch", &summary);
+
+                // Stream results to output immediately as they're available
+                Self::write_results(&results, sinks).await?;
+
+                current_chunk.clear();
             }
         }
 
+        if !current_chunk.is_empty() && self.deadline_passed(&summary) {
+            summary.truncated = Some(self.write_checkpoint(&current_chunk, &mut source).await?);
+        } else if !current_chunk.is_empty() {
+            // Process any remaining domains in the final chunk
+            info!(chunk_size = current_chunk.len(), "Processing final chunk");
+            let results = self.process_lines(&current_chunk, &rate_limiter).await;
+            let results = self.hold_back(results, &mut held_back);
+            results.iter().for_each(|result| summary.record(result));
+            Self::write_results(&results, sinks).await?;
+        }
+
+        if !held_back.is_empty() {
+            let results = self
+                .last_chance(held_back, &rate_limiter, &mut summary)
+                .await;
+            results.iter().for_each(|result| summary.record(result));
+            Self::write_results(&results, sinks).await?;
+        }
+
+        for sink in sinks.iter_mut() {
+            sink.close()
+                .await
+                .with_context(|| format!("Failed to close {} sink", sink.name()))?;
+        }
+
+        summary.finish();
+        if let Some(truncation) = &summary.truncated {
+            warn!(
+                domains_processed = summary.domains_processed,
+                unprocessed = truncation.unprocessed,
+                max_runtime_ms = truncation.max_runtime_ms,
+                checkpoint = %truncation.checkpoint.display(),
+                "Batch truncated at its maximum run time"
+            );
+        }
+        if let Some(tracker) = self.http_client.latency_tracker() {
+            summary.slow_hosts = tracker.findings();
+        }
+        summary.detectors = self.detector_stats.summaries();
+        for slow in &summary.slow_hosts {
+            warn!(
+                host = %slow.host,
+                p95_ms = slow.p95_ms,
+                threshold_ms = slow.threshold_ms,
+                times_flagged = slow.times_flagged,
+                "Host was slow during the batch"
+            );
+        }
+        info!(
+            domains_processed = summary.domains_processed,
+            tenants_found = summary.tenants_found,
+            errors = summary.errors,
+            elapsed_ms = summary.elapsed_ms,
+            "Batch processing completed"
+        );
+        for detector in &summary.detectors {
+            info!(
+                detector = %detector.detector,
+                successes = detector.successes,
+                failures = detector.failures,
+                p50_ms = detector.p50_ms,
+                p95_ms = detector.p95_ms,
+                p99_ms = detector.p99_ms,
+                max_ms = detector.max_ms,
+                "Detector statistics"
+            );
+        }
+        for stats in self.dns_resolver.upstream_stats() {
+            info!(
+                upstream = %stats.upstream,
+                queries = stats.queries,
+                failures = stats.failures,
+                average_latency_ms = stats.average_latency_ms,
+                healthy = stats.healthy,
+                "DNS resolver statistics"
+            );
+        }
+        if let Some(pool) = &self.lookup_pool {
+            let stats = pool.stats();
+            info!(
+                lookups = stats.lookups,
+                batches = stats.batches,
+                average_batch = stats.average_batch(),
+                largest_batch = stats.largest_batch,
+                "DNS pipeline statistics"
+            );
+        }
+        if let Some(store) = &self.capture {
+            let stats = store.stats();
+            info!(
+                captured = stats.captured,
+                deduplicated = stats.deduplicated,
+                "Federation responses captured"
+            );
+        }
+        for (class, suppressed) in self.log_sampler.suppressed() {
+            info!(class, suppressed, "Repeated log messages were suppressed");
+        }
+        Ok(summary)
+    }
+
+    /// Moves the results needing a roaming retry into `held_back`
+    ///
+    /// Without [`with_roaming`](Self::with_roaming) every result is returned.
+    fn hold_back(
+        &self,
+        results: Vec<DomainResult>,
+        held_back: &mut Vec<DomainResult>,
+    ) -> Vec<DomainResult> {
+        if self.roaming.is_none() {
+            return results;
+        }
+        let (failed, results): (Vec<_>, Vec<_>) = results.into_iter().partition(needs_last_chance);
+        held_back.extend(failed);
+        results
+    }
+
+    /// Checks held-back failures once more through the roaming components
+    ///
+    /// The failures are returned unchanged once the batch has exhausted its
+    /// run time. Domains failing again keep the error of their second check.
+    async fn last_chance(
+        &self,
+        failed: Vec<DomainResult>,
+        rate_limiter: &Arc<RateLimiter>,
+        summary: &mut BatchSummary,
+    ) -> Vec<DomainResult> {
+        let Some(roaming) = self.roaming() else {
+            return failed;
+        };
+        if self.deadline_passed(summary) {
+            warn!(
+                failed = failed.len(),
+                "No run time left for the roaming retry"
+            );
+            return failed;
+        }
         info!(
-            "Batch processing completed, processed {} domains in total",
-            domains_processed + current_chunk.len()
+            failed = failed.len(),
+            "Retrying failed domains through the roaming endpoint and resolver"
         );
+        let lines: Vec<InputLine> = failed
+            .into_iter()
+            .map(|result| InputLine {
+                domain: result.domain,
+                tags: result.tags,
+            })
+            .collect();
+        let results = roaming.process_lines(&lines, rate_limiter).await;
+        let recovered = results.iter().filter(|r| r.error.is_none()).count();
+        info!(
+            retried = results.len(),
+            recovered, "Roaming retry completed"
+        );
+        summary.roaming = Some(RoamingSummary {
+            retried: results.len(),
+            recovered,
+        });
+        results
+    }
+
+    /// Returns true once a batch started with `summary` has exhausted its run time
+    fn deadline_passed(&self, summary: &BatchSummary) -> bool {
+        self.deadline
+            .as_ref()
+            .is_some_and(|deadline| summary.stopwatch.elapsed() >= deadline.max_runtime)
+    }
+
+    /// Writes `pending` and the rest of `source` to the checkpoint file
+    ///
+    /// The checkpoint replaces any previous one only once `source` is drained,
+    /// so a batch resuming from a checkpoint may also write to it.
+    async fn write_checkpoint(
+        &self,
+        pending: &[InputLine],
+        source: &mut DomainSource,
+    ) -> Result<Truncation> {
+        let deadline = self
+            .deadline
+            .as_ref()
+            .context("No maximum run time configured")?;
+        let path = &deadline.checkpoint;
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let file = File::create(&partial)
+            .await
+            .with_context(|| format!("Failed to create checkpoint {}", partial.display()))?;
+        let mut writer = BufWriter::new(file);
+        let mut unprocessed = 0;
+        // Lines keep their tags so a resumed run carries them on
+        for line in pending {
+            writer.write_all(line.to_string().as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            unprocessed += 1;
+        }
+        while let Some(line) = source.next_line().await? {
+            writer.write_all(line.to_string().as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            unprocessed += 1;
+        }
+        writer
+            .flush()
+            .await
+            .with_context(|| format!("Failed to write checkpoint {}", partial.display()))?;
+        tokio::fs::rename(&partial, path)
+            .await
+            .with_context(|| format!("Failed to replace checkpoint {}", path.display()))?;
+        Ok(Truncation {
+            max_runtime_ms: u64::try_from(deadline.max_runtime.as_millis()).unwrap_or(u64::MAX),
+            unprocessed,
+            checkpoint: path.clone(),
+        })
+    }
+
+    /// Sanitizes a chunk of results and hands them to every sink
+    ///
+    /// Sinks are flushed once the whole chunk has been written, following the
+    /// streaming IO principle for large datasets.
+    ///
+    /// # Arguments
+    /// * `results` - Results of a processed chunk
+    /// * `sinks` - Destinations receiving every sanitized result
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or the first delivery error
+    pub async fn write_results(
+        results: &[DomainResult],
+        sinks: &mut [Box<dyn ResultSink>],
+    ) -> Result<()> {
+        for result in results {
+            // Sanitize the result before outputting it (implements security:output:sanitize_all_output rule)
+            Self::write_to_sinks(&sanitize_domain_result(result), sinks).await?;
+        }
+        Self::flush_sinks(sinks).await
+    }
+
+    /// Writes results that were sanitized before, as read back from a results file
+    ///
+    /// Unlike [`MdiChecker::write_results`], the results are delivered as
+    /// they are: escaping them again would turn `&amp;` into `&amp;amp;`.
+    ///
+    /// # Arguments
+    /// * `results` - Results that already went through sanitization
+    /// * `sinks` - Destinations receiving every result
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or the first delivery error
+    pub async fn write_sanitized_results(
+        results: &[DomainResult],
+        sinks: &mut [Box<dyn ResultSink>],
+    ) -> Result<()> {
+        for result in results {
+            Self::write_to_sinks(result, sinks).await?;
+        }
+        Self::flush_sinks(sinks).await
+    }
+
+    /// Hands one result to every sink
+    async fn write_to_sinks(
+        result: &DomainResult,
+        sinks: &mut [Box<dyn ResultSink>],
+    ) -> Result<()> {
+        for sink in sinks.iter_mut() {
+            sink.write(result)
+                .await
+                .with_context(|| format!("Failed to write result to {} sink", sink.name()))?;
+        }
+        Ok(())
+    }
+
+    /// Flushes every sink
+    async fn flush_sinks(sinks: &mut [Box<dyn ResultSink>]) -> Result<()> {
+        for sink in sinks.iter_mut() {
+            sink.flush()
+                .await
+                .with_context(|| format!("Failed to flush {} sink", sink.name()))?;
+        }
         Ok(())
     }
 
@@ -509,19 +1797,31 @@ impl MdiChecker {
     ///
     /// # Returns
     /// * `Vec<DomainResult>` - Results for all processed domains
-    async fn process_chunk(
+    pub(crate) async fn process_chunk(
         &self,
         domains: &[String],
         rate_limiter: &Arc<RateLimiter>,
+    ) -> Vec<DomainResult> {
+        let lines: Vec<InputLine> = domains.iter().map(InputLine::from).collect();
+        self.process_lines(&lines, rate_limiter).await
+    }
+
+    /// Processes a chunk of input lines, copying their tags into the results
+    ///
+    /// Behaves like [`process_chunk`](Self::process_chunk).
+    pub(crate) async fn process_lines(
+        &self,
+        lines: &[InputLine],
+        rate_limiter: &Arc<RateLimiter>,
     ) -> Vec<DomainResult> {
         // Process domains in parallel with rate limiting
         use futures::{stream, StreamExt}; // Import in function scope to avoid conflicts
 
-        stream::iter(domains)
-            .map(|domain| {
+        // Owned items keep the future `Send` when callers spawn it
+        stream::iter(lines.iter().cloned())
+            .map(|InputLine { domain, tags }| {
                 let checker = self.clone();
                 let rate_limiter = rate_limiter.clone();
-                let domain = domain.clone();
 
                 async move {
                     // Acquire rate limit permit using our new RateLimiter
@@ -529,38 +1829,48 @@ impl MdiChecker {
 
                     // If we fail to acquire a permit, return error result
                     if let Err(e) = permit_result {
-                        error!("Failed to acquire rate limit permit: {}", e);
+                        if let Some(occurrences) = checker.log_sampler.sample("core.rate_limit_permit") {
+                            error!(domain = %domain, error = %e, occurrences, "Failed to acquire rate limit permit");
+                        }
                         return DomainResult {
                             domain: domain.clone(),
-                            tenant: None,
-                            federated_domains: vec![],
-                            mdi_instance: None,
-                            processing_time_ms: 0,
-                            error: Some(format!("Rate limiting error: {}", e)),
+                            engagement: checker.engagement.clone(),
+                            tags,
+                            error: Some(format!("Rate limiting error: {:#}", e)),
+                            error_class: Some(ErrorClass::RateLimited),
+                            checked_at: Utc::now(),
+                            completed_at: Utc::now(),
+                            ..Default::default()
                         };
                     }
 
                     // Permit successfully acquired, proceed with domain check
                     let _permit = permit_result.unwrap();
-                    debug!("Processing domain: {}", domain);
+                    debug!(domain = %domain, "Processing domain");
 
                     let result = checker.check_domain(&domain).await;
 
                     // Convert Result to DomainResult
                     match result {
-                        Ok(domain_result) => domain_result,
+                        Ok(domain_result) => DomainResult {
+                            tags,
+                            ..domain_result
+                        },
                         Err(e) => DomainResult {
                             domain,
-                            tenant: None,
-                            federated_domains: vec![],
-                            mdi_instance: None,
-                            processing_time_ms: 0,
-                            error: Some(e.to_string()),
+                            engagement: checker.engagement.clone(),
+                            tags,
+                            error: Some(format!("{:#}", e)),
+                            error_class: Some(ErrorClass::classify(&e)),
+                            checked_at: Utc::now(),
+                            completed_at: Utc::now(),
+                            ..Default::default()
                         },
                     }
                 }
             })
             .buffer_unordered(self.concurrent_limit)
+            .inspect(|_| crate::service::progress())
             .collect()
             .await
     }
@@ -581,9 +1891,8 @@ impl MdiChecker {
     /// * `path` - Path to the domain list file
     ///
     /// # Returns
-    /// * `Result<Vec<String>>` - List of parsed domains or error
-    #[allow(dead_code)] // Retained for backward compatibility
-    async fn read_domains_from_file(&self, path: &Path) -> Result<Vec<String>> {
+    /// * `Result<Vec<InputLine>>` - List of parsed domains with their tags or error
+    pub(crate) async fn read_domains_from_file(&self, path: &Path) -> Result<Vec<InputLine>> {
         let file = File::open(path)
             .await
             .context(format!("Failed to open domain file: {:?}", path))?;
@@ -593,16 +1902,51 @@ impl MdiChecker {
         let mut domains = Vec::new();
 
         while let Some(line) = lines.next_line().await? {
-            let trimmed = line.trim();
-            if !trimmed.is_empty() && !trimmed.starts_with('#') {
-                domains.push(trimmed.to_string());
-            }
+            domains.extend(parse_input_line(&line));
         }
 
         Ok(domains)
     }
 }
 
+/// Domains fed into a batch, one at a time
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+enum DomainSource {
+    /// Lines of a domain file; blank lines and `#` comments are skipped
+    File {
+        reader: BufReader<File>,
+        line: String,
+    },
+    /// Domains already in memory
+    List(std::vec::IntoIter<InputLine>),
+}
+
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+impl DomainSource {
+    async fn next_line(&mut self) -> Result<Option<InputLine>> {
+        match self {
+            DomainSource::File { reader, line } => loop {
+                line.clear(); // Reuse the string to avoid allocations
+                if reader.read_line(line).await? == 0 {
+                    // End of file
+                    return Ok(None);
+                }
+                if let Some(input) = parse_input_line(line) {
+                    return Ok(Some(input));
+                }
+            },
+            DomainSource::List(domains) => Ok(domains.next()),
+        }
+    }
+}
+
+/// Returns true if a result failed for a reason a roaming retry may overcome
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+fn needs_last_chance(result: &DomainResult) -> bool {
+    ErrorClass::of(result).is_some_and(|class| class.is_environmental())
+}
+
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
 impl Clone for MdiChecker {
     fn clone(&self) -> Self {
         Self {
@@ -611,6 +1955,22 @@ impl Clone for MdiChecker {
             xml_parser: Arc::clone(&self.xml_parser),
             concurrent_limit: self.concurrent_limit,
             results_cache: Arc::clone(&self.results_cache),
+            ip_ranges: self.ip_ranges.clone(),
+            verified_domains: self.verified_domains.clone(),
+            engagement: self.engagement.clone(),
+            max_cache_age: self.max_cache_age,
+            cache_capacity: self.cache_capacity,
+            log_sampler: Arc::clone(&self.log_sampler),
+            capture: self.capture.clone(),
+            strict_schema: self.strict_schema,
+            trace: self.trace.clone(),
+            deadline: self.deadline.clone(),
+            rate_recorder: self.rate_recorder.clone(),
+            depth: self.depth,
+            detector_stats: Arc::clone(&self.detector_stats),
+            roaming: self.roaming.clone(),
+            lookup_pool: self.lookup_pool.clone(),
+            mdi_limiter: self.mdi_limiter.clone(),
         }
     }
 }
@@ -637,3 +1997,138 @@ pub struct FederationInfo {
     /// List of all federated domains discovered
     pub domains: Vec<String>,
 }
+
+/// Federation info with what is known about the response it came from
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+struct FederationResponse {
+    info: FederationInfo,
+    /// Canonical SHA-256 of the response
+    response_sha256: Option<String>,
+    /// Schema violations found in strict mode
+    schema_warnings: Vec<SchemaWarning>,
+}
+
+/// Aggregate outcome of a batch run
+///
+/// Collected while results stream through the sinks, so the summary stays
+/// small regardless of how many domains were processed.
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::{BatchSummary, DomainResult};
+///
+/// let mut summary = BatchSummary::new();
+/// summary.record(&DomainResult {
+///     domain: "example.com".to_string(),
+///     tenant: Some("example".to_string()),
+///     ..Default::default()
+/// });
+/// summary.finish();
+///
+/// assert_eq!(summary.domains_processed, 1);
+/// assert_eq!(summary.tenants_found, 1);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSummary {
+    /// Wall-clock time the batch started
+    pub started_at: DateTime<Utc>,
+    /// Wall-clock time the batch finished, once it has
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Total number of domains processed
+    pub domains_processed: usize,
+    /// Domains for which a Microsoft tenant was identified
+    pub tenants_found: usize,
+    /// Domains whose tenant has an MDI instance
+    pub mdi_instances: usize,
+    /// Domains whose check ended with an error
+    pub errors: usize,
+    /// Total batch duration in milliseconds
+    pub elapsed_ms: u64,
+    /// Hosts flagged for slow responses during the batch (see [`crate::latency`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slow_hosts: Vec<SlowHost>,
+    /// Set when the batch stopped at its maximum run time (see
+    /// [`MdiChecker::with_max_runtime`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<Truncation>,
+    /// Outcomes and latencies of every detector run by the checker (see
+    /// [`crate::stats`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub detectors: Vec<DetectorSummary>,
+    /// Outcome of the roaming retry, if failed domains were given a last
+    /// chance (see [`MdiChecker::with_roaming`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roaming: Option<RoamingSummary>,
+    /// Measures the duration independently of wall-clock adjustments
+    #[serde(skip)]
+    stopwatch: Stopwatch,
+}
+
+/// Domains a batch left unprocessed when it reached its maximum run time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Truncation {
+    /// Run time after which no new chunk was started, in milliseconds
+    pub max_runtime_ms: u64,
+    /// Number of domains never started
+    pub unprocessed: usize,
+    /// File listing the unprocessed domains, one per line
+    pub checkpoint: PathBuf,
+}
+
+/// Domains a batch checked again through the roaming endpoint and resolver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoamingSummary {
+    /// Failed domains checked again
+    pub retried: usize,
+    /// Domains whose second check succeeded
+    pub recovered: usize,
+}
+
+impl Default for BatchSummary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BatchSummary {
+    /// Creates an empty summary starting now
+    pub fn new() -> Self {
+        let stopwatch = Stopwatch::start();
+        Self {
+            started_at: stopwatch.started_at(),
+            completed_at: None,
+            domains_processed: 0,
+            tenants_found: 0,
+            mdi_instances: 0,
+            errors: 0,
+            elapsed_ms: 0,
+            slow_hosts: Vec::new(),
+            truncated: None,
+            detectors: Vec::new(),
+            roaming: None,
+            stopwatch,
+        }
+    }
+
+    /// Adds a single domain result to the counts
+    pub fn record(&mut self, result: &DomainResult) {
+        self.domains_processed += 1;
+        if result.tenant.is_some() {
+            self.tenants_found += 1;
+        }
+        if result.mdi_instance.is_some() {
+            self.mdi_instances += 1;
+        }
+        if result.error.is_some() {
+            self.errors += 1;
+        }
+    }
+
+    /// Records the completion time and total batch duration
+    pub fn finish(&mut self) {
+        self.elapsed_ms = self.stopwatch.elapsed_ms();
+        self.completed_at = Some(self.stopwatch.now());
+    }
+}
@@ -0,0 +1,391 @@
+//! Diagnostic bundles written when sentri panics or exits with a fatal error
+//!
+//! A bug report saying "it crashed" is rarely actionable. Once installed, the
+//! crash handler writes a bundle directory into the support directory and
+//! prints its path, so the bundle can be attached to the report:
+//!
+//! - `report.json` - What went wrong, where, a backtrace, the sentri version
+//!   and the platform
+//! - `config.json` - The command line and `SENTRI_*` variables
+//! - `state.json` - The last recorded progress of running work (see [`record_state`])
+//! - `recent.log` - The last log lines before the crash
+//!
+//! # Security Considerations
+//!
+//! Bundles are meant to be shared. Values of credential options and their
+//! variables are replaced by `[REDACTED]` before anything is written
+//! (security:output:error_info_control).
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::config::{env_var_name, ENV_PREFIX};
+use crate::secrets::SECRET_OPTIONS;
+
+/// Log lines kept for the bundle
+pub const RECENT_LOG_LINES: usize = 200;
+
+/// Replacement for credential values
+const REDACTED: &str = "[REDACTED]";
+
+/// Context of the installed crash handler
+static CONTEXT: OnceLock<CrashContext> = OnceLock::new();
+
+/// Ring buffer of the most recent log lines
+///
+/// Used as the writer of the tracing subscriber: every line is passed on to
+/// standard output, or standard error (see [`RecentLogs::with_stderr`]), and
+/// kept, without color codes, for crash bundles.
+#[derive(Debug, Clone)]
+pub struct RecentLogs {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+    stderr: bool,
+}
+
+impl RecentLogs {
+    /// Creates a buffer keeping the last `capacity` lines
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            stderr: false,
+        }
+    }
+
+    /// Passes lines on to standard error instead of standard output
+    ///
+    /// Used by `--quiet`, so that stdout only carries results.
+    pub fn with_stderr(mut self, stderr: bool) -> Self {
+        self.stderr = stderr;
+        self
+    }
+
+    /// Appends a line, dropping the oldest one when full
+    pub fn push(&self, line: &str) {
+        let line = strip_ansi(line);
+        let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        if self.capacity > 0 {
+            lines.push_back(line);
+        }
+    }
+
+    /// The buffered lines, oldest first
+    pub fn lines(&self) -> Vec<String> {
+        let lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        lines.iter().cloned().collect()
+    }
+}
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for RecentLogs {
+    type Writer = RecentLogsWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RecentLogsWriter {
+            logs: self.clone(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+/// Writer of a single log event, see [`RecentLogs`]
+pub struct RecentLogsWriter {
+    logs: RecentLogs,
+    buffer: Vec<u8>,
+}
+
+impl Write for RecentLogsWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.logs.stderr {
+            std::io::stderr().write_all(buf)?;
+        } else {
+            std::io::stdout().write_all(buf)?;
+        }
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.logs.stderr {
+            std::io::stderr().flush()
+        } else {
+            std::io::stdout().flush()
+        }
+    }
+}
+
+impl Drop for RecentLogsWriter {
+    fn drop(&mut self) {
+        for line in String::from_utf8_lossy(&self.buffer).lines() {
+            self.logs.push(line);
+        }
+    }
+}
+
+/// Removes terminal color sequences
+fn strip_ansi(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip to the final byte of the escape sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// What went wrong
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+    /// Panic message or fatal error
+    pub reason: String,
+    /// Source location of a panic
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Backtrace at the point of the crash
+    pub backtrace: String,
+    /// When the crash happened
+    pub crashed_at: DateTime<Utc>,
+    /// Version of sentri
+    pub version: String,
+    /// Operating system and architecture
+    pub platform: String,
+}
+
+impl CrashReport {
+    /// Creates a report of `reason`, capturing a backtrace
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            location: None,
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            crashed_at: Utc::now(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        }
+    }
+
+    /// Sets the source location of a panic
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+}
+
+/// Configuration snapshot stored in `config.json`
+#[derive(Debug, Serialize)]
+struct ConfigSnapshot<'a> {
+    args: &'a [String],
+    env: BTreeMap<String, String>,
+}
+
+/// Everything needed to write a bundle
+#[derive(Debug)]
+pub struct CrashContext {
+    support_dir: PathBuf,
+    args: Vec<String>,
+    logs: Option<RecentLogs>,
+    state: Mutex<BTreeMap<&'static str, serde_json::Value>>,
+}
+
+impl CrashContext {
+    /// Creates a context writing bundles into `support_dir`
+    pub fn new(support_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            support_dir: support_dir.into(),
+            args: Vec::new(),
+            logs: None,
+            state: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Records the command line, redacting credential values
+    pub fn with_args(mut self, args: impl IntoIterator<Item = String>) -> Self {
+        self.args = redact_args(args);
+        self
+    }
+
+    /// Includes the lines buffered by `logs` in bundles
+    pub fn with_logs(mut self, logs: RecentLogs) -> Self {
+        self.logs = Some(logs);
+        self
+    }
+
+    /// Directory bundles are written into
+    pub fn support_dir(&self) -> &Path {
+        &self.support_dir
+    }
+
+    /// Replaces the recorded state of the work called `name`
+    pub fn record_state(&self, name: &'static str, state: serde_json::Value) {
+        let mut states = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        states.insert(name, state);
+    }
+
+    /// Writes a bundle for `report`
+    ///
+    /// # Returns
+    /// * `Result<PathBuf>` - The bundle directory
+    pub fn write_bundle(&self, report: &CrashReport) -> Result<PathBuf> {
+        let dir = self.support_dir.join(format!(
+            "crash-{}-{}",
+            report.crashed_at.format("%Y%m%dT%H%M%SZ"),
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        ));
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let config = ConfigSnapshot {
+            args: &self.args,
+            env: sentri_env(),
+        };
+        let state = {
+            let states = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            serde_json::to_vec_pretty(&*states)?
+        };
+        let logs = self
+            .logs
+            .as_ref()
+            .map(|logs| logs.lines().join("\n"))
+            .unwrap_or_default();
+
+        for (name, content) in [
+            ("report.json", serde_json::to_vec_pretty(report)?),
+            ("config.json", serde_json::to_vec_pretty(&config)?),
+            ("state.json", state),
+            ("recent.log", logs.into_bytes()),
+        ] {
+            let path = dir.join(name);
+            std::fs::write(&path, content)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(dir)
+    }
+}
+
+/// Replaces the values of credential options in a command line
+///
+/// # Examples
+///
+/// ```
+/// use sentri::crash::redact_args;
+///
+/// let args = ["sentri", "serve", "--storage-key", "c2VjcmV0", "--es-password=hunter2"];
+/// assert_eq!(
+///     redact_args(args.map(String::from)),
+///     ["sentri", "serve", "--storage-key", "[REDACTED]", "--es-password=[REDACTED]"]
+/// );
+/// ```
+pub fn redact_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut redact_next = false;
+    args.into_iter()
+        .map(|arg| {
+            if std::mem::take(&mut redact_next) {
+                return REDACTED.to_string();
+            }
+            let Some(option) = arg.strip_prefix("--") else {
+                return arg;
+            };
+            match option.split_once('=') {
+                Some((name, _)) if SECRET_OPTIONS.contains(&name) => {
+                    format!("--{}={}", name, REDACTED)
+                }
+                None if SECRET_OPTIONS.contains(&option) => {
+                    redact_next = true;
+                    arg
+                }
+                _ => arg,
+            }
+        })
+        .collect()
+}
+
+/// `SENTRI_*` variables of the process, with credential values redacted
+fn sentri_env() -> BTreeMap<String, String> {
+    let secrets: Vec<String> = SECRET_OPTIONS
+        .iter()
+        .map(|option| env_var_name(option))
+        .collect();
+    std::env::vars()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .map(|(name, value)| {
+            let value = if secrets.contains(&name) {
+                REDACTED.to_string()
+            } else {
+                value
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+/// Installs `context` and a panic hook writing a bundle on every panic
+///
+/// The previous hook still runs afterwards. Only the first call has an effect.
+pub fn install(context: CrashContext) {
+    if CONTEXT.set(context).is_err() {
+        return;
+    }
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let reason = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => info
+                .payload()
+                .downcast_ref::<String>()
+                .cloned()
+                .unwrap_or_else(|| "panic".to_string()),
+        };
+        let mut report = CrashReport::new(reason);
+        if let Some(location) = info.location() {
+            report = report.with_location(location.to_string());
+        }
+        report_crash(&report);
+        previous(info);
+    }));
+}
+
+/// Writes a bundle for a fatal error through the installed context
+pub fn report_fatal_error(error: &anyhow::Error) {
+    report_crash(&CrashReport::new(format!("{:#}", error)));
+}
+
+/// Records the state of running work for bundles of the installed context
+pub fn record_state(name: &'static str, state: &impl Serialize) {
+    if let Some(context) = CONTEXT.get() {
+        if let Ok(state) = serde_json::to_value(state) {
+            context.record_state(name, state);
+        }
+    }
+}
+
+/// Writes a bundle and tells the user where to find it
+fn report_crash(report: &CrashReport) {
+    let Some(context) = CONTEXT.get() else {
+        return;
+    };
+    match context.write_bundle(report) {
+        Ok(dir) => eprintln!(
+            "sentri encountered a fatal problem. A diagnostic bundle was written to {}; \
+             please attach it to your bug report.",
+            dir.display()
+        ),
+        Err(e) => eprintln!("Failed to write diagnostic bundle: {:#}", e),
+    }
+}
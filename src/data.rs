@@ -0,0 +1,602 @@
+//! Reference data used by enrichment features
+//!
+//! Enrichment relies on three external data sets that change over time:
+//!
+//! - Microsoft service tags, for attributing IP addresses (see [`crate::attribution`])
+//! - The public suffix list, for finding the registrable part of a domain
+//! - Unicode confusables, for spotting look-alike domains
+//!
+//! `sentri update-data` downloads each set into a local data directory,
+//! verifies that it parses before replacing the previous copy and records
+//! its source and SHA-256 digest in a manifest. A compact copy of every set
+//! is bundled with the binary, so enrichment keeps working offline or before
+//! the first update.
+//!
+//! # Security Considerations
+//!
+//! - **Verification**: Downloads are parsed before they are installed, so a truncated
+//!   or hijacked response never replaces good data (security:input:sanitize_all_input)
+//! - **Atomic Replacement**: Files are written to a temporary name and renamed into
+//!   place, so readers never observe a partial file
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use crate::attribution::{IpRanges, SERVICE_TAGS_FILE};
+use crate::http::HttpClient;
+
+/// File name of the public suffix list inside the data directory
+pub const PUBLIC_SUFFIX_FILE: &str = "public_suffix_list.dat";
+
+/// File name of the Unicode confusables inside the data directory
+pub const CONFUSABLES_FILE: &str = "confusables.txt";
+
+/// File name of the manifest describing downloaded data
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Download page linking the current `ServiceTags_Public_*.json`
+///
+/// Microsoft publishes a new file weekly under a dated name, so the
+/// download URL has to be discovered from this page.
+pub const SERVICE_TAGS_PAGE_URL: &str =
+    "https://www.microsoft.com/en-us/download/details.aspx?id=56519";
+
+/// Canonical location of the public suffix list
+pub const PUBLIC_SUFFIX_URL: &str = "https://publicsuffix.org/list/public_suffix_list.dat";
+
+/// Canonical location of the Unicode confusables
+pub const CONFUSABLES_URL: &str = "https://www.unicode.org/Public/security/latest/confusables.txt";
+
+/// Bundled public suffix rules covering the most common registries
+const BUNDLED_PUBLIC_SUFFIXES: &str = "\
+// ===BEGIN ICANN DOMAINS===
+com
+net
+org
+edu
+gov
+mil
+int
+info
+biz
+io
+co
+us
+ca
+de
+fr
+nl
+eu
+ch
+se
+no
+dk
+fi
+es
+it
+be
+at
+pl
+in
+br
+com.br
+cn
+com.cn
+jp
+co.jp
+ne.jp
+or.jp
+kr
+co.kr
+au
+com.au
+net.au
+org.au
+edu.au
+gov.au
+nz
+co.nz
+uk
+co.uk
+org.uk
+ac.uk
+gov.uk
+ltd.uk
+plc.uk
+za
+co.za
+// ===END ICANN DOMAINS===
+";
+
+/// Bundled confusables for the Cyrillic and Greek letters most often used in look-alike domains
+const BUNDLED_CONFUSABLES: &str = "\
+0430 ;\t0061 ;\tMA\t# CYRILLIC SMALL LETTER A -> LATIN SMALL LETTER A
+0441 ;\t0063 ;\tMA\t# CYRILLIC SMALL LETTER ES -> LATIN SMALL LETTER C
+0501 ;\t0064 ;\tMA\t# CYRILLIC SMALL LETTER KOMI DE -> LATIN SMALL LETTER D
+0435 ;\t0065 ;\tMA\t# CYRILLIC SMALL LETTER IE -> LATIN SMALL LETTER E
+04BB ;\t0068 ;\tMA\t# CYRILLIC SMALL LETTER SHHA -> LATIN SMALL LETTER H
+0456 ;\t0069 ;\tMA\t# CYRILLIC SMALL LETTER BYELORUSSIAN-UKRAINIAN I -> LATIN SMALL LETTER I
+0458 ;\t006A ;\tMA\t# CYRILLIC SMALL LETTER JE -> LATIN SMALL LETTER J
+043E ;\t006F ;\tMA\t# CYRILLIC SMALL LETTER O -> LATIN SMALL LETTER O
+0440 ;\t0070 ;\tMA\t# CYRILLIC SMALL LETTER ER -> LATIN SMALL LETTER P
+051B ;\t0071 ;\tMA\t# CYRILLIC SMALL LETTER QA -> LATIN SMALL LETTER Q
+0455 ;\t0073 ;\tMA\t# CYRILLIC SMALL LETTER DZE -> LATIN SMALL LETTER S
+051D ;\t0077 ;\tMA\t# CYRILLIC SMALL LETTER WE -> LATIN SMALL LETTER W
+0445 ;\t0078 ;\tMA\t# CYRILLIC SMALL LETTER HA -> LATIN SMALL LETTER X
+0443 ;\t0079 ;\tMA\t# CYRILLIC SMALL LETTER U -> LATIN SMALL LETTER Y
+03B1 ;\t0061 ;\tMA\t# GREEK SMALL LETTER ALPHA -> LATIN SMALL LETTER A
+03B9 ;\t0069 ;\tMA\t# GREEK SMALL LETTER IOTA -> LATIN SMALL LETTER I
+03BF ;\t006F ;\tMA\t# GREEK SMALL LETTER OMICRON -> LATIN SMALL LETTER O
+03C1 ;\t0070 ;\tMA\t# GREEK SMALL LETTER RHO -> LATIN SMALL LETTER P
+03C5 ;\t0075 ;\tMA\t# GREEK SMALL LETTER UPSILON -> LATIN SMALL LETTER U
+03BD ;\t0076 ;\tMA\t# GREEK SMALL LETTER NU -> LATIN SMALL LETTER V
+";
+
+/// An external data set managed by `sentri update-data`
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum DataSet {
+    /// Microsoft service tags (IP ranges)
+    ServiceTags,
+    /// Public suffix list
+    PublicSuffixList,
+    /// Unicode confusables
+    Confusables,
+}
+
+impl DataSet {
+    /// All managed data sets
+    pub const ALL: [DataSet; 3] = [
+        DataSet::ServiceTags,
+        DataSet::PublicSuffixList,
+        DataSet::Confusables,
+    ];
+
+    /// File name of the data set inside the data directory
+    pub fn file_name(self) -> &'static str {
+        match self {
+            DataSet::ServiceTags => SERVICE_TAGS_FILE,
+            DataSet::PublicSuffixList => PUBLIC_SUFFIX_FILE,
+            DataSet::Confusables => CONFUSABLES_FILE,
+        }
+    }
+
+    /// Checks that downloaded content parses as this data set
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of entries (prefixes, rules or mappings) in the content
+    pub fn verify(self, content: &str) -> Result<usize> {
+        match self {
+            DataSet::ServiceTags => Ok(IpRanges::from_service_tags_json(content)?.len()),
+            DataSet::PublicSuffixList => {
+                if !content.contains("===BEGIN ICANN DOMAINS===") {
+                    anyhow::bail!("Public suffix list is missing the ICANN section");
+                }
+                let list = PublicSuffixList::parse(content);
+                if !list.rules.contains("com") {
+                    anyhow::bail!("Public suffix list does not contain the com rule");
+                }
+                Ok(list.len())
+            }
+            DataSet::Confusables => {
+                let confusables = Confusables::parse(content);
+                if confusables.is_empty() {
+                    anyhow::bail!("Confusables file contains no mappings");
+                }
+                Ok(confusables.len())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for DataSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DataSet::ServiceTags => "service-tags",
+            DataSet::PublicSuffixList => "public-suffix-list",
+            DataSet::Confusables => "confusables",
+        })
+    }
+}
+
+/// Returns the data directory to use
+///
+/// An explicit `--data-dir` wins; otherwise `$XDG_DATA_HOME/sentri` or
+/// `~/.local/share/sentri` is used when the environment allows it.
+pub fn resolve_data_dir(explicit: Option<&Path>) -> Option<PathBuf> {
+    if let Some(dir) = explicit {
+        return Some(dir.to_path_buf());
+    }
+    let base = match std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("sentri"))
+}
+
+/// Reads a data file, returning None when it has not been downloaded
+fn read_data_file(data_dir: Option<&Path>, file_name: &str) -> Result<Option<String>> {
+    let Some(path) = data_dir.map(|dir| dir.join(file_name)) else {
+        return Ok(None);
+    };
+    if !path.exists() {
+        debug!("{} not found, using bundled data", path.display());
+        return Ok(None);
+    }
+    std::fs::read_to_string(&path)
+        .map(Some)
+        .with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Public suffix rules for finding registrable domains
+#[derive(Debug, Clone, Default)]
+pub struct PublicSuffixList {
+    rules: HashSet<String>,
+    wildcards: HashSet<String>,
+    exceptions: HashSet<String>,
+}
+
+impl PublicSuffixList {
+    /// Parses the public suffix list format
+    pub fn parse(content: &str) -> Self {
+        let mut list = Self::default();
+        for line in content.lines() {
+            let Some(rule) = line.split_whitespace().next() else {
+                continue;
+            };
+            if rule.starts_with("//") {
+                continue;
+            }
+            let rule = rule.to_lowercase();
+            if let Some(exception) = rule.strip_prefix('!') {
+                list.exceptions.insert(exception.to_string());
+            } else if let Some(parent) = rule.strip_prefix("*.") {
+                list.wildcards.insert(parent.to_string());
+            } else {
+                list.rules.insert(rule);
+            }
+        }
+        list
+    }
+
+    /// Returns the bundled rules
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED_PUBLIC_SUFFIXES)
+    }
+
+    /// Loads the list from the data directory, falling back to the bundled rules
+    pub fn load(data_dir: Option<&Path>) -> Result<Self> {
+        Ok(match read_data_file(data_dir, PUBLIC_SUFFIX_FILE)? {
+            Some(content) => Self::parse(&content),
+            None => Self::bundled(),
+        })
+    }
+
+    /// Number of rules
+    pub fn len(&self) -> usize {
+        self.rules.len() + self.wildcards.len() + self.exceptions.len()
+    }
+
+    /// Returns true if no rules are loaded
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the registrable domain (public suffix plus one label)
+    ///
+    /// # Returns
+    /// * `Option<String>` - None if the domain is itself a public suffix
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::data::PublicSuffixList;
+    ///
+    /// let list = PublicSuffixList::bundled();
+    /// assert_eq!(list.registrable_domain("mail.contoso.co.uk").as_deref(), Some("contoso.co.uk"));
+    /// assert_eq!(list.registrable_domain("co.uk"), None);
+    /// ```
+    pub fn registrable_domain(&self, domain: &str) -> Option<String> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let labels: Vec<&str> = domain.split('.').collect();
+        let suffix_len = self.suffix_len(&labels);
+        (labels.len() > suffix_len).then(|| labels[labels.len() - suffix_len - 1..].join("."))
+    }
+
+    /// Number of labels forming the public suffix, defaulting to the top-level label
+    fn suffix_len(&self, labels: &[&str]) -> usize {
+        for i in 0..labels.len() {
+            let candidate = labels[i..].join(".");
+            if self.exceptions.contains(&candidate) {
+                return labels.len() - i - 1;
+            }
+            if self.rules.contains(&candidate) {
+                return labels.len() - i;
+            }
+            if i + 1 < labels.len() && self.wildcards.contains(&labels[i + 1..].join(".")) {
+                return labels.len() - i;
+            }
+        }
+        1
+    }
+}
+
+/// Unicode confusable mappings for comparing look-alike strings
+#[derive(Debug, Clone, Default)]
+pub struct Confusables {
+    mappings: HashMap<char, String>,
+}
+
+impl Confusables {
+    /// Parses the Unicode `confusables.txt` format
+    ///
+    /// Lines that do not map a single code point are skipped.
+    pub fn parse(content: &str) -> Self {
+        let parse_char = |hex: &str| u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+
+        let mappings = content
+            .lines()
+            .filter_map(|line| {
+                let line = line.split('#').next()?.trim_start_matches('\u{feff}');
+                let mut fields = line.split(';');
+                let source = parse_char(fields.next()?.trim())?;
+                let target = fields
+                    .next()?
+                    .split_whitespace()
+                    .map(parse_char)
+                    .collect::<Option<String>>()?;
+                Some((source, target))
+            })
+            .collect();
+        Self { mappings }
+    }
+
+    /// Returns the bundled mappings
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED_CONFUSABLES)
+    }
+
+    /// Loads the mappings from the data directory, falling back to the bundled ones
+    pub fn load(data_dir: Option<&Path>) -> Result<Self> {
+        Ok(match read_data_file(data_dir, CONFUSABLES_FILE)? {
+            Some(content) => Self::parse(&content),
+            None => Self::bundled(),
+        })
+    }
+
+    /// Number of mappings
+    pub fn len(&self) -> usize {
+        self.mappings.len()
+    }
+
+    /// Returns true if no mappings are loaded
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Maps every confusable character to its prototype
+    ///
+    /// Two strings with the same skeleton look alike.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::data::Confusables;
+    ///
+    /// let confusables = Confusables::bundled();
+    /// assert_eq!(confusables.skeleton("\u{0441}ontoso.com"), "contoso.com");
+    /// ```
+    pub fn skeleton(&self, value: &str) -> String {
+        value
+            .chars()
+            .map(|c| match self.mappings.get(&c) {
+                Some(target) => target.clone(),
+                None => c.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Provenance of a downloaded data file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// URL the file was downloaded from
+    pub source_url: String,
+    /// Hex-encoded SHA-256 digest of the file
+    pub sha256: String,
+    /// File size in bytes
+    pub bytes: usize,
+    /// Number of entries found during verification
+    pub entries: usize,
+    /// When the file was installed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Record of the data files installed in a data directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Entries keyed by data set
+    pub files: BTreeMap<DataSet, ManifestEntry>,
+}
+
+impl Manifest {
+    /// Loads the manifest of a data directory, returning an empty one if absent
+    pub async fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid manifest {}", path.display()))
+    }
+
+    /// Writes the manifest into a data directory
+    pub async fn save(&self, data_dir: &Path) -> Result<()> {
+        let content = format!("{}\n", serde_json::to_string_pretty(self)?);
+        write_atomic(&data_dir.join(MANIFEST_FILE), content.as_bytes()).await
+    }
+}
+
+/// Outcome of updating one data set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum UpdateOutcome {
+    /// The data set was downloaded, verified and installed
+    Updated {
+        /// Data set
+        dataset: DataSet,
+        /// Provenance of the installed file
+        #[serde(flatten)]
+        entry: ManifestEntry,
+    },
+    /// The update failed; enrichment keeps using the previous or bundled copy
+    Failed {
+        /// Data set
+        dataset: DataSet,
+        /// Reason for the failure
+        error: String,
+        /// Data used instead ("previous download" or "bundled")
+        fallback: String,
+    },
+}
+
+impl UpdateOutcome {
+    /// Returns true if the update failed
+    pub fn is_failed(&self) -> bool {
+        matches!(self, UpdateOutcome::Failed { .. })
+    }
+}
+
+/// Extracts the current service tag download URL from Microsoft's download page
+pub fn service_tags_download_url(page: &str) -> Option<String> {
+    let pattern = Regex::new(
+        r#"https://download\.microsoft\.com/download/[^"'\s<>]+/ServiceTags_Public_\d+\.json"#,
+    )
+    .expect("valid service tag URL pattern");
+    pattern.find(page).map(|m| m.as_str().to_string())
+}
+
+/// Writes a file through a temporary sibling and renames it into place
+async fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, content)
+        .await
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("Failed to install {}", path.display()))
+}
+
+/// Downloads and verifies data sets into a data directory
+///
+/// Each data set is handled independently: a failed download or verification
+/// leaves the previously installed file untouched and is reported as
+/// [`UpdateOutcome::Failed`], so one unreachable source never blocks the others.
+///
+/// # Arguments
+/// * `client` - HTTP client used for downloads
+/// * `data_dir` - Directory to install the files into (created if missing)
+/// * `datasets` - Data sets to update
+/// * `service_tags_url` - Direct service tag JSON URL, skipping discovery via the download page
+///
+/// # Returns
+/// * `Result<Vec<UpdateOutcome>>` - One outcome per data set; errors only if the
+///   data directory or manifest cannot be written
+pub async fn update_data(
+    client: &HttpClient,
+    data_dir: &Path,
+    datasets: &[DataSet],
+    service_tags_url: Option<&str>,
+) -> Result<Vec<UpdateOutcome>> {
+    tokio::fs::create_dir_all(data_dir)
+        .await
+        .with_context(|| format!("Failed to create data directory {}", data_dir.display()))?;
+
+    let mut manifest = Manifest::load(data_dir).await?;
+    let mut outcomes = Vec::with_capacity(datasets.len());
+
+    for &dataset in datasets {
+        match update_dataset(client, data_dir, dataset, service_tags_url).await {
+            Ok(entry) => {
+                info!(
+                    "Installed {} ({} entries, sha256 {})",
+                    dataset, entry.entries, entry.sha256
+                );
+                manifest.files.insert(dataset, entry.clone());
+                outcomes.push(UpdateOutcome::Updated { dataset, entry });
+            }
+            Err(e) => {
+                let fallback = if data_dir.join(dataset.file_name()).exists() {
+                    "previous download"
+                } else {
+                    "bundled"
+                };
+                warn!(
+                    "Failed to update {}: {:#}; using {} data",
+                    dataset, e, fallback
+                );
+                outcomes.push(UpdateOutcome::Failed {
+                    dataset,
+                    error: format!("{:#}", e),
+                    fallback: fallback.to_string(),
+                });
+            }
+        }
+    }
+
+    manifest.save(data_dir).await?;
+    Ok(outcomes)
+}
+
+/// Downloads, verifies and installs a single data set
+async fn update_dataset(
+    client: &HttpClient,
+    data_dir: &Path,
+    dataset: DataSet,
+    service_tags_url: Option<&str>,
+) -> Result<ManifestEntry> {
+    let url = match (dataset, service_tags_url) {
+        (DataSet::ServiceTags, Some(url)) => url.to_string(),
+        (DataSet::ServiceTags, None) => {
+            let page = client
+                .get(SERVICE_TAGS_PAGE_URL)
+                .await
+                .context("Failed to fetch service tag download page")?;
+            service_tags_download_url(&page)
+                .context("Service tag download link not found on download page")?
+        }
+        (DataSet::PublicSuffixList, _) => PUBLIC_SUFFIX_URL.to_string(),
+        (DataSet::Confusables, _) => CONFUSABLES_URL.to_string(),
+    };
+
+    debug!("Downloading {} from {}", dataset, url);
+    let content = client
+        .get(&url)
+        .await
+        .with_context(|| format!("Failed to download {}", url))?;
+    let entries = dataset
+        .verify(&content)
+        .with_context(|| format!("Verification of {} failed", url))?;
+
+    write_atomic(&data_dir.join(dataset.file_name()), content.as_bytes()).await?;
+
+    Ok(ManifestEntry {
+        source_url: url,
+        sha256: format!("{:x}", Sha256::digest(content.as_bytes())),
+        bytes: content.len(),
+        entries,
+        updated_at: Utc::now(),
+    })
+}
@@ -0,0 +1,99 @@
+//! Check depth levels selecting the detectors run for every domain
+//!
+//! `--depth` trades thoroughness for speed and footprint:
+//!
+//! - `fast`: federation lookup and tenant extraction
+//! - `standard`: adds the MDI sensor DNS lookups; the default
+//! - `deep`: adds attribution of every federated domain's MX and
+//!   autodiscover hosts to Microsoft ranges, as with `--attribute-ips`
+//!
+//! Every level sends exactly one Autodiscover request per domain, so the
+//! Microsoft API budget of `--rate-limit` applies unchanged. The DNS lookups
+//! a domain costs grow with the level, so each level has its own DNS query
+//! budget ([`Depth::dns_budget`]): `fast` needs none, while `deep` resolves
+//! several hosts per federated domain and gets a larger one.
+//!
+//! # Security Considerations
+//!
+//! - **Intrusive Detectors**: `deep` queries the target's own infrastructure
+//!   and honors `--require-ownership` like `--attribute-ips` does
+
+use clap::ValueEnum;
+use std::sync::Arc;
+
+use crate::rate_limit::RateLimiter;
+
+/// How thoroughly every domain is checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, ValueEnum)]
+pub enum Depth {
+    /// Federation and tenant only
+    Fast,
+    /// Federation, tenant and MDI sensor DNS lookups
+    #[default]
+    Standard,
+    /// Standard plus endpoint attribution of federated domains
+    Deep,
+}
+
+/// DNS query budget of a depth level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DnsBudget {
+    /// DNS queries per minute
+    pub queries_per_minute: usize,
+    /// DNS queries in flight at once
+    pub max_concurrent: usize,
+}
+
+impl DnsBudget {
+    /// Rate limiter enforcing the budget
+    pub fn limiter(&self) -> Arc<RateLimiter> {
+        Arc::new(RateLimiter::new(
+            self.queries_per_minute,
+            60_000,
+            self.max_concurrent,
+        ))
+    }
+}
+
+impl Depth {
+    /// Returns true if MDI sensor endpoints are resolved
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::depth::Depth;
+    ///
+    /// assert!(!Depth::Fast.checks_mdi());
+    /// assert!(Depth::Standard.checks_mdi());
+    /// ```
+    pub fn checks_mdi(self) -> bool {
+        self >= Depth::Standard
+    }
+
+    /// Returns true if federated endpoints are attributed to Microsoft ranges
+    ///
+    /// Lower levels still attribute endpoints when `--attribute-ips` is given.
+    pub fn attributes_endpoints(self) -> bool {
+        self == Depth::Deep
+    }
+
+    /// DNS query budget of the level
+    ///
+    /// `fast` and `standard` keep the resolver's default budget, which
+    /// `fast` only spends on health probes.
+    pub fn dns_budget(self) -> DnsBudget {
+        match self {
+            Depth::Fast | Depth::Standard => DEFAULT_DNS_BUDGET,
+            Depth::Deep => DnsBudget {
+                queries_per_minute: 300,
+                max_concurrent: 30,
+            },
+        }
+    }
+}
+
+/// Budget of [`crate::rate_limit::create_dns_query_limiter`], the resolver default
+const DEFAULT_DNS_BUDGET: DnsBudget = DnsBudget {
+    queries_per_minute: 100,
+    max_concurrent: 20,
+};
@@ -6,6 +6,14 @@
 //! - Automatic retries with exponential backoff and jitter
 //! - Intelligent error classification for better failure handling
 //! - Thread-safe implementation for concurrent usage
+//! - Load balancing across several upstream resolvers (see [`crate::dns_pool`])
+//! - EDNS Client Subnet control and randomized source ports (see [`crate::dns_privacy`])
+//! - Static overrides consulted before real resolution (see [`crate::dns_override`])
+//! - Immediate failure of real queries in offline mode (see [`crate::offline`])
+//! - DNSSEC-validated proofs that a name does not exist, with the `dnssec`
+//!   feature (see [`DnsResolver::prove_nonexistent`])
+//! - Queries pipelined over one TCP connection per upstream (see
+//!   [`crate::dns_pipeline`])
 //!
 //! # Security Considerations
 //!
@@ -33,15 +41,46 @@
 //! - **Concurrency Control**: Uses semaphores to limit concurrent operations
 //!   (concurrency:use_semaphores_for_concurrency_limits)
 
+use crate::dns_override::{DnsOverrides, Override};
+use crate::dns_pool::{Balancer, Strategy, UpstreamStats};
+use crate::dns_privacy::{PrivacyConfig, PrivacyRuntime, SourcePortStats};
+use crate::egress::ensure_allowed;
+use crate::logging::LogSampler;
+use crate::offline::ensure_online;
 use crate::rate_limit::{create_dns_query_limiter, RateLimiter};
 use crate::retry::{with_exponential_backoff, RetryConfig};
+use crate::time::Stopwatch;
+use crate::trace::span;
 use anyhow::{Context, Result};
-use std::net::IpAddr;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, warn};
-use trust_dns_resolver::config::ResolverOpts;
+use trust_dns_resolver::config::{
+    NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts,
+};
 use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
-use trust_dns_resolver::TokioAsyncResolver as AsyncResolver;
+use trust_dns_resolver::name_server::GenericConnector;
+use trust_dns_resolver::proto::rr::RecordType;
+
+/// Default time a resolver waits for an answer before retrying
+pub const DEFAULT_DNS_TIMEOUT_MS: u64 = 5000;
+
+/// Default number of times a query is sent before it fails
+pub const DEFAULT_DNS_ATTEMPTS: usize = 2;
+
+/// Public resolvers of the roaming retry when no other upstream is configured
+///
+/// Cloudflare and Quad9 answer from anycast networks independent of the
+/// scanning host's own DNS (see [`crate::core::MdiChecker::with_roaming`]).
+pub const ROAMING_RESOLVERS: [SocketAddr; 2] = [
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 53),
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(9, 9, 9, 9)), 53),
+];
+
+/// Resolver sending its queries through the privacy controls
+type AsyncResolver = trust_dns_resolver::AsyncResolver<GenericConnector<PrivacyRuntime>>;
 
 /// DNS resolver with caching, rate limiting, and security features
 ///
@@ -63,7 +102,8 @@ use trust_dns_resolver::TokioAsyncResolver as AsyncResolver;
 ///   (mdi:domains:rate_limit_domains)
 ///
 /// - **Timeout Management**: All DNS operations have configurable timeouts to prevent
-///   resource exhaustion from hanging connections. Default timeout is 5 seconds.
+///   resource exhaustion from hanging connections. Default timeout is 5 seconds,
+///   see [`DnsResolver::with_query_timeout`].
 ///   (security:network:timeout_all_requests)
 ///
 /// - **Retry Strategy**: Uses exponential backoff with jitter to handle transient
@@ -121,9 +161,151 @@ use trust_dns_resolver::TokioAsyncResolver as AsyncResolver;
 /// # }
 /// ```
 pub struct DnsResolver {
-    resolver: AsyncResolver,
+    upstreams: Vec<Upstream>,
+    balancer: Balancer,
     retry_config: RetryConfig,
     rate_limiter: Arc<RateLimiter>,
+    log_sampler: Arc<LogSampler>,
+    runtime: PrivacyRuntime,
+    overrides: Option<Arc<DnsOverrides>>,
+    query_timeout: Duration,
+    attempts: usize,
+    dnssec: bool,
+    pipelined: bool,
+}
+
+/// An upstream resolver queries can be sent to
+struct Upstream {
+    name: String,
+    config: ResolverConfig,
+    resolver: AsyncResolver,
+    /// Resolver validating DNSSEC, for proofs of nonexistence
+    validator: Option<AsyncResolver>,
+}
+
+impl Upstream {
+    fn new(
+        name: String,
+        config: ResolverConfig,
+        runtime: &PrivacyRuntime,
+        timeout: Duration,
+        attempts: usize,
+        dnssec: bool,
+        pipelined: bool,
+    ) -> Self {
+        let opts = resolver_opts(runtime.config(), timeout, attempts);
+        let resolver = match pipelined {
+            true => AsyncResolver::new(
+                tcp_only(&config),
+                opts,
+                GenericConnector::new(runtime.clone()),
+            ),
+            false => {
+                AsyncResolver::new(config.clone(), opts, GenericConnector::new(runtime.clone()))
+            }
+        };
+        // Validation fails on unsigned zones, so it is kept off the main resolver
+        let validator = dnssec.then(|| {
+            let mut opts = opts;
+            opts.validate = true;
+            opts.edns0 = true;
+            AsyncResolver::new(config.clone(), opts, GenericConnector::new(runtime.clone()))
+        });
+        Self {
+            name,
+            config,
+            resolver,
+            validator,
+        }
+    }
+}
+
+/// Name servers of the system configuration (`/etc/resolv.conf`, or the
+/// registry on Windows), read when a [`DnsResolver`] is created
+pub fn system_config() -> ResolverConfig {
+    config_or_default(trust_dns_resolver::system_conf::read_system_conf())
+}
+
+/// Returns the configuration read from the system, or the default
+/// configuration when it could not be read
+///
+/// Only the name servers and search domains are taken from the system; its
+/// options are replaced by sentri's own (see [`DnsResolver::with_query_timeout`]
+/// and [`DnsResolver::with_attempts`]).
+///
+/// # Examples
+///
+/// ```
+/// use sentri::dns::config_or_default;
+/// use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
+///
+/// let cloudflare = ResolverConfig::cloudflare();
+/// let read = Ok::<_, std::io::Error>((cloudflare.clone(), ResolverOpts::default()));
+/// assert_eq!(config_or_default(read), cloudflare);
+/// let unreadable = Err(std::io::Error::other("no resolv.conf"));
+/// assert_eq!(config_or_default(unreadable), ResolverConfig::default());
+/// ```
+pub fn config_or_default<E: std::fmt::Display>(
+    system: Result<(ResolverConfig, ResolverOpts), E>,
+) -> ResolverConfig {
+    match system {
+        Ok((config, _)) => config,
+        Err(e) => {
+            warn!(
+                "Failed to read the system DNS configuration, using the default resolvers: {}",
+                e
+            );
+            ResolverConfig::default()
+        }
+    }
+}
+
+/// Resolver options shared by every upstream
+fn resolver_opts(privacy: PrivacyConfig, timeout: Duration, attempts: usize) -> ResolverOpts {
+    // Use system configuration with performance optimizations
+    let mut opts = ResolverOpts::default();
+    opts.cache_size = 1024;
+    opts.positive_min_ttl = Some(std::time::Duration::from_secs(300));
+    opts.negative_min_ttl = Some(std::time::Duration::from_secs(60));
+    opts.timeout = timeout;
+    opts.attempts = attempts;
+    // The client subnet option travels in the EDNS record
+    opts.edns0 = privacy.ecs.is_some();
+    opts
+}
+
+/// The name servers of `config` reached over TCP
+///
+/// Each name server keeps its connection open and multiplexes the queries
+/// sent to it by query ID.
+fn tcp_only(config: &ResolverConfig) -> ResolverConfig {
+    let servers: Vec<NameServerConfig> = config
+        .name_servers()
+        .iter()
+        .filter(|server| server.protocol == Protocol::Tcp)
+        .cloned()
+        .collect();
+    ResolverConfig::from_parts(
+        config.domain().cloned(),
+        config.search().to_vec(),
+        NameServerConfigGroup::from(servers),
+    )
+}
+
+/// Returns true if an error means the upstream did not answer properly
+///
+/// A missing record is an answer, so it does not count against the upstream.
+fn is_upstream_failure(err: &ResolveError) -> bool {
+    !matches!(err.kind(), ResolveErrorKind::NoRecordsFound { .. })
+}
+
+/// Returns true if a lookup failed only because the name has no such record
+pub fn is_missing_record(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<ResolveError>()
+            .is_some_and(|e| !is_upstream_failure(e))
+    })
 }
 
 impl DnsResolver {
@@ -132,7 +314,8 @@ impl DnsResolver {
     /// Initializes a resolver with security-focused configuration:
     /// - System DNS configuration with secure defaults
     /// - 1024-entry cache with optimized TTLs (300s for positive, 60s for negative responses)
-    /// - 5-second timeout with 2 retry attempts to prevent hanging
+    /// - 5-second timeout with 2 attempts per query to prevent hanging, see
+    ///   [`DnsResolver::with_query_timeout`] and [`DnsResolver::with_attempts`]
     /// - Exponential backoff with jitter for robust retry behavior
     /// - Token bucket rate limiting to prevent DNS server overload and abuse
     ///
@@ -140,8 +323,8 @@ impl DnsResolver {
     ///
     /// This implementation adheres to several security best practices:
     ///
-    /// - **Configurable Timeouts**: All DNS operations have a 5-second timeout to prevent
-    ///   resource exhaustion (security:network:timeout_all_requests)
+    /// - **Configurable Timeouts**: All DNS operations have a timeout, 5 seconds by default,
+    ///   to prevent resource exhaustion (security:network:timeout_all_requests)
     /// - **Rate Limiting**: Uses token bucket algorithm to prevent abuse of DNS services
     ///   (mdi:domains:rate_limit_domains)
     /// - **Error Handling**: Properly propagates errors with context
@@ -187,18 +370,8 @@ impl DnsResolver {
     /// # }
     /// ```
     pub fn new() -> Result<Self> {
-        // Use system configuration with performance optimizations
-        let mut opts = ResolverOpts::default();
-        opts.cache_size = 1024;
-        opts.positive_min_ttl = Some(std::time::Duration::from_secs(300));
-        opts.negative_min_ttl = Some(std::time::Duration::from_secs(60));
-        opts.timeout = std::time::Duration::from_secs(5);
-        opts.attempts = 2;
-
-        let resolver = match AsyncResolver::tokio_from_system_conf() {
-            Ok(r) => r,
-            Err(e) => return Err(anyhow::anyhow!("Failed to create DNS resolver: {}", e)),
-        };
+        let config = system_config();
+        let runtime = PrivacyRuntime::default();
 
         // Default retry configuration for DNS resolution
         let retry_config = RetryConfig {
@@ -212,13 +385,268 @@ impl DnsResolver {
         // Create rate limiter for DNS queries
         let rate_limiter = Arc::new(create_dns_query_limiter());
 
+        let query_timeout = Duration::from_millis(DEFAULT_DNS_TIMEOUT_MS);
         Ok(Self {
-            resolver,
+            upstreams: vec![Upstream::new(
+                "system".to_string(),
+                config,
+                &runtime,
+                query_timeout,
+                DEFAULT_DNS_ATTEMPTS,
+                false,
+                false,
+            )],
+            balancer: Balancer::new(Strategy::default(), 1),
             retry_config,
             rate_limiter,
+            log_sampler: Arc::new(LogSampler::default()),
+            runtime,
+            overrides: None,
+            query_timeout,
+            attempts: DEFAULT_DNS_ATTEMPTS,
+            dnssec: false,
+            pipelined: false,
         })
     }
 
+    /// Replaces the system resolvers with the given upstreams
+    ///
+    /// Queries are spread across the upstreams according to `strategy`, see
+    /// [`crate::dns_pool`]. An empty list keeps the system configuration.
+    ///
+    /// # Arguments
+    /// * `upstreams` - Addresses of the upstream resolvers, queried over plain DNS
+    /// * `strategy` - How queries are distributed
+    ///
+    /// # Examples
+    /// ```
+    /// # use sentri::dns::DnsResolver;
+    /// # use sentri::dns_pool::Strategy;
+    /// # async {
+    /// let upstreams = ["1.1.1.1:53".parse()?, "9.9.9.9:53".parse()?];
+    /// let resolver = DnsResolver::new()?.with_upstreams(&upstreams, Strategy::Fastest);
+    /// assert_eq!(resolver.upstream_stats().len(), 2);
+    /// # Ok::<(), anyhow::Error>(())
+    /// # };
+    /// ```
+    pub fn with_upstreams(mut self, upstreams: &[SocketAddr], strategy: Strategy) -> Self {
+        if upstreams.is_empty() {
+            return self;
+        }
+        self.upstreams = upstreams
+            .iter()
+            .map(|address| {
+                let servers =
+                    NameServerConfigGroup::from_ips_clear(&[address.ip()], address.port(), true);
+                let config = ResolverConfig::from_parts(None, Vec::new(), servers);
+                Upstream::new(
+                    address.to_string(),
+                    config,
+                    &self.runtime,
+                    self.query_timeout,
+                    self.attempts,
+                    self.dnssec,
+                    self.pipelined,
+                )
+            })
+            .collect();
+        self.balancer = Balancer::new(strategy, upstreams.len());
+        self
+    }
+
+    /// Applies EDNS Client Subnet and source address settings to every query
+    ///
+    /// See [`crate::dns_privacy`]. Source ports are randomized regardless of
+    /// the settings.
+    ///
+    /// # Examples
+    /// ```
+    /// # use sentri::dns::DnsResolver;
+    /// # use sentri::dns_privacy::{Ecs, PrivacyConfig};
+    /// # async {
+    /// let privacy = PrivacyConfig { ecs: Some(Ecs::Off), bind_address: None };
+    /// let resolver = DnsResolver::new()?.with_privacy(privacy);
+    /// assert_eq!(resolver.source_ports().sockets, 0);
+    /// # Ok::<(), anyhow::Error>(())
+    /// # };
+    /// ```
+    pub fn with_privacy(mut self, privacy: PrivacyConfig) -> Self {
+        self.runtime = PrivacyRuntime::new(privacy);
+        self.rebuild_upstreams();
+        self
+    }
+
+    /// Sets how long a resolver is waited for before a query is sent again
+    ///
+    /// Slow corporate resolvers routinely need more than the default of
+    /// [`DEFAULT_DNS_TIMEOUT_MS`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use sentri::dns::DnsResolver;
+    /// # use std::time::Duration;
+    /// # async {
+    /// let resolver = DnsResolver::new()?.with_query_timeout(Duration::from_secs(15));
+    /// assert_eq!(resolver.query_timeout(), Duration::from_secs(15));
+    /// # Ok::<(), anyhow::Error>(())
+    /// # };
+    /// ```
+    pub fn with_query_timeout(mut self, timeout: Duration) -> Self {
+        self.query_timeout = timeout;
+        self.rebuild_upstreams();
+        self
+    }
+
+    /// Sets how many times a query is sent before it fails, at least once
+    ///
+    /// Attempts happen within a single lookup; failed lookups are retried
+    /// with backoff on top of them (see [`DnsResolver::with_retry_config`]).
+    pub fn with_attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts.max(1);
+        self.rebuild_upstreams();
+        self
+    }
+
+    /// Time a resolver is waited for before a query is sent again
+    pub fn query_timeout(&self) -> Duration {
+        self.query_timeout
+    }
+
+    /// Number of times a query is sent before it fails
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Recreates the upstream resolvers after their options changed
+    fn rebuild_upstreams(&mut self) {
+        self.upstreams = std::mem::take(&mut self.upstreams)
+            .into_iter()
+            .map(|upstream| {
+                Upstream::new(
+                    upstream.name,
+                    upstream.config,
+                    &self.runtime,
+                    self.query_timeout,
+                    self.attempts,
+                    self.dnssec,
+                    self.pipelined,
+                )
+            })
+            .collect();
+    }
+
+    /// Sends queries over TCP, pipelining them on one connection per upstream
+    ///
+    /// Meant for the workers of a [`crate::dns_pipeline::LookupPool`], which
+    /// send many queries at once. Client subnet options and a pinned source
+    /// address (see [`DnsResolver::with_privacy`]) only apply to UDP queries.
+    pub fn with_pipelining(mut self) -> Self {
+        self.pipelined = true;
+        self.rebuild_upstreams();
+        self
+    }
+
+    /// Returns true if queries are pipelined over TCP
+    pub fn is_pipelined(&self) -> bool {
+        self.pipelined
+    }
+
+    /// Enables proofs of nonexistence, see [`DnsResolver::prove_nonexistent`]
+    ///
+    /// Every upstream gets a second, DNSSEC-validating resolver that is only
+    /// used for these proofs, so unsigned zones keep resolving as before.
+    #[cfg(feature = "dnssec")]
+    pub fn with_dnssec_negatives(mut self) -> Result<Self> {
+        self.dnssec = true;
+        self.rebuild_upstreams();
+        Ok(self)
+    }
+
+    /// Enables proofs of nonexistence
+    ///
+    /// # Errors
+    /// This build does not include the `dnssec` feature, so this always fails.
+    #[cfg(not(feature = "dnssec"))]
+    pub fn with_dnssec_negatives(self) -> Result<Self> {
+        Err(anyhow::anyhow!(
+            "Cannot validate DNSSEC: sentri was built without the dnssec feature"
+        ))
+    }
+
+    /// Returns true if proofs of nonexistence are enabled
+    pub fn validates_negatives(&self) -> bool {
+        self.dnssec
+    }
+
+    /// Answers the names listed in `overrides` without querying any resolver
+    ///
+    /// See [`crate::dns_override`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use sentri::dns::DnsResolver;
+    /// # use sentri::dns_override::DnsOverrides;
+    /// # use std::sync::Arc;
+    /// # async {
+    /// let overrides = DnsOverrides::parse("192.0.2.10 lab.example.com")?;
+    /// let resolver = DnsResolver::new()?.with_overrides(Arc::new(overrides));
+    /// assert_eq!(resolver.resolve("lab.example.com").await?, ["192.0.2.10".parse::<std::net::IpAddr>()?]);
+    /// # Ok::<(), anyhow::Error>(())
+    /// # };
+    /// ```
+    pub fn with_overrides(mut self, overrides: Arc<DnsOverrides>) -> Self {
+        self.overrides = Some(overrides);
+        self
+    }
+
+    /// The override of `domain`, if any
+    fn overridden(&self, domain: &str) -> Option<&Override> {
+        self.overrides.as_ref()?.lookup(domain)
+    }
+
+    /// Source ports used by the queries sent so far
+    pub fn source_ports(&self) -> SourcePortStats {
+        self.runtime.source_ports()
+    }
+
+    /// Query counts, failures, latency and health of every upstream
+    pub fn upstream_stats(&self) -> Vec<UpstreamStats> {
+        self.balancer
+            .stats()
+            .into_iter()
+            .zip(&self.upstreams)
+            .map(|(stats, upstream)| UpstreamStats {
+                upstream: upstream.name.clone(),
+                ..stats
+            })
+            .collect()
+    }
+
+    /// Sends a query to the upstream chosen by the balancer, tracking its health
+    async fn query<T, F, Fut>(&self, lookup: F) -> Result<T, ResolveError>
+    where
+        F: FnOnce(AsyncResolver) -> Fut,
+        Fut: Future<Output = Result<T, ResolveError>>,
+    {
+        let index = self.balancer.select(Instant::now());
+        let upstream = &self.upstreams[index];
+        let stopwatch = Stopwatch::start();
+        let result = lookup(upstream.resolver.clone()).await;
+        match &result {
+            Err(e) if is_upstream_failure(e) => {
+                if self.balancer.record_failure(index, Instant::now()) {
+                    warn!(
+                        upstream = %upstream.name,
+                        error = %e,
+                        "DNS resolver taken out of rotation"
+                    );
+                }
+            }
+            _ => self.balancer.record_success(index, stopwatch.elapsed()),
+        }
+        result
+    }
+
     /// Sets a custom rate limiter for the DNS resolver.
     ///
     /// This method allows configuring a custom rate limiter for specialized
@@ -340,22 +768,59 @@ impl DnsResolver {
     /// # }
     /// ```
     pub async fn resolve(&self, domain: &str) -> Result<Vec<IpAddr>> {
-        debug!("Resolving DNS for domain: {}", domain);
+        self.resolve_limited(domain, &self.rate_limiter).await
+    }
+
+    /// Resolves a domain name like [`DnsResolver::resolve`], drawing from `limiter`
+    ///
+    /// Lets a detector spend a budget of its own instead of the resolver's
+    /// rate limiter.
+    ///
+    /// # Arguments
+    /// * `domain` - The domain name to resolve (should be pre-validated)
+    /// * `limiter` - Rate limiter granting the query
+    pub async fn resolve_limited(
+        &self,
+        domain: &str,
+        limiter: &RateLimiter,
+    ) -> Result<Vec<IpAddr>> {
+        let stopwatch = Stopwatch::start();
+        debug!(domain, "Resolving DNS");
+
+        match self.overridden(domain) {
+            Some(Override::Addresses(ips)) => {
+                debug!(domain, addresses = ips.len(), "Resolved DNS from override");
+                return Ok(ips.clone());
+            }
+            Some(Override::NxDomain) => {
+                return Err(anyhow::anyhow!(
+                    "DNS resolution failed for {}: NXDOMAIN (overridden)",
+                    domain
+                ));
+            }
+            None => {}
+        }
+        ensure_online(format_args!("DNS lookup of {}", domain))?;
+        ensure_allowed(domain, format_args!("DNS lookup of {}", domain))?;
+        let mut lookup_span = span("dns_lookup");
+        lookup_span.arg("name", domain);
 
         // Acquire rate limit permit before proceeding
-        debug!("Acquiring DNS rate limit permit");
-        let _permit = self.rate_limiter.acquire().await?;
-        debug!("DNS rate limit permit acquired, proceeding with resolution");
+        let _permit = limiter.acquire().await?;
+        debug!(
+            domain,
+            wait_ms = stopwatch.elapsed_ms(),
+            "DNS rate limit permit acquired"
+        );
 
         let domain_copy = domain.to_string();
         let result = with_exponential_backoff(
             || {
                 let domain = domain_copy.clone();
-                let resolver = &self.resolver;
                 async move {
-                    debug!("DNS lookup attempt for {}", domain);
-                    resolver
-                        .lookup_ip(&domain)
+                    debug!(domain = %domain, "DNS lookup attempt");
+                    let name = domain.clone();
+                    self.query(|resolver| async move { resolver.lookup_ip(name).await })
                         .await
                         .context(format!("DNS resolution failed for {}", domain))
                 }
@@ -370,19 +835,25 @@ impl DnsResolver {
                             | ResolveErrorKind::NoRecordsFound { .. }
                             | ResolveErrorKind::Proto(_)
                             | ResolveErrorKind::Io(_) => {
-                                warn!("Retriable DNS error: {}, will retry", resolve_err);
+                                if let Some(occurrences) = self.log_sampler.sample("dns.retriable") {
+                                    warn!(error = %resolve_err, occurrences, "Retriable DNS error, will retry");
+                                }
                                 return true;
                             }
                             // Don't retry permanent failures
                             _ => {
-                                warn!("Non-retriable DNS error: {}, will not retry", resolve_err);
+                                if let Some(occurrences) = self.log_sampler.sample("dns.non_retriable") {
+                                    warn!(error = %resolve_err, occurrences, "Non-retriable DNS error, will not retry");
+                                }
                                 return false;
                             }
                         }
                     }
                 }
                 // By default retry on unknown errors
-                warn!("Unknown DNS error: {}, will retry", err);
+                if let Some(occurrences) = self.log_sampler.sample("dns.unknown") {
+                    warn!(error = %err, occurrences, "Unknown DNS error, will retry");
+                }
                 true
             },
             &self.retry_config,
@@ -398,17 +869,188 @@ impl DnsResolver {
             ));
         }
 
-        debug!("Resolved {} IP addresses for {}", ips.len(), domain);
+        debug!(
+            domain,
+            addresses = ips.len(),
+            elapsed_ms = stopwatch.elapsed_ms(),
+            "Resolved DNS"
+        );
         Ok(ips)
     }
 
+    /// Looks up the mail exchangers of a domain, ordered by preference
+    ///
+    /// Shares the rate limiter with `resolve`. A domain without MX records
+    /// yields an empty list rather than an error.
+    ///
+    /// # Arguments
+    /// * `domain` - The domain name to query (should be pre-validated)
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>>` - Exchange host names without the trailing dot
+    pub async fn resolve_mx(&self, domain: &str) -> Result<Vec<String>> {
+        self.resolve_mx_limited(domain, &self.rate_limiter).await
+    }
+
+    /// Looks up mail exchangers like [`DnsResolver::resolve_mx`], drawing from `limiter`
+    ///
+    /// # Arguments
+    /// * `domain` - The domain name to query (should be pre-validated)
+    /// * `limiter` - Rate limiter granting the query
+    pub async fn resolve_mx_limited(
+        &self,
+        domain: &str,
+        limiter: &RateLimiter,
+    ) -> Result<Vec<String>> {
+        if self.overridden(domain) == Some(&Override::NxDomain) {
+            debug!(domain, "No MX records, overridden as NXDOMAIN");
+            return Ok(Vec::new());
+        }
+        ensure_online(format_args!("MX lookup of {}", domain))?;
+        ensure_allowed(domain, format_args!("MX lookup of {}", domain))?;
+        let mut lookup_span = span("mx_lookup");
+        lookup_span.arg("name", domain);
+        let _permit = limiter.acquire().await?;
+
+        let lookup = match self
+            .query(|resolver| async move { resolver.mx_lookup(domain).await })
+            .await
+        {
+            Ok(lookup) => lookup,
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                debug!(domain, "No MX records");
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(e).context(format!("MX lookup failed for {}", domain)),
+        };
+
+        let mut records: Vec<(u16, String)> = lookup
+            .iter()
+            .map(|mx| {
+                let exchange = mx.exchange().to_utf8();
+                (mx.preference(), exchange.trim_end_matches('.').to_string())
+            })
+            .collect();
+        records.sort();
+
+        Ok(records.into_iter().map(|(_, exchange)| exchange).collect())
+    }
+
+    /// Looks up the TXT records of a domain
+    ///
+    /// Shares the rate limiter with `resolve`. Character strings of a record
+    /// are concatenated; a domain without TXT records yields an empty list.
+    ///
+    /// # Arguments
+    /// * `domain` - The domain name to query (should be pre-validated)
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>>` - One string per TXT record
+    pub async fn resolve_txt(&self, domain: &str) -> Result<Vec<String>> {
+        if self.overridden(domain) == Some(&Override::NxDomain) {
+            debug!(domain, "No TXT records, overridden as NXDOMAIN");
+            return Ok(Vec::new());
+        }
+        ensure_online(format_args!("TXT lookup of {}", domain))?;
+        ensure_allowed(domain, format_args!("TXT lookup of {}", domain))?;
+        let mut lookup_span = span("txt_lookup");
+        lookup_span.arg("name", domain);
+        let _permit = self.rate_limiter.acquire().await?;
+
+        let lookup = match self
+            .query(|resolver| async move { resolver.txt_lookup(domain).await })
+            .await
+        {
+            Ok(lookup) => lookup,
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                debug!(domain, "No TXT records");
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(e).context(format!("TXT lookup failed for {}", domain)),
+        };
+
+        Ok(lookup
+            .iter()
+            .map(|txt| {
+                txt.txt_data()
+                    .iter()
+                    .map(|chunk| String::from_utf8_lossy(chunk))
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Returns true if DNSSEC proves that `name` has no address
+    ///
+    /// Queries the A record of `name` through the validating resolver of
+    /// [`DnsResolver::with_dnssec_negatives`]. Only an authenticated negative
+    /// answer, an NXDOMAIN or NODATA response whose NSEC records validate up to
+    /// the root trust anchor, counts as proof. Unsigned zones, zones denying
+    /// existence with NSEC3, overridden names and failed lookups all yield
+    /// false, as does a resolver without validation.
+    ///
+    /// # Arguments
+    /// * `name` - The name to query (should be pre-validated)
+    pub async fn prove_nonexistent(&self, name: &str) -> bool {
+        self.prove_nonexistent_limited(name, &self.rate_limiter)
+            .await
+    }
+
+    /// Looks for a proof like [`DnsResolver::prove_nonexistent`], drawing from `limiter`
+    ///
+    /// # Arguments
+    /// * `name` - The name to query (should be pre-validated)
+    /// * `limiter` - Rate limiter granting the query
+    pub async fn prove_nonexistent_limited(&self, name: &str, limiter: &RateLimiter) -> bool {
+        if self.overridden(name).is_some()
+            || ensure_online(format_args!("DNSSEC lookup of {}", name)).is_err()
+            || ensure_allowed(name, format_args!("DNSSEC lookup of {}", name)).is_err()
+        {
+            return false;
+        }
+        let index = self.balancer.select(Instant::now());
+        let Some(validator) = &self.upstreams[index].validator else {
+            return false;
+        };
+        let mut lookup_span = span("dnssec_lookup");
+        lookup_span.arg("name", name);
+        let Ok(_permit) = limiter.acquire().await else {
+            return false;
+        };
+
+        match validator.lookup(name, RecordType::A).await {
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                debug!(name, "DNSSEC proved the name has no address");
+                true
+            }
+            Ok(_) => false,
+            Err(e) => {
+                debug!(name, error = %e, "No DNSSEC proof of nonexistence");
+                false
+            }
+        }
+    }
+
+    /// Shares a log sampler limiting repetitive failure logs
+    ///
+    /// # Arguments
+    /// * `sampler` - Sampler shared with the other components of a checker
+    pub fn with_log_sampler(mut self, sampler: Arc<LogSampler>) -> Self {
+        self.log_sampler = sampler;
+        self
+    }
+
     /// Sets a custom retry configuration for the DNS resolver
     ///
     /// # Arguments
     /// * `config` - The retry configuration to use
-    #[allow(dead_code)]
     pub fn with_retry_config(mut self, config: RetryConfig) -> Self {
         self.retry_config = config;
         self
     }
+
+    /// Retry configuration of lookups
+    pub fn retry_config(&self) -> &RetryConfig {
+        &self.retry_config
+    }
 }
@@ -0,0 +1,150 @@
+//! Static DNS overrides read from a hosts-style file
+//!
+//! With `--dns-override <FILE>`, listed host names are answered from the file
+//! before any real resolution, both for DNS lookups and for the connections of
+//! the HTTP client. This pins hosts to lab servers, replays scans without
+//! network access and works around broken split-horizon DNS.
+//!
+//! Each line maps one address, or `NXDOMAIN`, to one or more host names;
+//! `#` starts a comment:
+//!
+//! ```text
+//! # Send autodiscover requests to the lab
+//! 192.0.2.10   autodiscover-s.outlook.com
+//! 2001:db8::10 autodiscover-s.outlook.com
+//! NXDOMAIN     contoso-corp.mail.protection.outlook.com
+//! ```
+//!
+//! Addresses of a name listed on several lines are combined. Overrides answer
+//! address lookups; MX and TXT lookups of a name mapped to addresses still go
+//! to the resolvers, while a name mapped to `NXDOMAIN` has no records at all.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Marker of names that do not exist
+const NXDOMAIN: &str = "NXDOMAIN";
+
+/// Fixed answer for a host name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Override {
+    /// The name resolves to these addresses
+    Addresses(Vec<IpAddr>),
+    /// The name does not exist
+    NxDomain,
+}
+
+/// Host names answered without real resolution
+///
+/// # Examples
+///
+/// ```
+/// use sentri::dns_override::{DnsOverrides, Override};
+///
+/// let overrides = DnsOverrides::parse(
+///     "192.0.2.10 lab.example.com\nNXDOMAIN gone.example.com # retired",
+/// )?;
+/// assert_eq!(
+///     overrides.lookup("LAB.example.com."),
+///     Some(&Override::Addresses(vec!["192.0.2.10".parse()?]))
+/// );
+/// assert_eq!(overrides.lookup("gone.example.com"), Some(&Override::NxDomain));
+/// assert_eq!(overrides.lookup("other.example.com"), None);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DnsOverrides {
+    entries: HashMap<String, Override>,
+}
+
+impl DnsOverrides {
+    /// Parses overrides in hosts-file format
+    ///
+    /// # Errors
+    /// * A line starts with neither an address nor `NXDOMAIN`
+    /// * A line has no host name
+    /// * A name is mapped both to addresses and to `NXDOMAIN`
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut entries: HashMap<String, Override> = HashMap::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let Some(target) = fields.next() else {
+                continue;
+            };
+            let answer = if target.eq_ignore_ascii_case(NXDOMAIN) {
+                Override::NxDomain
+            } else {
+                let address = target.parse::<IpAddr>().with_context(|| {
+                    format!("Line {}: expected an address or NXDOMAIN", index + 1)
+                })?;
+                Override::Addresses(vec![address])
+            };
+
+            let names: Vec<&str> = fields.collect();
+            if names.is_empty() {
+                anyhow::bail!("Line {}: no host name for {}", index + 1, target);
+            }
+            for name in names {
+                let name = normalize(name);
+                match (entries.get_mut(&name), &answer) {
+                    (None, _) => {
+                        entries.insert(name, answer.clone());
+                    }
+                    (Some(Override::Addresses(known)), Override::Addresses(new)) => {
+                        for address in new {
+                            if !known.contains(address) {
+                                known.push(*address);
+                            }
+                        }
+                    }
+                    (Some(Override::NxDomain), Override::NxDomain) => {}
+                    (Some(_), _) => anyhow::bail!(
+                        "Line {}: {} is mapped both to addresses and to NXDOMAIN",
+                        index + 1,
+                        name
+                    ),
+                }
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    /// Reads overrides from a hosts-style file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read DNS overrides from {}", path.display()))?;
+        Self::parse(&content)
+            .with_context(|| format!("Invalid DNS overrides in {}", path.display()))
+    }
+
+    /// The fixed answer for `name`, if it is overridden
+    ///
+    /// Names are compared case-insensitively and without a trailing dot.
+    pub fn lookup(&self, name: &str) -> Option<&Override> {
+        self.entries.get(&normalize(name))
+    }
+
+    /// Every overridden name with its answer
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Override)> {
+        self.entries
+            .iter()
+            .map(|(name, answer)| (name.as_str(), answer))
+    }
+
+    /// Number of overridden names
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no name is overridden
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
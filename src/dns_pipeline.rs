@@ -0,0 +1,225 @@
+//! Pipelined DNS lookups for very large batches
+//!
+//! Without pipelining, every MDI probe of a batch resolves its sensor names
+//! itself, one query per UDP socket. At 100k+ tenants the sockets, not the
+//! resolvers, become the bottleneck. With `--dns-pipeline-workers`, probes
+//! hand their names to a [`LookupPool`] instead: a fixed set of workers drain
+//! the queue in batches of up to [`PipelineConfig::depth`] names and send each
+//! batch at once through a resolver whose upstreams are queried over TCP (see
+//! [`DnsResolver::with_pipelining`]). The queries of a batch are pipelined on
+//! one connection per upstream and answered out of order by query ID.
+//!
+//! Lookups keep the rate limiting, retries, overrides and egress checks of
+//! [`DnsResolver::resolve`], so the rate limiter still caps how many queries
+//! are in flight; [`LookupPool::resolve_limited`] draws from another limiter,
+//! such as the MDI sensor budget of `--mdi-dns-rate-limit`. EDNS Client
+//! Subnet options and source address pinning (see [`crate::dns_privacy`])
+//! only apply to UDP, which is why pipelining cannot be combined with `--ecs`
+//! or `--dns-bind-address`.
+
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use serde::Serialize;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tracing::debug;
+
+use crate::dns::DnsResolver;
+use crate::rate_limit::RateLimiter;
+
+/// Default number of names a worker sends at once
+pub const DEFAULT_PIPELINE_DEPTH: usize = 64;
+
+/// Size of a lookup pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineConfig {
+    /// Workers draining the queue, at least one
+    pub workers: usize,
+    /// Maximum number of names a worker sends at once, at least one
+    pub depth: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            depth: DEFAULT_PIPELINE_DEPTH,
+        }
+    }
+}
+
+/// Lookups and batches handled by a pool so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PipelineStats {
+    /// Names resolved
+    pub lookups: u64,
+    /// Batches sent by the workers
+    pub batches: u64,
+    /// Largest batch sent
+    pub largest_batch: usize,
+}
+
+impl PipelineStats {
+    /// Average number of names per batch
+    pub fn average_batch(&self) -> f64 {
+        if self.batches == 0 {
+            return 0.0;
+        }
+        self.lookups as f64 / self.batches as f64
+    }
+}
+
+/// A queued lookup and where its answer goes
+struct Job {
+    name: String,
+    /// Limiter of the lookup, if not the resolver's
+    limiter: Option<Arc<RateLimiter>>,
+    reply: oneshot::Sender<Result<Vec<IpAddr>>>,
+}
+
+#[derive(Default)]
+struct Counters {
+    lookups: AtomicU64,
+    batches: AtomicU64,
+    largest_batch: AtomicUsize,
+}
+
+/// Workers resolving queued names in pipelined batches
+///
+/// Workers stop once the pool and every clone of its queue are dropped.
+///
+/// # Examples
+///
+/// ```
+/// use sentri::dns::DnsResolver;
+/// use sentri::dns_override::DnsOverrides;
+/// use sentri::dns_pipeline::{LookupPool, PipelineConfig};
+/// use std::sync::Arc;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let overrides = DnsOverrides::parse("192.0.2.10 contososensorapi.atp.azure.com")?;
+/// let resolver = DnsResolver::new()?
+///     .with_pipelining()
+///     .with_overrides(Arc::new(overrides));
+/// let pool = LookupPool::spawn(Arc::new(resolver), PipelineConfig::default());
+///
+/// let addresses = pool.resolve("contososensorapi.atp.azure.com").await?;
+/// assert_eq!(addresses, ["192.0.2.10".parse::<std::net::IpAddr>()?]);
+/// assert_eq!(pool.stats().lookups, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct LookupPool {
+    queue: mpsc::Sender<Job>,
+    counters: Arc<Counters>,
+}
+
+impl LookupPool {
+    /// Starts the workers of a pool resolving names with `resolver`
+    ///
+    /// # Panics
+    /// Panics when called outside of a Tokio runtime.
+    pub fn spawn(resolver: Arc<DnsResolver>, config: PipelineConfig) -> Self {
+        let depth = config.depth.max(1);
+        let workers = config.workers.max(1);
+        // Room for a full batch per worker waiting behind the batches in flight
+        let (queue, receiver) = mpsc::channel(depth * workers);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let counters = Arc::new(Counters::default());
+        for worker in 0..workers {
+            tokio::spawn(run_worker(
+                worker,
+                Arc::clone(&resolver),
+                Arc::clone(&receiver),
+                Arc::clone(&counters),
+                depth,
+            ));
+        }
+        Self { queue, counters }
+    }
+
+    /// Resolves `name` to IP addresses in the next batch of a worker
+    ///
+    /// Answers and errors are those of [`DnsResolver::resolve`].
+    pub async fn resolve(&self, name: &str) -> Result<Vec<IpAddr>> {
+        self.submit(name, None).await
+    }
+
+    /// Resolves `name` like [`LookupPool::resolve`], drawing from `limiter`
+    ///
+    /// See [`DnsResolver::resolve_limited`].
+    pub async fn resolve_limited(
+        &self,
+        name: &str,
+        limiter: Arc<RateLimiter>,
+    ) -> Result<Vec<IpAddr>> {
+        self.submit(name, Some(limiter)).await
+    }
+
+    /// Queues a lookup and waits for its answer
+    async fn submit(&self, name: &str, limiter: Option<Arc<RateLimiter>>) -> Result<Vec<IpAddr>> {
+        let (reply, answer) = oneshot::channel();
+        let job = Job {
+            name: name.to_string(),
+            limiter,
+            reply,
+        };
+        self.queue
+            .send(job)
+            .await
+            .ok()
+            .context("DNS lookup pool stopped")?;
+        answer.await.context("DNS lookup pool stopped")?
+    }
+
+    /// Lookups and batches handled so far
+    pub fn stats(&self) -> PipelineStats {
+        PipelineStats {
+            lookups: self.counters.lookups.load(Ordering::Relaxed),
+            batches: self.counters.batches.load(Ordering::Relaxed),
+            largest_batch: self.counters.largest_batch.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Takes up to `depth` queued jobs at a time and resolves them concurrently
+async fn run_worker(
+    worker: usize,
+    resolver: Arc<DnsResolver>,
+    receiver: Arc<Mutex<mpsc::Receiver<Job>>>,
+    counters: Arc<Counters>,
+    depth: usize,
+) {
+    let mut batch = Vec::with_capacity(depth);
+    loop {
+        // The lock is only held while waiting, so other workers send meanwhile
+        let received = receiver.lock().await.recv_many(&mut batch, depth).await;
+        if received == 0 {
+            debug!(worker, "DNS lookup pool closed");
+            return;
+        }
+        counters
+            .lookups
+            .fetch_add(received as u64, Ordering::Relaxed);
+        counters.batches.fetch_add(1, Ordering::Relaxed);
+        counters
+            .largest_batch
+            .fetch_max(received, Ordering::Relaxed);
+        debug!(worker, names = received, "Sending pipelined DNS batch");
+
+        join_all(batch.drain(..).map(|job| {
+            let resolver = &resolver;
+            async move {
+                let answer = match &job.limiter {
+                    Some(limiter) => resolver.resolve_limited(&job.name, limiter).await,
+                    None => resolver.resolve(&job.name).await,
+                };
+                // The prober may have given up waiting
+                let _ = job.reply.send(answer);
+            }
+        }))
+        .await;
+    }
+}
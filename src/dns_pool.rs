@@ -0,0 +1,246 @@
+//! Load balancing of DNS queries across several upstream resolvers
+//!
+//! A single resolver becomes the bottleneck at high query rates, and its
+//! outage stalls every scan. With `--resolver` given more than once, queries
+//! are spread across the upstreams by a [`Strategy`]:
+//!
+//! - `round-robin` - Each query goes to the next upstream in turn
+//! - `fastest` - Queries go to the upstream with the lowest average latency;
+//!   every [`PROBE_EVERY`]th query probes another one so recovered or faster
+//!   upstreams are noticed
+//! - `failover` - Queries go to the first upstream that is healthy, in the
+//!   order given
+//!
+//! An upstream that times out or fails [`FAILURE_THRESHOLD`] times in a row
+//! is taken out of rotation for [`COOLDOWN`]. Answers such as NXDOMAIN count
+//! as success: the upstream is working. Query counts, failures and latency are
+//! tracked per upstream and reported through [`UpstreamStats`].
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Consecutive failures after which an upstream is taken out of rotation
+pub const FAILURE_THRESHOLD: u32 = 3;
+
+/// How long a failing upstream stays out of rotation
+pub const COOLDOWN: Duration = Duration::from_secs(30);
+
+/// With the `fastest` strategy, one in this many queries probes another upstream
+pub const PROBE_EVERY: usize = 16;
+
+/// Weight of the latest query in the moving latency average
+const LATENCY_WEIGHT: f64 = 0.3;
+
+/// Port of plain DNS
+const DNS_PORT: u16 = 53;
+
+/// How queries are distributed across upstream resolvers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Rotate through the healthy upstreams
+    #[default]
+    RoundRobin,
+    /// Prefer the upstream with the lowest latency, probing the others
+    Fastest,
+    /// Use the first healthy upstream in the configured order
+    Failover,
+}
+
+/// Parses an upstream resolver address, defaulting to port 53
+///
+/// # Examples
+///
+/// ```
+/// use sentri::dns_pool::parse_upstream;
+///
+/// assert_eq!(parse_upstream("1.1.1.1").unwrap().to_string(), "1.1.1.1:53");
+/// assert_eq!(parse_upstream("9.9.9.9:5353").unwrap().port(), 5353);
+/// assert_eq!(parse_upstream("[2606:4700::1111]:53").unwrap().port(), 53);
+/// assert!(parse_upstream("dns.example.com").is_err());
+/// ```
+pub fn parse_upstream(value: &str) -> Result<SocketAddr> {
+    if let Ok(ip) = value.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, DNS_PORT));
+    }
+    value
+        .parse::<SocketAddr>()
+        .with_context(|| format!("Invalid resolver address: {}", value))
+}
+
+/// Health and metrics of one upstream
+#[derive(Debug, Default)]
+struct Health {
+    queries: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    average_latency_ms: Option<f64>,
+    down_until: Option<Instant>,
+}
+
+impl Health {
+    fn is_up(&self, now: Instant) -> bool {
+        self.down_until.is_none_or(|until| now >= until)
+    }
+}
+
+/// Metrics of an upstream resolver
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpstreamStats {
+    /// Address of the upstream, or `system` for the system configuration
+    pub upstream: String,
+    /// Queries sent to the upstream
+    pub queries: u64,
+    /// Queries that timed out or failed
+    pub failures: u64,
+    /// Moving average of the latency of answered queries
+    pub average_latency_ms: Option<f64>,
+    /// Whether the upstream is currently in rotation
+    pub healthy: bool,
+}
+
+/// Chooses the upstream for each query and tracks upstream health
+///
+/// # Examples
+///
+/// ```
+/// use sentri::dns_pool::{Balancer, Strategy};
+/// use std::time::{Duration, Instant};
+///
+/// let balancer = Balancer::new(Strategy::RoundRobin, 2);
+/// let now = Instant::now();
+/// assert_eq!(balancer.select(now), 0);
+/// assert_eq!(balancer.select(now), 1);
+///
+/// balancer.record_success(0, Duration::from_millis(12));
+/// assert_eq!(balancer.stats()[0].queries, 1);
+/// ```
+#[derive(Debug)]
+pub struct Balancer {
+    strategy: Strategy,
+    upstreams: Vec<Mutex<Health>>,
+    next: AtomicUsize,
+}
+
+impl Balancer {
+    /// Creates a balancer over `count` upstreams
+    pub fn new(strategy: Strategy, count: usize) -> Self {
+        Self {
+            strategy,
+            upstreams: (0..count.max(1))
+                .map(|_| Mutex::new(Health::default()))
+                .collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Strategy used to choose upstreams
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Returns the index of the upstream the next query should go to
+    ///
+    /// When every upstream is out of rotation, the one returning first is used.
+    pub fn select(&self, now: Instant) -> usize {
+        let health: Vec<(bool, Option<f64>, Option<Instant>)> = self
+            .upstreams
+            .iter()
+            .map(|upstream| {
+                let health = upstream.lock().unwrap_or_else(|e| e.into_inner());
+                (
+                    health.is_up(now),
+                    health.average_latency_ms,
+                    health.down_until,
+                )
+            })
+            .collect();
+        let healthy: Vec<usize> = (0..health.len()).filter(|&i| health[i].0).collect();
+        if healthy.is_empty() {
+            return (0..health.len()).min_by_key(|&i| health[i].2).unwrap_or(0);
+        }
+
+        match self.strategy {
+            Strategy::RoundRobin => {
+                let turn = self.next.fetch_add(1, Ordering::Relaxed);
+                healthy[turn % healthy.len()]
+            }
+            Strategy::Failover => healthy[0],
+            Strategy::Fastest => {
+                let turn = self.next.fetch_add(1, Ordering::Relaxed);
+                if turn % PROBE_EVERY == PROBE_EVERY - 1 {
+                    return healthy[(turn / PROBE_EVERY) % healthy.len()];
+                }
+                // Unmeasured upstreams are tried first, then the fastest wins
+                healthy
+                    .iter()
+                    .copied()
+                    .min_by(|&a, &b| {
+                        let latency = |i: usize| health[i].1.unwrap_or(-1.0);
+                        latency(a).total_cmp(&latency(b))
+                    })
+                    .unwrap_or(healthy[0])
+            }
+        }
+    }
+
+    /// Records an answered query
+    pub fn record_success(&self, index: usize, latency: Duration) {
+        let Some(upstream) = self.upstreams.get(index) else {
+            return;
+        };
+        let mut health = upstream.lock().unwrap_or_else(|e| e.into_inner());
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        health.queries += 1;
+        health.consecutive_failures = 0;
+        health.down_until = None;
+        health.average_latency_ms = Some(match health.average_latency_ms {
+            Some(average) => average + LATENCY_WEIGHT * (latency_ms - average),
+            None => latency_ms,
+        });
+    }
+
+    /// Records a failed query, taking the upstream out of rotation after
+    /// [`FAILURE_THRESHOLD`] consecutive failures
+    ///
+    /// # Returns
+    /// * `bool` - True if this failure took the upstream out of rotation
+    pub fn record_failure(&self, index: usize, now: Instant) -> bool {
+        let Some(upstream) = self.upstreams.get(index) else {
+            return false;
+        };
+        let mut health = upstream.lock().unwrap_or_else(|e| e.into_inner());
+        health.queries += 1;
+        health.failures += 1;
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= FAILURE_THRESHOLD && health.is_up(now) {
+            health.down_until = Some(now + COOLDOWN);
+            return true;
+        }
+        false
+    }
+
+    /// Metrics of every upstream, in configured order, without names
+    pub fn stats(&self) -> Vec<UpstreamStats> {
+        let now = Instant::now();
+        self.upstreams
+            .iter()
+            .enumerate()
+            .map(|(index, upstream)| {
+                let health = upstream.lock().unwrap_or_else(|e| e.into_inner());
+                UpstreamStats {
+                    upstream: index.to_string(),
+                    queries: health.queries,
+                    failures: health.failures,
+                    average_latency_ms: health.average_latency_ms,
+                    healthy: health.is_up(now),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,266 @@
+//! Privacy and anti-poisoning controls of outgoing DNS queries
+//!
+//! Every query leaves through the sockets of a [`PrivacyRuntime`], which
+//! applies two controls:
+//!
+//! - **EDNS Client Subnet** (RFC 7871) - Recursive resolvers may forward part
+//!   of the client address to authoritative servers. With [`Ecs::Off`] each
+//!   query carries a client subnet option with a source prefix of 0, which
+//!   tells the resolver not to reveal anything about the client. With
+//!   [`Ecs::Subnet`] the given subnet is forwarded instead, e.g. to obtain the
+//!   answers clients in a particular network would see. Without a setting,
+//!   queries are sent unchanged and the resolver applies its own policy.
+//! - **Source port randomization** - Each UDP query is sent from a fresh socket
+//!   on a random port of the IANA ephemeral range (RFC 6056) with a random
+//!   query ID, so an off-path attacker has to guess both to spoof an answer.
+//!   Sockets are refused unless their port is in [`EPHEMERAL_PORTS`], and the
+//!   ports used are tracked in [`SourcePortStats`]. The source address can be
+//!   pinned with [`PrivacyConfig::bind_address`]; the port always stays random.
+//!
+//! Client subnet options are only added to UDP queries. Answers too large for
+//! UDP are retried over TCP without the option, in which case the resolver
+//! falls back to its own policy.
+
+use anyhow::{Context, Result};
+use ipnet::IpNet;
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context as TaskContext, Poll};
+use trust_dns_resolver::name_server::{RuntimeProvider, TokioHandle, TokioRuntimeProvider};
+use trust_dns_resolver::proto::op::{Edns, Message};
+use trust_dns_resolver::proto::rr::rdata::opt::{ClientSubnet, EdnsOption};
+use trust_dns_resolver::proto::udp::DnsUdpSocket;
+use trust_dns_resolver::proto::TokioTime;
+
+/// Source ports UDP queries may be sent from
+pub const EPHEMERAL_PORTS: RangeInclusive<u16> = 49152..=u16::MAX;
+
+/// EDNS Client Subnet sent with every UDP query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ecs {
+    /// Ask resolvers not to forward any part of the client address
+    Off,
+    /// Forward this subnet as the client subnet
+    Subnet {
+        /// Network address, with the host bits cleared
+        address: IpAddr,
+        /// Number of significant bits of the address
+        prefix: u8,
+    },
+}
+
+impl Ecs {
+    /// The option as sent on the wire
+    fn client_subnet(&self) -> ClientSubnet {
+        match *self {
+            Ecs::Off => ClientSubnet::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0, 0),
+            Ecs::Subnet { address, prefix } => ClientSubnet::new(address, prefix, 0),
+        }
+    }
+}
+
+impl fmt::Display for Ecs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ecs::Off => write!(f, "off"),
+            Ecs::Subnet { address, prefix } => write!(f, "{}/{}", address, prefix),
+        }
+    }
+}
+
+/// Parses an EDNS Client Subnet setting: `off` or a subnet in CIDR notation
+///
+/// Host bits of the subnet are cleared, so only the network is ever sent.
+///
+/// # Examples
+///
+/// ```
+/// use sentri::dns_privacy::{parse_ecs, Ecs};
+///
+/// assert_eq!(parse_ecs("off").unwrap(), Ecs::Off);
+/// assert_eq!(parse_ecs("198.51.100.77/24").unwrap().to_string(), "198.51.100.0/24");
+/// assert_eq!(parse_ecs("2001:db8:1:2::/48").unwrap().to_string(), "2001:db8:1::/48");
+/// assert!(parse_ecs("198.51.100.0/33").is_err());
+/// assert!(parse_ecs("198.51.100.0").is_err());
+/// ```
+pub fn parse_ecs(value: &str) -> Result<Ecs> {
+    if value.eq_ignore_ascii_case("off") {
+        return Ok(Ecs::Off);
+    }
+    let subnet: IpNet = value
+        .parse()
+        .with_context(|| format!("Invalid client subnet, expected off or CIDR: {}", value))?;
+    Ok(Ecs::Subnet {
+        address: subnet.network(),
+        prefix: subnet.prefix_len(),
+    })
+}
+
+/// Privacy settings applied to outgoing queries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrivacyConfig {
+    /// Client subnet sent with every UDP query, if any
+    pub ecs: Option<Ecs>,
+    /// Local address queries are sent from; the port is always random
+    ///
+    /// Only used for upstreams of the same address family.
+    pub bind_address: Option<IpAddr>,
+}
+
+/// Source ports used by UDP queries
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SourcePortStats {
+    /// Sockets opened, one per query attempt
+    pub sockets: u64,
+    /// Sockets refused because their port was outside [`EPHEMERAL_PORTS`]
+    pub refused: u64,
+    /// Ports of the most recent sockets, oldest first
+    pub recent_ports: Vec<u16>,
+}
+
+/// Ports kept in [`SourcePortStats::recent_ports`]
+const RECENT_PORTS: usize = 64;
+
+/// Runtime of the DNS resolvers applying a [`PrivacyConfig`]
+#[derive(Clone, Default)]
+pub struct PrivacyRuntime {
+    inner: TokioRuntimeProvider,
+    config: PrivacyConfig,
+    ports: Arc<Mutex<SourcePortStats>>,
+}
+
+impl PrivacyRuntime {
+    /// Creates a runtime applying `config`
+    pub fn new(config: PrivacyConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Settings applied to outgoing queries
+    pub fn config(&self) -> PrivacyConfig {
+        self.config
+    }
+
+    /// Source ports used so far
+    pub fn source_ports(&self) -> SourcePortStats {
+        self.ports.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Records the port of a new socket, refusing ports outside the ephemeral range
+    fn check_port(&self, port: u16) -> io::Result<()> {
+        let mut ports = self.ports.lock().unwrap_or_else(|e| e.into_inner());
+        if !EPHEMERAL_PORTS.contains(&port) {
+            ports.refused += 1;
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("DNS source port {} is not randomized", port),
+            ));
+        }
+        ports.sockets += 1;
+        if ports.recent_ports.len() == RECENT_PORTS {
+            ports.recent_ports.remove(0);
+        }
+        ports.recent_ports.push(port);
+        Ok(())
+    }
+}
+
+impl RuntimeProvider for PrivacyRuntime {
+    type Handle = TokioHandle;
+    type Timer = TokioTime;
+    type Udp = PrivacySocket;
+    type Tcp = <TokioRuntimeProvider as RuntimeProvider>::Tcp;
+
+    fn create_handle(&self) -> Self::Handle {
+        self.inner.create_handle()
+    }
+
+    fn connect_tcp(
+        &self,
+        server_addr: SocketAddr,
+    ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Tcp>>>> {
+        self.inner.connect_tcp(server_addr)
+    }
+
+    fn bind_udp(
+        &self,
+        local_addr: SocketAddr,
+        _server_addr: SocketAddr,
+    ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Udp>>>> {
+        let mut local_addr = local_addr;
+        if let Some(ip) = self.config.bind_address {
+            if ip.is_ipv4() == local_addr.is_ipv4() {
+                local_addr.set_ip(ip);
+            }
+        }
+        let checked = self.check_port(local_addr.port());
+        let ecs = self.config.ecs;
+        Box::pin(async move {
+            checked?;
+            let socket = tokio::net::UdpSocket::bind(local_addr).await?;
+            Ok(PrivacySocket { socket, ecs })
+        })
+    }
+}
+
+/// UDP socket adding the client subnet option to outgoing queries
+pub struct PrivacySocket {
+    socket: tokio::net::UdpSocket,
+    ecs: Option<Ecs>,
+}
+
+impl DnsUdpSocket for PrivacySocket {
+    type Time = TokioTime;
+
+    fn poll_recv_from(
+        &self,
+        cx: &mut TaskContext<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        DnsUdpSocket::poll_recv_from(&self.socket, cx, buf)
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        let Some(ecs) = self.ecs else {
+            return DnsUdpSocket::poll_send_to(&self.socket, cx, buf, target);
+        };
+        let query = with_client_subnet(buf, &ecs)?;
+        match DnsUdpSocket::poll_send_to(&self.socket, cx, &query, target) {
+            // The caller only knows about the query it handed over
+            Poll::Ready(Ok(sent)) if sent == query.len() => Poll::Ready(Ok(buf.len())),
+            Poll::Ready(Ok(_)) => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "DNS query was not sent completely",
+            ))),
+            other => other,
+        }
+    }
+}
+
+/// Re-encodes a query with `ecs` as its client subnet option
+///
+/// Fails instead of sending the query unchanged, so a configured setting is
+/// never silently dropped.
+fn with_client_subnet(query: &[u8], ecs: &Ecs) -> io::Result<Vec<u8>> {
+    let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+    let mut message = Message::from_vec(query).map_err(invalid)?;
+    message
+        .extensions_mut()
+        .get_or_insert_with(Edns::new)
+        .options_mut()
+        .insert(EdnsOption::Subnet(ecs.client_subnet()));
+    message.to_vec().map_err(invalid)
+}
@@ -0,0 +1,192 @@
+//! Outbound allowlist for telemetry-free guarantee mode
+//!
+//! After `--strict-egress` (see [`set_strict_egress`]) every HTTP request,
+//! redirect and DNS lookup is checked against an allowlist of destination
+//! hosts before it is sent, and anything else fails immediately with an
+//! [`EgressError`]. The allowlist holds:
+//!
+//! - the Microsoft endpoints the scan itself needs ([`DEFAULT_ALLOWED_HOSTS`]):
+//!   the autodiscover service and its alternate endpoint,
+//!   `login.microsoftonline.com`, the tenant names under `onmicrosoft.com`
+//!   looked up by `sentri tenant`, and the MDI sensor API namespaces
+//! - the destinations of configured result sinks and alerters, registered
+//!   with [`allow_url`] or [`allow_host`] when they are built
+//! - hosts given with `--egress-allow`
+//!
+//! Report emails and uploads only connect to the SMTP server and bucket they
+//! are configured with.
+//!
+//! Patterns are host names matched without regard to case; a leading `*`
+//! matches any prefix, so `*.example.com` allows every subdomain of
+//! `example.com` and `*sensorapi.atp.azure.com` every tenant's sensor name.
+//!
+//! Lookups of the scanned domains themselves, as made by endpoint
+//! attribution and ownership verification, are refused unless the domains
+//! are allowlisted too. Callers can tell refusals apart by downcasting:
+//!
+//! ```
+//! use sentri::egress::{ensure_allowed, set_strict_egress, EgressError};
+//!
+//! set_strict_egress(true);
+//! assert!(ensure_allowed("login.microsoftonline.com", "GET").is_ok());
+//! let error = ensure_allowed("telemetry.example", "POST https://telemetry.example/").unwrap_err();
+//! assert!(error.downcast_ref::<EgressError>().is_some());
+//! assert_eq!(
+//!     error.to_string(),
+//!     "Destination telemetry.example is not allowed by --strict-egress: POST https://telemetry.example/"
+//! );
+//! # set_strict_egress(false);
+//! ```
+
+use anyhow::Result;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// Hosts every scan needs to reach
+///
+/// Sensor API names are the tenant name followed by a suffix of
+/// [`crate::core::MDI_SENSOR_ENDPOINTS`].
+pub const DEFAULT_ALLOWED_HOSTS: &[&str] = &[
+    "autodiscover-s.outlook.com",
+    "outlook.office365.com",
+    "login.microsoftonline.com",
+    "*.onmicrosoft.com",
+    "*sensorapi.security.microsoft.com",
+    "*sensorapi.atp.azure.com",
+];
+
+/// Whether outbound connections are restricted to the allowlist
+static STRICT_EGRESS: AtomicBool = AtomicBool::new(false);
+
+/// Host patterns allowed in addition to the defaults
+static ALLOWED_HOSTS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Enables or disables strict egress for the whole process
+pub fn set_strict_egress(strict: bool) {
+    STRICT_EGRESS.store(strict, Ordering::Relaxed);
+}
+
+/// Returns true if outbound connections are restricted to the allowlist
+pub fn is_strict_egress() -> bool {
+    STRICT_EGRESS.load(Ordering::Relaxed)
+}
+
+/// Adds a host pattern to the allowlist
+pub fn allow_host(pattern: &str) {
+    let pattern = normalize(pattern);
+    if pattern.is_empty() {
+        return;
+    }
+    let mut allowed = ALLOWED_HOSTS.write().unwrap_or_else(|e| e.into_inner());
+    if !allowed.contains(&pattern) {
+        allowed.push(pattern);
+    }
+}
+
+/// Adds the host of `url` to the allowlist
+///
+/// URLs without a host are ignored; requests to them are refused anyway.
+#[cfg(feature = "http")]
+pub fn allow_url(url: &str) {
+    if let Some(host) = host_of(url) {
+        allow_host(&host);
+    }
+}
+
+/// Host patterns allowed in addition to [`DEFAULT_ALLOWED_HOSTS`]
+pub fn allowed_hosts() -> Vec<String> {
+    ALLOWED_HOSTS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Returns true if `host` matches a pattern of the allowlist
+///
+/// # Examples
+///
+/// ```
+/// use sentri::egress::is_allowed;
+///
+/// assert!(is_allowed("AUTODISCOVER-S.OUTLOOK.COM."));
+/// assert!(is_allowed("contososensorapi.atp.azure.com"));
+/// assert!(!is_allowed("sensorapi.atp.azure.com.attacker.example"));
+/// ```
+pub fn is_allowed(host: &str) -> bool {
+    let host = normalize(host);
+    if host.is_empty() {
+        return false;
+    }
+    DEFAULT_ALLOWED_HOSTS
+        .iter()
+        .any(|pattern| matches(pattern, &host))
+        || ALLOWED_HOSTS
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .any(|pattern| matches(pattern, &host))
+}
+
+/// An outbound connection refused in strict egress mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EgressError {
+    /// Destination host, empty if the URL had none
+    pub host: String,
+    /// The refused operation, e.g. the request method and URL
+    pub operation: String,
+}
+
+impl fmt::Display for EgressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Destination {} is not allowed by --strict-egress: {}",
+            self.host, self.operation
+        )
+    }
+}
+
+impl std::error::Error for EgressError {}
+
+/// Fails with an [`EgressError`] if strict egress is enabled and `host` is
+/// not allowlisted
+pub fn ensure_allowed(host: &str, operation: impl fmt::Display) -> Result<()> {
+    if is_strict_egress() && !is_allowed(host) {
+        return Err(EgressError {
+            host: normalize(host),
+            operation: operation.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Like [`ensure_allowed`] for the host of `url`
+#[cfg(feature = "http")]
+pub fn ensure_url_allowed(url: &str, operation: impl fmt::Display) -> Result<()> {
+    ensure_allowed(&host_of(url).unwrap_or_default(), operation)
+}
+
+/// Host of `url`, if it parses and has one
+#[cfg(feature = "http")]
+fn host_of(url: &str) -> Option<String> {
+    reqwest::Url::parse(url).ok()?.host_str().map(|host| {
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string()
+    })
+}
+
+/// Lowercases `host` and strips a trailing dot
+fn normalize(host: &str) -> String {
+    host.trim().trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Returns true if the normalized `host` matches `pattern`
+fn matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix('*') {
+        Some(suffix) => host.len() > suffix.len() && host.ends_with(suffix),
+        None => pattern == host,
+    }
+}
@@ -0,0 +1,480 @@
+//! Encryption of stored data at rest
+//!
+//! Scan results, captured responses and audit records are reconnaissance
+//! data; when they accumulate on disk, for example in the server state
+//! directory or a capture directory on a consultant's laptop, they can be
+//! encrypted with a 256-bit AES-GCM [`StorageKey`] given as `--storage-key`.
+//!
+//! Line-oriented files (results, the capture index and the audit log) stay
+//! line-oriented. An encrypted file starts with a header line,
+//! [`ENCRYPTED_FILE_HEADER`] followed by a random file identifier; every
+//! further line is encrypted on its own by a [`LineEncryptor`] and written as
+//! [`ENCRYPTED_LINE_PREFIX`] followed by the base64 encoding of a random
+//! 96-bit nonce and the ciphertext. Files can therefore still be appended to
+//! and streamed, and plaintext files written before encryption was enabled
+//! remain readable next to encrypted ones through a [`LineDecoder`].
+//!
+//! Every line is authenticated together with the file identifier and its
+//! line number (see [`line_aad`]), so lines cannot be removed, reordered or
+//! moved to another file without failing to decrypt. Whole files, such as
+//! captured responses, and database rows are likewise bound to an identifier
+//! of their own.
+//!
+//! A key is 32 random bytes, base64-encoded:
+//!
+//! ```text
+//! openssl rand -base64 32 > /run/secrets/sentri-storage-key
+//! ```
+//!
+//! # Security Considerations
+//!
+//! - **Authenticated Encryption**: AES-GCM detects tampered, truncated,
+//!   removed, reordered or transplanted lines; they fail to decrypt rather
+//!   than yielding altered data. Records cut off the end of a file cannot be
+//!   told apart from records never written
+//! - **Key Handling**: The key is never logged or written to disk by sentri;
+//!   supply it through a secret reference (see [`crate::secrets`])
+//!   (security:output:error_info_control)
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+/// Marks an encrypted line; plaintext JSON lines start with `{`
+pub const ENCRYPTED_LINE_PREFIX: &str = "enc:v2:";
+
+/// Starts the header line of an encrypted file, followed by its identifier
+pub const ENCRYPTED_FILE_HEADER: &str = "enc:v2:file:";
+
+/// Length of an AES-256 key in bytes
+pub const STORAGE_KEY_LEN: usize = 32;
+
+/// Length of a file identifier in bytes
+pub const FILE_ID_LEN: usize = 16;
+
+/// Length of an AES-GCM nonce in bytes
+const NONCE_LEN: usize = 12;
+
+/// Domain separation of the key deriving [`StorageKey::keyed_id`]
+const KEYED_ID_CONTEXT: &[u8] = b"sentri keyed identifiers v1";
+
+/// Identifier of an encrypted file or database
+pub type FileId = [u8; FILE_ID_LEN];
+
+/// Key encrypting stored data
+pub struct StorageKey {
+    cipher: Aes256Gcm,
+    ids: Hmac<Sha256>,
+}
+
+impl std::fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StorageKey(..)")
+    }
+}
+
+impl StorageKey {
+    /// Creates a key from 32 raw bytes
+    ///
+    /// # Errors
+    /// * The key is not 32 bytes long
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != STORAGE_KEY_LEN {
+            anyhow::bail!(
+                "Storage key must be {} bytes, got {}",
+                STORAGE_KEY_LEN,
+                bytes.len()
+            );
+        }
+        let cipher =
+            Aes256Gcm::new_from_slice(bytes).map_err(|_| anyhow::anyhow!("Invalid storage key"))?;
+        let id_key = Sha256::new()
+            .chain_update(KEYED_ID_CONTEXT)
+            .chain_update(bytes)
+            .finalize();
+        let ids = <Hmac<Sha256> as Mac>::new_from_slice(&id_key)
+            .map_err(|_| anyhow::anyhow!("Invalid storage key"))?;
+        Ok(Self { cipher, ids })
+    }
+
+    /// Creates a key from its base64 encoding
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::encryption::{line_aad, StorageKey};
+    ///
+    /// let key = StorageKey::from_base64("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=").unwrap();
+    /// let aad = line_aad(&[7; 16], 0);
+    /// let sealed = key.encrypt(b"contoso.com", &aad).unwrap();
+    /// assert_eq!(key.decrypt(&sealed, &aad).unwrap(), b"contoso.com");
+    /// assert!(key.decrypt(&sealed, &line_aad(&[7; 16], 1)).is_err());
+    /// ```
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = BASE64
+            .decode(encoded.trim())
+            .context("Storage key is not valid base64")?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Generates a random key, returning it base64-encoded
+    pub fn generate() -> String {
+        BASE64.encode(Aes256Gcm::generate_key(&mut OsRng))
+    }
+
+    /// Encrypts data bound to `aad`, returning the nonce followed by the ciphertext
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypts the output of [`StorageKey::encrypt`] for the same `aad`
+    ///
+    /// # Errors
+    /// * The data was encrypted with another key or for another `aad`, or was modified
+    pub fn decrypt(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            anyhow::bail!("Encrypted data is truncated");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Decryption failed: wrong storage key, or the data was modified or moved"
+                )
+            })
+    }
+
+    /// Stable identifier of `value` that only holders of the key can compute
+    ///
+    /// Names encrypted records where the plaintext name, e.g. a domain or
+    /// the hash of a response, would reveal what is stored.
+    pub fn keyed_id(&self, value: &str) -> String {
+        let mut mac = self.ids.clone();
+        mac.update(value.as_bytes());
+        format!("{:x}", mac.finalize().into_bytes())
+    }
+}
+
+/// Associated data of line `line` (counted from 0 after the header) of the file `file_id`
+pub fn line_aad(file_id: &FileId, line: u64) -> Vec<u8> {
+    let mut aad = file_id.to_vec();
+    aad.extend_from_slice(&line.to_be_bytes());
+    aad
+}
+
+/// Returns a new random file identifier
+pub fn new_file_id() -> FileId {
+    let mut file_id = [0; FILE_ID_LEN];
+    OsRng.fill_bytes(&mut file_id);
+    file_id
+}
+
+/// Returns the header line of an encrypted file
+pub fn file_header(file_id: &FileId) -> String {
+    format!("{}{}", ENCRYPTED_FILE_HEADER, BASE64.encode(file_id))
+}
+
+/// Parses a header line, returning `None` for any other line
+pub fn parse_file_header(line: &str) -> Option<Result<FileId>> {
+    let encoded = line.strip_prefix(ENCRYPTED_FILE_HEADER)?;
+    Some(
+        BASE64
+            .decode(encoded.trim_end())
+            .ok()
+            .and_then(|id| FileId::try_from(id).ok())
+            .context("Invalid encrypted file header"),
+    )
+}
+
+/// Encrypts the lines of one file, binding each to the file and its position
+///
+/// # Examples
+///
+/// ```
+/// use sentri::encryption::{LineDecoder, LineEncryptor, StorageKey};
+/// use std::sync::Arc;
+///
+/// let key = Arc::new(StorageKey::from_base64(&StorageKey::generate()).unwrap());
+/// let mut encryptor = LineEncryptor::new(Arc::clone(&key));
+/// let file = [
+///     encryptor.header(),
+///     encryptor.encrypt(r#"{"domain":"contoso.com"}"#).unwrap(),
+///     encryptor.encrypt(r#"{"domain":"fabrikam.com"}"#).unwrap(),
+/// ];
+///
+/// let mut decoder = LineDecoder::new();
+/// assert_eq!(decoder.decode(Some(&key), &file[0]).unwrap(), None);
+/// assert_eq!(
+///     decoder.decode(Some(&key), &file[1]).unwrap().unwrap(),
+///     r#"{"domain":"contoso.com"}"#
+/// );
+///
+/// // Lines cannot be dropped or reordered
+/// let mut decoder = LineDecoder::new();
+/// decoder.decode(Some(&key), &file[0]).unwrap();
+/// assert!(decoder.decode(Some(&key), &file[2]).is_err());
+/// ```
+pub struct LineEncryptor {
+    key: Arc<StorageKey>,
+    file_id: FileId,
+    next_line: u64,
+}
+
+impl LineEncryptor {
+    /// Starts a new encrypted file with a random identifier
+    ///
+    /// Its [`LineEncryptor::header`] must be written before the first line.
+    pub fn new(key: Arc<StorageKey>) -> Self {
+        Self {
+            key,
+            file_id: new_file_id(),
+            next_line: 0,
+        }
+    }
+
+    /// Continues the encrypted file whose lines so far are `content`
+    ///
+    /// Every existing line is decrypted, so appending with the wrong key or
+    /// to a tampered file fails.
+    ///
+    /// # Errors
+    /// * `content` is not empty and not an encrypted file of `key`
+    pub fn resume(key: Arc<StorageKey>, content: &str) -> Result<Option<Self>> {
+        let mut decoder = LineDecoder::new();
+        for (index, line) in content.lines().enumerate() {
+            decoder
+                .decode(Some(&key), line)
+                .with_context(|| format!("Unreadable line {}", index + 1))?;
+        }
+        let (file_id, next_line) = match decoder.state {
+            DecoderState::Start if content.trim().is_empty() => return Ok(None),
+            DecoderState::Encrypted { file_id, next_line } => (file_id, next_line),
+            _ => anyhow::bail!("File is not encrypted"),
+        };
+        Ok(Some(Self {
+            key,
+            file_id,
+            next_line,
+        }))
+    }
+
+    /// Header line naming the file
+    pub fn header(&self) -> String {
+        file_header(&self.file_id)
+    }
+
+    /// Encrypts the next line of the file (without its newline)
+    pub fn encrypt(&mut self, line: &str) -> Result<String> {
+        let sealed = self
+            .key
+            .encrypt(line.as_bytes(), &line_aad(&self.file_id, self.next_line))?;
+        self.next_line += 1;
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_LINE_PREFIX,
+            BASE64.encode(sealed)
+        ))
+    }
+}
+
+/// Appends lines to a stored file, encrypting them if it is encrypted
+///
+/// Used for the append-only files that outlive a run, the capture index and
+/// the audit log. A file keeps the form it was created in: appending with a
+/// key to a plaintext file, or without one to an encrypted file, fails
+/// rather than mixing both in one file.
+pub struct LineAppender {
+    file: tokio::fs::File,
+    encryptor: Option<LineEncryptor>,
+}
+
+impl LineAppender {
+    /// Opens the file at `path` for appending, creating it if needed
+    ///
+    /// # Errors
+    /// * The file is plaintext and `key` is given, or encrypted and it is not
+    /// * The file is encrypted with another key or was tampered with
+    pub async fn open(path: &Path, key: Option<Arc<StorageKey>>) -> Result<Self> {
+        let content = match tokio::fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let encryptor = match key {
+            Some(key) => Some(
+                match LineEncryptor::resume(Arc::clone(&key), &content).with_context(|| {
+                    format!(
+                        "Cannot append encrypted lines to {}; move it aside to start an encrypted file",
+                        path.display()
+                    )
+                })? {
+                    Some(encryptor) => encryptor,
+                    None => LineEncryptor::new(key),
+                },
+            ),
+            None => {
+                let first = content.lines().find(|line| !line.trim().is_empty());
+                if first.is_some_and(|line| line.starts_with(ENCRYPTED_LINE_PREFIX)) {
+                    anyhow::bail!(
+                        "{} is encrypted; a storage key is required",
+                        path.display()
+                    );
+                }
+                None
+            }
+        };
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        if let Some(encryptor) = encryptor.as_ref().filter(|_| content.trim().is_empty()) {
+            let mut header = encryptor.header();
+            header.push('\n');
+            file.write_all(header.as_bytes()).await?;
+        }
+        Ok(Self { file, encryptor })
+    }
+
+    /// Appends `line` (without its newline) and flushes it
+    pub async fn append(&mut self, line: &str) -> Result<()> {
+        let mut line = match &mut self.encryptor {
+            Some(encryptor) => encryptor.encrypt(line)?,
+            None => line.to_string(),
+        };
+        line.push('\n');
+        self.file.write_all(line.as_bytes()).await?;
+        self.file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Position of a [`LineDecoder`] in its file
+#[derive(Debug, Clone, Copy)]
+enum DecoderState {
+    /// Nothing but blank lines read yet
+    Start,
+    /// The file is plaintext
+    Plain,
+    /// The file is encrypted; `next_line` counts lines after the header
+    Encrypted { file_id: FileId, next_line: u64 },
+}
+
+/// Returns the plaintext of the lines of one stored file, in order
+///
+/// Plaintext files pass through unchanged. Encrypted files need the key;
+/// their header line and blank lines decode to `None`.
+#[derive(Debug, Clone, Copy)]
+pub struct LineDecoder {
+    state: DecoderState,
+}
+
+impl Default for LineDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineDecoder {
+    /// Starts reading a file from its first line
+    pub fn new() -> Self {
+        Self {
+            state: DecoderState::Start,
+        }
+    }
+
+    /// Decodes the next line of the file
+    ///
+    /// A line that fails to decrypt still counts, so a single damaged line
+    /// does not make the lines after it unreadable.
+    ///
+    /// # Errors
+    /// * The file is encrypted and no key, or the wrong key, is given
+    /// * The line was modified, removed, reordered or taken from another file
+    /// * A plaintext file contains encrypted lines, or an encrypted file plaintext
+    pub fn decode(&mut self, key: Option<&StorageKey>, line: &str) -> Result<Option<String>> {
+        match self.state {
+            DecoderState::Start => {
+                if let Some(file_id) = parse_file_header(line) {
+                    let file_id = file_id?;
+                    key.context("Data is encrypted; a storage key is required")?;
+                    self.state = DecoderState::Encrypted {
+                        file_id,
+                        next_line: 0,
+                    };
+                    return Ok(None);
+                }
+                if line.starts_with(ENCRYPTED_LINE_PREFIX) {
+                    anyhow::bail!("Encrypted line without a file header");
+                }
+                if !line.trim().is_empty() {
+                    self.state = DecoderState::Plain;
+                }
+                Ok(Some(line.to_string()))
+            }
+            DecoderState::Plain => {
+                if line.starts_with(ENCRYPTED_LINE_PREFIX) {
+                    anyhow::bail!("Encrypted line in a plaintext file");
+                }
+                Ok(Some(line.to_string()))
+            }
+            DecoderState::Encrypted {
+                file_id,
+                ref mut next_line,
+            } => {
+                if line.trim().is_empty() {
+                    return Ok(None);
+                }
+                let line_number = *next_line;
+                *next_line += 1;
+                let key = key.context("Data is encrypted; a storage key is required")?;
+                let encoded = line
+                    .strip_prefix(ENCRYPTED_LINE_PREFIX)
+                    .filter(|_| parse_file_header(line).is_none())
+                    .context("Unencrypted line in an encrypted file")?;
+                let sealed = BASE64
+                    .decode(encoded.trim_end())
+                    .context("Encrypted line is not valid base64")?;
+                let plaintext = key.decrypt(&sealed, &line_aad(&file_id, line_number))?;
+                String::from_utf8(plaintext)
+                    .context("Decrypted line is not UTF-8")
+                    .map(Some)
+            }
+        }
+    }
+
+    /// True once the header of an encrypted file was read
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self.state, DecoderState::Encrypted { .. })
+    }
+}
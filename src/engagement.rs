@@ -0,0 +1,106 @@
+//! Engagement metadata attributing scans to an authorization
+//!
+//! Reconnaissance should only happen under an authorized engagement. With
+//! `--engagement-id` and `--operator`, sentri stamps that attribution onto
+//! everything it produces so later review can tie activity back to it:
+//!
+//! - Every `DomainResult` carries an `engagement` object
+//! - Server jobs record the engagement they ran under; a submission may name
+//!   its own, otherwise the server's applies
+//! - Audit records name the engagement of each API request, taken from the
+//!   `X-Engagement-Id` and `X-Operator` headers or the server's defaults
+//!
+//! # Security Considerations
+//!
+//! Values end up in logs and result files, so they are validated: at most
+//! [`MAX_FIELD_LEN`] characters and no control characters
+//! (security:input:sanitize_all_input).
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Maximum length of an engagement ID or operator name
+pub const MAX_FIELD_LEN: usize = 128;
+
+/// Request header naming the engagement of an API request
+pub const ENGAGEMENT_ID_HEADER: &str = "x-engagement-id";
+
+/// Request header naming the operator behind an API request
+pub const OPERATOR_HEADER: &str = "x-operator";
+
+/// Authorized engagement and operator a scan is performed for
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Engagement {
+    /// Identifier of the engagement, e.g. a contract or ticket number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engagement_id: Option<String>,
+    /// Person or team performing the scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+}
+
+impl Engagement {
+    /// Creates engagement metadata, validating both fields
+    ///
+    /// # Returns
+    /// * `Result<Option<Self>>` - None if neither field is set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::engagement::Engagement;
+    ///
+    /// let engagement = Engagement::new(Some("ENG-2024-017".into()), Some("alice".into()))
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(engagement.engagement_id.as_deref(), Some("ENG-2024-017"));
+    /// assert!(Engagement::new(None, None).unwrap().is_none());
+    /// assert!(Engagement::new(Some("bad\nid".into()), None).is_err());
+    /// ```
+    pub fn new(engagement_id: Option<String>, operator: Option<String>) -> Result<Option<Self>> {
+        let engagement = Self {
+            engagement_id,
+            operator,
+        };
+        engagement.validate()?;
+        Ok((!engagement.is_empty()).then_some(engagement))
+    }
+
+    /// Returns true if neither field is set
+    pub fn is_empty(&self) -> bool {
+        self.engagement_id.is_none() && self.operator.is_none()
+    }
+
+    /// Checks both fields for length and control characters
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("engagement ID", &self.engagement_id),
+            ("operator", &self.operator),
+        ] {
+            let Some(value) = value else { continue };
+            if value.trim().is_empty() {
+                anyhow::bail!("The {} must not be empty", name);
+            }
+            if value.chars().count() > MAX_FIELD_LEN {
+                anyhow::bail!("The {} exceeds {} characters", name, MAX_FIELD_LEN);
+            }
+            if value.chars().any(char::is_control) {
+                anyhow::bail!("The {} contains control characters", name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills unset fields from `defaults`
+    pub fn or(self, defaults: Option<&Self>) -> Self {
+        match defaults {
+            Some(defaults) => Self {
+                engagement_id: self
+                    .engagement_id
+                    .or_else(|| defaults.engagement_id.clone()),
+                operator: self.operator.or_else(|| defaults.operator.clone()),
+            },
+            None => self,
+        }
+    }
+}
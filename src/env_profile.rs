@@ -0,0 +1,216 @@
+//! Environment-aware default tuning
+//!
+//! The built-in defaults of `--concurrent-requests`, `--chunk-size`,
+//! `--cache-size` and the number of runtime worker threads suit a typical
+//! workstation. With `--env-profile auto`, sentri instead inspects the host
+//! at startup and picks them from what it finds:
+//!
+//! - Available cores, total memory and the open file descriptor limit
+//!   ([`Resources::detect`])
+//! - Whether it runs in CI, recognized by the `CI` variable most CI systems set
+//!
+//! The host is classified as a laptop, a server or a CI runner
+//! ([`Environment`]), each with its own baseline, which is then clamped to
+//! the detected resources ([`Tuning::for_resources`]). The chosen values are
+//! logged at startup. Options given on the command line or through their
+//! `SENTRI_*` variable always win over the profile.
+//!
+//! # Performance Considerations
+//!
+//! - **Descriptors**: concurrency never exceeds what the descriptor limit
+//!   allows ([`fd_limit::max_concurrency`])
+//! - **Memory**: the result cache is bounded to about 1/64 of total memory
+
+use clap::ValueEnum;
+
+use crate::fd_limit::{self, max_concurrency};
+
+/// Rough in-memory size of a cached result, used to bound the cache
+const CACHED_RESULT_BYTES: u64 = 2 * 1024;
+
+/// Share of total memory the result cache may take, as a divisor
+const CACHE_MEMORY_DIVISOR: u64 = 64;
+
+/// Cores and memory from which a host is considered a server
+const SERVER_MIN_CORES: usize = 8;
+const SERVER_MIN_MEMORY: u64 = 16 * 1024 * 1024 * 1024;
+
+/// How defaults are chosen at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum EnvProfile {
+    /// Built-in defaults
+    #[default]
+    Off,
+    /// Defaults derived from the cores, memory and descriptor limit of the host
+    Auto,
+}
+
+/// Kind of host sentri runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Environment {
+    /// Workstation sharing its resources with interactive use
+    Laptop,
+    /// Dedicated host with many cores and plenty of memory
+    Server,
+    /// Short-lived CI runner with few cores and strict limits
+    Ci,
+}
+
+impl Environment {
+    /// Name used in logs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Environment::Laptop => "laptop",
+            Environment::Server => "server",
+            Environment::Ci => "ci",
+        }
+    }
+}
+
+/// Resources of the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resources {
+    /// Cores available to the process
+    pub cores: usize,
+    /// Total memory in bytes, if known
+    pub memory_bytes: Option<u64>,
+    /// Soft limit of open file descriptors, if known
+    pub fd_limit: Option<u64>,
+    /// True when running in CI
+    pub ci: bool,
+}
+
+impl Resources {
+    /// Inspects the running host
+    pub fn detect() -> Self {
+        Self {
+            cores: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4),
+            memory_bytes: total_memory(),
+            fd_limit: fd_limit::current().map(|limit| limit.soft),
+            ci: std::env::var("CI")
+                .is_ok_and(|value| !matches!(value.as_str(), "" | "0" | "false")),
+        }
+    }
+
+    /// Classifies the host
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::env_profile::{Environment, Resources};
+    ///
+    /// let laptop = Resources { cores: 4, memory_bytes: Some(8 << 30), fd_limit: None, ci: false };
+    /// assert_eq!(laptop.environment(), Environment::Laptop);
+    /// assert_eq!(Resources { ci: true, ..laptop }.environment(), Environment::Ci);
+    /// ```
+    pub fn environment(&self) -> Environment {
+        if self.ci {
+            Environment::Ci
+        } else if self.cores >= SERVER_MIN_CORES
+            && self
+                .memory_bytes
+                .is_some_and(|memory| memory >= SERVER_MIN_MEMORY)
+        {
+            Environment::Server
+        } else {
+            Environment::Laptop
+        }
+    }
+}
+
+/// Defaults chosen by `--env-profile auto`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tuning {
+    /// Kind of host the values were chosen for
+    pub environment: Environment,
+    /// Tokio worker threads
+    pub worker_threads: usize,
+    /// Domain checks in flight at once
+    pub concurrent_requests: usize,
+    /// Domains read per batch chunk
+    pub chunk_size: usize,
+    /// Results kept in the result cache
+    pub cache_size: usize,
+}
+
+impl Tuning {
+    /// Chooses defaults for the running host
+    pub fn detect() -> Self {
+        Self::for_resources(&Resources::detect())
+    }
+
+    /// Chooses defaults for a host with the given resources
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::env_profile::{Resources, Tuning};
+    ///
+    /// let resources = Resources { cores: 4, memory_bytes: None, fd_limit: Some(256), ci: false };
+    /// let tuning = Tuning::for_resources(&resources);
+    /// // (256 - 64) descriptors at 4 sockets per check
+    /// assert_eq!(tuning.concurrent_requests, 48);
+    /// ```
+    pub fn for_resources(resources: &Resources) -> Self {
+        let cores = resources.cores.max(1);
+        let environment = resources.environment();
+        let (worker_threads, concurrent_requests, chunk_size, cache_size) = match environment {
+            Environment::Ci => (cores.min(2), 20, 250, 10_000),
+            Environment::Laptop => ((cores + 2).min(8), 50, 500, 50_000),
+            Environment::Server => ((cores + 2).min(32), 200, 2_000, 500_000),
+        };
+
+        let concurrent_requests = match resources.fd_limit {
+            Some(limit) => concurrent_requests.min(max_concurrency(limit)),
+            None => concurrent_requests,
+        };
+        let cache_size = match resources.memory_bytes {
+            Some(memory) => {
+                let by_memory = memory / CACHE_MEMORY_DIVISOR / CACHED_RESULT_BYTES;
+                cache_size.min(usize::try_from(by_memory).unwrap_or(usize::MAX))
+            }
+            None => cache_size,
+        };
+
+        Self {
+            environment,
+            worker_threads,
+            concurrent_requests,
+            chunk_size,
+            cache_size,
+        }
+    }
+}
+
+/// Total memory from /proc/meminfo
+#[cfg(target_os = "linux")]
+fn total_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    parse_meminfo(&meminfo)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn total_memory() -> Option<u64> {
+    None
+}
+
+/// Parses the `MemTotal` line of /proc/meminfo into bytes
+///
+/// # Examples
+///
+/// ```
+/// use sentri::env_profile::parse_meminfo;
+///
+/// let meminfo = "MemTotal:       16303212 kB\nMemFree:         1234 kB\n";
+/// assert_eq!(parse_meminfo(meminfo), Some(16303212 * 1024));
+/// assert_eq!(parse_meminfo("MemFree: 1 kB"), None);
+/// ```
+pub fn parse_meminfo(meminfo: &str) -> Option<u64> {
+    let line = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?;
+    let kib: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    kib.checked_mul(1024)
+}
@@ -0,0 +1,242 @@
+//! Structured classes of scan failures
+//!
+//! Every failed [`DomainResult`] carries an [`ErrorClass`] next to its
+//! message, so that consumers can tell transient failures (timeouts, rate
+//! limiting) from permanent ones (invalid domains) without parsing text.
+//! `sentri batch --from-results old.jsonl --retry-classes timeout,rate_limited`
+//! uses the classes to re-check only the failures worth retrying.
+//!
+//! Results written before classes existed only have a message; for them the
+//! class is derived from the message by [`ErrorClass::from_message`].
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+#[cfg(feature = "dns")]
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+
+use crate::core::DomainResult;
+use crate::egress::EgressError;
+#[cfg(feature = "http")]
+use crate::http::ResponseTooLarge;
+use crate::offline::OfflineError;
+use crate::xml::{normalize_fault_message, SoapFault, INVALID_DOMAIN_ERROR, SERVER_BUSY_ERROR};
+
+/// Prefixes of the messages of domains rejected by validation
+const INVALID_DOMAIN_PREFIXES: [&str; 2] = ["invalid domain format", "suspicious domain detected"];
+
+/// Class of a scan failure
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// The domain failed validation, or the service rejected it
+    InvalidDomain,
+    /// A request or lookup did not complete in time
+    Timeout,
+    /// The remote service or the local rate limiter throttled the scan
+    RateLimited,
+    /// A DNS lookup failed
+    Dns,
+    /// No connection could be established
+    Connect,
+    /// The service answered with an unsuccessful HTTP status
+    HttpStatus,
+    /// The service answered with a SOAP fault that is neither throttling nor
+    /// a rejected domain
+    SoapFault,
+    /// The response could not be understood
+    InvalidResponse,
+    /// Network access was disabled by `--offline`
+    Offline,
+    /// The destination is not allowlisted and `--strict-egress` is enabled
+    EgressBlocked,
+    /// Any other failure
+    Other,
+}
+
+impl ErrorClass {
+    /// Classifies an error from its type, falling back to its messages
+    pub fn classify(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if cause.downcast_ref::<OfflineError>().is_some() {
+                return ErrorClass::Offline;
+            }
+            if cause.downcast_ref::<EgressError>().is_some() {
+                return ErrorClass::EgressBlocked;
+            }
+            #[cfg(feature = "http")]
+            if cause.downcast_ref::<ResponseTooLarge>().is_some() {
+                return ErrorClass::InvalidResponse;
+            }
+            if let Some(fault) = cause.downcast_ref::<SoapFault>() {
+                return if fault.is_invalid_domain() {
+                    ErrorClass::InvalidDomain
+                } else if fault.is_server_busy() {
+                    ErrorClass::RateLimited
+                } else {
+                    ErrorClass::SoapFault
+                };
+            }
+            #[cfg(feature = "http")]
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                if e.is_timeout() {
+                    return ErrorClass::Timeout;
+                }
+                if e.is_connect() {
+                    return ErrorClass::Connect;
+                }
+            }
+            #[cfg(feature = "dns")]
+            if let Some(e) = cause.downcast_ref::<ResolveError>() {
+                return match e.kind() {
+                    ResolveErrorKind::Timeout => ErrorClass::Timeout,
+                    _ => ErrorClass::Dns,
+                };
+            }
+            if cause
+                .downcast_ref::<tokio::time::error::Elapsed>()
+                .is_some()
+            {
+                return ErrorClass::Timeout;
+            }
+            if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                if e.kind() == std::io::ErrorKind::TimedOut {
+                    return ErrorClass::Timeout;
+                }
+            }
+        }
+        Self::from_message(&format!("{:#}", error))
+    }
+
+    /// Classifies an error from its message alone
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::error_class::ErrorClass;
+    ///
+    /// assert_eq!(
+    ///     ErrorClass::from_message("HTTP request failed with status: 429 Too Many Requests"),
+    ///     ErrorClass::RateLimited
+    /// );
+    /// assert_eq!(
+    ///     ErrorClass::from_message("Invalid domain format: timeout.example"),
+    ///     ErrorClass::InvalidDomain
+    /// );
+    /// ```
+    pub fn from_message(message: &str) -> Self {
+        let fault_code = message
+            .contains("SOAP fault")
+            .then(|| normalize_fault_message(message))
+            .flatten();
+        let message = message.to_ascii_lowercase();
+        let contains = |needles: &[&str]| needles.iter().any(|needle| message.contains(needle));
+
+        // Validation messages end with the domain, which may contain any word
+        let rejected_domain = INVALID_DOMAIN_PREFIXES
+            .iter()
+            .any(|prefix| message.starts_with(prefix))
+            || fault_code == Some(INVALID_DOMAIN_ERROR)
+            || (contains(&["soap fault"]) && contains(&["invaliddomain"]));
+        if rejected_domain {
+            ErrorClass::InvalidDomain
+        } else if fault_code == Some(SERVER_BUSY_ERROR) {
+            ErrorClass::RateLimited
+        } else if contains(&["disabled by --offline"]) {
+            ErrorClass::Offline
+        } else if contains(&["not allowed by --strict-egress"]) {
+            ErrorClass::EgressBlocked
+        } else if contains(&[
+            "429",
+            "too many requests",
+            "rate limit",
+            "serverbusy",
+            "server is busy",
+        ]) {
+            ErrorClass::RateLimited
+        } else if contains(&["timed out", "timeout", "deadline has elapsed"]) {
+            ErrorClass::Timeout
+        } else if contains(&["dns", "nxdomain", "no record"]) {
+            ErrorClass::Dns
+        } else if contains(&["connect", "unreachable", "connection"]) {
+            ErrorClass::Connect
+        } else if contains(&["soap fault"]) {
+            ErrorClass::SoapFault
+        } else if contains(&["failed with status"]) {
+            ErrorClass::HttpStatus
+        } else if contains(&[
+            "xml",
+            "soap",
+            "federation response",
+            "parse",
+            "exceeds the limit",
+        ]) {
+            ErrorClass::InvalidResponse
+        } else {
+            ErrorClass::Other
+        }
+    }
+
+    /// Class of a failed result; `None` if the scan succeeded
+    ///
+    /// Uses the recorded class, or derives it from the message of results
+    /// written without one.
+    pub fn of(result: &DomainResult) -> Option<Self> {
+        let error = result.error.as_deref()?;
+        Some(
+            result
+                .error_class
+                .unwrap_or_else(|| Self::from_message(error)),
+        )
+    }
+
+    /// Returns true if the failure may be caused by the scanning environment
+    ///
+    /// Such failures can go away on another resolver or autodiscover endpoint;
+    /// rejected domains and failures imposed by `--offline` or
+    /// `--strict-egress` cannot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::error_class::ErrorClass;
+    ///
+    /// assert!(ErrorClass::Timeout.is_environmental());
+    /// assert!(!ErrorClass::InvalidDomain.is_environmental());
+    /// ```
+    pub fn is_environmental(&self) -> bool {
+        !matches!(
+            self,
+            ErrorClass::InvalidDomain
+                | ErrorClass::Offline
+                | ErrorClass::EgressBlocked
+                | ErrorClass::Other
+        )
+    }
+
+    /// Name used in JSON and on the command line
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorClass::InvalidDomain => "invalid_domain",
+            ErrorClass::Timeout => "timeout",
+            ErrorClass::RateLimited => "rate_limited",
+            ErrorClass::Dns => "dns",
+            ErrorClass::Connect => "connect",
+            ErrorClass::HttpStatus => "http_status",
+            ErrorClass::SoapFault => "soap_fault",
+            ErrorClass::InvalidResponse => "invalid_response",
+            ErrorClass::Offline => "offline",
+            ErrorClass::EgressBlocked => "egress_blocked",
+            ErrorClass::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
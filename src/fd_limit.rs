@@ -0,0 +1,184 @@
+//! Open file descriptor limit preflight
+//!
+//! Every domain check in flight holds sockets for autodiscover, DNS and
+//! attribution. A `--concurrent-requests` beyond what `RLIMIT_NOFILE`
+//! allows makes connects fail with "Too many open files", which surfaces as
+//! a scattering of confusing network errors halfway through a batch.
+//!
+//! At startup, [`preflight`] compares the requested concurrency with the
+//! soft limit. With `--raise-fd-limit`, it first tries to raise the soft
+//! limit as far as needed, up to the hard limit. If the limit still does not
+//! suffice, concurrency is clamped to what it allows and a warning names
+//! both values.
+//!
+//! On platforms without resource limits the preflight does nothing.
+
+use tracing::{info, warn};
+
+/// Descriptors kept free for output files, sinks and the runtime itself
+pub const RESERVED_FDS: u64 = 64;
+
+/// Sockets a domain check holds open at most (autodiscover, DNS, attribution)
+pub const SOCKETS_PER_CHECK: u64 = 4;
+
+/// Soft and hard limit of open file descriptors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FdLimit {
+    /// Limit enforced on the process
+    pub soft: u64,
+    /// Ceiling the soft limit may be raised to without privileges
+    pub hard: u64,
+}
+
+/// Descriptors needed to run `concurrency` checks at once
+///
+/// # Examples
+///
+/// ```
+/// use sentri::fd_limit::required_fds;
+///
+/// assert_eq!(required_fds(100), 464);
+/// ```
+pub fn required_fds(concurrency: usize) -> u64 {
+    (concurrency as u64)
+        .saturating_mul(SOCKETS_PER_CHECK)
+        .saturating_add(RESERVED_FDS)
+}
+
+/// Number of checks a soft limit of `soft` descriptors allows at once, at least one
+///
+/// # Examples
+///
+/// ```
+/// use sentri::fd_limit::max_concurrency;
+///
+/// // (256 - 64) descriptors at 4 sockets per check
+/// assert_eq!(max_concurrency(256), 48);
+/// assert_eq!(max_concurrency(10), 1);
+/// ```
+pub fn max_concurrency(soft: u64) -> usize {
+    let checks = soft.saturating_sub(RESERVED_FDS) / SOCKETS_PER_CHECK;
+    usize::try_from(checks).unwrap_or(usize::MAX).max(1)
+}
+
+/// Returns the concurrency to run with under `limit`
+///
+/// # Examples
+///
+/// ```
+/// use sentri::fd_limit::{clamp_concurrency, FdLimit};
+///
+/// let limit = FdLimit { soft: 1024, hard: 4096 };
+/// assert_eq!(clamp_concurrency(100, limit), 100);
+/// assert_eq!(clamp_concurrency(1000, limit), 240);
+/// ```
+pub fn clamp_concurrency(requested: usize, limit: FdLimit) -> usize {
+    requested.min(max_concurrency(limit.soft))
+}
+
+/// Checks the descriptor limit against `concurrency` and returns the
+/// concurrency to run with
+///
+/// With `raise`, a soft limit too low for `concurrency` is first raised
+/// towards the hard limit. Clamping and raising are logged.
+pub fn preflight(concurrency: usize, raise: bool) -> usize {
+    let Some(mut limit) = current() else {
+        return concurrency;
+    };
+    let required = required_fds(concurrency);
+    if limit.soft >= required {
+        return concurrency;
+    }
+    if raise {
+        let target = required.min(limit.hard);
+        if target > limit.soft {
+            match raise_soft_limit(target) {
+                Ok(raised) => {
+                    info!(
+                        from = limit.soft,
+                        to = raised.soft,
+                        "Raised the open file descriptor limit"
+                    );
+                    limit = raised;
+                }
+                Err(e) => warn!("Failed to raise the open file descriptor limit: {}", e),
+            }
+        }
+    }
+
+    let clamped = clamp_concurrency(concurrency, limit);
+    if clamped < concurrency {
+        warn!(
+            requested = concurrency,
+            concurrency = clamped,
+            soft_limit = limit.soft,
+            hard_limit = limit.hard,
+            "--concurrent-requests exceeds the open file descriptor limit; clamping. \
+             Raise `ulimit -n` or pass --raise-fd-limit"
+        );
+    }
+    clamped
+}
+
+/// Current descriptor limits; None where unknown or unlimited
+#[cfg(unix)]
+pub fn current() -> Option<FdLimit> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit only writes to the struct passed to it
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return None;
+    }
+    if limit.rlim_cur == libc::RLIM_INFINITY {
+        return None;
+    }
+    let hard = match limit.rlim_max {
+        libc::RLIM_INFINITY => u64::MAX,
+        hard => to_u64(hard),
+    };
+    Some(FdLimit {
+        soft: to_u64(limit.rlim_cur),
+        hard,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn current() -> Option<FdLimit> {
+    None
+}
+
+/// Raises the soft descriptor limit to `target`, which must not exceed the hard limit
+#[cfg(unix)]
+#[allow(clippy::useless_conversion)] // rlim_t is narrower on some targets
+pub fn raise_soft_limit(target: u64) -> std::io::Result<FdLimit> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit and setrlimit only access the struct passed to them
+    unsafe {
+        if libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        limit.rlim_cur = libc::rlim_t::try_from(target).unwrap_or(libc::RLIM_INFINITY);
+        if libc::setrlimit(libc::RLIMIT_NOFILE, &limit) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    current().ok_or_else(|| std::io::Error::other("descriptor limit unavailable"))
+}
+
+#[cfg(not(unix))]
+pub fn raise_soft_limit(_target: u64) -> std::io::Result<FdLimit> {
+    Err(std::io::Error::other(
+        "descriptor limits are not supported on this platform",
+    ))
+}
+
+#[cfg(unix)]
+#[allow(clippy::useless_conversion)] // rlim_t is narrower on some targets
+fn to_u64(value: libc::rlim_t) -> u64 {
+    u64::from(value)
+}
@@ -0,0 +1,335 @@
+//! Graph export of tenants, domains, federation and MDI endpoints
+//!
+//! `sentri graph` turns a results file into a graph for visualization tools
+//! such as Gephi, Maltego or Graphviz; `sentri batch --format dot|graphml`
+//! writes the same graph directly at the end of a run (see
+//! [`GraphSink`](crate::sinks::graph::GraphSink)):
+//!
+//! - **Nodes** - Tenants, scanned and federated domains, and MDI instances
+//! - **Edges** - `tenant` from a domain to its tenant, `federation` from a
+//!   scanned domain to each domain federated with it, and `mdi` from a tenant
+//!   to its MDI instance
+//!
+//! The graph is written as Graphviz DOT, GraphML or the JSON Graph Format.
+//! Nodes and edges are sorted, so the same results always produce the same
+//! file. Results of failed scans only contribute their domain node.
+//!
+//! # Security Considerations
+//!
+//! Domain names and tenant identifiers come from remote responses. They are
+//! escaped for each output format so that no value can break out of its
+//! string or attribute (security:output:error_info_control).
+
+use clap::ValueEnum;
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::core::DomainResult;
+
+/// File format of an exported graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz DOT
+    #[default]
+    Dot,
+    /// GraphML, read by Gephi, yEd and Cytoscape
+    Graphml,
+    /// JSON Graph Format
+    Json,
+}
+
+/// Kind of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    /// A Microsoft tenant
+    Tenant,
+    /// A scanned or federated domain
+    Domain,
+    /// An MDI instance
+    MdiInstance,
+}
+
+impl NodeKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            NodeKind::Tenant => "tenant",
+            NodeKind::Domain => "domain",
+            NodeKind::MdiInstance => "mdi_instance",
+        }
+    }
+}
+
+/// Relation expressed by an edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// Domain belongs to a tenant
+    Tenant,
+    /// Scanned domain is federated with another domain
+    Federation,
+    /// Tenant runs an MDI instance
+    Mdi,
+}
+
+impl EdgeKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            EdgeKind::Tenant => "tenant",
+            EdgeKind::Federation => "federation",
+            EdgeKind::Mdi => "mdi",
+        }
+    }
+}
+
+/// A node of the graph
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Node {
+    /// Unique identifier, the kind and label joined by a colon
+    pub id: String,
+    /// Kind of the node
+    pub kind: NodeKind,
+    /// Tenant identifier, domain name or instance URL
+    pub label: String,
+}
+
+/// A directed edge of the graph
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Edge {
+    /// Identifier of the source node
+    pub source: String,
+    /// Identifier of the target node
+    pub target: String,
+    /// Relation between the nodes
+    pub kind: EdgeKind,
+}
+
+/// Graph of tenants, domains and MDI instances
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::graph::{Graph, GraphFormat};
+///
+/// let result: DomainResult = serde_json::from_str(r#"{
+///     "domain": "contoso.com",
+///     "tenant": "contoso",
+///     "federated_domains": ["contoso.com", "fabrikam.com"],
+///     "mdi_instance": "contososensorapi.atp.azure.com",
+///     "processing_time_ms": 120,
+///     "error": null
+/// }"#)?;
+///
+/// let graph = Graph::from_results(&[result]);
+/// assert_eq!(graph.nodes().len(), 4);
+/// assert_eq!(graph.edges().len(), 4);
+/// assert!(graph.render(GraphFormat::Dot).contains("\"domain:contoso.com\" -> \"tenant:contoso\""));
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    nodes: BTreeMap<String, Node>,
+    edges: BTreeSet<Edge>,
+}
+
+impl Graph {
+    /// Builds the graph of a set of results
+    pub fn from_results(results: &[DomainResult]) -> Self {
+        let mut graph = Self::default();
+        for result in results {
+            graph.add(result);
+        }
+        graph
+    }
+
+    /// Adds the nodes and edges of a result
+    pub fn add(&mut self, result: &DomainResult) {
+        let domain = self.add_node(NodeKind::Domain, &result.domain);
+        if result.error.is_some() {
+            return;
+        }
+
+        let tenant = result
+            .tenant
+            .as_deref()
+            .map(|tenant| self.add_node(NodeKind::Tenant, tenant));
+        if let Some(tenant) = &tenant {
+            self.add_edge(&domain, tenant, EdgeKind::Tenant);
+        }
+
+        for federated in &result.federated_domains {
+            if federated.eq_ignore_ascii_case(&result.domain) {
+                continue;
+            }
+            let federated = self.add_node(NodeKind::Domain, federated);
+            self.add_edge(&domain, &federated, EdgeKind::Federation);
+            if let Some(tenant) = &tenant {
+                self.add_edge(&federated, tenant, EdgeKind::Tenant);
+            }
+        }
+
+        if let Some(instance) = &result.mdi_instance {
+            let instance = self.add_node(NodeKind::MdiInstance, instance);
+            // Without a tenant, the instance hangs off the domain
+            let owner = tenant.as_ref().unwrap_or(&domain);
+            self.add_edge(owner, &instance, EdgeKind::Mdi);
+        }
+    }
+
+    /// Nodes, sorted by identifier
+    pub fn nodes(&self) -> Vec<&Node> {
+        self.nodes.values().collect()
+    }
+
+    /// Edges, sorted by source, target and kind
+    pub fn edges(&self) -> Vec<&Edge> {
+        self.edges.iter().collect()
+    }
+
+    /// Writes the graph in `format`
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Graphml => self.to_graphml(),
+            GraphFormat::Json => self.to_json(),
+        }
+    }
+
+    fn add_node(&mut self, kind: NodeKind, label: &str) -> String {
+        let label = match kind {
+            NodeKind::Domain => label.trim_end_matches('.').to_ascii_lowercase(),
+            _ => label.to_string(),
+        };
+        let id = format!("{}:{}", kind.as_str(), label);
+        self.nodes.entry(id.clone()).or_insert(Node {
+            id: id.clone(),
+            kind,
+            label,
+        });
+        id
+    }
+
+    fn add_edge(&mut self, source: &str, target: &str, kind: EdgeKind) {
+        self.edges.insert(Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            kind,
+        });
+    }
+
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph sentri {\n");
+        for node in self.nodes.values() {
+            let shape = match node.kind {
+                NodeKind::Tenant => "box",
+                NodeKind::Domain => "ellipse",
+                NodeKind::MdiInstance => "diamond",
+            };
+            let _ = writeln!(
+                dot,
+                "  {} [label={}, kind={}, shape={}];",
+                dot_string(&node.id),
+                dot_string(&node.label),
+                node.kind.as_str(),
+                shape
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                dot,
+                "  {} -> {} [label={}];",
+                dot_string(&edge.source),
+                dot_string(&edge.target),
+                edge.kind.as_str()
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn to_graphml(&self) -> String {
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+            "  <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+            "  <key id=\"relation\" for=\"edge\" attr.name=\"relation\" attr.type=\"string\"/>\n",
+            "  <graph id=\"sentri\" edgedefault=\"directed\">\n",
+        ));
+        for node in self.nodes.values() {
+            let _ = writeln!(
+                xml,
+                "    <node id=\"{}\"><data key=\"label\">{}</data><data key=\"kind\">{}</data></node>",
+                encode_double_quoted_attribute(&node.id),
+                encode_text(&node.label),
+                node.kind.as_str()
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                xml,
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"relation\">{}</data></edge>",
+                encode_double_quoted_attribute(&edge.source),
+                encode_double_quoted_attribute(&edge.target),
+                edge.kind.as_str()
+            );
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+
+    fn to_json(&self) -> String {
+        let nodes: serde_json::Map<String, serde_json::Value> = self
+            .nodes
+            .values()
+            .map(|node| {
+                (
+                    node.id.clone(),
+                    json!({ "label": node.label, "metadata": { "kind": node.kind } }),
+                )
+            })
+            .collect();
+        let edges: Vec<serde_json::Value> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                json!({
+                    "source": edge.source,
+                    "target": edge.target,
+                    "relation": edge.kind,
+                })
+            })
+            .collect();
+        let graph = json!({
+            "graph": {
+                "id": "sentri",
+                "directed": true,
+                "nodes": nodes,
+                "edges": edges,
+            }
+        });
+        format!("{:#}\n", graph)
+    }
+}
+
+/// Quotes a DOT identifier
+fn dot_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' | '\r' => quoted.push(' '),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
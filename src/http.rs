@@ -7,9 +7,10 @@
 //! - TCP keepalive for connection reuse
 //! - Built-in rate limiting to respect Microsoft API constraints
 //! - Automatic retries with exponential backoff
-//! - Error classification for better failure handling
+//! - Error classification for better failure handling, including SOAP faults
 //! - Configurable TLS certificate validation
 //! - Configurable redirect limits for security
+//! - Transparent gzip, deflate and brotli decompression with a size cap
 //!
 //! # Security Considerations
 //!
@@ -25,18 +26,38 @@
 //! - **Redirect Limits**: Redirects are limited to prevent redirect loops and potential
 //!   security issues. This limit is configurable (security:network:limit_redirect_follows).
 //!
+//! - **Egress Allowlist**: With `--strict-egress`, requests and redirects to hosts outside
+//!   the allowlist of [`crate::egress`] fail before any connection is made.
+//!
 //! - **Timeout Enforcement**: All network operations have mandatory timeouts to prevent
 //!   resource exhaustion (security:network:timeout_all_requests).
+//!
+//! - **Decompression Limits**: Compressed responses are decompressed while they are read and
+//!   abandoned once they exceed a maximum decompressed size, so a small compressed body cannot
+//!   expand into gigabytes of memory (security:input:limit_input_size).
 
 use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_LANGUAGE, CONTENT_TYPE};
 use reqwest::{Client, ClientBuilder, StatusCode};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tracing::{debug, info, warn};
 
+use crate::dns_override::{DnsOverrides, Override};
+use crate::egress::ensure_url_allowed;
+use crate::error_class::ErrorClass;
+use crate::latency::LatencyTracker;
+use crate::logging::LogSampler;
+use crate::offline::ensure_online;
 use crate::rate_limit::{create_microsoft_api_limiter, RateLimiter};
 use crate::retry::{with_exponential_backoff, RetryConfig};
+use crate::time::Stopwatch;
+use crate::trace::span;
+use crate::xml::{decode_response, parse_soap_fault, SoapFault};
 
 /// High-performance HTTP client for Microsoft API interactions
 ///
@@ -99,8 +120,66 @@ pub struct HttpClient {
     autodiscover_url: String,
     retry_config: RetryConfig,
     rate_limiter: Arc<RateLimiter>,
+    log_sampler: Arc<LogSampler>,
+    max_decompressed_size: usize,
+    max_response_size: usize,
+    latency: Option<Arc<LatencyTracker>>,
+}
+
+/// Autodiscover endpoint federation information is requested from
+pub const DEFAULT_AUTODISCOVER_URL: &str =
+    "https://autodiscover-s.outlook.com/autodiscover/autodiscover.svc";
+
+/// Second autodiscover endpoint answering the same federation requests
+///
+/// Served from another host name and front end, it is the endpoint of the
+/// roaming retry (see [`crate::core::MdiChecker::with_roaming`]).
+pub const ALTERNATE_AUTODISCOVER_URL: &str =
+    "https://outlook.office365.com/autodiscover/autodiscover.svc";
+
+/// Default `Accept-Language` of every request
+///
+/// Autodiscover localizes fault strings; pinning the language keeps them
+/// comparable across scanning hosts (see [`crate::xml::normalize_fault_message`]).
+pub const DEFAULT_ACCEPT_LANGUAGE: &str = "en-US";
+
+/// Default maximum size of an autodiscover SOAP response body (1 MiB)
+///
+/// Federation responses list the domains of one tenant and stay far below
+/// this, even for tenants with thousands of domains.
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 1024 * 1024;
+
+/// Error of a response body larger than the configured limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseTooLarge {
+    /// The exceeded limit in bytes
+    pub limit: usize,
+    /// Size announced by the Content-Length header, if the body was rejected
+    /// before it was read
+    pub content_length: Option<u64>,
+}
+
+impl fmt::Display for ResponseTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.content_length {
+            Some(length) => write!(
+                f,
+                "Response body of {} bytes exceeds the limit of {} bytes",
+                length, self.limit
+            ),
+            None => write!(f, "Response body exceeds the limit of {} bytes", self.limit),
+        }
+    }
 }
 
+impl std::error::Error for ResponseTooLarge {}
+
+/// Default maximum size of a response body after decompression (32 MiB)
+///
+/// Autodiscover responses are a few kilobytes; the largest bodies read are
+/// downloads of reference data such as Microsoft service tags.
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 32 * 1024 * 1024;
+
 /// Builder for configuring and constructing an HttpClient
 ///
 /// This builder provides fine-grained control over the HTTP client configuration,
@@ -144,6 +223,13 @@ pub struct HttpClientBuilder {
     pool_max_idle_per_host: usize,
     pool_idle_timeout: Duration,
     tcp_keepalive: Duration,
+    http2_prior_knowledge: bool,
+    dns_overrides: Option<Arc<DnsOverrides>>,
+    compression: bool,
+    max_decompressed_size: usize,
+    max_response_size: usize,
+    accept_language: String,
+    autodiscover_url: String,
 }
 
 impl Default for HttpClientBuilder {
@@ -165,6 +251,18 @@ impl Default for HttpClientBuilder {
             pool_max_idle_per_host: 50,
             pool_idle_timeout: Duration::from_secs(30),
             tcp_keepalive: Duration::from_secs(60),
+            // Autodiscover endpoints speak HTTP/2 directly
+            http2_prior_knowledge: true,
+            dns_overrides: None,
+            // Compressed transfer is accepted, with a cap on the decompressed size
+            // (security:input:limit_input_size)
+            compression: true,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            // Hard cap on SOAP response bodies (security:input:limit_input_size)
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            // Pinned so fault strings are not localized by the scanning host's locale
+            accept_language: DEFAULT_ACCEPT_LANGUAGE.to_string(),
+            autodiscover_url: DEFAULT_AUTODISCOVER_URL.to_string(),
         }
     }
 }
@@ -302,33 +400,167 @@ impl HttpClientBuilder {
         self
     }
 
+    /// Controls whether HTTP/2 is assumed without ALPN negotiation
+    ///
+    /// Prior knowledge is enabled by default because Microsoft's autodiscover
+    /// endpoints speak HTTP/2 directly. Clients talking to third-party
+    /// endpoints, such as result sinks, should disable it so that HTTP/1.1-only
+    /// servers remain reachable.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether to use HTTP/2 prior knowledge (default: true)
+    ///
+    /// # Returns
+    /// * `Self` - The builder with HTTP version negotiation configured
+    pub fn http2_prior_knowledge(mut self, enabled: bool) -> Self {
+        self.http2_prior_knowledge = enabled;
+        self
+    }
+
+    /// Connects to the hosts listed in `overrides` at their fixed addresses
+    ///
+    /// Requests to a host overridden as `NXDOMAIN` fail without a connection
+    /// attempt. See [`crate::dns_override`].
+    ///
+    /// # Arguments
+    /// * `overrides` - Host names answered without real resolution
+    ///
+    /// # Returns
+    /// * `Self` - The builder with DNS overrides configured
+    pub fn dns_overrides(mut self, overrides: Arc<DnsOverrides>) -> Self {
+        self.dns_overrides = Some(overrides);
+        self
+    }
+
+    /// Controls whether compressed responses are requested
+    ///
+    /// When enabled (the default), requests advertise gzip, deflate and brotli
+    /// in `Accept-Encoding` and compressed responses are decompressed
+    /// transparently as they are read.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether to accept compressed responses
+    ///
+    /// # Returns
+    /// * `Self` - The builder with compression configured
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// Sets the maximum size of a response body after decompression
+    ///
+    /// Reading a body stops with a [`ResponseTooLarge`] error as soon as it
+    /// exceeds the limit, which protects batch runs from decompression bombs.
+    /// Uncompressed bodies are subject to the same limit.
+    ///
+    /// # Arguments
+    /// * `bytes` - Maximum decompressed size in bytes
+    ///   (default: [`DEFAULT_MAX_DECOMPRESSED_SIZE`])
+    ///
+    /// # Returns
+    /// * `Self` - The builder with the size limit configured
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sentri::http::HttpClient;
+    /// let client = HttpClient::builder()
+    ///     .max_decompressed_size(1024 * 1024)
+    ///     .build()
+    ///     .expect("Failed to create HTTP client");
+    /// ```
+    pub fn max_decompressed_size(mut self, bytes: usize) -> Self {
+        self.max_decompressed_size = bytes;
+        self
+    }
+
+    /// Sets the maximum size of an autodiscover SOAP response body
+    ///
+    /// `post_soap_request` rejects responses announcing a larger body before
+    /// reading them and stops reading once the body grows past the limit, so
+    /// hundreds of concurrent requests cannot consume unbounded memory.
+    ///
+    /// # Arguments
+    /// * `bytes` - Maximum body size in bytes (default: [`DEFAULT_MAX_RESPONSE_SIZE`])
+    ///
+    /// # Returns
+    /// * `Self` - The builder with the size limit configured
+    pub fn max_response_size(mut self, bytes: usize) -> Self {
+        self.max_response_size = bytes;
+        self
+    }
+
+    /// Sets the `Accept-Language` header sent with every request
+    ///
+    /// # Arguments
+    /// * `language` - Language range such as `en-US` (default: [`DEFAULT_ACCEPT_LANGUAGE`])
+    ///
+    /// # Returns
+    /// * `Self` - The builder with the language configured
+    pub fn accept_language(mut self, language: impl Into<String>) -> Self {
+        self.accept_language = language.into();
+        self
+    }
+
+    /// Sets the autodiscover endpoint federation requests are sent to
+    ///
+    /// # Arguments
+    /// * `url` - HTTPS URL of the endpoint (default: [`DEFAULT_AUTODISCOVER_URL`])
+    ///
+    /// # Returns
+    /// * `Self` - The builder with the endpoint configured
+    pub fn autodiscover_url(mut self, url: impl Into<String>) -> Self {
+        self.autodiscover_url = url.into();
+        self
+    }
+
     /// Builds the HttpClient with the configured settings
     ///
     /// # Returns
     /// * `Result<HttpClient>` - The configured client or error if build failed
     ///
     /// # Errors
-    /// * Returns error if client creation fails
+    /// * Returns error if client creation fails or the language is not a valid header value
     pub fn build(self) -> Result<HttpClient> {
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(
+            ACCEPT_LANGUAGE,
+            HeaderValue::from_str(&self.accept_language)
+                .with_context(|| format!("Invalid Accept-Language {:?}", self.accept_language))?,
+        );
+
         let mut builder = ClientBuilder::new()
+            .default_headers(default_headers)
             .timeout(self.timeout)
             .user_agent(&self.user_agent)
             .pool_max_idle_per_host(self.pool_max_idle_per_host)
             .pool_idle_timeout(self.pool_idle_timeout)
             .tcp_keepalive(self.tcp_keepalive)
             .danger_accept_invalid_certs(!self.verify_certificates)
-            .https_only(true) // Force HTTPS for security
-            .http2_prior_knowledge();
-
-        // Configure redirect policy
-        if self.max_redirects > 0 {
-            builder = builder.redirect(reqwest::redirect::Policy::limited(
-                self.max_redirects as usize,
-            ));
-        } else {
-            builder = builder.redirect(reqwest::redirect::Policy::none());
+            .gzip(self.compression)
+            .deflate(self.compression)
+            .brotli(self.compression)
+            .https_only(true); // Force HTTPS for security
+
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
         }
 
+        // Configure redirect policy; redirect targets must pass --strict-egress too
+        let redirects = if self.max_redirects > 0 {
+            reqwest::redirect::Policy::limited(self.max_redirects as usize)
+        } else {
+            reqwest::redirect::Policy::none()
+        };
+        builder = builder.redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            let url = attempt.url().to_string();
+            match ensure_url_allowed(&url, format_args!("redirect to {}", url)) {
+                Ok(()) => redirects.redirect(attempt),
+                Err(e) => attempt.error(e),
+            }
+        }));
+
         // Set minimum TLS version if specified
         if let Some(version) = self.min_tls_version {
             builder = builder.min_tls_version(version);
@@ -341,6 +573,19 @@ impl HttpClientBuilder {
             builder = builder.pool_idle_timeout(None);
         }
 
+        // Apply DNS overrides; the port of the URL is used, not this one
+        for (host, answer) in self
+            .dns_overrides
+            .iter()
+            .flat_map(|overrides| overrides.iter())
+        {
+            let addresses: Vec<SocketAddr> = match answer {
+                Override::Addresses(ips) => ips.iter().map(|ip| SocketAddr::new(*ip, 0)).collect(),
+                Override::NxDomain => Vec::new(),
+            };
+            builder = builder.resolve_to_addrs(host, &addresses);
+        }
+
         let client = builder.build().context("Failed to create HTTP client")?;
 
         // Create a rate limiter following Microsoft's recommended limits
@@ -348,10 +593,13 @@ impl HttpClientBuilder {
 
         Ok(HttpClient {
             client,
-            autodiscover_url: "https://autodiscover-s.outlook.com/autodiscover/autodiscover.svc"
-                .to_string(),
+            autodiscover_url: self.autodiscover_url,
             retry_config: RetryConfig::default(),
             rate_limiter,
+            log_sampler: Arc::new(LogSampler::default()),
+            max_decompressed_size: self.max_decompressed_size,
+            max_response_size: self.max_response_size,
+            latency: None,
         })
     }
 }
@@ -445,16 +693,38 @@ impl HttpClient {
         self
     }
 
+    /// Shares a log sampler limiting repetitive failure logs
+    ///
+    /// # Arguments
+    /// * `sampler` - Sampler shared with the other components of a checker
+    pub fn with_log_sampler(mut self, sampler: Arc<LogSampler>) -> Self {
+        self.log_sampler = sampler;
+        self
+    }
+
+    /// Records the latency of every request and time-boxes slow hosts
+    ///
+    /// # Arguments
+    /// * `tracker` - Tracker shared with the checker reporting its findings
+    pub fn with_latency_tracker(mut self, tracker: Arc<LatencyTracker>) -> Self {
+        self.latency = Some(tracker);
+        self
+    }
+
     /// Sets a custom retry configuration for the HTTP client
     ///
     /// # Arguments
     /// * `config` - The retry configuration to use
-    #[allow(dead_code)]
     pub fn with_retry_config(mut self, config: RetryConfig) -> Self {
         self.retry_config = config;
         self
     }
 
+    /// Retry configuration of autodiscover requests
+    pub fn retry_config(&self) -> &RetryConfig {
+        &self.retry_config
+    }
+
     /// Determines if a response error is retriable
     ///
     /// # Arguments
@@ -469,6 +739,36 @@ impl HttpClient {
         status.as_u16() == 429 || status.is_server_error()
     }
 
+    /// Determines if a failed request is sent again
+    ///
+    /// Server-busy SOAP faults, timeouts, connection failures and retriable
+    /// statuses are retried; faults rejecting the domain are not.
+    ///
+    /// # Arguments
+    /// * `err` - The error of the failed attempt
+    ///
+    /// # Returns
+    /// True if the request should be retried, false otherwise
+    pub fn is_retriable_error(&self, err: &anyhow::Error) -> bool {
+        // Server-busy faults are transient, a rejected domain is not
+        if let Some(fault) = err.downcast_ref::<SoapFault>() {
+            return fault.is_retriable();
+        }
+
+        // Check if this is an error with a status code we can retry on
+        if let Some(status) = err
+            .chain()
+            .filter_map(|e| e.downcast_ref::<reqwest::Error>())
+            .filter_map(|e| e.status())
+            .next()
+        {
+            return self.is_retriable_status(status);
+        }
+
+        // Network errors, timeouts, etc. are all retriable
+        matches!(err.downcast_ref::<reqwest::Error>(), Some(e) if e.is_timeout() || e.is_connect())
+    }
+
     /// Sends a SOAP request to the autodiscover endpoint with exponential backoff retries
     ///
     /// This method handles the complete request workflow:
@@ -505,43 +805,315 @@ impl HttpClient {
     /// # }
     /// ```
     pub async fn post_soap_request(&self, body: &str) -> Result<String> {
-        debug!("Sending SOAP request to autodiscover endpoint");
+        debug!(url = %self.autodiscover_url, "Sending SOAP request");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/xml; charset=utf-8"),
+        );
+        headers.insert(
+            "SOAPAction",
+            HeaderValue::from_static("http://schemas.microsoft.com/exchange/2010/Autodiscover/Autodiscover/GetFederationInformation"),
+        );
+
+        let response = self
+            .send_post(&self.autodiscover_url, headers, body)
+            .await
+            .context("Failed to send SOAP request")?;
+        // Refuse announced oversized bodies without reading them
+        if let Some(length) = response.content_length() {
+            if length > self.max_response_size as u64 {
+                return Err(anyhow::Error::new(ResponseTooLarge {
+                    limit: self.max_response_size,
+                    content_length: Some(length),
+                }))
+                .context("SOAP response rejected");
+            }
+        }
+        let content_type = content_type_of(&response);
+        let bytes = self
+            .read_body(response, self.max_response_size)
+            .await
+            .context("SOAP response rejected")?;
+        // Proxies may re-encode the body; parse it as UTF-8 regardless
+        let response_text = decode_response(&bytes, content_type.as_deref())
+            .context("Failed to decode SOAP response")?;
+
+        debug!(bytes = response_text.len(), "Received SOAP response");
+        Ok(response_text)
+    }
+
+    /// Sends a POST request to an arbitrary endpoint with rate limiting and retries
+    ///
+    /// This is the shared request path used by the autodiscover SOAP calls and
+    /// by result sinks that deliver findings to external services. It applies
+    /// the same rate limiting, exponential backoff and retry classification as
+    /// `post_soap_request`.
+    ///
+    /// # Arguments
+    /// * `url` - The HTTPS endpoint to send the request to
+    /// * `headers` - Request headers, including the content type
+    /// * `body` - The request body
+    ///
+    /// # Returns
+    /// * `Result<String>` - The response text or error
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sentri::http::HttpClient;
+    /// # use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+    /// # use std::time::Duration;
+    /// # async fn example() -> anyhow::Result<()> {
+    /// let client = HttpClient::builder()
+    ///     .timeout(Duration::from_secs(10))
+    ///     .http2_prior_knowledge(false)
+    ///     .build()?;
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    ///
+    /// let response = client
+    ///     .post("https://collector.example.com/ingest", headers, r#"{"ok":true}"#)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn post(&self, url: &str, headers: HeaderMap, body: &str) -> Result<String> {
+        let response = self.send_post(url, headers, body).await?;
+        let body = self.read_body(response, self.max_decompressed_size).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Sends a GET request with rate limiting and retries
+    ///
+    /// Used to download reference data such as Microsoft service tags. Applies
+    /// the same rate limiting, exponential backoff and retry classification as
+    /// `post`.
+    ///
+    /// # Arguments
+    /// * `url` - The HTTPS endpoint to fetch
+    ///
+    /// # Returns
+    /// * `Result<String>` - The response text or error
+    pub async fn get(&self, url: &str) -> Result<String> {
+        ensure_online(format_args!("GET {}", url))?;
+        ensure_url_allowed(url, format_args!("GET {}", url))?;
+        debug!(url, "Sending GET request");
+        let response = self.execute(|| self.client.get(url)).await?;
+        let body = self.read_body(response, self.max_decompressed_size).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Checks that the autodiscover endpoint answers over HTTPS
+    ///
+    /// Sends a single GET without rate limiting or retries, so health checks
+    /// neither wait behind scans nor retry against a failing endpoint. Any
+    /// HTTP response counts as reachable; the service answers plain GETs with
+    /// an error status.
+    ///
+    /// # Returns
+    /// * `Result<reqwest::StatusCode>` - Status of the response, or the connection error
+    pub async fn probe_autodiscover(&self) -> Result<reqwest::StatusCode> {
+        ensure_online(format_args!("GET {}", self.autodiscover_url))?;
+        ensure_url_allowed(
+            &self.autodiscover_url,
+            format_args!("GET {}", self.autodiscover_url),
+        )?;
+        let response = self
+            .client
+            .get(&self.autodiscover_url)
+            .send()
+            .await
+            .context("Autodiscover endpoint unreachable")?;
+        Ok(response.status())
+    }
+
+    /// Latency tracker of the client, if latencies are recorded
+    pub fn latency_tracker(&self) -> Option<&Arc<LatencyTracker>> {
+        self.latency.as_ref()
+    }
+
+    /// Host name of the autodiscover endpoint
+    pub fn autodiscover_host(&self) -> Option<String> {
+        reqwest::Url::parse(&self.autodiscover_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+    }
+
+    /// Reads the (decompressed) body of `response`, up to `limit` bytes
+    ///
+    /// The body is read chunk by chunk so that an oversized body is abandoned
+    /// without being buffered completely (security:input:limit_input_size).
+    /// The limit never exceeds the maximum decompressed size.
+    async fn read_body(&self, mut response: reqwest::Response, limit: usize) -> Result<Vec<u8>> {
+        let limit = limit.min(self.max_decompressed_size);
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .context("Failed to read response body")?
+        {
+            if body.len() + chunk.len() > limit {
+                return Err(anyhow::Error::new(ResponseTooLarge {
+                    limit,
+                    content_length: None,
+                }));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
 
+    /// Sends a single request, recording its latency per host
+    ///
+    /// Requests to a host flagged as slow by the latency tracker are
+    /// time-boxed to the tracker's threshold (see [`crate::latency`]).
+    async fn send_timed(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let Some(tracker) = &self.latency else {
+            return builder.send().await.context("Failed to send request");
+        };
+        let request = builder.build().context("Failed to send request")?;
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let time_box = tracker.time_box(&host);
+
+        let started = Instant::now();
+        let sent = self.client.execute(request);
+        let result = match time_box {
+            Some(limit) => match tokio::time::timeout(limit, sent).await {
+                Ok(result) => result.context("Failed to send request"),
+                Err(elapsed) => Err(anyhow::Error::new(elapsed).context(format!(
+                    "Request to slow host {} time-boxed after {} ms",
+                    host,
+                    limit.as_millis()
+                ))),
+            },
+            None => sent.await.context("Failed to send request"),
+        };
+
+        // Answers and timeouts measure the host; other failures say nothing about it
+        let timed_out = match &result {
+            Ok(_) => false,
+            Err(e) => ErrorClass::classify(e) == ErrorClass::Timeout,
+        };
+        if result.is_ok() || timed_out {
+            if let Some(finding) = tracker.record(&host, started.elapsed()) {
+                warn!(
+                    host = %finding.host,
+                    p95_ms = finding.p95_ms,
+                    threshold_ms = finding.threshold_ms,
+                    penalty_secs = tracker.config().penalty.as_secs(),
+                    "Slow host flagged; its requests are time-boxed"
+                );
+            }
+        }
+        result
+    }
+
+    /// Sends a POST request, applying rate limiting and retries
+    async fn send_post(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+        body: &str,
+    ) -> Result<reqwest::Response> {
+        ensure_online(format_args!("POST {}", url))?;
+        ensure_url_allowed(url, format_args!("POST {}", url))?;
+        debug!(url, "Sending POST request");
+        self.execute(|| {
+            self.client
+                .post(url)
+                .headers(headers.clone())
+                .body(body.to_string())
+        })
+        .await
+    }
+
+    /// Sends a request built by `build`, applying rate limiting and retries
+    ///
+    /// The builder is invoked once per attempt so every retry sends a fresh request.
+    ///
+    /// # Returns
+    /// * `Result<reqwest::Response>` - The successful response, body unread
+    async fn execute<F>(&self, build: F) -> Result<reqwest::Response>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
         // Acquire rate limit permit before proceeding
-        debug!("Acquiring rate limit permit");
+        let stopwatch = Stopwatch::start();
+        let wait = span("rate_limit_wait");
         let _permit = self.rate_limiter.acquire().await?;
-        debug!("Rate limit permit acquired, proceeding with request");
+        drop(wait);
+        debug!(
+            wait_ms = stopwatch.elapsed_ms(),
+            "Rate limit permit acquired"
+        );
 
-        let body_owned = body.to_string();
-        let client = self.client.clone();
-        let url = self.autodiscover_url.clone();
         let retry_config = &self.retry_config;
+        let attempts = AtomicU32::new(0);
 
         // Use exponential backoff for the request
         let response = with_exponential_backoff(
             || async {
-                let resp = client
-                    .post(&url)
-                    .header("Content-Type", "text/xml; charset=utf-8")
-                    .header("SOAPAction", "http://schemas.microsoft.com/exchange/2010/Autodiscover/Autodiscover/GetFederationInformation")
-                    .body(body_owned.clone())
-                    .send()
-                    .await
-                    .context("Failed to send SOAP request")?;
+                let mut attempt = span("http_attempt");
+                attempt.arg("attempt", attempts.fetch_add(1, Ordering::Relaxed) + 1);
+                let resp = self.send_timed(build()).await;
+                match &resp {
+                    Ok(resp) => attempt.arg("status", resp.status().as_u16()),
+                    Err(e) => attempt.arg("error_class", ErrorClass::classify(e).as_str()),
+                }
+                let resp = resp?;
 
                 // Check if the response status indicates success
                 if !resp.status().is_success() {
                     let status = resp.status();
+                    // SOAP services answer faults with an error status; keep the
+                    // fault so it can decide whether the request is retried
+                    let content_type = content_type_of(&resp);
+                    let fault = self
+                        .read_body(resp, self.max_response_size)
+                        .await
+                        .ok()
+                        .and_then(|bytes| decode_response(&bytes, content_type.as_deref()).ok())
+                        .as_deref()
+                        .and_then(parse_soap_fault);
+                    if let Some(fault) = fault {
+                        if let Some(occurrences) = self.log_sampler.sample("http.soap_fault") {
+                            warn!(
+                                status = status.as_u16(),
+                                fault = %fault,
+                                retriable = fault.is_retriable(),
+                                occurrences,
+                                "SOAP fault received"
+                            );
+                        }
+                        return Err(anyhow::Error::new(fault)
+                            .context(format!("HTTP request failed with status: {}", status)));
+                    }
                     let err = anyhow::anyhow!("HTTP request failed with status: {}", status);
 
                     // Log different messages based on status code
                     if status.as_u16() == 429 {
-                        warn!("Rate limit exceeded, will retry: {}", status);
+                        if let Some(occurrences) = self.log_sampler.sample("http.rate_limited") {
+                            warn!(
+                                status = status.as_u16(),
+                                occurrences, "Rate limit exceeded, will retry"
+                            );
+                        }
                     } else if status.is_server_error() {
-                        warn!("Server error, will retry: {}", status);
-                    } else {
+                        if let Some(occurrences) = self.log_sampler.sample("http.server_error") {
+                            warn!(
+                                status = status.as_u16(),
+                                occurrences, "Server error, will retry"
+                            );
+                        }
+                    } else if let Some(occurrences) = self.log_sampler.sample("http.client_error") {
                         // Client errors (4xx) other than 429 are not generally retriable
-                        info!("Non-retriable client error: {}", status);
+                        info!(
+                            status = status.as_u16(),
+                            occurrences, "Non-retriable client error"
+                        );
                     }
 
                     return Err(err);
@@ -549,29 +1121,20 @@ impl HttpClient {
 
                 Ok(resp)
             },
-            |err| {
-                // Check if this is an error with a status code we can retry on
-                if let Some(status) = err.chain()
-                    .filter_map(|e| e.downcast_ref::<reqwest::Error>())
-                    .filter_map(|e| e.status())
-                    .next()
-                {
-                    return self.is_retriable_status(status);
-                }
-
-                // Network errors, timeouts, etc. are all retriable
-                matches!(err.downcast_ref::<reqwest::Error>(), Some(e) if e.is_timeout() || e.is_connect())
-            },
+            |err| self.is_retriable_error(err),
             retry_config,
         )
         .await?;
 
-        let response_text = response
-            .text()
-            .await
-            .context("Failed to read response body")?;
-
-        debug!("Received SOAP response");
-        Ok(response_text)
+        Ok(response)
     }
 }
+
+/// Value of the Content-Type header of `response`, if it is valid text
+fn content_type_of(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
@@ -0,0 +1,392 @@
+//! Asynchronous scan jobs for server mode
+//!
+//! A job is a one-off scan of a submitted domain list. Submitting returns
+//! immediately with a job ID; the scan runs in the background, reports its
+//! progress as a [`BatchSummary`] and can be cancelled at any time. Results
+//! are appended to a JSONL file as each chunk completes, so they can be
+//! fetched while the job is still running.
+//!
+//! Completed results and the final status are also broadcast as [`JobEvent`]s
+//! so clients can follow a job live (see [`JobManager::subscribe`]).
+//!
+//! Job status is kept in memory and does not survive a restart; result files
+//! remain in the state directory.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+use crate::core::{BatchSummary, DomainResult, MdiChecker};
+use crate::encryption::StorageKey;
+use crate::engagement::Engagement;
+use crate::rate_limit::RateBudget;
+use crate::sanitize::sanitize_domain_result;
+use crate::sinks::stored_results_sink;
+use crate::validation::validate_domain;
+
+/// Largest domain list accepted for a single job
+pub const MAX_JOB_DOMAINS: usize = 100_000;
+
+/// Events buffered per job before slow subscribers start missing results
+const EVENT_BUFFER: usize = 1024;
+
+/// Lifecycle state of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// Submitted, not yet started
+    Queued,
+    /// Scanning
+    Running,
+    /// Every domain was scanned
+    Completed,
+    /// Stopped on request; results cover the domains scanned until then
+    Cancelled,
+    /// Stopped by an error
+    Failed,
+}
+
+impl JobState {
+    /// Returns true once the job can no longer change
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobState::Completed | JobState::Cancelled | JobState::Failed
+        )
+    }
+}
+
+/// Domain list submitted as a job
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JobRequest {
+    /// Domains to scan
+    pub domains: Vec<String>,
+    /// Engagement the job runs under (`engagement_id`, `operator`); unset
+    /// fields default to the server's
+    #[serde(flatten)]
+    pub engagement: Engagement,
+}
+
+/// Externally visible status of a job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+    /// Unique identifier
+    pub id: String,
+    /// Name of the API key that submitted the job
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Lifecycle state
+    pub state: JobState,
+    /// Number of submitted domains
+    pub total: usize,
+    /// Counts over the domains scanned so far
+    pub progress: BatchSummary,
+    /// When the job was submitted
+    pub created_at: DateTime<Utc>,
+    /// When the job finished, if it has
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Reason the job failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Engagement the job runs under, stamped onto its results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engagement: Option<Engagement>,
+}
+
+/// Live update published while a job runs
+#[derive(Debug, Clone)]
+pub enum JobEvent {
+    /// A domain finished scanning
+    Result(Box<DomainResult>),
+    /// The job finished; no further events follow
+    Finished(Box<JobStatus>),
+}
+
+struct JobEntry {
+    status: JobStatus,
+    cancel: CancellationToken,
+    events: broadcast::Sender<JobEvent>,
+}
+
+/// Runs jobs and tracks their status
+pub struct JobManager {
+    checker: MdiChecker,
+    budget: Arc<RateBudget>,
+    results_dir: PathBuf,
+    storage_key: Option<Arc<StorageKey>>,
+    jobs: Mutex<HashMap<String, JobEntry>>,
+}
+
+impl JobManager {
+    /// Creates a manager writing results below `state_dir/jobs`
+    ///
+    /// # Arguments
+    /// * `checker` - Checker used for job scans
+    /// * `state_dir` - Server state directory
+    /// * `budget` - Rate budget; jobs draw from their owner's share
+    pub async fn new(
+        checker: MdiChecker,
+        state_dir: &Path,
+        budget: Arc<RateBudget>,
+    ) -> Result<Self> {
+        let results_dir = state_dir.join("jobs");
+        tokio::fs::create_dir_all(&results_dir)
+            .await
+            .with_context(|| format!("Failed to create {}", results_dir.display()))?;
+        Ok(Self {
+            checker,
+            budget,
+            results_dir,
+            storage_key: None,
+            jobs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Encrypts result files with the given key
+    pub fn with_storage_key(mut self, key: Arc<StorageKey>) -> Self {
+        self.storage_key = Some(key);
+        self
+    }
+
+    /// Key result files are encrypted with, if any
+    pub fn storage_key(&self) -> Option<&StorageKey> {
+        self.storage_key.as_deref()
+    }
+
+    /// Checker used for job scans
+    pub fn checker(&self) -> &MdiChecker {
+        &self.checker
+    }
+
+    /// Rate budget jobs draw from
+    pub fn budget(&self) -> &RateBudget {
+        &self.budget
+    }
+
+    /// Validates a request and starts the job in the background
+    ///
+    /// # Errors
+    /// * The domain list is empty, too large or contains an invalid domain
+    pub async fn submit(
+        self: &Arc<Self>,
+        request: JobRequest,
+        owner: Option<String>,
+    ) -> Result<JobStatus> {
+        if request.domains.is_empty() {
+            anyhow::bail!("Job must contain at least one domain");
+        }
+        if request.domains.len() > MAX_JOB_DOMAINS {
+            anyhow::bail!(
+                "Job contains {} domains, the maximum is {}",
+                request.domains.len(),
+                MAX_JOB_DOMAINS
+            );
+        }
+        for domain in &request.domains {
+            validate_domain(domain).map_err(|e| anyhow::anyhow!("{}: {}", domain, e))?;
+        }
+        request.engagement.validate()?;
+        let engagement = request.engagement.or(self.checker.engagement());
+
+        let status = JobStatus {
+            id: uuid::Uuid::new_v4().to_string(),
+            owner,
+            state: JobState::Queued,
+            total: request.domains.len(),
+            progress: BatchSummary::new(),
+            created_at: Utc::now(),
+            finished_at: None,
+            error: None,
+            engagement: (!engagement.is_empty()).then_some(engagement),
+        };
+        let cancel = CancellationToken::new();
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        self.jobs.lock().await.insert(
+            status.id.clone(),
+            JobEntry {
+                status: status.clone(),
+                cancel: cancel.clone(),
+                events,
+            },
+        );
+        info!("Submitted job {} with {} domains", status.id, status.total);
+
+        let manager = Arc::clone(self);
+        let id = status.id.clone();
+        let owner = status.owner.clone();
+        tokio::spawn(async move {
+            manager
+                .run(&id, owner.as_deref(), request.domains, cancel)
+                .await;
+        });
+
+        Ok(status)
+    }
+
+    /// Returns the status of a job
+    pub async fn get(&self, id: &str) -> Option<JobStatus> {
+        self.jobs.lock().await.get(id).map(|job| job.status.clone())
+    }
+
+    /// Lists all jobs, newest first
+    pub async fn list(&self) -> Vec<JobStatus> {
+        let mut jobs: Vec<JobStatus> = self
+            .jobs
+            .lock()
+            .await
+            .values()
+            .map(|job| job.status.clone())
+            .collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        jobs
+    }
+
+    /// Requests cancellation of a job
+    ///
+    /// # Returns
+    /// * `Option<bool>` - None if the job does not exist, false if it already finished
+    pub async fn cancel(&self, id: &str) -> Option<bool> {
+        let jobs = self.jobs.lock().await;
+        let job = jobs.get(id)?;
+        if job.status.state.is_finished() {
+            return Some(false);
+        }
+        job.cancel.cancel();
+        info!("Cancellation requested for job {}", id);
+        Some(true)
+    }
+
+    /// Forgets jobs that finished before `cutoff`, returning how many
+    ///
+    /// Their result files are left to [`crate::retention::purge_results`].
+    pub async fn forget_finished_before(&self, cutoff: DateTime<Utc>) -> usize {
+        let mut jobs = self.jobs.lock().await;
+        let before = jobs.len();
+        jobs.retain(|_, job| job.status.finished_at.is_none_or(|at| at >= cutoff));
+        before - jobs.len()
+    }
+
+    /// Subscribes to a job's live events
+    ///
+    /// Only results completed after subscribing are delivered; earlier ones
+    /// are available from [`JobManager::results_path`]. No receiver is
+    /// returned for a job that has already finished.
+    ///
+    /// # Returns
+    /// * `Option<(JobStatus, Option<broadcast::Receiver<JobEvent>>)>` - Current status
+    ///   and receiver, or None if the job does not exist
+    pub async fn subscribe(
+        &self,
+        id: &str,
+    ) -> Option<(JobStatus, Option<broadcast::Receiver<JobEvent>>)> {
+        let jobs = self.jobs.lock().await;
+        let job = jobs.get(id)?;
+        let receiver = (!job.status.state.is_finished()).then(|| job.events.subscribe());
+        Some((job.status.clone(), receiver))
+    }
+
+    /// Path of a job's JSONL results
+    pub fn results_path(&self, id: &str) -> PathBuf {
+        self.results_dir.join(format!("{}.jsonl", id))
+    }
+
+    /// Scans the job's domains chunk by chunk, stopping early on cancellation
+    async fn run(
+        &self,
+        id: &str,
+        owner: Option<&str>,
+        domains: Vec<String>,
+        cancel: CancellationToken,
+    ) {
+        self.update(id, |status| {
+            status.state = JobState::Running;
+            status.progress = BatchSummary::new();
+        })
+        .await;
+
+        let outcome = self.scan(id, owner, &domains, &cancel).await;
+        let state = match &outcome {
+            Ok(true) => JobState::Completed,
+            Ok(false) => JobState::Cancelled,
+            Err(e) => {
+                error!("Job {} failed: {:#}", id, e);
+                JobState::Failed
+            }
+        };
+        self.update(id, |status| {
+            status.state = state;
+            status.progress.finish();
+            status.finished_at = Some(Utc::now());
+            status.error = outcome.err().map(|e| format!("{:#}", e));
+        })
+        .await;
+        if let Some(job) = self.jobs.lock().await.get(id) {
+            // Sending fails only when nobody is subscribed
+            let _ = job
+                .events
+                .send(JobEvent::Finished(Box::new(job.status.clone())));
+        }
+        info!("Job {} finished as {:?}", id, state);
+    }
+
+    /// Returns false if the scan stopped early because of cancellation
+    async fn scan(
+        &self,
+        id: &str,
+        owner: Option<&str>,
+        domains: &[String],
+        cancel: &CancellationToken,
+    ) -> Result<bool> {
+        let rate_limiter = self.budget.limiter_for(owner);
+        let engagement = self.get(id).await.and_then(|status| status.engagement);
+        let mut sink =
+            stored_results_sink(&self.results_path(id), self.storage_key.as_ref()).await?;
+
+        for chunk in domains.chunks(self.checker.concurrent_limit().max(1)) {
+            let results = tokio::select! {
+                _ = cancel.cancelled() => {
+                    sink.close().await?;
+                    return Ok(false);
+                }
+                results = self.checker.process_chunk(chunk, rate_limiter) => results,
+            };
+
+            let sanitized: Vec<DomainResult> = results
+                .iter()
+                .map(|result| {
+                    sanitize_domain_result(&DomainResult {
+                        engagement: engagement.clone(),
+                        ..result.clone()
+                    })
+                })
+                .collect();
+            for result in &sanitized {
+                sink.write(result).await?;
+            }
+            sink.flush().await?;
+
+            if let Some(job) = self.jobs.lock().await.get_mut(id) {
+                for (result, clean) in results.iter().zip(sanitized) {
+                    job.status.progress.record(result);
+                    let _ = job.events.send(JobEvent::Result(Box::new(clean)));
+                }
+            }
+        }
+
+        sink.close().await?;
+        Ok(true)
+    }
+
+    async fn update(&self, id: &str, change: impl FnOnce(&mut JobStatus)) {
+        if let Some(job) = self.jobs.lock().await.get_mut(id) {
+            change(&mut job.status);
+        }
+    }
+}
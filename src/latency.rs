@@ -0,0 +1,210 @@
+//! Per-host latency tracking and time-boxing of slow hosts
+//!
+//! With `--slow-host-threshold-ms <MS>`, the time every request takes to be
+//! answered is recorded per remote host. A host whose 95th percentile over
+//! its last [`LatencyConfig::window`] requests exceeds the threshold is
+//! flagged as slow for [`LatencyConfig::penalty`]. While flagged, requests to
+//! the host are time-boxed to the threshold instead of waiting for the full
+//! request timeout, so a single degraded endpoint cannot stall a large batch.
+//! Once the penalty expires, the host starts over with an empty window.
+//!
+//! Flagging a host is a finding, not a silent slowdown: it is logged when it
+//! happens and every flagged host is reported as a [`SlowHost`] in the
+//! `slow_hosts` of the batch summary.
+//!
+//! The autodiscover endpoint and result sinks are the hosts queried today;
+//! fallback endpoints share the same tracker once they are probed.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default number of recent requests the percentile is computed over
+pub const DEFAULT_WINDOW: usize = 50;
+
+/// Default number of requests a host needs before it can be flagged
+pub const DEFAULT_MIN_SAMPLES: usize = 10;
+
+/// Default time a slow host stays flagged
+pub const DEFAULT_PENALTY: Duration = Duration::from_secs(300);
+
+/// Settings of a [`LatencyTracker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyConfig {
+    /// p95 latency above which a host is flagged, and the time box of its requests
+    pub threshold: Duration,
+    /// Number of recent requests per host the percentile is computed over
+    pub window: usize,
+    /// Requests a host needs before it can be flagged
+    pub min_samples: usize,
+    /// Time a flagged host stays time-boxed
+    pub penalty: Duration,
+}
+
+impl LatencyConfig {
+    /// Configuration flagging hosts whose p95 latency exceeds `threshold`
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            window: DEFAULT_WINDOW,
+            min_samples: DEFAULT_MIN_SAMPLES,
+            penalty: DEFAULT_PENALTY,
+        }
+    }
+}
+
+/// A host flagged for slow responses during the run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlowHost {
+    /// Host name of the endpoint
+    pub host: String,
+    /// 95th percentile latency when the host was last flagged
+    pub p95_ms: u64,
+    /// Configured threshold the percentile exceeded
+    pub threshold_ms: u64,
+    /// Requests the percentile was computed over
+    pub samples: usize,
+    /// When the host was last flagged
+    pub flagged_at: DateTime<Utc>,
+    /// How often the host was flagged during the run
+    pub times_flagged: u32,
+}
+
+#[derive(Default)]
+struct HostState {
+    /// Latencies of recent requests in milliseconds, oldest first
+    samples: VecDeque<u64>,
+    /// End of the current penalty, while the host is flagged
+    slow_until: Option<Instant>,
+}
+
+/// Latency statistics of every host requests were sent to
+pub struct LatencyTracker {
+    config: LatencyConfig,
+    hosts: Mutex<HashMap<String, HostState>>,
+    findings: Mutex<Vec<SlowHost>>,
+}
+
+impl LatencyTracker {
+    /// Creates a tracker without any recorded requests
+    pub fn new(config: LatencyConfig) -> Self {
+        Self {
+            config,
+            hosts: Mutex::new(HashMap::new()),
+            findings: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Settings of the tracker
+    pub fn config(&self) -> &LatencyConfig {
+        &self.config
+    }
+
+    /// Records a request to `host` answered after `latency`
+    ///
+    /// # Returns
+    /// * `Option<SlowHost>` - The finding if the request got the host flagged
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::latency::{LatencyConfig, LatencyTracker};
+    /// use std::time::Duration;
+    ///
+    /// let tracker = LatencyTracker::new(LatencyConfig {
+    ///     min_samples: 2,
+    ///     ..LatencyConfig::new(Duration::from_millis(500))
+    /// });
+    /// assert!(tracker.record("slow.example", Duration::from_secs(2)).is_none());
+    /// let finding = tracker.record("slow.example", Duration::from_secs(2)).unwrap();
+    /// assert_eq!(finding.p95_ms, 2000);
+    /// assert_eq!(tracker.time_box("slow.example"), Some(Duration::from_millis(500)));
+    /// ```
+    pub fn record(&self, host: &str, latency: Duration) -> Option<SlowHost> {
+        let host = host.to_ascii_lowercase();
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let state = hosts.entry(host.clone()).or_default();
+        state.samples.push_back(latency.as_millis() as u64);
+        while state.samples.len() > self.config.window.max(1) {
+            state.samples.pop_front();
+        }
+
+        if state.slow_until.is_some() || state.samples.len() < self.config.min_samples {
+            return None;
+        }
+        let p95_ms = percentile_95(&state.samples)?;
+        if p95_ms <= self.config.threshold.as_millis() as u64 {
+            return None;
+        }
+        state.slow_until = Some(Instant::now() + self.config.penalty);
+        let samples = state.samples.len();
+        drop(hosts);
+
+        let mut findings = self.findings.lock().unwrap_or_else(|e| e.into_inner());
+        let finding = match findings.iter_mut().find(|finding| finding.host == host) {
+            Some(finding) => {
+                finding.p95_ms = p95_ms;
+                finding.samples = samples;
+                finding.flagged_at = Utc::now();
+                finding.times_flagged += 1;
+                finding
+            }
+            None => {
+                findings.push(SlowHost {
+                    host,
+                    p95_ms,
+                    threshold_ms: self.config.threshold.as_millis() as u64,
+                    samples,
+                    flagged_at: Utc::now(),
+                    times_flagged: 1,
+                });
+                findings.last_mut()?
+            }
+        };
+        Some(finding.clone())
+    }
+
+    /// Time box of requests to `host`; `None` unless the host is flagged
+    ///
+    /// A host whose penalty has expired is unflagged and starts over with an
+    /// empty window.
+    pub fn time_box(&self, host: &str) -> Option<Duration> {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let state = hosts.get_mut(&host.to_ascii_lowercase())?;
+        let until = state.slow_until?;
+        if Instant::now() < until {
+            return Some(self.config.threshold);
+        }
+        state.slow_until = None;
+        state.samples.clear();
+        None
+    }
+
+    /// 95th percentile latency of the recent requests to `host`
+    pub fn p95(&self, host: &str) -> Option<Duration> {
+        let hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let state = hosts.get(&host.to_ascii_lowercase())?;
+        percentile_95(&state.samples).map(Duration::from_millis)
+    }
+
+    /// Every host flagged during the run, in the order they were first flagged
+    pub fn findings(&self) -> Vec<SlowHost> {
+        self.findings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// Nearest-rank 95th percentile of `samples`
+fn percentile_95(samples: &VecDeque<u64>) -> Option<u64> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<u64> = samples.iter().copied().collect();
+    sorted.sort_unstable();
+    let rank = (sorted.len() * 95).div_ceil(100);
+    sorted.get(rank.saturating_sub(1)).copied()
+}
@@ -1,12 +1,107 @@
-// Sentri: Microsoft Defender for Identity (MDI) Scanner
-// Exposes the core functionality of the Sentri application as a library
+//! Sentri: Microsoft Defender for Identity (MDI) Scanner
+//!
+//! Exposes the core functionality of the Sentri application as a library.
+//! The supported, semver-stable API is re-exported from [`prelude`]; modules
+//! hidden from the documentation serve the `sentri` binary and may change
+//! in any release.
 
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+pub mod accounting;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
+pub mod alert;
+pub mod attribution;
+pub mod baseline;
+pub mod build_info;
+pub mod capture;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
 pub mod cli;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
+pub mod config;
 pub mod core;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
+pub mod crash;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+pub mod data;
+pub mod depth;
+#[cfg(feature = "dns")]
 pub mod dns;
+pub mod dns_override;
+#[cfg(feature = "dns")]
+pub mod dns_pipeline;
+pub mod dns_pool;
+#[cfg(feature = "dns")]
+pub mod dns_privacy;
+pub mod egress;
+pub mod encryption;
+pub mod engagement;
+#[doc(hidden)]
+pub mod env_profile;
+pub mod error_class;
+#[doc(hidden)]
+pub mod fd_limit;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+pub mod graph;
+#[cfg(feature = "http")]
 pub mod http;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
+pub mod jobs;
+pub mod latency;
+pub mod logging;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
+pub mod notify;
+pub mod offline;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+pub mod output;
+pub mod ownership;
+pub mod policy;
+pub mod prelude;
+#[doc(hidden)]
+pub mod provenance;
+pub mod random;
 pub mod rate_limit;
+pub mod rate_report;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
+pub mod reload;
+pub mod rescan;
+pub mod result_index;
+pub mod result_reader;
+pub mod result_schema;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
+pub mod retention;
 pub mod retry;
+#[cfg(feature = "sanitize-html")]
 pub mod sanitize;
+#[doc(hidden)]
+pub mod scan_window;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
+pub mod scheduler;
+pub mod secrets;
+#[cfg(feature = "server")]
+#[doc(hidden)]
+pub mod server;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
+pub mod service;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+pub mod sinks;
+pub mod stats;
+pub mod tags;
+pub mod time;
+pub mod trace;
+#[cfg(feature = "http")]
+pub mod upload;
 pub mod validation;
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[doc(hidden)]
+pub mod watch;
 pub mod xml;
+pub mod xml_schema;
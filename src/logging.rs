@@ -0,0 +1,90 @@
+//! Sampling of repetitive log messages
+//!
+//! During an outage every domain of a batch fails the same way, and logging
+//! each failure buries everything else. A [`LogSampler`] counts occurrences
+//! per error class: the first few are logged, after that only one in every
+//! `every`. Sampled messages carry the running count in an `occurrences`
+//! field, and [`LogSampler::suppressed`] reports what was left out.
+//!
+//! # Examples
+//!
+//! ```
+//! use sentri::logging::LogSampler;
+//! use tracing::warn;
+//!
+//! let sampler = LogSampler::new(2, 10);
+//! for _ in 0..25 {
+//!     if let Some(occurrences) = sampler.sample("http.server_error") {
+//!         warn!(occurrences, "Server error, will retry");
+//!     }
+//! }
+//! // Logged: occurrences 1, 2, 12 and 22
+//! assert_eq!(sampler.suppressed(), vec![("http.server_error", 21)]);
+//! ```
+
+use dashmap::DashMap;
+
+/// Occurrences of an error class that are always logged
+pub const DEFAULT_FIRST: u64 = 10;
+
+/// Once past the first occurrences, one in this many is logged
+pub const DEFAULT_EVERY: u64 = 100;
+
+/// Decides per error class whether a message should be logged
+#[derive(Debug)]
+pub struct LogSampler {
+    first: u64,
+    every: u64,
+    counts: DashMap<&'static str, u64>,
+}
+
+impl Default for LogSampler {
+    fn default() -> Self {
+        Self::new(DEFAULT_FIRST, DEFAULT_EVERY)
+    }
+}
+
+impl LogSampler {
+    /// Creates a sampler logging the first `first` occurrences of a class, then one in `every`
+    pub fn new(first: u64, every: u64) -> Self {
+        Self {
+            first,
+            every: every.max(1),
+            counts: DashMap::new(),
+        }
+    }
+
+    /// Counts an occurrence of `class`
+    ///
+    /// # Returns
+    /// * `Option<u64>` - The number of occurrences so far if this one should be logged
+    pub fn sample(&self, class: &'static str) -> Option<u64> {
+        let mut count = self.counts.entry(class).or_insert(0);
+        *count += 1;
+        let occurrences = *count;
+        let logged =
+            occurrences <= self.first || (occurrences - self.first).is_multiple_of(self.every);
+        logged.then_some(occurrences)
+    }
+
+    /// Number of occurrences of `class` seen so far
+    pub fn occurrences(&self, class: &str) -> u64 {
+        self.counts.get(class).map_or(0, |count| *count)
+    }
+
+    /// Classes with occurrences that were not logged, and how many
+    pub fn suppressed(&self) -> Vec<(&'static str, u64)> {
+        let mut suppressed: Vec<(&'static str, u64)> = self
+            .counts
+            .iter()
+            .filter_map(|entry| {
+                let occurrences = *entry.value();
+                let logged = occurrences.min(self.first)
+                    + occurrences.saturating_sub(self.first) / self.every;
+                (occurrences > logged).then(|| (*entry.key(), occurrences - logged))
+            })
+            .collect();
+        suppressed.sort();
+        suppressed
+    }
+}
@@ -1,12 +1,105 @@
-use anyhow::Result;
-use clap::Parser;
-use sentri::cli::Cli;
+use anyhow::{Context, Result};
+use sentri::alert::build_alerters;
+use sentri::attribution::IpRanges;
+use sentri::baseline::Baseline;
+use sentri::capture::{Evidence, ResponseStore};
+use sentri::cli::{BaselineAction, OwnershipAction, ServiceAction};
+use sentri::config::ConfigFile;
 use sentri::core::MdiChecker;
+use sentri::crash::{self, CrashContext, RecentLogs};
+use sentri::data::{resolve_data_dir, update_data, DataSet};
+use sentri::depth::Depth;
+use sentri::dns::{DnsResolver, DEFAULT_DNS_ATTEMPTS, DEFAULT_DNS_TIMEOUT_MS, ROAMING_RESOLVERS};
+use sentri::dns_override::DnsOverrides;
+use sentri::dns_pipeline::PipelineConfig;
+use sentri::dns_privacy::PrivacyConfig;
+use sentri::encryption::{LineDecoder, StorageKey};
+use sentri::engagement::Engagement;
+use sentri::fd_limit;
+use sentri::graph::Graph;
+use sentri::http::{
+    HttpClient, ALTERNATE_AUTODISCOVER_URL, DEFAULT_ACCEPT_LANGUAGE, DEFAULT_AUTODISCOVER_URL,
+    DEFAULT_MAX_RESPONSE_SIZE,
+};
+#[cfg(feature = "server")]
+use sentri::jobs::JobManager;
+use sentri::latency::{LatencyConfig, LatencyTracker};
+use sentri::notify::{send_report, EmailConfig, RunOutcome};
+use sentri::offline;
+use sentri::output::cloud::is_object_url;
+use sentri::output::fields::FieldSelection;
+use sentri::ownership::OwnershipStore;
+use sentri::policy::{read_results, Policy, RegoPolicy};
+#[cfg(feature = "server")]
+use sentri::rate_limit::RateBudget;
+use sentri::rate_limit::RateLimiter;
+use sentri::rate_report::RateRecorder;
+use sentri::reload::LiveConfig;
+use sentri::rescan::{carried_over, domains_to_rescan, domains_to_retry, with_tags};
+use sentri::result_index::find_results;
+use sentri::result_reader::stream_results;
+use sentri::result_schema::domain_result_schema;
+#[cfg(feature = "server")]
+use sentri::retention::{purge, RetentionPolicy};
 use sentri::sanitize::sanitize_domain_result;
+#[cfg(feature = "server")]
+use sentri::scheduler::Scheduler;
+#[cfg(feature = "server")]
+use sentri::server::{serve, ApiKeys, AuditLog, AuditRecord, ServerState, AUDIT_LOG_FILE};
+use sentri::service::{unit_file, Notifier};
+use sentri::sinks::bucketed::bucket_result;
+use sentri::sinks::{
+    build_sinks, compressed_sink, fields_sink, format_sink, object_sink, sqlite_sink, BucketedSink,
+    FanOutSink, JsonlFileSink, OutputFormat, ResultSink, RoutingSink, SharedFileSink, SocketSink,
+    TenantReportSink, UsageReportSink,
+};
+use sentri::trace::TraceRecorder;
+use sentri::upload::upload_file;
+use sentri::watch::run_watch;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::runtime::Builder;
-use tracing::{debug, info};
+use tracing::{debug, error, info, warn};
+
+/// Minimum timeout for reference data downloads
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Checkpoint of `batch --max-runtime` without an output file
+const DEFAULT_CHECKPOINT_FILE: &str = "sentri-remaining.txt";
 
 fn main() -> Result<()> {
+    // Parsed before the runtime is built, as --env-profile may size it
+    let cli = sentri::config::parse()?;
+
+    #[cfg(windows)]
+    if let sentri::cli::Commands::Service {
+        action: ServiceAction::Run { name, args },
+    } = &cli.command
+    {
+        let mut service_cli = sentri::config::try_parse_from(
+            std::iter::once("sentri").chain(args.iter().map(String::as_str)),
+        )?;
+        service_cli.resolve_secrets(&sentri::secrets::SecretResolver::default())?;
+        return sentri::service::windows::run(name, move |stop| {
+            runtime(&service_cli)
+                .block_on(async {
+                    tokio::select! {
+                        result = async_main(service_cli) => result,
+                        _ = stop => Ok(()),
+                    }
+                })
+                .inspect_err(crash::report_fatal_error)
+        });
+    }
+
+    runtime(&cli)
+        .block_on(async_main(cli))
+        .inspect_err(crash::report_fatal_error)
+}
+
+/// Builds the Tokio runtime sized for `cli`
+fn runtime(cli: &sentri::cli::Cli) -> tokio::runtime::Runtime {
     // Configure Tokio runtime with appropriate worker threads
     // This follows the rule limit_tokio_worker_threads from .windsurfrules
     let num_cpus = std::thread::available_parallelism()
@@ -16,53 +109,1060 @@ fn main() -> Result<()> {
     // Limit maximum worker threads to avoid excessive resource usage
     // Use available_parallelism but cap it at 16 to prevent excessive context switching
     // For IO-heavy workloads like network requests, slightly more threads than cores can be beneficial
-    let worker_threads = std::cmp::min(num_cpus + 2, 16);
+    let worker_threads = match &cli.tuning {
+        Some(tuning) => tuning.worker_threads,
+        None => std::cmp::min(num_cpus + 2, 16),
+    };
 
     debug!(
         "Configuring Tokio runtime with {} worker threads",
         worker_threads
     );
 
-    let runtime = Builder::new_multi_thread()
+    Builder::new_multi_thread()
         .worker_threads(worker_threads)
         .enable_all()
         .build()
-        .expect("Failed to create Tokio runtime");
-
-    // Run our async main function in the configured runtime
-    runtime.block_on(async_main())
+        .expect("Failed to create Tokio runtime")
 }
 
-async fn async_main() -> Result<()> {
-    // Initialize tracing
+async fn async_main(cli: sentri::cli::Cli) -> Result<()> {
+    // Initialize tracing, keeping recent lines for crash bundles
+    let recent_logs = RecentLogs::new(crash::RECENT_LOG_LINES).with_stderr(cli.quiet);
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(recent_logs.clone())
         .init();
 
-    let cli = Cli::parse();
-    let checker = MdiChecker::new(cli.concurrent_requests, cli.timeout_ms)?;
+    if let Some(tuning) = &cli.tuning {
+        let chunk_size = match &cli.command {
+            sentri::cli::Commands::Batch { chunk_size, .. } => Some(*chunk_size),
+            _ => None,
+        };
+        info!(
+            environment = tuning.environment.as_str(),
+            worker_threads = tuning.worker_threads,
+            concurrent_requests = cli.concurrent_requests,
+            cache_size = ?cli.cache_size,
+            chunk_size = ?chunk_size,
+            "Tuned defaults for this host"
+        );
+    }
+    let data_dir = resolve_data_dir(cli.data_dir.as_deref());
+    let support_dir = cli
+        .support_dir
+        .clone()
+        .or_else(|| data_dir.as_ref().map(|dir| dir.join("support")))
+        .unwrap_or_else(|| std::env::temp_dir().join("sentri-support"));
+    crash::install(
+        CrashContext::new(support_dir)
+            .with_args(std::env::args())
+            .with_logs(recent_logs),
+    );
+    let storage_key = cli
+        .storage_key
+        .as_deref()
+        .map(StorageKey::from_base64)
+        .transpose()?
+        .map(Arc::new);
+    let integrity = match &cli.expected_binary_sha256 {
+        Some(expected) => {
+            let report = sentri::provenance::verify_running_binary(expected)?;
+            if report.verified {
+                info!(
+                    binary = %report.binary.display(),
+                    sha256 = %report.sha256,
+                    "Binary integrity verified"
+                );
+            } else {
+                error!(
+                    binary = %report.binary.display(),
+                    sha256 = %report.sha256,
+                    expected = %report.expected,
+                    "Binary integrity check failed"
+                );
+            }
+            Some(report)
+        }
+        None => None,
+    };
+    if let Some(report) = integrity.as_ref().filter(|report| !report.verified) {
+        #[cfg(feature = "server")]
+        if let sentri::cli::Commands::Serve { state_dir, .. } = &cli.command {
+            tokio::fs::create_dir_all(state_dir).await?;
+            AuditLog::open(&state_dir.join(AUDIT_LOG_FILE), storage_key.clone())
+                .await?
+                .record(&AuditRecord::integrity(report.clone()))
+                .await?;
+        }
+        anyhow::bail!(
+            "Binary integrity check failed: {} has SHA-256 {}, expected {}",
+            report.binary.display(),
+            report.sha256,
+            report.expected
+        );
+    }
+    if let Some(seed) = cli.seed {
+        sentri::random::set_seed(seed)?;
+        info!(seed, "Using a fixed random seed");
+    }
+    if cli.offline {
+        offline::set_offline(true);
+        info!("Offline mode: network operations are disabled");
+    }
+    for host in &cli.egress_allow {
+        sentri::egress::allow_host(host);
+    }
+    if cli.strict_egress {
+        sentri::egress::set_strict_egress(true);
+        info!(
+            allowed = ?sentri::egress::allowed_hosts(),
+            "Strict egress: outbound connections are limited to the allowlist"
+        );
+    }
+    let config = match &cli.config {
+        Some(path) => ConfigFile::load(path).await?,
+        None => ConfigFile::default(),
+    };
+    let dns_privacy = PrivacyConfig {
+        ecs: cli.ecs,
+        bind_address: cli.dns_bind_address,
+    };
+    let dns_overrides = match &cli.dns_override {
+        Some(path) => {
+            let overrides = DnsOverrides::load(path)?;
+            info!(names = overrides.len(), path = %path.display(), "Loaded DNS overrides");
+            Some(Arc::new(overrides))
+        }
+        None => None,
+    };
+    if cli.attribute_ips && cli.depth == Depth::Fast {
+        anyhow::bail!("--attribute-ips cannot be combined with --depth fast");
+    }
+    if cli.dns_pipeline_workers.is_some() && dns_privacy != PrivacyConfig::default() {
+        // Pipelined queries travel over TCP, where neither setting applies
+        anyhow::bail!("--dns-pipeline-workers cannot be combined with --ecs or --dns-bind-address");
+    }
+    let dns_budget = cli.depth.dns_budget();
+    let dns_resolver = |upstreams: &[std::net::SocketAddr]| {
+        let mut resolver = DnsResolver::new()?
+            .with_upstreams(upstreams, cli.resolver_strategy)
+            .with_privacy(dns_privacy)
+            .with_query_timeout(Duration::from_millis(cli.dns_timeout_ms))
+            .with_attempts(usize::from(cli.dns_attempts));
+        if let Some(overrides) = &dns_overrides {
+            resolver = resolver.with_overrides(Arc::clone(overrides));
+        }
+        if cli.dnssec_negatives {
+            resolver = resolver.with_dnssec_negatives()?;
+        }
+        if cli.dns_pipeline_workers.is_some() {
+            resolver = resolver.with_pipelining();
+        }
+        if dns_budget != Depth::default().dns_budget() {
+            resolver = resolver.with_rate_limiter(dns_budget.limiter());
+        }
+        if let Some(settings) = &config.retry.dns {
+            let retry = settings
+                .apply(resolver.retry_config().clone())
+                .context("Invalid [retry.dns] configuration")?;
+            resolver = resolver.with_retry_config(retry);
+        }
+        anyhow::Ok(resolver)
+    };
+    let concurrent_requests = fd_limit::preflight(cli.concurrent_requests, cli.raise_fd_limit);
+    let mut checker = MdiChecker::new(concurrent_requests, cli.timeout_ms)?;
+    if !cli.resolvers.is_empty()
+        || dns_privacy != PrivacyConfig::default()
+        || dns_overrides.is_some()
+        || cli.dnssec_negatives
+        || cli.dns_pipeline_workers.is_some()
+        || cli.dns_timeout_ms != DEFAULT_DNS_TIMEOUT_MS
+        || usize::from(cli.dns_attempts) != DEFAULT_DNS_ATTEMPTS
+        || config.retry.dns.is_some()
+        || dns_budget != Depth::default().dns_budget()
+    {
+        checker = checker.with_dns_resolver(dns_resolver(&cli.resolvers)?);
+    }
+    if cli.mdi_dns_rate_limit.is_some()
+        || cli.mdi_dns_concurrency.is_some()
+        || config.mdi_dns.is_some()
+    {
+        let mut budget = match &config.mdi_dns {
+            Some(settings) => settings
+                .apply(dns_budget)
+                .context("Invalid [mdi_dns] configuration")?,
+            None => dns_budget,
+        };
+        if let Some(per_minute) = cli.mdi_dns_rate_limit {
+            budget.queries_per_minute = per_minute as usize;
+        }
+        if let Some(concurrency) = cli.mdi_dns_concurrency {
+            budget.max_concurrent = concurrency as usize;
+        }
+        info!(
+            queries_per_minute = budget.queries_per_minute,
+            max_concurrent = budget.max_concurrent,
+            "MDI sensor lookups have their own DNS budget"
+        );
+        checker = checker.with_mdi_rate_limiter(budget.limiter());
+    }
+    if let Some(workers) = cli.dns_pipeline_workers {
+        checker = checker.with_dns_pipeline(PipelineConfig {
+            workers: usize::from(workers),
+            depth: usize::from(cli.dns_pipeline_depth),
+        });
+    }
+    let http_client = |autodiscover_url: &str| {
+        let mut builder = HttpClient::builder()
+            .timeout(Duration::from_millis(cli.timeout_ms))
+            .max_response_size(cli.max_response_size)
+            .accept_language(cli.accept_language.clone())
+            .autodiscover_url(autodiscover_url);
+        if let Some(overrides) = &dns_overrides {
+            builder = builder.dns_overrides(Arc::clone(overrides));
+        }
+        let mut client = builder.build()?;
+        if let Some(threshold_ms) = cli.slow_host_threshold_ms {
+            let latency = LatencyConfig::new(Duration::from_millis(threshold_ms));
+            client = client.with_latency_tracker(Arc::new(LatencyTracker::new(latency)));
+        }
+        if let Some(settings) = &config.retry.http {
+            let retry = settings
+                .apply(client.retry_config().clone())
+                .context("Invalid [retry.http] configuration")?;
+            client = client.with_retry_config(retry);
+        }
+        anyhow::Ok(client)
+    };
+    if dns_overrides.is_some()
+        || cli.max_response_size != DEFAULT_MAX_RESPONSE_SIZE
+        || cli.slow_host_threshold_ms.is_some()
+        || cli.accept_language != DEFAULT_ACCEPT_LANGUAGE
+        || config.retry.http.is_some()
+    {
+        checker = checker.with_http_client(http_client(DEFAULT_AUTODISCOVER_URL)?);
+    }
+    if cli.roaming_retry {
+        // An empty list keeps the system configuration
+        let upstreams = match (cli.roaming_resolvers.is_empty(), cli.resolvers.is_empty()) {
+            (false, _) => cli.roaming_resolvers.clone(),
+            (true, false) => Vec::new(),
+            (true, true) => ROAMING_RESOLVERS.to_vec(),
+        };
+        checker = checker.with_roaming(
+            http_client(ALTERNATE_AUTODISCOVER_URL)?,
+            dns_resolver(&upstreams)?,
+        );
+    }
+    checker = checker.with_depth(cli.depth);
+    if cli.attribute_ips || cli.depth.attributes_endpoints() {
+        checker = checker.with_ip_attribution(IpRanges::load(data_dir.as_deref())?);
+    }
+    if let Some(engagement) = Engagement::new(cli.engagement_id.clone(), cli.operator.clone())? {
+        checker = checker.with_engagement(engagement);
+    }
+    if let Some(max_age) = cli.max_age {
+        checker = checker.with_max_cache_age(max_age);
+    }
+    if let Some(capacity) = cli.cache_size {
+        checker = checker.with_cache_capacity(capacity);
+    }
+    if let Some(dir) = &cli.capture_dir {
+        info!("Capturing federation responses in {}", dir.display());
+        checker = checker.with_capture(Arc::new(
+            ResponseStore::open(dir, storage_key.clone()).await?,
+        ));
+    }
+    if cli.strict_schema {
+        checker = checker.with_strict_schema();
+    }
+    let trace = cli
+        .trace_file
+        .as_ref()
+        .map(|_| Arc::new(TraceRecorder::new()));
+    if let Some(recorder) = &trace {
+        checker = checker.with_trace(Arc::clone(recorder));
+    }
+    let write_trace = || async {
+        if let (Some(recorder), Some(path)) = (&trace, &cli.trace_file) {
+            match recorder.write(path).await {
+                Ok(()) => info!(
+                    spans = recorder.spans(),
+                    path = %path.display(),
+                    "Trace written"
+                ),
+                Err(e) => error!("Failed to write trace: {:#}", e),
+            }
+        }
+    };
+    if cli.require_ownership {
+        let store = OwnershipStore::load(&cli.ownership_file).await?;
+        let verified = store.verified_domains();
+        info!(
+            "Intrusive detectors restricted to {} verified domains",
+            verified.len()
+        );
+        checker = checker.with_verified_domains(verified);
+    }
 
     match &cli.command {
         sentri::cli::Commands::Single { domain } => {
+            let format = cli.format.unwrap_or(OutputFormat::Json);
+            let mut sink = match FieldSelection::new(&cli.fields)? {
+                Some(fields) => fields_sink(format, None, fields).await?,
+                None => format_sink(format, None, None).await?,
+            };
+
             info!("Checking single domain: {}", domain);
-            let result = checker.check_domain(domain).await?;
+            let result = checker.check_domain_with_roaming(domain).await;
+            write_trace().await;
+            let mut result = result?;
+            if let Some(bucket) = cli.timestamp_bucket {
+                result = bucket_result(&result, bucket);
+            }
 
             // Sanitize output before displaying (implements security:output:sanitize_all_output rule)
             let sanitized_result = sanitize_domain_result(&result);
-            println!("{}", serde_json::to_string_pretty(&sanitized_result)?);
+            sink.write(&sanitized_result).await?;
+            sink.close().await?;
+        }
+        sentri::cli::Commands::Federation { domain } => {
+            info!("Enumerating federated domains of: {}", domain);
+            let result = checker.check_federation(domain).await;
+            write_trace().await;
+            let mut result = sanitize_domain_result(&result?);
+            match cli.format {
+                Some(format) => {
+                    if let Some(bucket) = cli.timestamp_bucket {
+                        result = bucket_result(&result, bucket);
+                    }
+                    let mut sink = match FieldSelection::new(&cli.fields)? {
+                        Some(fields) => fields_sink(format, None, fields).await?,
+                        None => format_sink(format, None, None).await?,
+                    };
+                    sink.write(&result).await?;
+                    sink.close().await?;
+                }
+                None => {
+                    if let Some(error) = &result.error {
+                        anyhow::bail!("Federation lookup of {} failed: {}", domain, error);
+                    }
+                    for federated in &result.federated_domains {
+                        println!("{}", federated);
+                    }
+                }
+            }
+        }
+        sentri::cli::Commands::Tenant { name } => {
+            let format = cli.format.unwrap_or(OutputFormat::Json);
+            let mut sink = match FieldSelection::new(&cli.fields)? {
+                Some(fields) => fields_sink(format, None, fields).await?,
+                None => format_sink(format, None, None).await?,
+            };
+
+            info!("Checking tenant: {}", name);
+            let result = checker.check_tenant(name).await;
+            write_trace().await;
+            let mut result = result?;
+            if let Some(bucket) = cli.timestamp_bucket {
+                result = bucket_result(&result, bucket);
+            }
+
+            let sanitized_result = sanitize_domain_result(&result);
+            sink.write(&sanitized_result).await?;
+            sink.close().await?;
         }
         sentri::cli::Commands::Batch {
             input_file,
+            from_results,
+            only,
+            retry_classes,
             output_file,
+            append,
+            index,
+            compress,
+            socket,
+            policy,
+            tenant_report,
+            usage_report,
+            rate_report,
+            output_csv,
+            output_parquet,
+            output_sqlite,
+            max_runtime,
+            checkpoint_file,
             chunk_size,
             rate_limit,
+            sinks: sink_args,
+            upload,
+            notify,
         } => {
-            info!("Processing batch from file: {:?}", input_file);
-            checker
-                .process_batch(input_file, output_file.as_ref(), *chunk_size, *rate_limit)
-                .await?;
+            if let Some(max_runtime) = *max_runtime {
+                let checkpoint = match (checkpoint_file, output_file) {
+                    (Some(path), _) => path.clone(),
+                    (None, Some(output)) if !is_object_url(output) => {
+                        let mut path = output.as_os_str().to_owned();
+                        path.push(".remaining");
+                        PathBuf::from(path)
+                    }
+                    (None, _) => PathBuf::from(DEFAULT_CHECKPOINT_FILE),
+                };
+                checker = checker.with_max_runtime(max_runtime, checkpoint);
+            }
+            let rate_recorder = rate_report.as_ref().map(|_| Arc::new(RateRecorder::new()));
+            if let Some(recorder) = &rate_recorder {
+                checker = checker.with_rate_recorder(Arc::clone(recorder));
+            }
+            let run = async {
+                let policy = match policy {
+                    Some(path) => Some(Policy::load(path).await?),
+                    None => None,
+                };
+                let format = cli.format.unwrap_or(match output_file {
+                    Some(_) => OutputFormat::Jsonl,
+                    None => OutputFormat::Json,
+                });
+                let fields = FieldSelection::new(&cli.fields)?;
+                if fields.is_some() && (socket.is_some() || *append || *index) {
+                    anyhow::bail!("--fields cannot be combined with --socket, --append or --index");
+                }
+                if policy.is_some()
+                    && (fields.is_some() || output_file.as_deref().is_some_and(is_object_url))
+                {
+                    anyhow::bail!(
+                        "--policy applies to JUnit reports and cannot be combined with --fields or an object store URL"
+                    );
+                }
+                let primary: Box<dyn ResultSink> = match (socket, output_file, compress) {
+                    (Some(path), _, _) => {
+                        if cli
+                            .format
+                            .is_some_and(|format| format != OutputFormat::Jsonl)
+                        {
+                            anyhow::bail!(
+                                "--socket streams NDJSON and cannot be combined with --format"
+                            );
+                        }
+                        Box::new(SocketSink::connect(path).await?)
+                    }
+                    (None, Some(path), compression) if is_object_url(path) => {
+                        if *append || *index || upload.upload_to.is_some() {
+                            anyhow::bail!(
+                                "--append, --index and --upload-to need a local output file"
+                            );
+                        }
+                        let url = path.to_string_lossy();
+                        object_sink(format, &url, fields, *compression).await?
+                    }
+                    (None, Some(path), None) if *append => {
+                        if format != OutputFormat::Jsonl {
+                            anyhow::bail!("--append only supports JSONL output");
+                        }
+                        Box::new(SharedFileSink::open(path).await?)
+                    }
+                    (None, Some(path), Some(compression)) => {
+                        compressed_sink(format, path, fields, *compression).await?
+                    }
+                    (None, Some(path), None) if *index => {
+                        if format != OutputFormat::Jsonl {
+                            anyhow::bail!("--index only supports JSONL output");
+                        }
+                        Box::new(JsonlFileSink::create(path).await?.with_index().await?)
+                    }
+                    (None, _, _) => match fields {
+                        Some(fields) => fields_sink(format, output_file.as_deref(), fields).await?,
+                        None => format_sink(format, output_file.as_deref(), policy).await?,
+                    },
+                };
+                let mut outputs = vec![primary];
+                if let Some(path) = output_csv {
+                    outputs.push(format_sink(OutputFormat::Csv, Some(path), None).await?);
+                }
+                if let Some(path) = output_parquet {
+                    outputs.push(format_sink(OutputFormat::Parquet, Some(path), None).await?);
+                }
+                if let Some(path) = output_sqlite {
+                    outputs.push(sqlite_sink(path, storage_key.clone())?);
+                }
+                let mut sinks = match outputs.len() {
+                    1 => outputs,
+                    _ => vec![Box::new(FanOutSink::new(outputs)) as Box<dyn ResultSink>],
+                };
+                sinks.extend(build_sinks(
+                    sink_args,
+                    Duration::from_millis(cli.timeout_ms),
+                )?);
+                if !config.routes.is_empty() {
+                    let timeout = Duration::from_millis(cli.timeout_ms);
+                    sinks.push(Box::new(
+                        RoutingSink::from_routes(&config.routes, timeout).await?,
+                    ));
+                }
+                if let Some(path) = tenant_report {
+                    sinks.push(Box::new(TenantReportSink::new(path)));
+                }
+                if let Some(path) = usage_report {
+                    sinks.push(Box::new(UsageReportSink::new(path)));
+                }
+                if let Some(bucket) = cli.timestamp_bucket {
+                    sinks = sinks
+                        .into_iter()
+                        .map(|sink| {
+                            Box::new(BucketedSink::new(sink, bucket)) as Box<dyn ResultSink>
+                        })
+                        .collect();
+                }
+
+                let summary = match (input_file, from_results) {
+                    (_, Some(results_file)) => {
+                        let results = read_results(results_file).await?;
+                        let total = results.len();
+                        let domains = if retry_classes.is_empty() {
+                            let domains = domains_to_rescan(&results, only.unwrap_or_default());
+                            with_tags(domains, &results)
+                        } else {
+                            let domains = domains_to_retry(&results, retry_classes);
+                            let lines = with_tags(domains.clone(), &results);
+                            let kept = carried_over(results, &domains);
+                            info!("Carrying over {} unchanged results", kept.len());
+                            MdiChecker::write_sanitized_results(&kept, &mut sinks).await?;
+                            lines
+                        };
+                        info!(
+                            "Re-scanning {} of {} results from {:?}",
+                            domains.len(),
+                            total,
+                            results_file
+                        );
+                        checker
+                            .process_domains_with_sinks(
+                                domains,
+                                &mut sinks,
+                                *chunk_size,
+                                *rate_limit,
+                            )
+                            .await?
+                    }
+                    (Some(input_file), None) => {
+                        info!("Processing batch from file: {:?}", input_file);
+                        checker
+                            .process_batch_with_sinks(
+                                input_file,
+                                &mut sinks,
+                                *chunk_size,
+                                *rate_limit,
+                            )
+                            .await?
+                    }
+                    (None, None) => anyhow::bail!("No input file given"),
+                };
+
+                if let (Some(destination), Some(output_file)) = (&upload.upload_to, output_file) {
+                    let url = upload_file(
+                        output_file,
+                        destination,
+                        upload.upload_sse,
+                        upload.upload_kms_key_id.as_deref(),
+                    )
+                    .await?;
+                    info!("Results uploaded to {}", url);
+                }
+
+                Ok::<_, anyhow::Error>(summary)
+            };
+            let result = run.await;
+            write_trace().await;
+            if let (Some(recorder), Some(path)) = (&rate_recorder, rate_report) {
+                match recorder.write(path).await {
+                    Ok(report) if report.compliant => info!(
+                        requests = report.requests,
+                        max_per_window = report.max_per_window,
+                        limit = report.limit,
+                        path = %path.display(),
+                        "Rate report written"
+                    ),
+                    Ok(report) => warn!(
+                        bursts = report.bursts.len(),
+                        peak_concurrent = report.peak_concurrent,
+                        path = %path.display(),
+                        "Rate report written; the run exceeded its rate limit"
+                    ),
+                    Err(e) => error!("Failed to write rate report: {:#}", e),
+                }
+            }
+
+            // Report the outcome either way; a failed notification must not mask the batch result
+            if let Some(email) = EmailConfig::from_args(notify) {
+                let outcome = match &result {
+                    Ok(summary) => RunOutcome::Completed(summary.clone()),
+                    Err(e) => RunOutcome::Failed(format!("{:#}", e)),
+                };
+                if let Err(e) = send_report(&email, &outcome).await {
+                    error!("Failed to send email report: {:#}", e);
+                }
+            }
+
+            result?;
+        }
+        sentri::cli::Commands::Policy {
+            policy_file,
+            rego_file,
+            rego_query,
+            results_file,
+        } => {
+            let results = read_results(results_file).await?;
+            let mut violations = Vec::new();
+
+            if let Some(policy_file) = policy_file {
+                violations.extend(Policy::load(policy_file).await?.evaluate(&results));
+            }
+            if !rego_file.is_empty() {
+                let mut rego = RegoPolicy::load(rego_file, rego_query)?;
+                violations.extend(rego.evaluate(&results)?);
+            }
+
+            for violation in &violations {
+                println!("{}", serde_json::to_string(violation)?);
+            }
+
+            if !violations.is_empty() {
+                anyhow::bail!(
+                    "{} policy violations across {} results",
+                    violations.len(),
+                    results.len()
+                );
+            }
+            info!("All {} results comply with the policy", results.len());
+        }
+        sentri::cli::Commands::Graph {
+            results_file,
+            graph_format,
+            output_file,
+        } => {
+            let mut results = stream_results(results_file);
+            let mut graph = Graph::default();
+            while let Some(result) = results.next().await {
+                graph.add(&result?);
+            }
+            let rendered = graph.render(*graph_format);
+            match output_file {
+                Some(path) => {
+                    tokio::fs::write(path, rendered)
+                        .await
+                        .with_context(|| format!("Failed to write graph to {}", path.display()))?;
+                    info!(
+                        nodes = graph.nodes().len(),
+                        edges = graph.edges().len(),
+                        path = %path.display(),
+                        "Graph written"
+                    );
+                }
+                None => print!("{}", rendered),
+            }
+        }
+        sentri::cli::Commands::Report {
+            results_file,
+            output_file,
+        } => {
+            let format = cli.format.unwrap_or(OutputFormat::Markdown);
+            if format != OutputFormat::Markdown {
+                anyhow::bail!("report only supports --format markdown");
+            }
+            let mut results = stream_results(results_file);
+            let mut sink = format_sink(format, output_file.as_deref(), None).await?;
+            let mut count = 0;
+            while let Some(result) = results.next().await {
+                sink.write(&result?).await?;
+                count += 1;
+            }
+            sink.close().await?;
+            if let Some(path) = output_file {
+                info!("Report of {} results written to {}", count, path.display());
+            }
+        }
+        sentri::cli::Commands::VerifyEvidence {
+            results_file,
+            capture_dir,
+        } => {
+            let mut results = stream_results(results_file);
+            let mut evidence = Evidence::load(capture_dir, storage_key.clone()).await?;
+            let (mut verified, mut missing) = (0, 0);
+            while let Some(result) = results.next().await {
+                let result = result?;
+                let Some(response_sha256) = &result.response_sha256 else {
+                    continue;
+                };
+                let response = evidence.find(&result.domain, response_sha256).await?;
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "domain": result.domain,
+                        "response_sha256": response_sha256,
+                        "response": response,
+                    })
+                );
+                match response {
+                    Some(_) => verified += 1,
+                    None => missing += 1,
+                }
+            }
+            if missing > 0 {
+                anyhow::bail!("{} findings have no matching captured response", missing);
+            }
+            info!("All {} findings match a captured response", verified);
+        }
+        sentri::cli::Commands::Lookup {
+            results_file,
+            domains,
+        } => {
+            let mut sink = format_sink(cli.format.unwrap_or_default(), None, None).await?;
+            let mut missing = 0;
+            for (domain, result) in find_results(results_file, domains).await? {
+                match result {
+                    Some(result) => sink.write(&result).await?,
+                    None => {
+                        warn!(domain = %domain, "No result for domain");
+                        missing += 1;
+                    }
+                }
+            }
+            sink.close().await?;
+            if missing > 0 {
+                anyhow::bail!("{} of {} domains have no result", missing, domains.len());
+            }
+        }
+        sentri::cli::Commands::Baseline { action } => match action {
+            BaselineAction::Create {
+                results_file,
+                baseline_file,
+            } => {
+                let mut results = stream_results(results_file);
+                let mut baseline = Baseline::empty();
+                while let Some(result) = results.next().await {
+                    baseline.add(&result?);
+                }
+                baseline.save(baseline_file).await?;
+                info!(
+                    "Baseline with {} domains written to {:?}",
+                    baseline.domains.len(),
+                    baseline_file
+                );
+            }
+            BaselineAction::Check {
+                results_file,
+                baseline_file,
+            } => {
+                let baseline = Baseline::load(baseline_file).await?;
+                let mut results = stream_results(results_file);
+                let mut check = baseline.start_check();
+                while let Some(result) = results.next().await {
+                    check.add(&result?);
+                }
+                let drifts = check.finish();
+
+                for drift in &drifts {
+                    println!("{}", serde_json::to_string(drift)?);
+                }
+
+                if !drifts.is_empty() {
+                    anyhow::bail!("{} drifts from baseline {:?}", drifts.len(), baseline_file);
+                }
+                info!("Results match baseline {:?}", baseline_file);
+            }
+        },
+        sentri::cli::Commands::VerifyOwnership { action } => {
+            let mut store = OwnershipStore::load(&cli.ownership_file).await?;
+            match action {
+                OwnershipAction::Issue { domain } => {
+                    let record = store.issue(domain, chrono::Utc::now())?.clone();
+                    store.save(&cli.ownership_file).await?;
+                    println!("{}. TXT \"{}\"", domain, record.txt_value());
+                    info!(
+                        "Publish the record above, then run `sentri verify-ownership check --domain {}`",
+                        domain
+                    );
+                }
+                OwnershipAction::Check { domain } => {
+                    if store.get(domain).is_none() {
+                        anyhow::bail!(
+                            "No token issued for {}; run `verify-ownership issue` first",
+                            domain
+                        );
+                    }
+                    let txt_records = dns_resolver(&cli.resolvers)?.resolve_txt(domain).await?;
+                    if !store.record_verification(domain, &txt_records, chrono::Utc::now()) {
+                        anyhow::bail!(
+                            "Verification token not found in the TXT records of {}",
+                            domain
+                        );
+                    }
+                    store.save(&cli.ownership_file).await?;
+                    info!("Ownership of {} verified", domain);
+                }
+                OwnershipAction::List => {
+                    println!("{}", serde_json::to_string_pretty(&store.domains)?);
+                }
+            }
+        }
+        sentri::cli::Commands::Watch {
+            input_file,
+            interval_secs,
+            rate_limit,
+            max_iterations,
+            tombstone_after,
+            alerts,
+        } => {
+            info!("Watching domains from file: {:?}", input_file);
+            let alerters = build_alerters(alerts, Duration::from_millis(cli.timeout_ms))?;
+            let limiter = Arc::new(RateLimiter::new(
+                *rate_limit as usize,
+                60_000,
+                checker.concurrent_limit(),
+            ));
+            let live = live_config(
+                cli.config.as_deref(),
+                config,
+                limiter,
+                *rate_limit,
+                &checker,
+            )
+            .await?;
+            let _reloader = AbortOnDrop(live.spawn_watcher());
+            let notifier = Notifier::from_env();
+            notifier.ready();
+            notifier.arm_watchdog();
+            run_watch(
+                &checker,
+                input_file,
+                Duration::from_secs(*interval_secs),
+                &live,
+                *max_iterations,
+                *tombstone_after,
+                &alerters,
+            )
+            .await?;
+        }
+        sentri::cli::Commands::UpdateData {
+            only,
+            service_tags_url,
+        } => {
+            let data_dir =
+                data_dir.ok_or_else(|| anyhow::anyhow!("No data directory; pass --data-dir"))?;
+            let datasets = if only.is_empty() {
+                DataSet::ALL.to_vec()
+            } else {
+                only.clone()
+            };
+
+            // Service tag files are several megabytes, so allow more than the scan timeout
+            let client = HttpClient::builder()
+                .timeout(Duration::from_millis(cli.timeout_ms).max(DOWNLOAD_TIMEOUT))
+                .http2_prior_knowledge(false)
+                .build()?;
+            let outcomes =
+                update_data(&client, &data_dir, &datasets, service_tags_url.as_deref()).await?;
+
+            for outcome in &outcomes {
+                println!("{}", serde_json::to_string(outcome)?);
+            }
+
+            let failed = outcomes.iter().filter(|o| o.is_failed()).count();
+            if failed > 0 {
+                anyhow::bail!(
+                    "{} of {} data sets could not be updated",
+                    failed,
+                    outcomes.len()
+                );
+            }
+            info!("Enrichment data in {:?} is up to date", data_dir);
+        }
+        #[cfg(feature = "server")]
+        sentri::cli::Commands::Serve {
+            listen,
+            state_dir,
+            rate_limit,
+            api_keys,
+            retention,
+            audit_retention,
+        } => {
+            let api_keys = match api_keys {
+                Some(path) => Some(ApiKeys::load(path).await?),
+                None => None,
+            };
+            let mut budget = RateBudget::new(*rate_limit, checker.concurrent_limit());
+            for key in api_keys.iter().flat_map(|keys| keys.keys()) {
+                budget = budget.with_owner(&key.name, key.rate_limit);
+            }
+            let budget = Arc::new(budget);
+            let live = live_config(
+                cli.config.as_deref(),
+                config,
+                Arc::clone(budget.shared()),
+                *rate_limit,
+                &checker,
+            )
+            .await?;
+            let _reloader = AbortOnDrop(live.spawn_watcher());
+
+            let mut scheduler = Scheduler::open(checker.clone(), state_dir, Arc::clone(&budget))
+                .await?
+                .with_live_config(live);
+            let mut jobs = JobManager::new(checker, state_dir, budget).await?;
+            match &storage_key {
+                Some(key) => {
+                    scheduler = scheduler.with_storage_key(Arc::clone(key));
+                    jobs = jobs.with_storage_key(Arc::clone(key));
+                    info!("Stored results and audit records are encrypted");
+                }
+                None => warn!("No storage key configured; stored results are not encrypted"),
+            }
+
+            let audit_log =
+                AuditLog::open(&state_dir.join(AUDIT_LOG_FILE), storage_key.clone()).await?;
+            if let Some(report) = integrity {
+                audit_log.record(&AuditRecord::integrity(report)).await?;
+            }
+            let mut state = ServerState::new(scheduler, jobs)
+                .with_audit_log(audit_log)
+                .with_retention(RetentionPolicy {
+                    results: *retention,
+                    audit: *audit_retention,
+                });
+            match api_keys {
+                Some(keys) => {
+                    info!(
+                        "API key authentication enabled for {} keys",
+                        keys.keys().len()
+                    );
+                    state = state.with_api_keys(keys);
+                }
+                None => warn!("No API keys configured; the server API is unauthenticated"),
+            }
+
+            let listener = tokio::net::TcpListener::bind(listen).await?;
+            let notifier = Notifier::from_env();
+            notifier.ready();
+            notifier.status(&format!("Listening on {}", listener.local_addr()?));
+            notifier.arm_watchdog();
+            serve(listener, state).await?;
+        }
+        #[cfg(not(feature = "server"))]
+        sentri::cli::Commands::Serve { .. } | sentri::cli::Commands::Purge { .. } => {
+            anyhow::bail!("sentri was built without the server feature")
+        }
+        sentri::cli::Commands::Schema => {
+            println!("{}", serde_json::to_string_pretty(&domain_result_schema())?);
+        }
+        sentri::cli::Commands::Decrypt { input_file } => {
+            let key = storage_key.context("--storage-key is required")?;
+            let content = tokio::fs::read_to_string(input_file).await?;
+            let mut decoder = LineDecoder::new();
+            for (index, line) in content.lines().enumerate() {
+                let line = decoder
+                    .decode(Some(&key), line)
+                    .with_context(|| format!("Unreadable line {}", index + 1))?;
+                if let Some(line) = line {
+                    println!("{}", line);
+                }
+            }
+        }
+        sentri::cli::Commands::Service { action } => match action {
+            ServiceAction::Unit {
+                user,
+                watchdog,
+                args,
+            } => {
+                let executable =
+                    std::env::current_exe().context("Failed to locate the sentri executable")?;
+                print!(
+                    "{}",
+                    unit_file(
+                        &executable.to_string_lossy(),
+                        args,
+                        user.as_deref(),
+                        *watchdog
+                    )
+                );
+            }
+            #[cfg(windows)]
+            ServiceAction::Install {
+                name,
+                account,
+                args,
+            } => {
+                let executable =
+                    std::env::current_exe().context("Failed to locate the sentri executable")?;
+                sentri::service::windows::install(name, &executable, account.as_deref(), args)?;
+                info!(
+                    "Registered Windows service {}; start it with sc start {}",
+                    name, name
+                );
+            }
+            #[cfg(windows)]
+            ServiceAction::Uninstall { name } => {
+                sentri::service::windows::uninstall(name)?;
+                info!("Removed Windows service {}", name);
+            }
+            #[cfg(windows)]
+            ServiceAction::Run { .. } => {
+                anyhow::bail!("sentri service run is started by the Service Control Manager")
+            }
+        },
+        #[cfg(feature = "server")]
+        sentri::cli::Commands::Purge {
+            state_dir,
+            older_than,
+            audit_older_than,
+            dry_run,
+        } => {
+            let policy = RetentionPolicy {
+                results: *older_than,
+                audit: audit_older_than.or(*older_than),
+            };
+            if policy.is_unlimited() {
+                anyhow::bail!("Nothing to purge; pass --older-than or --audit-older-than");
+            }
+            let report = purge(
+                state_dir,
+                &policy,
+                chrono::Utc::now(),
+                *dry_run,
+                storage_key.as_ref(),
+            )
+            .await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
         }
     }
 
     Ok(())
 }
+
+/// Creates the reloadable configuration of `serve` and `watch`, applying its
+/// `[rate_limit]` section to `limiter`
+async fn live_config(
+    path: Option<&Path>,
+    config: ConfigFile,
+    limiter: Arc<RateLimiter>,
+    rate_limit: u64,
+    checker: &MdiChecker,
+) -> anyhow::Result<Arc<LiveConfig>> {
+    let live = LiveConfig::new(
+        path,
+        config,
+        limiter,
+        rate_limit,
+        checker.concurrent_limit(),
+    );
+    live.apply().await?;
+    Ok(Arc::new(live))
+}
+
+/// Aborts a background task when dropped, e.g. when its command returns
+struct AbortOnDrop(tokio::task::JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
@@ -0,0 +1,317 @@
+//! Completion notifications for batch runs
+//!
+//! Sends a short report by email when a batch completes or fails, so
+//! operators running long scans unattended learn about the outcome without
+//! polling the worker. The report contains the aggregate batch summary and can
+//! optionally carry an HTML rendering of it as an attachment.
+//!
+//! SMTP delivery is only compiled with the `email` cargo feature; rendering is
+//! always available.
+//!
+//! # Security Considerations
+//!
+//! - **Transport Security**: Mail is submitted over STARTTLS by default, or implicit
+//!   TLS when requested; plaintext SMTP is not supported
+//!   (security:network:secure_tls_versions)
+//! - **Output Sanitization**: Error messages are escaped before being embedded in the
+//!   HTML report (security:output:sanitize_all_output)
+
+use anyhow::Result;
+use html_escape::encode_text;
+
+use crate::cli::NotifyArgs;
+use crate::core::{BatchSummary, Truncation};
+use crate::latency::SlowHost;
+use crate::stats::DetectorSummary;
+
+/// Final outcome of a run reported in notifications
+#[derive(Debug, Clone)]
+pub enum RunOutcome {
+    /// The batch finished and produced a summary
+    Completed(BatchSummary),
+    /// The batch aborted with the given error
+    Failed(String),
+}
+
+/// SMTP settings and recipients for report delivery
+#[derive(Debug, Clone)]
+pub struct EmailConfig {
+    /// Report recipients
+    pub recipients: Vec<String>,
+    /// Sender address
+    pub from: String,
+    /// SMTP relay host name
+    pub smtp_host: String,
+    /// SMTP port, defaulting to 587 (STARTTLS) or 465 (implicit TLS)
+    pub smtp_port: Option<u16>,
+    /// Optional SMTP user name
+    pub username: Option<String>,
+    /// Optional SMTP password
+    pub password: Option<String>,
+    /// Use implicit TLS instead of STARTTLS
+    pub implicit_tls: bool,
+    /// Attach an HTML rendering of the report
+    pub attach_html: bool,
+}
+
+impl EmailConfig {
+    /// Derives the email settings from command-line options
+    ///
+    /// # Returns
+    /// * `Option<Self>` - The settings, or None if no recipients were given
+    pub fn from_args(args: &NotifyArgs) -> Option<Self> {
+        if args.email_report.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            recipients: args.email_report.clone(),
+            from: args.email_from.clone(),
+            smtp_host: args.smtp_host.clone()?,
+            smtp_port: args.smtp_port,
+            username: args.smtp_username.clone(),
+            password: args.smtp_password.clone(),
+            implicit_tls: args.smtp_implicit_tls,
+            attach_html: args.email_attach_html,
+        })
+    }
+}
+
+/// Builds the subject line for a report
+///
+/// # Examples
+///
+/// ```
+/// use sentri::notify::{report_subject, RunOutcome};
+///
+/// let subject = report_subject(&RunOutcome::Failed("disk full".to_string()));
+/// assert!(subject.contains("failed"));
+/// ```
+pub fn report_subject(outcome: &RunOutcome) -> String {
+    match outcome {
+        RunOutcome::Completed(summary) => match &summary.truncated {
+            Some(truncation) => format!(
+                "[sentri] Batch truncated: {} domains, {} with MDI, {} unprocessed",
+                summary.domains_processed, summary.mdi_instances, truncation.unprocessed
+            ),
+            None => format!(
+                "[sentri] Batch completed: {} domains, {} with MDI",
+                summary.domains_processed, summary.mdi_instances
+            ),
+        },
+        RunOutcome::Failed(_) => "[sentri] Batch failed".to_string(),
+    }
+}
+
+/// Renders the plain-text report body
+///
+/// # Arguments
+/// * `outcome` - Outcome of the run
+///
+/// # Returns
+/// * `String` - Human-readable report
+pub fn render_text(outcome: &RunOutcome) -> String {
+    match outcome {
+        RunOutcome::Completed(summary) => {
+            let mut report = format!(
+                "Sentri batch completed.\n\n\
+                 Started:            {}\n\
+                 Duration:           {} ms\n\
+                 Domains processed:  {}\n\
+                 Tenants found:      {}\n\
+                 MDI instances:      {}\n\
+                 Errors:             {}\n",
+                summary.started_at.to_rfc3339(),
+                summary.elapsed_ms,
+                summary.domains_processed,
+                summary.tenants_found,
+                summary.mdi_instances,
+                summary.errors,
+            );
+            if !summary.slow_hosts.is_empty() {
+                report.push_str("\nSlow hosts:\n");
+                for slow in &summary.slow_hosts {
+                    report.push_str(&format!("  {}\n", describe_slow_host(slow)));
+                }
+            }
+            if !summary.detectors.is_empty() {
+                report.push_str("\nDetectors:\n");
+                for detector in &summary.detectors {
+                    report.push_str(&format!(
+                        "  {:<11} {}\n",
+                        detector.detector.as_str(),
+                        describe_detector(detector)
+                    ));
+                }
+            }
+            if let Some(truncation) = &summary.truncated {
+                report.push_str(&format!("\n{}\n", describe_truncation(truncation)));
+            }
+            report
+        }
+        RunOutcome::Failed(error) => format!("Sentri batch failed.\n\nError: {}\n", error),
+    }
+}
+
+/// Renders the report as a standalone HTML document
+///
+/// # Arguments
+/// * `outcome` - Outcome of the run
+///
+/// # Returns
+/// * `String` - HTML document with all dynamic content escaped
+pub fn render_html(outcome: &RunOutcome) -> String {
+    let body = match outcome {
+        RunOutcome::Completed(summary) => {
+            let rows = [
+                ("Started", summary.started_at.to_rfc3339()),
+                ("Duration (ms)", summary.elapsed_ms.to_string()),
+                ("Domains processed", summary.domains_processed.to_string()),
+                ("Tenants found", summary.tenants_found.to_string()),
+                ("MDI instances", summary.mdi_instances.to_string()),
+                ("Errors", summary.errors.to_string()),
+            ];
+            let slow_hosts = summary.slow_hosts.iter().map(describe_slow_host);
+            let truncation = summary.truncated.as_ref().map(describe_truncation);
+            let detectors = summary.detectors.iter().map(|detector| {
+                (
+                    format!("Detector {}", detector.detector),
+                    describe_detector(detector),
+                )
+            });
+            let rows: String = rows
+                .into_iter()
+                .map(|(label, value)| (label.to_string(), value))
+                .chain(slow_hosts.map(|slow| ("Slow host".to_string(), slow)))
+                .chain(detectors)
+                .chain(truncation.map(|truncation| ("Truncated".to_string(), truncation)))
+                .map(|(label, value)| {
+                    format!(
+                        "<tr><th>{}</th><td>{}</td></tr>",
+                        label,
+                        encode_text(&value)
+                    )
+                })
+                .collect();
+            format!("<h1>Sentri batch completed</h1><table>{}</table>", rows)
+        }
+        RunOutcome::Failed(error) => {
+            format!("<h1>Sentri batch failed</h1><p>{}</p>", encode_text(error))
+        }
+    };
+
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Sentri report</title></head><body>{}</body></html>",
+        body
+    )
+}
+
+/// One line describing where a batch stopped at its maximum run time
+fn describe_truncation(truncation: &Truncation) -> String {
+    format!(
+        "Stopped after {} ms; {} domains unprocessed, listed in {}",
+        truncation.max_runtime_ms,
+        truncation.unprocessed,
+        truncation.checkpoint.display()
+    )
+}
+
+/// One line describing the outcomes and latencies of a detector
+fn describe_detector(detector: &DetectorSummary) -> String {
+    format!(
+        "{} ok, {} failed; p50 {} ms, p95 {} ms, p99 {} ms, max {} ms",
+        detector.successes,
+        detector.failures,
+        detector.p50_ms,
+        detector.p95_ms,
+        detector.p99_ms,
+        detector.max_ms
+    )
+}
+
+/// One line describing a host flagged for slow responses
+fn describe_slow_host(slow: &SlowHost) -> String {
+    format!(
+        "{} (p95 {} ms over {} requests, threshold {} ms, flagged {}x)",
+        slow.host, slow.p95_ms, slow.samples, slow.threshold_ms, slow.times_flagged
+    )
+}
+
+/// Sends the report for a run to all configured recipients
+///
+/// # Arguments
+/// * `config` - SMTP settings and recipients
+/// * `outcome` - Outcome of the run
+///
+/// # Returns
+/// * `Result<()>` - Success or error if the message could not be built or delivered
+#[cfg(feature = "email")]
+pub async fn send_report(config: &EmailConfig, outcome: &RunOutcome) -> Result<()> {
+    use anyhow::Context;
+    use lettre::message::{header::ContentType, Attachment, Mailbox, MultiPart, SinglePart};
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+    use tracing::info;
+
+    crate::offline::ensure_online(format_args!("email via {}", config.smtp_host))?;
+    let mut builder = Message::builder()
+        .from(
+            config
+                .from
+                .parse::<Mailbox>()
+                .context("Invalid sender address")?,
+        )
+        .subject(report_subject(outcome));
+    for recipient in &config.recipients {
+        builder = builder.to(recipient
+            .parse::<Mailbox>()
+            .with_context(|| format!("Invalid recipient address: {}", recipient))?);
+    }
+
+    let text = SinglePart::plain(render_text(outcome));
+    let message = if config.attach_html {
+        builder.multipart(
+            MultiPart::mixed().singlepart(text).singlepart(
+                Attachment::new("sentri-report.html".to_string())
+                    .body(render_html(outcome), ContentType::TEXT_HTML),
+            ),
+        )
+    } else {
+        builder.singlepart(text)
+    }
+    .context("Failed to build report email")?;
+
+    let mut transport = if config.implicit_tls {
+        AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+    }
+    .context("Failed to configure SMTP transport")?;
+
+    if let Some(port) = config.smtp_port {
+        transport = transport.port(port);
+    }
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    transport
+        .build()
+        .send(message)
+        .await
+        .context("Failed to send report email")?;
+
+    info!("Report emailed to {} recipients", config.recipients.len());
+    Ok(())
+}
+
+/// Sends the report for a run to all configured recipients
+///
+/// This build does not include the `email` feature, so sending always fails
+/// with an explanatory error.
+#[cfg(not(feature = "email"))]
+pub async fn send_report(_config: &EmailConfig, _outcome: &RunOutcome) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "Cannot send email report: sentri was built without the email feature"
+    ))
+}
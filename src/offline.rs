@@ -0,0 +1,71 @@
+//! Offline mode for air-gapped analysis environments
+//!
+//! After `--offline` (see [`set_offline`]) no network operation is attempted.
+//! HTTP requests, DNS queries, object storage uploads and report emails fail
+//! immediately with an [`OfflineError`] instead of waiting for timeouts, while
+//! everything that works on local data keeps working: parsing and reporting of
+//! result files, diffs, policies, names answered by DNS overrides and results
+//! already in the checker's cache.
+//!
+//! Callers can tell these failures apart from real network errors by
+//! downcasting:
+//!
+//! ```
+//! use sentri::offline::{ensure_online, set_offline, OfflineError};
+//!
+//! set_offline(true);
+//! let error = ensure_online("GET https://example.com").unwrap_err();
+//! assert!(error.downcast_ref::<OfflineError>().is_some());
+//! assert_eq!(
+//!     error.to_string(),
+//!     "Network access disabled by --offline: GET https://example.com"
+//! );
+//! # set_offline(false);
+//! ```
+
+use anyhow::Result;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the process runs offline
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables offline mode for the whole process
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Returns true if network operations are disabled
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// A network operation refused in offline mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfflineError {
+    /// The refused operation, e.g. the request method and URL
+    pub operation: String,
+}
+
+impl fmt::Display for OfflineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Network access disabled by --offline: {}",
+            self.operation
+        )
+    }
+}
+
+impl std::error::Error for OfflineError {}
+
+/// Fails with an [`OfflineError`] for `operation` if offline mode is enabled
+pub fn ensure_online(operation: impl fmt::Display) -> Result<()> {
+    if is_offline() {
+        return Err(OfflineError {
+            operation: operation.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
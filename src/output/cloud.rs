@@ -0,0 +1,102 @@
+//! Batch output written straight to cloud object storage
+//!
+//! `sentri batch --output-file` also takes an object store URL, so scheduled
+//! scans running in containers stream their results to a bucket without
+//! needing local disk:
+//!
+//! ```text
+//! sentri batch --input-file domains.txt --output-file s3://scan-results/acme/results.jsonl
+//! sentri batch --input-file domains.txt --output-file azblob://scans/results.jsonl.gz --compress gzip
+//! ```
+//!
+//! Supported URLs are `s3://bucket/key`, `azblob://container/key` (also
+//! `az://`, `abfs://` and `abfss://`) and `gs://bucket/key`. Results are
+//! buffered in memory and sent in multipart upload parts; the object only
+//! appears once the batch ends, so an interrupted run leaves nothing behind.
+//! Credentials come from the provider environment variables, as with
+//! `--upload-to` (see [`crate::upload`]).
+//!
+//! Only line formats can be streamed this way, optionally compressed (see
+//! [`super::compression`]). The object store integration is only compiled
+//! with the `object-store` cargo feature.
+
+use anyhow::Result;
+use std::path::Path;
+
+use super::compression::{Compression, OutputWriter};
+
+/// URL schemes of object store outputs
+const OBJECT_SCHEMES: &[&str] = &["s3", "azblob", "az", "abfs", "abfss", "gs"];
+
+/// Returns true when an output path is an object store URL rather than a local file
+///
+/// # Examples
+///
+/// ```
+/// use sentri::output::cloud::is_object_url;
+/// use std::path::Path;
+///
+/// assert!(is_object_url(Path::new("s3://scan-results/results.jsonl")));
+/// assert!(is_object_url(Path::new("azblob://scans/results.jsonl")));
+/// assert!(!is_object_url(Path::new("results/s3.jsonl")));
+/// assert!(!is_object_url(Path::new("file:///tmp/results.jsonl")));
+/// ```
+pub fn is_object_url(path: &Path) -> bool {
+    path.to_str()
+        .and_then(|url| url.split_once("://"))
+        .is_some_and(|(scheme, _)| {
+            OBJECT_SCHEMES
+                .iter()
+                .any(|known| scheme.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Opens a streaming upload to `url`, compressing it with `compression`
+///
+/// Besides the URLs of [`is_object_url`], any URL understood by the
+/// `object_store` crate is accepted, such as `file:///tmp/results.jsonl`.
+/// The object is only complete after the returned writer has been shut down.
+///
+/// # Errors
+/// * The crate was built without the `object-store` feature
+/// * Sentri runs offline, or the URL is not a supported object store
+#[cfg(feature = "object-store")]
+pub async fn create_object_writer(
+    url: &str,
+    compression: Option<Compression>,
+) -> Result<OutputWriter> {
+    use anyhow::Context;
+    use object_store::buffered::BufWriter;
+
+    crate::offline::ensure_online(format_args!("write results to {}", url))?;
+    // object_store names the Azure Blob scheme `az`
+    let target = match url.split_once("://") {
+        Some((scheme, rest)) if scheme.eq_ignore_ascii_case("azblob") => format!("az://{}", rest),
+        _ => url.to_string(),
+    };
+    let parsed =
+        reqwest::Url::parse(&target).with_context(|| format!("Invalid output URL: {}", url))?;
+    let (store, path) = crate::upload::open_store(&parsed, Vec::new())
+        .with_context(|| format!("Unsupported output URL: {}", url))?;
+
+    let writer = BufWriter::new(store.into(), path);
+    Ok(match compression {
+        Some(compression) => compression.encoder(writer),
+        None => Box::new(writer),
+    })
+}
+
+/// Opens a streaming upload to `url`
+///
+/// This build does not include the `object-store` feature, so every object
+/// store output fails with an explanatory error.
+#[cfg(not(feature = "object-store"))]
+pub async fn create_object_writer(
+    url: &str,
+    _compression: Option<Compression>,
+) -> Result<OutputWriter> {
+    Err(anyhow::anyhow!(
+        "Cannot write results to {}: sentri was built without the object-store feature",
+        url
+    ))
+}
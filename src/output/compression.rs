@@ -0,0 +1,59 @@
+//! Compression of batch output files
+//!
+//! `sentri batch --compress gzip|zstd` streams the output file through an
+//! async compressor while results are written, so huge runs never hit the
+//! disk uncompressed and need no post-processing step:
+//!
+//! ```text
+//! sentri batch --input-file domains.txt --output-file results.jsonl.gz --compress gzip
+//! zcat results.jsonl.gz | jq -r 'select(.mdi_instance) | .domain'
+//! ```
+//!
+//! Only line formats (`json`, `jsonl`, `csv`, `table`, `cef`, `leef` and
+//! `--fields`) are compressed; documents such as Parquet and Excel workbooks
+//! are already compressed or written in one piece. The compressed stream is
+//! finished when the batch ends, so an interrupted run leaves a truncated
+//! file whose complete lines can still be decompressed.
+
+use anyhow::{Context, Result};
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+use clap::ValueEnum;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::AsyncWrite;
+
+/// Destination of compressed output
+pub type OutputWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Compression of an output file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    /// gzip, read by zcat and most log shippers
+    Gzip,
+    /// Zstandard, faster and smaller than gzip
+    Zstd,
+}
+
+impl Compression {
+    /// Wraps `writer` in a compressor
+    ///
+    /// The compressed stream is only complete after the returned writer has
+    /// been shut down.
+    pub fn encoder<W>(self, writer: W) -> OutputWriter
+    where
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        match self {
+            Compression::Gzip => Box::new(GzipEncoder::new(writer)),
+            Compression::Zstd => Box::new(ZstdEncoder::new(writer)),
+        }
+    }
+}
+
+/// Creates (or truncates) `path` and compresses everything written to it
+pub async fn create_compressed(path: &Path, compression: Compression) -> Result<OutputWriter> {
+    let file = File::create(path)
+        .await
+        .with_context(|| format!("Failed to create output file {}", path.display()))?;
+    Ok(compression.encoder(file))
+}
@@ -0,0 +1,146 @@
+//! Field selection for `--fields`
+//!
+//! `--fields domain,tenant,mdi_instance` writes only the named keys of each
+//! result in `json` and `jsonl` output, so consumers piping into `jq` need
+//! not wade through large `federated_domains` arrays they never read. Keys
+//! appear in the requested order; a field the result omits, such as an
+//! absent `response_sha256`, is written as `null`.
+//!
+//! Field names are those of the result schema printed by `sentri schema`.
+
+use anyhow::{bail, Result};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+
+use super::{Formatter, OutputFormat};
+use crate::core::DomainResult;
+use crate::result_schema::domain_result_schema;
+
+/// Validated list of result fields to write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSelection {
+    fields: Vec<String>,
+}
+
+impl FieldSelection {
+    /// Validates field names, returning `None` when the list is empty
+    ///
+    /// # Errors
+    /// * A name is not a field of results, or is given twice
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::output::fields::FieldSelection;
+    ///
+    /// let fields = ["domain".to_string(), "tenant".to_string()];
+    /// assert!(FieldSelection::new(&fields).unwrap().is_some());
+    /// assert!(FieldSelection::new(&[]).unwrap().is_none());
+    /// assert!(FieldSelection::new(&["tennant".to_string()]).is_err());
+    /// ```
+    pub fn new(fields: &[String]) -> Result<Option<Self>> {
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        let schema = domain_result_schema();
+        let known = schema["properties"]
+            .as_object()
+            .map(|properties| properties.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let mut selected: Vec<String> = Vec::with_capacity(fields.len());
+        for field in fields.iter().map(|field| field.trim()) {
+            if !known.iter().any(|name| name == field) {
+                bail!(
+                    "Unknown result field '{}' in --fields; expected one of: {}",
+                    field,
+                    known.join(", ")
+                );
+            }
+            if selected.iter().any(|name| name == field) {
+                bail!("Result field '{}' is listed twice in --fields", field);
+            }
+            selected.push(field.to_string());
+        }
+        Ok(Some(Self { fields: selected }))
+    }
+
+    /// Names of the selected fields, in the requested order
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Returns the selected fields of `result`
+    pub fn select(&self, result: &DomainResult) -> Result<SelectedFields> {
+        let mut full = match serde_json::to_value(result)? {
+            Value::Object(full) => full,
+            _ => bail!("Result did not serialize to an object"),
+        };
+        Ok(SelectedFields(
+            self.fields
+                .iter()
+                .map(|field| (field.clone(), full.remove(field).unwrap_or(Value::Null)))
+                .collect(),
+        ))
+    }
+}
+
+/// Selected fields of one result, serializing as an object in selection order
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectedFields(pub Vec<(String, Value)>);
+
+impl Serialize for SelectedFields {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (field, value) in &self.0 {
+            map.serialize_entry(field, value)?;
+        }
+        map.end()
+    }
+}
+
+/// JSON or JSONL writing only the selected fields of each result
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::output::fields::{FieldSelection, FieldsFormatter};
+/// use sentri::output::{Formatter, OutputFormat};
+///
+/// let fields = FieldSelection::new(&["tenant".to_string(), "domain".to_string()])
+///     .unwrap()
+///     .unwrap();
+/// let formatter = FieldsFormatter::new(OutputFormat::Jsonl, fields).unwrap();
+/// let result = DomainResult { domain: "contoso.com".to_string(), ..Default::default() };
+/// assert_eq!(formatter.format(&result).unwrap(), r#"{"tenant":null,"domain":"contoso.com"}"#);
+/// ```
+pub struct FieldsFormatter {
+    fields: FieldSelection,
+    pretty: bool,
+}
+
+impl FieldsFormatter {
+    /// Creates the formatter for `format`
+    ///
+    /// # Errors
+    /// * The format is neither `json` nor `jsonl`
+    pub fn new(format: OutputFormat, fields: FieldSelection) -> Result<Self> {
+        let pretty = match format {
+            OutputFormat::Json => true,
+            OutputFormat::Jsonl => false,
+            _ => bail!("--fields only applies to json and jsonl output"),
+        };
+        Ok(Self { fields, pretty })
+    }
+}
+
+impl Formatter for FieldsFormatter {
+    fn format(&self, result: &DomainResult) -> Result<String> {
+        let selected = self.fields.select(result)?;
+        Ok(if self.pretty {
+            serde_json::to_string_pretty(&selected)?
+        } else {
+            serde_json::to_string(&selected)?
+        })
+    }
+}
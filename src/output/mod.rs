@@ -0,0 +1,317 @@
+//! Rendering of domain results for `--format`
+//!
+//! Every command printing results, `single`, `batch` and `lookup`, renders
+//! them through the same [`Formatter`] selected with the global `--format`
+//! flag:
+//!
+//! - `json`: one pretty-printed JSON object per result, the default on a terminal
+//! - `jsonl`: one compact JSON object per line, the default for output files
+//! - `csv`: a header row and one row per result, for spreadsheets
+//! - `table`: aligned columns for quick triage in a terminal
+//! - `cef` and `leef`: one SIEM event per line for ArcSight and QRadar (see [`siem`])
+//!
+//! With `--fields`, `json` and `jsonl` only write the selected keys of each
+//! result (see [`fields`]).
+//!
+//! Batch runs can also write Apache Parquet files for analytics engines (see
+//! [`parquet`], built with `--features parquet`) and Excel workbooks (see
+//! [`xlsx`], built with `--features xlsx`).
+//!
+//! The `junit`, `grepable`, `parquet`, `markdown`, `xlsx`, `dot` and `graphml`
+//! formats are whole documents with a header and trailer; their sinks (see
+//! [`crate::sinks`]) render them without a per-result formatter.
+//!
+//! ```text
+//! DOMAIN                          TENANT            MDI  INSTANCE                                  GENERATION  TIME    TAGS     ERROR
+//! contoso.com                     contoso           yes  contososensorapi.atp.azure.com            legacy      123ms   bu=emea  -
+//! ```
+//!
+//! # Security Considerations
+//!
+//! - **Sanitized Output**: Formatters render results that already passed
+//!   through the sanitize module; control characters are additionally
+//!   replaced in CSV and table cells so a value cannot break the layout
+//!   (security:output:sanitize_all_output)
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+use crate::core::{DomainResult, MdiGeneration};
+use crate::tags::format_tags;
+
+pub mod cloud;
+pub mod compression;
+pub mod fields;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod siem;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
+
+pub use siem::{CefFormatter, LeefFormatter};
+
+/// Value written for missing fields in tables
+const MISSING: &str = "-";
+
+/// Output format of results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// One pretty-printed JSON object per result
+    Json,
+    /// One compact JSON object per line, also accepted as `ndjson`
+    #[default]
+    #[value(alias = "ndjson")]
+    Jsonl,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Aligned columns for reading in a terminal
+    Table,
+    /// JUnit XML report with one test case per domain
+    Junit,
+    /// One line of tab-separated fields per domain, for grep and cut
+    Grepable,
+    /// Apache Parquet file for DuckDB, Spark and pandas (requires an output file)
+    Parquet,
+    /// ArcSight Common Event Format, one event per line
+    Cef,
+    /// QRadar Log Event Extended Format, one event per line
+    Leef,
+    /// Markdown executive summary with one table row per tenant
+    Markdown,
+    /// Excel workbook with result and tenant sheets (requires an output file)
+    Xlsx,
+    /// Graphviz DOT graph of tenants, domains and MDI instances
+    Dot,
+    /// GraphML graph of tenants, domains and MDI instances, for Gephi
+    Graphml,
+}
+
+/// Renders results as text, one result at a time
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::output::{formatter, OutputFormat};
+///
+/// let result = DomainResult {
+///     domain: "contoso.com".to_string(),
+///     tenant: Some("contoso".to_string()),
+///     ..Default::default()
+/// };
+/// let csv = formatter(OutputFormat::Csv).unwrap();
+/// assert!(csv.header().unwrap().starts_with("domain,tenant,mdi,"));
+/// assert!(csv.format(&result).unwrap().starts_with("contoso.com,contoso,false,"));
+/// ```
+pub trait Formatter: Send + Sync {
+    /// Line written once before the first result, e.g. column names
+    fn header(&self) -> Option<String> {
+        None
+    }
+
+    /// Renders one result, without a trailing line break
+    fn format(&self, result: &DomainResult) -> Result<String>;
+}
+
+/// Pretty-printed JSON
+pub struct JsonFormatter;
+
+impl Formatter for JsonFormatter {
+    fn format(&self, result: &DomainResult) -> Result<String> {
+        Ok(serde_json::to_string_pretty(result)?)
+    }
+}
+
+/// Compact JSON, one object per line
+pub struct JsonlFormatter;
+
+impl Formatter for JsonlFormatter {
+    fn format(&self, result: &DomainResult) -> Result<String> {
+        Ok(serde_json::to_string(result)?)
+    }
+}
+
+/// Columns of CSV output
+const CSV_COLUMNS: &[&str] = &[
+    "domain",
+    "tenant",
+    "mdi",
+    "mdi_instance",
+    "mdi_generation",
+    "federated_domains",
+    "processing_time_ms",
+    "error",
+    "error_class",
+    "checked_at",
+    "tags",
+];
+
+/// Comma-separated values as described by RFC 4180
+///
+/// Federated domains and tags are joined with `;` into a single cell each.
+pub struct CsvFormatter;
+
+impl Formatter for CsvFormatter {
+    fn header(&self) -> Option<String> {
+        Some(CSV_COLUMNS.join(","))
+    }
+
+    fn format(&self, result: &DomainResult) -> Result<String> {
+        let cells = [
+            result.domain.clone(),
+            result.tenant.clone().unwrap_or_default(),
+            result.mdi_instance.is_some().to_string(),
+            result.mdi_instance.clone().unwrap_or_default(),
+            generation(result).unwrap_or_default().to_string(),
+            result.federated_domains.join(";"),
+            result.processing_time_ms.to_string(),
+            result.error.clone().unwrap_or_default(),
+            result
+                .error_class
+                .map(|class| class.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            result.checked_at.to_rfc3339(),
+            format_tags(&result.tags),
+        ];
+        Ok(cells
+            .iter()
+            .map(|cell| csv_cell(cell))
+            .collect::<Vec<_>>()
+            .join(","))
+    }
+}
+
+/// Quotes a CSV cell when it holds a separator or quote
+///
+/// Control characters, including line breaks, are replaced by spaces first.
+pub(crate) fn csv_cell(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    if value.contains([',', '"']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Columns of table output with their widths, shared by the header and rows
+const TABLE_COLUMNS: &[(&str, usize)] = &[
+    ("DOMAIN", 30),
+    ("TENANT", 16),
+    ("MDI", 3),
+    ("INSTANCE", 40),
+    ("GENERATION", 10),
+    ("TIME", 6),
+    ("TAGS", 24),
+    ("ERROR", 0),
+];
+
+/// Spaces between two columns
+const TABLE_GAP: usize = 2;
+
+/// Human-readable table with one row per result
+///
+/// Every column starts at the offset given by the widths of the columns
+/// before it, in the header and in every row, so rows of a streamed batch
+/// line up without buffering. Longer values are not cut: they push the next
+/// cells right until a cell with room to spare absorbs the overflow, from
+/// where the row lines up with the header again.
+pub struct TableFormatter;
+
+impl TableFormatter {
+    fn row(cells: &[String]) -> String {
+        let mut row = String::new();
+        let mut len = 0;
+        let mut offset = 0;
+        for (index, (cell, (_, width))) in cells.iter().zip(TABLE_COLUMNS).enumerate() {
+            let start = match index {
+                0 => 0,
+                _ => offset.max(len + TABLE_GAP),
+            };
+            row.extend(std::iter::repeat_n(' ', start - len));
+            row.push_str(cell);
+            len = start + cell.chars().count();
+            offset += width + TABLE_GAP;
+        }
+        row.trim_end().to_string()
+    }
+}
+
+impl Formatter for TableFormatter {
+    fn header(&self) -> Option<String> {
+        let names: Vec<String> = TABLE_COLUMNS
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        Some(Self::row(&names))
+    }
+
+    fn format(&self, result: &DomainResult) -> Result<String> {
+        let mdi = if result.mdi_instance.is_some() {
+            "yes"
+        } else {
+            "no"
+        };
+        let cells = [
+            table_cell(Some(&result.domain)),
+            table_cell(result.tenant.as_deref()),
+            mdi.to_string(),
+            table_cell(result.mdi_instance.as_deref()),
+            table_cell(generation(result)),
+            format!("{}ms", result.processing_time_ms),
+            table_cell(Some(&format_tags(&result.tags))),
+            table_cell(result.error.as_deref()),
+        ];
+        Ok(Self::row(&cells))
+    }
+}
+
+/// Renders a table cell, `-` when missing
+fn table_cell(value: Option<&str>) -> String {
+    match value.filter(|value| !value.is_empty()) {
+        Some(value) => value
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect(),
+        None => MISSING.to_string(),
+    }
+}
+
+/// Name of the MDI generation of a result, as serialized in JSON
+fn generation(result: &DomainResult) -> Option<&'static str> {
+    result.mdi_generation.map(generation_name)
+}
+
+/// Name of an MDI generation, as serialized in JSON
+fn generation_name(generation: MdiGeneration) -> &'static str {
+    match generation {
+        MdiGeneration::Legacy => "legacy",
+        MdiGeneration::Unified => "unified",
+    }
+}
+
+/// Formatter rendering results in `format`
+///
+/// Returns `None` for the document formats `junit`, `grepable`, `parquet`,
+/// `markdown`, `xlsx`, `dot` and `graphml`, which are written by sinks of their own (see
+/// [`crate::sinks::format_sink`]).
+pub fn formatter(format: OutputFormat) -> Option<Box<dyn Formatter>> {
+    match format {
+        OutputFormat::Json => Some(Box::new(JsonFormatter)),
+        OutputFormat::Jsonl => Some(Box::new(JsonlFormatter)),
+        OutputFormat::Csv => Some(Box::new(CsvFormatter)),
+        OutputFormat::Table => Some(Box::new(TableFormatter)),
+        OutputFormat::Cef => Some(Box::new(CefFormatter)),
+        OutputFormat::Leef => Some(Box::new(LeefFormatter)),
+        OutputFormat::Junit
+        | OutputFormat::Grepable
+        | OutputFormat::Parquet
+        | OutputFormat::Markdown
+        | OutputFormat::Xlsx
+        | OutputFormat::Dot
+        | OutputFormat::Graphml => None,
+    }
+}
@@ -0,0 +1,230 @@
+//! Apache Parquet encoding of domain results
+//!
+//! Multi-million domain scans produce JSONL files that are slow to load into
+//! analytics engines. [`ParquetWriter`] encodes results as a Parquet file
+//! that DuckDB, Spark, pandas and friends read directly, e.g.
+//! `SELECT tenant, count(*) FROM 'results.parquet' GROUP BY tenant`.
+//!
+//! Files are written by the Arrow implementation of Parquet, Snappy
+//! compressed, with a row group every [`ROW_GROUP_ROWS`] rows. Columns:
+//!
+//! | Column               | Type                             |
+//! |----------------------|----------------------------------|
+//! | `domain`             | string                           |
+//! | `tenant`             | string, nullable                 |
+//! | `mdi`                | boolean                          |
+//! | `mdi_instance`       | string, nullable                 |
+//! | `mdi_generation`     | string, nullable                 |
+//! | `federated_domains`  | list of strings                  |
+//! | `processing_time_ms` | int64                            |
+//! | `response_sha256`    | string, nullable                 |
+//! | `error`              | string, nullable                 |
+//! | `error_class`        | string, nullable                 |
+//! | `from_cache`         | boolean                          |
+//! | `checked_at`         | timestamp (microseconds, UTC)    |
+//! | `completed_at`       | timestamp (microseconds, UTC)    |
+//! | `tags`               | map of strings to strings        |
+//!
+//! Tags can be queried by key, e.g. `SELECT domain FROM 'results.parquet'
+//! WHERE tags['bu'] = 'emea'`.
+//!
+//! Parquet files are only readable once their footer is written by
+//! [`ParquetWriter::finish`]; an interrupted batch leaves an unreadable file.
+
+use anyhow::{Context, Result};
+use arrow_array::builder::{ListBuilder, MapBuilder, MapFieldNames, StringBuilder};
+use arrow_array::{
+    ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::build_info::LONG_VERSION;
+use crate::core::DomainResult;
+
+/// Rows per row group
+pub const ROW_GROUP_ROWS: usize = 100_000;
+
+/// Time zone of the timestamp columns
+const UTC: &str = "UTC";
+
+/// Arrow schema of the Parquet files
+///
+/// # Examples
+///
+/// ```
+/// use sentri::output::parquet::schema;
+///
+/// let schema = schema();
+/// assert_eq!(schema.field(0).name(), "domain");
+/// assert!(schema.field_with_name("tags").is_ok());
+/// ```
+pub fn schema() -> SchemaRef {
+    let timestamp = DataType::Timestamp(TimeUnit::Microsecond, Some(UTC.into()));
+    Arc::new(Schema::new(vec![
+        Field::new("domain", DataType::Utf8, false),
+        Field::new("tenant", DataType::Utf8, true),
+        Field::new("mdi", DataType::Boolean, false),
+        Field::new("mdi_instance", DataType::Utf8, true),
+        Field::new("mdi_generation", DataType::Utf8, true),
+        Field::new("federated_domains", DataType::List(list_element()), false),
+        Field::new("processing_time_ms", DataType::Int64, false),
+        Field::new("response_sha256", DataType::Utf8, true),
+        Field::new("error", DataType::Utf8, true),
+        Field::new("error_class", DataType::Utf8, true),
+        Field::new("from_cache", DataType::Boolean, false),
+        Field::new("checked_at", timestamp.clone(), false),
+        Field::new("completed_at", timestamp, false),
+        Field::new_map("tags", "key_value", tag_key(), tag_value(), false, false),
+    ]))
+}
+
+fn list_element() -> Arc<Field> {
+    Arc::new(Field::new("element", DataType::Utf8, false))
+}
+
+fn tag_key() -> Arc<Field> {
+    Arc::new(Field::new("key", DataType::Utf8, false))
+}
+
+fn tag_value() -> Arc<Field> {
+    Arc::new(Field::new("value", DataType::Utf8, false))
+}
+
+/// Columns of `results` as an Arrow record batch of [`schema`]
+pub fn record_batch(results: &[DomainResult]) -> Result<RecordBatch> {
+    let strings = |value: fn(&DomainResult) -> Option<&str>| -> ArrayRef {
+        Arc::new(results.iter().map(value).collect::<StringArray>())
+    };
+    let timestamps = |value: fn(&DomainResult) -> i64| -> ArrayRef {
+        Arc::new(
+            TimestampMicrosecondArray::from_iter_values(results.iter().map(value))
+                .with_timezone(UTC),
+        )
+    };
+
+    let mut federated_domains = ListBuilder::new(StringBuilder::new()).with_field(list_element());
+    let mut tags = MapBuilder::new(
+        Some(MapFieldNames {
+            entry: "key_value".to_string(),
+            key: "key".to_string(),
+            value: "value".to_string(),
+        }),
+        StringBuilder::new(),
+        StringBuilder::new(),
+    )
+    .with_keys_field(tag_key())
+    .with_values_field(tag_value());
+    for result in results {
+        for domain in &result.federated_domains {
+            federated_domains.values().append_value(domain);
+        }
+        federated_domains.append(true);
+        for (key, value) in &result.tags {
+            tags.keys().append_value(key);
+            tags.values().append_value(value);
+        }
+        tags.append(true)?;
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        strings(|r| Some(&r.domain)),
+        strings(|r| r.tenant.as_deref()),
+        Arc::new(BooleanArray::from_iter(
+            results.iter().map(|r| Some(r.mdi_instance.is_some())),
+        )),
+        strings(|r| r.mdi_instance.as_deref()),
+        strings(|r| r.mdi_generation.map(|generation| generation.as_str())),
+        Arc::new(federated_domains.finish()),
+        Arc::new(Int64Array::from_iter_values(
+            results.iter().map(|r| r.processing_time_ms as i64),
+        )),
+        strings(|r| r.response_sha256.as_deref()),
+        strings(|r| r.error.as_deref()),
+        strings(|r| r.error_class.map(|class| class.as_str())),
+        Arc::new(BooleanArray::from_iter(
+            results.iter().map(|r| Some(r.from_cache)),
+        )),
+        timestamps(|r| r.checked_at.timestamp_micros()),
+        timestamps(|r| r.completed_at.timestamp_micros()),
+        Arc::new(tags.finish()),
+    ];
+    RecordBatch::try_new(schema(), columns).context("Failed to encode results for Parquet")
+}
+
+/// Parquet encoder writing results to `W`
+///
+/// Rows are buffered until a row group of [`ROW_GROUP_ROWS`] is complete;
+/// [`ParquetWriter::finish`] writes the last row group and the footer.
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::output::parquet::ParquetWriter;
+///
+/// let mut writer = ParquetWriter::new(Vec::new()).unwrap();
+/// writer
+///     .write(&[DomainResult {
+///         domain: "contoso.com".to_string(),
+///         ..Default::default()
+///     }])
+///     .unwrap();
+/// assert_eq!(writer.rows(), 1);
+/// let file = writer.finish().unwrap();
+/// assert!(file.starts_with(b"PAR1") && file.ends_with(b"PAR1"));
+/// ```
+pub struct ParquetWriter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    rows: usize,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// Starts a Parquet file in `writer`
+    pub fn new(writer: W) -> Result<Self> {
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_row_count(Some(ROW_GROUP_ROWS))
+            .set_created_by(format!("sentri version {}", LONG_VERSION))
+            .build();
+        let writer = ArrowWriter::try_new(writer, schema(), Some(properties))
+            .context("Failed to start Parquet file")?;
+        Ok(Self { writer, rows: 0 })
+    }
+
+    /// Number of rows written so far
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Appends `results` as rows
+    pub fn write(&mut self, results: &[DomainResult]) -> Result<()> {
+        if results.is_empty() {
+            return Ok(());
+        }
+        self.writer
+            .write(&record_batch(results)?)
+            .context("Failed to write Parquet rows")?;
+        self.rows += results.len();
+        Ok(())
+    }
+
+    /// Writes the last row group and the footer, returning the writer
+    pub fn finish(self) -> Result<W> {
+        self.writer
+            .into_inner()
+            .context("Failed to write Parquet footer")
+    }
+}
+
+impl<W: Write + Send> std::fmt::Debug for ParquetWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParquetWriter")
+            .field("rows", &self.rows)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,231 @@
+//! CEF and LEEF renderings of domain results for SIEM ingestion
+//!
+//! `--format cef` writes one ArcSight Common Event Format line per result and
+//! `--format leef` one IBM QRadar Log Event Extended Format line, so results
+//! can be forwarded by a syslog agent or collector without a custom parser:
+//!
+//! ```text
+//! CEF:0|sentri|sentri|0.1.0|mdi-instance|MDI instance found|3|rt=1714566896000 end=1714566897000 dhost=contoso.com cs1Label=tenant cs1=contoso ...
+//! LEEF:1.0|sentri|sentri|0.1.0|mdi-instance|cat=mdi-instance<TAB>sev=3<TAB>devTime=May 01 2024 12:34:56.000 UTC<TAB>...
+//! ```
+//!
+//! Every result becomes an event classified by [`event_kind`]. CEF uses its
+//! standard extension keys (`rt`, `end`, `dhost`, `msg`) plus labelled
+//! custom strings for the remaining fields, and input tags as `flexString1`;
+//! LEEF uses its predefined `cat`,
+//! `sev` and `devTime` attributes plus camelCase custom attributes,
+//! separated by tabs. Fields without a value are left out.
+//!
+//! # Security Considerations
+//!
+//! - **Log Injection**: Values are escaped as both formats require and
+//!   control characters are replaced, so an error message cannot forge
+//!   fields or additional events (security:output:sanitize_all_output)
+
+use anyhow::Result;
+
+use super::{generation, Formatter};
+use crate::core::DomainResult;
+use crate::tags::format_tags;
+
+/// Vendor and product reported in event headers
+const PRODUCT: &str = "sentri";
+
+/// `devTimeFormat` of LEEF events, the Java pattern of `devTime`
+const LEEF_TIME_FORMAT: &str = "MMM dd yyyy HH:mm:ss.SSS z";
+
+/// Classification of a result as a SIEM event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventKind {
+    /// Event class ID (CEF signature ID, LEEF event ID)
+    pub id: &'static str,
+    /// Human-readable event name
+    pub name: &'static str,
+    /// Severity from 0 (lowest) to 10
+    pub severity: u8,
+}
+
+/// Classifies a result: failed checks, MDI instances, tenants, or nothing found
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::output::siem::event_kind;
+///
+/// let result = DomainResult { tenant: Some("contoso".to_string()), ..Default::default() };
+/// assert_eq!(event_kind(&result).id, "tenant-found");
+/// ```
+pub fn event_kind(result: &DomainResult) -> EventKind {
+    if result.error.is_some() {
+        EventKind {
+            id: "check-failed",
+            name: "Domain check failed",
+            severity: 4,
+        }
+    } else if result.mdi_instance.is_some() {
+        EventKind {
+            id: "mdi-instance",
+            name: "MDI instance found",
+            severity: 3,
+        }
+    } else if result.tenant.is_some() {
+        EventKind {
+            id: "tenant-found",
+            name: "Microsoft tenant found",
+            severity: 1,
+        }
+    } else {
+        EventKind {
+            id: "no-tenant",
+            name: "No Microsoft tenant",
+            severity: 0,
+        }
+    }
+}
+
+/// Fields without a standard key, named as CEF custom string labels and LEEF attributes
+///
+/// The position of a field gives its CEF custom string, `cs1` to `cs6`.
+fn custom_fields(result: &DomainResult) -> [(&'static str, Option<String>); 6] {
+    [
+        ("tenant", result.tenant.clone()),
+        ("mdiInstance", result.mdi_instance.clone()),
+        ("mdiGeneration", generation(result).map(str::to_string)),
+        (
+            "federatedDomains",
+            (!result.federated_domains.is_empty()).then(|| result.federated_domains.join(";")),
+        ),
+        (
+            "errorClass",
+            result.error_class.map(|class| class.as_str().to_string()),
+        ),
+        ("responseSha256", result.response_sha256.clone()),
+    ]
+}
+
+/// ArcSight Common Event Format, version 0
+pub struct CefFormatter;
+
+impl Formatter for CefFormatter {
+    fn format(&self, result: &DomainResult) -> Result<String> {
+        let kind = event_kind(result);
+        let mut extension = vec![
+            (
+                "rt".to_string(),
+                result.checked_at.timestamp_millis().to_string(),
+            ),
+            (
+                "end".to_string(),
+                result.completed_at.timestamp_millis().to_string(),
+            ),
+            ("dhost".to_string(), result.domain.clone()),
+        ];
+        for (index, (label, value)) in custom_fields(result).into_iter().enumerate() {
+            if let Some(value) = value {
+                let key = format!("cs{}", index + 1);
+                extension.push((format!("{}Label", key), label.to_string()));
+                extension.push((key, value));
+            }
+        }
+        if !result.tags.is_empty() {
+            extension.push(("flexString1Label".to_string(), "tags".to_string()));
+            extension.push(("flexString1".to_string(), format_tags(&result.tags)));
+        }
+        extension.push(("cn1Label".to_string(), "processingTimeMs".to_string()));
+        extension.push(("cn1".to_string(), result.processing_time_ms.to_string()));
+        if let Some(error) = &result.error {
+            extension.push(("msg".to_string(), error.clone()));
+        }
+
+        let extension: Vec<String> = extension
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, cef_value(value)))
+            .collect();
+        Ok(format!(
+            "CEF:0|{}|{}|{}|{}|{}|{}|{}",
+            cef_header(PRODUCT),
+            cef_header(PRODUCT),
+            cef_header(env!("CARGO_PKG_VERSION")),
+            cef_header(kind.id),
+            cef_header(kind.name),
+            kind.severity,
+            extension.join(" ")
+        ))
+    }
+}
+
+/// Escapes a CEF header field
+fn cef_header(value: &str) -> String {
+    printable(value).replace('\\', "\\\\").replace('|', "\\|")
+}
+
+/// Escapes a CEF extension value
+fn cef_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace("\r\n", "\\n")
+        .replace(['\r', '\n'], "\\n")
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// IBM QRadar Log Event Extended Format, version 1.0, tab-delimited
+pub struct LeefFormatter;
+
+impl Formatter for LeefFormatter {
+    fn format(&self, result: &DomainResult) -> Result<String> {
+        let kind = event_kind(result);
+        let devtime = result
+            .checked_at
+            .format("%b %d %Y %H:%M:%S%.3f UTC")
+            .to_string();
+        let mut attributes = vec![
+            ("cat", kind.id.to_string()),
+            ("sev", kind.severity.to_string()),
+            ("devTime", devtime),
+            ("devTimeFormat", LEEF_TIME_FORMAT.to_string()),
+            ("domain", result.domain.clone()),
+        ];
+        for (key, value) in custom_fields(result) {
+            if let Some(value) = value {
+                attributes.push((key, value));
+            }
+        }
+        if !result.tags.is_empty() {
+            attributes.push(("tags", format_tags(&result.tags)));
+        }
+        attributes.push(("processingTimeMs", result.processing_time_ms.to_string()));
+        if let Some(error) = &result.error {
+            attributes.push(("error", error.clone()));
+        }
+
+        let attributes: Vec<String> = attributes
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, printable(value)))
+            .collect();
+        Ok(format!(
+            "LEEF:1.0|{}|{}|{}|{}|{}",
+            leef_header(PRODUCT),
+            leef_header(PRODUCT),
+            leef_header(env!("CARGO_PKG_VERSION")),
+            leef_header(kind.id),
+            attributes.join("\t")
+        ))
+    }
+}
+
+/// Escapes a LEEF header field
+fn leef_header(value: &str) -> String {
+    printable(value).replace('|', "\\|")
+}
+
+/// Replaces control characters, including the LEEF tab delimiter, by spaces
+fn printable(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
@@ -0,0 +1,188 @@
+//! Excel workbook encoding of batch results
+//!
+//! Many recipients of scan results live in Excel. `--format xlsx` writes a
+//! workbook with two sheets:
+//!
+//! - `Results`: one row per domain, with the columns of `--format csv`
+//! - `Tenants`: one row per discovered tenant, aggregated as by
+//!   `--tenant-report` (see [`crate::sinks::tenant_report`])
+//!
+//! Cells are typed, so MDI presence is a boolean, processing times are
+//! numbers and check times are dates (UTC) that sort and filter as such.
+//! Both sheets have a frozen, filterable header row.
+//!
+//! The workbook is assembled in memory and written when the batch ends.
+//! A sheet holds at most 1,048,575 rows below its header; larger batches
+//! fail rather than silently drop results. Built with `--features xlsx`.
+
+use anyhow::{Context, Result};
+use rust_xlsxwriter::{ColNum, Format, RowNum, Workbook, Worksheet};
+
+use super::{generation, generation_name};
+use crate::core::DomainResult;
+use crate::sinks::tenant_report::TenantReport;
+use crate::tags::format_tags;
+
+/// Name of the sheet with one row per domain
+pub const RESULTS_SHEET: &str = "Results";
+
+/// Name of the sheet with one row per tenant
+pub const TENANTS_SHEET: &str = "Tenants";
+
+/// Columns of the results sheet
+const RESULT_COLUMNS: &[&str] = &[
+    "domain",
+    "tenant",
+    "mdi",
+    "mdi_instance",
+    "mdi_generation",
+    "federated_domains",
+    "processing_time_ms",
+    "error",
+    "error_class",
+    "checked_at",
+    "tags",
+];
+
+/// Columns of the tenants sheet
+const TENANT_COLUMNS: &[&str] = &[
+    "tenant",
+    "domain_count",
+    "scanned_count",
+    "mdi",
+    "mdi_instance",
+    "mdi_generation",
+    "scanned_domains",
+];
+
+/// Excel number format of check times
+const DATETIME_FORMAT: &str = "yyyy-mm-dd hh:mm:ss";
+
+/// Builds an XLSX workbook from results
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::output::xlsx::XlsxWriter;
+///
+/// let mut writer = XlsxWriter::new().unwrap();
+/// writer.push(&DomainResult { domain: "contoso.com".to_string(), ..Default::default() }).unwrap();
+/// assert_eq!(writer.rows(), 1);
+///
+/// let bytes = writer.finish().unwrap();
+/// assert!(bytes.starts_with(b"PK"));
+/// ```
+pub struct XlsxWriter {
+    results: Worksheet,
+    rows: RowNum,
+    tenants: TenantReport,
+    datetime: Format,
+}
+
+impl XlsxWriter {
+    /// Creates a writer with the header row of the results sheet
+    pub fn new() -> Result<Self> {
+        let mut results = Worksheet::new();
+        results.set_name(RESULTS_SHEET)?;
+        write_header(&mut results, RESULT_COLUMNS)?;
+        Ok(Self {
+            results,
+            rows: 0,
+            tenants: TenantReport::default(),
+            datetime: Format::new().set_num_format(DATETIME_FORMAT),
+        })
+    }
+
+    /// Adds a result as a row of the results sheet and to its tenant
+    ///
+    /// # Errors
+    /// * The results sheet is full
+    pub fn push(&mut self, result: &DomainResult) -> Result<()> {
+        let row = self.rows + 1;
+        let sheet = &mut self.results;
+        sheet
+            .write_string(row, 0, &result.domain)
+            .context("Results no longer fit into the XLSX sheet")?;
+        if let Some(tenant) = &result.tenant {
+            sheet.write_string(row, 1, tenant)?;
+        }
+        sheet.write_boolean(row, 2, result.mdi_instance.is_some())?;
+        if let Some(instance) = &result.mdi_instance {
+            sheet.write_string(row, 3, instance)?;
+        }
+        if let Some(generation) = generation(result) {
+            sheet.write_string(row, 4, generation)?;
+        }
+        sheet.write_string(row, 5, result.federated_domains.join(";"))?;
+        sheet.write_number(row, 6, result.processing_time_ms as f64)?;
+        if let Some(error) = &result.error {
+            sheet.write_string(row, 7, error)?;
+        }
+        if let Some(class) = result.error_class {
+            sheet.write_string(row, 8, class.as_str())?;
+        }
+        sheet.write_datetime_with_format(row, 9, result.checked_at.naive_utc(), &self.datetime)?;
+        if !result.tags.is_empty() {
+            sheet.write_string(row, 10, format_tags(&result.tags))?;
+        }
+        self.rows = row;
+        self.tenants.add(result);
+        Ok(())
+    }
+
+    /// Number of result rows written so far
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Adds the tenants sheet and returns the encoded workbook
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        finish_sheet(&mut self.results, self.rows, RESULT_COLUMNS.len())?;
+
+        let mut tenants = Worksheet::new();
+        tenants.set_name(TENANTS_SHEET)?;
+        write_header(&mut tenants, TENANT_COLUMNS)?;
+        let summaries = self.tenants.summaries();
+        for (row, summary) in (1..).zip(&summaries) {
+            tenants.write_string(row, 0, &summary.tenant)?;
+            tenants.write_number(row, 1, summary.domain_count as f64)?;
+            tenants.write_number(row, 2, summary.scanned_domains.len() as f64)?;
+            tenants.write_boolean(row, 3, summary.mdi)?;
+            if let Some(instance) = &summary.mdi_instance {
+                tenants.write_string(row, 4, instance)?;
+            }
+            if let Some(generation) = summary.mdi_generation {
+                tenants.write_string(row, 5, generation_name(generation))?;
+            }
+            tenants.write_string(row, 6, summary.scanned_domains.join(";"))?;
+        }
+        finish_sheet(
+            &mut tenants,
+            summaries.len() as RowNum,
+            TENANT_COLUMNS.len(),
+        )?;
+
+        let mut workbook = Workbook::new();
+        workbook.push_worksheet(self.results);
+        workbook.push_worksheet(tenants);
+        workbook
+            .save_to_buffer()
+            .context("Failed to encode XLSX workbook")
+    }
+}
+
+/// Writes the bold header row of a sheet
+fn write_header(sheet: &mut Worksheet, columns: &[&str]) -> Result<()> {
+    let bold = Format::new().set_bold();
+    sheet.write_row_with_format(0, 0, columns.iter().copied(), &bold)?;
+    Ok(())
+}
+
+/// Freezes the header row, adds filters and sizes the columns of a sheet
+fn finish_sheet(sheet: &mut Worksheet, rows: RowNum, columns: usize) -> Result<()> {
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofilter(0, 0, rows, columns as ColNum - 1)?;
+    sheet.autofit();
+    Ok(())
+}
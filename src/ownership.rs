@@ -0,0 +1,158 @@
+//! Domain ownership verification
+//!
+//! Before running detectors that touch a target's own infrastructure, an
+//! operator can prove control of the domain with a DNS TXT record:
+//!
+//! 1. `sentri verify-ownership issue --domain contoso.com` generates a random
+//!    token and prints the record to publish, e.g.
+//!    `contoso.com. TXT "sentri-verification=3f9c..."`
+//! 2. `sentri verify-ownership check --domain contoso.com` looks the record
+//!    up and marks the domain as verified
+//!
+//! Tokens and verification state are kept in an ownership file (JSON). With
+//! `--require-ownership`, intrusive detectors only run against verified
+//! domains; currently that is IP attribution (`--attribute-ips`), which
+//! resolves the MX and `autodiscover.` hosts of federated domains. Lookups
+//! against Microsoft's autodiscover service and MDI sensor names are passive
+//! with respect to the target and are not gated.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::validation::validate_domain;
+
+/// Prefix of the TXT record value carrying the token
+pub const TXT_PREFIX: &str = "sentri-verification=";
+
+/// Number of random bytes in a token
+const TOKEN_BYTES: usize = 16;
+
+/// Verification state of one domain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnershipRecord {
+    /// Token expected in the domain's TXT records
+    pub token: String,
+    /// When the token was generated
+    pub issued_at: DateTime<Utc>,
+    /// When the token was found in DNS, if it has been
+    pub verified_at: Option<DateTime<Utc>>,
+}
+
+impl OwnershipRecord {
+    /// Value of the TXT record to publish
+    pub fn txt_value(&self) -> String {
+        format!("{}{}", TXT_PREFIX, self.token)
+    }
+}
+
+/// Issued tokens and verified domains, by lowercase domain
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OwnershipStore {
+    /// Verification state per domain
+    pub domains: BTreeMap<String, OwnershipRecord>,
+}
+
+impl OwnershipStore {
+    /// Loads an ownership file; a missing file yields an empty store
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = match tokio::fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read ownership file {}", path.display()))
+            }
+        };
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid ownership file {}", path.display()))
+    }
+
+    /// Writes the store as pretty-printed JSON
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        let content = format!("{}\n", serde_json::to_string_pretty(self)?);
+        tokio::fs::write(path, content)
+            .await
+            .with_context(|| format!("Failed to write ownership file {}", path.display()))
+    }
+
+    /// Generates a new token for a domain, revoking any earlier verification
+    ///
+    /// # Errors
+    /// * The domain is invalid
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::ownership::OwnershipStore;
+    ///
+    /// let mut store = OwnershipStore::default();
+    /// let record = store.issue("Contoso.com", chrono::Utc::now()).unwrap().clone();
+    /// let published = vec![record.txt_value()];
+    /// assert!(store.record_verification("contoso.com", &published, chrono::Utc::now()));
+    /// assert!(store.is_verified("CONTOSO.COM"));
+    /// ```
+    pub fn issue(&mut self, domain: &str, now: DateTime<Utc>) -> Result<&OwnershipRecord> {
+        validate_domain(domain).map_err(anyhow::Error::msg)?;
+        let bytes: [u8; TOKEN_BYTES] = rand::thread_rng().gen();
+        let token = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let record = OwnershipRecord {
+            token,
+            issued_at: now,
+            verified_at: None,
+        };
+        let key = domain.to_lowercase();
+        self.domains.insert(key.clone(), record);
+        Ok(&self.domains[&key])
+    }
+
+    /// Returns the state of a domain
+    pub fn get(&self, domain: &str) -> Option<&OwnershipRecord> {
+        self.domains.get(&domain.to_lowercase())
+    }
+
+    /// Marks a domain verified if its TXT records contain the issued token
+    ///
+    /// # Arguments
+    /// * `domain` - Domain a token was issued for
+    /// * `txt_records` - TXT records currently published for the domain
+    /// * `now` - Time of the lookup
+    ///
+    /// # Returns
+    /// * `bool` - True if the token was found; false if it was not or none was issued
+    pub fn record_verification(
+        &mut self,
+        domain: &str,
+        txt_records: &[String],
+        now: DateTime<Utc>,
+    ) -> bool {
+        let Some(record) = self.domains.get_mut(&domain.to_lowercase()) else {
+            return false;
+        };
+        let expected = record.txt_value();
+        if txt_records.iter().any(|txt| txt.trim() == expected) {
+            record.verified_at = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns true if the domain's token has been found in DNS
+    pub fn is_verified(&self, domain: &str) -> bool {
+        self.get(domain)
+            .is_some_and(|record| record.verified_at.is_some())
+    }
+
+    /// Lowercase names of all verified domains
+    pub fn verified_domains(&self) -> HashSet<String> {
+        self.domains
+            .iter()
+            .filter(|(_, record)| record.verified_at.is_some())
+            .map(|(domain, _)| domain.clone())
+            .collect()
+    }
+}
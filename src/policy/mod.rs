@@ -0,0 +1,259 @@
+//! Pass/fail policy evaluation over scan results
+//!
+//! A policy is a TOML file made of rules. Each rule applies one check to a
+//! set of domains and yields a [`Violation`] for every result that does not
+//! comply:
+//!
+//! ```toml
+//! [[rule]]
+//! name = "production-coverage"
+//! check = "require_mdi"
+//! domains = ["contoso.com", "*.contoso.net"]
+//!
+//! [[rule]]
+//! name = "single-tenant"
+//! check = "allowed_tenants"
+//! tenants = ["contoso"]
+//! ```
+//!
+//! Rules without `domains` apply to every result. Domains listed explicitly
+//! (without wildcards) must also be present in the results; a missing domain
+//! is itself a violation, so a shrinking scan cannot silently pass.
+//!
+//! Policies back the `policy` subcommand and the JUnit output format. Teams
+//! standardized on OPA can use Rego policies instead (see [`rego`]).
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::core::{DomainResult, MdiGeneration};
+use crate::result_reader::read_results_mapped;
+
+pub mod rego;
+
+pub use rego::RegoPolicy;
+
+/// A set of rules evaluated against scan results
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Policy {
+    /// Rules in evaluation order
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+/// A single policy rule
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    /// Rule name reported with violations
+    pub name: String,
+    /// Domains the rule applies to; exact names or `*.suffix` wildcards
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Check applied to every matching result
+    #[serde(flatten)]
+    pub check: Check,
+}
+
+/// Assertion applied to a single result
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum Check {
+    /// An MDI instance must be detected
+    RequireMdi,
+    /// An MDI instance must be detected on the given sensor endpoint generation
+    RequireGeneration {
+        /// Required generation
+        generation: MdiGeneration,
+    },
+    /// The domain must resolve to a Microsoft tenant
+    RequireTenant,
+    /// The domain must belong to one of the given tenants
+    AllowedTenants {
+        /// Permitted tenant names
+        tenants: Vec<String>,
+    },
+    /// Federated domains must match one of the given patterns
+    AllowedFederatedDomains {
+        /// Permitted domains; exact names or `*.suffix` wildcards
+        patterns: Vec<String>,
+    },
+    /// The scan must have completed without error
+    NoScanErrors,
+}
+
+/// A rule violated by a result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    /// Name of the violated rule
+    pub rule: String,
+    /// Domain the violation relates to
+    pub domain: String,
+    /// Human-readable explanation
+    pub message: String,
+}
+
+/// Matches a domain against an exact name or a `*.suffix` wildcard
+///
+/// # Examples
+///
+/// ```
+/// use sentri::policy::domain_matches;
+///
+/// assert!(domain_matches("*.contoso.com", "eu.contoso.com"));
+/// assert!(domain_matches("contoso.com", "CONTOSO.com"));
+/// assert!(!domain_matches("*.contoso.com", "contoso.com"));
+/// ```
+pub fn domain_matches(pattern: &str, domain: &str) -> bool {
+    let domain = domain.to_ascii_lowercase();
+    let pattern = pattern.to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => domain.ends_with(&format!(".{}", suffix)),
+        None => domain == pattern,
+    }
+}
+
+impl Check {
+    /// Evaluates the check against one result
+    ///
+    /// # Returns
+    /// * `Option<String>` - The failure message, or None if the result complies
+    pub fn evaluate(&self, result: &DomainResult) -> Option<String> {
+        match self {
+            Self::NoScanErrors => result
+                .error
+                .as_ref()
+                .map(|error| format!("Scan failed: {}", error)),
+            // The remaining checks say nothing about failed scans
+            _ if result.error.is_some() => None,
+            Self::RequireMdi => result
+                .mdi_instance
+                .is_none()
+                .then(|| "No MDI instance detected".to_string()),
+            Self::RequireGeneration { generation } => match result.mdi_generation {
+                Some(found) if found == *generation => None,
+                Some(found) => Some(format!(
+                    "MDI sensor endpoint generation is {:?}, expected {:?}",
+                    found, generation
+                )),
+                None => Some("No MDI instance detected".to_string()),
+            },
+            Self::RequireTenant => result
+                .tenant
+                .is_none()
+                .then(|| "No Microsoft tenant identified".to_string()),
+            Self::AllowedTenants { tenants } => match &result.tenant {
+                Some(tenant) if tenants.iter().any(|t| t.eq_ignore_ascii_case(tenant)) => None,
+                Some(tenant) => Some(format!("Federated with unapproved tenant {}", tenant)),
+                None => None,
+            },
+            Self::AllowedFederatedDomains { patterns } => {
+                let unexpected: Vec<&str> = result
+                    .federated_domains
+                    .iter()
+                    .filter(|d| !patterns.iter().any(|p| domain_matches(p, d)))
+                    .map(String::as_str)
+                    .collect();
+                (!unexpected.is_empty())
+                    .then(|| format!("Unapproved federated domains: {}", unexpected.join(", ")))
+            }
+        }
+    }
+}
+
+impl Rule {
+    /// Returns true if the rule applies to the given domain
+    pub fn applies_to(&self, domain: &str) -> bool {
+        self.domains.is_empty() || self.domains.iter().any(|p| domain_matches(p, domain))
+    }
+}
+
+impl Policy {
+    /// Parses a policy from TOML
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::policy::Policy;
+    ///
+    /// let policy = Policy::from_toml(r#"
+    ///     [[rule]]
+    ///     name = "coverage"
+    ///     check = "require_mdi"
+    /// "#).unwrap();
+    /// assert_eq!(policy.rules.len(), 1);
+    /// ```
+    pub fn from_toml(content: &str) -> Result<Self> {
+        toml::from_str(content).context("Invalid policy file")
+    }
+
+    /// Loads a policy file from disk
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read policy file {}", path.display()))?;
+        Self::from_toml(&content)
+    }
+
+    /// Evaluates every applicable rule against a single result
+    pub fn check_result(&self, result: &DomainResult) -> Vec<Violation> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.applies_to(&result.domain))
+            .filter_map(|rule| {
+                rule.check.evaluate(result).map(|message| Violation {
+                    rule: rule.name.clone(),
+                    domain: result.domain.clone(),
+                    message,
+                })
+            })
+            .collect()
+    }
+
+    /// Evaluates the policy against a complete result set
+    ///
+    /// In addition to the per-result checks, explicitly listed domains that
+    /// are missing from the results are reported.
+    ///
+    /// # Returns
+    /// * `Vec<Violation>` - All violations; empty when the results comply
+    pub fn evaluate(&self, results: &[DomainResult]) -> Vec<Violation> {
+        let mut violations: Vec<Violation> = results
+            .iter()
+            .flat_map(|result| self.check_result(result))
+            .collect();
+
+        for rule in &self.rules {
+            for domain in rule.domains.iter().filter(|d| !d.starts_with("*.")) {
+                if !results.iter().any(|r| domain_matches(domain, &r.domain)) {
+                    violations.push(Violation {
+                        rule: rule.name.clone(),
+                        domain: domain.clone(),
+                        message: "Domain missing from results".to_string(),
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+/// Reads every result of a JSONL file produced by a batch run
+///
+/// The file is memory-mapped and parsed in parallel (see
+/// [`crate::result_reader`]). Callers that look at one result at a time
+/// should use [`crate::result_reader::stream_results`] instead, which does
+/// not hold the whole file in memory.
+///
+/// # Arguments
+/// * `path` - Results file with one JSON object per line
+///
+/// # Returns
+/// * `Result<Vec<DomainResult>>` - Parsed results, or error on the first invalid line
+pub async fn read_results(path: &Path) -> Result<Vec<DomainResult>> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || read_results_mapped(&path)?.collect())
+        .await
+        .context("Results reader panicked")?
+}
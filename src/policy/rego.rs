@@ -0,0 +1,173 @@
+//! Rego (Open Policy Agent) policy evaluation
+//!
+//! Complements the native TOML policy format for teams that already write
+//! their guardrails in Rego. Policies are evaluated in-process with the
+//! `regorus` interpreter; no OPA server or binary is required.
+//!
+//! Each scan result is passed as `input` and the deny rule (by default
+//! `data.sentri.deny`) is evaluated, following the conftest convention:
+//!
+//! ```rego
+//! package sentri
+//!
+//! deny contains msg if {
+//!     input.error == null
+//!     input.mdi_instance == null
+//!     msg := sprintf("%s has no MDI instance", [input.domain])
+//! }
+//! ```
+//!
+//! Deny entries may be plain strings or objects with `msg` and an optional
+//! `rule` name.
+//!
+//! Rego evaluation is only compiled with the `rego` cargo feature.
+
+use anyhow::Result;
+use std::path::PathBuf;
+
+use super::Violation;
+use crate::core::DomainResult;
+
+/// Rule evaluated when no query is given
+pub const DEFAULT_DENY_RULE: &str = "data.sentri.deny";
+
+/// Converts one deny entry into a violation
+///
+/// # Arguments
+/// * `entry` - A deny set member: a message string or an object with `msg`/`rule`
+/// * `default_rule` - Rule name used when the entry does not carry one
+/// * `domain` - Domain of the evaluated result
+pub fn deny_entry_to_violation(
+    entry: &serde_json::Value,
+    default_rule: &str,
+    domain: &str,
+) -> Violation {
+    let (rule, message) = match entry {
+        serde_json::Value::String(message) => (default_rule.to_string(), message.clone()),
+        serde_json::Value::Object(fields) => (
+            fields
+                .get("rule")
+                .and_then(|rule| rule.as_str())
+                .unwrap_or(default_rule)
+                .to_string(),
+            fields
+                .get("msg")
+                .and_then(|msg| msg.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| entry.to_string()),
+        ),
+        other => (default_rule.to_string(), other.to_string()),
+    };
+
+    Violation {
+        rule,
+        domain: domain.to_string(),
+        message,
+    }
+}
+
+/// Rego policies loaded into an in-process interpreter
+#[cfg(feature = "rego")]
+pub struct RegoPolicy {
+    engine: regorus::Engine,
+    deny_rule: String,
+}
+
+#[cfg(feature = "rego")]
+impl RegoPolicy {
+    /// Loads one or more Rego policy files
+    ///
+    /// # Arguments
+    /// * `paths` - Rego source files
+    /// * `deny_rule` - Fully qualified rule producing the deny set, e.g. `data.sentri.deny`
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The loaded policies, or error if a file fails to parse
+    pub fn load(paths: &[PathBuf], deny_rule: &str) -> Result<Self> {
+        use anyhow::Context;
+
+        let mut engine = regorus::Engine::new();
+        for path in paths {
+            engine
+                .add_policy_from_file(path)
+                .with_context(|| format!("Failed to load Rego policy {}", path.display()))?;
+        }
+
+        Ok(Self {
+            engine,
+            deny_rule: deny_rule.to_string(),
+        })
+    }
+
+    /// Evaluates the deny rule with a single result as input
+    pub fn check_result(&mut self, result: &DomainResult) -> Result<Vec<Violation>> {
+        use anyhow::Context;
+
+        self.engine
+            .set_input(regorus::Value::from(serde_json::to_value(result)?));
+        let denied = self
+            .engine
+            .eval_rule(self.deny_rule.clone())
+            .with_context(|| format!("Failed to evaluate {}", self.deny_rule))?;
+
+        if denied == regorus::Value::Undefined {
+            return Ok(Vec::new());
+        }
+
+        let rule_name = self
+            .deny_rule
+            .rsplit('.')
+            .next()
+            .unwrap_or(&self.deny_rule)
+            .to_string();
+        Ok(match serde_json::to_value(&denied)? {
+            serde_json::Value::Array(entries) => entries
+                .iter()
+                .map(|entry| deny_entry_to_violation(entry, &rule_name, &result.domain))
+                .collect(),
+            serde_json::Value::Bool(false) | serde_json::Value::Null => Vec::new(),
+            other => vec![deny_entry_to_violation(&other, &rule_name, &result.domain)],
+        })
+    }
+
+    /// Evaluates the deny rule against every result
+    ///
+    /// # Returns
+    /// * `Result<Vec<Violation>>` - All violations, or the first evaluation error
+    pub fn evaluate(&mut self, results: &[DomainResult]) -> Result<Vec<Violation>> {
+        let mut violations = Vec::new();
+        for result in results {
+            violations.extend(self.check_result(result)?);
+        }
+        Ok(violations)
+    }
+}
+
+/// Rego policies loaded into an in-process interpreter
+///
+/// This build does not include the `rego` feature, so loading policies
+/// always fails with an explanatory error.
+#[cfg(not(feature = "rego"))]
+pub struct RegoPolicy {
+    _private: (),
+}
+
+#[cfg(not(feature = "rego"))]
+impl RegoPolicy {
+    /// Loads one or more Rego policy files
+    pub fn load(_paths: &[PathBuf], _deny_rule: &str) -> Result<Self> {
+        Err(anyhow::anyhow!(
+            "Cannot evaluate Rego policies: sentri was built without the rego feature"
+        ))
+    }
+
+    /// Evaluates the deny rule with a single result as input
+    pub fn check_result(&mut self, _result: &DomainResult) -> Result<Vec<Violation>> {
+        Ok(Vec::new())
+    }
+
+    /// Evaluates the deny rule against every result
+    pub fn evaluate(&mut self, _results: &[DomainResult]) -> Result<Vec<Violation>> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,51 @@
+//! Stable library surface of sentri
+//!
+//! Crates embedding the scanner should import from here rather than from the
+//! modules behind it:
+//!
+//! ```
+//! use sentri::prelude::*;
+//!
+//! let result: DomainResult = serde_json::from_str(r#"{
+//!     "domain": "contoso.com",
+//!     "tenant": "contoso",
+//!     "federated_domains": ["contoso.com"],
+//!     "mdi_instance": "contososensorapi.atp.azure.com",
+//!     "processing_time_ms": 120,
+//!     "error": null
+//! }"#)?;
+//! assert_eq!(result.schema_version, RESULT_SCHEMA_VERSION);
+//! assert!(validate_domain(&result.domain).is_ok());
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+//!
+//! Everything re-exported here follows semantic versioning: it is only
+//! removed or changed incompatibly together with a major version bump (a
+//! minor bump while sentri is 0.x). Modules hidden from the documentation,
+//! such as `cli`, `config` and `server`, exist for the `sentri` binary and
+//! may change in any release.
+//!
+//! Without the default features, only the result types, validation and the
+//! autodiscover XML parser are available (see the crate features in the
+//! README).
+
+pub use crate::core::{
+    BatchSummary, DomainResult, FederationInfo, MdiConfidence, MdiGeneration, RESULT_SCHEMA_VERSION,
+};
+pub use crate::error_class::ErrorClass;
+pub use crate::tags::Tags;
+pub use crate::validation::{validate_domain, DomainValidator};
+pub use crate::xml::{SoapFault, XmlParser};
+
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+pub use crate::{
+    core::MdiChecker,
+    depth::Depth,
+    output::{Formatter, OutputFormat},
+    sinks::ResultSink,
+};
+
+#[cfg(feature = "dns")]
+pub use crate::dns::DnsResolver;
+#[cfg(feature = "http")]
+pub use crate::http::HttpClient;
@@ -0,0 +1,95 @@
+//! Integrity self-check of the running binary
+//!
+//! Deployments with supply-chain requirements pin the release they run: with
+//! `--expected-binary-sha256 <HEX>` sentri hashes its own executable at
+//! startup and refuses to run when the hash differs from the expected value,
+//! typically the SHA-256 published alongside a signed release. The outcome of
+//! the check is logged either way and, for `sentri serve`, appended to the
+//! audit trail as an [`IntegrityReport`].
+//!
+//! The check is optional; without an expected value nothing is hashed.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Outcome of an integrity self-check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// Path of the hashed executable
+    pub binary: PathBuf,
+    /// Version the binary reports
+    pub version: String,
+    /// SHA-256 of the executable, lowercase hex
+    pub sha256: String,
+    /// Expected SHA-256, lowercase hex
+    pub expected: String,
+    /// Whether the hashes match
+    pub verified: bool,
+    /// When the check ran
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Normalizes an expected SHA-256 to lowercase hex
+///
+/// Accepts an optional `sha256:` prefix as printed by most release tooling.
+///
+/// # Examples
+///
+/// ```
+/// use sentri::provenance::parse_expected_sha256;
+///
+/// let hex = "AB".repeat(32);
+/// assert_eq!(parse_expected_sha256(&format!("sha256:{hex}")).unwrap(), "ab".repeat(32));
+/// assert!(parse_expected_sha256("abc").is_err());
+/// ```
+pub fn parse_expected_sha256(expected: &str) -> Result<String> {
+    let trimmed = expected.trim();
+    let hex = trimmed
+        .strip_prefix("sha256:")
+        .or_else(|| trimmed.strip_prefix("SHA256:"))
+        .unwrap_or(trimmed);
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("Invalid expected SHA-256 '{expected}': expected 64 hex characters");
+    }
+    Ok(hex.to_ascii_lowercase())
+}
+
+/// SHA-256 of the file at `path`, lowercase hex
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {} for hashing", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read {} for hashing", path.display()))?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Hashes the executable at `binary` and compares it to `expected`
+///
+/// A mismatch is reported, not returned as an error; callers decide whether
+/// to refuse to run.
+pub fn verify_binary(binary: &Path, expected: &str) -> Result<IntegrityReport> {
+    let expected = parse_expected_sha256(expected)?;
+    let sha256 = sha256_file(binary)?;
+    Ok(IntegrityReport {
+        binary: binary.to_path_buf(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        verified: sha256 == expected,
+        sha256,
+        expected,
+        checked_at: Utc::now(),
+    })
+}
+
+/// Hashes the running executable and compares it to `expected`
+pub fn verify_running_binary(expected: &str) -> Result<IntegrityReport> {
+    let binary = std::env::current_exe().context("Failed to locate the running executable")?;
+    verify_binary(&binary, expected)
+}
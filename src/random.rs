@@ -0,0 +1,78 @@
+//! Seedable randomness for reproducible runs
+//!
+//! Randomized behavior such as retry jitter draws from [`rng`]. Normally each
+//! generator is seeded from the operating system; after `--seed` (see
+//! [`set_seed`]) every generator is derived from the fixed seed and the name
+//! of its stream, so a run can be replayed exactly while debugging or in
+//! simulation tests.
+//!
+//! With a fixed seed, concurrent retries of a stream wait for the same
+//! jittered delays. That gives up some of the spread jitter exists for, which
+//! is acceptable for debugging but not for production scans.
+//!
+//! # Security Considerations
+//!
+//! Secrets never come from these generators: ownership tokens, storage keys
+//! and nonces always use the operating system's random source
+//! (security:crypto:use_secure_random).
+
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::sync::OnceLock;
+
+/// Stream of the retry jitter
+pub const RETRY_JITTER_STREAM: &str = "retry.jitter";
+
+/// Seed fixed for the process, if any
+static SEED: OnceLock<u64> = OnceLock::new();
+
+/// Fixes the seed of every stream for the rest of the process
+///
+/// # Errors
+/// * A different seed was already set
+pub fn set_seed(seed: u64) -> Result<()> {
+    let current = *SEED.get_or_init(|| seed);
+    if current != seed {
+        anyhow::bail!("Random seed already set to {}", current);
+    }
+    Ok(())
+}
+
+/// The fixed seed, if one was set
+pub fn seed() -> Option<u64> {
+    SEED.get().copied()
+}
+
+/// Returns a generator for `stream`, derived from the fixed seed if one was set
+pub fn rng(stream: &str) -> StdRng {
+    match seed() {
+        Some(seed) => seeded_rng(seed, stream),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Returns the generator of `stream` under `seed`
+///
+/// The same seed and stream always yield the same sequence.
+///
+/// # Examples
+///
+/// ```
+/// use rand::Rng;
+/// use sentri::random::seeded_rng;
+///
+/// let mut a = seeded_rng(7, "retry.jitter");
+/// let mut b = seeded_rng(7, "retry.jitter");
+/// assert_eq!(a.gen::<u64>(), b.gen::<u64>());
+/// ```
+pub fn seeded_rng(seed: u64, stream: &str) -> StdRng {
+    StdRng::seed_from_u64(seed ^ stream_hash(stream))
+}
+
+/// FNV-1a hash of a stream name; stable, unlike the standard library hasher
+fn stream_hash(stream: &str) -> u64 {
+    stream.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
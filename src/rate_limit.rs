@@ -1,23 +1,37 @@
 use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, Semaphore};
 use tokio::time::sleep;
 use tracing::debug;
 
+use crate::rate_report::{InFlight, RateRecorder};
+
 /// A token bucket rate limiter for controlling request rates
 #[derive(Debug)]
 pub struct RateLimiter {
     /// Maximum number of requests allowed in a time period
-    capacity: usize,
+    capacity: AtomicUsize,
     /// Current token count
     tokens: Mutex<usize>,
     /// Time period for token replenishment in milliseconds
-    refill_time_ms: u64,
+    refill_time_ms: AtomicU64,
     /// Last time tokens were refilled
     last_refill: Mutex<Instant>,
     /// Semaphore to limit concurrent requests
     concurrency_limit: Arc<Semaphore>,
+    /// Permits the semaphore was configured with
+    max_concurrent: AtomicUsize,
+    /// Permits still to withdraw after lowering `max_concurrent` while they were in use
+    excess_permits: AtomicUsize,
+    /// Shared limiter that must also grant every request
+    parent: Option<Arc<RateLimiter>>,
+    /// Refill periods since the limiter was created
+    periods: AtomicU64,
+    /// Recorder of granted requests, if reporting
+    recorder: Option<Arc<RateRecorder>>,
 }
 
 impl RateLimiter {
@@ -32,14 +46,50 @@ impl RateLimiter {
         let now = Instant::now();
 
         Self {
-            capacity: requests_per_period,
+            capacity: AtomicUsize::new(requests_per_period),
             tokens: Mutex::new(requests_per_period),
-            refill_time_ms: period_ms,
+            refill_time_ms: AtomicU64::new(period_ms),
             last_refill: Mutex::new(now),
             concurrency_limit: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent: AtomicUsize::new(max_concurrent),
+            excess_permits: AtomicUsize::new(0),
+            parent: None,
+            periods: AtomicU64::new(0),
+            recorder: None,
         }
     }
 
+    /// Records every granted request for a compliance report; see [`crate::rate_report`]
+    pub fn with_recorder(mut self, recorder: Arc<RateRecorder>) -> Self {
+        let period = Duration::from_millis(*self.refill_time_ms.get_mut());
+        recorder.start(
+            *self.last_refill.get_mut(),
+            period,
+            *self.capacity.get_mut() as u64,
+            *self.max_concurrent.get_mut(),
+        );
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Nests this limiter under a shared parent limiter
+    ///
+    /// Every permit then requires a token from both limiters, so several
+    /// children with their own budgets never exceed the parent's combined
+    /// budget.
+    ///
+    /// # Examples
+    /// ```
+    /// # use sentri::rate_limit::RateLimiter;
+    /// # use std::sync::Arc;
+    /// let global = Arc::new(RateLimiter::new(60, 60_000, 10));
+    /// let team = RateLimiter::new(20, 60_000, 5).with_parent(global);
+    /// ```
+    pub fn with_parent(mut self, parent: Arc<RateLimiter>) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
     /// Acquires permission to make a request, waiting if necessary
     ///
     /// This function will wait until a token is available in the bucket,
@@ -61,17 +111,44 @@ impl RateLimiter {
             sleep(wait_time).await;
         }
 
-        // Then acquire a permit for concurrency limiting
-        let permit = self
-            .concurrency_limit
-            .clone()
-            .acquire_owned()
-            .await
-            .context("Failed to acquire concurrency permit")?;
+        // Then acquire a permit for concurrency limiting, withdrawing
+        // permits returned after the limit was lowered
+        let permit = loop {
+            let permit = self
+                .concurrency_limit
+                .clone()
+                .acquire_owned()
+                .await
+                .context("Failed to acquire concurrency permit")?;
+            if take_one(&self.excess_permits) {
+                permit.forget();
+                continue;
+            }
+            break permit;
+        };
+
+        // Finally the shared budget, if this limiter is nested
+        let parent = match &self.parent {
+            Some(parent) => Some(Box::new(Box::pin(parent.acquire()).await?)),
+            None => None,
+        };
 
         debug!("Rate limit permit acquired");
 
-        Ok(RateLimitGuard { _permit: permit })
+        Ok(RateLimitGuard {
+            _permit: permit,
+            _parent: parent,
+            _in_flight: self.recorder.as_ref().map(RateRecorder::in_flight),
+        })
+    }
+
+    /// Takes a token without waiting
+    ///
+    /// Returns false when the bucket is empty. No concurrency permit is held,
+    /// which suits request quotas that reject excess requests instead of
+    /// queueing them.
+    pub async fn try_take(&self) -> bool {
+        self.try_acquire().await == Duration::ZERO
     }
 
     /// Tries to acquire a token from the bucket. If no tokens are available,
@@ -80,29 +157,61 @@ impl RateLimiter {
         let mut tokens = self.tokens.lock().await;
         let mut last_refill = self.last_refill.lock().await;
         let now = Instant::now();
-
-        // Calculate how many tokens to add based on elapsed time
-        let elapsed = now.duration_since(*last_refill).as_millis() as u64;
-
-        if elapsed >= self.refill_time_ms {
-            let periods = elapsed / self.refill_time_ms;
-            let new_tokens = periods as usize * self.capacity;
-
-            *tokens = (*tokens + new_tokens).min(self.capacity);
-            *last_refill = now - Duration::from_millis(elapsed % self.refill_time_ms);
-        }
+        self.refill(&mut tokens, &mut last_refill, now);
 
         if *tokens > 0 {
             *tokens -= 1;
+            if let Some(recorder) = &self.recorder {
+                recorder.record(self.periods.load(Ordering::Relaxed));
+            }
             Duration::ZERO
         } else {
             // Calculate time until next token replenishment
             let time_since_last_refill = now.duration_since(*last_refill).as_millis() as u64;
-            let time_until_next_token = self.refill_time_ms.saturating_sub(time_since_last_refill);
+            let time_until_next_token = self
+                .refill_time_ms
+                .load(Ordering::Relaxed)
+                .saturating_sub(time_since_last_refill);
             Duration::from_millis(time_until_next_token)
         }
     }
 
+    /// Adds the tokens accumulated since the last refill
+    fn refill(&self, tokens: &mut usize, last_refill: &mut Instant, now: Instant) {
+        // Calculate how many tokens to add based on elapsed time
+        let elapsed = now.duration_since(*last_refill).as_millis() as u64;
+        let refill_time_ms = self.refill_time_ms.load(Ordering::Relaxed);
+        let capacity = self.capacity.load(Ordering::Relaxed);
+
+        if elapsed >= refill_time_ms {
+            let periods = elapsed / refill_time_ms;
+            let new_tokens = (periods as usize).saturating_mul(capacity);
+
+            *tokens = tokens.saturating_add(new_tokens).min(capacity);
+            *last_refill = now - Duration::from_millis(elapsed % refill_time_ms);
+            self.periods.fetch_add(periods, Ordering::Relaxed);
+        }
+    }
+
+    /// Reports the tokens and concurrency permits currently available
+    ///
+    /// Nothing is consumed, so this is safe to call from health checks.
+    pub async fn usage(&self) -> LimiterUsage {
+        let mut tokens = self.tokens.lock().await;
+        let mut last_refill = self.last_refill.lock().await;
+        self.refill(&mut tokens, &mut last_refill, Instant::now());
+
+        LimiterUsage {
+            tokens_available: *tokens,
+            capacity: self.capacity.load(Ordering::Relaxed),
+            permits_available: self
+                .concurrency_limit
+                .available_permits()
+                .saturating_sub(self.excess_permits.load(Ordering::Relaxed)),
+            max_concurrent: self.max_concurrent.load(Ordering::Relaxed),
+        }
+    }
+
     /// Updates the rate limiter configuration
     ///
     /// # Arguments
@@ -136,7 +245,6 @@ impl RateLimiter {
     /// # Ok::<(), anyhow::Error>(())
     /// # };
     /// ```
-    #[allow(dead_code)]
     pub async fn update_config(
         &self,
         requests_per_period: usize,
@@ -153,15 +261,18 @@ impl RateLimiter {
 
         // Always ensure at least one token is available after update
         // This guarantees a waiting request can proceed immediately
-        let new_tokens = if requests_per_period > self.capacity {
+        let capacity = self.capacity.swap(requests_per_period, Ordering::Relaxed);
+        let new_tokens = if requests_per_period > capacity {
             // If capacity increased, add at least one new token
-            1.max((requests_per_period - self.capacity) / 2) // Add half the difference but at least 1
+            1.max((requests_per_period - capacity) / 2) // Add half the difference but at least 1
         } else {
             0 // Don't add tokens if capacity decreased
         };
 
         // Set current tokens to at least new_tokens
         *tokens = (*tokens + new_tokens).min(requests_per_period);
+        self.refill_time_ms
+            .store(period_ms.max(1), Ordering::Relaxed);
 
         // Reset the last refill time to now
         *last_refill = Instant::now();
@@ -169,19 +280,119 @@ impl RateLimiter {
         debug!("Updated rate limiter, new tokens available: {}", *tokens);
 
         // Update semaphore for concurrency
-        let current_permits = self.concurrency_limit.available_permits();
-        let diff = max_concurrent as isize - current_permits as isize;
+        let previous = self.max_concurrent.swap(max_concurrent, Ordering::Relaxed);
+        if max_concurrent > previous {
+            // Cancel pending withdrawals first, then add permits
+            let mut added = max_concurrent - previous;
+            while added > 0 && take_one(&self.excess_permits) {
+                added -= 1;
+            }
+            self.concurrency_limit.add_permits(added);
+            debug!("Added {} concurrency permits", added);
+        } else if max_concurrent < previous {
+            // Idle permits are withdrawn now; permits of requests in flight
+            // are withdrawn by the next acquire after they are returned
+            let reduction = previous - max_concurrent;
+            let forgotten = self.concurrency_limit.forget_permits(reduction);
+            self.excess_permits
+                .fetch_add(reduction - forgotten, Ordering::Relaxed);
+            debug!("Withdrew {} concurrency permits", reduction);
+        }
+
+        Ok(())
+    }
+}
+
+/// Decrements `counter` if it is positive, returning true if it was
+fn take_one(counter: &AtomicUsize) -> bool {
+    counter
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+        .is_ok()
+}
+
+/// Point-in-time usage of a [`RateLimiter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimiterUsage {
+    /// Tokens left in the current period
+    pub tokens_available: usize,
+    /// Tokens granted per period
+    pub capacity: usize,
+    /// Requests that may start without waiting for a running one to finish
+    pub permits_available: usize,
+    /// Requests allowed in flight at once
+    pub max_concurrent: usize,
+}
+
+impl LimiterUsage {
+    /// Returns true when the next request would have to wait
+    pub fn is_saturated(&self) -> bool {
+        self.tokens_available == 0 || self.permits_available == 0
+    }
+}
 
-        if diff > 0 {
-            // Add permits if new limit is higher
-            self.concurrency_limit.add_permits(diff as usize);
-            debug!("Added {} concurrency permits", diff);
+/// A shared rate limit with per-owner budgets nested inside it
+///
+/// Used by server mode so that scans of different API keys each have their
+/// own budget while never exceeding the server-wide limit together.
+///
+/// # Examples
+/// ```
+/// # use sentri::rate_limit::RateBudget;
+/// let budget = RateBudget::new(60, 10).with_owner("red-team", 20);
+/// let limiter = budget.limiter_for(Some("red-team"));
+/// let shared = budget.limiter_for(None);
+/// ```
+#[derive(Debug)]
+pub struct RateBudget {
+    shared: Arc<RateLimiter>,
+    owners: HashMap<String, Arc<RateLimiter>>,
+    max_concurrent: usize,
+}
+
+impl RateBudget {
+    /// Creates a budget of `requests_per_minute` shared by all scans
+    pub fn new(requests_per_minute: u64, max_concurrent: usize) -> Self {
+        Self {
+            shared: Arc::new(RateLimiter::new(
+                requests_per_minute as usize,
+                60_000,
+                max_concurrent,
+            )),
+            owners: HashMap::new(),
+            max_concurrent,
         }
+    }
 
-        // Note that if diff < 0, we don't reduce permits, as they will
-        // naturally decrease as current requests complete
+    /// Gives an owner its own budget within the shared one
+    pub fn with_owner(mut self, owner: impl Into<String>, requests_per_minute: u64) -> Self {
+        let limiter = RateLimiter::new(requests_per_minute as usize, 60_000, self.max_concurrent)
+            .with_parent(Arc::clone(&self.shared));
+        self.owners.insert(owner.into(), Arc::new(limiter));
+        self
+    }
 
-        Ok(())
+    /// The server-wide limiter every scan draws from
+    pub fn shared(&self) -> &Arc<RateLimiter> {
+        &self.shared
+    }
+
+    /// Returns the budget of an owner, if it has one
+    pub fn owner(&self, owner: &str) -> Option<&Arc<RateLimiter>> {
+        self.owners.get(owner)
+    }
+
+    /// Names of the owners with their own budget, sorted
+    pub fn owner_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.owners.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns the limiter for scans of an owner, or the shared limiter
+    pub fn limiter_for(&self, owner: Option<&str>) -> &Arc<RateLimiter> {
+        owner
+            .and_then(|owner| self.owners.get(owner))
+            .unwrap_or(&self.shared)
     }
 }
 
@@ -189,6 +400,8 @@ impl RateLimiter {
 #[derive(Debug)]
 pub struct RateLimitGuard {
     _permit: tokio::sync::OwnedSemaphorePermit,
+    _parent: Option<Box<RateLimitGuard>>,
+    _in_flight: Option<InFlight>,
 }
 
 /// Helper function to create a rate limiter specifically for Microsoft API limits
@@ -0,0 +1,241 @@
+//! Rate limit compliance report of batch runs
+//!
+//! `sentri batch --rate-report rate.json` records every request granted by
+//! the batch's rate limiter and writes, when the batch ends, evidence that
+//! the scan stayed inside the agreed throttling constraints:
+//!
+//! - The configured limit (`--rate-limit` per minute) and concurrency
+//!   (`--concurrent-requests`)
+//! - The requests of every one-minute window of the run, with the maximum,
+//!   the 99th percentile and the mean per window
+//! - Every window with more requests than the limit, and the peak number of
+//!   requests in flight
+//!
+//! ```json
+//! {
+//!   "started_at": "2024-05-01T12:00:00Z",
+//!   "completed_at": "2024-05-01T12:42:10Z",
+//!   "window_ms": 60000,
+//!   "limit": 30,
+//!   "max_concurrent": 10,
+//!   "requests": 1254,
+//!   "peak_concurrent": 10,
+//!   "max_per_window": 30,
+//!   "p99_per_window": 30,
+//!   "mean_per_window": 29.16,
+//!   "bursts": [],
+//!   "compliant": true,
+//!   "windows": [{ "start": "2024-05-01T12:00:00Z", "requests": 30 }]
+//! }
+//! ```
+//!
+//! A request is one domain check, the unit `--rate-limit` is counted in;
+//! every domain of the batch takes one, including those answered from the
+//! cache. Windows are the refill periods of the limiter, so a limiter
+//! working as configured never shows a burst.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Requests granted in one window of a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateWindow {
+    /// Start of the window
+    pub start: DateTime<Utc>,
+    /// Requests granted in the window
+    pub requests: u64,
+}
+
+/// Request rates of a run against its configured limits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateReport {
+    /// Start of the first window, when the limiter was created
+    pub started_at: DateTime<Utc>,
+    /// Time the report was made
+    pub completed_at: DateTime<Utc>,
+    /// Length of a window, the refill period of the limiter, in milliseconds
+    pub window_ms: u64,
+    /// Requests allowed per window
+    pub limit: u64,
+    /// Requests allowed in flight at once
+    pub max_concurrent: usize,
+    /// Requests granted over the run
+    pub requests: u64,
+    /// Most requests in flight at once
+    pub peak_concurrent: usize,
+    /// Most requests granted in one window
+    pub max_per_window: u64,
+    /// 99th percentile of the requests per window, by nearest rank
+    pub p99_per_window: u64,
+    /// Mean requests per window
+    pub mean_per_window: f64,
+    /// Windows with more requests than the limit
+    pub bursts: Vec<RateWindow>,
+    /// True when no window exceeded the limit and concurrency stayed within its limit
+    pub compliant: bool,
+    /// Every window of the run, in order
+    pub windows: Vec<RateWindow>,
+}
+
+/// Recorder of the requests granted by a rate limiter
+///
+/// Attached with [`RateLimiter::with_recorder`](crate::rate_limit::RateLimiter::with_recorder).
+///
+/// # Examples
+///
+/// ```
+/// use sentri::rate_limit::RateLimiter;
+/// use sentri::rate_report::RateRecorder;
+/// use std::sync::Arc;
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let recorder = Arc::new(RateRecorder::new());
+/// let limiter = RateLimiter::new(30, 60_000, 5).with_recorder(recorder.clone());
+/// for _ in 0..3 {
+///     limiter.acquire().await.unwrap();
+/// }
+///
+/// let report = recorder.report().unwrap();
+/// assert_eq!((report.requests, report.limit, report.max_per_window), (3, 30, 3));
+/// assert!(report.compliant);
+/// # });
+/// ```
+#[derive(Debug, Default)]
+pub struct RateRecorder {
+    state: Mutex<Option<RecorderState>>,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct RecorderState {
+    origin: Instant,
+    started_at: DateTime<Utc>,
+    window: Duration,
+    limit: u64,
+    max_concurrent: usize,
+    /// Requests granted per window since `origin`
+    windows: Vec<u64>,
+}
+
+impl RateRecorder {
+    /// Creates a recorder, started by the limiter it is attached to
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts the windows at `origin`, the start of the limiter's first refill period
+    pub(crate) fn start(
+        &self,
+        origin: Instant,
+        window: Duration,
+        limit: u64,
+        max_concurrent: usize,
+    ) {
+        let started_at =
+            Utc::now() - chrono::Duration::from_std(origin.elapsed()).unwrap_or_default();
+        *self.state.lock().unwrap() = Some(RecorderState {
+            origin,
+            started_at,
+            window: window.max(Duration::from_millis(1)),
+            limit,
+            max_concurrent,
+            windows: Vec::new(),
+        });
+    }
+
+    /// Records a request granted in the `window`-th refill period of the limiter
+    pub(crate) fn record(&self, window: u64) {
+        if let Some(state) = self.state.lock().unwrap().as_mut() {
+            let index = window as usize;
+            if state.windows.len() <= index {
+                state.windows.resize(index + 1, 0);
+            }
+            state.windows[index] += 1;
+        }
+    }
+
+    /// Marks a request in flight until the returned guard is dropped
+    pub(crate) fn in_flight(self: &Arc<Self>) -> InFlight {
+        let in_flight = self.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak_in_flight.fetch_max(in_flight, Ordering::Relaxed);
+        InFlight(Arc::clone(self))
+    }
+
+    /// Report of the requests recorded so far, or `None` before the recorder was started
+    pub fn report(&self) -> Option<RateReport> {
+        let state = self.state.lock().unwrap();
+        let state = state.as_ref()?;
+
+        // Windows without requests up to now count as well
+        let mut counts = state.windows.clone();
+        let elapsed = (state.origin.elapsed().as_nanos() / state.window.as_nanos()) as usize;
+        counts.resize(counts.len().max(elapsed + 1), 0);
+        let window = chrono::Duration::from_std(state.window).unwrap_or_default();
+        let windows: Vec<RateWindow> = counts
+            .iter()
+            .enumerate()
+            .map(|(index, &requests)| RateWindow {
+                start: state.started_at + window * index as i32,
+                requests,
+            })
+            .collect();
+        let bursts: Vec<RateWindow> = windows
+            .iter()
+            .filter(|window| window.requests > state.limit)
+            .cloned()
+            .collect();
+        let requests: u64 = counts.iter().sum();
+        let peak_concurrent = self.peak_in_flight.load(Ordering::Relaxed);
+
+        let mut sorted = counts.clone();
+        sorted.sort_unstable();
+        let rank = (sorted.len() * 99).div_ceil(100).max(1);
+        Some(RateReport {
+            started_at: state.started_at,
+            completed_at: Utc::now(),
+            window_ms: state.window.as_millis() as u64,
+            limit: state.limit,
+            max_concurrent: state.max_concurrent,
+            requests,
+            peak_concurrent,
+            max_per_window: sorted.last().copied().unwrap_or(0),
+            p99_per_window: sorted[rank - 1],
+            mean_per_window: requests as f64 / counts.len() as f64,
+            compliant: bursts.is_empty() && peak_concurrent <= state.max_concurrent,
+            bursts,
+            windows,
+        })
+    }
+
+    /// Writes the report as pretty-printed JSON
+    ///
+    /// # Errors
+    /// * The recorder was never started
+    /// * The file cannot be written
+    pub async fn write(&self, path: &Path) -> Result<RateReport> {
+        let report = self
+            .report()
+            .context("No requests were rate limited in this run")?;
+        let json = serde_json::to_string_pretty(&report)?;
+        tokio::fs::write(path, json + "\n")
+            .await
+            .with_context(|| format!("Failed to write rate report {}", path.display()))?;
+        Ok(report)
+    }
+}
+
+/// A request in flight, released when dropped
+#[derive(Debug)]
+pub(crate) struct InFlight(Arc<RateRecorder>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
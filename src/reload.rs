@@ -0,0 +1,210 @@
+//! Live configuration reloading for `serve` and `watch`
+//!
+//! Long-running modes re-read the `--config` file without a restart, either
+//! when its modification time changes or, on Unix, when the process receives
+//! `SIGHUP`:
+//!
+//! ```text
+//! systemctl reload sentri    # ExecReload=/bin/kill -HUP $MAINPID
+//! ```
+//!
+//! Only settings that can change under running scans are applied:
+//!
+//! - `[rate_limit]` updates the shared rate limiter in place. Scans in flight
+//!   keep their permits; new requests draw from the new budget. Removing the
+//!   section restores the command-line values.
+//! - `[windows]` is consulted before every scheduled run or watch cycle.
+//!
+//! Retry policies and sink settings are read at startup only. A file that
+//! fails to parse or validate is rejected as a whole and the previous
+//! configuration stays active.
+
+use anyhow::{Context, Result};
+use arc_swap::ArcSwap;
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::config::ConfigFile;
+use crate::rate_limit::RateLimiter;
+
+/// Interval at which the configuration file is checked for changes
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Longest wait before re-checking a closed scan window, so reloads take effect
+const WINDOW_TICK: Duration = Duration::from_secs(60);
+
+/// Configuration that can change while sentri runs
+pub struct LiveConfig {
+    path: Option<PathBuf>,
+    current: ArcSwap<ConfigFile>,
+    modified: Mutex<Option<SystemTime>>,
+    limiter: Arc<RateLimiter>,
+    requests_per_minute: u64,
+    max_concurrent: usize,
+}
+
+impl LiveConfig {
+    /// Creates the live configuration
+    ///
+    /// # Arguments
+    /// * `path` - Configuration file to reload, if any
+    /// * `config` - Configuration loaded at startup
+    /// * `limiter` - Limiter updated from the `[rate_limit]` section
+    /// * `requests_per_minute` - Rate from the command line, used when the section omits it
+    /// * `max_concurrent` - Concurrency from the command line, used when the section omits it
+    pub fn new(
+        path: Option<&Path>,
+        config: ConfigFile,
+        limiter: Arc<RateLimiter>,
+        requests_per_minute: u64,
+        max_concurrent: usize,
+    ) -> Self {
+        let modified = path.and_then(modified_time);
+        Self {
+            path: path.map(Path::to_path_buf),
+            current: ArcSwap::from_pointee(config),
+            modified: Mutex::new(modified),
+            limiter,
+            requests_per_minute,
+            max_concurrent,
+        }
+    }
+
+    /// The configuration currently in effect
+    pub fn current(&self) -> Arc<ConfigFile> {
+        self.current.load_full()
+    }
+
+    /// The limiter the `[rate_limit]` section applies to
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+
+    /// Applies the current `[rate_limit]` section to the limiter
+    ///
+    /// # Errors
+    /// * The section holds a zero rate or concurrency
+    pub async fn apply(&self) -> Result<()> {
+        self.apply_config(&self.current()).await
+    }
+
+    async fn apply_config(&self, config: &ConfigFile) -> Result<()> {
+        let (requests_per_minute, max_concurrent) = config
+            .rate_limit
+            .clone()
+            .unwrap_or_default()
+            .apply(self.requests_per_minute, self.max_concurrent)
+            .context("Invalid [rate_limit] section")?;
+        self.limiter
+            .update_config(requests_per_minute as usize, 60_000, max_concurrent)
+            .await
+    }
+
+    /// Re-reads the configuration file and applies it
+    ///
+    /// # Returns
+    /// * `Result<bool>` - Whether the configuration changed
+    ///
+    /// # Errors
+    /// * The file cannot be read, parsed or validated; the previous
+    ///   configuration stays in effect
+    pub async fn reload(&self) -> Result<bool> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+        *self.modified.lock().unwrap_or_else(|e| e.into_inner()) = modified_time(path);
+        let config = ConfigFile::load(path).await?;
+        if config == *self.current() {
+            return Ok(false);
+        }
+        self.apply_config(&config).await?;
+        self.current.store(Arc::new(config));
+        Ok(true)
+    }
+
+    /// Returns true if the file's modification time differs from the last load
+    fn changed_on_disk(&self) -> bool {
+        let Some(path) = &self.path else {
+            return false;
+        };
+        *self.modified.lock().unwrap_or_else(|e| e.into_inner()) != modified_time(path)
+    }
+
+    /// Waits until the `[windows]` section allows a scan to start
+    pub async fn wait_for_window(&self) {
+        let mut logged = false;
+        while let Some(wait) = self.current().windows.until_open(Utc::now()) {
+            if !logged {
+                info!(
+                    "Outside the allowed scan windows, waiting {}s",
+                    wait.as_secs()
+                );
+                logged = true;
+            }
+            crate::service::idle(wait.clamp(Duration::from_secs(1), WINDOW_TICK)).await;
+        }
+    }
+
+    /// Reloads on file changes and `SIGHUP` until the task is aborted
+    pub fn spawn_watcher(self: &Arc<Self>) -> JoinHandle<()> {
+        let live = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut hangup = Hangup::new();
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {
+                        if !live.changed_on_disk() {
+                            continue;
+                        }
+                    }
+                    _ = hangup.recv() => info!("Received SIGHUP, reloading configuration"),
+                }
+                match live.reload().await {
+                    Ok(true) => info!("Configuration reloaded"),
+                    Ok(false) => {}
+                    Err(e) => error!("Configuration not reloaded: {:#}", e),
+                }
+            }
+        })
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Stream of `SIGHUP` signals; never yields where unsupported
+struct Hangup {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl Hangup {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            Self {
+                signal: signal(SignalKind::hangup())
+                    .map_err(|e| error!("Failed to listen for SIGHUP: {}", e))
+                    .ok(),
+            }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = &mut self.signal {
+            if signal.recv().await.is_some() {
+                return;
+            }
+        }
+        std::future::pending::<()>().await
+    }
+}
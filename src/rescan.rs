@@ -0,0 +1,152 @@
+//! Re-scanning the domains of a previous results file
+//!
+//! `sentri batch --from-results old.jsonl` takes its domains from an earlier
+//! run instead of a domain list, which streamlines re-running yesterday's
+//! failures. [`RescanFilter`] selects which results are scanned again; each
+//! domain is scanned once, in the order of its first result.
+//!
+//! With `--retry-classes timeout,rate_limited`, only failures of those
+//! [`ErrorClass`]es are re-checked and the output is the merged results file:
+//! every other record is carried over unchanged (see [`carried_over`]),
+//! followed by the refreshed records.
+//!
+//! Re-scanned domains keep the input tags of their previous result (see
+//! [`with_tags`]).
+
+use clap::ValueEnum;
+use std::collections::{HashMap, HashSet};
+
+use crate::core::DomainResult;
+use crate::error_class::ErrorClass;
+use crate::tags::{InputLine, Tags};
+
+/// Results whose domains are scanned again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum RescanFilter {
+    /// Every domain of the results file
+    #[default]
+    All,
+    /// Domains whose scan failed
+    Errors,
+    /// Domains scanned successfully without an MDI instance
+    NoMdi,
+    /// Domains with an MDI instance
+    Mdi,
+}
+
+impl RescanFilter {
+    /// Returns true if `result` is selected by the filter
+    pub fn matches(&self, result: &DomainResult) -> bool {
+        match self {
+            RescanFilter::All => true,
+            RescanFilter::Errors => result.error.is_some(),
+            RescanFilter::NoMdi => result.error.is_none() && result.mdi_instance.is_none(),
+            RescanFilter::Mdi => result.mdi_instance.is_some(),
+        }
+    }
+}
+
+/// Domains of the results selected by `filter`, without duplicates
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::rescan::{domains_to_rescan, RescanFilter};
+///
+/// let results = vec![
+///     DomainResult { domain: "contoso.com".into(), ..Default::default() },
+///     DomainResult {
+///         domain: "fabrikam.com".into(),
+///         error: Some("timed out".into()),
+///         ..Default::default()
+///     },
+/// ];
+/// assert_eq!(domains_to_rescan(&results, RescanFilter::Errors), ["fabrikam.com"]);
+/// assert_eq!(domains_to_rescan(&results, RescanFilter::All).len(), 2);
+/// ```
+pub fn domains_to_rescan(results: &[DomainResult], filter: RescanFilter) -> Vec<String> {
+    unique_domains(results.iter().filter(|result| filter.matches(result)))
+}
+
+/// Domains of failed results whose error class is one of `classes`, without duplicates
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::error_class::ErrorClass;
+/// use sentri::rescan::domains_to_retry;
+///
+/// let failed = |domain: &str, error: &str| DomainResult {
+///     domain: domain.into(),
+///     error: Some(error.into()),
+///     ..Default::default()
+/// };
+/// let results = vec![
+///     failed("contoso.com", "Request timed out"),
+///     failed("fabrikam.com", "Invalid domain format: fabrikam.com"),
+/// ];
+/// assert_eq!(domains_to_retry(&results, &[ErrorClass::Timeout]), ["contoso.com"]);
+/// ```
+pub fn domains_to_retry(results: &[DomainResult], classes: &[ErrorClass]) -> Vec<String> {
+    unique_domains(
+        results
+            .iter()
+            .filter(|result| ErrorClass::of(result).is_some_and(|class| classes.contains(&class))),
+    )
+}
+
+/// Results of domains that are not re-checked, in their original order
+pub fn carried_over(results: Vec<DomainResult>, rechecked: &[String]) -> Vec<DomainResult> {
+    let rechecked: HashSet<String> = rechecked
+        .iter()
+        .map(|domain| domain.to_ascii_lowercase())
+        .collect();
+    results
+        .into_iter()
+        .filter(|result| !rechecked.contains(&result.domain.trim().to_ascii_lowercase()))
+        .collect()
+}
+
+/// Pairs domains with the tags of their first result in `results`
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::rescan::with_tags;
+///
+/// let mut result = DomainResult { domain: "Contoso.com".into(), ..Default::default() };
+/// result.tags.insert("bu".into(), "emea".into());
+///
+/// let lines = with_tags(vec!["contoso.com".into(), "fabrikam.com".into()], &[result]);
+/// assert_eq!(lines[0].tags["bu"], "emea");
+/// assert!(lines[1].tags.is_empty());
+/// ```
+pub fn with_tags(domains: Vec<String>, results: &[DomainResult]) -> Vec<InputLine> {
+    let mut tags: HashMap<String, &Tags> = HashMap::new();
+    for result in results.iter().filter(|result| !result.tags.is_empty()) {
+        tags.entry(result.domain.trim().to_ascii_lowercase())
+            .or_insert(&result.tags);
+    }
+    domains
+        .into_iter()
+        .map(|domain| InputLine {
+            tags: tags
+                .get(&domain.to_ascii_lowercase())
+                .map(|tags| (*tags).clone())
+                .unwrap_or_default(),
+            domain,
+        })
+        .collect()
+}
+
+fn unique_domains<'a>(results: impl Iterator<Item = &'a DomainResult>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    results
+        .map(|result| result.domain.trim())
+        .filter(|domain| !domain.is_empty() && seen.insert(domain.to_ascii_lowercase()))
+        .map(str::to_string)
+        .collect()
+}
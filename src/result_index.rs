@@ -0,0 +1,222 @@
+//! Sidecar indexes of JSONL results files
+//!
+//! Results files of large batches reach gigabytes, and tooling interested in a
+//! handful of domains should not have to parse all of them. With
+//! `sentri batch --index`, the JSONL output is accompanied by
+//! `<output>.idx` (see [`index_path`]), a JSONL file with one
+//! [`IndexEntry`] per result giving the byte offset and length of its line:
+//!
+//! ```text
+//! {"domain":"contoso.com","offset":0,"length":412}
+//! {"domain":"fabrikam.com","offset":413,"length":398}
+//! ```
+//!
+//! The index is written incrementally, flushed together with the output after
+//! every chunk, so an interrupted batch still leaves a usable index of the
+//! results written so far. [`find_results`] looks domains up through the index
+//! and falls back to reading the whole file when there is none. A line that
+//! no longer holds the indexed domain, e.g. because the results file was
+//! rewritten, is reported as a stale index instead of returning a wrong result.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+
+use crate::core::DomainResult;
+use crate::result_reader::stream_results;
+
+/// Suffix appended to the path of a results file to name its index
+pub const INDEX_SUFFIX: &str = ".idx";
+
+/// Location of one result in a results file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Domain of the result
+    pub domain: String,
+    /// Byte offset of the result's line
+    pub offset: u64,
+    /// Length of the line in bytes, without the line break
+    pub length: u64,
+}
+
+/// Path of the index of the results file `results`
+///
+/// # Examples
+///
+/// ```
+/// use sentri::result_index::index_path;
+/// use std::path::Path;
+///
+/// assert_eq!(index_path(Path::new("out/results.jsonl")), Path::new("out/results.jsonl.idx"));
+/// ```
+pub fn index_path(results: &Path) -> PathBuf {
+    let mut path = results.as_os_str().to_owned();
+    path.push(INDEX_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Appends index entries while a results file is written
+pub struct IndexWriter {
+    file: File,
+    offset: u64,
+}
+
+impl IndexWriter {
+    /// Creates (or truncates) the index of the results file `results`
+    pub async fn create(results: &Path) -> Result<Self> {
+        let path = index_path(results);
+        let file = File::create(&path)
+            .await
+            .with_context(|| format!("Failed to create index {}", path.display()))?;
+        Ok(Self { file, offset: 0 })
+    }
+
+    /// Records that the line of `domain`, `length` bytes without its line
+    /// break, follows the previously recorded lines
+    pub async fn record(&mut self, domain: &str, length: u64) -> Result<()> {
+        let entry = IndexEntry {
+            domain: domain.to_ascii_lowercase(),
+            offset: self.offset,
+            length,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        self.file.write_all(&line).await?;
+        self.offset += length + 1;
+        Ok(())
+    }
+
+    /// Flushes the entries recorded so far
+    pub async fn flush(&mut self) -> Result<()> {
+        self.file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Index of a results file, loaded in memory
+#[derive(Debug, Default)]
+pub struct ResultIndex {
+    /// Latest entry of each lowercase domain
+    entries: HashMap<String, IndexEntry>,
+}
+
+impl ResultIndex {
+    /// Loads the index of the results file `results`, if it has one
+    pub async fn load(results: &Path) -> Result<Option<Self>> {
+        let path = index_path(results);
+        let file = match File::open(&path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to open index {}", path.display()))
+            }
+        };
+        let mut lines = BufReader::new(file).lines();
+        let mut entries = HashMap::new();
+        let mut line_number = 0;
+        while let Some(line) = lines.next_line().await? {
+            line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: IndexEntry = serde_json::from_str(&line).with_context(|| {
+                format!(
+                    "Invalid entry on line {} of {}",
+                    line_number,
+                    path.display()
+                )
+            })?;
+            // Later lines supersede earlier ones
+            entries.insert(entry.domain.clone(), entry);
+        }
+        Ok(Some(Self { entries }))
+    }
+
+    /// Number of indexed domains
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no domain is indexed
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Location of the result of `domain`
+    pub fn get(&self, domain: &str) -> Option<&IndexEntry> {
+        self.entries.get(&domain.to_ascii_lowercase())
+    }
+}
+
+/// Reads the result at `entry` from the results file `results`
+pub async fn read_result_at(results: &Path, entry: &IndexEntry) -> Result<DomainResult> {
+    let stale = || {
+        format!(
+            "Stale index of {}: no result at offset {}",
+            results.display(),
+            entry.offset
+        )
+    };
+    let mut file = File::open(results)
+        .await
+        .with_context(|| format!("Failed to open results file {}", results.display()))?;
+    file.seek(SeekFrom::Start(entry.offset)).await?;
+    let mut line = vec![0; entry.length as usize];
+    file.read_exact(&mut line).await.with_context(stale)?;
+    let result: DomainResult = serde_json::from_slice(&line).with_context(stale)?;
+    if !result.domain.eq_ignore_ascii_case(&entry.domain) {
+        bail!(
+            "Stale index of {}: offset {} holds {}, not {}",
+            results.display(),
+            entry.offset,
+            result.domain,
+            entry.domain
+        );
+    }
+    Ok(result)
+}
+
+/// Results of `domains` in the results file `results`, in the order asked for
+///
+/// Uses the index of the file when it has one and scans the whole file
+/// otherwise, keeping only the results asked for. Domains without a result map to `None`; the last result of a
+/// domain listed several times wins.
+pub async fn find_results(
+    results: &Path,
+    domains: &[String],
+) -> Result<Vec<(String, Option<DomainResult>)>> {
+    let mut found = Vec::with_capacity(domains.len());
+    match ResultIndex::load(results).await? {
+        Some(index) => {
+            for domain in domains {
+                let result = match index.get(domain) {
+                    Some(entry) => Some(read_result_at(results, entry).await?),
+                    None => None,
+                };
+                found.push((domain.clone(), result));
+            }
+        }
+        None => {
+            let mut by_domain: HashMap<String, Option<DomainResult>> = domains
+                .iter()
+                .map(|domain| (domain.to_ascii_lowercase(), None))
+                .collect();
+            let mut stream = stream_results(results);
+            while let Some(result) = stream.next().await {
+                let result = result?;
+                if let Some(found) = by_domain.get_mut(&result.domain.to_ascii_lowercase()) {
+                    *found = Some(result);
+                }
+            }
+            for domain in domains {
+                let result = by_domain[&domain.to_ascii_lowercase()].clone();
+                found.push((domain.clone(), result));
+            }
+        }
+    }
+    Ok(found)
+}
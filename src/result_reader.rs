@@ -0,0 +1,306 @@
+//! Memory-mapped, parallel reading of large JSONL results files
+//!
+//! Post-processing subcommands (`policy`, `graph`, `baseline`, `lookup`,
+//! `verify-evidence` and `batch --from-results`) read whole results files,
+//! which reach tens of millions of records for recurring scans. Parsing them
+//! line by line on a single core dominates their run time, so the file is
+//! instead mapped into memory with [`MappedFile`], cut into chunks at line
+//! boundaries and the chunks are parsed in parallel on the rayon thread pool.
+//!
+//! Results are yielded lazily by [`ResultLines`] in the order of the file:
+//! only one window of chunks, a few per rayon thread, is parsed ahead of the
+//! caller, so reading a file needs memory for that window rather than for
+//! every record. Async callers consume a [`ResultStream`] from
+//! [`stream_results`].
+//!
+//! # Performance Considerations
+//!
+//! - The file is never copied into the heap; the kernel pages it in as the
+//!   chunks are parsed (performance:memory:use_streaming_io)
+//! - Chunks are about [`CHUNK_SIZE`] bytes so small files are not split into
+//!   more tasks than is worth scheduling
+//!
+//! Reading past the end of a mapping of a file truncated in the meantime
+//! faults, so on Unix a [`MappedFile`] holds a shared lock on the file while
+//! it is mapped, and results files are only truncated under an exclusive lock
+//! taken by [`truncate_results_file`]. Appending to a mapped file is safe.
+
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::collections::VecDeque;
+use std::fs::{File, TryLockError};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::core::DomainResult;
+
+/// Size of a chunk handed to a parser thread
+pub const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Chunks per rayon thread parsed ahead of the caller, so threads finishing
+/// early pick up more work
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// Results buffered between the parser and an async [`ResultStream`]
+const STREAM_CAPACITY: usize = 1024;
+
+/// Read-only memory mapping of a whole file
+///
+/// On Unix the file stays locked for shared access until the mapping is
+/// dropped, so writers going through [`truncate_results_file`] cannot
+/// truncate it while it is read. Writers ignoring the lock can; the mapping
+/// then faults on access to the truncated pages. Windows refuses to truncate
+/// mapped files, and its locks would also block appending writers, so the
+/// file is not locked there.
+pub struct MappedFile {
+    map: Option<Mmap>,
+    _file: File,
+}
+
+impl MappedFile {
+    /// Maps the file at `path`
+    ///
+    /// Waits while the file is being truncated. Empty files, which cannot be
+    /// mapped, are read as empty.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open results file {}", path.display()))?;
+        #[cfg(unix)]
+        file.lock_shared()
+            .with_context(|| format!("Failed to lock results file {}", path.display()))?;
+        let map = if file.metadata()?.len() == 0 {
+            None
+        } else {
+            Some(
+                unsafe { Mmap::map(&file) }
+                    .with_context(|| format!("Failed to map results file {}", path.display()))?,
+            )
+        };
+        Ok(Self { map, _file: file })
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+}
+
+/// Truncates a results file that is about to be rewritten
+///
+/// Fails instead of truncating while a [`MappedFile`] of the file is open.
+pub fn truncate_results_file(file: &File, path: &Path) -> Result<()> {
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            anyhow::bail!(
+                "Results file {} is being read; retry once the reader has finished",
+                path.display()
+            )
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(e)
+                .with_context(|| format!("Failed to lock results file {}", path.display()))
+        }
+    }
+    let truncated = file
+        .set_len(0)
+        .with_context(|| format!("Failed to truncate results file {}", path.display()));
+    file.unlock()?;
+    truncated
+}
+
+/// Lazy iterator over the JSONL results of `D`, skipping blank lines
+///
+/// Yields results in the order of the file. Parsing stops at the first
+/// invalid line, which is yielded as an error naming its line number.
+pub struct ResultLines<D> {
+    data: D,
+    offset: usize,
+    line: usize,
+    parsed: VecDeque<DomainResult>,
+    error: Option<anyhow::Error>,
+}
+
+impl<D: Deref<Target = [u8]>> ResultLines<D> {
+    fn new(data: D) -> Self {
+        Self {
+            data,
+            offset: 0,
+            line: 0,
+            parsed: VecDeque::new(),
+            error: None,
+        }
+    }
+
+    /// Parses the next window of chunks in parallel
+    fn parse_window(&mut self) {
+        let data = &self.data[self.offset..];
+        let chunks = split_lines(
+            data,
+            CHUNK_SIZE,
+            rayon::current_num_threads() * CHUNKS_PER_THREAD,
+        );
+        let parsed: Vec<ParsedChunk> = chunks.par_iter().map(|chunk| parse_chunk(chunk)).collect();
+
+        for (chunk, (results, error)) in chunks.iter().zip(parsed) {
+            self.parsed.extend(results);
+            if let Some((line, error)) = error {
+                self.error = Some(
+                    anyhow::Error::new(error)
+                        .context(format!("Invalid result on line {}", self.line + line)),
+                );
+                self.offset = self.data.len();
+                return;
+            }
+            self.line += chunk.iter().filter(|&&b| b == b'\n').count();
+            self.offset += chunk.len();
+        }
+    }
+}
+
+impl<D: Deref<Target = [u8]>> Iterator for ResultLines<D> {
+    type Item = Result<DomainResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.parsed.is_empty() && self.offset < self.data.len() {
+            self.parse_window();
+        }
+        match self.parsed.pop_front() {
+            Some(result) => Some(Ok(result)),
+            None => self.error.take().map(Err),
+        }
+    }
+}
+
+/// Parses JSONL results lazily, skipping blank lines
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::result_reader::parse_results;
+///
+/// let line = |domain: &str| {
+///     let result = DomainResult { domain: domain.to_string(), ..Default::default() };
+///     serde_json::to_string(&result).unwrap()
+/// };
+///
+/// let jsonl = format!("{}\n\n{}\n", line("contoso.com"), line("fabrikam.com"));
+/// let results: Vec<DomainResult> = parse_results(jsonl.as_bytes())
+///     .collect::<anyhow::Result<_>>()
+///     .unwrap();
+/// assert_eq!(results[1].domain, "fabrikam.com");
+///
+/// let jsonl = format!("{}\nnot json\n", line("contoso.com"));
+/// let mut results = parse_results(jsonl.as_bytes());
+/// assert_eq!(results.next().unwrap().unwrap().domain, "contoso.com");
+/// let error = results.next().unwrap().unwrap_err();
+/// assert_eq!(error.to_string(), "Invalid result on line 2");
+/// assert!(results.next().is_none());
+/// ```
+pub fn parse_results(data: &[u8]) -> ResultLines<&[u8]> {
+    ResultLines::new(data)
+}
+
+/// Reads results lazily from a JSONL file through a memory mapping
+///
+/// Blocks while parsing; async callers go through [`stream_results`] or,
+/// to collect every result, [`crate::policy::read_results`].
+pub fn read_results_mapped(path: &Path) -> Result<ResultLines<MappedFile>> {
+    Ok(ResultLines::new(MappedFile::open(path)?))
+}
+
+/// Results of a file parsed on the blocking thread pool
+///
+/// Created by [`stream_results`].
+pub struct ResultStream {
+    results: mpsc::Receiver<Result<DomainResult>>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl ResultStream {
+    /// Next result of the file, or `None` once every result was read
+    pub async fn next(&mut self) -> Option<Result<DomainResult>> {
+        if let Some(result) = self.results.recv().await {
+            return Some(result);
+        }
+        match self.reader.take()?.await {
+            Ok(()) => None,
+            Err(e) => Some(Err(e).context("Results reader panicked")),
+        }
+    }
+}
+
+/// Reads the results of a JSONL file in the background
+///
+/// Parsing stays at most a bounded number of results ahead of the consumer.
+/// Errors opening or parsing the file are yielded by the stream.
+///
+/// # Panics
+/// Panics when called outside of a Tokio runtime.
+pub fn stream_results(path: &Path) -> ResultStream {
+    let path: PathBuf = path.to_path_buf();
+    let (sender, results) = mpsc::channel(STREAM_CAPACITY);
+    let reader = tokio::task::spawn_blocking(move || {
+        let lines = match read_results_mapped(&path) {
+            Ok(lines) => lines,
+            Err(e) => {
+                let _ = sender.blocking_send(Err(e));
+                return;
+            }
+        };
+        for result in lines {
+            if sender.blocking_send(result).is_err() {
+                return;
+            }
+        }
+    });
+    ResultStream {
+        results,
+        reader: Some(reader),
+    }
+}
+
+/// Cuts up to `max_chunks` chunks of about `chunk_size` bytes ending after a
+/// line break off the front of `data`
+fn split_lines(data: &[u8], chunk_size: usize, max_chunks: usize) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < data.len() && chunks.len() < max_chunks.max(1) {
+        let mut end = (start + chunk_size).min(data.len());
+        if end < data.len() {
+            end = match data[end..].iter().position(|&b| b == b'\n') {
+                Some(offset) => end + offset + 1,
+                None => data.len(),
+            };
+        }
+        chunks.push(&data[start..end]);
+        start = end;
+    }
+    chunks
+}
+
+/// Results of one chunk up to its first invalid line, and the number of that
+/// line within the chunk with its error
+type ParsedChunk = (Vec<DomainResult>, Option<(usize, serde_json::Error)>);
+
+/// Parses the lines of one chunk, stopping at the first invalid line
+fn parse_chunk(chunk: &[u8]) -> ParsedChunk {
+    let mut results = Vec::new();
+    for (index, line) in chunk.split(|&b| b == b'\n').enumerate() {
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+        match serde_json::from_slice(line) {
+            Ok(result) => results.push(result),
+            Err(e) => return (results, Some((index + 1, e))),
+        }
+    }
+    (results, None)
+}
@@ -0,0 +1,192 @@
+//! JSON Schema of the result format
+//!
+//! Every [`DomainResult`](crate::core::DomainResult) carries a
+//! `schema_version`, and `sentri schema` prints the JSON Schema (draft
+//! 2020-12) of that version, so pipelines consuming `json`/`jsonl` output can
+//! validate records and notice a breaking change before ingesting it:
+//!
+//! ```text
+//! sentri schema > sentri-result.schema.json
+//! ```
+//!
+//! Optional fields may be added within a version; properties unknown to the
+//! schema are therefore allowed.
+
+use serde_json::{json, Value};
+
+use crate::core::RESULT_SCHEMA_VERSION;
+
+/// `$id` of the schema, suffixed with the schema version
+pub const SCHEMA_ID_PREFIX: &str = "https://github.com/copyleftdev/sentri/schemas/domain-result/v";
+
+/// Returns the JSON Schema of [`DomainResult`](crate::core::DomainResult)
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::RESULT_SCHEMA_VERSION;
+/// use sentri::result_schema::domain_result_schema;
+///
+/// let schema = domain_result_schema();
+/// assert_eq!(schema["properties"]["schema_version"]["const"], RESULT_SCHEMA_VERSION);
+/// assert!(schema["required"].as_array().unwrap().contains(&"domain".into()));
+/// ```
+pub fn domain_result_schema() -> Value {
+    let nullable_string = json!({ "type": ["string", "null"] });
+    let timestamp = json!({
+        "type": "string",
+        "format": "date-time",
+        "description": "RFC 3339 timestamp in UTC"
+    });
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": format!("{}{}", SCHEMA_ID_PREFIX, RESULT_SCHEMA_VERSION),
+        "title": "DomainResult",
+        "description": "Result of scanning one domain for Microsoft tenant and MDI information",
+        "type": "object",
+        "required": [
+            "schema_version",
+            "domain",
+            "tenant",
+            "federated_domains",
+            "mdi_instance",
+            "mdi_generation",
+            "processing_time_ms",
+            "error",
+            "from_cache",
+            "checked_at",
+            "completed_at"
+        ],
+        "properties": {
+            "schema_version": {
+                "description": "Version of the result format",
+                "const": RESULT_SCHEMA_VERSION
+            },
+            "domain": {
+                "description": "The domain that was scanned",
+                "type": "string"
+            },
+            "tenant": {
+                "description": "Microsoft tenant name, if detected",
+                "type": ["string", "null"]
+            },
+            "federated_domains": {
+                "description": "Domains federated with the scanned domain",
+                "type": "array",
+                "items": { "type": "string" }
+            },
+            "mdi_instance": {
+                "description": "Host of the MDI sensor API, if detected",
+                "type": ["string", "null"]
+            },
+            "mdi_generation": {
+                "description": "Generation of the MDI sensor endpoint namespace",
+                "enum": ["legacy", "unified", null]
+            },
+            "mdi_confidence": {
+                "description": "Whether DNSSEC confirms that the tenant has no MDI instance",
+                "enum": ["unconfirmed", "dnssec_confirmed"]
+            },
+            "endpoint_anomalies": {
+                "description": "Federated endpoints resolving outside Microsoft address space",
+                "type": "array",
+                "items": { "$ref": "#/$defs/EndpointAnomaly" }
+            },
+            "engagement": {
+                "description": "Engagement the scan was performed under",
+                "$ref": "#/$defs/Engagement"
+            },
+            "tags": {
+                "description": "Tags of the domain in the batch input",
+                "type": "object",
+                "additionalProperties": { "type": "string" }
+            },
+            "processing_time_ms": {
+                "description": "Time taken to process the domain in milliseconds",
+                "type": "integer",
+                "minimum": 0
+            },
+            "response_sha256": {
+                "description": "Canonical SHA-256 of the federation response",
+                "type": "string",
+                "pattern": "^[0-9a-f]{64}$"
+            },
+            "schema_warnings": {
+                "description": "Deviations of the response from the Autodiscover schema",
+                "type": "array",
+                "items": { "$ref": "#/$defs/SchemaWarning" }
+            },
+            "error": {
+                "description": "Error message if the scan failed",
+                "type": ["string", "null"]
+            },
+            "error_class": {
+                "description": "Class of the failure, if the scan failed",
+                "enum": [
+                    "invalid_domain",
+                    "timeout",
+                    "rate_limited",
+                    "dns",
+                    "connect",
+                    "http_status",
+                    "soap_fault",
+                    "invalid_response",
+                    "offline",
+                    "egress_blocked",
+                    "other"
+                ]
+            },
+            "from_cache": {
+                "description": "True if the result was served from the cache",
+                "type": "boolean"
+            },
+            "checked_at": timestamp,
+            "completed_at": timestamp
+        },
+        "$defs": {
+            "EndpointAnomaly": {
+                "type": "object",
+                "required": ["domain", "kind", "host", "addresses"],
+                "properties": {
+                    "domain": { "type": "string" },
+                    "kind": { "enum": ["mx", "autodiscover"] },
+                    "host": { "type": "string" },
+                    "addresses": {
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "anyOf": [{ "format": "ipv4" }, { "format": "ipv6" }]
+                        }
+                    }
+                }
+            },
+            "Engagement": {
+                "type": "object",
+                "properties": {
+                    "engagement_id": nullable_string,
+                    "operator": nullable_string
+                }
+            },
+            "SchemaWarning": {
+                "type": "object",
+                "required": ["violation", "path", "message"],
+                "properties": {
+                    "violation": {
+                        "enum": [
+                            "malformed",
+                            "unexpected_element",
+                            "missing_element",
+                            "too_many_elements",
+                            "out_of_order",
+                            "unexpected_text",
+                            "invalid_value"
+                        ]
+                    },
+                    "path": { "type": "string" },
+                    "message": { "type": "string" }
+                }
+            }
+        }
+    })
+}
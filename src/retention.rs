@@ -0,0 +1,264 @@
+//! Retention of stored results and audit records
+//!
+//! Server mode accumulates scan results (`jobs/*.jsonl`, `results/<schedule>/*.jsonl`)
+//! and an audit trail (`audit.jsonl`) in its state directory. Data-handling
+//! agreements often cap how long such reconnaissance data may be kept, so
+//! both can be given a maximum age:
+//!
+//! - `sentri purge --state-dir DIR --older-than 90d` removes expired data once
+//! - `sentri serve --retention 90d --audit-retention 365d` enforces the limits
+//!   hourly while the server runs
+//!
+//! A result file's age is taken from its last modification, i.e. when the
+//! scan that wrote it finished. Audit records are aged individually by their
+//! timestamp; records that cannot be parsed are kept.
+//!
+//! Watch mode keeps its state in memory only and has nothing to purge.
+//!
+//! Audit records are defined by the server, so `purge` and
+//! `purge_audit_file` are only built with the `server` feature.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+#[cfg(feature = "server")]
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "server")]
+use tracing::{info, warn};
+
+#[cfg(feature = "server")]
+use crate::encryption::{LineDecoder, LineEncryptor, StorageKey};
+#[cfg(feature = "server")]
+use crate::server::{AuditRecord, AUDIT_LOG_FILE};
+
+/// Maximum ages of stored data; `None` keeps data forever
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Maximum age of job and schedule result files
+    pub results: Option<Duration>,
+    /// Maximum age of audit records
+    pub audit: Option<Duration>,
+}
+
+impl RetentionPolicy {
+    /// Returns true if the policy keeps everything
+    pub fn is_unlimited(&self) -> bool {
+        self.results.is_none() && self.audit.is_none()
+    }
+}
+
+/// What a purge removed (or would remove, in a dry run)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PurgeReport {
+    /// Result files deleted
+    pub result_files: usize,
+    /// Bytes freed by deleting result files
+    pub result_bytes: u64,
+    /// Audit records dropped
+    pub audit_records: usize,
+}
+
+/// Parses an age such as `90d`, `12h`, `30m`, `2w` or `3600s`
+///
+/// # Examples
+///
+/// ```
+/// use sentri::retention::parse_age;
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_age("90d").unwrap(), Duration::from_secs(90 * 86_400));
+/// assert!(parse_age("90").is_err());
+/// ```
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .filter(|&i| i > 0)
+        .with_context(|| format!("Invalid age {:?}; expected e.g. 90d", value))?;
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid age {:?}", value))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => anyhow::bail!("Invalid age unit {:?}; use s, m, h, d or w", unit),
+    };
+    number
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .with_context(|| format!("Age {:?} is too large", value))
+}
+
+/// Returns the instant before which data is expired
+pub fn cutoff(now: DateTime<Utc>, max_age: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(max_age)
+        .ok()
+        .and_then(|age| now.checked_sub_signed(age))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// Deletes result files in the state directory last written before `cutoff`
+///
+/// # Arguments
+/// * `state_dir` - Server state directory
+/// * `cutoff` - Files modified before this instant are deleted
+/// * `dry_run` - Only count the files that would be deleted
+pub async fn purge_results(
+    state_dir: &Path,
+    cutoff: DateTime<Utc>,
+    dry_run: bool,
+) -> Result<PurgeReport> {
+    let mut dirs = vec![state_dir.join("jobs")];
+    let results_dir = state_dir.join("results");
+    if let Ok(mut schedules) = tokio::fs::read_dir(&results_dir).await {
+        while let Some(entry) = schedules.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+    }
+
+    let cutoff = SystemTime::from(cutoff);
+    let mut report = PurgeReport::default();
+    for dir in dirs {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() || metadata.modified()? >= cutoff {
+                continue;
+            }
+            if !dry_run {
+                tokio::fs::remove_file(&path)
+                    .await
+                    .with_context(|| format!("Failed to delete {}", path.display()))?;
+            }
+            report.result_files += 1;
+            report.result_bytes += metadata.len();
+        }
+    }
+    Ok(report)
+}
+
+/// Drops audit records older than `cutoff`, returning how many were dropped
+///
+/// The file is rewritten atomically; a missing file counts as empty. An
+/// encrypted file needs its `key` and is rewritten encrypted, as a new file.
+/// Writers holding the file open must reopen it afterwards (see
+/// `AuditLog::purge`).
+#[cfg(feature = "server")]
+pub async fn purge_audit_file(
+    path: &Path,
+    cutoff: DateTime<Utc>,
+    dry_run: bool,
+    key: Option<&Arc<StorageKey>>,
+) -> Result<usize> {
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    let mut decoder = LineDecoder::new();
+    let mut kept = Vec::new();
+    let mut dropped = 0;
+    for (index, line) in content.lines().enumerate() {
+        let Some(line) = decoder
+            .decode(key.map(|key| key.as_ref()), line)
+            .with_context(|| format!("Unreadable line {} of {}", index + 1, path.display()))?
+        else {
+            continue;
+        };
+        match serde_json::from_str::<AuditRecord>(&line) {
+            Ok(record) if record.timestamp < cutoff => dropped += 1,
+            Ok(_) => kept.push(line),
+            Err(e) => {
+                warn!(
+                    "Keeping unparseable audit record in {}: {}",
+                    path.display(),
+                    e
+                );
+                kept.push(line);
+            }
+        }
+    }
+
+    if dropped > 0 && !dry_run {
+        let mut encryptor = key
+            .filter(|_| decoder.is_encrypted())
+            .map(|key| LineEncryptor::new(Arc::clone(key)));
+        let mut rewritten = String::with_capacity(content.len());
+        if let Some(encryptor) = &encryptor {
+            rewritten.push_str(&encryptor.header());
+            rewritten.push('\n');
+        }
+        for line in kept {
+            match &mut encryptor {
+                Some(encryptor) => rewritten.push_str(&encryptor.encrypt(&line)?),
+                None => rewritten.push_str(&line),
+            }
+            rewritten.push('\n');
+        }
+        let temp = path.with_extension("jsonl.tmp");
+        tokio::fs::write(&temp, rewritten)
+            .await
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        tokio::fs::rename(&temp, path)
+            .await
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+    }
+    Ok(dropped)
+}
+
+/// Applies a retention policy to a state directory that no server is using
+///
+/// # Arguments
+/// * `state_dir` - Server state directory
+/// * `policy` - Maximum ages of results and audit records
+/// * `now` - Reference time for computing ages
+/// * `dry_run` - Only report what would be removed
+/// * `key` - Storage key the audit log is encrypted with, if any
+#[cfg(feature = "server")]
+pub async fn purge(
+    state_dir: &Path,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+    dry_run: bool,
+    key: Option<&Arc<StorageKey>>,
+) -> Result<PurgeReport> {
+    let mut report = match policy.results {
+        Some(max_age) => purge_results(state_dir, cutoff(now, max_age), dry_run).await?,
+        None => PurgeReport::default(),
+    };
+    if let Some(max_age) = policy.audit {
+        report.audit_records = purge_audit_file(
+            &state_dir.join(AUDIT_LOG_FILE),
+            cutoff(now, max_age),
+            dry_run,
+            key,
+        )
+        .await?;
+    }
+    info!(
+        "{} {} result files ({} bytes) and {} audit records from {}",
+        if dry_run { "Would purge" } else { "Purged" },
+        report.result_files,
+        report.result_bytes,
+        report.audit_records,
+        state_dir.display()
+    );
+    Ok(report)
+}
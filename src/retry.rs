@@ -6,7 +6,8 @@
 //!
 //! - Configurable retry counts and backoff parameters
 //! - Exponential delay between retry attempts
-//! - Optional jitter to prevent thundering herd problems
+//! - Optional jitter to prevent thundering herd problems, reproducible with
+//!   `--seed` (see [`crate::random`])
 //! - Custom retry condition evaluation
 //! - Detailed retry attempt logging
 //!
@@ -14,11 +15,15 @@
 //! the application's logging system for observability.
 
 use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::Rng;
 use std::future::Future;
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::debug;
 
+use crate::random::{self, RETRY_JITTER_STREAM};
+
 /// Configuration for the exponential backoff retry strategy
 ///
 /// Controls how retry operations are performed, including:
@@ -44,6 +49,7 @@ use tracing::debug;
 ///     add_jitter: true,
 /// };
 /// ```
+#[derive(Debug, Clone, PartialEq)]
 pub struct RetryConfig {
     /// Maximum number of retry attempts
     pub max_retries: u32,
@@ -133,6 +139,8 @@ where
 {
     let mut attempt = 0;
     let mut backoff_ms = config.initial_backoff_ms;
+    // Created on the first retry; most operations succeed right away
+    let mut jitter_rng: Option<StdRng> = None;
 
     loop {
         let result = operation().await;
@@ -149,7 +157,8 @@ where
 
                 // Calculate next backoff with optional jitter
                 let jitter_ms = if config.add_jitter {
-                    let jitter_factor = rand::random::<f64>() * 0.2 + 0.9; // 0.9-1.1 range
+                    let rng = jitter_rng.get_or_insert_with(|| random::rng(RETRY_JITTER_STREAM));
+                    let jitter_factor = rng.gen::<f64>() * 0.2 + 0.9; // 0.9-1.1 range
                     (backoff_ms as f64 * jitter_factor) as u64
                 } else {
                     backoff_ms
@@ -159,8 +168,10 @@ where
                 let delay = std::cmp::min(jitter_ms, config.max_backoff_ms);
 
                 debug!(
-                    "Retry attempt {}/{} after {}ms delay",
-                    attempt, config.max_retries, delay
+                    attempt,
+                    max_retries = config.max_retries,
+                    delay_ms = delay,
+                    "Retrying after backoff"
                 );
 
                 sleep(Duration::from_millis(delay)).await;
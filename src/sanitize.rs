@@ -3,8 +3,36 @@
 // Output sanitization module to prevent information leaks
 // Implements the security:output:sanitize_all_output rule
 
+use crate::attribution::EndpointAnomaly;
 use crate::core::DomainResult;
+use crate::engagement::Engagement;
+use crate::xml_schema::SchemaWarning;
 use html_escape::encode_text;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::LazyLock;
+
+// Patterns are compiled once, on first use, instead of for every result
+
+/// Unix paths such as /home/user/path
+static UNIX_PATH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(/[a-zA-Z0-9_\-\.]+)+").expect("valid Unix path pattern"));
+
+/// Windows drive and UNC paths such as C:\Users\alice or \\server\share
+static WINDOWS_PATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\b[a-zA-Z]:|\\\\[a-zA-Z0-9_\-\.$]+)(?:\\[a-zA-Z0-9_\-\.$]+)+\\?")
+        .expect("valid Windows path pattern")
+});
+
+/// Candidates for IPv4 addresses, confirmed by parsing
+static IPV4_CANDIDATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b\d{1,3}(?:\.\d{1,3}){3}\b").expect("valid IPv4 pattern"));
+
+/// Candidates for IPv6 addresses, confirmed by parsing
+static IPV6_CANDIDATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[0-9a-fA-F]{0,4}(?::[0-9a-fA-F]{0,4}){2,7}").expect("valid IPv6 pattern")
+});
 
 /// Sanitizes a domain result before output to prevent information leaks
 ///
@@ -21,6 +49,8 @@ use html_escape::encode_text;
 pub fn sanitize_domain_result(result: &DomainResult) -> DomainResult {
     // Create a new result with sanitized fields
     DomainResult {
+        schema_version: result.schema_version,
+
         // Sanitize the domain name
         domain: sanitize_domain(&result.domain),
 
@@ -37,11 +67,63 @@ pub fn sanitize_domain_result(result: &DomainResult) -> DomainResult {
         // Sanitize optional MDI instance
         mdi_instance: result.mdi_instance.as_ref().map(|m| sanitize_string(m)),
 
+        // Keep the enumerated endpoint generation
+        mdi_generation: result.mdi_generation,
+
+        // Keep the enumerated confidence of a negative verdict
+        mdi_confidence: result.mdi_confidence,
+
+        // Sanitize endpoint and domain names of attribution anomalies
+        endpoint_anomalies: result
+            .endpoint_anomalies
+            .iter()
+            .map(|a| EndpointAnomaly {
+                domain: sanitize_domain(&a.domain),
+                host: sanitize_domain(&a.host),
+                ..a.clone()
+            })
+            .collect(),
+
+        // Sanitize engagement metadata
+        engagement: result.engagement.as_ref().map(|e| Engagement {
+            engagement_id: e.engagement_id.as_ref().map(|id| sanitize_string(id)),
+            operator: e.operator.as_ref().map(|op| sanitize_string(op)),
+        }),
+
+        // Sanitize keys and values of input tags
+        tags: result
+            .tags
+            .iter()
+            .map(|(key, value)| (sanitize_string(key), sanitize_string(value)))
+            .collect(),
+
         // Keep numeric processing time
         processing_time_ms: result.processing_time_ms,
 
+        // Sanitize optional response hash
+        response_sha256: result.response_sha256.as_ref().map(|h| sanitize_string(h)),
+
+        // Sanitize paths and messages of schema warnings
+        schema_warnings: result
+            .schema_warnings
+            .iter()
+            .map(|w| SchemaWarning {
+                violation: w.violation,
+                path: sanitize_string(&w.path),
+                message: sanitize_string(&w.message),
+            })
+            .collect(),
+
         // Sanitize optional error message
         error: result.error.as_ref().map(|e| sanitize_error(e)),
+
+        // Keep the enumerated error class
+        error_class: result.error_class,
+
+        // Keep cache provenance
+        from_cache: result.from_cache,
+        checked_at: result.checked_at,
+        completed_at: result.completed_at,
     }
 }
 
@@ -53,16 +135,11 @@ pub fn sanitize_domain_result(result: &DomainResult) -> DomainResult {
 /// # Returns
 /// * `String` - Sanitized domain
 fn sanitize_domain(domain: &str) -> String {
-    let trimmed = domain.trim();
-
     // Filter out any control characters
-    let filtered = trimmed
-        .chars()
-        .filter(|c| !c.is_control())
-        .collect::<String>();
+    let filtered = without_controls(domain.trim());
 
     // Encode HTML entities to prevent XSS if output is rendered in HTML
-    encode_text(&filtered).to_string()
+    encode_text(&filtered).into_owned()
 }
 
 /// Sanitizes a general string value
@@ -73,20 +150,27 @@ fn sanitize_domain(domain: &str) -> String {
 /// # Returns
 /// * `String` - Sanitized string
 fn sanitize_string(value: &str) -> String {
-    let trimmed = value.trim();
-
     // Filter out control characters
-    let filtered = trimmed
-        .chars()
-        .filter(|c| !c.is_control())
-        .collect::<String>();
+    let filtered = without_controls(value.trim());
 
     // Encode HTML entities
-    encode_text(&filtered).to_string()
+    encode_text(&filtered).into_owned()
+}
+
+/// Removes control characters, copying only values that contain any
+fn without_controls(value: &str) -> Cow<'_, str> {
+    if value.contains(char::is_control) {
+        Cow::Owned(value.chars().filter(|c| !c.is_control()).collect())
+    } else {
+        Cow::Borrowed(value)
+    }
 }
 
 /// Sanitizes error messages to prevent leaking internal details
 ///
+/// Unix and Windows paths and IPv4 and IPv6 addresses are replaced with
+/// placeholders.
+///
 /// # Arguments
 /// * `error` - Error message to sanitize
 ///
@@ -94,16 +178,49 @@ fn sanitize_string(value: &str) -> String {
 /// * `String` - Sanitized error message
 fn sanitize_error(error: &str) -> String {
     // Filter out any internal paths or IPs that might be in error messages
-    let filtered = error.replace(|c: char| c.is_control(), "");
-    let sanitized = encode_text(&filtered).to_string();
-
-    // Ensure we don't leak absolute paths
-    // This regex pattern will replace things like /home/user/path with [REDACTED]
-    let path_pattern = regex::Regex::new(r"(/[a-zA-Z0-9_\-\.]+)+")
-        .unwrap_or_else(|_| regex::Regex::new(r"").unwrap());
-    path_pattern
-        .replace_all(&sanitized, "[REDACTED_PATH]")
-        .to_string()
+    let filtered = without_controls(error);
+    let sanitized = encode_text(&filtered);
+
+    // Most messages hold neither, so only scan them when they could
+    let redacted = if sanitized.contains(['/', '\\']) {
+        let unix = UNIX_PATH.replace_all(&sanitized, "[REDACTED_PATH]");
+        Cow::Owned(
+            WINDOWS_PATH
+                .replace_all(&unix, "[REDACTED_PATH]")
+                .into_owned(),
+        )
+    } else {
+        sanitized
+    };
+    let redacted = if redacted.contains('.') {
+        Cow::Owned(
+            IPV4_CANDIDATE
+                .replace_all(&redacted, |caps: &Captures| {
+                    redact_if(&caps[0], caps[0].parse::<Ipv4Addr>().is_ok())
+                })
+                .into_owned(),
+        )
+    } else {
+        redacted
+    };
+    if redacted.contains(':') {
+        IPV6_CANDIDATE
+            .replace_all(&redacted, |caps: &Captures| {
+                redact_if(&caps[0], caps[0].parse::<Ipv6Addr>().is_ok())
+            })
+            .into_owned()
+    } else {
+        redacted.into_owned()
+    }
+}
+
+/// Replaces a matched address with a placeholder if it is one
+fn redact_if(matched: &str, is_address: bool) -> String {
+    if is_address {
+        "[REDACTED_IP]".to_string()
+    } else {
+        matched.to_string()
+    }
 }
 
 #[cfg(test)]
@@ -132,15 +249,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sanitize_error_windows_paths() {
+        assert_eq!(
+            sanitize_error(r"Cannot open C:\Users\alice\sentri\domains.txt: denied"),
+            "Cannot open [REDACTED_PATH]: denied"
+        );
+        assert_eq!(
+            sanitize_error(r"Share \\fileserver\scans\out unavailable"),
+            "Share [REDACTED_PATH] unavailable"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_error_ip_addresses() {
+        assert_eq!(
+            sanitize_error("connect to 10.20.30.40:443 failed"),
+            "connect to [REDACTED_IP]:443 failed"
+        );
+        assert_eq!(
+            sanitize_error("connect to [2001:db8::1]:443 failed, fallback ::1"),
+            "connect to [[REDACTED_IP]]:443 failed, fallback [REDACTED_IP]"
+        );
+        // Versions and times only look like addresses
+        assert_eq!(
+            sanitize_error("server 1.2.3.400 at 12:30:45 rejected version 2.0.1"),
+            "server 1.2.3.400 at 12:30:45 rejected version 2.0.1"
+        );
+    }
+
     #[test]
     fn test_sanitize_domain_result() {
         let result = DomainResult {
+            schema_version: crate::core::RESULT_SCHEMA_VERSION,
             domain: "<script>evil.com".to_string(),
             tenant: Some("tenant<img src=x>".to_string()),
             federated_domains: vec!["a.com".to_string(), "b.com\n".to_string()],
             mdi_instance: Some("instance.atp.azure.com".to_string()),
+            mdi_generation: None,
+            mdi_confidence: None,
+            endpoint_anomalies: vec![],
+            engagement: Some(Engagement {
+                engagement_id: Some("ENG-1".to_string()),
+                operator: Some("<b>alice</b>".to_string()),
+            }),
+            tags: [("owner".to_string(), "<i>bob</i>".to_string())].into(),
             processing_time_ms: 100,
+            response_sha256: None,
+            schema_warnings: vec![],
             error: Some("Failed at /home/user/code.rs".to_string()),
+            error_class: None,
+            from_cache: true,
+            checked_at: chrono::Utc::now(),
+            completed_at: chrono::Utc::now(),
         };
 
         let sanitized = sanitize_domain_result(&result);
@@ -158,6 +319,11 @@ mod tests {
             sanitized.mdi_instance,
             Some("instance.atp.azure.com".to_string())
         );
+        assert_eq!(
+            sanitized.engagement.and_then(|e| e.operator),
+            Some("&lt;b&gt;alice&lt;/b&gt;".to_string())
+        );
+        assert_eq!(sanitized.tags["owner"], "&lt;i&gt;bob&lt;/i&gt;");
         assert_eq!(
             sanitized.error,
             Some("Failed at [REDACTED_PATH]".to_string())
@@ -0,0 +1,148 @@
+//! Times of day long-running modes may start scans
+//!
+//! The `[windows]` section of the configuration file restricts scheduled
+//! scans of `serve` and cycles of `watch` to maintenance windows, e.g. to
+//! keep scanning traffic out of business hours:
+//!
+//! ```toml
+//! [windows]
+//! allowed = ["22:00-06:00", "12:00-13:00"]
+//! ```
+//!
+//! Windows are `HH:MM-HH:MM` ranges in UTC; a window whose end is earlier
+//! than its start spans midnight. Without any window, scans may start at any
+//! time. Windows only gate when a scan starts: a scan running as its window
+//! closes is finished, and on-demand jobs submitted through the API are not
+//! restricted.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveTime, Timelike, Utc};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Seconds in a day
+const DAY_SECS: u32 = 24 * 60 * 60;
+
+/// A daily time range in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ScanWindow {
+    /// Time the window opens
+    pub start: NaiveTime,
+    /// Time the window closes
+    pub end: NaiveTime,
+}
+
+impl ScanWindow {
+    /// Returns true if `time` lies in the window
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveTime;
+    /// use sentri::scan_window::ScanWindow;
+    ///
+    /// let night: ScanWindow = "22:00-06:00".parse().unwrap();
+    /// assert!(night.contains(NaiveTime::from_hms_opt(23, 30, 0).unwrap()));
+    /// assert!(night.contains(NaiveTime::from_hms_opt(5, 59, 0).unwrap()));
+    /// assert!(!night.contains(NaiveTime::from_hms_opt(6, 0, 0).unwrap()));
+    /// ```
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Time from `time` until the window next opens
+    fn until_start(&self, time: NaiveTime) -> Duration {
+        let now = time.num_seconds_from_midnight();
+        let start = self.start.num_seconds_from_midnight();
+        Duration::from_secs(u64::from((start + DAY_SECS - now) % DAY_SECS))
+    }
+}
+
+impl FromStr for ScanWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (start, end) = value
+            .split_once('-')
+            .with_context(|| format!("Scan window '{}' is not HH:MM-HH:MM", value))?;
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .with_context(|| format!("Invalid time '{}' in scan window '{}'", time, value))
+        };
+        let window = Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        };
+        if window.start == window.end {
+            bail!("Scan window '{}' is empty", value);
+        }
+        Ok(window)
+    }
+}
+
+impl TryFrom<String> for ScanWindow {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for ScanWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// The `[windows]` section: when scans may start
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScanWindows {
+    /// Allowed windows; scans may start at any time when empty
+    #[serde(default)]
+    pub allowed: Vec<ScanWindow>,
+}
+
+impl ScanWindows {
+    /// Returns true if a scan may start at `now`
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        self.until_open(now).is_none()
+    }
+
+    /// Time from `now` until a window opens, or `None` if one is open
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use sentri::scan_window::ScanWindows;
+    /// use std::time::Duration;
+    ///
+    /// let windows = ScanWindows { allowed: vec!["22:00-06:00".parse().unwrap()] };
+    /// let evening = Utc.with_ymd_and_hms(2024, 5, 1, 20, 30, 0).unwrap();
+    /// assert_eq!(windows.until_open(evening), Some(Duration::from_secs(90 * 60)));
+    /// assert_eq!(ScanWindows::default().until_open(evening), None);
+    /// ```
+    pub fn until_open(&self, now: DateTime<Utc>) -> Option<Duration> {
+        let time = now.time();
+        if self.allowed.is_empty() || self.allowed.iter().any(|window| window.contains(time)) {
+            return None;
+        }
+        self.allowed
+            .iter()
+            .map(|window| window.until_start(time))
+            .min()
+    }
+}
@@ -0,0 +1,554 @@
+//! Recurring scans driven by cron expressions
+//!
+//! The scheduler manages registered domain lists that are rescanned on a
+//! cron schedule while sentri runs in server mode. Schedule definitions and
+//! the status of their last run are persisted as JSON in the server's state
+//! directory, so they survive restarts; the results of every run are written
+//! as JSONL next to them.
+//!
+//! Cron expressions use the familiar five fields (minute, hour, day of month,
+//! month, day of week) and are evaluated in UTC. A six-field expression with
+//! a leading seconds field is accepted as well.
+//!
+//! # Rate Limiting
+//!
+//! All scheduled runs draw from one [`RateBudget`], so overlapping schedules
+//! never exceed the configured Microsoft-side budget (mdi:api:respect_api_limits).
+//!
+//! # Scan Windows
+//!
+//! With [`Scheduler::with_live_config`], schedules due outside the allowed
+//! scan windows stay due and run once a window opens; see
+//! [`crate::scan_window`]. Runs started with [`Scheduler::run_now`] are not
+//! restricted.
+//!
+//! # Clock Adjustments
+//!
+//! Sleeps are monotonic while cron occurrences are wall-clock times. When the
+//! system clock jumps further than
+//! [`SKEW_TOLERANCE`](crate::time::SKEW_TOLERANCE), every schedule is
+//! re-planned from the new time instead of waiting out a stale `next_run` or
+//! replaying occurrences that no real time passed for.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::core::{BatchSummary, MdiChecker};
+use crate::encryption::StorageKey;
+use crate::rate_limit::RateBudget;
+use crate::reload::LiveConfig;
+use crate::sanitize::sanitize_domain_result;
+use crate::sinks::stored_results_sink;
+use crate::time::Stopwatch;
+use crate::validation::validate_domain;
+
+/// File holding the persisted schedules inside the state directory
+pub const SCHEDULES_FILE: &str = "schedules.json";
+
+/// Longest the scheduler sleeps before re-evaluating schedules
+const MAX_TICK: Duration = Duration::from_secs(60);
+
+/// Parses a five- or six-field cron expression
+///
+/// # Errors
+/// * The expression has the wrong number of fields or an invalid field
+///
+/// # Examples
+///
+/// ```
+/// use sentri::scheduler::parse_cron;
+///
+/// assert!(parse_cron("0 6 * * Mon-Fri").is_ok());
+/// assert!(parse_cron("every day").is_err());
+/// ```
+pub fn parse_cron(expression: &str) -> Result<Schedule> {
+    let fields = expression.split_whitespace().count();
+    let normalized = match fields {
+        5 => format!("0 {}", expression.trim()),
+        6 => expression.trim().to_string(),
+        _ => anyhow::bail!(
+            "Cron expression must have 5 fields (minute hour day month weekday), got {}",
+            fields
+        ),
+    };
+    Schedule::from_str(&normalized)
+        .map_err(|e| anyhow::anyhow!("Invalid cron expression '{}': {}", expression, e))
+}
+
+/// Outcome of a scheduled run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunState {
+    /// The run is in progress
+    Running,
+    /// Every domain was scanned and the results were written
+    Succeeded,
+    /// The run could not complete
+    Failed,
+}
+
+/// Status of the most recent run of a schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunStatus {
+    /// State of the run
+    pub state: RunState,
+    /// When the run started
+    pub started_at: DateTime<Utc>,
+    /// When the run ended, if it has
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Aggregate counts of a completed run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<BatchSummary>,
+    /// JSONL file holding the results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results_file: Option<PathBuf>,
+    /// Reason a run failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Definition of a new schedule, as submitted over the API
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduleRequest {
+    /// Human-readable name
+    pub name: String,
+    /// Cron expression (UTC)
+    pub cron: String,
+    /// Registered domain list to scan
+    pub domains: Vec<String>,
+    /// Whether the schedule is active
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// A registered recurring scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledScan {
+    /// Unique identifier
+    pub id: String,
+    /// Human-readable name
+    pub name: String,
+    /// Cron expression (UTC)
+    pub cron: String,
+    /// Registered domain list to scan
+    pub domains: Vec<String>,
+    /// Whether the schedule is active
+    pub enabled: bool,
+    /// When the schedule was registered
+    pub created_at: DateTime<Utc>,
+    /// Name of the API key that registered the schedule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// When the schedule is next due, if enabled
+    pub next_run: Option<DateTime<Utc>>,
+    /// Status of the most recent run
+    pub last_run: Option<RunStatus>,
+}
+
+impl ScheduledScan {
+    /// Validates a request and creates the schedule
+    ///
+    /// # Errors
+    /// * The cron expression is invalid, the domain list is empty or a domain is invalid
+    pub fn from_request(request: ScheduleRequest, now: DateTime<Utc>) -> Result<Self> {
+        let schedule = parse_cron(&request.cron)?;
+        if request.domains.is_empty() {
+            anyhow::bail!("Schedule must contain at least one domain");
+        }
+        for domain in &request.domains {
+            validate_domain(domain).map_err(|e| anyhow::anyhow!("{}: {}", domain, e))?;
+        }
+
+        Ok(Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: request.name,
+            cron: request.cron,
+            domains: request.domains,
+            enabled: request.enabled,
+            created_at: now,
+            owner: None,
+            next_run: request
+                .enabled
+                .then(|| schedule.after(&now).next())
+                .flatten(),
+            last_run: None,
+        })
+    }
+
+    /// Sets the owning API key
+    pub fn with_owner(mut self, owner: impl Into<String>) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    /// Returns true if the schedule should run at `now`
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.enabled && self.next_run.is_some_and(|next| next <= now)
+    }
+
+    /// Advances `next_run` to the first occurrence after `now`
+    fn reschedule(&mut self, now: DateTime<Utc>) {
+        self.next_run = if self.enabled {
+            parse_cron(&self.cron)
+                .ok()
+                .and_then(|schedule| schedule.after(&now).next())
+        } else {
+            None
+        };
+    }
+}
+
+/// Manages persisted schedules and executes them when due
+pub struct Scheduler {
+    checker: MdiChecker,
+    budget: Arc<RateBudget>,
+    state_dir: PathBuf,
+    storage_key: Option<Arc<StorageKey>>,
+    live: Option<Arc<LiveConfig>>,
+    schedules: Mutex<BTreeMap<String, ScheduledScan>>,
+    running: Mutex<HashSet<String>>,
+}
+
+impl Scheduler {
+    /// Opens the scheduler state in `state_dir`, loading persisted schedules
+    ///
+    /// Runs interrupted by a restart are marked as failed.
+    ///
+    /// # Arguments
+    /// * `checker` - Checker used for scheduled scans
+    /// * `state_dir` - Directory for schedule definitions and results (created if missing)
+    /// * `budget` - Rate budget; runs draw from their owner's share
+    pub async fn open(
+        checker: MdiChecker,
+        state_dir: &Path,
+        budget: Arc<RateBudget>,
+    ) -> Result<Self> {
+        tokio::fs::create_dir_all(state_dir)
+            .await
+            .with_context(|| format!("Failed to create state directory {}", state_dir.display()))?;
+
+        let path = state_dir.join(SCHEDULES_FILE);
+        let mut schedules: BTreeMap<String, ScheduledScan> = if path.exists() {
+            let content = tokio::fs::read_to_string(&path)
+                .await
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid schedule file {}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        for schedule in schedules.values_mut() {
+            if let Some(run) = schedule
+                .last_run
+                .as_mut()
+                .filter(|run| run.state == RunState::Running)
+            {
+                run.state = RunState::Failed;
+                run.error = Some("Interrupted by server restart".to_string());
+            }
+        }
+        info!(
+            "Loaded {} schedules from {}",
+            schedules.len(),
+            path.display()
+        );
+
+        Ok(Self {
+            budget,
+            checker,
+            state_dir: state_dir.to_path_buf(),
+            storage_key: None,
+            live: None,
+            schedules: Mutex::new(schedules),
+            running: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Encrypts result files with the given key
+    pub fn with_storage_key(mut self, key: Arc<StorageKey>) -> Self {
+        self.storage_key = Some(key);
+        self
+    }
+
+    /// Starts due runs only inside the scan windows of `live`
+    pub fn with_live_config(mut self, live: Arc<LiveConfig>) -> Self {
+        self.live = Some(live);
+        self
+    }
+
+    /// Key result files are encrypted with, if any
+    pub fn storage_key(&self) -> Option<&StorageKey> {
+        self.storage_key.as_deref()
+    }
+
+    /// Directory holding schedule definitions and results
+    pub fn state_dir(&self) -> &Path {
+        &self.state_dir
+    }
+
+    /// Registers a validated schedule
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use sentri::core::MdiChecker;
+    /// # use sentri::rate_limit::RateBudget;
+    /// # use sentri::scheduler::{ScheduleRequest, ScheduledScan, Scheduler};
+    /// # use std::path::Path;
+    /// # use std::sync::Arc;
+    /// # async fn example() -> anyhow::Result<()> {
+    /// let budget = Arc::new(RateBudget::new(50, 10));
+    /// let scheduler = Scheduler::open(MdiChecker::new(10, 5000)?, Path::new("state"), budget).await?;
+    /// let request = ScheduleRequest {
+    ///     name: "nightly".to_string(),
+    ///     cron: "0 2 * * *".to_string(),
+    ///     domains: vec!["contoso.com".to_string()],
+    ///     enabled: true,
+    /// };
+    /// scheduler.add(ScheduledScan::from_request(request, chrono::Utc::now())?).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn add(&self, schedule: ScheduledScan) -> Result<()> {
+        let mut schedules = self.schedules.lock().await;
+        info!("Registered schedule {} ({})", schedule.id, schedule.name);
+        schedules.insert(schedule.id.clone(), schedule);
+        self.persist(&schedules).await
+    }
+
+    /// Lists all schedules ordered by identifier
+    pub async fn list(&self) -> Vec<ScheduledScan> {
+        self.schedules.lock().await.values().cloned().collect()
+    }
+
+    /// Returns a single schedule
+    pub async fn get(&self, id: &str) -> Option<ScheduledScan> {
+        self.schedules.lock().await.get(id).cloned()
+    }
+
+    /// Removes a schedule, returning false if it does not exist
+    pub async fn remove(&self, id: &str) -> Result<bool> {
+        let mut schedules = self.schedules.lock().await;
+        if schedules.remove(id).is_none() {
+            return Ok(false);
+        }
+        self.persist(&schedules).await?;
+        info!("Removed schedule {}", id);
+        Ok(true)
+    }
+
+    /// Returns true if a run of the schedule is in progress
+    pub async fn is_running(&self, id: &str) -> bool {
+        self.running.lock().await.contains(id)
+    }
+
+    /// Runs a schedule immediately, regardless of its cron expression
+    ///
+    /// # Returns
+    /// * `Result<Option<RunStatus>>` - Status of the finished run, or None if the
+    ///   schedule does not exist or is already running
+    pub async fn run_now(&self, id: &str) -> Result<Option<RunStatus>> {
+        let Some(schedule) = self.get(id).await else {
+            return Ok(None);
+        };
+        self.execute(schedule).await
+    }
+
+    /// Runs every due schedule once
+    ///
+    /// Due schedules run concurrently with each other but a schedule is
+    /// never started while a previous run of it is still in progress.
+    /// Outside the allowed scan windows, nothing is started.
+    pub async fn run_due(self: &Arc<Self>) {
+        let now = Utc::now();
+        if let Some(live) = &self.live {
+            if !live.current().windows.is_open(now) {
+                return;
+            }
+        }
+        let due: Vec<ScheduledScan> = self
+            .schedules
+            .lock()
+            .await
+            .values()
+            .filter(|schedule| schedule.is_due(now))
+            .cloned()
+            .collect();
+
+        for schedule in due {
+            let scheduler = Arc::clone(self);
+            tokio::spawn(async move {
+                let id = schedule.id.clone();
+                if let Err(e) = scheduler.execute(schedule).await {
+                    error!("Scheduled run of {} failed: {:#}", id, e);
+                }
+            });
+        }
+    }
+
+    /// Evaluates schedules until the task is dropped
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            self.run_due().await;
+
+            let clock = Stopwatch::start();
+            let now = clock.started_at();
+            let next_due = self
+                .schedules
+                .lock()
+                .await
+                .values()
+                .filter(|schedule| schedule.enabled)
+                .filter_map(|schedule| schedule.next_run)
+                .min();
+            let sleep = next_due
+                .and_then(|next| (next - now).to_std().ok())
+                .unwrap_or(MAX_TICK)
+                .min(MAX_TICK);
+            crate::service::idle(sleep).await;
+
+            let wall_now = Utc::now();
+            if clock.clock_jumped(wall_now) {
+                warn!(
+                    "System clock moved by {}s, re-planning schedules",
+                    clock.skew(wall_now).num_seconds()
+                );
+                if let Err(e) = self.reschedule_all(wall_now).await {
+                    error!("Failed to re-plan schedules: {:#}", e);
+                }
+            }
+        }
+    }
+
+    /// Recomputes `next_run` of every schedule from `now`
+    pub async fn reschedule_all(&self, now: DateTime<Utc>) -> Result<()> {
+        let mut schedules = self.schedules.lock().await;
+        for schedule in schedules.values_mut() {
+            schedule.reschedule(now);
+        }
+        self.persist(&schedules).await
+    }
+
+    /// Executes a run, recording its status before and after
+    async fn execute(&self, schedule: ScheduledScan) -> Result<Option<RunStatus>> {
+        if !self.running.lock().await.insert(schedule.id.clone()) {
+            warn!("Schedule {} is still running, skipping", schedule.id);
+            return Ok(None);
+        }
+
+        let started_at = Utc::now();
+        let running = RunStatus {
+            state: RunState::Running,
+            started_at,
+            finished_at: None,
+            summary: None,
+            results_file: None,
+            error: None,
+        };
+        self.update(&schedule.id, |s| {
+            s.last_run = Some(running);
+            s.reschedule(started_at);
+        })
+        .await?;
+
+        info!(
+            "Running schedule {} ({}) over {} domains",
+            schedule.id,
+            schedule.name,
+            schedule.domains.len()
+        );
+        let outcome = self.scan(&schedule, started_at).await;
+        self.running.lock().await.remove(&schedule.id);
+
+        let status = match outcome {
+            Ok((summary, results_file)) => RunStatus {
+                state: RunState::Succeeded,
+                started_at,
+                finished_at: Some(Utc::now()),
+                summary: Some(summary),
+                results_file: Some(results_file),
+                error: None,
+            },
+            Err(e) => RunStatus {
+                state: RunState::Failed,
+                started_at,
+                finished_at: Some(Utc::now()),
+                summary: None,
+                results_file: None,
+                error: Some(format!("{:#}", e)),
+            },
+        };
+        let recorded = status.clone();
+        self.update(&schedule.id, |s| s.last_run = Some(recorded))
+            .await?;
+        Ok(Some(status))
+    }
+
+    /// Scans the schedule's domains and writes the results
+    async fn scan(
+        &self,
+        schedule: &ScheduledScan,
+        started_at: DateTime<Utc>,
+    ) -> Result<(BatchSummary, PathBuf)> {
+        let dir = self.state_dir.join("results").join(&schedule.id);
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join(format!("{}.jsonl", started_at.format("%Y%m%dT%H%M%SZ")));
+
+        let rate_limiter = self.budget.limiter_for(schedule.owner.as_deref());
+
+        let mut summary = BatchSummary::new();
+        let results = self
+            .checker
+            .process_chunk(&schedule.domains, rate_limiter)
+            .await;
+
+        let mut sink = stored_results_sink(&path, self.storage_key.as_ref()).await?;
+        for result in &results {
+            summary.record(result);
+            sink.write(&sanitize_domain_result(result)).await?;
+        }
+        sink.close().await?;
+        summary.finish();
+
+        Ok((summary, path))
+    }
+
+    /// Applies a change to a schedule, if it still exists, and persists it
+    async fn update(&self, id: &str, change: impl FnOnce(&mut ScheduledScan)) -> Result<()> {
+        let mut schedules = self.schedules.lock().await;
+        if let Some(schedule) = schedules.get_mut(id) {
+            change(schedule);
+            self.persist(&schedules).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the schedules through a temporary file
+    async fn persist(&self, schedules: &BTreeMap<String, ScheduledScan>) -> Result<()> {
+        let path = self.state_dir.join(SCHEDULES_FILE);
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(schedules)?)
+            .await
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .with_context(|| format!("Failed to persist {}", path.display()))
+    }
+}
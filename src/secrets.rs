@@ -0,0 +1,184 @@
+//! Secret resolution for credentials passed as options
+//!
+//! Credential options (`--es-password`, `--la-shared-key`, ...) accept either
+//! the secret itself or a reference naming where to read it:
+//!
+//! | Value                       | Secret                                         |
+//! |-----------------------------|------------------------------------------------|
+//! | `env:ES_PASSWORD`           | Value of the environment variable              |
+//! | `file:/run/secrets/es`      | Contents of the file, without trailing newline |
+//! | `cmd:vault kv get -field=pw secret/es` | Standard output of the command      |
+//! | `literal:file:abc`          | `file:abc`, for secrets that look like a reference |
+//!
+//! Each credential option also has a `SENTRI_<OPTION>_FILE` variable (e.g.
+//! `SENTRI_ES_PASSWORD_FILE=/run/secrets/es`), matching the convention of
+//! mounted Kubernetes and Docker secrets. It is used when neither the flag
+//! nor `SENTRI_<OPTION>` is set.
+//!
+//! Further backends implement [`SecretProvider`] and are registered with
+//! [`SecretResolver::with_provider`].
+//!
+//! # Security Considerations
+//!
+//! - **Command Execution**: `cmd:` references are split on whitespace and run
+//!   directly, without a shell, so no shell expansion takes place
+//! - **Error Information Control**: Errors name the reference, never the
+//!   resolved secret (security:output:error_info_control)
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Credential options, by long flag, that accept references and `_FILE` variables
+pub const SECRET_OPTIONS: &[&str] = &[
+    "pagerduty-routing-key",
+    "opsgenie-api-key",
+    "la-shared-key",
+    "la-token",
+    "es-password",
+    "es-api-key",
+    "splunk-token",
+    "kafka-sasl-password",
+    "pg-dsn",
+    "smtp-password",
+    "storage-key",
+];
+
+/// Prefix that passes the rest of a value through unchanged
+const LITERAL_SCHEME: &str = "literal";
+
+/// A backend that turns a reference into a secret
+pub trait SecretProvider: Send + Sync {
+    /// Scheme handled by the provider, as in `<scheme>:<reference>`
+    fn scheme(&self) -> &str;
+
+    /// Returns the secret a reference points to
+    fn resolve(&self, reference: &str) -> Result<String>;
+}
+
+/// Reads secrets from environment variables (`env:NAME`)
+pub struct EnvProvider;
+
+impl SecretProvider for EnvProvider {
+    fn scheme(&self) -> &str {
+        "env"
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String> {
+        std::env::var(reference)
+            .with_context(|| format!("Environment variable {} is not set", reference))
+    }
+}
+
+/// Reads secrets from files (`file:/path`)
+pub struct FileProvider;
+
+impl SecretProvider for FileProvider {
+    fn scheme(&self) -> &str {
+        "file"
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String> {
+        read_secret_file(Path::new(reference))
+    }
+}
+
+/// Reads secrets from the output of a command (`cmd:program args...`)
+pub struct CommandProvider;
+
+impl SecretProvider for CommandProvider {
+    fn scheme(&self) -> &str {
+        "cmd"
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String> {
+        let mut words = reference.split_whitespace();
+        let program = words.next().context("Empty secret command")?;
+        let output = std::process::Command::new(program)
+            .args(words)
+            .stderr(std::process::Stdio::inherit())
+            .output()
+            .with_context(|| format!("Failed to run secret command {}", program))?;
+        if !output.status.success() {
+            anyhow::bail!("Secret command {} exited with {}", program, output.status);
+        }
+        let secret = String::from_utf8(output.stdout)
+            .with_context(|| format!("Secret command {} printed invalid UTF-8", program))?;
+        Ok(trim_newline(secret))
+    }
+}
+
+/// Reads a secret file, dropping the trailing newline most editors add
+pub fn read_secret_file(path: &Path) -> Result<String> {
+    let secret = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read secret file {}", path.display()))?;
+    Ok(trim_newline(secret))
+}
+
+fn trim_newline(mut secret: String) -> String {
+    let trimmed = secret.trim_end_matches(['\r', '\n']).len();
+    secret.truncate(trimmed);
+    secret
+}
+
+/// Resolves option values through the registered providers
+pub struct SecretResolver {
+    providers: Vec<Box<dyn SecretProvider>>,
+}
+
+impl Default for SecretResolver {
+    /// A resolver with the env, file and cmd providers
+    fn default() -> Self {
+        Self {
+            providers: vec![
+                Box::new(EnvProvider),
+                Box::new(FileProvider),
+                Box::new(CommandProvider),
+            ],
+        }
+    }
+}
+
+impl SecretResolver {
+    /// Registers an additional provider, replacing one with the same scheme
+    pub fn with_provider(mut self, provider: impl SecretProvider + 'static) -> Self {
+        self.providers.retain(|p| p.scheme() != provider.scheme());
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    /// Resolves a value that is either a secret or a reference to one
+    ///
+    /// Values without a registered scheme are returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::secrets::SecretResolver;
+    ///
+    /// let resolver = SecretResolver::default();
+    /// assert_eq!(resolver.resolve("hunter2").unwrap(), "hunter2");
+    /// assert_eq!(resolver.resolve("literal:env:X").unwrap(), "env:X");
+    /// ```
+    pub fn resolve(&self, value: &str) -> Result<String> {
+        let Some((scheme, reference)) = value.split_once(':') else {
+            return Ok(value.to_string());
+        };
+        if scheme == LITERAL_SCHEME {
+            return Ok(reference.to_string());
+        }
+        match self.providers.iter().find(|p| p.scheme() == scheme) {
+            Some(provider) => provider
+                .resolve(reference)
+                .with_context(|| format!("Failed to resolve secret reference {}:", scheme)),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    /// Resolves an optional option value in place
+    pub fn resolve_option(&self, value: &mut Option<String>) -> Result<()> {
+        if let Some(secret) = value {
+            *secret = self.resolve(secret)?;
+        }
+        Ok(())
+    }
+}
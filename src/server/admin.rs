@@ -0,0 +1,153 @@
+//! Live tuning of rate limits and concurrency
+//!
+//! When a shared instance starts getting throttled, an operator holding the
+//! `admin` scope can lower its Microsoft-side budget without a restart:
+//!
+//! | Method | Path            | Description                                  |
+//! |--------|-----------------|----------------------------------------------|
+//! | `GET`  | `/admin/limits` | Server-wide and per-key limits and usage     |
+//! | `PUT`  | `/admin/limits` | Change the rate or concurrency of one budget |
+//!
+//! ```text
+//! curl -X PUT localhost:8080/admin/limits -H "Authorization: Bearer $ADMIN_KEY" \
+//!   -H 'Content-Type: application/json' -d '{"requests_per_minute": 10, "max_concurrent": 2}'
+//! ```
+//!
+//! Without `key`, the server-wide budget is changed; with it, the budget of
+//! that API key. Scans in flight keep the permits they hold and new requests
+//! draw from the new limits. Changes last until the process restarts or a
+//! reloaded `[rate_limit]` section replaces them (see [`crate::reload`]).
+//!
+//! The audit record of a change carries the limits before and after.
+
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::warn;
+
+use super::auth::{AuditChange, Caller, Scope};
+use super::{ApiError, ServerState};
+use crate::rate_limit::RateLimiter;
+
+/// Limits and current usage of one budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LimitSettings {
+    /// Requests per minute
+    pub requests_per_minute: u64,
+    /// Requests in flight at once
+    pub max_concurrent: usize,
+    /// Tokens left in the current minute
+    pub tokens_available: usize,
+    /// Requests that may start without waiting
+    pub permits_available: usize,
+}
+
+impl LimitSettings {
+    async fn of(limiter: &RateLimiter) -> Self {
+        let usage = limiter.usage().await;
+        Self {
+            requests_per_minute: usage.capacity as u64,
+            max_concurrent: usage.max_concurrent,
+            tokens_available: usage.tokens_available,
+            permits_available: usage.permits_available,
+        }
+    }
+}
+
+/// Response of `GET /admin/limits`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Limits {
+    /// Server-wide budget every scan draws from
+    pub shared: LimitSettings,
+    /// Budgets of API keys, nested in the server-wide one
+    pub keys: BTreeMap<String, LimitSettings>,
+}
+
+/// Body of `PUT /admin/limits`; unset fields keep their value
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitUpdate {
+    /// API key whose budget to change; the server-wide budget when unset
+    pub key: Option<String>,
+    /// New requests per minute
+    pub requests_per_minute: Option<u64>,
+    /// New number of requests in flight at once
+    pub max_concurrent: Option<usize>,
+}
+
+pub(super) async fn get_limits(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Limits>, ApiError> {
+    caller.require(Scope::Admin)?;
+    let budget = state.jobs.budget();
+    let mut keys = BTreeMap::new();
+    for name in budget.owner_names() {
+        if let Some(limiter) = budget.owner(name) {
+            keys.insert(name.to_string(), LimitSettings::of(limiter).await);
+        }
+    }
+    Ok(Json(Limits {
+        shared: LimitSettings::of(budget.shared()).await,
+        keys,
+    }))
+}
+
+pub(super) async fn update_limits(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Json(update): Json<LimitUpdate>,
+) -> Result<Response, ApiError> {
+    caller.require(Scope::Admin)?;
+    if update.requests_per_minute.is_none() && update.max_concurrent.is_none() {
+        return Err(ApiError::BadRequest(
+            "Set requests_per_minute, max_concurrent or both".to_string(),
+        ));
+    }
+    if update.requests_per_minute == Some(0) || update.max_concurrent == Some(0) {
+        return Err(ApiError::BadRequest(
+            "Limits must be at least 1".to_string(),
+        ));
+    }
+
+    let budget = state.jobs.budget();
+    let limiter = match &update.key {
+        Some(key) => budget.owner(key).ok_or(ApiError::NotFound)?,
+        None => budget.shared(),
+    };
+    let before = LimitSettings::of(limiter).await;
+    let requests_per_minute = update
+        .requests_per_minute
+        .unwrap_or(before.requests_per_minute);
+    let max_concurrent = update.max_concurrent.unwrap_or(before.max_concurrent);
+    limiter
+        .update_config(requests_per_minute as usize, 60_000, max_concurrent)
+        .await?;
+    let after = LimitSettings::of(limiter).await;
+
+    let target = update.key.as_deref().unwrap_or("shared");
+    warn!(
+        target_budget = target,
+        by = caller.key.as_deref().unwrap_or("-"),
+        requests_per_minute,
+        max_concurrent,
+        "Rate limits changed through the admin API"
+    );
+    let change = json!({
+        "limits": target,
+        "before": {
+            "requests_per_minute": before.requests_per_minute,
+            "max_concurrent": before.max_concurrent,
+        },
+        "after": {
+            "requests_per_minute": requests_per_minute,
+            "max_concurrent": max_concurrent,
+        },
+    });
+    let mut response = Json(after).into_response();
+    response.extensions_mut().insert(AuditChange(change));
+    Ok(response)
+}
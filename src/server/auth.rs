@@ -0,0 +1,503 @@
+//! API-key authentication, per-key quotas and audit logging
+//!
+//! A shared server instance is configured with a TOML file listing one key
+//! per team. Only the SHA-256 digest of each key is stored:
+//!
+//! ```toml
+//! [[key]]
+//! name = "red-team"
+//! # echo -n "$KEY" | sha256sum
+//! key_sha256 = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+//! # Microsoft-side requests per minute for this key's scans
+//! rate_limit = 20
+//! # API requests per minute
+//! requests_per_minute = 120
+//!
+//! [[key]]
+//! name = "dashboard"
+//! key_sha256 = "..."
+//! # Read results and status only; cannot launch or cancel scans
+//! scopes = ["read"]
+//! ```
+//!
+//! Keys carry the `read` and `scan` [`Scope`]s unless `scopes` says otherwise.
+//! Listing and fetching require `read`; creating, running, cancelling and
+//! deleting schedules or jobs require `scan`; changing server settings
+//! through the [`admin`](super::admin) endpoints requires `admin`, which is
+//! only granted when listed explicitly. A missing scope is answered with 403.
+//!
+//! Clients present the key as `Authorization: Bearer <key>` or `X-API-Key`.
+//! Scans owned by a key draw from that key's budget, which is itself nested
+//! in the server-wide rate limit, so one team cannot exhaust the Microsoft
+//! budget of the others.
+//!
+//! Every API request is audited with the calling key, method, path,
+//! response status, engagement and, for admin requests, the settings changed, both to the `sentri::audit` tracing
+//! target and, when configured, as JSON lines in an audit file. Clients name
+//! their engagement with the `X-Engagement-Id` and `X-Operator` headers;
+//! unset headers default to the server's `--engagement-id` and `--operator`.
+//!
+//! # Security Considerations
+//!
+//! - **Key Storage**: Plaintext keys are never stored or logged; only digests are
+//!   compared, in constant time (security:output:error_info_control)
+//! - **Quota Enforcement**: Excess API requests are rejected with 429 rather than
+//!   queued, so a misbehaving client cannot tie up server resources
+
+use anyhow::{Context, Result};
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use super::{ApiError, ServerState};
+use crate::encryption::{LineAppender, StorageKey};
+use crate::engagement::{Engagement, ENGAGEMENT_ID_HEADER, OPERATOR_HEADER};
+use crate::provenance::IntegrityReport;
+use crate::rate_limit::RateLimiter;
+
+/// File name of the audit trail inside the server state directory
+pub const AUDIT_LOG_FILE: &str = "audit.jsonl";
+
+/// A configured API key
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKey {
+    /// Team or client name, used as schedule owner and in the audit log
+    pub name: String,
+    /// Hex-encoded SHA-256 digest of the key
+    pub key_sha256: String,
+    /// Microsoft-side requests per minute for scans owned by this key
+    #[serde(default = "default_rate_limit")]
+    pub rate_limit: u64,
+    /// API requests per minute
+    #[serde(default = "default_requests_per_minute")]
+    pub requests_per_minute: u64,
+    /// Operations the key may perform
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<Scope>,
+}
+
+/// Permission granted to an API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// List and fetch schedules, jobs and results
+    Read,
+    /// Launch, cancel and delete scans
+    Scan,
+    /// Change server-wide settings such as rate limits
+    Admin,
+}
+
+impl Scope {
+    /// Every scope; granted when authentication is disabled
+    pub const ALL: &'static [Scope] = &[Scope::Read, Scope::Scan, Scope::Admin];
+
+    /// Scopes of keys without an explicit list
+    pub const DEFAULT: &'static [Scope] = &[Scope::Read, Scope::Scan];
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::Read => write!(f, "read"),
+            Scope::Scan => write!(f, "scan"),
+            Scope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+fn default_rate_limit() -> u64 {
+    20
+}
+
+fn default_requests_per_minute() -> u64 {
+    120
+}
+
+fn default_scopes() -> Vec<Scope> {
+    Scope::DEFAULT.to_vec()
+}
+
+#[derive(Deserialize)]
+struct ApiKeyFile {
+    #[serde(default, rename = "key")]
+    keys: Vec<ApiKey>,
+}
+
+/// The identity behind an API request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    /// Name of the authenticated key, or None when authentication is disabled
+    pub key: Option<String>,
+    /// Scopes granted to the caller
+    pub scopes: Vec<Scope>,
+}
+
+impl Caller {
+    /// Caller of a server without authentication, holding every scope
+    pub fn anonymous() -> Self {
+        Self {
+            key: None,
+            scopes: Scope::ALL.to_vec(),
+        }
+    }
+
+    /// Returns an error unless the caller holds `scope`
+    pub fn require(&self, scope: Scope) -> Result<(), ApiError> {
+        if self.scopes.contains(&scope) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden(format!(
+                "API key lacks the '{}' scope",
+                scope
+            )))
+        }
+    }
+
+    /// Returns true if the caller may see a resource owned by `owner`
+    ///
+    /// Without authentication every resource is visible.
+    pub fn can_access(&self, owner: Option<&str>) -> bool {
+        match &self.key {
+            Some(key) => owner == Some(key.as_str()),
+            None => true,
+        }
+    }
+}
+
+/// Configured API keys with their request quotas
+pub struct ApiKeys {
+    keys: Vec<ApiKey>,
+    quotas: HashMap<String, RateLimiter>,
+}
+
+impl ApiKeys {
+    /// Creates the key set, validating names, digests and scopes
+    ///
+    /// # Errors
+    /// * A name is duplicated, a digest is not 64 hex characters or a key has no scopes
+    pub fn new(keys: Vec<ApiKey>) -> Result<Self> {
+        let mut quotas = HashMap::new();
+        for key in &keys {
+            if key.key_sha256.len() != 64 || !key.key_sha256.chars().all(|c| c.is_ascii_hexdigit())
+            {
+                anyhow::bail!("API key '{}' has an invalid key_sha256", key.name);
+            }
+            if key.scopes.is_empty() {
+                anyhow::bail!("API key '{}' has no scopes", key.name);
+            }
+            let quota = RateLimiter::new(key.requests_per_minute as usize, 60_000, 1);
+            if quotas.insert(key.name.clone(), quota).is_some() {
+                anyhow::bail!("Duplicate API key name '{}'", key.name);
+            }
+        }
+        Ok(Self { keys, quotas })
+    }
+
+    /// Parses a TOML key file
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let file: ApiKeyFile = toml::from_str(content).context("Invalid API key file")?;
+        if file.keys.is_empty() {
+            anyhow::bail!("API key file defines no keys");
+        }
+        Self::new(file.keys)
+    }
+
+    /// Loads a TOML key file
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read API key file {}", path.display()))?;
+        Self::from_toml(&content).with_context(|| format!("Failed to load {}", path.display()))
+    }
+
+    /// Configured keys
+    pub fn keys(&self) -> &[ApiKey] {
+        &self.keys
+    }
+
+    /// Returns the key matching a presented secret
+    pub fn authenticate(&self, secret: &str) -> Option<&ApiKey> {
+        let digest = format!("{:x}", Sha256::digest(secret.as_bytes()));
+        self.keys
+            .iter()
+            .find(|key| constant_time_eq(&key.key_sha256.to_ascii_lowercase(), &digest))
+    }
+
+    /// Takes one request from a key's quota, returning false when it is exhausted
+    pub async fn take_quota(&self, name: &str) -> bool {
+        match self.quotas.get(name) {
+            Some(quota) => quota.try_take().await,
+            None => false,
+        }
+    }
+}
+
+/// Compares two strings without short-circuiting on the first difference
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// Extracts the presented key from the request headers
+fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(value) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        return Some(value.trim());
+    }
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// One audited API request or startup event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// When the request completed
+    pub timestamp: DateTime<Utc>,
+    /// Authenticated key name, if any
+    pub key: Option<String>,
+    /// Client address, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_addr: Option<SocketAddr>,
+    /// HTTP method
+    pub method: String,
+    /// Request path
+    pub path: String,
+    /// Response status code
+    pub status: u16,
+    /// Engagement the request was made under, if declared
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engagement: Option<Engagement>,
+    /// Integrity self-check of the binary; set on `STARTUP` records only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<IntegrityReport>,
+    /// Settings changed by the request, before and after; see [`AuditChange`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<serde_json::Value>,
+}
+
+/// Response extension through which a handler adds what it changed to the
+/// request's audit record
+#[derive(Debug, Clone)]
+pub struct AuditChange(pub serde_json::Value);
+
+impl AuditRecord {
+    /// Startup record carrying the outcome of the integrity self-check
+    ///
+    /// The status is 200 when the binary matched the expected hash and 412
+    /// when it did not.
+    pub fn integrity(report: IntegrityReport) -> Self {
+        Self {
+            timestamp: report.checked_at,
+            key: None,
+            remote_addr: None,
+            method: "STARTUP".to_string(),
+            path: "integrity".to_string(),
+            status: if report.verified { 200 } else { 412 },
+            engagement: None,
+            integrity: Some(report),
+            change: None,
+        }
+    }
+}
+
+/// Append-only JSONL audit trail
+///
+/// Encrypted with the server's storage key when one is configured (see
+/// [`crate::encryption`]).
+pub struct AuditLog {
+    path: PathBuf,
+    key: Option<Arc<StorageKey>>,
+    file: Mutex<LineAppender>,
+}
+
+impl AuditLog {
+    /// Opens (or creates) an audit file for appending
+    ///
+    /// # Errors
+    /// * The file was written with a different key, or without one
+    pub async fn open(path: &Path, key: Option<Arc<StorageKey>>) -> Result<Self> {
+        let file = LineAppender::open(path, key.clone())
+            .await
+            .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            key,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Appends a record
+    pub async fn record(&self, record: &AuditRecord) -> Result<()> {
+        let line = serde_json::to_string(record)?;
+        self.file
+            .lock()
+            .await
+            .append(&line)
+            .await
+            .with_context(|| format!("Failed to write audit log {}", self.path.display()))
+    }
+
+    /// Drops records older than `cutoff`, returning how many were dropped
+    pub async fn purge(&self, cutoff: DateTime<Utc>) -> Result<usize> {
+        let mut file = self.file.lock().await;
+        let dropped =
+            crate::retention::purge_audit_file(&self.path, cutoff, false, self.key.as_ref())
+                .await?;
+        if dropped > 0 {
+            // The file was replaced; append to the new one
+            *file = LineAppender::open(&self.path, self.key.clone())
+                .await
+                .with_context(|| format!("Failed to reopen audit log {}", self.path.display()))?;
+        }
+        Ok(dropped)
+    }
+}
+
+/// Middleware authenticating requests, enforcing quotas and auditing the outcome
+pub async fn authenticate(
+    State(state): State<ServerState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let remote_addr = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0);
+
+    let engagement = match request_engagement(request.headers()) {
+        Ok(engagement) => engagement.or(state.jobs.checker().engagement()),
+        Err(e) => {
+            let response = ApiError::BadRequest(format!("{:#}", e)).into_response();
+            return audit(&state, None, remote_addr, method, path, None, response).await;
+        }
+    };
+    request.extensions_mut().insert(engagement.clone());
+
+    let (key, response) = match &state.api_keys {
+        None => {
+            request.extensions_mut().insert(Caller::anonymous());
+            (None, next.run(request).await)
+        }
+        Some(keys) => match presented_key(request.headers()).and_then(|s| keys.authenticate(s)) {
+            None => (None, unauthorized()),
+            Some(key) => {
+                let name = key.name.clone();
+                if keys.take_quota(&name).await {
+                    request.extensions_mut().insert(Caller {
+                        key: Some(name.clone()),
+                        scopes: key.scopes.clone(),
+                    });
+                    (Some(name), next.run(request).await)
+                } else {
+                    (Some(name), quota_exceeded())
+                }
+            }
+        },
+    };
+
+    let engagement = (!engagement.is_empty()).then_some(engagement);
+    audit(&state, key, remote_addr, method, path, engagement, response).await
+}
+
+/// Reads the engagement headers of a request
+fn request_engagement(headers: &HeaderMap) -> Result<Engagement> {
+    let header = |name: &str| -> Result<Option<String>> {
+        headers
+            .get(name)
+            .map(|value| {
+                value
+                    .to_str()
+                    .map(|value| value.trim().to_string())
+                    .with_context(|| format!("Invalid {} header", name))
+            })
+            .transpose()
+    };
+    let engagement = Engagement {
+        engagement_id: header(ENGAGEMENT_ID_HEADER)?,
+        operator: header(OPERATOR_HEADER)?,
+    };
+    engagement.validate()?;
+    Ok(engagement)
+}
+
+/// Logs and records the outcome of a request, passing the response through
+async fn audit(
+    state: &ServerState,
+    key: Option<String>,
+    remote_addr: Option<SocketAddr>,
+    method: String,
+    path: String,
+    engagement: Option<Engagement>,
+    response: Response,
+) -> Response {
+    let record = AuditRecord {
+        timestamp: Utc::now(),
+        key,
+        remote_addr,
+        method,
+        path,
+        status: response.status().as_u16(),
+        engagement,
+        integrity: None,
+        change: response
+            .extensions()
+            .get::<AuditChange>()
+            .map(|change| change.0.clone()),
+    };
+    info!(
+        target: "sentri::audit",
+        key = record.key.as_deref().unwrap_or("-"),
+        method = %record.method,
+        path = %record.path,
+        status = record.status,
+        engagement = record
+            .engagement
+            .as_ref()
+            .and_then(|e| e.engagement_id.as_deref())
+            .unwrap_or("-"),
+        change = record.change.as_ref().map(|change| change.to_string()),
+        "API request"
+    );
+    if let Some(audit) = &state.audit_log {
+        if let Err(e) = audit.record(&record).await {
+            error!("Failed to write audit record: {:#}", e);
+        }
+    }
+
+    response
+}
+
+fn unauthorized() -> Response {
+    let mut response =
+        ApiError::Unauthorized("Missing or invalid API key".to_string()).into_response();
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+fn quota_exceeded() -> Response {
+    let mut response = ApiError::TooManyRequests.into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from_static("60"));
+    response
+}
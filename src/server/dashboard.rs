@@ -0,0 +1,54 @@
+//! Embedded web dashboard
+//!
+//! A static page compiled into the binary and served at `/`. It lists jobs
+//! with their progress, charts MDI coverage (tenants with an MDI instance out
+//! of all identified tenants) and searches stored results through the
+//! regular JSON API, so no separate frontend needs to be deployed.
+//!
+//! The assets themselves are served without authentication; the page asks
+//! for an API key and presents it on every API call it makes.
+//!
+//! # Security Considerations
+//!
+//! - **Content Security Policy**: Scripts, styles and API calls are restricted
+//!   to the server's own origin
+//! - **Output Encoding**: API values are inserted as text, never as HTML, so a
+//!   hostile tenant or domain name cannot inject script
+//!   (security:output:encode_output)
+
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+
+const INDEX_HTML: &str = include_str!("dashboard/index.html");
+const DASHBOARD_JS: &str = include_str!("dashboard/dashboard.js");
+const DASHBOARD_CSS: &str = include_str!("dashboard/dashboard.css");
+
+const CONTENT_SECURITY_POLICY: &str = "default-src 'self'; frame-ancestors 'none'";
+
+/// Routes serving the dashboard assets
+pub fn routes<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route("/", get(|| asset("text/html; charset=utf-8", INDEX_HTML)))
+        .route(
+            "/assets/dashboard.js",
+            get(|| asset("text/javascript; charset=utf-8", DASHBOARD_JS)),
+        )
+        .route(
+            "/assets/dashboard.css",
+            get(|| asset("text/css; charset=utf-8", DASHBOARD_CSS)),
+        )
+}
+
+async fn asset(content_type: &'static str, body: &'static str) -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        body,
+    )
+}
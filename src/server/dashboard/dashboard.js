@@ -0,0 +1,147 @@
+"use strict";
+
+// Values from the API are inserted with textContent only, never as HTML.
+
+const REFRESH_MS = 3000;
+let apiKey = sessionStorage.getItem("sentri-api-key") || "";
+
+async function api(path) {
+  const headers = apiKey ? { Authorization: "Bearer " + apiKey } : {};
+  const response = await fetch(path, { headers });
+  if (!response.ok) {
+    const body = await response.json().catch(() => ({}));
+    throw new Error(body.error || response.status + " " + response.statusText);
+  }
+  return response.json();
+}
+
+function showError(error) {
+  const banner = document.getElementById("error");
+  banner.hidden = !error;
+  banner.textContent = error ? error.message : "";
+}
+
+function cell(row, value, className) {
+  const td = row.insertCell();
+  td.textContent = value === null || value === undefined ? "" : String(value);
+  if (className) {
+    td.className = className;
+  }
+  return td;
+}
+
+function bar(fraction) {
+  const outer = document.createElement("div");
+  const inner = document.createElement("div");
+  inner.style.width = Math.round(Math.min(Math.max(fraction, 0), 1) * 100) + "%";
+  outer.appendChild(inner);
+  return outer;
+}
+
+function time(value) {
+  return value ? new Date(value).toLocaleString() : "";
+}
+
+function renderJobs(jobs) {
+  const body = document.getElementById("jobs");
+  body.replaceChildren();
+  for (const job of jobs) {
+    const row = body.insertRow();
+    cell(row, job.id.slice(0, 8)).title = job.id;
+    cell(row, job.owner || "");
+    cell(row, job.state, "state-" + job.state);
+    const progress = bar(job.progress.domains_processed / job.total);
+    progress.className = "progress";
+    const td = cell(row, "");
+    td.appendChild(progress);
+    td.title = job.progress.domains_processed + " / " + job.total;
+    cell(row, job.progress.tenants_found);
+    cell(row, job.progress.mdi_instances);
+    cell(row, job.progress.errors);
+    cell(row, time(job.created_at));
+  }
+}
+
+// Share of identified tenants with an MDI instance, overall and per job
+function renderCoverage(jobs) {
+  const chart = document.getElementById("coverage");
+  chart.replaceChildren();
+  const total = jobs.reduce(
+    (sum, job) => ({
+      tenants: sum.tenants + job.progress.tenants_found,
+      mdi: sum.mdi + job.progress.mdi_instances,
+    }),
+    { tenants: 0, mdi: 0 },
+  );
+  const rows = [{ label: "All jobs", tenants: total.tenants, mdi: total.mdi }].concat(
+    jobs.map((job) => ({
+      label: job.id.slice(0, 8) + (job.owner ? " (" + job.owner + ")" : ""),
+      tenants: job.progress.tenants_found,
+      mdi: job.progress.mdi_instances,
+    })),
+  );
+  for (const entry of rows) {
+    const row = document.createElement("div");
+    row.className = "row";
+    const label = document.createElement("span");
+    label.className = "label";
+    label.textContent = entry.label;
+    const coverage = bar(entry.tenants ? entry.mdi / entry.tenants : 0);
+    coverage.className = "bar";
+    const value = document.createElement("span");
+    value.className = "value";
+    value.textContent = entry.mdi + " / " + entry.tenants + " tenants";
+    row.append(label, coverage, value);
+    chart.appendChild(row);
+  }
+}
+
+async function refreshJobs() {
+  try {
+    const jobs = await api("/jobs");
+    renderJobs(jobs);
+    renderCoverage(jobs);
+    showError(null);
+  } catch (error) {
+    showError(error);
+  }
+}
+
+async function search(event) {
+  if (event) {
+    event.preventDefault();
+  }
+  const query = document.getElementById("query").value;
+  try {
+    const hits = await api("/results?q=" + encodeURIComponent(query));
+    const body = document.getElementById("results");
+    body.replaceChildren();
+    for (const hit of hits) {
+      const row = body.insertRow();
+      cell(row, hit.result.domain);
+      cell(row, hit.result.tenant);
+      cell(row, hit.result.mdi_instance);
+      cell(row, hit.result.mdi_generation);
+      cell(row, hit.result.error);
+      cell(row, hit.source + " " + hit.source_id.slice(0, 8)).title = hit.source_id;
+      cell(row, time(hit.scanned_at));
+    }
+    showError(null);
+  } catch (error) {
+    showError(error);
+  }
+}
+
+document.getElementById("api-key").value = apiKey;
+document.getElementById("key-form").addEventListener("submit", (event) => {
+  event.preventDefault();
+  apiKey = document.getElementById("api-key").value.trim();
+  sessionStorage.setItem("sentri-api-key", apiKey);
+  refreshJobs();
+  search();
+});
+document.getElementById("search-form").addEventListener("submit", search);
+
+refreshJobs();
+search();
+setInterval(refreshJobs, REFRESH_MS);
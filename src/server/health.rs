@@ -0,0 +1,194 @@
+//! Health and readiness probes
+//!
+//! | Path       | Checks                                         | Use              |
+//! |------------|------------------------------------------------|------------------|
+//! | `/healthz` | State directory, rate limiter                  | Liveness probe   |
+//! | `/readyz`  | The above plus autodiscover reachability, DNS  | Readiness probe  |
+//!
+//! Both answer with the status of each component:
+//!
+//! ```json
+//! {
+//!   "status": "warn",
+//!   "checks": [
+//!     {"component": "state_dir", "status": "pass", "latency_ms": 0},
+//!     {"component": "rate_limit", "status": "warn", "latency_ms": 0,
+//!      "detail": "0 of 50 requests left this minute, 5 of 5 slots free"}
+//!   ]
+//! }
+//! ```
+//!
+//! A component that fails makes the probe answer 503; a saturated rate
+//! limiter only warns, since scans still progress, just more slowly.
+//! Liveness deliberately ignores external dependencies: restarting the
+//! process does not fix a network outage.
+//!
+//! External checks are cached for [`EXTERNAL_CHECK_TTL`] so frequent probes
+//! do not add load on Microsoft's endpoints or the resolver. The probes are
+//! served without authentication.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use super::ServerState;
+
+/// How long autodiscover and DNS results are reused
+pub const EXTERNAL_CHECK_TTL: Duration = Duration::from_secs(30);
+
+/// Time allowed for a single external check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// File written and removed to prove the state directory is writable
+const PROBE_FILE: &str = ".health-probe";
+
+/// Outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    /// The component works
+    Pass,
+    /// The component works but is degraded
+    Warn,
+    /// The component does not work
+    Fail,
+}
+
+/// Status of one component
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentCheck {
+    /// Component name
+    pub component: String,
+    /// Outcome
+    pub status: CheckStatus,
+    /// Time the check took
+    pub latency_ms: u64,
+    /// Explanation of a warning or failure, or a measurement
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Response of a probe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// Worst status of all components
+    pub status: CheckStatus,
+    /// Individual components
+    pub checks: Vec<ComponentCheck>,
+}
+
+impl HealthReport {
+    fn new(checks: Vec<ComponentCheck>) -> Self {
+        let status = checks
+            .iter()
+            .map(|check| check.status)
+            .max()
+            .unwrap_or(CheckStatus::Pass);
+        Self { status, checks }
+    }
+
+    fn into_response(self) -> (StatusCode, Json<Self>) {
+        let code = if self.status == CheckStatus::Fail {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        };
+        (code, Json(self))
+    }
+}
+
+/// Cache of the external dependency checks
+#[derive(Default)]
+pub struct HealthCache {
+    external: Mutex<Option<(Instant, Vec<ComponentCheck>)>>,
+}
+
+/// Runs a check with a timeout, timing it
+async fn timed<F>(component: &str, check: F) -> ComponentCheck
+where
+    F: Future<Output = anyhow::Result<(CheckStatus, String)>>,
+{
+    let start = Instant::now();
+    let (status, detail) = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok((status, detail))) => (status, detail),
+        Ok(Err(e)) => (CheckStatus::Fail, format!("{:#}", e)),
+        Err(_) => (
+            CheckStatus::Fail,
+            format!("No answer within {:?}", CHECK_TIMEOUT),
+        ),
+    };
+    ComponentCheck {
+        component: component.to_string(),
+        status,
+        latency_ms: start.elapsed().as_millis() as u64,
+        detail: Some(detail).filter(|detail| !detail.is_empty()),
+    }
+}
+
+/// Checks that don't leave the host
+async fn local_checks(state: &ServerState) -> Vec<ComponentCheck> {
+    let probe = state.scheduler.state_dir().join(PROBE_FILE);
+    let state_dir = timed("state_dir", async {
+        tokio::fs::write(&probe, b"ok").await?;
+        tokio::fs::remove_file(&probe).await?;
+        Ok((CheckStatus::Pass, String::new()))
+    })
+    .await;
+
+    let rate_limit = timed("rate_limit", async {
+        let usage = state.jobs.budget().shared().usage().await;
+        let detail = format!(
+            "{} of {} requests left this minute, {} slots free",
+            usage.tokens_available, usage.capacity, usage.permits_available
+        );
+        let status = if usage.is_saturated() {
+            CheckStatus::Warn
+        } else {
+            CheckStatus::Pass
+        };
+        Ok((status, detail))
+    })
+    .await;
+
+    vec![state_dir, rate_limit]
+}
+
+/// Checks of Microsoft's autodiscover endpoint and DNS, cached
+async fn external_checks(state: &ServerState) -> Vec<ComponentCheck> {
+    let mut cache = state.health.external.lock().await;
+    if let Some((checked_at, checks)) = cache.as_ref() {
+        if checked_at.elapsed() < EXTERNAL_CHECK_TTL {
+            return checks.clone();
+        }
+    }
+
+    let checker = state.jobs.checker();
+    let autodiscover = timed("autodiscover", async {
+        let status = checker.probe_autodiscover().await?;
+        Ok((CheckStatus::Pass, format!("HTTP {}", status)))
+    })
+    .await;
+    let dns = timed("dns", async {
+        let addresses = checker.probe_dns().await?;
+        Ok((CheckStatus::Pass, format!("{} addresses", addresses)))
+    })
+    .await;
+
+    let checks = vec![autodiscover, dns];
+    *cache = Some((Instant::now(), checks.clone()));
+    checks
+}
+
+pub(super) async fn healthz(State(state): State<ServerState>) -> (StatusCode, Json<HealthReport>) {
+    HealthReport::new(local_checks(&state).await).into_response()
+}
+
+pub(super) async fn readyz(State(state): State<ServerState>) -> (StatusCode, Json<HealthReport>) {
+    let mut checks = local_checks(&state).await;
+    checks.extend(external_checks(&state).await);
+    HealthReport::new(checks).into_response()
+}
@@ -0,0 +1,190 @@
+//! Job management endpoints
+//!
+//! | Method   | Path                  | Description                          |
+//! |----------|-----------------------|--------------------------------------|
+//! | `GET`    | `/jobs`               | List jobs, newest first              |
+//! | `POST`   | `/jobs`               | Submit a domain list, returns the ID |
+//! | `GET`    | `/jobs/{id}`          | Job state and progress               |
+//! | `DELETE` | `/jobs/{id}`          | Cancel a queued or running job       |
+//! | `GET`    | `/jobs/{id}/results`  | Stream results as JSONL              |
+//! | `GET`    | `/jobs/{id}/events`   | Follow the job live (SSE)            |
+//!
+//! The events endpoint is a Server-Sent Events stream. It opens with a
+//! `status` event carrying the current [`JobStatus`], sends a `result` event
+//! for each domain as it completes and ends with a `finished` event carrying
+//! the final status. A client too slow to keep up receives a `lagged` event
+//! with the number of results it missed; those remain in `/jobs/{id}/results`.
+
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::io::ReaderStream;
+use tracing::warn;
+
+use super::{ApiError, Caller, Scope, ServerState};
+use crate::encryption::LineDecoder;
+use crate::engagement::Engagement;
+use crate::jobs::{JobEvent, JobManager, JobRequest, JobStatus};
+
+/// Looks up a job visible to the caller
+async fn visible_job(
+    state: &ServerState,
+    caller: &Caller,
+    id: &str,
+) -> Result<JobStatus, ApiError> {
+    state
+        .jobs
+        .get(id)
+        .await
+        .filter(|job| caller.can_access(job.owner.as_deref()))
+        .ok_or(ApiError::NotFound)
+}
+
+pub(super) async fn list_jobs(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<JobStatus>>, ApiError> {
+    caller.require(Scope::Read)?;
+    let jobs = state.jobs.list().await;
+    Ok(Json(
+        jobs.into_iter()
+            .filter(|job| caller.can_access(job.owner.as_deref()))
+            .collect(),
+    ))
+}
+
+pub(super) async fn submit_job(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Extension(engagement): Extension<Engagement>,
+    Json(mut request): Json<JobRequest>,
+) -> Result<(StatusCode, Json<JobStatus>), ApiError> {
+    caller.require(Scope::Scan)?;
+    // The request headers' engagement applies unless the body names one
+    request.engagement = request.engagement.or(Some(&engagement));
+    let status = Arc::clone(&state.jobs)
+        .submit(request, caller.key.clone())
+        .await
+        .map_err(|e| ApiError::BadRequest(format!("{:#}", e)))?;
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+pub(super) async fn get_job(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Json<JobStatus>, ApiError> {
+    caller.require(Scope::Read)?;
+    visible_job(&state, &caller, &id).await.map(Json)
+}
+
+pub(super) async fn cancel_job(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    caller.require(Scope::Scan)?;
+    visible_job(&state, &caller, &id).await?;
+    match state.jobs.cancel(&id).await {
+        Some(true) => Ok(StatusCode::ACCEPTED),
+        Some(false) => Err(ApiError::Conflict(format!(
+            "Job {} has already finished",
+            id
+        ))),
+        None => Err(ApiError::NotFound),
+    }
+}
+
+/// Streams an encrypted result file as plaintext JSONL, line by line
+fn decrypted_body(file: tokio::fs::File, jobs: Arc<JobManager>) -> Body {
+    let lines = BufReader::new(file).lines();
+    Body::from_stream(stream::unfold(
+        (lines, jobs, LineDecoder::new()),
+        |(mut lines, jobs, mut decoder)| async move {
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => return None,
+                    Err(e) => return Some((Err(e.into()), (lines, jobs, decoder))),
+                };
+                match decoder.decode(jobs.storage_key(), &line) {
+                    Ok(None) => continue,
+                    Ok(Some(mut line)) => {
+                        line.push('\n');
+                        return Some((Ok(line), (lines, jobs, decoder)));
+                    }
+                    Err(e) => return Some((Err(e), (lines, jobs, decoder))),
+                }
+            }
+        },
+    ))
+}
+
+pub(super) async fn job_results(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    caller.require(Scope::Read)?;
+    visible_job(&state, &caller, &id).await?;
+
+    // Results written so far; a queued job has no file yet
+    let body = match tokio::fs::File::open(state.jobs.results_path(&id)).await {
+        Ok(file) if state.jobs.storage_key().is_some() => decrypted_body(file, state.jobs.clone()),
+        Ok(file) => Body::from_stream(ReaderStream::new(file)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Body::empty(),
+        Err(e) => return Err(ApiError::Internal(e.into())),
+    };
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], body).into_response())
+}
+
+pub(super) async fn job_events(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Sse<BoxStream<'static, Result<Event, axum::Error>>>, ApiError> {
+    caller.require(Scope::Read)?;
+    visible_job(&state, &caller, &id).await?;
+    let (status, receiver) = state.jobs.subscribe(&id).await.ok_or(ApiError::NotFound)?;
+
+    let events = match receiver {
+        // Already finished: report the final status and close
+        None => stream::once(async move { Event::default().event("finished").json_data(&status) })
+            .boxed(),
+        Some(receiver) => {
+            let initial = Event::default().event("status").json_data(&status);
+            let updates = stream::unfold(Some(receiver), move |receiver| {
+                let id = id.clone();
+                async move {
+                    let mut receiver = receiver?;
+                    let event = match receiver.recv().await {
+                        Ok(JobEvent::Result(result)) => {
+                            Event::default().event("result").json_data(&*result)
+                        }
+                        Ok(JobEvent::Finished(status)) => {
+                            return Some((
+                                Event::default().event("finished").json_data(&status),
+                                None,
+                            ));
+                        }
+                        Err(RecvError::Lagged(missed)) => {
+                            warn!("Event subscriber of job {} missed {} results", id, missed);
+                            Ok(Event::default().event("lagged").data(missed.to_string()))
+                        }
+                        Err(RecvError::Closed) => return None,
+                    };
+                    Some((event, Some(receiver)))
+                }
+            });
+            stream::once(async move { initial }).chain(updates).boxed()
+        }
+    };
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,345 @@
+//! Server mode: sentri as a small internal scanning service
+//!
+//! `sentri serve` exposes a JSON HTTP API for managing recurring scans of
+//! registered domain lists. Schedules and their last-run status live in the
+//! state directory and are executed by the embedded [`Scheduler`].
+//!
+//! With an API key file (see [`auth`]) every request must carry a key, each
+//! key only sees the schedules it registered, each key's scans and API calls
+//! are held to their own quotas, read-only keys cannot launch scans, and only
+//! admin keys can change rate limits at runtime.
+//!
+//! # Endpoints
+//!
+//! | Method   | Path                    | Description                          |
+//! |----------|-------------------------|--------------------------------------|
+//! | `GET`    | `/schedules`            | List schedules with last-run status  |
+//! | `POST`   | `/schedules`            | Register a schedule                  |
+//! | `GET`    | `/schedules/{id}`       | Show one schedule                    |
+//! | `DELETE` | `/schedules/{id}`       | Remove a schedule                    |
+//! | `POST`   | `/schedules/{id}/run`   | Start a run immediately              |
+//! | `GET`    | `/results`              | Search stored results ([`results`])  |
+//! | `GET`    | `/admin/limits`         | Show rate limits ([`admin`])         |
+//! | `PUT`    | `/admin/limits`         | Change rate limits ([`admin`])       |
+//! | `GET`    | `/version`              | Build metadata ([`BuildInfo`])       |
+//! | `GET`    | `/`                     | Web dashboard ([`dashboard`])        |
+//! | `GET`    | `/healthz`, `/readyz`   | Probes ([`health`])                  |
+//!
+//! One-off scans are submitted as asynchronous jobs that can be followed
+//! live; see [`jobs`] for the `/jobs` endpoints.
+//!
+//! # Security Considerations
+//!
+//! - **Listen Address**: The server binds to loopback by default; expose it
+//!   deliberately, behind TLS termination and with API keys configured
+//! - **Input Validation**: Submitted domains and cron expressions are validated
+//!   before they are stored (security:input:sanitize_all_input)
+//! - **Data Retention**: With `--retention`/`--audit-retention`, expired
+//!   results and audit records are purged hourly (see [`crate::retention`])
+//! - **Error Information Control**: Internal errors are logged in full but
+//!   reported to clients without detail (security:output:error_info_control)
+
+pub mod admin;
+pub mod auth;
+pub mod dashboard;
+pub mod health;
+pub mod jobs;
+pub mod results;
+
+use anyhow::Result;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{middleware, Extension, Json, Router};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+pub use auth::{
+    ApiKey, ApiKeys, AuditChange, AuditLog, AuditRecord, Caller, Scope, AUDIT_LOG_FILE,
+};
+
+use crate::build_info::BuildInfo;
+use crate::jobs::JobManager;
+use crate::retention::{self, PurgeReport, RetentionPolicy};
+use crate::scheduler::{ScheduleRequest, ScheduledScan, Scheduler};
+
+/// Interval between retention passes while serving
+const RETENTION_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Shared state of the API handlers
+#[derive(Clone)]
+pub struct ServerState {
+    /// Scheduler managing recurring scans
+    pub scheduler: Arc<Scheduler>,
+    /// Manager of one-off scan jobs
+    pub jobs: Arc<JobManager>,
+    /// Accepted API keys; authentication is disabled when None
+    pub api_keys: Option<Arc<ApiKeys>>,
+    /// Audit trail of API requests
+    pub audit_log: Option<Arc<AuditLog>>,
+    /// Cached results of the readiness checks
+    pub health: Arc<health::HealthCache>,
+    /// Maximum ages of stored results and audit records
+    pub retention: RetentionPolicy,
+}
+
+impl ServerState {
+    /// Creates the server state without authentication
+    pub fn new(scheduler: Scheduler, jobs: JobManager) -> Self {
+        Self {
+            scheduler: Arc::new(scheduler),
+            jobs: Arc::new(jobs),
+            api_keys: None,
+            audit_log: None,
+            health: Arc::new(health::HealthCache::default()),
+            retention: RetentionPolicy::default(),
+        }
+    }
+
+    /// Requires one of the given API keys on every request
+    pub fn with_api_keys(mut self, keys: ApiKeys) -> Self {
+        self.api_keys = Some(Arc::new(keys));
+        self
+    }
+
+    /// Records every API request in an audit file
+    pub fn with_audit_log(mut self, log: AuditLog) -> Self {
+        self.audit_log = Some(Arc::new(log));
+        self
+    }
+
+    /// Purges stored data older than the policy allows while serving
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+}
+
+/// Error returned by API handlers, rendered as `{"error": "..."}`
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request was malformed or failed validation
+    BadRequest(String),
+    /// The request carried no valid API key
+    Unauthorized(String),
+    /// The API key lacks the scope the request needs
+    Forbidden(String),
+    /// The caller's request quota is exhausted
+    TooManyRequests,
+    /// The addressed resource does not exist
+    NotFound,
+    /// The request conflicts with the current state
+    Conflict(String),
+    /// An unexpected failure; details are logged, not returned
+    Internal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError::Internal(e)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::Unauthorized(message) => (StatusCode::UNAUTHORIZED, message),
+            ApiError::Forbidden(message) => (StatusCode::FORBIDDEN, message),
+            ApiError::TooManyRequests => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "API request quota exceeded".to_string(),
+            ),
+            ApiError::NotFound => (StatusCode::NOT_FOUND, "Not found".to_string()),
+            ApiError::Conflict(message) => (StatusCode::CONFLICT, message),
+            ApiError::Internal(e) => {
+                error!("Internal server error: {:#}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_string(),
+                )
+            }
+        };
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+/// Builds the API router
+pub fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/schedules", get(list_schedules).post(create_schedule))
+        .route("/schedules/:id", get(get_schedule).delete(delete_schedule))
+        .route("/schedules/:id/run", post(run_schedule))
+        .route("/jobs", get(jobs::list_jobs).post(jobs::submit_job))
+        .route("/jobs/:id", get(jobs::get_job).delete(jobs::cancel_job))
+        .route("/jobs/:id/results", get(jobs::job_results))
+        .route("/jobs/:id/events", get(jobs::job_events))
+        .route("/results", get(results::search_results))
+        .route(
+            "/admin/limits",
+            get(admin::get_limits).put(admin::update_limits),
+        )
+        .route("/version", get(version))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::authenticate,
+        ))
+        // Added after the layer: probes and static assets need no API key
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .merge(dashboard::routes())
+        .with_state(state)
+}
+
+/// Serves the API and runs the scheduler until the process is stopped
+///
+/// # Arguments
+/// * `listener` - Bound TCP listener
+/// * `state` - Server state
+pub async fn serve(listener: TcpListener, state: ServerState) -> Result<()> {
+    info!("Sentri server listening on {}", listener.local_addr()?);
+    let scheduler = tokio::spawn(Arc::clone(&state.scheduler).run());
+    let retention = (!state.retention.is_unlimited()).then(|| {
+        let state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = enforce_retention(&state).await {
+                    error!("Retention enforcement failed: {:#}", e);
+                }
+                tokio::time::sleep(RETENTION_INTERVAL).await;
+            }
+        })
+    });
+
+    let app = router(state).into_make_service_with_connect_info::<SocketAddr>();
+    let result = axum::serve(listener, app).await;
+    scheduler.abort();
+    if let Some(retention) = retention {
+        retention.abort();
+    }
+    Ok(result?)
+}
+
+/// Removes results, finished jobs and audit records the retention policy expired
+///
+/// # Returns
+/// * `Result<PurgeReport>` - What was removed
+pub async fn enforce_retention(state: &ServerState) -> Result<PurgeReport> {
+    let now = chrono::Utc::now();
+    let mut report = PurgeReport::default();
+    if let Some(max_age) = state.retention.results {
+        let cutoff = retention::cutoff(now, max_age);
+        state.jobs.forget_finished_before(cutoff).await;
+        report = retention::purge_results(state.scheduler.state_dir(), cutoff, false).await?;
+    }
+    if let (Some(max_age), Some(log)) = (state.retention.audit, &state.audit_log) {
+        report.audit_records = log.purge(retention::cutoff(now, max_age)).await?;
+    }
+    if report != PurgeReport::default() {
+        info!(
+            "Retention purged {} result files ({} bytes) and {} audit records",
+            report.result_files, report.result_bytes, report.audit_records
+        );
+    }
+    Ok(report)
+}
+
+/// Reports the version, commit and build time of the server
+async fn version(Extension(caller): Extension<Caller>) -> Result<Json<BuildInfo>, ApiError> {
+    caller.require(Scope::Read)?;
+    Ok(Json(BuildInfo::current()))
+}
+
+async fn list_schedules(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<ScheduledScan>>, ApiError> {
+    caller.require(Scope::Read)?;
+    let schedules = state.scheduler.list().await;
+    Ok(Json(
+        schedules
+            .into_iter()
+            .filter(|schedule| caller.can_access(schedule.owner.as_deref()))
+            .collect(),
+    ))
+}
+
+async fn create_schedule(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Json(request): Json<ScheduleRequest>,
+) -> Result<(StatusCode, Json<ScheduledScan>), ApiError> {
+    caller.require(Scope::Scan)?;
+    let mut schedule = ScheduledScan::from_request(request, chrono::Utc::now())
+        .map_err(|e| ApiError::BadRequest(format!("{:#}", e)))?;
+    if let Some(key) = &caller.key {
+        schedule = schedule.with_owner(key);
+    }
+    state.scheduler.add(schedule.clone()).await?;
+    Ok((StatusCode::CREATED, Json(schedule)))
+}
+
+/// Looks up a schedule visible to the caller
+async fn visible_schedule(
+    state: &ServerState,
+    caller: &Caller,
+    id: &str,
+) -> Result<ScheduledScan, ApiError> {
+    state
+        .scheduler
+        .get(id)
+        .await
+        .filter(|schedule| caller.can_access(schedule.owner.as_deref()))
+        .ok_or(ApiError::NotFound)
+}
+
+async fn get_schedule(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Json<ScheduledScan>, ApiError> {
+    caller.require(Scope::Read)?;
+    visible_schedule(&state, &caller, &id).await.map(Json)
+}
+
+async fn delete_schedule(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    caller.require(Scope::Scan)?;
+    visible_schedule(&state, &caller, &id).await?;
+    if state.scheduler.remove(&id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound)
+    }
+}
+
+async fn run_schedule(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    caller.require(Scope::Scan)?;
+    visible_schedule(&state, &caller, &id).await?;
+    if state.scheduler.is_running(&id).await {
+        return Err(ApiError::Conflict(format!(
+            "Schedule {} is already running",
+            id
+        )));
+    }
+
+    let scheduler = Arc::clone(&state.scheduler);
+    tokio::spawn(async move {
+        if let Err(e) = scheduler.run_now(&id).await {
+            error!("Manual run of {} failed: {:#}", id, e);
+        }
+    });
+    Ok(StatusCode::ACCEPTED)
+}
@@ -0,0 +1,160 @@
+//! Search over stored scan results
+//!
+//! `GET /results?q=<text>&limit=<n>` searches the result files kept in the
+//! state directory: every job visible to the caller and the latest run of
+//! every visible schedule. A result matches when its domain, tenant, MDI
+//! instance or one of its federated domains contains the query
+//! (case-insensitive); an empty query matches everything. Results are
+//! returned newest source first, up to `limit` (default 100, at most 1000).
+
+use axum::extract::{Query, State};
+use axum::{Extension, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::warn;
+
+use super::{ApiError, Caller, Scope, ServerState};
+use crate::core::DomainResult;
+use crate::encryption::{LineDecoder, StorageKey};
+
+/// Results returned when the request sets no limit
+const DEFAULT_LIMIT: usize = 100;
+
+/// Upper bound on results returned by one search
+const MAX_LIMIT: usize = 1000;
+
+/// Search parameters
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// Text to look for; empty matches every result
+    #[serde(default)]
+    pub q: String,
+    /// Maximum number of results
+    pub limit: Option<usize>,
+}
+
+/// Where a stored result came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultSource {
+    /// A one-off job
+    Job,
+    /// A scheduled run
+    Schedule,
+}
+
+/// A stored result matching a search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    /// Kind of scan that produced the result
+    pub source: ResultSource,
+    /// ID of the job or schedule
+    pub source_id: String,
+    /// When the producing scan started
+    pub scanned_at: DateTime<Utc>,
+    /// The result itself
+    pub result: DomainResult,
+}
+
+/// Returns true if the result matches a lowercase query
+fn matches(result: &DomainResult, query: &str) -> bool {
+    let contains = |value: &str| value.to_lowercase().contains(query);
+    query.is_empty()
+        || contains(&result.domain)
+        || result.tenant.as_deref().is_some_and(contains)
+        || result.mdi_instance.as_deref().is_some_and(contains)
+        || result.federated_domains.iter().any(|d| contains(d))
+}
+
+/// Appends matching results from one JSONL file until `limit` hits are collected
+async fn search_file(
+    path: &Path,
+    key: Option<&StorageKey>,
+    query: &str,
+    limit: usize,
+    source: (ResultSource, &str, DateTime<Utc>),
+    hits: &mut Vec<SearchHit>,
+) -> anyhow::Result<()> {
+    let file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        // Queued jobs have not written anything yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut lines = BufReader::new(file).lines();
+    let mut decoder = LineDecoder::new();
+    while let Some(line) = lines.next_line().await? {
+        if hits.len() >= limit {
+            break;
+        }
+        let line = match decoder.decode(key, &line) {
+            Ok(Some(line)) => line,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Skipping unreadable result in {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let result: DomainResult = match serde_json::from_str(&line) {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("Skipping malformed result in {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if matches(&result, query) {
+            hits.push(SearchHit {
+                source: source.0,
+                source_id: source.1.to_string(),
+                scanned_at: source.2,
+                result,
+            });
+        }
+    }
+    Ok(())
+}
+
+pub(super) async fn search_results(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Query(search): Query<SearchQuery>,
+) -> Result<Json<Vec<SearchHit>>, ApiError> {
+    caller.require(Scope::Read)?;
+    let query = search.q.trim().to_lowercase();
+    let limit = search.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    let mut sources = Vec::new();
+    for job in state.jobs.list().await {
+        if caller.can_access(job.owner.as_deref()) {
+            let path = state.jobs.results_path(&job.id);
+            sources.push((ResultSource::Job, job.id, job.created_at, path));
+        }
+    }
+    for schedule in state.scheduler.list().await {
+        if !caller.can_access(schedule.owner.as_deref()) {
+            continue;
+        }
+        if let Some(run) = schedule.last_run {
+            if let Some(path) = run.results_file {
+                sources.push((ResultSource::Schedule, schedule.id, run.started_at, path));
+            }
+        }
+    }
+    sources.sort_by_key(|source| std::cmp::Reverse(source.2));
+
+    let mut hits = Vec::new();
+    for (source, id, scanned_at, path) in &sources {
+        if hits.len() >= limit {
+            break;
+        }
+        let key = match source {
+            ResultSource::Job => state.jobs.storage_key(),
+            ResultSource::Schedule => state.scheduler.storage_key(),
+        };
+        let origin = (*source, id.as_str(), *scanned_at);
+        search_file(path, key, &query, limit, origin, &mut hits).await?;
+    }
+    Ok(Json(hits))
+}
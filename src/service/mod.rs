@@ -0,0 +1,247 @@
+//! Integration with service managers for `serve` and `watch`
+//!
+//! Long-running modes are meant to run under a service manager rather than
+//! in a terminal multiplexer. Under systemd, sentri implements the
+//! `sd_notify` protocol, so a unit can use `Type=notify`:
+//!
+//! - `READY=1` is sent once `serve` listens, or `watch` starts its first
+//!   cycle, so dependent units start only after sentri is usable
+//! - With `WatchdogSec=`, `WATCHDOG=1` is sent as scans make progress and
+//!   while the scan loops wait for their next run (see [`progress`]), so
+//!   systemd restarts a process whose scans are wedged
+//!
+//! - `systemctl reload` sends `SIGHUP`, which re-reads the `--config` file;
+//!   see [`crate::reload`]
+//!
+//! Outside systemd, where `NOTIFY_SOCKET` is unset, every notification is a
+//! no-op. `sentri service unit` prints a unit file for a command line:
+//!
+//! ```text
+//! sentri service unit -- serve --state-dir /var/lib/sentri > /etc/systemd/system/sentri.service
+//! ```
+//!
+//! On Windows, `sentri service install` registers a command line with the
+//! Service Control Manager, which then runs it as a service that stops on
+//! `sc stop`; see [`windows`].
+
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+#[cfg(unix)]
+use tracing::{debug, warn};
+
+#[cfg(windows)]
+pub mod windows;
+
+/// Variable naming the socket systemd listens for notifications on
+pub const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+
+/// Watchdog armed for this process, pinged by [`progress`]
+static WATCHDOG: OnceLock<Watchdog> = OnceLock::new();
+
+/// Client of the service manager's notification socket
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Notifier {
+    active: bool,
+}
+
+impl Notifier {
+    /// Notifier for the socket in `NOTIFY_SOCKET`; inactive when unset
+    pub fn from_env() -> Self {
+        Self {
+            active: std::env::var_os(NOTIFY_SOCKET_ENV).is_some_and(|socket| !socket.is_empty()),
+        }
+    }
+
+    /// Returns true if a service manager listens for notifications
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Tells the service manager that startup has finished
+    pub fn ready(&self) {
+        #[cfg(unix)]
+        self.send(sd_notify::NotifyState::Ready);
+    }
+
+    /// Tells the service manager that the process is alive
+    pub fn watchdog(&self) {
+        #[cfg(unix)]
+        self.send(sd_notify::NotifyState::Watchdog);
+    }
+
+    /// Shows a one-line status in `systemctl status`
+    pub fn status(&self, status: &str) {
+        #[cfg(unix)]
+        self.send(sd_notify::NotifyState::Status(&status.replace('\n', " ")));
+        #[cfg(not(unix))]
+        let _ = status;
+    }
+
+    /// Sends a notification, logging rather than failing on errors
+    #[cfg(unix)]
+    fn send(&self, state: sd_notify::NotifyState) {
+        if !self.active {
+            return;
+        }
+        match sd_notify::notify(false, &[state]) {
+            Ok(()) => debug!("Notified service manager"),
+            Err(e) => warn!("Failed to notify service manager: {}", e),
+        }
+    }
+
+    /// Interval of watchdog pings: half the watchdog timeout
+    ///
+    /// `None` when no watchdog is configured for this process; the
+    /// `WATCHDOG_USEC` of a parent process, whose `WATCHDOG_PID` differs, is
+    /// ignored.
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        if !self.active {
+            return None;
+        }
+        #[cfg(unix)]
+        {
+            let mut usec = 0;
+            sd_notify::watchdog_enabled(false, &mut usec)
+                .then(|| Duration::from_micros(usec / 2))
+                .filter(|interval| !interval.is_zero())
+        }
+        #[cfg(not(unix))]
+        None
+    }
+
+    /// Arms the watchdog configured for this process, if any
+    ///
+    /// From then on [`progress`] pings it, so it only stays satisfied while
+    /// the scan loops make progress.
+    ///
+    /// # Returns
+    /// * `Option<Duration>` - Interval of the pings, None without a watchdog
+    pub fn arm_watchdog(&self) -> Option<Duration> {
+        let interval = self.watchdog_interval()?;
+        let watchdog = WATCHDOG.get_or_init(|| Watchdog {
+            notifier: *self,
+            interval,
+            last_ping: Mutex::new(None),
+        });
+        progress();
+        Some(watchdog.interval)
+    }
+}
+
+/// Watchdog pinged from the progress of the scan loops
+struct Watchdog {
+    notifier: Notifier,
+    interval: Duration,
+    last_ping: Mutex<Option<Instant>>,
+}
+
+/// Reports that a scan or scheduling loop made progress
+///
+/// Pings the armed watchdog at most once per interval; a no-op when no
+/// watchdog is armed.
+pub fn progress() {
+    let Some(watchdog) = WATCHDOG.get() else {
+        return;
+    };
+    let mut last_ping = watchdog.last_ping.lock().unwrap_or_else(|e| e.into_inner());
+    if last_ping.is_some_and(|last| last.elapsed() < watchdog.interval / 2) {
+        return;
+    }
+    *last_ping = Some(Instant::now());
+    drop(last_ping);
+    watchdog.notifier.watchdog();
+}
+
+/// Sleeps for `duration` in a loop waiting for its next run, reporting
+/// [`progress`] often enough to keep an armed watchdog satisfied
+pub async fn idle(duration: Duration) {
+    let Some(watchdog) = WATCHDOG.get() else {
+        tokio::time::sleep(duration).await;
+        return;
+    };
+    let deadline = tokio::time::Instant::now() + duration;
+    loop {
+        progress();
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        if remaining.is_zero() {
+            return;
+        }
+        tokio::time::sleep(remaining.min(watchdog.interval / 2)).await;
+    }
+}
+
+/// Renders a systemd unit running sentri with `args`
+///
+/// # Arguments
+/// * `executable` - Absolute path of the sentri binary
+/// * `args` - Arguments of the service, e.g. `serve --state-dir /var/lib/sentri`
+/// * `user` - Account the service runs as; a transient one when `None`
+/// * `watchdog` - Watchdog timeout, if systemd should restart a hung process
+///
+/// # Examples
+///
+/// ```
+/// use sentri::service::unit_file;
+/// use std::time::Duration;
+///
+/// let unit = unit_file("/usr/bin/sentri", &["serve".to_string()], None, Some(Duration::from_secs(60)));
+/// assert!(unit.contains("Type=notify\n"));
+/// assert!(unit.contains("ExecStart=/usr/bin/sentri serve\n"));
+/// assert!(unit.contains("WatchdogSec=60\n"));
+/// assert!(unit.contains("DynamicUser=yes\n"));
+/// ```
+pub fn unit_file(
+    executable: &str,
+    args: &[String],
+    user: Option<&str>,
+    watchdog: Option<Duration>,
+) -> String {
+    let command = std::iter::once(executable)
+        .chain(args.iter().map(String::as_str))
+        .map(quote_arg)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut unit = String::from(
+        "[Unit]\n\
+         Description=Sentri MDI discovery service\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=notify\n",
+    );
+    unit.push_str(&format!("ExecStart={}\n", command));
+    unit.push_str("ExecReload=/bin/kill -HUP $MAINPID\n");
+    unit.push_str("Restart=on-failure\n");
+    if let Some(watchdog) = watchdog {
+        unit.push_str(&format!("WatchdogSec={}\n", watchdog.as_secs().max(1)));
+    }
+    match user {
+        Some(user) => unit.push_str(&format!("User={}\n", user)),
+        None => unit.push_str("DynamicUser=yes\n"),
+    }
+    unit.push_str(
+        "NoNewPrivileges=yes\n\
+         ProtectSystem=strict\n\
+         ProtectHome=yes\n\
+         PrivateTmp=yes\n\
+         StateDirectory=sentri\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+    );
+    unit
+}
+
+/// Quotes an `ExecStart=` argument containing whitespace or quotes
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        return arg.replace('%', "%%");
+    }
+    format!(
+        "\"{}\"",
+        arg.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('%', "%%")
+    )
+}
@@ -0,0 +1,184 @@
+//! Windows service registration for `serve` and `watch`
+//!
+//! `sentri service install -- <args>` registers a service with the Service
+//! Control Manager whose command line is `sentri service run --name <name>
+//! -- <args>`. The SCM starts it at boot, restarts it after failures and
+//! stops it with `sc stop` or at shutdown:
+//!
+//! ```text
+//! sentri service install --account "NT AUTHORITY\LocalService" -- serve --state-dir C:\ProgramData\sentri
+//! sc start sentri
+//! ```
+//!
+//! `run` hands the process to the SCM's dispatcher, reports the service as
+//! running and stops the command when the SCM asks it to.
+
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tracing::error;
+use windows_service::service::{
+    ServiceAccess, ServiceAction, ServiceActionType, ServiceControl, ServiceControlAccept,
+    ServiceErrorControl, ServiceExitCode, ServiceFailureActions, ServiceFailureResetPeriod,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+/// Name of the service unless `--name` is given
+pub const DEFAULT_SERVICE_NAME: &str = "sentri";
+
+/// Delay before the SCM restarts a failed service
+const RESTART_DELAY: Duration = Duration::from_secs(10);
+
+/// Command run by the service; receives a signal when the SCM stops it
+type Work = Box<dyn FnOnce(oneshot::Receiver<()>) -> Result<()> + Send>;
+
+/// Service name and command handed from [`run`] to the dispatcher's thread
+static SERVICE: Mutex<Option<(String, Work)>> = Mutex::new(None);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Registers the service `name` running sentri with `args`
+///
+/// # Arguments
+/// * `name` - Name of the service, e.g. for `sc start`
+/// * `executable` - Absolute path of the sentri binary
+/// * `account` - Account the service runs as; LocalSystem when `None`
+/// * `args` - Arguments of the service, e.g. `serve --state-dir C:\ProgramData\sentri`
+pub fn install(
+    name: &str,
+    executable: &Path,
+    account: Option<&str>,
+    args: &[String],
+) -> Result<()> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .context("Failed to connect to the Service Control Manager")?;
+
+    let launch_arguments = ["service", "run", "--name", name, "--"]
+        .into_iter()
+        .map(OsString::from)
+        .chain(args.iter().map(OsString::from))
+        .collect();
+    let info = ServiceInfo {
+        name: name.into(),
+        display_name: format!("Sentri MDI discovery ({})", name).into(),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: executable.to_path_buf(),
+        launch_arguments,
+        dependencies: Vec::new(),
+        account_name: account.map(OsString::from),
+        account_password: None,
+    };
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG | ServiceAccess::START)
+        .with_context(|| format!("Failed to register Windows service {}", name))?;
+    service.set_description("Discovers Microsoft Defender for Identity instances")?;
+    service.update_failure_actions(ServiceFailureActions {
+        reset_period: ServiceFailureResetPeriod::After(Duration::from_secs(24 * 60 * 60)),
+        reboot_msg: None,
+        command: None,
+        actions: Some(vec![
+            ServiceAction {
+                action_type: ServiceActionType::Restart,
+                delay: RESTART_DELAY,
+            };
+            3
+        ]),
+    })?;
+    Ok(())
+}
+
+/// Removes the service `name` registered by [`install`]
+pub fn uninstall(name: &str) -> Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .context("Failed to connect to the Service Control Manager")?;
+    let service = manager
+        .open_service(name, ServiceAccess::DELETE)
+        .with_context(|| format!("Failed to open Windows service {}", name))?;
+    service
+        .delete()
+        .with_context(|| format!("Failed to remove Windows service {}", name))
+}
+
+/// Runs `work` as the service `name` until it returns or the SCM stops it
+///
+/// Blocks the calling thread, which must be the main thread of a process
+/// started by the SCM.
+pub fn run<F>(name: &str, work: F) -> Result<()>
+where
+    F: FnOnce(oneshot::Receiver<()>) -> Result<()> + Send + 'static,
+{
+    *SERVICE.lock().unwrap_or_else(|e| e.into_inner()) = Some((name.to_string(), Box::new(work)));
+    service_dispatcher::start(name, ffi_service_main).context(
+        "Failed to connect to the Service Control Manager; start the service with sc start",
+    )
+}
+
+/// Entry point the dispatcher calls on its own thread
+fn service_main(_arguments: Vec<OsString>) {
+    let Some((name, work)) = SERVICE.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return;
+    };
+    if let Err(e) = run_service(&name, work) {
+        error!("Windows service {} failed: {:#}", name, e);
+    }
+}
+
+fn run_service(name: &str, work: Work) -> Result<()> {
+    let (stop, stopped) = oneshot::channel();
+    let stop = Mutex::new(Some(stop));
+    let status = service_control_handler::register(name, move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            if let Some(stop) = stop.lock().unwrap_or_else(|e| e.into_inner()).take() {
+                let _ = stop.send(());
+            }
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })
+    .context("Failed to register the service control handler")?;
+
+    status.set_service_status(service_status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        ServiceExitCode::Win32(0),
+    ))?;
+    let outcome = work(stopped);
+    let exit_code = match outcome {
+        Ok(()) => ServiceExitCode::Win32(0),
+        Err(_) => ServiceExitCode::ServiceSpecific(1),
+    };
+    status.set_service_status(service_status(
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        exit_code,
+    ))?;
+    outcome
+}
+
+fn service_status(
+    current_state: ServiceState,
+    controls_accepted: ServiceControlAccept,
+    exit_code: ServiceExitCode,
+) -> ServiceStatus {
+    ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state,
+        controls_accepted,
+        exit_code,
+        checkpoint: 0,
+        wait_hint: Duration::ZERO,
+        process_id: None,
+    }
+}
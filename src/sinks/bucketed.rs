@@ -0,0 +1,75 @@
+//! Timestamp bucketing of results shared outside the organization
+//!
+//! With `--timestamp-bucket <AGE>`, the `checked_at` and `completed_at`
+//! timestamps of every exported result are rounded down to the start of
+//! their bucket (see [`crate::time::bucket_timestamp`]), e.g. to the hour
+//! with `1h`, so result sets reveal when a scan ran only at that precision.
+//! Processing times are kept, since they describe the remote services rather
+//! than the operator.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+
+use super::ResultSink;
+use crate::core::DomainResult;
+use crate::time::bucket_timestamp;
+
+/// Copy of `result` with its timestamps rounded down to `bucket`
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use sentri::core::DomainResult;
+/// use sentri::sinks::bucketed::bucket_result;
+/// use std::time::Duration;
+///
+/// let result = DomainResult {
+///     checked_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 34, 56).unwrap(),
+///     completed_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 34, 57).unwrap(),
+///     ..Default::default()
+/// };
+/// let bucketed = bucket_result(&result, Duration::from_secs(86_400));
+/// assert_eq!(bucketed.checked_at, Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap());
+/// assert_eq!(bucketed.completed_at, bucketed.checked_at);
+/// ```
+pub fn bucket_result(result: &DomainResult, bucket: Duration) -> DomainResult {
+    DomainResult {
+        checked_at: bucket_timestamp(result.checked_at, bucket),
+        completed_at: bucket_timestamp(result.completed_at, bucket),
+        ..result.clone()
+    }
+}
+
+/// Sink rounding the timestamps of every result before passing it on
+pub struct BucketedSink {
+    inner: Box<dyn ResultSink>,
+    bucket: Duration,
+}
+
+impl BucketedSink {
+    /// Wraps `inner`, rounding timestamps down to `bucket`
+    pub fn new(inner: Box<dyn ResultSink>, bucket: Duration) -> Self {
+        Self { inner, bucket }
+    }
+}
+
+#[async_trait]
+impl ResultSink for BucketedSink {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.inner.write(&bucket_result(result, self.bucket)).await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.inner.flush().await
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.inner.close().await
+    }
+}
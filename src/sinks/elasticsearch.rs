@@ -0,0 +1,291 @@
+//! Elasticsearch / OpenSearch bulk result sink
+//!
+//! Indexes sanitized results through the `_bulk` API so teams that centralize
+//! scan data in Elastic can query findings as soon as each chunk completes.
+//!
+//! - Index names may contain strftime placeholders (e.g. `sentri-%Y.%m.%d`)
+//!   which are expanded in UTC when each batch is delivered
+//! - Documents use the scanned domain as `_id`, so re-scans overwrite earlier
+//!   findings instead of duplicating them
+//! - Basic and API-key authentication are supported
+//!
+//! # Security Considerations
+//!
+//! - Credentials are only sent in the `Authorization` header over HTTPS
+//!   (security:network:validate_ssl_certs)
+//! - Per-item bulk failures are surfaced as errors instead of being silently dropped
+//!   (rust:errors:proper_error_context)
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde_json::json;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::cli::SinkArgs;
+use crate::core::DomainResult;
+use crate::http::HttpClient;
+use crate::sinks::ResultSink;
+
+/// Default index results are written to
+pub const DEFAULT_INDEX: &str = "sentri-results";
+
+/// Default number of results delivered per bulk request
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Authentication for an Elasticsearch or OpenSearch cluster
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElasticsearchAuth {
+    /// No authentication
+    None,
+    /// HTTP basic authentication
+    Basic {
+        /// User name
+        username: String,
+        /// Password
+        password: String,
+    },
+    /// Elasticsearch API key (base64-encoded `id:api_key`)
+    ApiKey(String),
+}
+
+impl ElasticsearchAuth {
+    /// Returns the `Authorization` header value, if any
+    fn header_value(&self) -> Option<String> {
+        match self {
+            Self::None => None,
+            Self::Basic { username, password } => Some(format!(
+                "Basic {}",
+                BASE64.encode(format!("{}:{}", username, password))
+            )),
+            Self::ApiKey(key) => Some(format!("ApiKey {}", key)),
+        }
+    }
+}
+
+/// Result sink indexing batches of results through the `_bulk` API
+///
+/// # Examples
+///
+/// ```
+/// use sentri::sinks::{ElasticsearchAuth, ElasticsearchSink};
+/// use std::time::Duration;
+///
+/// # fn example() -> anyhow::Result<()> {
+/// let sink = ElasticsearchSink::new(
+///     "https://elastic.example.com:9200",
+///     "sentri-%Y.%m.%d",
+///     ElasticsearchAuth::ApiKey("base64-key".to_string()),
+///     Duration::from_secs(10),
+/// )?
+/// .with_batch_size(1000);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ElasticsearchSink {
+    client: HttpClient,
+    base_url: String,
+    index: String,
+    auth: ElasticsearchAuth,
+    batch_size: usize,
+    buffer: Vec<DomainResult>,
+}
+
+impl ElasticsearchSink {
+    /// Creates a new Elasticsearch sink
+    ///
+    /// # Arguments
+    /// * `base_url` - Cluster URL, e.g. `https://elastic.example.com:9200`
+    /// * `index` - Target index name, optionally containing strftime placeholders
+    /// * `auth` - Cluster authentication
+    /// * `timeout` - Request timeout for bulk calls
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The sink or error if the HTTP client could not be created
+    pub fn new(
+        base_url: &str,
+        index: &str,
+        auth: ElasticsearchAuth,
+        timeout: Duration,
+    ) -> Result<Self> {
+        crate::egress::allow_url(base_url);
+        let client = HttpClient::builder()
+            .timeout(timeout)
+            .http2_prior_knowledge(false)
+            .build()?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            index: index.to_string(),
+            auth,
+            batch_size: DEFAULT_BATCH_SIZE,
+            buffer: Vec::with_capacity(DEFAULT_BATCH_SIZE),
+        })
+    }
+
+    /// Creates a sink from command-line options
+    ///
+    /// # Returns
+    /// * `Result<Option<Self>>` - The sink, or None if no cluster URL was configured
+    pub fn from_args(args: &SinkArgs, timeout: Duration) -> Result<Option<Self>> {
+        let Some(url) = &args.es_url else {
+            return Ok(None);
+        };
+
+        let auth = match (&args.es_api_key, &args.es_username, &args.es_password) {
+            (Some(key), _, _) => ElasticsearchAuth::ApiKey(key.clone()),
+            (None, Some(username), Some(password)) => ElasticsearchAuth::Basic {
+                username: username.clone(),
+                password: password.clone(),
+            },
+            _ => ElasticsearchAuth::None,
+        };
+
+        let mut sink = Self::new(
+            url,
+            args.es_index.as_deref().unwrap_or(DEFAULT_INDEX),
+            auth,
+            timeout,
+        )?;
+        if let Some(batch_size) = args.es_batch_size {
+            sink = sink.with_batch_size(batch_size);
+        }
+
+        Ok(Some(sink))
+    }
+
+    /// Sets the number of results delivered per bulk request
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Expands strftime placeholders in the configured index name
+    ///
+    /// # Returns
+    /// * `String` - The concrete index name for the current UTC time
+    pub fn resolve_index(&self) -> String {
+        if self.index.contains('%') {
+            chrono::Utc::now().format(&self.index).to_string()
+        } else {
+            self.index.clone()
+        }
+    }
+}
+
+#[async_trait]
+impl ResultSink for ElasticsearchSink {
+    fn name(&self) -> &str {
+        "elasticsearch"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.buffer.push(result.clone());
+        if self.buffer.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let body = bulk_body(&self.resolve_index(), &self.buffer)?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson"),
+        );
+        if let Some(value) = self.auth.header_value() {
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(&value)?);
+        }
+
+        let response = self
+            .client
+            .post(&format!("{}/_bulk", self.base_url), headers, &body)
+            .await
+            .context("Failed to deliver results to Elasticsearch")?;
+
+        check_bulk_response(&response)?;
+
+        debug!("Indexed {} results in Elasticsearch", self.buffer.len());
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+/// Builds an NDJSON `_bulk` request body indexing each result by domain
+///
+/// # Arguments
+/// * `index` - Concrete index name
+/// * `results` - Results to index
+///
+/// # Returns
+/// * `Result<String>` - The request body, terminated by a newline as required by `_bulk`
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::sinks::elasticsearch::bulk_body;
+///
+/// let result = DomainResult { domain: "example.com".to_string(), ..Default::default() };
+/// let body = bulk_body("sentri", &[result]).unwrap();
+/// assert_eq!(body.lines().count(), 2);
+/// assert!(body.ends_with('\n'));
+/// ```
+pub fn bulk_body(index: &str, results: &[DomainResult]) -> Result<String> {
+    let mut body = String::new();
+    for result in results {
+        let action = json!({ "index": { "_index": index, "_id": result.domain } });
+        body.push_str(&serde_json::to_string(&action)?);
+        body.push('\n');
+        body.push_str(&serde_json::to_string(result)?);
+        body.push('\n');
+    }
+    Ok(body)
+}
+
+/// Inspects a `_bulk` response for per-item failures
+///
+/// The bulk API answers with HTTP 200 even when individual documents are
+/// rejected, so the `errors` flag must be checked explicitly.
+///
+/// # Arguments
+/// * `response` - Raw JSON response body
+///
+/// # Returns
+/// * `Result<()>` - Ok if every item was indexed, error describing the first failure otherwise
+pub fn check_bulk_response(response: &str) -> Result<()> {
+    let value: serde_json::Value =
+        serde_json::from_str(response).context("Invalid Elasticsearch bulk response")?;
+
+    if !value["errors"].as_bool().unwrap_or(false) {
+        return Ok(());
+    }
+
+    let items = value["items"].as_array().cloned().unwrap_or_default();
+    let failures: Vec<&serde_json::Value> = items
+        .iter()
+        .filter_map(|item| item.as_object()?.values().next())
+        .filter(|item| item.get("error").is_some())
+        .collect();
+
+    let reason = failures
+        .first()
+        .and_then(|item| item["error"]["reason"].as_str())
+        .unwrap_or("unknown error");
+
+    Err(anyhow!(
+        "Elasticsearch rejected {} of {} documents: {}",
+        failures.len(),
+        items.len(),
+        reason
+    ))
+}
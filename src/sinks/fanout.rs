@@ -0,0 +1,94 @@
+//! Fan-out of results to several output files at once
+//!
+//! `sentri batch --output-file results.jsonl --output-csv results.csv
+//! --output-sqlite results.db` writes every result to all three targets in a
+//! single pass, so consumers wanting different formats do not need the scan
+//! to be re-run or its JSONL output to be converted. [`FanOutSink`] hands
+//! each result to every target in order and names the failing target in
+//! errors.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+use super::ResultSink;
+use crate::core::DomainResult;
+
+/// Sink writing every result to each of several targets
+///
+/// # Examples
+///
+/// ```
+/// # use sentri::core::DomainResult;
+/// # use sentri::sinks::{FanOutSink, ResultSink};
+/// # use anyhow::Result;
+/// #
+/// # async fn example(csv: Box<dyn ResultSink>, jsonl: Box<dyn ResultSink>) -> Result<()> {
+/// let mut sink = FanOutSink::new(vec![jsonl, csv]);
+/// sink.write(&DomainResult { domain: "contoso.com".to_string(), ..Default::default() })
+///     .await?;
+/// sink.close().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FanOutSink {
+    targets: Vec<Box<dyn ResultSink>>,
+}
+
+impl FanOutSink {
+    /// Fans results out to `targets`, written in the given order
+    pub fn new(targets: Vec<Box<dyn ResultSink>>) -> Self {
+        Self { targets }
+    }
+
+    /// Number of targets
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Returns true if results go nowhere
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+}
+
+#[async_trait]
+impl ResultSink for FanOutSink {
+    fn name(&self) -> &str {
+        "fan-out"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        for target in &mut self.targets {
+            target
+                .write(result)
+                .await
+                .with_context(|| format!("Failed to write result to {} output", target.name()))?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        for target in &mut self.targets {
+            target
+                .flush()
+                .await
+                .with_context(|| format!("Failed to flush {} output", target.name()))?;
+        }
+        Ok(())
+    }
+
+    /// Closes every target, even after one of them failed
+    async fn close(&mut self) -> Result<()> {
+        let mut first_error = None;
+        for target in &mut self.targets {
+            let closed = target
+                .close()
+                .await
+                .with_context(|| format!("Failed to close {} output", target.name()));
+            if let Err(e) = closed {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
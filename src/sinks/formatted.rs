@@ -0,0 +1,124 @@
+//! Sink rendering results through an [`crate::output::Formatter`]
+//!
+//! Backs the `json`, `jsonl`, `csv` and `table` formats of `--format` for
+//! both output files and stdout. The formatter's header, such as the CSV
+//! column names, is written when the sink is created, so an empty run still
+//! produces a well-formed file. Output files can be compressed on the fly
+//! (see [`crate::output::compression`]) or streamed to object storage (see
+//! [`crate::output::cloud`]).
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+use super::ResultSink;
+use crate::core::DomainResult;
+use crate::output::cloud::create_object_writer;
+use crate::output::compression::{create_compressed, Compression, OutputWriter};
+use crate::output::Formatter;
+
+/// Sink writing formatted results to a file or stdout
+pub struct FormattedSink {
+    formatter: Box<dyn Formatter>,
+    writer: Option<OutputWriter>,
+}
+
+impl FormattedSink {
+    /// Creates the sink and writes the formatter's header
+    ///
+    /// # Arguments
+    /// * `formatter` - Renders every result
+    /// * `path` - Output file, or `None` to print to stdout
+    pub async fn create(formatter: Box<dyn Formatter>, path: Option<&Path>) -> Result<Self> {
+        let writer = match path {
+            Some(path) => Some(Box::new(
+                File::create(path)
+                    .await
+                    .with_context(|| format!("Failed to create output file {}", path.display()))?,
+            ) as OutputWriter),
+            None => None,
+        };
+        Self::with_writer(formatter, writer).await
+    }
+
+    /// Creates the sink writing a compressed output file
+    ///
+    /// # Arguments
+    /// * `formatter` - Renders every result
+    /// * `path` - Output file
+    /// * `compression` - Compression of the file
+    pub async fn create_compressed(
+        formatter: Box<dyn Formatter>,
+        path: &Path,
+        compression: Compression,
+    ) -> Result<Self> {
+        let writer = create_compressed(path, compression).await?;
+        Self::with_writer(formatter, Some(writer)).await
+    }
+
+    /// Creates the sink streaming results to an object store
+    ///
+    /// # Arguments
+    /// * `formatter` - Renders every result
+    /// * `url` - Object store URL, e.g. `s3://bucket/results.jsonl`
+    /// * `compression` - Compression of the object
+    pub async fn create_object(
+        formatter: Box<dyn Formatter>,
+        url: &str,
+        compression: Option<Compression>,
+    ) -> Result<Self> {
+        let writer = create_object_writer(url, compression).await?;
+        Self::with_writer(formatter, Some(writer)).await
+    }
+
+    async fn with_writer(
+        formatter: Box<dyn Formatter>,
+        writer: Option<OutputWriter>,
+    ) -> Result<Self> {
+        let mut sink = Self { formatter, writer };
+        if let Some(header) = sink.formatter.header() {
+            sink.write_line(&header).await?;
+        }
+        Ok(sink)
+    }
+
+    async fn write_line(&mut self, line: &str) -> Result<()> {
+        match &mut self.writer {
+            Some(writer) => {
+                writer.write_all(line.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+            }
+            None => println!("{}", line),
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ResultSink for FormattedSink {
+    fn name(&self) -> &str {
+        "formatted"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        let line = self.formatter.format(result)?;
+        self.write_line(&line).await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if let Some(writer) = &mut self.writer {
+            writer.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        // Shutting down finishes a compressed stream and completes an upload
+        if let Some(writer) = &mut self.writer {
+            writer.shutdown().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+//! Tenant and domain graph written at the end of a batch
+//!
+//! `--format dot` and `--format graphml` write the graph of `sentri graph`
+//! (see [`crate::graph`]) without a results file in between: tenants are
+//! connected to their scanned and federated domains and to their MDI
+//! instances, ready for Graphviz or Gephi. Nodes and edges are collected in
+//! memory and the graph is written when the sink is closed.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+use super::ResultSink;
+use crate::core::DomainResult;
+use crate::graph::{Graph, GraphFormat};
+
+/// Sink writing the tenant and domain graph of all results it receives
+pub struct GraphSink {
+    format: GraphFormat,
+    path: Option<PathBuf>,
+    graph: Graph,
+}
+
+impl GraphSink {
+    /// Creates a sink writing the graph in `format` to `path`, or stdout when `None`
+    pub fn new(format: GraphFormat, path: Option<&Path>) -> Self {
+        Self {
+            format,
+            path: path.map(Path::to_path_buf),
+            graph: Graph::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl ResultSink for GraphSink {
+    fn name(&self) -> &str {
+        "graph"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.graph.add(result);
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        let rendered = self.graph.render(self.format);
+        match &self.path {
+            Some(path) => tokio::fs::write(path, rendered)
+                .await
+                .with_context(|| format!("Failed to write graph to {}", path.display())),
+            None => {
+                print!("{}", rendered);
+                Ok(())
+            }
+        }
+    }
+}
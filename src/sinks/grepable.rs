@@ -0,0 +1,157 @@
+//! Grepable text output in the style of Nmap and Masscan
+//!
+//! Every result becomes a single line of tab-separated `Key: value` fields,
+//! so shell pipelines can filter results without JSON tooling (tabs shown as
+//! spaces):
+//!
+//! ```text
+//! # sentri 0.1.1 grepable output
+//! Domain: contoso.com  Tenant: contoso  MDI: yes  Instance: contososensorapi.atp.azure.com  Federated: 2  Tags: bu=emea  Error: -
+//! Domain: broken.com  Tenant: -  MDI: no  Instance: -  Federated: 0  Tags: -  Error: timeout
+//! # sentri done: 2 domains, 1 with MDI, 1 errors
+//! ```
+//!
+//! e.g. `grep 'MDI: yes' results.gnmap | cut -f1`. Missing values are written
+//! as `-`, and comment lines start with `#`.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+use super::ResultSink;
+use crate::build_info::LONG_VERSION;
+use crate::core::DomainResult;
+use crate::tags::format_tags;
+
+/// Value written for missing fields
+const MISSING: &str = "-";
+
+/// Formats a result as one grepable line, without the line break
+///
+/// Tabs, line breaks and other control characters in values are replaced by
+/// spaces so a line can never be split.
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::sinks::grepable::grepable_line;
+///
+/// let result = DomainResult {
+///     domain: "example.com".to_string(),
+///     error: Some("DNS\tfailure".to_string()),
+///     ..Default::default()
+/// };
+/// assert_eq!(
+///     grepable_line(&result),
+///     "Domain: example.com\tTenant: -\tMDI: no\tInstance: -\tFederated: 0\tTags: -\tError: DNS failure"
+/// );
+/// ```
+pub fn grepable_line(result: &DomainResult) -> String {
+    let mdi = if result.mdi_instance.is_some() {
+        "yes"
+    } else {
+        "no"
+    };
+    format!(
+        "Domain: {}\tTenant: {}\tMDI: {}\tInstance: {}\tFederated: {}\tTags: {}\tError: {}",
+        field(Some(&result.domain)),
+        field(result.tenant.as_deref()),
+        mdi,
+        field(result.mdi_instance.as_deref()),
+        result.federated_domains.len(),
+        field(Some(&format_tags(&result.tags))),
+        field(result.error.as_deref()),
+    )
+}
+
+fn field(value: Option<&str>) -> String {
+    match value.filter(|value| !value.is_empty()) {
+        Some(value) => value
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect(),
+        None => MISSING.to_string(),
+    }
+}
+
+/// Sink writing grepable lines to a file or stdout
+pub struct GrepableSink {
+    writer: Option<File>,
+    domains: usize,
+    with_mdi: usize,
+    errors: usize,
+}
+
+impl GrepableSink {
+    /// Creates the sink and writes the header line
+    ///
+    /// # Arguments
+    /// * `path` - Output file, or `None` to print to stdout
+    pub async fn create(path: Option<&Path>) -> Result<Self> {
+        let writer = match path {
+            Some(path) => Some(
+                File::create(path)
+                    .await
+                    .with_context(|| format!("Failed to create output file {}", path.display()))?,
+            ),
+            None => None,
+        };
+        let mut sink = Self {
+            writer,
+            domains: 0,
+            with_mdi: 0,
+            errors: 0,
+        };
+        let header = format!("# sentri {} grepable output", LONG_VERSION);
+        sink.write_line(&header).await?;
+        Ok(sink)
+    }
+
+    async fn write_line(&mut self, line: &str) -> Result<()> {
+        match &mut self.writer {
+            Some(writer) => {
+                writer.write_all(line.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+            }
+            None => println!("{}", line),
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ResultSink for GrepableSink {
+    fn name(&self) -> &str {
+        "grepable"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.domains += 1;
+        if result.mdi_instance.is_some() {
+            self.with_mdi += 1;
+        }
+        if result.error.is_some() {
+            self.errors += 1;
+        }
+        self.write_line(&grepable_line(result)).await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if let Some(writer) = &mut self.writer {
+            writer.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        let trailer = format!(
+            "# sentri done: {} domains, {} with MDI, {} errors",
+            self.domains, self.with_mdi, self.errors
+        );
+        self.write_line(&trailer).await?;
+        self.flush().await
+    }
+}
@@ -0,0 +1,201 @@
+//! JUnit XML report sink for CI policy gates
+//!
+//! Maps every scanned domain to a JUnit test case so CI systems (GitHub
+//! Actions, GitLab, Azure DevOps, Jenkins) can display MDI coverage as a test
+//! report and fail pipelines when coverage regresses:
+//!
+//! - A domain passes when its check accepts the result
+//! - A domain fails when the check rejects it, by default when no MDI
+//!   instance was detected
+//! - A domain whose scan failed is reported as an error
+//!
+//! Input tags of a domain become `tag.<key>` properties of its test case.
+//!
+//! JUnit reports are single documents, so test cases are collected in memory
+//! and the report is written when the sink is closed.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use quick_xml::escape::escape;
+use std::path::{Path, PathBuf};
+
+use super::ResultSink;
+use crate::core::DomainResult;
+use crate::tags::Tags;
+
+/// Test suite name used in generated reports
+const SUITE_NAME: &str = "sentri";
+
+/// Class name attached to every test case
+const CLASS_NAME: &str = "sentri.mdi";
+
+/// Decides whether a successfully scanned domain passes
+///
+/// Returns `None` when the result is acceptable, or the failure message.
+pub type JunitCheck = Box<dyn Fn(&DomainResult) -> Option<String> + Send>;
+
+/// Default check: every domain must have an MDI instance
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::sinks::junit::require_mdi;
+///
+/// let result = DomainResult {
+///     domain: "example.com".to_string(),
+///     ..Default::default()
+/// };
+/// assert!(require_mdi(&result).is_some());
+/// ```
+pub fn require_mdi(result: &DomainResult) -> Option<String> {
+    match result.mdi_instance {
+        Some(_) => None,
+        None => Some(format!("No MDI instance detected for {}", result.domain)),
+    }
+}
+
+/// Outcome of a single test case
+enum CaseOutcome {
+    Passed,
+    Failed(String),
+    Errored(String),
+}
+
+/// A collected test case
+struct TestCase {
+    name: String,
+    time_secs: f64,
+    outcome: CaseOutcome,
+    tags: Tags,
+}
+
+/// Sink writing a JUnit XML report when the batch completes
+pub struct JunitSink {
+    path: Option<PathBuf>,
+    check: JunitCheck,
+    cases: Vec<TestCase>,
+}
+
+impl JunitSink {
+    /// Creates a JUnit sink
+    ///
+    /// # Arguments
+    /// * `path` - Report file, or `None` to print the report to stdout
+    pub fn new(path: Option<&Path>) -> Self {
+        Self {
+            path: path.map(Path::to_path_buf),
+            check: Box::new(require_mdi),
+            cases: Vec::new(),
+        }
+    }
+
+    /// Replaces the default MDI presence check with a custom policy
+    pub fn with_check(mut self, check: JunitCheck) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// Renders the collected test cases as a JUnit XML document
+    pub fn render(&self) -> String {
+        let failures = self
+            .cases
+            .iter()
+            .filter(|case| matches!(case.outcome, CaseOutcome::Failed(_)))
+            .count();
+        let errors = self
+            .cases
+            .iter()
+            .filter(|case| matches!(case.outcome, CaseOutcome::Errored(_)))
+            .count();
+        let time: f64 = self.cases.iter().map(|case| case.time_secs).sum();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"{name}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" time=\"{time:.3}\">\n",
+            name = SUITE_NAME,
+            tests = self.cases.len(),
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"{name}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" time=\"{time:.3}\">\n",
+            name = SUITE_NAME,
+            tests = self.cases.len(),
+        ));
+
+        for case in &self.cases {
+            let open = format!(
+                "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                CLASS_NAME,
+                escape(&case.name),
+                case.time_secs
+            );
+            let mut children = String::new();
+            if !case.tags.is_empty() {
+                children.push_str("      <properties>\n");
+                for (key, value) in &case.tags {
+                    children.push_str(&format!(
+                        "        <property name=\"tag.{}\" value=\"{}\"/>\n",
+                        escape(key),
+                        escape(value)
+                    ));
+                }
+                children.push_str("      </properties>\n");
+            }
+            match &case.outcome {
+                CaseOutcome::Passed => {}
+                CaseOutcome::Failed(message) => children.push_str(&format!(
+                    "      <failure message=\"{}\" type=\"MdiPolicy\"/>\n",
+                    escape(message)
+                )),
+                CaseOutcome::Errored(message) => children.push_str(&format!(
+                    "      <error message=\"{}\" type=\"ScanError\"/>\n",
+                    escape(message)
+                )),
+            }
+            if children.is_empty() {
+                xml.push_str(&format!("{}/>\n", open));
+            } else {
+                xml.push_str(&format!("{}>\n{}    </testcase>\n", open, children));
+            }
+        }
+
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+#[async_trait]
+impl ResultSink for JunitSink {
+    fn name(&self) -> &str {
+        "junit"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        let outcome = match &result.error {
+            Some(error) => CaseOutcome::Errored(error.clone()),
+            None => match (self.check)(result) {
+                Some(message) => CaseOutcome::Failed(message),
+                None => CaseOutcome::Passed,
+            },
+        };
+
+        self.cases.push(TestCase {
+            name: result.domain.clone(),
+            time_secs: result.processing_time_ms as f64 / 1000.0,
+            outcome,
+            tags: result.tags.clone(),
+        });
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        let report = self.render();
+        match &self.path {
+            Some(path) => tokio::fs::write(path, report)
+                .await
+                .with_context(|| format!("Failed to write JUnit report {}", path.display()))?,
+            None => print!("{}", report),
+        }
+        Ok(())
+    }
+}
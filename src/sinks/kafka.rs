@@ -0,0 +1,320 @@
+//! Kafka producer result sink
+//!
+//! Publishes every sanitized result as one message to a Kafka topic, so
+//! downstream enrichment services can consume findings while a long batch is
+//! still running:
+//!
+//! ```text
+//! sentri batch --input-file domains.txt \
+//!   --kafka-brokers kafka-1.internal:9093,kafka-2.internal:9093 \
+//!   --kafka-topic sentri.results --kafka-tls \
+//!   --kafka-sasl-mechanism SCRAM-SHA-512 --kafka-sasl-username sentri \
+//!   --kafka-sasl-password file:/run/secrets/kafka
+//! ```
+//!
+//! - The message key is the domain and the value the result as compact JSON
+//! - Keys are assigned to partitions with murmur2, the way the Java client's
+//!   default partitioner does, so every result of a domain lands in the same
+//!   partition as with any other producer
+//! - Results are buffered and produced in batches; a batch only counts as
+//!   delivered once all in-sync replicas have it, and results whose delivery
+//!   failed stay buffered for the next flush while delivered ones are not
+//!   produced again
+//!
+//! The sink is built on librdkafka, which negotiates protocol versions with
+//! the brokers, and is only available with the `kafka` feature.
+//!
+//! # Security Considerations
+//!
+//! - `--kafka-tls` encrypts the connections and verifies the brokers against
+//!   the system CA store or `--kafka-ca-file` (security:network:validate_ssl_certs);
+//!   without it, SASL credentials are sent in the clear
+//! - Bootstrap brokers are allowlisted for `--strict-egress`; brokers the
+//!   cluster advertises under other names need `--egress-allow` and are
+//!   checked before any result is produced
+//! - Deliveries time out after `--timeout-ms`
+//!   (security:network:timeout_all_requests)
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use futures::future::join_all;
+use rdkafka::config::ClientConfig;
+use rdkafka::error::RDKafkaErrorCode;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::util::Timeout;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::cli::SinkArgs;
+use crate::core::DomainResult;
+use crate::sinks::ResultSink;
+
+/// Default number of results produced per request
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Client identifier sent with every request
+const CLIENT_ID: &str = "sentri";
+
+/// TLS and SASL settings of the broker connections
+#[derive(Debug, Clone, Default)]
+pub struct KafkaSecurity {
+    /// Connect to the brokers over TLS
+    pub tls: bool,
+    /// CA certificates (PEM) verifying the brokers instead of the system store
+    pub ca_file: Option<PathBuf>,
+    /// SASL mechanism: PLAIN, SCRAM-SHA-256 or SCRAM-SHA-512
+    pub sasl_mechanism: Option<String>,
+    /// SASL user name
+    pub sasl_username: Option<String>,
+    /// SASL password
+    pub sasl_password: Option<String>,
+}
+
+impl KafkaSecurity {
+    /// librdkafka `security.protocol` matching the settings
+    fn protocol(&self) -> &'static str {
+        match (self.tls, self.sasl_mechanism.is_some()) {
+            (false, false) => "plaintext",
+            (true, false) => "ssl",
+            (false, true) => "sasl_plaintext",
+            (true, true) => "sasl_ssl",
+        }
+    }
+}
+
+/// Result sink producing one message per result to a Kafka topic
+///
+/// # Examples
+///
+/// ```
+/// use sentri::sinks::KafkaSink;
+/// use std::time::Duration;
+///
+/// let sink = KafkaSink::new(
+///     &["kafka-1.internal:9092".to_string()],
+///     "sentri.results",
+///     Duration::from_secs(10),
+/// )
+/// .with_batch_size(200);
+/// assert_eq!(sink.topic(), "sentri.results");
+/// ```
+pub struct KafkaSink {
+    brokers: Vec<String>,
+    topic: String,
+    timeout: Duration,
+    batch_size: usize,
+    security: KafkaSecurity,
+    buffer: Vec<DomainResult>,
+    producer: Option<FutureProducer>,
+}
+
+impl KafkaSink {
+    /// Creates a new Kafka sink
+    ///
+    /// No connection is made until the first results are delivered.
+    ///
+    /// # Arguments
+    /// * `brokers` - Bootstrap brokers as `host:port`
+    /// * `topic` - Topic the results are produced to
+    /// * `timeout` - Timeout of deliveries
+    pub fn new(brokers: &[String], topic: &str, timeout: Duration) -> Self {
+        for broker in brokers {
+            crate::egress::allow_host(host_of(broker));
+        }
+        Self {
+            brokers: brokers.to_vec(),
+            topic: topic.to_string(),
+            timeout,
+            batch_size: DEFAULT_BATCH_SIZE,
+            security: KafkaSecurity::default(),
+            buffer: Vec::with_capacity(DEFAULT_BATCH_SIZE),
+            producer: None,
+        }
+    }
+
+    /// Creates a sink from command-line options
+    ///
+    /// # Returns
+    /// * `Option<Self>` - The sink, or None if no brokers were configured
+    pub fn from_args(args: &SinkArgs, timeout: Duration) -> Option<Self> {
+        let topic = args.kafka_topic.as_deref()?;
+        if args.kafka_brokers.is_empty() {
+            return None;
+        }
+        let mut sink =
+            Self::new(&args.kafka_brokers, topic, timeout).with_security(KafkaSecurity {
+                tls: args.kafka_tls,
+                ca_file: args.kafka_ca_file.clone(),
+                sasl_mechanism: args.kafka_sasl_mechanism.clone(),
+                sasl_username: args.kafka_sasl_username.clone(),
+                sasl_password: args.kafka_sasl_password.clone(),
+            });
+        if let Some(batch_size) = args.kafka_batch_size {
+            sink = sink.with_batch_size(batch_size);
+        }
+        Some(sink)
+    }
+
+    /// Sets the number of results produced per request
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets the TLS and SASL settings of the broker connections
+    pub fn with_security(mut self, security: KafkaSecurity) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Topic the results are produced to
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// librdkafka configuration of the producer
+    fn client_config(&self) -> ClientConfig {
+        let timeout_ms = self
+            .timeout
+            .as_millis()
+            .clamp(1, i32::MAX as u128)
+            .to_string();
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", self.brokers.join(","))
+            .set("client.id", CLIENT_ID)
+            .set("acks", "all")
+            .set("enable.idempotence", "true")
+            .set("partitioner", "murmur2_random")
+            .set("request.timeout.ms", &timeout_ms)
+            .set("message.timeout.ms", &timeout_ms)
+            .set("security.protocol", self.security.protocol());
+        if let Some(ca_file) = &self.security.ca_file {
+            config.set("ssl.ca.location", ca_file.display().to_string());
+        }
+        if let Some(mechanism) = &self.security.sasl_mechanism {
+            config.set("sasl.mechanism", mechanism);
+        }
+        if let Some(username) = &self.security.sasl_username {
+            config.set("sasl.username", username);
+        }
+        if let Some(password) = &self.security.sasl_password {
+            config.set("sasl.password", password);
+        }
+        config
+    }
+
+    /// Creates the producer once the brokers of the cluster are known to be
+    /// reachable under offline mode and the egress allowlist
+    async fn connect(&self) -> Result<FutureProducer> {
+        for broker in &self.brokers {
+            let operation = format!("connect to Kafka broker {}", broker);
+            crate::offline::ensure_online(&operation)?;
+            crate::egress::ensure_allowed(host_of(broker), &operation)?;
+        }
+        let producer: FutureProducer = self
+            .client_config()
+            .create()
+            .context("Failed to create the Kafka producer")?;
+
+        let (client, topic, timeout) = (producer.clone(), self.topic.clone(), self.timeout);
+        let brokers = tokio::task::spawn_blocking(move || {
+            let metadata = client
+                .client()
+                .fetch_metadata(Some(&topic), timeout)
+                .context("Failed to read Kafka cluster metadata")?;
+            if let Some(error) = metadata.topics().first().and_then(|topic| topic.error()) {
+                bail!(
+                    "Kafka topic {} is unavailable: {}",
+                    topic,
+                    RDKafkaErrorCode::from(error)
+                );
+            }
+            Ok(metadata
+                .brokers()
+                .iter()
+                .map(|broker| format!("{}:{}", broker.host(), broker.port()))
+                .collect::<Vec<_>>())
+        })
+        .await
+        .context("Kafka metadata task failed")??;
+        for broker in &brokers {
+            let operation = format!("connect to Kafka broker {}", broker);
+            crate::egress::ensure_allowed(host_of(broker), &operation)?;
+        }
+        Ok(producer)
+    }
+}
+
+#[async_trait]
+impl ResultSink for KafkaSink {
+    fn name(&self) -> &str {
+        "kafka"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.buffer.push(result.clone());
+        if self.buffer.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let producer = match &self.producer {
+            Some(producer) => producer.clone(),
+            None => {
+                let producer = self.connect().await.with_context(|| {
+                    format!("Failed to produce results to Kafka topic {}", self.topic)
+                })?;
+                self.producer.insert(producer).clone()
+            }
+        };
+
+        let payloads = self
+            .buffer
+            .iter()
+            .map(serde_json::to_vec)
+            .collect::<Result<Vec<_>, _>>()?;
+        let buffer = std::mem::take(&mut self.buffer);
+        let deliveries = join_all(buffer.iter().zip(&payloads).map(|(result, payload)| {
+            let record = FutureRecord::to(&self.topic)
+                .key(&result.domain)
+                .payload(payload);
+            producer.send(record, Timeout::After(self.timeout))
+        }))
+        .await;
+
+        let count = buffer.len();
+        let mut error = None;
+        for (result, delivery) in buffer.into_iter().zip(deliveries) {
+            if let Err((e, _)) = delivery {
+                error.get_or_insert(e);
+                self.buffer.push(result);
+            }
+        }
+        if let Some(e) = error {
+            return Err(anyhow::Error::new(e).context(format!(
+                "Failed to produce {} of {} results to Kafka topic {}",
+                self.buffer.len(),
+                count,
+                self.topic
+            )));
+        }
+        debug!("Produced {} results to Kafka topic {}", count, self.topic);
+        Ok(())
+    }
+}
+
+/// Host part of a `host:port` broker address
+fn host_of(broker: &str) -> &str {
+    let host = match broker.rsplit_once(':') {
+        Some((host, _)) => host,
+        None => broker,
+    };
+    host.trim_start_matches('[').trim_end_matches(']')
+}
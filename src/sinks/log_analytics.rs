@@ -0,0 +1,295 @@
+//! Azure Log Analytics / Microsoft Sentinel result sink
+//!
+//! Delivers sanitized results to a Log Analytics workspace so defenders can
+//! query findings directly from Sentinel. Two ingestion paths are supported:
+//!
+//! - The HTTP Data Collector API, authenticated with the workspace ID and shared key
+//! - The Logs Ingestion API, posting to a data collection rule (DCR) stream with a
+//!   bearer token obtained by the caller
+//!
+//! # Security Considerations
+//!
+//! - The shared key is only used to compute the HMAC-SHA256 request signature and is
+//!   never sent over the wire (security:output:error_info_control)
+//! - All requests go through `HttpClient`, which enforces HTTPS, certificate validation
+//!   and timeouts (security:network:validate_ssl_certs)
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use sha2::Sha256;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::cli::SinkArgs;
+use crate::core::DomainResult;
+use crate::http::HttpClient;
+use crate::sinks::ResultSink;
+
+/// Default custom log type for the HTTP Data Collector API
+pub const DEFAULT_LOG_TYPE: &str = "SentriMdi";
+
+/// Default stream name for DCR-based ingestion
+pub const DEFAULT_DCR_STREAM: &str = "Custom-SentriMdi_CL";
+
+/// Default number of results delivered per request
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Authentication and addressing for a Log Analytics workspace
+#[derive(Debug, Clone)]
+pub enum LogAnalyticsAuth {
+    /// HTTP Data Collector API with workspace shared key signing
+    SharedKey {
+        /// Workspace (customer) ID
+        workspace_id: String,
+        /// Base64-encoded primary or secondary workspace key
+        shared_key: String,
+        /// Custom log type; Log Analytics appends `_CL` to form the table name
+        log_type: String,
+    },
+    /// Logs Ingestion API through a data collection rule
+    DataCollectionRule {
+        /// Data collection endpoint URL
+        endpoint: String,
+        /// Immutable ID of the data collection rule
+        rule_id: String,
+        /// Stream declared in the data collection rule
+        stream: String,
+        /// Bearer token for the `https://monitor.azure.com` audience
+        token: String,
+    },
+}
+
+impl LogAnalyticsAuth {
+    /// Derives the ingestion configuration from command-line options
+    ///
+    /// # Returns
+    /// * `Option<Self>` - The configuration, or None if no workspace was configured
+    pub fn from_args(args: &SinkArgs) -> Option<Self> {
+        if let (Some(workspace_id), Some(shared_key)) = (&args.la_workspace_id, &args.la_shared_key)
+        {
+            return Some(Self::SharedKey {
+                workspace_id: workspace_id.clone(),
+                shared_key: shared_key.clone(),
+                log_type: args
+                    .la_log_type
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_LOG_TYPE.to_string()),
+            });
+        }
+
+        if let (Some(endpoint), Some(rule_id), Some(token)) =
+            (&args.la_dce_url, &args.la_dcr_id, &args.la_token)
+        {
+            return Some(Self::DataCollectionRule {
+                endpoint: endpoint.trim_end_matches('/').to_string(),
+                rule_id: rule_id.clone(),
+                stream: args
+                    .la_stream
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_DCR_STREAM.to_string()),
+                token: token.clone(),
+            });
+        }
+
+        None
+    }
+}
+
+/// Result sink posting batches of results to Azure Log Analytics
+///
+/// # Examples
+///
+/// ```
+/// use sentri::sinks::{LogAnalyticsAuth, LogAnalyticsSink};
+/// use std::time::Duration;
+///
+/// # fn example() -> anyhow::Result<()> {
+/// let sink = LogAnalyticsSink::new(
+///     LogAnalyticsAuth::SharedKey {
+///         workspace_id: "00000000-0000-0000-0000-000000000000".to_string(),
+///         shared_key: "c2VjcmV0".to_string(),
+///         log_type: "SentriMdi".to_string(),
+///     },
+///     Duration::from_secs(10),
+/// )?
+/// .with_batch_size(200);
+/// # Ok(())
+/// # }
+/// ```
+pub struct LogAnalyticsSink {
+    client: HttpClient,
+    auth: LogAnalyticsAuth,
+    batch_size: usize,
+    buffer: Vec<DomainResult>,
+}
+
+impl LogAnalyticsSink {
+    /// Creates a new Log Analytics sink
+    ///
+    /// # Arguments
+    /// * `auth` - Workspace addressing and credentials
+    /// * `timeout` - Request timeout for ingestion calls
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The sink or error if the HTTP client could not be created
+    pub fn new(auth: LogAnalyticsAuth, timeout: Duration) -> Result<Self> {
+        match &auth {
+            LogAnalyticsAuth::SharedKey { workspace_id, .. } => {
+                crate::egress::allow_host(&format!("{}.ods.opinsights.azure.com", workspace_id))
+            }
+            LogAnalyticsAuth::DataCollectionRule { endpoint, .. } => {
+                crate::egress::allow_url(endpoint)
+            }
+        }
+        let client = HttpClient::builder()
+            .timeout(timeout)
+            .http2_prior_knowledge(false)
+            .build()?;
+
+        Ok(Self {
+            client,
+            auth,
+            batch_size: DEFAULT_BATCH_SIZE,
+            buffer: Vec::with_capacity(DEFAULT_BATCH_SIZE),
+        })
+    }
+
+    /// Sets the number of results delivered per request
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Builds the request URL and headers for a payload of the given length
+    fn request(&self, content_length: usize) -> Result<(String, HeaderMap)> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let url = match &self.auth {
+            LogAnalyticsAuth::SharedKey {
+                workspace_id,
+                shared_key,
+                log_type,
+            } => {
+                let date = chrono::Utc::now()
+                    .format("%a, %d %b %Y %H:%M:%S GMT")
+                    .to_string();
+                let signature =
+                    shared_key_signature(workspace_id, shared_key, &date, content_length)?;
+
+                headers.insert("Log-Type", HeaderValue::from_str(log_type)?);
+                headers.insert("x-ms-date", HeaderValue::from_str(&date)?);
+                headers.insert(AUTHORIZATION, HeaderValue::from_str(&signature)?);
+
+                format!(
+                    "https://{}.ods.opinsights.azure.com/api/logs?api-version=2016-04-01",
+                    workspace_id
+                )
+            }
+            LogAnalyticsAuth::DataCollectionRule {
+                endpoint,
+                rule_id,
+                stream,
+                token,
+            } => {
+                headers.insert(
+                    AUTHORIZATION,
+                    HeaderValue::from_str(&format!("Bearer {}", token))?,
+                );
+
+                format!(
+                    "{}/dataCollectionRules/{}/streams/{}?api-version=2023-01-01",
+                    endpoint, rule_id, stream
+                )
+            }
+        };
+
+        Ok((url, headers))
+    }
+}
+
+#[async_trait]
+impl ResultSink for LogAnalyticsSink {
+    fn name(&self) -> &str {
+        "log-analytics"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.buffer.push(result.clone());
+        if self.buffer.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let body = serde_json::to_string(&self.buffer)?;
+        let (url, headers) = self.request(body.len())?;
+
+        self.client
+            .post(&url, headers, &body)
+            .await
+            .context("Failed to deliver results to Log Analytics")?;
+
+        debug!("Delivered {} results to Log Analytics", self.buffer.len());
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+/// Computes the `Authorization` header value for the HTTP Data Collector API
+///
+/// The signature is an HMAC-SHA256 over the canonical request string, keyed
+/// with the base64-decoded workspace key.
+///
+/// # Arguments
+/// * `workspace_id` - Workspace (customer) ID
+/// * `shared_key` - Base64-encoded workspace key
+/// * `date` - RFC 1123 date sent in the `x-ms-date` header
+/// * `content_length` - Length of the request body in bytes
+///
+/// # Returns
+/// * `Result<String>` - `SharedKey <workspace>:<signature>` or error if the key is invalid
+///
+/// # Examples
+///
+/// ```
+/// use sentri::sinks::log_analytics::shared_key_signature;
+///
+/// let header = shared_key_signature(
+///     "workspace",
+///     "c2VjcmV0",
+///     "Mon, 04 Apr 2016 08:00:00 GMT",
+///     2,
+/// ).unwrap();
+/// assert!(header.starts_with("SharedKey workspace:"));
+/// ```
+pub fn shared_key_signature(
+    workspace_id: &str,
+    shared_key: &str,
+    date: &str,
+    content_length: usize,
+) -> Result<String> {
+    let key = BASE64
+        .decode(shared_key)
+        .map_err(|_| anyhow!("Log Analytics shared key is not valid base64"))?;
+
+    let string_to_sign = format!(
+        "POST\n{}\napplication/json\nx-ms-date:{}\n/api/logs",
+        content_length, date
+    );
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key)
+        .map_err(|_| anyhow!("Invalid Log Analytics shared key length"))?;
+    mac.update(string_to_sign.as_bytes());
+    let signature = BASE64.encode(mac.finalize().into_bytes());
+
+    Ok(format!("SharedKey {}:{}", workspace_id, signature))
+}
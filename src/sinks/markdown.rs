@@ -0,0 +1,272 @@
+//! Markdown executive summary aggregated per tenant
+//!
+//! `--format markdown` condenses results into a document for pasting into
+//! tickets and wikis: totals first, then one table row per discovered
+//! tenant with its domains, MDI status and the kinds of federated domains:
+//!
+//! ```text
+//! # Sentri MDI Discovery Summary
+//!
+//! | Domains scanned | Tenants | Tenants with MDI | Failed checks |
+//! |---|---|---|---|
+//! | 3 | 1 | 1 | 1 |
+//!
+//! ## Tenants
+//!
+//! | Tenant | Domains | Scanned | MDI | MDI instance | Federation |
+//! |---|---|---|---|---|---|
+//! | contoso | 3 | contoso.com, contoso.de | yes | contososensorapi.atp.azure.com (legacy) | 2 custom, 1 onmicrosoft |
+//!
+//! ## Failed Checks
+//!
+//! - broken.com: timeout
+//! ```
+//!
+//! Tenants are aggregated with a [`TenantReport`], so the tables match
+//! `--tenant-report`. Results are collected in memory and the document is
+//! written when the sink is closed.
+//!
+//! # Security Considerations
+//!
+//! - **Sanitized Output**: Pipes and control characters in values are escaped
+//!   so a value cannot break out of its table cell
+//!   (security:output:sanitize_all_output)
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use super::tenant_report::TenantReport;
+use super::ResultSink;
+use crate::build_info::LONG_VERSION;
+use crate::core::{DomainResult, MdiGeneration};
+
+/// Value written for missing fields
+const MISSING: &str = "-";
+
+/// Kind of a domain federated with a tenant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FederationKind {
+    /// Verified custom domain of the organization
+    Custom,
+    /// Initial `<tenant>.onmicrosoft.com` domain
+    Onmicrosoft,
+    /// Mail routing `<tenant>.mail.onmicrosoft.com` domain
+    MailRouting,
+}
+
+impl FederationKind {
+    /// Classifies a federated domain
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::sinks::markdown::FederationKind;
+    ///
+    /// assert_eq!(FederationKind::of("contoso.com"), FederationKind::Custom);
+    /// assert_eq!(FederationKind::of("contoso.onmicrosoft.com"), FederationKind::Onmicrosoft);
+    /// assert_eq!(FederationKind::of("contoso.mail.onmicrosoft.com"), FederationKind::MailRouting);
+    /// ```
+    pub fn of(domain: &str) -> Self {
+        let domain = domain.to_ascii_lowercase();
+        if domain.ends_with(".mail.onmicrosoft.com") {
+            FederationKind::MailRouting
+        } else if domain.ends_with(".onmicrosoft.com") {
+            FederationKind::Onmicrosoft
+        } else {
+            FederationKind::Custom
+        }
+    }
+
+    /// Name used in the report
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FederationKind::Custom => "custom",
+            FederationKind::Onmicrosoft => "onmicrosoft",
+            FederationKind::MailRouting => "mail routing",
+        }
+    }
+}
+
+/// Results aggregated for the Markdown summary
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::sinks::markdown::MarkdownReport;
+///
+/// let mut report = MarkdownReport::default();
+/// report.add(&DomainResult {
+///     domain: "contoso.com".to_string(),
+///     tenant: Some("contoso".to_string()),
+///     federated_domains: vec!["contoso.com".to_string(), "contoso.onmicrosoft.com".to_string()],
+///     ..Default::default()
+/// });
+///
+/// let markdown = report.render();
+/// assert!(markdown.contains("| contoso | 2 | contoso.com | no | - | 1 custom, 1 onmicrosoft |"));
+/// ```
+#[derive(Default)]
+pub struct MarkdownReport {
+    tenants: TenantReport,
+    federation: BTreeMap<String, BTreeMap<String, FederationKind>>,
+    domains: usize,
+    failures: Vec<(String, String)>,
+}
+
+impl MarkdownReport {
+    /// Adds a result to the summary
+    pub fn add(&mut self, result: &DomainResult) {
+        self.domains += 1;
+        if let Some(error) = &result.error {
+            self.failures.push((result.domain.clone(), error.clone()));
+        }
+        self.tenants.add(result);
+        if let Some(tenant) = &result.tenant {
+            let kinds = self
+                .federation
+                .entry(tenant.to_ascii_lowercase())
+                .or_default();
+            for domain in std::iter::once(&result.domain).chain(&result.federated_domains) {
+                let domain = domain.to_ascii_lowercase();
+                let kind = FederationKind::of(&domain);
+                kinds.insert(domain, kind);
+            }
+        }
+    }
+
+    /// Renders the summary as a Markdown document
+    pub fn render(&self) -> String {
+        let summaries = self.tenants.summaries();
+        let with_mdi = summaries.iter().filter(|summary| summary.mdi).count();
+
+        let mut out = String::from("# Sentri MDI Discovery Summary\n\n");
+        let _ = writeln!(out, "Generated by sentri {}\n", LONG_VERSION);
+        out.push_str("| Domains scanned | Tenants | Tenants with MDI | Failed checks |\n");
+        out.push_str("|---|---|---|---|\n");
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            self.domains,
+            summaries.len(),
+            with_mdi,
+            self.failures.len()
+        );
+
+        out.push_str("\n## Tenants\n\n");
+        if summaries.is_empty() {
+            out.push_str("No tenants were discovered.\n");
+        } else {
+            out.push_str("| Tenant | Domains | Scanned | MDI | MDI instance | Federation |\n");
+            out.push_str("|---|---|---|---|---|---|\n");
+            for summary in &summaries {
+                let instance = match (&summary.mdi_instance, summary.mdi_generation) {
+                    (Some(instance), Some(generation)) => {
+                        format!("{} ({})", instance, generation_name(generation))
+                    }
+                    (Some(instance), None) => instance.clone(),
+                    (None, _) => MISSING.to_string(),
+                };
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} | {} | {} |",
+                    cell(&summary.tenant),
+                    summary.domain_count,
+                    cell(&summary.scanned_domains.join(", ")),
+                    if summary.mdi { "yes" } else { "no" },
+                    cell(&instance),
+                    cell(&self.federation_kinds(&summary.tenant)),
+                );
+            }
+        }
+
+        if !self.failures.is_empty() {
+            out.push_str("\n## Failed Checks\n\n");
+            for (domain, error) in &self.failures {
+                let _ = writeln!(out, "- {}: {}", cell(domain), cell(error));
+            }
+        }
+        out
+    }
+
+    /// Counts of every kind of domain federated with a tenant, e.g. "2 custom, 1 onmicrosoft"
+    fn federation_kinds(&self, tenant: &str) -> String {
+        let mut counts: BTreeMap<FederationKind, usize> = BTreeMap::new();
+        for kind in self
+            .federation
+            .get(tenant)
+            .into_iter()
+            .flat_map(|d| d.values())
+        {
+            *counts.entry(*kind).or_default() += 1;
+        }
+        counts
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind.as_str()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn generation_name(generation: MdiGeneration) -> &'static str {
+    match generation {
+        MdiGeneration::Legacy => "legacy",
+        MdiGeneration::Unified => "unified",
+    }
+}
+
+/// Escapes a value for a table cell or list item
+fn cell(value: &str) -> String {
+    if value.is_empty() {
+        return MISSING.to_string();
+    }
+    value
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .replace('|', "\\|")
+}
+
+/// Sink writing a [`MarkdownReport`] to a file or stdout when closed
+pub struct MarkdownSink {
+    path: Option<PathBuf>,
+    report: MarkdownReport,
+}
+
+impl MarkdownSink {
+    /// Creates a sink writing the summary to `path`, or stdout when `None`
+    pub fn new(path: Option<&Path>) -> Self {
+        Self {
+            path: path.map(Path::to_path_buf),
+            report: MarkdownReport::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl ResultSink for MarkdownSink {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.report.add(result);
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        let markdown = self.report.render();
+        match &self.path {
+            Some(path) => tokio::fs::write(path, markdown)
+                .await
+                .with_context(|| format!("Failed to write Markdown summary {}", path.display())),
+            None => {
+                print!("{}", markdown);
+                Ok(())
+            }
+        }
+    }
+}
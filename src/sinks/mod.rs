@@ -0,0 +1,509 @@
+//! Result sinks for delivering scan findings to files and external services
+//!
+//! Batch processing streams every sanitized `DomainResult` through one or more
+//! sinks. A sink decides how results are serialized and where they end up:
+//!
+//! - Local JSONL files and stdout for interactive use
+//! - JSON, CSV and table renderings of `--format` (see [`crate::output`])
+//! - JSONL files appended to by several processes under a file lock
+//! - JUnit XML reports for CI policy gates
+//! - Grepable one-line-per-domain text for shell pipelines
+//! - NDJSON streams to Unix domain sockets and named pipes of local collectors
+//! - Azure Log Analytics workspaces so findings land directly in Microsoft Sentinel
+//! - Elasticsearch / OpenSearch clusters through the `_bulk` API
+//! - Splunk HTTP Event Collectors
+//! - Kafka topics, one message per result (`kafka` feature)
+//! - Webhooks receiving JSON arrays of results
+//! - A JSONL report with one summary per discovered tenant
+//! - A Markdown executive summary per tenant for tickets and wikis
+//! - A DOT or GraphML graph of tenants and their domains for Graphviz and Gephi
+//! - A CSV usage report per customer for billing
+//! - Apache Parquet files for analytics engines (`parquet` feature)
+//! - Excel workbooks with a results and a tenants sheet (`xlsx` feature)
+//! - A SQLite database upserted per domain for a queryable history (`sqlite` feature)
+//! - A PostgreSQL table upserted per domain for scan data shared between teams (`postgres` feature)
+//!
+//! Several output files of different formats are written in one pass through
+//! a [`FanOutSink`]; a [`RoutingSink`] delivers results only to the
+//! destinations whose rules they match. Any sink can be wrapped in a [`BucketedSink`] to round the
+//! timestamps of results it receives.
+//!
+//! # Security Considerations
+//!
+//! - **Sanitized Output**: Sinks only ever receive results that have passed through
+//!   the sanitize module (security:output:sanitize_all_output)
+//! - **Transport Security**: Remote sinks reuse `HttpClient`, inheriting HTTPS-only
+//!   transport, certificate validation and request timeouts
+//!   (security:network:validate_ssl_certs, security:network:timeout_all_requests)
+//!
+//! # Performance Considerations
+//!
+//! - Remote sinks buffer results and deliver them in batches to keep request
+//!   counts low (mdi:api:respect_api_limits)
+//! - Sinks are flushed after every processed chunk so memory usage stays bounded
+//!   (performance:memory:use_streaming_io)
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::cli::SinkArgs;
+use crate::core::DomainResult;
+use crate::encryption::{LineEncryptor, StorageKey};
+use crate::graph::GraphFormat;
+use crate::output::compression::Compression;
+use crate::output::fields::{FieldSelection, FieldsFormatter};
+use crate::output::{formatter, Formatter};
+use crate::policy::Policy;
+use crate::result_index::IndexWriter;
+use crate::result_reader::truncate_results_file;
+
+pub use crate::output::OutputFormat;
+
+pub mod bucketed;
+pub mod elasticsearch;
+pub mod fanout;
+pub mod formatted;
+pub mod graph;
+pub mod grepable;
+pub mod junit;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod log_analytics;
+pub mod markdown;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod routing;
+pub mod shared;
+pub mod socket;
+pub mod splunk;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod tenant_report;
+pub mod usage_report;
+pub mod webhook;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
+
+pub use bucketed::BucketedSink;
+pub use elasticsearch::{ElasticsearchAuth, ElasticsearchSink};
+pub use fanout::FanOutSink;
+pub use formatted::FormattedSink;
+pub use graph::GraphSink;
+pub use grepable::GrepableSink;
+pub use junit::JunitSink;
+#[cfg(feature = "kafka")]
+pub use kafka::{KafkaSecurity, KafkaSink};
+pub use log_analytics::{LogAnalyticsAuth, LogAnalyticsSink};
+pub use markdown::MarkdownSink;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresSink;
+pub use routing::RoutingSink;
+pub use shared::SharedFileSink;
+pub use socket::SocketSink;
+pub use splunk::SplunkHecSink;
+pub use tenant_report::TenantReportSink;
+pub use usage_report::UsageReportSink;
+pub use webhook::WebhookSink;
+
+/// Destination for sanitized domain results produced by batch processing
+///
+/// Implementations may write each result immediately or buffer them and
+/// deliver them in batches; buffered data must be delivered by `flush`.
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::sinks::ResultSink;
+/// use async_trait::async_trait;
+///
+/// struct CountingSink {
+///     count: usize,
+/// }
+///
+/// #[async_trait]
+/// impl ResultSink for CountingSink {
+///     fn name(&self) -> &str {
+///         "counting"
+///     }
+///
+///     async fn write(&mut self, _result: &DomainResult) -> anyhow::Result<()> {
+///         self.count += 1;
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait ResultSink: Send {
+    /// Short human-readable name used in logs and error context
+    fn name(&self) -> &str;
+
+    /// Accepts a single sanitized result
+    async fn write(&mut self, result: &DomainResult) -> Result<()>;
+
+    /// Delivers any buffered results
+    ///
+    /// Called after every processed chunk.
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Finalizes the output once the batch has completed
+    ///
+    /// Sinks producing a single document, such as JUnit reports, write it
+    /// here. The default implementation delivers any remaining buffered results.
+    async fn close(&mut self) -> Result<()> {
+        self.flush().await
+    }
+}
+
+/// Sink writing one compact JSON object per line to a file
+pub struct JsonlFileSink {
+    path: PathBuf,
+    writer: File,
+    encryptor: Option<LineEncryptor>,
+    index: Option<IndexWriter>,
+}
+
+impl JsonlFileSink {
+    /// Creates (or truncates) the output file
+    ///
+    /// The file is truncated under the lock of
+    /// [`crate::result_reader::truncate_results_file`], so results being read
+    /// from it are not cut short.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the JSONL file to write
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The sink or error if the file could not be created
+    pub async fn create(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .await
+            .context("Failed to create output file")?
+            .into_std()
+            .await;
+        truncate_results_file(&file, path)?;
+        let writer = File::from_std(file);
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer,
+            encryptor: None,
+            index: None,
+        })
+    }
+
+    /// Encrypts every line with the given key (see [`crate::encryption`])
+    ///
+    /// Writes the header line of the encrypted file.
+    pub async fn with_encryption(mut self, key: Arc<StorageKey>) -> Result<Self> {
+        let encryptor = LineEncryptor::new(key);
+        let mut header = encryptor.header();
+        header.push('\n');
+        self.writer.write_all(header.as_bytes()).await?;
+        self.encryptor = Some(encryptor);
+        Ok(self)
+    }
+
+    /// Also writes a sidecar index of the file (see [`crate::result_index`])
+    pub async fn with_index(mut self) -> Result<Self> {
+        self.index = Some(IndexWriter::create(&self.path).await?);
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl ResultSink for JsonlFileSink {
+    fn name(&self) -> &str {
+        "jsonl-file"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        let mut json_line = serde_json::to_string(result)?;
+        if let Some(encryptor) = &mut self.encryptor {
+            json_line = encryptor.encrypt(&json_line)?;
+        }
+        if let Some(index) = &mut self.index {
+            index.record(&result.domain, json_line.len() as u64).await?;
+        }
+        json_line.push('\n');
+        self.writer.write_all(json_line.as_bytes()).await?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        // Flush after each chunk to avoid buffering too much data
+        self.writer.flush().await?;
+        if let Some(index) = &mut self.index {
+            index.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Sink pretty-printing each result as JSON to stdout
+pub struct StdoutSink;
+
+#[async_trait]
+impl ResultSink for StdoutSink {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        println!("{}", serde_json::to_string_pretty(result)?);
+        Ok(())
+    }
+}
+
+/// Creates the primary sink for a batch run
+///
+/// Results go to a JSONL file when an output path is given, otherwise they
+/// are pretty-printed to stdout.
+///
+/// # Arguments
+/// * `output_file` - Optional path of the JSONL output file
+///
+/// # Returns
+/// * `Result<Box<dyn ResultSink>>` - The primary sink
+pub async fn primary_sink(output_file: Option<&Path>) -> Result<Box<dyn ResultSink>> {
+    Ok(match output_file {
+        Some(path) => Box::new(JsonlFileSink::create(path).await?),
+        None => Box::new(StdoutSink),
+    })
+}
+
+/// Creates the JSONL sink for results kept in a state directory
+///
+/// Lines are encrypted when a storage key is configured.
+pub async fn stored_results_sink(
+    path: &Path,
+    key: Option<&Arc<StorageKey>>,
+) -> Result<Box<dyn ResultSink>> {
+    let mut sink = JsonlFileSink::create(path).await?;
+    if let Some(key) = key {
+        sink = sink.with_encryption(key.clone()).await?;
+    }
+    Ok(Box::new(sink))
+}
+
+/// Creates the primary sink for results in the requested format
+///
+/// # Arguments
+/// * `format` - Output format
+/// * `output_file` - Optional output path; stdout is used when omitted
+/// * `policy` - Optional policy deciding JUnit test failures instead of the
+///   default MDI presence check
+///
+/// # Returns
+/// * `Result<Box<dyn ResultSink>>` - The primary sink
+pub async fn format_sink(
+    format: OutputFormat,
+    output_file: Option<&Path>,
+    policy: Option<Policy>,
+) -> Result<Box<dyn ResultSink>> {
+    match format {
+        OutputFormat::Jsonl if output_file.is_some() => primary_sink(output_file).await,
+        OutputFormat::Junit => {
+            let mut sink = JunitSink::new(output_file);
+            if let Some(policy) = policy {
+                sink = sink.with_check(Box::new(move |result| {
+                    let violations = policy.check_result(result);
+                    (!violations.is_empty()).then(|| {
+                        violations
+                            .iter()
+                            .map(|v| format!("{}: {}", v.rule, v.message))
+                            .collect::<Vec<_>>()
+                            .join("; ")
+                    })
+                }));
+            }
+            Ok(Box::new(sink))
+        }
+        OutputFormat::Grepable => Ok(Box::new(GrepableSink::create(output_file).await?)),
+        OutputFormat::Parquet => parquet_sink(output_file).await,
+        OutputFormat::Markdown => Ok(Box::new(MarkdownSink::new(output_file))),
+        OutputFormat::Xlsx => xlsx_sink(output_file).await,
+        OutputFormat::Dot => Ok(Box::new(GraphSink::new(GraphFormat::Dot, output_file))),
+        OutputFormat::Graphml => Ok(Box::new(GraphSink::new(GraphFormat::Graphml, output_file))),
+        format => {
+            let formatter = formatter(format).context("Format has no result formatter")?;
+            Ok(Box::new(
+                FormattedSink::create(formatter, output_file).await?,
+            ))
+        }
+    }
+}
+
+/// Creates the primary sink writing only the `--fields` selection of results
+///
+/// # Errors
+/// * The format is neither `json` nor `jsonl`
+/// * The output file cannot be created
+pub async fn fields_sink(
+    format: OutputFormat,
+    output_file: Option<&Path>,
+    fields: FieldSelection,
+) -> Result<Box<dyn ResultSink>> {
+    let formatter = FieldsFormatter::new(format, fields)?;
+    Ok(Box::new(
+        FormattedSink::create(Box::new(formatter), output_file).await?,
+    ))
+}
+
+/// Creates the primary sink writing a compressed output file
+///
+/// Results are rendered in `format`, or only their `fields` when given,
+/// and streamed through the compressor of `compression`.
+///
+/// # Errors
+/// * The format is a document format without a per-result formatter
+/// * The output file cannot be created
+pub async fn compressed_sink(
+    format: OutputFormat,
+    path: &Path,
+    fields: Option<FieldSelection>,
+    compression: Compression,
+) -> Result<Box<dyn ResultSink>> {
+    let formatter: Box<dyn Formatter> = match fields {
+        Some(fields) => Box::new(FieldsFormatter::new(format, fields)?),
+        None => formatter(format).context(
+            "--compress only supports the json, jsonl, csv, table, cef and leef formats",
+        )?,
+    };
+    Ok(Box::new(
+        FormattedSink::create_compressed(formatter, path, compression).await?,
+    ))
+}
+
+/// Creates the primary sink streaming results to an object store URL
+///
+/// Results are rendered in `format`, or only their `fields` when given, and
+/// compressed with `compression` if set (see [`crate::output::cloud`]).
+///
+/// # Errors
+/// * The format is a document format without a per-result formatter
+/// * The crate was built without the `object-store` feature
+/// * The URL is not a supported object store
+pub async fn object_sink(
+    format: OutputFormat,
+    url: &str,
+    fields: Option<FieldSelection>,
+    compression: Option<Compression>,
+) -> Result<Box<dyn ResultSink>> {
+    let formatter: Box<dyn Formatter> = match fields {
+        Some(fields) => Box::new(FieldsFormatter::new(format, fields)?),
+        None => formatter(format).context(
+            "Object store outputs only support the json, jsonl, csv, table, cef and leef formats",
+        )?,
+    };
+    Ok(Box::new(
+        FormattedSink::create_object(formatter, url, compression).await?,
+    ))
+}
+
+/// Creates the Parquet sink of `--format parquet`
+#[cfg(feature = "parquet")]
+async fn parquet_sink(output_file: Option<&Path>) -> Result<Box<dyn ResultSink>> {
+    let path = output_file.context("Parquet output requires --output-file")?;
+    Ok(Box::new(parquet::ParquetSink::create(path).await?))
+}
+
+/// Creates the Parquet sink of `--format parquet`
+#[cfg(not(feature = "parquet"))]
+async fn parquet_sink(_output_file: Option<&Path>) -> Result<Box<dyn ResultSink>> {
+    Err(anyhow::anyhow!(
+        "Cannot write Parquet output: sentri was built without the parquet feature"
+    ))
+}
+
+/// Creates the workbook sink of `--format xlsx`
+#[cfg(feature = "xlsx")]
+async fn xlsx_sink(output_file: Option<&Path>) -> Result<Box<dyn ResultSink>> {
+    let path = output_file.context("XLSX output requires --output-file")?;
+    Ok(Box::new(xlsx::XlsxSink::create(path).await?))
+}
+
+/// Creates the workbook sink of `--format xlsx`
+#[cfg(not(feature = "xlsx"))]
+async fn xlsx_sink(_output_file: Option<&Path>) -> Result<Box<dyn ResultSink>> {
+    Err(anyhow::anyhow!(
+        "Cannot write XLSX output: sentri was built without the xlsx feature"
+    ))
+}
+
+/// Creates the SQLite store of `--output-sqlite`, encrypted with `key` if given
+#[cfg(feature = "sqlite")]
+pub fn sqlite_sink(path: &Path, key: Option<Arc<StorageKey>>) -> Result<Box<dyn ResultSink>> {
+    Ok(Box::new(sqlite::SqliteSink::open(path, key)?))
+}
+
+/// Creates the SQLite store of `--output-sqlite`, encrypted with `key` if given
+#[cfg(not(feature = "sqlite"))]
+pub fn sqlite_sink(_path: &Path, _key: Option<Arc<StorageKey>>) -> Result<Box<dyn ResultSink>> {
+    Err(anyhow::anyhow!(
+        "Cannot write to SQLite: sentri was built without the sqlite feature"
+    ))
+}
+
+/// Builds the additional remote sinks requested on the command line
+///
+/// # Arguments
+/// * `args` - Sink options parsed from the command line
+/// * `timeout` - Request timeout for sinks that deliver results over HTTP
+///
+/// # Returns
+/// * `Result<Vec<Box<dyn ResultSink>>>` - Configured sinks, possibly empty
+pub fn build_sinks(args: &SinkArgs, timeout: Duration) -> Result<Vec<Box<dyn ResultSink>>> {
+    let mut sinks: Vec<Box<dyn ResultSink>> = Vec::new();
+
+    if let Some(auth) = LogAnalyticsAuth::from_args(args) {
+        let mut sink = LogAnalyticsSink::new(auth, timeout)?;
+        if let Some(batch_size) = args.la_batch_size {
+            sink = sink.with_batch_size(batch_size);
+        }
+        sinks.push(Box::new(sink));
+    }
+
+    if let Some(sink) = ElasticsearchSink::from_args(args, timeout)? {
+        sinks.push(Box::new(sink));
+    }
+
+    if let Some(sink) = SplunkHecSink::from_args(args, timeout)? {
+        sinks.push(Box::new(sink));
+    }
+
+    #[cfg(feature = "kafka")]
+    if let Some(sink) = KafkaSink::from_args(args, timeout) {
+        sinks.push(Box::new(sink));
+    }
+    #[cfg(not(feature = "kafka"))]
+    if !args.kafka_brokers.is_empty() {
+        anyhow::bail!("Cannot produce to Kafka: sentri was built without the kafka feature");
+    }
+
+    #[cfg(feature = "postgres")]
+    if let Some(sink) = PostgresSink::from_args(args, timeout)? {
+        sinks.push(Box::new(sink));
+    }
+    #[cfg(not(feature = "postgres"))]
+    if args.pg_dsn.is_some() {
+        anyhow::bail!("Cannot write to PostgreSQL: sentri was built without the postgres feature");
+    }
+
+    Ok(sinks)
+}
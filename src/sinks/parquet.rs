@@ -0,0 +1,82 @@
+//! Parquet file sink for loading large scans into analytics engines
+//!
+//! Results are encoded by [`crate::output::parquet::ParquetWriter`]; a row
+//! group is written every [`crate::output::parquet::ROW_GROUP_ROWS`] results
+//! and the footer when the sink is closed. Encoding and file I/O run on the
+//! blocking thread pool.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::ResultSink;
+use crate::core::DomainResult;
+use crate::output::parquet::{ParquetWriter, ROW_GROUP_ROWS};
+
+/// Sink writing results to a Parquet file
+pub struct ParquetSink {
+    writer: Option<ParquetWriter<BufWriter<File>>>,
+    buffer: Vec<DomainResult>,
+}
+
+impl ParquetSink {
+    /// Creates (or truncates) the Parquet file at `path`
+    pub async fn create(path: &Path) -> Result<Self> {
+        let file = tokio::fs::File::create(path)
+            .await
+            .with_context(|| format!("Failed to create output file {}", path.display()))?
+            .into_std()
+            .await;
+        let writer = ParquetWriter::new(BufWriter::new(file))?;
+        Ok(Self {
+            writer: Some(writer),
+            buffer: Vec::new(),
+        })
+    }
+
+    fn take_writer(&mut self) -> Result<ParquetWriter<BufWriter<File>>> {
+        self.writer.take().context("Parquet file is already closed")
+    }
+}
+
+#[async_trait]
+impl ResultSink for ParquetSink {
+    fn name(&self) -> &str {
+        "parquet"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.buffer.push(result.clone());
+        if self.buffer.len() >= ROW_GROUP_ROWS {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut writer = self.take_writer()?;
+        let results = std::mem::take(&mut self.buffer);
+        let writer = tokio::task::spawn_blocking(move || -> Result<_> {
+            writer.write(&results)?;
+            Ok(writer)
+        })
+        .await??;
+        self.writer = Some(writer);
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.flush().await?;
+        let writer = self.take_writer()?;
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            writer.finish()?.flush()?;
+            Ok(())
+        })
+        .await?
+    }
+}
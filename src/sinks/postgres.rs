@@ -0,0 +1,417 @@
+//! PostgreSQL result table for scan data shared between teams
+//!
+//! With `--pg-dsn`, every result is upserted into a PostgreSQL table keyed by
+//! domain, so several runners can centralize their findings in one database
+//! and re-scans update the rows of the domains they cover:
+//!
+//! ```text
+//! sentri batch --input-file domains.txt \
+//!   --pg-dsn "postgres://sentri@db.internal/scans?sslmode=require" \
+//!   --pg-table mdi.results
+//! ```
+//!
+//! - The table (`sentri_results` by default, optionally schema-qualified) and
+//!   its tenant index are created on first use if missing
+//! - Columns mirror the SQLite store (see `--output-sqlite`); `federated_domains`
+//!   is a `TEXT[]`, timestamps are `TIMESTAMPTZ` and `result` holds the
+//!   complete result as `JSONB`, e.g. `result->'tags'->>'bu'`
+//! - Results are buffered and upserted with one multi-row `INSERT` per batch
+//!
+//! The DSN takes the key/value or URL form understood by libpq, e.g.
+//! `host=db.internal dbname=scans user=sentri password=...`. The sink is only
+//! built with the `postgres` feature.
+//!
+//! # Security Considerations
+//!
+//! - The DSN usually carries a password: `--pg-dsn` accepts secret references
+//!   (see [`crate::secrets`]) and errors only ever name its hosts and database
+//!   (security:output:error_info_control)
+//! - TLS is negotiated as requested by `sslmode` (`prefer` by default), with
+//!   certificate validation against the system roots
+//!   (security:network:validate_ssl_certs)
+//! - Database hosts are allowlisted for `--strict-egress`; connections and
+//!   statements time out after `--timeout-ms` (security:network:timeout_all_requests)
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use postgres_native_tls::MakeTlsConnector;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio_postgres::config::Host;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, Config};
+use tracing::{debug, warn};
+
+use crate::cli::SinkArgs;
+use crate::core::{DomainResult, MdiGeneration};
+use crate::sinks::ResultSink;
+
+/// Table receiving the results unless `--pg-table` names another
+pub const DEFAULT_TABLE: &str = "sentri_results";
+
+/// Default number of results upserted per statement
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Largest batch whose parameters fit the 65535 bind parameters of a statement
+pub const MAX_BATCH_SIZE: usize = 5000;
+
+/// Columns of the results table, in parameter order
+const COLUMNS: [&str; 13] = [
+    "domain",
+    "tenant",
+    "mdi",
+    "mdi_instance",
+    "mdi_generation",
+    "federated_domains",
+    "processing_time_ms",
+    "error",
+    "error_class",
+    "from_cache",
+    "checked_at",
+    "completed_at",
+    "result",
+];
+
+/// Result sink upserting results into a PostgreSQL table
+///
+/// # Examples
+///
+/// ```
+/// use sentri::sinks::PostgresSink;
+/// use std::time::Duration;
+///
+/// let sink = PostgresSink::new("host=db.internal dbname=scans user=sentri", Duration::from_secs(10))?
+///     .with_table("mdi.results")?
+///     .with_batch_size(200);
+/// assert_eq!(sink.table(), "\"mdi\".\"results\"");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct PostgresSink {
+    config: Config,
+    table: String,
+    index: String,
+    timeout: Duration,
+    batch_size: usize,
+    buffer: Vec<DomainResult>,
+    client: Option<Client>,
+}
+
+impl PostgresSink {
+    /// Creates a new PostgreSQL sink
+    ///
+    /// No connection is made until the first results are delivered.
+    ///
+    /// # Arguments
+    /// * `dsn` - Connection string, in key/value or URL form
+    /// * `timeout` - Timeout of the connection and of every statement
+    ///
+    /// # Errors
+    /// Returns an error if the DSN cannot be parsed; the error does not include it
+    pub fn new(dsn: &str, timeout: Duration) -> Result<Self> {
+        // The parse error may quote the DSN, password included
+        let config = Config::from_str(dsn)
+            .map_err(|_| anyhow::anyhow!("Invalid PostgreSQL DSN given to --pg-dsn"))?;
+        if config.get_hosts().is_empty() {
+            bail!("The PostgreSQL DSN given to --pg-dsn names no host");
+        }
+        for host in config.get_hosts() {
+            if let Host::Tcp(host) = host {
+                crate::egress::allow_host(host);
+            }
+        }
+        let (table, index) = table_names(DEFAULT_TABLE)?;
+        Ok(Self {
+            config,
+            table,
+            index,
+            timeout,
+            batch_size: DEFAULT_BATCH_SIZE,
+            buffer: Vec::with_capacity(DEFAULT_BATCH_SIZE),
+            client: None,
+        })
+    }
+
+    /// Creates a sink from command-line options
+    ///
+    /// # Returns
+    /// * `Result<Option<Self>>` - The sink, or None if no DSN was configured
+    pub fn from_args(args: &SinkArgs, timeout: Duration) -> Result<Option<Self>> {
+        let Some(dsn) = args.pg_dsn.as_deref() else {
+            return Ok(None);
+        };
+        let mut sink = Self::new(dsn, timeout)?;
+        if let Some(table) = &args.pg_table {
+            sink = sink.with_table(table)?;
+        }
+        if let Some(batch_size) = args.pg_batch_size {
+            sink = sink.with_batch_size(batch_size);
+        }
+        Ok(Some(sink))
+    }
+
+    /// Sets the table receiving the results, optionally schema-qualified
+    ///
+    /// # Errors
+    /// Returns an error if the name is empty or has more than two parts
+    pub fn with_table(mut self, table: &str) -> Result<Self> {
+        (self.table, self.index) = table_names(table)?;
+        Ok(self)
+    }
+
+    /// Sets the number of results upserted per statement, at most [`MAX_BATCH_SIZE`]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, MAX_BATCH_SIZE);
+        self
+    }
+
+    /// Quoted name of the table receiving the results
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Hosts and database of the DSN, for messages that must not show its password
+    fn target(&self) -> String {
+        let hosts: Vec<String> = self
+            .config
+            .get_hosts()
+            .iter()
+            .map(|host| match host {
+                Host::Tcp(host) => host.clone(),
+                #[cfg(unix)]
+                Host::Unix(path) => path.display().to_string(),
+            })
+            .collect();
+        match self.config.get_dbname() {
+            Some(dbname) => format!("{}/{}", hosts.join(","), dbname),
+            None => hosts.join(","),
+        }
+    }
+
+    /// Connects to the database and creates the results table if missing
+    async fn connect(&self) -> Result<Client> {
+        let operation = format!("connect to PostgreSQL database {}", self.target());
+        for host in self.config.get_hosts() {
+            // Unix sockets reach a local server
+            if let Host::Tcp(host) = host {
+                crate::offline::ensure_online(&operation)?;
+                crate::egress::ensure_allowed(host, &operation)?;
+            }
+        }
+
+        let tls = MakeTlsConnector::new(
+            native_tls::TlsConnector::new().context("Failed to initialize TLS")?,
+        );
+        let (client, connection) = tokio::time::timeout(self.timeout, self.config.connect(tls))
+            .await
+            .with_context(|| format!("Timed out trying to {}", operation))?
+            .with_context(|| format!("Failed to {}", operation))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                warn!("PostgreSQL connection closed: {}", e);
+            }
+        });
+
+        tokio::time::timeout(
+            self.timeout,
+            client.batch_execute(&schema(&self.table, &self.index)),
+        )
+        .await
+        .context("Timed out creating the PostgreSQL results table")?
+        .with_context(|| format!("Failed to create PostgreSQL table {}", self.table))?;
+        Ok(client)
+    }
+
+    /// Upserts the buffered results in one statement
+    async fn upsert(&mut self) -> Result<()> {
+        let client = match self.client.take() {
+            Some(client) if !client.is_closed() => client,
+            _ => self.connect().await?,
+        };
+
+        let buffer = std::mem::take(&mut self.buffer);
+        // A statement may not update the same row twice, so only the latest
+        // result of a domain is kept
+        let mut seen = HashSet::new();
+        let mut latest: Vec<&DomainResult> = buffer
+            .iter()
+            .rev()
+            .filter(|result| seen.insert(result.domain.as_str()))
+            .collect();
+        latest.reverse();
+
+        let outcome = async {
+            let rows = latest
+                .iter()
+                .map(|result| Row::new(result))
+                .collect::<Result<Vec<_>>>()?;
+            let params: Vec<&(dyn ToSql + Sync)> = rows.iter().flat_map(Row::params).collect();
+            let statement = upsert_statement(&self.table, rows.len());
+            tokio::time::timeout(self.timeout, client.execute(statement.as_str(), &params))
+                .await
+                .context("Timed out upserting results")??;
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+
+        match outcome {
+            Ok(()) => self.client = Some(client),
+            Err(_) => self.buffer = buffer,
+        }
+        outcome
+    }
+}
+
+#[async_trait]
+impl ResultSink for PostgresSink {
+    fn name(&self) -> &str {
+        "postgres"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.buffer.push(result.clone());
+        if self.buffer.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let count = self.buffer.len();
+        self.upsert().await.with_context(|| {
+            format!("Failed to write results to PostgreSQL table {}", self.table)
+        })?;
+        debug!(
+            "Upserted {} results into PostgreSQL table {}",
+            count, self.table
+        );
+        Ok(())
+    }
+}
+
+/// Column values of a result
+struct Row<'a> {
+    domain: &'a str,
+    tenant: Option<&'a str>,
+    mdi: bool,
+    mdi_instance: Option<&'a str>,
+    mdi_generation: Option<&'static str>,
+    federated_domains: &'a [String],
+    processing_time_ms: i64,
+    error: Option<&'a str>,
+    error_class: Option<&'static str>,
+    from_cache: bool,
+    checked_at: DateTime<Utc>,
+    completed_at: DateTime<Utc>,
+    result: serde_json::Value,
+}
+
+impl<'a> Row<'a> {
+    fn new(result: &'a DomainResult) -> Result<Self> {
+        Ok(Self {
+            domain: &result.domain,
+            tenant: result.tenant.as_deref(),
+            mdi: result.mdi_instance.is_some(),
+            mdi_instance: result.mdi_instance.as_deref(),
+            mdi_generation: result.mdi_generation.map(|generation| match generation {
+                MdiGeneration::Legacy => "legacy",
+                MdiGeneration::Unified => "unified",
+            }),
+            federated_domains: &result.federated_domains,
+            processing_time_ms: i64::try_from(result.processing_time_ms).unwrap_or(i64::MAX),
+            error: result.error.as_deref(),
+            error_class: result.error_class.map(|class| class.as_str()),
+            from_cache: result.from_cache,
+            checked_at: result.checked_at,
+            completed_at: result.completed_at,
+            result: serde_json::to_value(result)?,
+        })
+    }
+
+    /// Parameters in the order of [`COLUMNS`]
+    fn params(&self) -> [&(dyn ToSql + Sync); COLUMNS.len()] {
+        [
+            &self.domain,
+            &self.tenant,
+            &self.mdi,
+            &self.mdi_instance,
+            &self.mdi_generation,
+            &self.federated_domains,
+            &self.processing_time_ms,
+            &self.error,
+            &self.error_class,
+            &self.from_cache,
+            &self.checked_at,
+            &self.completed_at,
+            &self.result,
+        ]
+    }
+}
+
+/// Statements creating the results table and its tenant index if missing
+fn schema(table: &str, index: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            domain TEXT PRIMARY KEY,
+            tenant TEXT,
+            mdi BOOLEAN NOT NULL,
+            mdi_instance TEXT,
+            mdi_generation TEXT,
+            federated_domains TEXT[] NOT NULL,
+            processing_time_ms BIGINT NOT NULL,
+            error TEXT,
+            error_class TEXT,
+            from_cache BOOLEAN NOT NULL,
+            checked_at TIMESTAMPTZ NOT NULL,
+            completed_at TIMESTAMPTZ NOT NULL,
+            result JSONB NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS {index} ON {table} (tenant);",
+        table = table,
+        index = index,
+    )
+}
+
+/// Statement upserting `rows` results, replacing the rows of their domains
+fn upsert_statement(table: &str, rows: usize) -> String {
+    let values: Vec<String> = (0..rows)
+        .map(|row| {
+            let params: Vec<String> = (1..=COLUMNS.len())
+                .map(|column| format!("${}", row * COLUMNS.len() + column))
+                .collect();
+            format!("({})", params.join(", "))
+        })
+        .collect();
+    let updates: Vec<String> = COLUMNS[1..]
+        .iter()
+        .map(|column| format!("{column} = EXCLUDED.{column}"))
+        .collect();
+    format!(
+        "INSERT INTO {} ({}) VALUES {} ON CONFLICT (domain) DO UPDATE SET {}",
+        table,
+        COLUMNS.join(", "),
+        values.join(", "),
+        updates.join(", ")
+    )
+}
+
+/// Quoted names of a table given as `table` or `schema.table` and of its tenant index
+fn table_names(table: &str) -> Result<(String, String)> {
+    let parts: Vec<&str> = table.split('.').collect();
+    if parts.len() > 2 || parts.iter().any(|part| part.is_empty()) {
+        bail!("Invalid PostgreSQL table name: {}", table);
+    }
+    let quoted: Vec<String> = parts.iter().map(|part| quote_identifier(part)).collect();
+    // Indices live in the schema of their table and take an unqualified name
+    let index = quote_identifier(&format!("{}_tenant", parts[parts.len() - 1]));
+    Ok((quoted.join("."), index))
+}
+
+/// Quotes an identifier so that any name is taken literally
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
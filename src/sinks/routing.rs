@@ -0,0 +1,240 @@
+//! Routing of results to sinks by tag or finding
+//!
+//! Routes are `[[route]]` sections of the `--config` file. Each names a
+//! destination and the conditions a result must meet to be delivered there;
+//! every condition is optional and all given ones must hold:
+//!
+//! ```toml
+//! # MDI-absent results of EMEA domains page the EMEA team
+//! [[route]]
+//! name = "emea-gaps"
+//! tags = { bu = "emea" }
+//! mdi = false
+//! failed = false
+//! webhook = "https://hooks.example.com/services/emea"
+//!
+//! # Everything else of interest lands in files
+//! [[route]]
+//! name = "unified"
+//! fields = { mdi_generation = "unified" }
+//! file = "routed/unified.jsonl"
+//!
+//! [[route]]
+//! name = "contoso"
+//! domains = ["contoso.com", "*.contoso.com"]
+//! file = "routed/contoso.jsonl"
+//! ```
+//!
+//! - `domains` - exact names or `*.suffix` wildcards, as in policies
+//! - `tags` - input tags the domain must carry (see [`crate::tags`])
+//! - `mdi` - whether an MDI instance was detected
+//! - `failed` - whether the scan failed
+//! - `fields` - result fields, named as in `sentri schema`, that must have
+//!   exactly the given values
+//!
+//! A destination is either a `webhook` URL receiving JSON arrays of results
+//! or a `file` written as JSONL. Routes are evaluated independently, so a
+//! result matching several routes is delivered to each. Routing comes on top
+//! of the output file and the other sinks of a batch, which still receive
+//! every result.
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::webhook::WebhookSink;
+use super::{JsonlFileSink, ResultSink};
+use crate::core::DomainResult;
+use crate::policy::domain_matches;
+use crate::result_schema::domain_result_schema;
+
+/// A routing rule of the `--config` file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    /// Route name used in logs and errors
+    pub name: String,
+    /// Domains the route applies to; exact names or `*.suffix` wildcards
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Tags the domain must carry with exactly these values
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+    /// Whether an MDI instance must (or must not) have been detected
+    pub mdi: Option<bool>,
+    /// Whether the scan must (or must not) have failed
+    pub failed: Option<bool>,
+    /// Result fields that must have exactly these values
+    #[serde(default)]
+    pub fields: BTreeMap<String, Value>,
+    /// Webhook receiving the matching results
+    pub webhook: Option<String>,
+    /// JSONL file receiving the matching results
+    pub file: Option<PathBuf>,
+}
+
+impl Route {
+    /// Returns true if `result` meets every condition of the route
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::core::DomainResult;
+    /// use sentri::sinks::routing::Route;
+    ///
+    /// let route = Route {
+    ///     name: "emea-gaps".to_string(),
+    ///     tags: [("bu".to_string(), "emea".to_string())].into(),
+    ///     mdi: Some(false),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let mut result = DomainResult { domain: "contoso.com".to_string(), ..Default::default() };
+    /// assert!(!route.matches(&result));
+    /// result.tags.insert("bu".to_string(), "emea".to_string());
+    /// assert!(route.matches(&result));
+    /// result.mdi_instance = Some("contososensorapi.atp.azure.com".to_string());
+    /// assert!(!route.matches(&result));
+    /// ```
+    pub fn matches(&self, result: &DomainResult) -> bool {
+        if !self.domains.is_empty()
+            && !self
+                .domains
+                .iter()
+                .any(|pattern| domain_matches(pattern, &result.domain))
+        {
+            return false;
+        }
+        if self
+            .tags
+            .iter()
+            .any(|(key, value)| result.tags.get(key) != Some(value))
+        {
+            return false;
+        }
+        if self
+            .mdi
+            .is_some_and(|mdi| mdi != result.mdi_instance.is_some())
+        {
+            return false;
+        }
+        if self
+            .failed
+            .is_some_and(|failed| failed != result.error.is_some())
+        {
+            return false;
+        }
+        if self.fields.is_empty() {
+            return true;
+        }
+        let Ok(Value::Object(fields)) = serde_json::to_value(result) else {
+            return false;
+        };
+        self.fields
+            .iter()
+            .all(|(field, value)| fields.get(field).unwrap_or(&Value::Null) == value)
+    }
+
+    /// Checks the route and creates the sink of its destination
+    ///
+    /// # Errors
+    /// * The route has no destination or more than one
+    /// * A field condition names no result field
+    /// * The destination cannot be created
+    pub async fn sink(&self, timeout: Duration) -> Result<Box<dyn ResultSink>> {
+        let schema = domain_result_schema();
+        for field in self.fields.keys() {
+            if schema["properties"].get(field).is_none() {
+                bail!(
+                    "Route '{}' matches unknown result field '{}'",
+                    self.name,
+                    field
+                );
+            }
+        }
+        match (&self.webhook, &self.file) {
+            (Some(url), None) => Ok(Box::new(WebhookSink::new(url, timeout)?)),
+            (None, Some(path)) => Ok(Box::new(JsonlFileSink::create(path).await.with_context(
+                || {
+                    format!(
+                        "Failed to create {} for route '{}'",
+                        path.display(),
+                        self.name
+                    )
+                },
+            )?)),
+            (None, None) => bail!("Route '{}' needs a webhook or a file", self.name),
+            (Some(_), Some(_)) => bail!("Route '{}' has both a webhook and a file", self.name),
+        }
+    }
+}
+
+/// Sink delivering every result to the sinks of the routes it matches
+pub struct RoutingSink {
+    routes: Vec<(Route, Box<dyn ResultSink>)>,
+}
+
+impl RoutingSink {
+    /// Creates a sink routing to the given destinations
+    pub fn new(routes: Vec<(Route, Box<dyn ResultSink>)>) -> Self {
+        Self { routes }
+    }
+
+    /// Creates the destinations of the routes of a configuration file
+    ///
+    /// # Errors
+    /// * A route is invalid or its destination cannot be created
+    pub async fn from_routes(routes: &[Route], timeout: Duration) -> Result<Self> {
+        let mut sinks = Vec::with_capacity(routes.len());
+        for route in routes {
+            sinks.push((route.clone(), route.sink(timeout).await?));
+        }
+        Ok(Self::new(sinks))
+    }
+}
+
+#[async_trait]
+impl ResultSink for RoutingSink {
+    fn name(&self) -> &str {
+        "routing"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        for (route, sink) in &mut self.routes {
+            if route.matches(result) {
+                sink.write(result)
+                    .await
+                    .with_context(|| format!("Route '{}' failed", route.name))?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        for (route, sink) in &mut self.routes {
+            sink.flush()
+                .await
+                .with_context(|| format!("Route '{}' failed", route.name))?;
+        }
+        Ok(())
+    }
+
+    /// Closes every destination, even after one of them failed
+    async fn close(&mut self) -> Result<()> {
+        let mut first_error = None;
+        for (route, sink) in &mut self.routes {
+            let closed = sink
+                .close()
+                .await
+                .with_context(|| format!("Route '{}' failed", route.name));
+            if let Err(e) = closed {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
@@ -0,0 +1,93 @@
+//! JSONL output shared by several writers
+//!
+//! When several sentri processes append to the same results file, for
+//! example one batch per shard of a large domain list, their writes must not
+//! interleave. With `sentri batch --output-file <FILE> --append` each process
+//! buffers the results of a chunk and appends them in a single write while
+//! holding an exclusive lock on the file, so every line in the file is a
+//! complete JSON object.
+//!
+//! Locks are advisory (`flock` on Unix, `LockFileEx` on Windows): every
+//! writer of the file has to use `--append`. Should a writer die in the middle
+//! of a line, the next writer starts on a fresh line, leaving a single
+//! truncated line instead of gluing its first result to it.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::fs::OpenOptions;
+
+use super::ResultSink;
+use crate::core::DomainResult;
+
+/// Sink appending JSONL to a file shared with other processes
+pub struct SharedFileSink {
+    file: Arc<File>,
+    buffer: Vec<u8>,
+}
+
+impl SharedFileSink {
+    /// Opens `path` for appending, creating it if needed
+    pub async fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to open shared output file {}", path.display()))?;
+        Ok(Self {
+            file: Arc::new(file.into_std().await),
+            buffer: Vec::new(),
+        })
+    }
+}
+
+/// Appends `lines` under an exclusive lock of `file`
+fn append_locked(file: &File, lines: &[u8]) -> std::io::Result<()> {
+    file.lock()?;
+    let result = (|| {
+        let mut file = file;
+        let length = file.metadata()?.len();
+        if length > 0 {
+            let mut last = [0u8; 1];
+            file.seek(SeekFrom::Start(length - 1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
+        }
+        file.write_all(lines)?;
+        file.flush()
+    })();
+    file.unlock()?;
+    result
+}
+
+#[async_trait]
+impl ResultSink for SharedFileSink {
+    fn name(&self) -> &str {
+        "shared-file"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        serde_json::to_writer(&mut self.buffer, result)?;
+        self.buffer.push(b'\n');
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let lines = std::mem::take(&mut self.buffer);
+        let file = Arc::clone(&self.file);
+        tokio::task::spawn_blocking(move || append_locked(&file, &lines))
+            .await?
+            .context("Failed to append to shared output file")?;
+        Ok(())
+    }
+}
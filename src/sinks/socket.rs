@@ -0,0 +1,85 @@
+//! NDJSON streaming to a Unix domain socket or named pipe
+//!
+//! Local collector agents (Vector, Fluent Bit, custom daemons) usually listen
+//! on a Unix socket or read from a named pipe. With `sentri batch --socket
+//! <PATH>` results are streamed there as compact NDJSON, one object per line,
+//! instead of going to a file or stdout, so nothing has to be written to disk
+//! and picked up again.
+//!
+//! The collector must be listening before the batch starts: sentri connects
+//! to a socket, or opens a named pipe for writing, which waits until the pipe
+//! has a reader. Anything else at the path is rejected.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+
+use super::ResultSink;
+use crate::core::DomainResult;
+
+/// Sink streaming NDJSON to a Unix domain socket or named pipe
+pub struct SocketSink {
+    writer: BufWriter<Box<dyn AsyncWrite + Send + Unpin>>,
+}
+
+impl SocketSink {
+    /// Connects to the socket, or opens the named pipe, at `path`
+    ///
+    /// # Errors
+    /// * Nothing exists at `path`, or it is neither a socket nor a named pipe
+    /// * No process is listening on the socket
+    /// * The platform has no Unix domain sockets
+    pub async fn connect(path: &Path) -> Result<Self> {
+        let writer = open(path)
+            .await
+            .with_context(|| format!("Failed to open result stream {}", path.display()))?;
+        Ok(Self {
+            writer: BufWriter::new(writer),
+        })
+    }
+}
+
+#[cfg(unix)]
+async fn open(path: &Path) -> Result<Box<dyn AsyncWrite + Send + Unpin>> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let file_type = tokio::fs::metadata(path).await?.file_type();
+    if file_type.is_socket() {
+        Ok(Box::new(tokio::net::UnixStream::connect(path).await?))
+    } else if file_type.is_fifo() {
+        let pipe = tokio::fs::OpenOptions::new().write(true).open(path).await?;
+        Ok(Box::new(pipe))
+    } else {
+        anyhow::bail!("Not a Unix socket or named pipe")
+    }
+}
+
+#[cfg(not(unix))]
+async fn open(_path: &Path) -> Result<Box<dyn AsyncWrite + Send + Unpin>> {
+    anyhow::bail!("Unix domain sockets are not supported on this platform")
+}
+
+#[async_trait]
+impl ResultSink for SocketSink {
+    fn name(&self) -> &str {
+        "socket"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        let mut line = serde_json::to_string(result)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.writer.shutdown().await?;
+        Ok(())
+    }
+}
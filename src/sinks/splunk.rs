@@ -0,0 +1,260 @@
+//! Splunk HTTP Event Collector result sink
+//!
+//! Sends sanitized results to a Splunk HTTP Event Collector (HEC) so findings
+//! can be searched in Splunk as soon as each chunk completes.
+//!
+//! - Every result becomes one event, timestamped with the time its check
+//!   started; a request carries a batch of events, concatenated as HEC expects
+//! - `--splunk-hec-url` is the base URL of the collector, e.g.
+//!   `https://splunk.example.com:8088`; the event endpoint is appended unless
+//!   the URL already names a `/services/collector` endpoint
+//! - The source type defaults to `sentri:result`; the index defaults to the
+//!   one assigned to the token
+//!
+//! # Security Considerations
+//!
+//! - The HEC token is only sent in the `Authorization` header over HTTPS
+//!   (security:network:validate_ssl_certs)
+//! - Requests go through `HttpClient`, so throttled or failed deliveries are
+//!   retried with backoff and rejected batches are surfaced as errors
+//!   (rust:errors:proper_error_context)
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde_json::json;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::cli::SinkArgs;
+use crate::core::DomainResult;
+use crate::http::HttpClient;
+use crate::sinks::ResultSink;
+
+/// Default source type of events
+pub const DEFAULT_SOURCETYPE: &str = "sentri:result";
+
+/// Default number of results delivered per request
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Path of the JSON event endpoint of a collector
+const EVENT_PATH: &str = "/services/collector/event";
+
+/// Result sink posting batches of results to a Splunk HTTP Event Collector
+///
+/// # Examples
+///
+/// ```
+/// use sentri::sinks::SplunkHecSink;
+/// use std::time::Duration;
+///
+/// # fn example() -> anyhow::Result<()> {
+/// let sink = SplunkHecSink::new(
+///     "https://splunk.example.com:8088",
+///     "00000000-0000-0000-0000-000000000000",
+///     Duration::from_secs(10),
+/// )?
+/// .with_index("security")
+/// .with_batch_size(200);
+/// assert_eq!(sink.url(), "https://splunk.example.com:8088/services/collector/event");
+/// # Ok(())
+/// # }
+/// ```
+pub struct SplunkHecSink {
+    client: HttpClient,
+    url: String,
+    token: String,
+    index: Option<String>,
+    sourcetype: String,
+    batch_size: usize,
+    buffer: Vec<DomainResult>,
+}
+
+impl SplunkHecSink {
+    /// Creates a new Splunk HEC sink
+    ///
+    /// # Arguments
+    /// * `url` - Collector base URL or full event endpoint URL
+    /// * `token` - HEC token
+    /// * `timeout` - Request timeout for event submissions
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The sink or error if the HTTP client could not be created
+    pub fn new(url: &str, token: &str, timeout: Duration) -> Result<Self> {
+        crate::egress::allow_url(url);
+        let client = HttpClient::builder()
+            .timeout(timeout)
+            .http2_prior_knowledge(false)
+            .build()?;
+
+        let url = url.trim_end_matches('/');
+        let url = if url.contains("/services/collector") {
+            url.to_string()
+        } else {
+            format!("{}{}", url, EVENT_PATH)
+        };
+
+        Ok(Self {
+            client,
+            url,
+            token: token.to_string(),
+            index: None,
+            sourcetype: DEFAULT_SOURCETYPE.to_string(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            buffer: Vec::with_capacity(DEFAULT_BATCH_SIZE),
+        })
+    }
+
+    /// Creates a sink from command-line options
+    ///
+    /// # Returns
+    /// * `Result<Option<Self>>` - The sink, or None if no collector URL was configured
+    pub fn from_args(args: &SinkArgs, timeout: Duration) -> Result<Option<Self>> {
+        let (Some(url), Some(token)) = (&args.splunk_hec_url, &args.splunk_token) else {
+            return Ok(None);
+        };
+
+        let mut sink = Self::new(url, token, timeout)?;
+        if let Some(index) = &args.splunk_index {
+            sink = sink.with_index(index);
+        }
+        if let Some(sourcetype) = &args.splunk_sourcetype {
+            sink = sink.with_sourcetype(sourcetype);
+        }
+        if let Some(batch_size) = args.splunk_batch_size {
+            sink = sink.with_batch_size(batch_size);
+        }
+
+        Ok(Some(sink))
+    }
+
+    /// Sets the index events are written to instead of the token's default
+    pub fn with_index(mut self, index: &str) -> Self {
+        self.index = Some(index.to_string());
+        self
+    }
+
+    /// Sets the source type of events
+    pub fn with_sourcetype(mut self, sourcetype: &str) -> Self {
+        self.sourcetype = sourcetype.to_string();
+        self
+    }
+
+    /// Sets the number of results delivered per request
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Event endpoint the results are posted to
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[async_trait]
+impl ResultSink for SplunkHecSink {
+    fn name(&self) -> &str {
+        "splunk-hec"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.buffer.push(result.clone());
+        if self.buffer.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let body = event_body(&self.buffer, self.index.as_deref(), &self.sourcetype)?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Splunk {}", self.token))?,
+        );
+
+        let response = self
+            .client
+            .post(&self.url, headers, &body)
+            .await
+            .context("Failed to deliver results to Splunk HEC")?;
+
+        check_hec_response(&response)?;
+
+        debug!("Delivered {} results to Splunk HEC", self.buffer.len());
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+/// Builds an HEC request body with one event per result
+///
+/// # Arguments
+/// * `results` - Results to send
+/// * `index` - Target index, or None for the token's default
+/// * `sourcetype` - Source type of the events
+///
+/// # Returns
+/// * `Result<String>` - Newline-separated JSON events
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::sinks::splunk::event_body;
+///
+/// let result = DomainResult { domain: "example.com".to_string(), ..Default::default() };
+/// let body = event_body(&[result], None, "sentri:result").unwrap();
+/// assert_eq!(body.lines().count(), 1);
+/// assert!(body.contains(r#""host":"example.com""#));
+/// ```
+pub fn event_body(
+    results: &[DomainResult],
+    index: Option<&str>,
+    sourcetype: &str,
+) -> Result<String> {
+    let mut body = String::new();
+    for result in results {
+        let mut event = json!({
+            "time": result.checked_at.timestamp_millis() as f64 / 1000.0,
+            "host": result.domain,
+            "source": "sentri",
+            "sourcetype": sourcetype,
+            "event": result,
+        });
+        if let Some(index) = index {
+            event["index"] = json!(index);
+        }
+        body.push_str(&serde_json::to_string(&event)?);
+        body.push('\n');
+    }
+    Ok(body)
+}
+
+/// Inspects an HEC response for a rejected batch
+///
+/// # Arguments
+/// * `response` - Raw JSON response body
+///
+/// # Returns
+/// * `Result<()>` - Ok if the events were accepted, error with the collector's reason otherwise
+pub fn check_hec_response(response: &str) -> Result<()> {
+    let value: serde_json::Value =
+        serde_json::from_str(response).context("Invalid Splunk HEC response")?;
+
+    match value["code"].as_i64() {
+        Some(0) => Ok(()),
+        code => Err(anyhow!(
+            "Splunk HEC rejected the events: {} (code {})",
+            value["text"].as_str().unwrap_or("unknown error"),
+            code.map_or_else(|| "missing".to_string(), |code| code.to_string())
+        )),
+    }
+}
@@ -0,0 +1,352 @@
+//! SQLite result store for a queryable local history
+//!
+//! With `--output-sqlite results.db`, every result is upserted into the
+//! `results` table of a SQLite database, keyed by domain, so re-scans update
+//! the rows of the domains they cover and keep all others:
+//!
+//! ```sql
+//! SELECT tenant, count(*) FROM results WHERE mdi GROUP BY tenant;
+//! SELECT domain, checked_at FROM results WHERE error_class = 'timeout';
+//! ```
+//!
+//! Besides one column per field of interest, `result` holds the complete
+//! result as JSON, including input tags such as
+//! `json_extract(result, '$.tags.bu')`. Domains are indexed through the primary key, tenants
+//! through `results_tenant`. Rows are written in one transaction per chunk.
+//!
+//! With `--storage-key`, results are instead kept in `encrypted_results`,
+//! one row per domain named by a keyed hash of the domain and holding the
+//! encrypted JSON (see [`crate::encryption`]). Rows are bound to their
+//! database and domain, so they cannot be moved between either, and the
+//! database can only be queried through sentri. A database is either
+//! encrypted or not; it cannot be written with and without the key.
+//!
+//! The store bundles SQLite through `rusqlite` and is only built with the
+//! `sqlite` feature. Statements run on the blocking thread pool, never on
+//! the scan's async workers.
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use super::ResultSink;
+use crate::core::DomainResult;
+use crate::encryption::{new_file_id, FileId, StorageKey};
+
+/// Creates the results table and its indices if missing
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS results (
+        domain TEXT PRIMARY KEY NOT NULL,
+        tenant TEXT,
+        mdi INTEGER NOT NULL,
+        mdi_instance TEXT,
+        mdi_generation TEXT,
+        federated_domains TEXT NOT NULL,
+        processing_time_ms INTEGER NOT NULL,
+        error TEXT,
+        error_class TEXT,
+        from_cache INTEGER NOT NULL,
+        checked_at TEXT NOT NULL,
+        completed_at TEXT NOT NULL,
+        result TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS results_tenant ON results (tenant);
+";
+
+/// Creates the tables of an encrypted store if missing
+const ENCRYPTED_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sentri_store (
+        name TEXT PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS encrypted_results (
+        id TEXT PRIMARY KEY NOT NULL,
+        result TEXT NOT NULL
+    );
+";
+
+/// Inserts an encrypted result or replaces the row of its domain
+const ENCRYPTED_UPSERT: &str = "
+    INSERT INTO encrypted_results (id, result) VALUES (?1, ?2)
+    ON CONFLICT (id) DO UPDATE SET result = excluded.result
+";
+
+/// Plaintext sealed in `sentri_store` to detect a wrong key on open
+const KEY_CHECK: &[u8] = b"sentri storage key check";
+
+/// Inserts a result or replaces the row of its domain
+const UPSERT: &str = "
+    INSERT INTO results (
+        domain, tenant, mdi, mdi_instance, mdi_generation, federated_domains,
+        processing_time_ms, error, error_class, from_cache, checked_at,
+        completed_at, result
+    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+    ON CONFLICT (domain) DO UPDATE SET
+        tenant = excluded.tenant,
+        mdi = excluded.mdi,
+        mdi_instance = excluded.mdi_instance,
+        mdi_generation = excluded.mdi_generation,
+        federated_domains = excluded.federated_domains,
+        processing_time_ms = excluded.processing_time_ms,
+        error = excluded.error,
+        error_class = excluded.error_class,
+        from_cache = excluded.from_cache,
+        checked_at = excluded.checked_at,
+        completed_at = excluded.completed_at,
+        result = excluded.result
+";
+
+/// Encrypts and decrypts the rows of one encrypted store
+struct RowCipher {
+    key: Arc<StorageKey>,
+    file_id: FileId,
+}
+
+impl RowCipher {
+    /// Reads the identifier of the store, creating it in a new store
+    fn open(connection: &Connection, key: Arc<StorageKey>, path: &Path) -> Result<Self> {
+        let stored: Option<String> = connection
+            .query_row(
+                "SELECT value FROM sentri_store WHERE name = 'file_id'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(stored) = stored else {
+            let file_id = new_file_id();
+            let check = key.encrypt(KEY_CHECK, &file_id)?;
+            connection.execute(
+                "INSERT INTO sentri_store (name, value) VALUES ('file_id', ?1), ('key_check', ?2)",
+                params![BASE64.encode(file_id), BASE64.encode(check)],
+            )?;
+            return Ok(Self { key, file_id });
+        };
+        let file_id = BASE64
+            .decode(stored)
+            .ok()
+            .and_then(|id| FileId::try_from(id).ok())
+            .with_context(|| format!("Invalid store identifier in {}", path.display()))?;
+        let check: String = connection.query_row(
+            "SELECT value FROM sentri_store WHERE name = 'key_check'",
+            [],
+            |row| row.get(0),
+        )?;
+        BASE64
+            .decode(check)
+            .context("Invalid key check")
+            .and_then(|check| key.decrypt(&check, &file_id))
+            .with_context(|| {
+                format!(
+                    "{} was encrypted with a different storage key",
+                    path.display()
+                )
+            })?;
+        Ok(Self { key, file_id })
+    }
+
+    fn aad(&self, id: &str) -> Vec<u8> {
+        let mut aad = self.file_id.to_vec();
+        aad.extend_from_slice(id.as_bytes());
+        aad
+    }
+
+    /// Returns the row identifier and encrypted value of `result`
+    fn seal(&self, result: &DomainResult) -> Result<(String, String)> {
+        let id = self.key.keyed_id(&result.domain);
+        let sealed = self
+            .key
+            .encrypt(serde_json::to_string(result)?.as_bytes(), &self.aad(&id))?;
+        Ok((id, BASE64.encode(sealed)))
+    }
+
+    /// Decrypts the value of the row `id`
+    fn open_row(&self, id: &str, value: &str) -> Result<DomainResult> {
+        let sealed = BASE64
+            .decode(value)
+            .context("Invalid encrypted result in SQLite store")?;
+        let json = self.key.decrypt(&sealed, &self.aad(id))?;
+        serde_json::from_slice(&json).context("Invalid result in SQLite store")
+    }
+}
+
+fn has_table(connection: &Connection, name: &str) -> Result<bool> {
+    let tables: i64 = connection.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [name],
+        |row| row.get(0),
+    )?;
+    Ok(tables > 0)
+}
+
+/// Checks that the database at `path` is encrypted exactly when `key` is given
+fn check_encryption(connection: &Connection, path: &Path, key: Option<&StorageKey>) -> Result<()> {
+    if key.is_none() && has_table(connection, "encrypted_results")? {
+        anyhow::bail!("{} is encrypted; a storage key is required", path.display());
+    }
+    if key.is_some() && has_table(connection, "results")? {
+        let rows: i64 =
+            connection.query_row("SELECT count(*) FROM results", [], |row| row.get(0))?;
+        if rows > 0 {
+            anyhow::bail!(
+                "{} holds unencrypted results; store encrypted results in a new database",
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Sink upserting results into a SQLite database
+pub struct SqliteSink {
+    connection: Arc<Mutex<Connection>>,
+    cipher: Option<Arc<RowCipher>>,
+    buffer: Vec<DomainResult>,
+}
+
+impl SqliteSink {
+    /// Opens (or creates) the database at `path` and its results table
+    ///
+    /// Results are encrypted with `key` when given.
+    ///
+    /// # Errors
+    /// * The database is encrypted and no key, or another key, is given
+    /// * A key is given and the database holds unencrypted results
+    pub fn open(path: &Path, key: Option<Arc<StorageKey>>) -> Result<Self> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("Failed to open SQLite database {}", path.display()))?;
+        check_encryption(&connection, path, key.as_deref())?;
+        let cipher = match key {
+            Some(key) => {
+                connection
+                    .execute_batch(ENCRYPTED_SCHEMA)
+                    .context("Failed to create the SQLite results table")?;
+                Some(Arc::new(RowCipher::open(&connection, key, path)?))
+            }
+            None => {
+                connection
+                    .execute_batch(SCHEMA)
+                    .context("Failed to create the SQLite results table")?;
+                None
+            }
+        };
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            cipher,
+            buffer: Vec::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl ResultSink for SqliteSink {
+    fn name(&self) -> &str {
+        "sqlite"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.buffer.push(result.clone());
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let results = std::mem::take(&mut self.buffer);
+        let connection = Arc::clone(&self.connection);
+        let cipher = self.cipher.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|_| anyhow!("SQLite connection poisoned by a failed write"))?;
+            match cipher {
+                Some(cipher) => upsert_encrypted(&mut connection, &cipher, &results),
+                None => upsert(&mut connection, &results),
+            }
+        })
+        .await
+        .context("SQLite writer task failed")?
+    }
+}
+
+/// Upserts `results` in one transaction
+fn upsert(connection: &mut Connection, results: &[DomainResult]) -> Result<()> {
+    let transaction = connection.transaction()?;
+    {
+        let mut statement = transaction.prepare_cached(UPSERT)?;
+        for result in results {
+            statement
+                .execute(params![
+                    result.domain,
+                    result.tenant,
+                    result.mdi_instance.is_some(),
+                    result.mdi_instance,
+                    result.mdi_generation.map(|generation| generation.as_str()),
+                    serde_json::to_string(&result.federated_domains)?,
+                    result.processing_time_ms as i64,
+                    result.error,
+                    result.error_class.map(|class| class.as_str()),
+                    result.from_cache,
+                    result.checked_at.to_rfc3339(),
+                    result.completed_at.to_rfc3339(),
+                    serde_json::to_string(result)?,
+                ])
+                .with_context(|| format!("Failed to store {} in SQLite", result.domain))?;
+        }
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Upserts encrypted `results` in one transaction
+fn upsert_encrypted(
+    connection: &mut Connection,
+    cipher: &RowCipher,
+    results: &[DomainResult],
+) -> Result<()> {
+    let transaction = connection.transaction()?;
+    {
+        let mut statement = transaction.prepare_cached(ENCRYPTED_UPSERT)?;
+        for result in results {
+            let (id, sealed) = cipher.seal(result)?;
+            statement
+                .execute(params![id, sealed])
+                .with_context(|| format!("Failed to store {} in SQLite", result.domain))?;
+        }
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Reads every result of the database at `path`, ordered by domain
+///
+/// # Errors
+/// * The database is encrypted and `key` is not its key, or a row was tampered with
+pub fn read_sqlite_results(path: &Path, key: Option<Arc<StorageKey>>) -> Result<Vec<DomainResult>> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open SQLite database {}", path.display()))?;
+    check_encryption(&connection, path, key.as_deref())?;
+    if let Some(key) = key {
+        let cipher = RowCipher::open(&connection, key, path)?;
+        let mut statement = connection.prepare("SELECT id, result FROM encrypted_results")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut results = rows
+            .map(|row| {
+                let (id, value) = row?;
+                cipher.open_row(&id, &value)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        results.sort_by(|a, b| a.domain.cmp(&b.domain));
+        return Ok(results);
+    }
+    let mut statement = connection.prepare("SELECT result FROM results ORDER BY domain")?;
+    let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+    rows.map(|json| serde_json::from_str(&json?).context("Invalid result in SQLite store"))
+        .collect()
+}
@@ -0,0 +1,165 @@
+//! Tenant summary report keyed by onmicrosoft name
+//!
+//! Large scans touch many domains of the same organization. The tenant report
+//! condenses them into one JSON line per discovered tenant, the
+//! `<name>.onmicrosoft.com` prefix, with the number of domains known for the
+//! tenant, the scanned domains that led to it and its MDI status:
+//!
+//! ```text
+//! {"tenant":"contoso","domain_count":3,"scanned_domains":["contoso.com","contoso.de"],"mdi":true,"mdi_instance":"contososensorapi.atp.azure.com","mdi_generation":"legacy"}
+//! ```
+//!
+//! Results without a tenant, including failed checks, are left out. Tenants
+//! are collected in memory and the report is written, sorted by tenant name,
+//! when the sink is closed.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use super::ResultSink;
+use crate::core::{DomainResult, MdiGeneration};
+
+/// One discovered tenant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantSummary {
+    /// Tenant name, the prefix of its `onmicrosoft.com` domain
+    pub tenant: String,
+    /// Distinct domains known for the tenant, scanned or federated
+    pub domain_count: usize,
+    /// Scanned domains that belong to the tenant, sorted
+    pub scanned_domains: Vec<String>,
+    /// True if an MDI instance was detected for the tenant
+    pub mdi: bool,
+    /// Sensor API hostname of the MDI instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mdi_instance: Option<String>,
+    /// Endpoint generation of the MDI instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mdi_generation: Option<MdiGeneration>,
+}
+
+#[derive(Default)]
+struct TenantEntry {
+    domains: BTreeSet<String>,
+    scanned_domains: BTreeSet<String>,
+    mdi_instance: Option<String>,
+    mdi_generation: Option<MdiGeneration>,
+}
+
+/// Tenants aggregated from domain results
+///
+/// # Examples
+///
+/// ```
+/// use sentri::core::DomainResult;
+/// use sentri::sinks::tenant_report::TenantReport;
+///
+/// let mut report = TenantReport::default();
+/// for domain in ["contoso.com", "contoso.de"] {
+///     report.add(&DomainResult {
+///         domain: domain.to_string(),
+///         tenant: Some("contoso".to_string()),
+///         federated_domains: vec![domain.to_string(), "contoso.onmicrosoft.com".to_string()],
+///         ..Default::default()
+///     });
+/// }
+///
+/// let tenants = report.summaries();
+/// assert_eq!(tenants.len(), 1);
+/// assert_eq!(tenants[0].domain_count, 3);
+/// assert!(!tenants[0].mdi);
+/// ```
+#[derive(Default)]
+pub struct TenantReport {
+    tenants: BTreeMap<String, TenantEntry>,
+}
+
+impl TenantReport {
+    /// Adds a result to the tenant it belongs to; results without a tenant are ignored
+    pub fn add(&mut self, result: &DomainResult) {
+        let Some(tenant) = &result.tenant else {
+            return;
+        };
+        let entry = self.tenants.entry(tenant.to_ascii_lowercase()).or_default();
+        let domain = result.domain.to_ascii_lowercase();
+        entry.domains.insert(domain.clone());
+        entry.scanned_domains.insert(domain);
+        entry.domains.extend(
+            result
+                .federated_domains
+                .iter()
+                .map(|domain| domain.to_ascii_lowercase()),
+        );
+        if entry.mdi_instance.is_none() && result.mdi_instance.is_some() {
+            entry.mdi_instance = result.mdi_instance.clone();
+            entry.mdi_generation = result.mdi_generation;
+        }
+    }
+
+    /// Number of tenants discovered so far
+    pub fn len(&self) -> usize {
+        self.tenants.len()
+    }
+
+    /// Returns true if no tenant was discovered
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+
+    /// Summaries of every tenant, sorted by tenant name
+    pub fn summaries(&self) -> Vec<TenantSummary> {
+        self.tenants
+            .iter()
+            .map(|(tenant, entry)| TenantSummary {
+                tenant: tenant.clone(),
+                domain_count: entry.domains.len(),
+                scanned_domains: entry.scanned_domains.iter().cloned().collect(),
+                mdi: entry.mdi_instance.is_some(),
+                mdi_instance: entry.mdi_instance.clone(),
+                mdi_generation: entry.mdi_generation,
+            })
+            .collect()
+    }
+}
+
+/// Sink writing a [`TenantReport`] as JSONL when closed
+pub struct TenantReportSink {
+    path: PathBuf,
+    report: TenantReport,
+}
+
+impl TenantReportSink {
+    /// Creates a sink writing the report to `path`
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            report: TenantReport::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl ResultSink for TenantReportSink {
+    fn name(&self) -> &str {
+        "tenant-report"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.report.add(result);
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        let mut lines = String::new();
+        for summary in self.report.summaries() {
+            lines.push_str(&serde_json::to_string(&summary)?);
+            lines.push('\n');
+        }
+        tokio::fs::write(&self.path, lines)
+            .await
+            .with_context(|| format!("Failed to write tenant report {}", self.path.display()))
+    }
+}
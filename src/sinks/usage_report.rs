@@ -0,0 +1,51 @@
+//! Per-customer usage report for billing
+//!
+//! Collects a [`UsageReport`] from the results of a batch and writes it as
+//! CSV when the sink is closed, one row per `customer` tag of the input:
+//!
+//! ```text
+//! customer,domains,requests,cache_hits,failed,compute_ms,first_checked_at,last_completed_at
+//! Acme Corp,2,2,0,0,1840,2026-05-04T09:00:01+00:00,2026-05-04T09:00:03+00:00
+//! ```
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+use super::ResultSink;
+use crate::accounting::UsageReport;
+use crate::core::DomainResult;
+
+/// Sink writing a [`UsageReport`] as CSV when closed
+pub struct UsageReportSink {
+    path: PathBuf,
+    report: UsageReport,
+}
+
+impl UsageReportSink {
+    /// Creates a sink writing the report to `path`
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            report: UsageReport::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl ResultSink for UsageReportSink {
+    fn name(&self) -> &str {
+        "usage-report"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.report.add(result);
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        tokio::fs::write(&self.path, self.report.to_csv())
+            .await
+            .with_context(|| format!("Failed to write usage report {}", self.path.display()))
+    }
+}
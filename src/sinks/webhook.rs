@@ -0,0 +1,117 @@
+//! Generic webhook result sink
+//!
+//! Posts results as a JSON array to an HTTPS endpoint, such as a chat
+//! integration, a ticketing system or an automation platform. Used as the
+//! destination of routing rules (see [`crate::sinks::routing`]).
+//!
+//! # Security Considerations
+//!
+//! - Requests go through `HttpClient`, inheriting HTTPS-only transport,
+//!   certificate validation and retries with backoff
+//!   (security:network:validate_ssl_certs)
+//! - Payloads are built from sanitized results only
+//!   (security:output:sanitize_all_output)
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use std::time::Duration;
+use tracing::debug;
+
+use crate::core::DomainResult;
+use crate::http::HttpClient;
+use crate::sinks::ResultSink;
+
+/// Default number of results delivered per request
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Result sink posting batches of results to a webhook
+///
+/// # Examples
+///
+/// ```
+/// use sentri::sinks::webhook::WebhookSink;
+/// use std::time::Duration;
+///
+/// # fn example() -> anyhow::Result<()> {
+/// let sink = WebhookSink::new("https://hooks.example.com/sentri", Duration::from_secs(10))?
+///     .with_batch_size(20);
+/// assert_eq!(sink.url(), "https://hooks.example.com/sentri");
+/// # Ok(())
+/// # }
+/// ```
+pub struct WebhookSink {
+    client: HttpClient,
+    url: String,
+    batch_size: usize,
+    buffer: Vec<DomainResult>,
+}
+
+impl WebhookSink {
+    /// Creates a new webhook sink
+    ///
+    /// # Arguments
+    /// * `url` - Endpoint receiving the results
+    /// * `timeout` - Request timeout for deliveries
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The sink or error if the HTTP client could not be created
+    pub fn new(url: &str, timeout: Duration) -> Result<Self> {
+        crate::egress::allow_url(url);
+        let client = HttpClient::builder()
+            .timeout(timeout)
+            .http2_prior_knowledge(false)
+            .build()?;
+
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            buffer: Vec::with_capacity(DEFAULT_BATCH_SIZE),
+        })
+    }
+
+    /// Sets the number of results delivered per request
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Endpoint the results are posted to
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[async_trait]
+impl ResultSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.buffer.push(result.clone());
+        if self.buffer.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let body = serde_json::to_string(&self.buffer)?;
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        self.client
+            .post(&self.url, headers, &body)
+            .await
+            .with_context(|| format!("Failed to deliver results to webhook {}", self.url))?;
+
+        debug!("Delivered {} results to webhook", self.buffer.len());
+        self.buffer.clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+//! Excel workbook sink for recipients working in spreadsheets
+//!
+//! Results are collected by [`crate::output::xlsx::XlsxWriter`]; the
+//! workbook, including its per-tenant sheet, is written when the sink is
+//! closed.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+use super::ResultSink;
+use crate::core::DomainResult;
+use crate::output::xlsx::XlsxWriter;
+
+/// Sink writing results to an XLSX workbook
+pub struct XlsxSink {
+    path: PathBuf,
+    writer: Option<XlsxWriter>,
+}
+
+impl XlsxSink {
+    /// Creates (or truncates) the workbook at `path`
+    ///
+    /// The file is created right away so an unwritable path fails before scanning.
+    pub async fn create(path: &Path) -> Result<Self> {
+        tokio::fs::File::create(path)
+            .await
+            .with_context(|| format!("Failed to create output file {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Some(XlsxWriter::new()?),
+        })
+    }
+}
+
+#[async_trait]
+impl ResultSink for XlsxSink {
+    fn name(&self) -> &str {
+        "xlsx"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.writer
+            .as_mut()
+            .context("XLSX workbook is already closed")?
+            .push(result)
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        let Some(writer) = self.writer.take() else {
+            return Ok(());
+        };
+        let workbook = writer.finish()?;
+        tokio::fs::write(&self.path, workbook)
+            .await
+            .with_context(|| format!("Failed to write workbook {}", self.path.display()))
+    }
+}
@@ -0,0 +1,160 @@
+//! Per-detector success, failure and latency statistics
+//!
+//! Every domain check runs a pipeline of detectors (see [`crate::depth`]).
+//! [`DetectorStats`] counts how often each of them succeeded or failed and
+//! keeps a histogram of their latencies, so a batch can report which stage
+//! is slow or flaky in the environment it runs in. The statistics end up in
+//! the `detectors` of the batch summary and in notification reports.
+//!
+//! A detector fails when it could not get an answer, such as a federation
+//! request timing out or a DNS lookup erroring. Finding nothing is an answer:
+//! a domain without a tenant, a tenant without an MDI instance or a missing
+//! DNS record counts as a success.
+//!
+//! # Performance Considerations
+//!
+//! - Latencies are counted per millisecond rather than stored, so memory is
+//!   bounded by the request timeout, not by the number of domains, and
+//!   percentiles stay exact (performance:memory:bound_collections)
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A stage of the domain check pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Detector {
+    /// Autodiscover federation request
+    Federation,
+    /// Tenant extraction from the federated domains
+    Tenant,
+    /// MDI sensor DNS lookups
+    Mdi,
+    /// Attribution of federated endpoints to Microsoft ranges
+    Endpoints,
+}
+
+impl Detector {
+    /// Name of the detector in summaries and logs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Detector::Federation => "federation",
+            Detector::Tenant => "tenant",
+            Detector::Mdi => "mdi",
+            Detector::Endpoints => "endpoints",
+        }
+    }
+}
+
+impl fmt::Display for Detector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Statistics of one detector over a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DetectorSummary {
+    /// The detector
+    pub detector: Detector,
+    /// Runs that got an answer
+    pub successes: u64,
+    /// Runs that failed
+    pub failures: u64,
+    /// Median latency in milliseconds
+    pub p50_ms: u64,
+    /// 95th percentile latency in milliseconds
+    pub p95_ms: u64,
+    /// 99th percentile latency in milliseconds
+    pub p99_ms: u64,
+    /// Slowest run in milliseconds
+    pub max_ms: u64,
+}
+
+#[derive(Default)]
+struct DetectorState {
+    successes: u64,
+    failures: u64,
+    /// Number of runs per latency in milliseconds
+    latencies: BTreeMap<u64, u64>,
+}
+
+impl DetectorState {
+    /// Latency below which `percent` of the runs completed, by nearest rank
+    fn percentile(&self, percent: u64) -> u64 {
+        let total = self.successes + self.failures;
+        let rank = (total * percent).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (&latency_ms, &count) in &self.latencies {
+            seen += count;
+            if seen >= rank {
+                return latency_ms;
+            }
+        }
+        0
+    }
+}
+
+/// Outcomes and latencies of every detector run
+///
+/// # Examples
+///
+/// ```
+/// use sentri::stats::{Detector, DetectorStats};
+/// use std::time::Duration;
+///
+/// let stats = DetectorStats::new();
+/// stats.record(Detector::Federation, true, Duration::from_millis(120));
+/// stats.record(Detector::Federation, false, Duration::from_millis(5000));
+///
+/// let summary = &stats.summaries()[0];
+/// assert_eq!((summary.successes, summary.failures), (1, 1));
+/// assert_eq!(summary.p50_ms, 120);
+/// assert_eq!(summary.max_ms, 5000);
+/// ```
+#[derive(Default)]
+pub struct DetectorStats {
+    detectors: Mutex<BTreeMap<Detector, DetectorState>>,
+}
+
+impl DetectorStats {
+    /// Creates statistics without any recorded runs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a run of `detector` that took `latency`
+    pub fn record(&self, detector: Detector, success: bool, latency: Duration) {
+        let mut detectors = self.detectors.lock().unwrap();
+        let state = detectors.entry(detector).or_default();
+        if success {
+            state.successes += 1;
+        } else {
+            state.failures += 1;
+        }
+        *state
+            .latencies
+            .entry(latency.as_millis() as u64)
+            .or_default() += 1;
+    }
+
+    /// Statistics of every detector that ran, in pipeline order
+    pub fn summaries(&self) -> Vec<DetectorSummary> {
+        let detectors = self.detectors.lock().unwrap();
+        detectors
+            .iter()
+            .map(|(&detector, state)| DetectorSummary {
+                detector,
+                successes: state.successes,
+                failures: state.failures,
+                p50_ms: state.percentile(50),
+                p95_ms: state.percentile(95),
+                p99_ms: state.percentile(99),
+                max_ms: state.latencies.keys().next_back().copied().unwrap_or(0),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,172 @@
+//! Tags annotating the domains of a batch input
+//!
+//! Every line of a batch input names a domain, optionally followed by CSV
+//! columns of `key=value` tags:
+//!
+//! ```text
+//! domain,tags
+//! contoso.com,bu=emea,owner=alice
+//! fabrikam.com,"owner=Smith, Bob"
+//! woodgrovebank.com,Acme Corp
+//! tailspintoys.com
+//! ```
+//!
+//! The tags are copied into the `tags` of every result of the domain and
+//! written by all output formats, so results can be grouped and routed
+//! downstream without joining them back to the input. A column without `=`
+//! is shorthand for the [`CUSTOMER_TAG`] (see [`crate::accounting`]); only
+//! the first one counts. A first line naming the `domain` column is treated
+//! as a header, and double quotes let values contain commas.
+//!
+//! Checkpoints written by `--max-runtime` keep the tags of the domains they
+//! list.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Tags of a domain, sorted by key
+pub type Tags = BTreeMap<String, String>;
+
+/// Tag holding the customer a domain is scanned for
+pub const CUSTOMER_TAG: &str = "customer";
+
+/// A domain of a batch input with its tags
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputLine {
+    /// Domain to scan
+    pub domain: String,
+    /// Tags copied into the domain's results
+    pub tags: Tags,
+}
+
+impl InputLine {
+    /// Creates an untagged input line
+    pub fn new(domain: impl Into<String>) -> Self {
+        Self {
+            domain: domain.into(),
+            tags: Tags::new(),
+        }
+    }
+}
+
+impl From<String> for InputLine {
+    fn from(domain: String) -> Self {
+        Self::new(domain)
+    }
+}
+
+impl From<&String> for InputLine {
+    fn from(domain: &String) -> Self {
+        Self::new(domain.clone())
+    }
+}
+
+/// Renders the line in input syntax, as written to checkpoints
+impl fmt::Display for InputLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.domain)?;
+        for (key, value) in &self.tags {
+            let tag = format!("{}={}", key, value);
+            if tag.contains([',', '"']) {
+                write!(f, ",\"{}\"", tag.replace('"', "\"\""))?;
+            } else {
+                write!(f, ",{}", tag)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders tags as `key=value` pairs separated by `;`, for single-cell outputs
+///
+/// # Examples
+///
+/// ```
+/// use sentri::tags::{format_tags, Tags};
+///
+/// let tags = Tags::from([("owner".to_string(), "alice".to_string()), ("bu".to_string(), "emea".to_string())]);
+/// assert_eq!(format_tags(&tags), "bu=emea;owner=alice");
+/// ```
+pub fn format_tags(tags: &Tags) -> String {
+    tags.iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Parses a line of a batch input
+///
+/// Returns `None` for blank lines, `#` comments and a `domain` header.
+/// Columns with an empty key or value are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use sentri::tags::parse_input_line;
+///
+/// let line = parse_input_line("contoso.com,bu=emea, owner=alice").unwrap();
+/// assert_eq!(line.domain, "contoso.com");
+/// assert_eq!(line.tags["bu"], "emea");
+/// assert_eq!(line.tags["owner"], "alice");
+/// assert_eq!(line.to_string(), "contoso.com,bu=emea,owner=alice");
+///
+/// let line = parse_input_line("fabrikam.com,\"Acme, Inc\"").unwrap();
+/// assert_eq!(line.tags["customer"], "Acme, Inc");
+///
+/// assert!(parse_input_line("domain,tags").is_none());
+/// assert!(parse_input_line("# comment").is_none());
+/// ```
+pub fn parse_input_line(line: &str) -> Option<InputLine> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let mut columns = split_columns(line).into_iter();
+    let domain = columns.next()?;
+    if domain.is_empty() || domain.eq_ignore_ascii_case("domain") {
+        return None;
+    }
+
+    let mut tags = Tags::new();
+    let mut customer = None;
+    for column in columns {
+        match column.split_once('=') {
+            Some((key, value)) => {
+                let (key, value) = (key.trim(), value.trim());
+                if !key.is_empty() && !value.is_empty() {
+                    tags.insert(key.to_string(), value.to_string());
+                }
+            }
+            None if customer.is_none() && !column.is_empty() => customer = Some(column),
+            None => {}
+        }
+    }
+    if let Some(customer) = customer {
+        tags.entry(CUSTOMER_TAG.to_string()).or_insert(customer);
+    }
+    Some(InputLine { domain, tags })
+}
+
+/// Splits a line into trimmed CSV columns
+///
+/// Quotes may open anywhere in a column, so both `"owner=Smith, Bob"` and
+/// `owner="Smith, Bob"` hold one tag; `""` inside quotes is a literal quote.
+fn split_columns(line: &str) -> Vec<String> {
+    let mut columns = Vec::new();
+    let mut column = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                column.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => columns.push(std::mem::take(&mut column).trim().to_string()),
+            c => column.push(c),
+        }
+    }
+    columns.push(column.trim().to_string());
+    columns
+}
@@ -0,0 +1,112 @@
+//! Monotonic durations anchored to wall-clock timestamps
+//!
+//! Durations measured with `Utc::now()` go wrong when the system clock is
+//! adjusted mid-run (NTP corrections, VM resume, manual changes), while an
+//! `Instant` alone cannot be reported. A [`Stopwatch`] reads the wall clock
+//! once when it starts and measures everything after that monotonically, so
+//! the timestamps and durations it reports always agree with each other.
+//!
+//! Long-running loops compare the anchored time with the wall clock through
+//! [`Stopwatch::skew`] to notice clock jumps and re-plan wall-clock based
+//! work such as cron schedules.
+
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/// Clock drift below which the wall clock is considered unchanged
+pub const SKEW_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// Measures elapsed time monotonically from a wall-clock anchor
+///
+/// # Examples
+///
+/// ```
+/// use sentri::time::Stopwatch;
+///
+/// let stopwatch = Stopwatch::start();
+/// assert!(stopwatch.now() >= stopwatch.started_at());
+/// assert!(!stopwatch.clock_jumped(chrono::Utc::now()));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Stopwatch {
+    start: Instant,
+    started_at: DateTime<Utc>,
+}
+
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl Stopwatch {
+    /// Starts measuring now
+    pub fn start() -> Self {
+        Self {
+            start: Instant::now(),
+            started_at: Utc::now(),
+        }
+    }
+
+    /// Wall-clock time the stopwatch was started
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
+    /// Monotonic time elapsed since the start
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Monotonic time elapsed since the start in whole milliseconds
+    pub fn elapsed_ms(&self) -> u64 {
+        self.elapsed().as_millis() as u64
+    }
+
+    /// Current time derived from the start time and the monotonic clock
+    ///
+    /// Unaffected by wall-clock adjustments made after the start.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.started_at
+            + chrono::Duration::from_std(self.elapsed()).unwrap_or(chrono::TimeDelta::MAX)
+    }
+
+    /// How far the wall clock has moved away from the monotonic clock
+    ///
+    /// Positive when the wall clock jumped forward, negative when it was set back.
+    pub fn skew(&self, wall_now: DateTime<Utc>) -> chrono::Duration {
+        wall_now - self.now()
+    }
+
+    /// Returns true if the wall clock drifted further than [`SKEW_TOLERANCE`]
+    pub fn clock_jumped(&self, wall_now: DateTime<Utc>) -> bool {
+        self.skew(wall_now).abs() > chrono::Duration::from_std(SKEW_TOLERANCE).unwrap_or_default()
+    }
+}
+
+/// Rounds `timestamp` down to the start of its `bucket`
+///
+/// Buckets are aligned to the Unix epoch, so a bucket of one day starts at
+/// midnight UTC. A zero bucket leaves the timestamp unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use sentri::time::bucket_timestamp;
+/// use std::time::Duration;
+///
+/// let checked_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 34, 56).unwrap();
+/// assert_eq!(
+///     bucket_timestamp(checked_at, Duration::from_secs(3_600)),
+///     Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap()
+/// );
+/// ```
+pub fn bucket_timestamp(timestamp: DateTime<Utc>, bucket: Duration) -> DateTime<Utc> {
+    let bucket_ms = bucket.as_millis().min(i64::MAX as u128) as i64;
+    if bucket_ms == 0 {
+        return timestamp;
+    }
+    let millis = timestamp.timestamp_millis();
+    DateTime::from_timestamp_millis(millis - millis.rem_euclid(bucket_ms)).unwrap_or(timestamp)
+}
@@ -0,0 +1,228 @@
+//! Per-domain pipeline traces in Chrome trace-event format
+//!
+//! With `--trace-file <PATH>`, every domain check records a tree of spans,
+//! `check_domain` with its `validation`, `federation`, `tenant`, `mdi` and
+//! `endpoints` phases, and below them the rate limiter waits, HTTP attempts
+//! (one per retry) and DNS lookups they caused. The spans are written as
+//! [Chrome trace events] when the run ends, so the file opens in Perfetto
+//! (<https://ui.perfetto.dev>) or `chrome://tracing`. Each domain gets its
+//! own track, named after the domain.
+//!
+//! Spans are recorded through the domain a future is [`DomainTrace::scope`]d
+//! to, so code deep in the pipeline only calls [`span`] and needs no
+//! recorder of its own. Outside a traced check, [`span`] records nothing.
+//!
+//! ```
+//! use sentri::trace::{span, TraceRecorder};
+//! use std::sync::Arc;
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let recorder = Arc::new(TraceRecorder::new());
+//! recorder
+//!     .domain("contoso.com")
+//!     .scope(async {
+//!         let _check = span("check_domain");
+//!         let mut federation = span("federation");
+//!         federation.arg("attempts", 1);
+//!     })
+//!     .await;
+//! assert_eq!(recorder.spans(), 2);
+//! # });
+//! ```
+//!
+//! [Chrome trace events]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::future::Future;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::build_info::LONG_VERSION;
+
+/// Process ID of every event; a trace covers a single run
+const PID: u32 = 1;
+
+tokio::task_local! {
+    /// Domain the spans of the current future are recorded for
+    static CURRENT: DomainTrace;
+}
+
+/// One event of the trace file
+#[derive(Debug, Clone, Serialize)]
+struct TraceEvent {
+    name: String,
+    /// `X` for complete spans, `M` for track metadata
+    ph: &'static str,
+    /// Start in microseconds since the recorder was created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<u64>,
+    /// Duration in microseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<u64>,
+    pid: u32,
+    tid: u64,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    args: Map<String, Value>,
+}
+
+/// Spans of every traced domain check of a run
+pub struct TraceRecorder {
+    started: Instant,
+    next_track: AtomicU64,
+    events: Mutex<Vec<TraceEvent>>,
+}
+
+impl Default for TraceRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TraceRecorder {
+    /// Creates a recorder; span timestamps are relative to this moment
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            next_track: AtomicU64::new(1),
+            events: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Opens a track for the check of `domain`
+    pub fn domain(self: &Arc<Self>, domain: &str) -> DomainTrace {
+        let track = self.next_track.fetch_add(1, Ordering::Relaxed);
+        let mut args = Map::new();
+        args.insert("name".to_string(), Value::from(domain));
+        self.push(TraceEvent {
+            name: "thread_name".to_string(),
+            ph: "M",
+            ts: None,
+            dur: None,
+            pid: PID,
+            tid: track,
+            args,
+        });
+        DomainTrace {
+            recorder: Arc::clone(self),
+            track,
+        }
+    }
+
+    /// Number of spans recorded so far
+    pub fn spans(&self) -> usize {
+        self.events
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|event| event.ph == "X")
+            .count()
+    }
+
+    /// Renders the trace as a Chrome trace-event JSON document
+    pub fn to_json(&self) -> Value {
+        let events = self
+            .events
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        serde_json::json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+            "otherData": { "generator": format!("sentri {}", LONG_VERSION) },
+        })
+    }
+
+    /// Writes the trace to `path`
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec(&self.to_json())?;
+        tokio::fs::write(path, json)
+            .await
+            .with_context(|| format!("Failed to write trace to {}", path.display()))
+    }
+
+    fn push(&self, event: TraceEvent) {
+        self.events
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(event);
+    }
+
+    /// Microseconds from the creation of the recorder to `instant`
+    fn micros_since_start(&self, instant: Instant) -> u64 {
+        instant.saturating_duration_since(self.started).as_micros() as u64
+    }
+}
+
+/// Track of one domain check
+#[derive(Clone)]
+pub struct DomainTrace {
+    recorder: Arc<TraceRecorder>,
+    track: u64,
+}
+
+impl DomainTrace {
+    /// Runs `future` with [`span`] recording onto this track
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+}
+
+/// A span recorded when dropped; inert outside a traced check
+pub struct Span {
+    active: Option<ActiveSpan>,
+}
+
+struct ActiveSpan {
+    trace: DomainTrace,
+    name: &'static str,
+    started: Instant,
+    args: Map<String, Value>,
+}
+
+impl Span {
+    /// Attaches an argument shown with the span, e.g. an attempt number
+    pub fn arg(&mut self, key: &str, value: impl Into<Value>) {
+        if let Some(active) = &mut self.active {
+            active.args.insert(key.to_string(), value.into());
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(active) = self.active.take() else {
+            return;
+        };
+        let recorder = &active.trace.recorder;
+        let ts = recorder.micros_since_start(active.started);
+        let end = recorder.micros_since_start(Instant::now());
+        recorder.push(TraceEvent {
+            name: active.name.to_string(),
+            ph: "X",
+            ts: Some(ts),
+            dur: Some(end.saturating_sub(ts)),
+            pid: PID,
+            tid: active.trace.track,
+            args: active.args,
+        });
+    }
+}
+
+/// Starts a span on the track of the current domain check
+///
+/// Spans end when dropped; spans started while another is open nest below it.
+pub fn span(name: &'static str) -> Span {
+    let trace = CURRENT.try_with(DomainTrace::clone).ok();
+    Span {
+        active: trace.map(|trace| ActiveSpan {
+            trace,
+            name,
+            started: Instant::now(),
+            args: Map::new(),
+        }),
+    }
+}
@@ -0,0 +1,216 @@
+//! Post-run upload of completed result files to cloud object storage
+//!
+//! Large scans often run on ephemeral cloud workers whose local disk disappears
+//! with the instance. This module pushes finished output files to Amazon S3,
+//! Azure Blob Storage or Google Cloud Storage once a batch completes.
+//!
+//! The object store integration is only compiled with the `object-store` cargo
+//! feature; without it, requesting an upload fails with a clear error.
+//!
+//! # Security Considerations
+//!
+//! - **Credentials**: Cloud credentials are read from the standard provider
+//!   environment variables (`AWS_*`, `AZURE_*`, `GOOGLE_*`) and never from the
+//!   command line (security:output:error_info_control)
+//! - **Encryption at Rest**: S3 uploads can request SSE-S3, SSE-KMS or DSSE-KMS
+//!   server-side encryption; Azure and GCS always encrypt at rest
+//!
+//! # Performance Considerations
+//!
+//! - Files are streamed through multipart uploads in fixed-size parts, so
+//!   multi-gigabyte outputs never have to be held in memory
+//!   (performance:memory:use_streaming_io)
+
+use anyhow::Result;
+use clap::ValueEnum;
+use std::path::Path;
+
+/// Server-side encryption requested for uploaded objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ServerSideEncryption {
+    /// Use the bucket's default encryption settings
+    #[default]
+    Default,
+    /// S3-managed keys (SSE-S3, AES256)
+    Aes256,
+    /// AWS KMS-managed keys (SSE-KMS)
+    Kms,
+    /// Dual-layer AWS KMS encryption (DSSE-KMS)
+    DsseKms,
+}
+
+impl ServerSideEncryption {
+    /// Returns the object store configuration options for this encryption mode
+    ///
+    /// # Arguments
+    /// * `kms_key_id` - KMS key ID or ARN used with the KMS-based modes
+    ///
+    /// # Returns
+    /// * `Vec<(String, String)>` - Builder configuration key/value pairs
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::upload::ServerSideEncryption;
+    ///
+    /// let options = ServerSideEncryption::Kms.config_options(Some("alias/scans"));
+    /// assert!(options.contains(&("aws_server_side_encryption".to_string(), "aws:kms".to_string())));
+    /// ```
+    pub fn config_options(&self, kms_key_id: Option<&str>) -> Vec<(String, String)> {
+        let sse = match self {
+            Self::Default => return Vec::new(),
+            Self::Aes256 => "AES256",
+            Self::Kms => "aws:kms",
+            Self::DsseKms => "aws:kms:dsse",
+        };
+
+        let mut options = vec![("aws_server_side_encryption".to_string(), sse.to_string())];
+        if let Some(key_id) = kms_key_id {
+            options.push(("aws_sse_kms_key_id".to_string(), key_id.to_string()));
+        }
+        options
+    }
+}
+
+/// Resolves the object key for an uploaded file
+///
+/// A destination ending in `/` is treated as a prefix and the local file name
+/// is appended; any other destination is used as the full object key.
+///
+/// # Arguments
+/// * `destination` - Destination URL, e.g. `s3://bucket/scans/`
+/// * `local` - Local file being uploaded
+///
+/// # Returns
+/// * `String` - The full destination URL of the uploaded object
+///
+/// # Examples
+///
+/// ```
+/// use sentri::upload::destination_url;
+/// use std::path::Path;
+///
+/// assert_eq!(
+///     destination_url("s3://bucket/scans/", Path::new("/tmp/results.jsonl")),
+///     "s3://bucket/scans/results.jsonl"
+/// );
+/// assert_eq!(
+///     destination_url("gs://bucket/latest.jsonl", Path::new("results.jsonl")),
+///     "gs://bucket/latest.jsonl"
+/// );
+/// ```
+pub fn destination_url(destination: &str, local: &Path) -> String {
+    if destination.ends_with('/') {
+        let file_name = local
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        format!("{}{}", destination, file_name)
+    } else {
+        destination.to_string()
+    }
+}
+
+/// Uploads a completed result file to object storage
+///
+/// Supported destinations are `s3://`, `az://`/`abfs://`/`https://<account>.blob.core.windows.net`
+/// and `gs://` URLs. Credentials are taken from the provider environment variables.
+///
+/// # Arguments
+/// * `local` - File to upload
+/// * `destination` - Destination URL or prefix (see [`destination_url`])
+/// * `sse` - Server-side encryption mode for S3
+/// * `kms_key_id` - KMS key for the KMS-based encryption modes
+///
+/// # Returns
+/// * `Result<String>` - The URL of the uploaded object
+///
+/// # Errors
+/// * The crate was built without the `object-store` feature
+/// * The destination URL is not a supported object store
+/// * Reading the file or uploading it failed
+#[cfg(feature = "object-store")]
+pub async fn upload_file(
+    local: &Path,
+    destination: &str,
+    sse: ServerSideEncryption,
+    kms_key_id: Option<&str>,
+) -> Result<String> {
+    use anyhow::Context;
+    use object_store::WriteMultipart;
+    use tokio::io::AsyncReadExt;
+    use tracing::info;
+
+    /// Size of each multipart upload part (S3 requires at least 5MB)
+    const PART_SIZE: usize = 8 * 1024 * 1024;
+
+    let target = destination_url(destination, local);
+    crate::offline::ensure_online(format_args!("upload to {}", target))?;
+    let url = reqwest::Url::parse(&target)
+        .with_context(|| format!("Invalid upload destination: {}", target))?;
+
+    let (store, path) = open_store(&url, sse.config_options(kms_key_id))
+        .with_context(|| format!("Unsupported upload destination: {}", target))?;
+
+    let mut file = tokio::fs::File::open(local)
+        .await
+        .with_context(|| format!("Failed to open result file {}", local.display()))?;
+
+    let upload = store
+        .put_multipart(&path)
+        .await
+        .context("Failed to start multipart upload")?;
+    let mut writer = WriteMultipart::new_with_chunk_size(upload, PART_SIZE);
+
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut uploaded = 0u64;
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        writer.wait_for_capacity(4).await?;
+        writer.write(&buffer[..read]);
+        uploaded += read as u64;
+    }
+
+    writer
+        .finish()
+        .await
+        .context("Failed to complete multipart upload")?;
+
+    info!("Uploaded {} bytes to {}", uploaded, target);
+    Ok(target)
+}
+
+/// Opens the object store of `url`
+///
+/// Provider credentials come from the environment, as with the official
+/// CLIs; `options` add to them.
+#[cfg(feature = "object-store")]
+pub(crate) fn open_store(
+    url: &reqwest::Url,
+    options: Vec<(String, String)>,
+) -> object_store::Result<(Box<dyn object_store::ObjectStore>, object_store::path::Path)> {
+    let options = std::env::vars()
+        .map(|(key, value)| (key.to_ascii_lowercase(), value))
+        .chain(options);
+    object_store::parse_url_opts(url, options)
+}
+
+/// Uploads a completed result file to object storage
+///
+/// This build does not include the `object-store` feature, so every upload
+/// request fails with an explanatory error.
+#[cfg(not(feature = "object-store"))]
+pub async fn upload_file(
+    _local: &Path,
+    destination: &str,
+    _sse: ServerSideEncryption,
+    _kms_key_id: Option<&str>,
+) -> Result<String> {
+    Err(anyhow::anyhow!(
+        "Cannot upload to {}: sentri was built without the object-store feature",
+        destination
+    ))
+}
@@ -0,0 +1,369 @@
+//! Continuous monitoring of a fixed set of domains
+//!
+//! Watch mode periodically rescans the monitored domains and compares each
+//! cycle with the previous one. Changes worth attention are reported as
+//! [`WatchEvent`]s:
+//!
+//! - A domain that previously had an MDI instance no longer has one
+//! - A federated domain appears that was neither federated in the previous
+//!   cycle nor part of the monitored list
+//! - A previously seen domain is gone, either removed from the input file or
+//!   failing to scan for several consecutive cycles; a tombstone carrying its
+//!   last-seen time lets downstream stores record the removal
+//!
+//! Events are printed as JSON lines and forwarded to the configured
+//! [`Alerter`]s.
+//!
+//! # Security Considerations
+//!
+//! - Results are sanitized before comparison, so events never carry raw
+//!   response content (security:output:sanitize_all_output)
+//! - Scans go through the same rate limiter as batch mode, whose budget can
+//!   be reloaded from the configuration file (mdi:api:respect_api_limits)
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::Duration,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    alert::Alerter, core::DomainResult, core::MdiChecker, reload::LiveConfig,
+    sanitize::sanitize_domain_result,
+};
+
+/// A change detected between two watch cycles
+///
+/// # Examples
+///
+/// ```
+/// use sentri::watch::WatchEvent;
+///
+/// let event = WatchEvent::MdiLost {
+///     domain: "contoso.com".to_string(),
+///     previous_instance: "contososensorapi.atp.azure.com".to_string(),
+/// };
+/// assert_eq!(event.dedup_key(), "sentri:mdi-lost:contoso.com");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WatchEvent {
+    /// A monitored domain no longer resolves an MDI instance
+    MdiLost {
+        /// Monitored domain
+        domain: String,
+        /// MDI instance seen in the previous cycle
+        previous_instance: String,
+    },
+    /// A previously unknown domain joined the monitored domain's federation
+    NewFederatedDomain {
+        /// Monitored domain
+        domain: String,
+        /// Newly federated domain
+        federated_domain: String,
+    },
+    /// A previously seen domain is gone
+    Tombstone {
+        /// Monitored domain
+        domain: String,
+        /// Why the domain is considered gone
+        reason: TombstoneReason,
+        /// Time of the domain's last successful scan
+        last_seen: DateTime<Utc>,
+    },
+}
+
+/// Why a domain was tombstoned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TombstoneReason {
+    /// The domain is no longer listed in the input file
+    RemovedFromInput,
+    /// Scans of the domain kept failing
+    StoppedResolving,
+}
+
+impl WatchEvent {
+    /// Machine-readable event kind
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::MdiLost { .. } => "mdi-lost",
+            Self::NewFederatedDomain { .. } => "new-federated-domain",
+            Self::Tombstone { .. } => "tombstone",
+        }
+    }
+
+    /// Monitored domain the event relates to
+    pub fn domain(&self) -> &str {
+        match self {
+            Self::MdiLost { domain, .. }
+            | Self::NewFederatedDomain { domain, .. }
+            | Self::Tombstone { domain, .. } => domain,
+        }
+    }
+
+    /// Deduplication key identifying the incident for this event
+    ///
+    /// The key depends only on the event kind and the affected domain, so the
+    /// same condition observed in consecutive cycles maps to one incident.
+    pub fn dedup_key(&self) -> String {
+        let subject = match self {
+            Self::MdiLost { domain, .. } | Self::Tombstone { domain, .. } => domain,
+            Self::NewFederatedDomain {
+                federated_domain, ..
+            } => federated_domain,
+        };
+        format!("sentri:{}:{}", self.kind(), subject)
+    }
+
+    /// One-line human-readable description
+    pub fn summary(&self) -> String {
+        match self {
+            Self::MdiLost {
+                domain,
+                previous_instance,
+            } => format!(
+                "{} no longer has an MDI instance (previously {})",
+                domain, previous_instance
+            ),
+            Self::NewFederatedDomain {
+                domain,
+                federated_domain,
+            } => format!(
+                "New federated domain {} appeared for {}",
+                federated_domain, domain
+            ),
+            Self::Tombstone {
+                domain,
+                reason,
+                last_seen,
+            } => format!(
+                "{} {} (last seen {})",
+                domain,
+                match reason {
+                    TombstoneReason::RemovedFromInput => "was removed from the monitored list",
+                    TombstoneReason::StoppedResolving => "stopped resolving",
+                },
+                last_seen.to_rfc3339()
+            ),
+        }
+    }
+
+    /// Severity in PagerDuty terms (critical, error, warning or info)
+    pub fn severity(&self) -> &'static str {
+        match self {
+            Self::MdiLost { .. } => "critical",
+            Self::NewFederatedDomain { .. } => "warning",
+            Self::Tombstone { .. } => "info",
+        }
+    }
+}
+
+/// Compares two scan cycles and returns the detected changes
+///
+/// Domains whose current scan failed, or that were not scanned successfully
+/// in the previous cycle, produce no events: a transient error must not look
+/// like a lost MDI instance.
+///
+/// # Arguments
+/// * `previous` - Last successful result per monitored domain
+/// * `current` - Results of the cycle that just finished
+///
+/// # Returns
+/// * `Vec<WatchEvent>` - Detected changes in the order of `current`
+pub fn detect_changes(
+    previous: &HashMap<String, DomainResult>,
+    current: &[DomainResult],
+) -> Vec<WatchEvent> {
+    let monitored: HashSet<&str> = current.iter().map(|r| r.domain.as_str()).collect();
+    let mut events = Vec::new();
+
+    for result in current.iter().filter(|r| r.error.is_none()) {
+        let Some(before) = previous.get(&result.domain) else {
+            continue;
+        };
+
+        if let (Some(instance), None) = (&before.mdi_instance, &result.mdi_instance) {
+            events.push(WatchEvent::MdiLost {
+                domain: result.domain.clone(),
+                previous_instance: instance.clone(),
+            });
+        }
+
+        for federated in &result.federated_domains {
+            if !before.federated_domains.contains(federated)
+                && !monitored.contains(federated.as_str())
+            {
+                events.push(WatchEvent::NewFederatedDomain {
+                    domain: result.domain.clone(),
+                    federated_domain: federated.clone(),
+                });
+            }
+        }
+    }
+
+    events
+}
+
+/// Domains seen by earlier watch cycles
+///
+/// Keeps the last successful result of every monitored domain for
+/// [`detect_changes`] and tombstones domains that are gone.
+#[derive(Debug)]
+pub struct WatchHistory {
+    tombstone_after: u32,
+    last_good: HashMap<String, DomainResult>,
+    last_seen: HashMap<String, DateTime<Utc>>,
+    failures: HashMap<String, u32>,
+}
+
+impl WatchHistory {
+    /// Creates an empty history
+    ///
+    /// # Arguments
+    /// * `tombstone_after` - Consecutive failed scans after which a domain is tombstoned
+    pub fn new(tombstone_after: u32) -> Self {
+        Self {
+            tombstone_after: tombstone_after.max(1),
+            last_good: HashMap::new(),
+            last_seen: HashMap::new(),
+            failures: HashMap::new(),
+        }
+    }
+
+    /// Last successful result per domain
+    pub fn previous(&self) -> &HashMap<String, DomainResult> {
+        &self.last_good
+    }
+
+    /// Records a cycle's results, returning tombstones for domains that are gone
+    ///
+    /// A tombstoned domain is forgotten; if it returns, it is treated as new.
+    ///
+    /// # Arguments
+    /// * `current` - Results of the cycle that just finished
+    /// * `now` - When the cycle finished
+    ///
+    /// # Returns
+    /// * `Vec<WatchEvent>` - Tombstones, removed domains first, in domain order
+    pub fn record(&mut self, current: &[DomainResult], now: DateTime<Utc>) -> Vec<WatchEvent> {
+        let monitored: HashSet<&str> = current.iter().map(|r| r.domain.as_str()).collect();
+        let mut removed: Vec<String> = self
+            .last_good
+            .keys()
+            .filter(|domain| !monitored.contains(domain.as_str()))
+            .cloned()
+            .collect();
+        removed.sort();
+        let mut tombstones: Vec<WatchEvent> = removed
+            .into_iter()
+            .filter_map(|domain| self.tombstone(domain, TombstoneReason::RemovedFromInput))
+            .collect();
+
+        let mut failing = Vec::new();
+        for result in current {
+            if result.error.is_none() {
+                self.last_good.insert(result.domain.clone(), result.clone());
+                self.last_seen.insert(result.domain.clone(), now);
+                self.failures.remove(&result.domain);
+            } else if self.last_good.contains_key(&result.domain) {
+                let failures = self.failures.entry(result.domain.clone()).or_insert(0);
+                *failures += 1;
+                if *failures >= self.tombstone_after {
+                    failing.push(result.domain.clone());
+                }
+            }
+        }
+        failing.sort();
+        tombstones.extend(
+            failing
+                .into_iter()
+                .filter_map(|domain| self.tombstone(domain, TombstoneReason::StoppedResolving)),
+        );
+        tombstones
+    }
+
+    fn tombstone(&mut self, domain: String, reason: TombstoneReason) -> Option<WatchEvent> {
+        self.last_good.remove(&domain);
+        self.failures.remove(&domain);
+        let last_seen = self.last_seen.remove(&domain)?;
+        Some(WatchEvent::Tombstone {
+            domain,
+            reason,
+            last_seen,
+        })
+    }
+}
+
+/// Runs watch mode until interrupted or `max_iterations` cycles completed
+///
+/// # Arguments
+/// * `checker` - Checker used for scanning; its cache is cleared every cycle
+/// * `input_file` - File listing the monitored domains (re-read every cycle)
+/// * `interval` - Pause between the end of one cycle and the start of the next
+/// * `live` - Rate limiter and scan windows; cycles start only inside a window
+/// * `max_iterations` - Optional number of cycles after which to stop
+/// * `tombstone_after` - Consecutive failed scans after which a domain is tombstoned
+/// * `alerters` - Incident destinations for detected events
+///
+/// # Returns
+/// * `Result<()>` - Success, or error if the domain file cannot be read
+pub async fn run_watch(
+    checker: &MdiChecker,
+    input_file: &Path,
+    interval: Duration,
+    live: &LiveConfig,
+    max_iterations: Option<u64>,
+    tombstone_after: u32,
+    alerters: &[Box<dyn Alerter>],
+) -> Result<()> {
+    let mut history = WatchHistory::new(tombstone_after);
+    let mut iteration = 0u64;
+
+    loop {
+        live.wait_for_window().await;
+        iteration += 1;
+        checker.clear_cache();
+
+        let domains = checker.read_domains_from_file(input_file).await?;
+        info!(
+            "Watch cycle {}: scanning {} domains",
+            iteration,
+            domains.len()
+        );
+
+        let current: Vec<DomainResult> = checker
+            .process_lines(&domains, live.limiter())
+            .await
+            .iter()
+            .map(sanitize_domain_result)
+            .collect();
+
+        let mut events = detect_changes(history.previous(), &current);
+        events.extend(history.record(&current, Utc::now()));
+        if events.is_empty() {
+            info!("Watch cycle {}: no changes detected", iteration);
+        }
+
+        for event in &events {
+            warn!("{}", event.summary());
+            println!("{}", serde_json::to_string(event)?);
+
+            for alerter in alerters {
+                if let Err(e) = alerter.trigger(event).await {
+                    error!("Failed to send {} alert: {:#}", alerter.name(), e);
+                }
+            }
+        }
+
+        if max_iterations.is_some_and(|max| iteration >= max) {
+            return Ok(());
+        }
+
+        crate::service::idle(interval).await;
+    }
+}
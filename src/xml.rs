@@ -22,13 +22,189 @@
 //! - Minimizes memory allocations by using references where possible
 //! - Implements early validation to fail fast on invalid responses
 //! - Uses HashSet for O(1) lookups of namespaces and required elements
+//!
+//! # Canonical Form
+//!
+//! [`canonicalize`] writes a response in a canonical form, in the spirit of
+//! XML C14N, so that a response can be identified by [`canonical_sha256`]
+//! regardless of how it was serialized on the wire:
+//!
+//! - The XML declaration, comments, processing instructions and doctype are dropped
+//! - Whitespace around text and between elements is dropped
+//! - Empty elements are written as a start and end tag pair
+//! - Attributes are sorted by name and written in double quotes
+//! - CDATA sections become escaped text; `&`, `<`, `>` (and `"` in
+//!   attributes) are the only escaped characters
+//!
+//! # Character Encodings
+//!
+//! Proxies sometimes re-encode response bodies, for example to UTF-16, or
+//! entity-encode them as a whole. [`decode_response`] turns the raw body into
+//! UTF-8 text before parsing, taking the encoding from (in order) a byte
+//! order mark, the `charset` of the Content-Type header, the byte pattern of
+//! a UTF-16 document without a byte order mark, and the `encoding` of the
+//! XML declaration, defaulting to UTF-8. Bodies that are not valid in their
+//! encoding are rejected with an error naming the encoding.
+//!
+//! # SOAP Faults
+//!
+//! Autodiscover reports some failures, such as an overloaded server or an
+//! unknown domain, as a SOAP Fault instead of a federation response. Both
+//! SOAP 1.1 (`faultcode`, `faultstring`, `detail`) and SOAP 1.2 (`Code`,
+//! `Reason`, `Detail`) faults are read by [`parse_soap_fault`] into a
+//! [`SoapFault`], which is returned as the error of
+//! [`XmlParser::parse_federation_response`] and decides whether the HTTP
+//! client retries the request (see [`SoapFault::is_retriable`]).
 
 use anyhow::{anyhow, Context, Result};
-use quick_xml::{events::Event, Reader};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use sha2::{Digest, Sha256};
 use std::collections::HashSet;
+use std::fmt;
 use tracing::{debug, warn};
 use uuid::Uuid;
 
+/// Fault codes (or detail codes) of an overloaded server, worth retrying
+const SERVER_BUSY_CODES: [&str; 3] = ["ServerBusy", "ErrorServerBusy", "ErrorTimeoutExpired"];
+
+/// Fault codes (or detail codes) of a domain the service rejects
+const INVALID_DOMAIN_CODES: [&str; 2] = ["InvalidDomain", "ErrorInvalidDomain"];
+
+/// Error code of faults reporting an overloaded server
+pub const SERVER_BUSY_ERROR: &str = "ErrorServerBusy";
+
+/// Error code of faults rejecting the requested domain
+pub const INVALID_DOMAIN_ERROR: &str = "ErrorInvalidDomain";
+
+/// Known fault messages, lowercase, with the error code they stand for
+///
+/// Fault strings follow the Accept-Language of the request, which sentri pins
+/// to `en-US` by default; the other phrases are those of common Exchange
+/// locales, for proxies or tenants that localize regardless.
+const LOCALIZED_FAULT_MESSAGES: [(&str, &str); 18] = [
+    ("busy", SERVER_BUSY_ERROR),
+    ("der server ist ausgelastet", SERVER_BUSY_ERROR),
+    ("le serveur est occupé", SERVER_BUSY_ERROR),
+    ("el servidor está ocupado", SERVER_BUSY_ERROR),
+    ("il server è occupato", SERVER_BUSY_ERROR),
+    ("o servidor está ocupado", SERVER_BUSY_ERROR),
+    ("de server is bezet", SERVER_BUSY_ERROR),
+    ("サーバーはビジー", SERVER_BUSY_ERROR),
+    ("服务器忙", SERVER_BUSY_ERROR),
+    ("invalid domain", INVALID_DOMAIN_ERROR),
+    ("ungültige domäne", INVALID_DOMAIN_ERROR),
+    ("domaine non valide", INVALID_DOMAIN_ERROR),
+    ("dominio no válido", INVALID_DOMAIN_ERROR),
+    ("dominio non valido", INVALID_DOMAIN_ERROR),
+    ("domínio inválido", INVALID_DOMAIN_ERROR),
+    ("ongeldig domein", INVALID_DOMAIN_ERROR),
+    ("無効なドメイン", INVALID_DOMAIN_ERROR),
+    ("无效的域", INVALID_DOMAIN_ERROR),
+];
+
+/// Error code of a known, possibly localized, fault message
+///
+/// # Examples
+///
+/// ```
+/// use sentri::xml::{normalize_fault_message, SERVER_BUSY_ERROR};
+///
+/// assert_eq!(
+///     normalize_fault_message("Der Server ist ausgelastet. Versuchen Sie es später erneut."),
+///     Some(SERVER_BUSY_ERROR)
+/// );
+/// assert_eq!(normalize_fault_message("Access denied"), None);
+/// ```
+pub fn normalize_fault_message(message: &str) -> Option<&'static str> {
+    let message = message.to_lowercase();
+    LOCALIZED_FAULT_MESSAGES
+        .iter()
+        .find(|(phrase, _)| message.contains(phrase))
+        .map(|(_, code)| *code)
+}
+
+/// SOAP Fault returned instead of a federation response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoapFault {
+    /// Local name of the fault code, e.g. `Server` or `Receiver`
+    pub code: String,
+    /// Human readable fault string (SOAP 1.1) or reason text (SOAP 1.2)
+    pub reason: String,
+    /// Exchange error code from the fault detail or the SOAP 1.2 subcode,
+    /// e.g. `ErrorServerBusy`
+    pub detail_code: Option<String>,
+}
+
+impl SoapFault {
+    /// Returns true if the service reported it was too busy to answer
+    pub fn is_server_busy(&self) -> bool {
+        self.has_code(&SERVER_BUSY_CODES)
+            || normalize_fault_message(&self.reason) == Some(SERVER_BUSY_ERROR)
+    }
+
+    /// Returns true if the service rejected the requested domain
+    pub fn is_invalid_domain(&self) -> bool {
+        self.has_code(&INVALID_DOMAIN_CODES)
+            || normalize_fault_message(&self.reason) == Some(INVALID_DOMAIN_ERROR)
+    }
+
+    /// Error code of the fault: the detail code, or the code of a known
+    /// (possibly localized) fault string
+    pub fn error_code(&self) -> Option<&str> {
+        self.detail_code
+            .as_deref()
+            .or_else(|| normalize_fault_message(&self.reason))
+    }
+
+    /// Returns true if sending the same request again may succeed
+    ///
+    /// Only server-busy faults are retried; a rejected domain stays rejected,
+    /// and any other fault is treated as permanent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentri::xml::SoapFault;
+    ///
+    /// let fault = SoapFault {
+    ///     code: "Server".into(),
+    ///     reason: "The server cannot service this request right now.".into(),
+    ///     detail_code: Some("ErrorServerBusy".into()),
+    /// };
+    /// assert!(fault.is_retriable());
+    /// ```
+    pub fn is_retriable(&self) -> bool {
+        self.is_server_busy() && !self.is_invalid_domain()
+    }
+
+    fn has_code(&self, codes: &[&str]) -> bool {
+        codes.iter().any(|code| {
+            self.code.eq_ignore_ascii_case(code)
+                || self
+                    .detail_code
+                    .as_deref()
+                    .is_some_and(|detail| detail.eq_ignore_ascii_case(code))
+        })
+    }
+}
+
+impl fmt::Display for SoapFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SOAP fault {}: {}", self.code, self.reason)?;
+        // Localized faults group under the same code as English ones
+        if let Some(error_code) = self.error_code() {
+            write!(f, " ({})", error_code)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SoapFault {}
+
 /// Parser for SOAP XML requests and responses related to Microsoft Autodiscover services
 pub struct XmlParser {
     /// Known valid autodiscover namespaces
@@ -163,6 +339,7 @@ impl XmlParser {
     /// * `Result<FederationInfo>` - Federation info containing discovered domains or an error
     ///
     /// # Error conditions
+    /// * A SOAP Fault, returned as a [`SoapFault`] error
     /// * Empty or malformed XML content
     /// * Missing required elements
     /// * Invalid namespace
@@ -201,6 +378,13 @@ impl XmlParser {
     ) -> Result<crate::core::FederationInfo> {
         debug!("Parsing federation response XML");
 
+        // Responses decoded elsewhere may still start with a byte order mark
+        let xml_content = xml_content.trim_start_matches('\u{feff}');
+
+        if let Some(fault) = parse_soap_fault(xml_content) {
+            return Err(anyhow::Error::new(fault)).context("Autodiscover returned a SOAP fault");
+        }
+
         // Basic XML structure validation check
         self.validate_federation_response_structure(xml_content)
             .context("XML structure validation failed")?;
@@ -465,3 +649,325 @@ impl XmlParser {
         namespace.contains("microsoft.com")
     }
 }
+
+/// Decodes a response `body` into UTF-8 text
+///
+/// See the [module documentation](self#character-encodings) for how the
+/// encoding is chosen. A body that is entity-encoded as a whole
+/// (`&lt;soap:Envelope ...`) is unescaped once.
+///
+/// # Arguments
+/// * `body` - The raw response body
+/// * `content_type` - Value of the Content-Type header, if any
+///
+/// # Returns
+/// * `Result<String>` - The decoded text without byte order mark, or an error
+///   if the body is not valid in its encoding
+///
+/// # Examples
+///
+/// ```
+/// use sentri::xml::decode_response;
+///
+/// // UTF-16LE with a byte order mark, as re-encoded by some proxies
+/// let mut body = vec![0xFF, 0xFE];
+/// body.extend("<a>contoso.com</a>".encode_utf16().flat_map(u16::to_le_bytes));
+/// assert_eq!(decode_response(&body, None)?, "<a>contoso.com</a>");
+///
+/// let latin1 = b"<a>caf\xe9</a>";
+/// assert_eq!(
+///     decode_response(latin1, Some("text/xml; charset=iso-8859-1"))?,
+///     "<a>caf\u{e9}</a>"
+/// );
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn decode_response(body: &[u8], content_type: Option<&str>) -> Result<String> {
+    let text = match Encoding::for_bom(body) {
+        Some((encoding, bom_length)) => decode_strict(encoding, &body[bom_length..])?,
+        None => {
+            let encoding = content_type
+                .and_then(charset_of)
+                .or_else(|| sniff_utf16(body))
+                .or_else(|| declared_encoding(body));
+            match encoding {
+                Some(encoding) => decode_strict(encoding, body)?,
+                None => String::from_utf8(body.to_vec()).map_err(|e| {
+                    anyhow!(
+                        "Response body is not valid UTF-8 and declares no encoding (invalid byte at offset {})",
+                        e.utf8_error().valid_up_to()
+                    )
+                })?,
+            }
+        }
+    };
+
+    // Some proxies HTML-escape the whole document
+    let trimmed = text.trim_start();
+    if trimmed.starts_with("&lt;") {
+        return Ok(quick_xml::escape::unescape(trimmed)
+            .context("Failed to unescape entity-encoded response")?
+            .into_owned());
+    }
+    Ok(text)
+}
+
+/// Decodes `bytes` as `encoding`, failing on malformed sequences
+fn decode_strict(encoding: &'static Encoding, bytes: &[u8]) -> Result<String> {
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|text| text.into_owned())
+        .ok_or_else(|| anyhow!("Response body is not valid {}", encoding.name()))
+}
+
+/// Encoding named by the `charset` parameter of a Content-Type header value
+fn charset_of(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Encoding::for_label(value.trim().trim_matches('"').as_bytes())
+    })
+}
+
+/// UTF-16 without a byte order mark, recognized by its leading `<`
+fn sniff_utf16(body: &[u8]) -> Option<&'static Encoding> {
+    match body {
+        [b'<', 0, _, 0, ..] => Some(UTF_16LE),
+        [0, b'<', 0, _, ..] => Some(UTF_16BE),
+        _ => None,
+    }
+}
+
+/// Encoding named by the XML declaration of an ASCII-compatible body
+fn declared_encoding(body: &[u8]) -> Option<&'static Encoding> {
+    let head = &body[..body.len().min(256)];
+    if !head.starts_with(b"<?xml") {
+        return None;
+    }
+    let end = head.windows(2).position(|window| window == b"?>")?;
+    let declaration = std::str::from_utf8(&head[..end]).ok()?;
+    let value = declaration.split("encoding").nth(1)?;
+    let value = value.trim_start().strip_prefix('=')?.trim_start();
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let label = value[1..].split(quote).next()?;
+    let encoding = Encoding::for_label(label.as_bytes())?;
+    // A body read as ASCII cannot really be UTF-16
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        return None;
+    }
+    Some(encoding)
+}
+
+/// Reads the SOAP Fault in the body of `xml`
+///
+/// # Returns
+/// * `Option<SoapFault>` - The fault; `None` if `xml` is not a well-formed
+///   SOAP message with a fault body
+///
+/// # Examples
+///
+/// ```
+/// use sentri::xml::parse_soap_fault;
+///
+/// let xml = r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+///   <s:Body>
+///     <s:Fault>
+///       <faultcode>s:Client</faultcode>
+///       <faultstring>Invalid domain</faultstring>
+///     </s:Fault>
+///   </s:Body>
+/// </s:Envelope>"#;
+/// let fault = parse_soap_fault(xml).unwrap();
+/// assert_eq!(fault.code, "Client");
+/// assert!(fault.is_invalid_domain());
+/// assert!(!fault.is_retriable());
+/// ```
+pub fn parse_soap_fault(xml: &str) -> Option<SoapFault> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut path: Vec<String> = Vec::new();
+    // Depth of the Fault element within `path`, once found
+    let mut fault_depth = None;
+    let mut fault_closed = false;
+    let mut code = None;
+    let mut reason = None;
+    let mut detail_code = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = local_name(e.name().as_ref())?;
+                if fault_depth.is_none()
+                    && name == "Fault"
+                    && path.last().is_some_and(|parent| parent == "Body")
+                {
+                    fault_depth = Some(path.len() + 1);
+                }
+                path.push(name);
+            }
+            Ok(Event::End(_)) => {
+                if fault_depth == Some(path.len()) {
+                    fault_closed = true;
+                }
+                path.pop();
+            }
+            Ok(Event::Text(e)) => {
+                let Some(depth) = fault_depth.filter(|depth| path.len() > *depth) else {
+                    continue;
+                };
+                let text = e.unescape().ok()?.trim().to_string();
+                let within: Vec<&str> = path[depth..].iter().map(String::as_str).collect();
+                match within.as_slice() {
+                    ["faultcode"] | ["Code", "Value"] => {
+                        code.get_or_insert_with(|| strip_prefix(&text));
+                    }
+                    ["faultstring"] | ["Reason", "Text"] => {
+                        reason.get_or_insert(text);
+                    }
+                    ["Code", "Subcode", "Value"] => {
+                        detail_code.get_or_insert_with(|| strip_prefix(&text));
+                    }
+                    [detail, .., element]
+                        if detail.eq_ignore_ascii_case("detail")
+                            && matches!(*element, "ResponseCode" | "ErrorCode") =>
+                    {
+                        detail_code.get_or_insert_with(|| strip_prefix(&text));
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(_) => return None,
+        }
+    }
+
+    // A truncated fault may lack its code or reason
+    if !fault_closed {
+        return None;
+    }
+    Some(SoapFault {
+        code: code.unwrap_or_default(),
+        reason: reason.unwrap_or_default(),
+        detail_code,
+    })
+}
+
+/// Name of an element without its namespace prefix
+fn local_name(name: &[u8]) -> Option<String> {
+    let name = std::str::from_utf8(name).ok()?;
+    Some(strip_prefix(name))
+}
+
+/// Local part of a qualified name such as `s:Server`
+fn strip_prefix(qualified: &str) -> String {
+    qualified
+        .rsplit_once(':')
+        .map_or(qualified, |(_, local)| local)
+        .to_string()
+}
+
+/// Writes `xml` in canonical form (see the [module documentation](self))
+///
+/// # Examples
+///
+/// ```
+/// use sentri::xml::canonicalize;
+///
+/// let wire = r#"<?xml version="1.0"?>
+/// <!-- served by edge-1 -->
+/// <a z='1' b="&amp;"><b/>
+///   <c><![CDATA[x < y]]></c>
+/// </a>"#;
+/// assert_eq!(
+///     canonicalize(wire)?,
+///     r#"<a b="&amp;" z="1"><b></b><c>x &lt; y</c></a>"#
+/// );
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn canonicalize(xml: &str) -> Result<String> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    reader.expand_empty_elements(true);
+
+    let mut canonical = String::with_capacity(xml.len());
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => write_start_tag(&mut canonical, &e)?,
+            Ok(Event::End(e)) => {
+                canonical.push_str("</");
+                canonical.push_str(std::str::from_utf8(e.name().as_ref())?);
+                canonical.push('>');
+            }
+            Ok(Event::Text(e)) => {
+                let text = e.unescape().context("Failed to unescape text content")?;
+                escape_into(&mut canonical, &text, false);
+            }
+            Ok(Event::CData(e)) => {
+                escape_into(&mut canonical, std::str::from_utf8(&e)?, false);
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => {
+                return Err(anyhow!(
+                    "XML parsing error at position {}: {}",
+                    reader.buffer_position(),
+                    e
+                ))
+                .context("Failed to canonicalize XML");
+            }
+        }
+    }
+    Ok(canonical)
+}
+
+/// Lowercase hex SHA-256 of the canonical form of `xml`
+///
+/// Identical for every serialization of the same document, such as the
+/// same response received with different whitespace or attribute order.
+pub fn canonical_sha256(xml: &str) -> Result<String> {
+    Ok(format!(
+        "{:x}",
+        Sha256::digest(canonicalize(xml)?.as_bytes())
+    ))
+}
+
+fn write_start_tag(canonical: &mut String, element: &BytesStart) -> Result<()> {
+    let mut attributes = Vec::new();
+    for attribute in element.attributes() {
+        let attribute = attribute.context("Invalid attribute")?;
+        let name = std::str::from_utf8(attribute.key.as_ref())?.to_string();
+        let value = attribute
+            .unescape_value()
+            .context("Failed to unescape attribute value")?
+            .into_owned();
+        attributes.push((name, value));
+    }
+    attributes.sort();
+
+    canonical.push('<');
+    canonical.push_str(std::str::from_utf8(element.name().as_ref())?);
+    for (name, value) in attributes {
+        canonical.push(' ');
+        canonical.push_str(&name);
+        canonical.push_str("=\"");
+        escape_into(canonical, &value, true);
+        canonical.push('"');
+    }
+    canonical.push('>');
+    Ok(())
+}
+
+fn escape_into(canonical: &mut String, value: &str, attribute: bool) {
+    for c in value.chars() {
+        match c {
+            '&' => canonical.push_str("&amp;"),
+            '<' => canonical.push_str("&lt;"),
+            '>' => canonical.push_str("&gt;"),
+            '"' if attribute => canonical.push_str("&quot;"),
+            _ => canonical.push(c),
+        }
+    }
+}
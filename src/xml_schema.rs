@@ -0,0 +1,505 @@
+//! Strict schema validation of federation responses
+//!
+//! With `--strict-schema`, every federation response is checked against a
+//! bundled copy of the parts of the Autodiscover SOAP schema (`messages.xsd`
+//! and `types.xsd`) that describe `GetFederationInformationResponseMessage`.
+//! Well-behaved Autodiscover endpoints always conform; violations usually
+//! mean that a proxy, TLS-inspecting middlebox or captive portal rewrote the
+//! response. The lenient parser keeps extracting what it can, and the
+//! violations are reported as structured [`SchemaWarning`]s on the result.
+//!
+//! The bundled schema checks, for every element below the SOAP envelope:
+//!
+//! - Its namespace and whether it may appear at that position
+//! - The order and number of occurrences of its children (`xs:sequence`)
+//! - Its text: enumeration values of `ErrorCode`, non-empty values for
+//!   domains and URIs, and no stray text in element-only content
+//!
+//! SOAP headers and faults are not validated beyond their position.
+//!
+//! # Security Considerations
+//!
+//! Element names and values in warnings come from the remote response; they
+//! are truncated and pass through the sanitize module like every other field
+//! of a result (security:output:sanitize_all_output).
+
+use quick_xml::events::Event;
+use quick_xml::name::ResolveResult;
+use quick_xml::NsReader;
+use serde::{Deserialize, Serialize};
+
+/// SOAP 1.1 envelope namespace
+const SOAP: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+
+/// Autodiscover SOAP service namespace
+const AUTODISCOVER: &str = "http://schemas.microsoft.com/exchange/2010/Autodiscover";
+
+/// Longest value quoted in a warning
+const MAX_QUOTED: usize = 64;
+
+/// Values of the `ErrorCode` enumeration (`types.xsd`)
+const ERROR_CODES: &[&str] = &[
+    "NoError",
+    "RedirectAddress",
+    "RedirectUrl",
+    "InvalidUser",
+    "InvalidRequest",
+    "InvalidSetting",
+    "SettingIsNotAvailable",
+    "ServerBusy",
+    "InvalidDomain",
+    "NotFederated",
+    "InternalServerError",
+];
+
+/// Kind of a schema violation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaViolation {
+    /// The response is not well-formed XML
+    Malformed,
+    /// An element is not allowed at its position
+    UnexpectedElement,
+    /// A required element is missing
+    MissingElement,
+    /// An element occurs more often than allowed
+    TooManyElements,
+    /// An element appears before a sibling it must follow
+    OutOfOrder,
+    /// Text appears in element-only content
+    UnexpectedText,
+    /// The text of an element is not a valid value
+    InvalidValue,
+}
+
+/// A deviation of a response from the Autodiscover schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaWarning {
+    /// Kind of the violation
+    pub violation: SchemaViolation,
+    /// Location of the offending element, e.g. `/Envelope/Body/Response`
+    pub path: String,
+    /// Human-readable description
+    pub message: String,
+}
+
+/// Content allowed in an element
+#[derive(Debug, Clone, Copy)]
+enum Content {
+    /// Any children and text; not validated
+    Any,
+    /// A sequence of child elements and no text
+    Sequence(&'static [Particle]),
+    /// Exactly one of the child elements and no text
+    Choice(&'static [&'static Element]),
+    /// Text only
+    Text(TextType),
+}
+
+/// Type of the text of a simple element
+#[derive(Debug, Clone, Copy)]
+enum TextType {
+    /// Any text, including none
+    String,
+    /// Non-empty text
+    Token,
+    /// One of the listed values
+    Enumeration(&'static [&'static str]),
+}
+
+/// Declaration of an element
+#[derive(Debug)]
+struct Element {
+    namespace: &'static str,
+    name: &'static str,
+    content: Content,
+}
+
+/// Occurrence of an element in a sequence
+#[derive(Debug)]
+struct Particle {
+    element: &'static Element,
+    min: u32,
+    /// Maximum occurrences; `None` for unbounded
+    max: Option<u32>,
+}
+
+const fn particle(element: &'static Element, min: u32, max: Option<u32>) -> Particle {
+    Particle { element, min, max }
+}
+
+const fn text(name: &'static str, text: TextType) -> Element {
+    Element {
+        namespace: AUTODISCOVER,
+        name,
+        content: Content::Text(text),
+    }
+}
+
+static ERROR_CODE: Element = text("ErrorCode", TextType::Enumeration(ERROR_CODES));
+static ERROR_MESSAGE: Element = text("ErrorMessage", TextType::String);
+static APPLICATION_URI: Element = text("ApplicationUri", TextType::Token);
+static DOMAIN: Element = text("Domain", TextType::Token);
+static ENDPOINT: Element = text("Endpoint", TextType::Token);
+static URI: Element = text("Uri", TextType::Token);
+
+static DOMAINS: Element = Element {
+    namespace: AUTODISCOVER,
+    name: "Domains",
+    content: Content::Sequence(&[particle(&DOMAIN, 0, None)]),
+};
+
+static TOKEN_ISSUER: Element = Element {
+    namespace: AUTODISCOVER,
+    name: "TokenIssuer",
+    content: Content::Sequence(&[particle(&ENDPOINT, 0, Some(1)), particle(&URI, 0, Some(1))]),
+};
+
+static TOKEN_ISSUERS: Element = Element {
+    namespace: AUTODISCOVER,
+    name: "TokenIssuers",
+    content: Content::Sequence(&[particle(&TOKEN_ISSUER, 0, None)]),
+};
+
+static RESPONSE: Element = Element {
+    namespace: AUTODISCOVER,
+    name: "Response",
+    content: Content::Sequence(&[
+        particle(&ERROR_CODE, 0, Some(1)),
+        particle(&ERROR_MESSAGE, 0, Some(1)),
+        particle(&APPLICATION_URI, 0, Some(1)),
+        particle(&DOMAINS, 0, Some(1)),
+        particle(&TOKEN_ISSUERS, 0, Some(1)),
+    ]),
+};
+
+static RESPONSE_MESSAGE: Element = Element {
+    namespace: AUTODISCOVER,
+    name: "GetFederationInformationResponseMessage",
+    content: Content::Sequence(&[particle(&RESPONSE, 1, Some(1))]),
+};
+
+static FAULT: Element = Element {
+    namespace: SOAP,
+    name: "Fault",
+    content: Content::Any,
+};
+
+static HEADER: Element = Element {
+    namespace: SOAP,
+    name: "Header",
+    content: Content::Any,
+};
+
+static BODY: Element = Element {
+    namespace: SOAP,
+    name: "Body",
+    content: Content::Choice(&[&RESPONSE_MESSAGE, &FAULT]),
+};
+
+static ENVELOPE: Element = Element {
+    namespace: SOAP,
+    name: "Envelope",
+    content: Content::Sequence(&[particle(&HEADER, 0, Some(1)), particle(&BODY, 1, Some(1))]),
+};
+
+/// Element being validated
+struct Frame {
+    /// Declaration; `None` inside content that is not validated
+    element: Option<&'static Element>,
+    path: String,
+    /// Occurrences of each child, indexed like the declared content
+    counts: Vec<u32>,
+    /// Index of the last child seen in a sequence
+    position: usize,
+    text: String,
+}
+
+impl Frame {
+    fn new(element: Option<&'static Element>, path: String) -> Self {
+        let children = match element.map(|element| element.content) {
+            Some(Content::Sequence(particles)) => particles.len(),
+            Some(Content::Choice(elements)) => elements.len(),
+            _ => 0,
+        };
+        Self {
+            element,
+            path,
+            counts: vec![0; children],
+            position: 0,
+            text: String::new(),
+        }
+    }
+}
+
+/// Validates a federation response against the bundled Autodiscover schema
+///
+/// # Returns
+/// * `Vec<SchemaWarning>` - Every violation found, empty if the response conforms
+///
+/// # Examples
+///
+/// ```
+/// use sentri::xml_schema::{validate_federation_response, SchemaViolation};
+///
+/// let response = r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+///   <s:Body>
+///     <GetFederationInformationResponseMessage xmlns="http://schemas.microsoft.com/exchange/2010/Autodiscover">
+///       <Response>
+///         <ErrorCode>NoError</ErrorCode>
+///         <Domains><Domain>contoso.com</Domain></Domains>
+///         <Injected>proxy</Injected>
+///       </Response>
+///     </GetFederationInformationResponseMessage>
+///   </s:Body>
+/// </s:Envelope>"#;
+///
+/// let warnings = validate_federation_response(response);
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!(warnings[0].violation, SchemaViolation::UnexpectedElement);
+/// assert_eq!(
+///     warnings[0].path,
+///     "/Envelope/Body/GetFederationInformationResponseMessage/Response/Injected"
+/// );
+/// ```
+pub fn validate_federation_response(xml: &str) -> Vec<SchemaWarning> {
+    let mut reader = NsReader::from_str(xml);
+    reader.trim_text(true);
+    reader.expand_empty_elements(true);
+
+    let mut warnings = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut seen_root = false;
+
+    loop {
+        let (namespace, event) = match reader.read_resolved_event() {
+            Ok(event) => event,
+            Err(e) => {
+                warnings.push(SchemaWarning {
+                    violation: SchemaViolation::Malformed,
+                    path: stack
+                        .last()
+                        .map_or_else(|| "/".to_string(), |f| f.path.clone()),
+                    message: format!(
+                        "Malformed XML at position {}: {}",
+                        reader.buffer_position(),
+                        e
+                    ),
+                });
+                return warnings;
+            }
+        };
+        match event {
+            Event::Start(start) => {
+                let namespace = match namespace {
+                    ResolveResult::Bound(namespace) => {
+                        String::from_utf8_lossy(namespace.as_ref()).into_owned()
+                    }
+                    _ => String::new(),
+                };
+                let name = String::from_utf8_lossy(start.local_name().as_ref()).into_owned();
+                let path = match stack.last() {
+                    Some(parent) => format!("{}/{}", parent.path, quote(&name)),
+                    None => format!("/{}", quote(&name)),
+                };
+
+                let element = match stack.last_mut() {
+                    Some(parent) => child(parent, &namespace, &name, &path, &mut warnings),
+                    None if seen_root => None,
+                    None => {
+                        seen_root = true;
+                        let matches = ENVELOPE.namespace == namespace && ENVELOPE.name == name;
+                        if !matches {
+                            warnings.push(unexpected(&path, &namespace, &name));
+                        }
+                        matches.then_some(&ENVELOPE)
+                    }
+                };
+                stack.push(Frame::new(element, path));
+            }
+            Event::Text(text) => {
+                if let Some(frame) = stack.last_mut() {
+                    frame.text.push_str(&String::from_utf8_lossy(&text));
+                }
+            }
+            Event::CData(text) => {
+                if let Some(frame) = stack.last_mut() {
+                    frame.text.push_str(&String::from_utf8_lossy(&text));
+                }
+            }
+            Event::End(_) => {
+                if let Some(frame) = stack.pop() {
+                    close(&frame, &mut warnings);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if let Some(frame) = stack.last() {
+        warnings.push(SchemaWarning {
+            violation: SchemaViolation::Malformed,
+            path: frame.path.clone(),
+            message: "Unexpected end of document".to_string(),
+        });
+    } else if !seen_root {
+        warnings.push(SchemaWarning {
+            violation: SchemaViolation::MissingElement,
+            path: "/".to_string(),
+            message: "Missing SOAP Envelope".to_string(),
+        });
+    }
+    warnings
+}
+
+/// Validates the position of a child of `parent`, returning its declaration
+fn child(
+    parent: &mut Frame,
+    namespace: &str,
+    name: &str,
+    path: &str,
+    warnings: &mut Vec<SchemaWarning>,
+) -> Option<&'static Element> {
+    let content = parent.element?.content;
+    let matches = |element: &Element| element.namespace == namespace && element.name == name;
+    match content {
+        Content::Any => None,
+        Content::Text(_) => {
+            warnings.push(unexpected(path, namespace, name));
+            None
+        }
+        Content::Choice(elements) => {
+            let Some(index) = elements.iter().position(|element| matches(element)) else {
+                warnings.push(unexpected(path, namespace, name));
+                return None;
+            };
+            parent.counts[index] += 1;
+            if parent.counts.iter().sum::<u32>() > 1 {
+                warnings.push(SchemaWarning {
+                    violation: SchemaViolation::TooManyElements,
+                    path: path.to_string(),
+                    message: format!("{} allows a single child", parent.path),
+                });
+            }
+            Some(elements[index])
+        }
+        Content::Sequence(particles) => {
+            let Some(index) = particles.iter().position(|p| matches(p.element)) else {
+                warnings.push(unexpected(path, namespace, name));
+                return None;
+            };
+            parent.counts[index] += 1;
+            if index < parent.position {
+                warnings.push(SchemaWarning {
+                    violation: SchemaViolation::OutOfOrder,
+                    path: path.to_string(),
+                    message: format!(
+                        "{} must precede {}",
+                        name, particles[parent.position].element.name
+                    ),
+                });
+            }
+            parent.position = parent.position.max(index);
+            if particles[index]
+                .max
+                .is_some_and(|max| parent.counts[index] > max)
+            {
+                warnings.push(SchemaWarning {
+                    violation: SchemaViolation::TooManyElements,
+                    path: path.to_string(),
+                    message: format!(
+                        "{} occurs more than {} times",
+                        name,
+                        particles[index].max.unwrap_or_default()
+                    ),
+                });
+            }
+            Some(particles[index].element)
+        }
+    }
+}
+
+/// Validates the text and required children of a completed element
+fn close(frame: &Frame, warnings: &mut Vec<SchemaWarning>) {
+    let Some(element) = frame.element else {
+        return;
+    };
+    let text = frame.text.trim();
+    match element.content {
+        Content::Any => {}
+        Content::Text(TextType::String) => {}
+        Content::Text(TextType::Token) => {
+            if text.is_empty() {
+                warnings.push(SchemaWarning {
+                    violation: SchemaViolation::InvalidValue,
+                    path: frame.path.clone(),
+                    message: format!("{} must not be empty", element.name),
+                });
+            }
+        }
+        Content::Text(TextType::Enumeration(values)) => {
+            if !values.contains(&text) {
+                warnings.push(SchemaWarning {
+                    violation: SchemaViolation::InvalidValue,
+                    path: frame.path.clone(),
+                    message: format!("{} is not a valid {}", quote(text), element.name),
+                });
+            }
+        }
+        Content::Sequence(particles) => {
+            check_no_text(frame, text, warnings);
+            for (particle, count) in particles.iter().zip(&frame.counts) {
+                if *count < particle.min {
+                    warnings.push(SchemaWarning {
+                        violation: SchemaViolation::MissingElement,
+                        path: frame.path.clone(),
+                        message: format!("Missing {}", particle.element.name),
+                    });
+                }
+            }
+        }
+        Content::Choice(elements) => {
+            check_no_text(frame, text, warnings);
+            if frame.counts.iter().all(|count| *count == 0) {
+                let names: Vec<&str> = elements.iter().map(|element| element.name).collect();
+                warnings.push(SchemaWarning {
+                    violation: SchemaViolation::MissingElement,
+                    path: frame.path.clone(),
+                    message: format!("Missing one of {}", names.join(", ")),
+                });
+            }
+        }
+    }
+}
+
+fn check_no_text(frame: &Frame, text: &str, warnings: &mut Vec<SchemaWarning>) {
+    if !text.is_empty() {
+        warnings.push(SchemaWarning {
+            violation: SchemaViolation::UnexpectedText,
+            path: frame.path.clone(),
+            message: format!("Unexpected text {}", quote(text)),
+        });
+    }
+}
+
+fn unexpected(path: &str, namespace: &str, name: &str) -> SchemaWarning {
+    let namespace = if namespace.is_empty() {
+        "no namespace".to_string()
+    } else {
+        quote(namespace)
+    };
+    SchemaWarning {
+        violation: SchemaViolation::UnexpectedElement,
+        path: path.to_string(),
+        message: format!("Unexpected element {} in {}", quote(name), namespace),
+    }
+}
+
+/// Truncates a remote value for inclusion in a warning
+fn quote(value: &str) -> String {
+    match value.char_indices().nth(MAX_QUOTED) {
+        Some((end, _)) => format!("{}...", &value[..end]),
+        None => value.to_string(),
+    }
+}
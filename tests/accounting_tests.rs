@@ -0,0 +1,125 @@
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use clap::Parser;
+use sentri::accounting::{customer, UsageReport, UNTAGGED};
+use sentri::cli::{Cli, Commands};
+use sentri::core::DomainResult;
+use sentri::error_class::ErrorClass;
+use sentri::sinks::{ResultSink, UsageReportSink};
+
+fn result(domain: &str, customer: Option<&str>, second: u32) -> DomainResult {
+    let at = Utc.with_ymd_and_hms(2026, 5, 4, 9, 0, second).unwrap();
+    let mut result = DomainResult {
+        domain: domain.to_string(),
+        processing_time_ms: 100,
+        checked_at: at,
+        completed_at: at + chrono::Duration::milliseconds(100),
+        ..Default::default()
+    };
+    if let Some(customer) = customer {
+        result
+            .tags
+            .insert("customer".to_string(), customer.to_string());
+    }
+    result
+}
+
+#[test]
+fn test_customer_of_result() {
+    assert_eq!(
+        customer(&result("contoso.com", Some("Acme Corp"), 0)),
+        "Acme Corp"
+    );
+    assert_eq!(customer(&result("tailspintoys.com", None, 0)), UNTAGGED);
+}
+
+#[test]
+fn test_usage_report_attributes_requests() {
+    let mut report = UsageReport::default();
+    report.add(&result("contoso.com", Some("Acme Corp"), 1));
+    report.add(&DomainResult {
+        from_cache: true,
+        ..result("fabrikam.com", Some("Acme Corp"), 0)
+    });
+    report.add(&DomainResult {
+        error: Some("Request timed out".to_string()),
+        error_class: Some(ErrorClass::Timeout),
+        ..result("contoso.com", Some("Acme Corp"), 3)
+    });
+    report.add(&DomainResult {
+        error: Some("Invalid domain".to_string()),
+        error_class: Some(ErrorClass::InvalidDomain),
+        processing_time_ms: 0,
+        ..result("woodgrovebank.com", Some("Globex"), 2)
+    });
+
+    let usage = report.usage();
+    let customers: Vec<_> = usage.iter().map(|usage| usage.customer.as_str()).collect();
+    assert_eq!(customers, ["Acme Corp", "Globex"]);
+
+    let acme = &usage[0];
+    assert_eq!(
+        (
+            acme.domains,
+            acme.requests,
+            acme.cache_hits,
+            acme.failed,
+            acme.compute_ms
+        ),
+        (3, 2, 1, 1, 300)
+    );
+    assert_eq!(
+        acme.first_checked_at,
+        result("fabrikam.com", None, 0).checked_at
+    );
+    assert_eq!(
+        acme.last_completed_at,
+        result("contoso.com", None, 3).completed_at
+    );
+
+    let globex = &usage[1];
+    assert_eq!((globex.domains, globex.requests, globex.failed), (1, 0, 1));
+}
+
+#[tokio::test]
+async fn test_usage_report_sink_writes_csv() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_usage_{}.csv", uuid::Uuid::new_v4()));
+    let mut sink = UsageReportSink::new(&path);
+    assert_eq!(sink.name(), "usage-report");
+    sink.write(&result("contoso.com", Some("Acme, Inc"), 0))
+        .await?;
+    sink.write(&result("tailspintoys.com", None, 1)).await?;
+    sink.flush().await?;
+    assert!(!path.exists());
+    sink.close().await?;
+
+    let content = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "customer,domains,requests,cache_hits,failed,compute_ms,first_checked_at,last_completed_at",
+            "(untagged),1,1,0,0,100,2026-05-04T09:00:01+00:00,2026-05-04T09:00:01.100+00:00",
+            "\"Acme, Inc\",1,1,0,0,100,2026-05-04T09:00:00+00:00,2026-05-04T09:00:00.100+00:00",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_batch_usage_report_option() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.csv",
+        "--usage-report",
+        "usage.csv",
+    ])?;
+    let Commands::Batch { usage_report, .. } = &cli.command else {
+        panic!("Expected Batch command");
+    };
+    assert_eq!(usage_report.as_deref(), Some("usage.csv".as_ref()));
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use clap::Parser;
+use sentri::attribution::{EndpointAnomaly, EndpointKind, IpRanges, SERVICE_TAGS_FILE};
+use sentri::cli::Cli;
+use sentri::core::DomainResult;
+use std::net::IpAddr;
+
+const SERVICE_TAGS: &str = r#"{
+  "changeNumber": 42,
+  "cloud": "Public",
+  "values": [
+    {
+      "name": "AzureCloud",
+      "id": "AzureCloud",
+      "properties": { "addressPrefixes": ["198.51.100.0/24", "2001:db8::/32"] }
+    },
+    {
+      "name": "AzureActiveDirectory",
+      "id": "AzureActiveDirectory",
+      "properties": { "addressPrefixes": ["198.51.100.128/25"] }
+    }
+  ]
+}"#;
+
+fn ip(address: &str) -> IpAddr {
+    address.parse().unwrap()
+}
+
+#[test]
+fn test_bundled_ranges() {
+    let ranges = IpRanges::bundled();
+    assert!(!ranges.is_empty());
+
+    // Exchange Online Protection is more specific than the Microsoft supernet
+    assert_eq!(ranges.attribute(ip("52.96.10.1")), Some("ExchangeOnline"));
+    assert_eq!(
+        ranges.attribute(ip("40.126.1.1")),
+        Some("AzureActiveDirectory")
+    );
+    assert_eq!(
+        ranges.attribute(ip("2a01:111:f400::1")),
+        Some("ExchangeOnline")
+    );
+    assert!(!ranges.is_microsoft(ip("203.0.113.10")));
+}
+
+#[test]
+fn test_service_tags_longest_prefix_match() -> Result<()> {
+    let ranges = IpRanges::from_service_tags_json(SERVICE_TAGS)?;
+    assert_eq!(ranges.len(), 3);
+    assert_eq!(ranges.attribute(ip("198.51.100.1")), Some("AzureCloud"));
+    assert_eq!(
+        ranges.attribute(ip("198.51.100.200")),
+        Some("AzureActiveDirectory")
+    );
+    assert_eq!(ranges.attribute(ip("2001:db8::1")), Some("AzureCloud"));
+    assert_eq!(ranges.attribute(ip("40.107.1.1")), None);
+    Ok(())
+}
+
+#[test]
+fn test_service_tags_rejects_empty_document() {
+    assert!(IpRanges::from_service_tags_json(r#"{"values": []}"#).is_err());
+    assert!(IpRanges::from_service_tags_json("not json").is_err());
+}
+
+#[test]
+fn test_load_prefers_data_directory() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_data_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+
+    // Missing file falls back to the bundled ranges
+    assert_eq!(IpRanges::load(Some(&dir))?.len(), IpRanges::bundled().len());
+
+    std::fs::write(dir.join(SERVICE_TAGS_FILE), SERVICE_TAGS)?;
+    assert_eq!(IpRanges::load(Some(&dir))?.len(), 3);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_check_endpoint_reports_external_addresses_only() {
+    let ranges = IpRanges::bundled();
+
+    assert!(ranges
+        .check_endpoint(
+            "contoso.com",
+            EndpointKind::Mx,
+            "contoso-com.mail.protection.outlook.com",
+            &[ip("52.101.1.1")],
+        )
+        .is_none());
+
+    let anomaly = ranges
+        .check_endpoint(
+            "contoso.com",
+            EndpointKind::Autodiscover,
+            "autodiscover.contoso.com",
+            &[ip("52.101.1.1"), ip("203.0.113.7")],
+        )
+        .expect("external address should be flagged");
+    assert_eq!(anomaly.kind, EndpointKind::Autodiscover);
+    assert_eq!(anomaly.addresses, vec![ip("203.0.113.7")]);
+}
+
+#[test]
+fn test_anomalies_serialization() -> Result<()> {
+    let mut result = DomainResult {
+        domain: "contoso.com".to_string(),
+        ..Default::default()
+    };
+    let json = serde_json::to_value(&result)?;
+    assert!(json.get("endpoint_anomalies").is_none());
+
+    result.endpoint_anomalies.push(EndpointAnomaly {
+        domain: "contoso.com".to_string(),
+        kind: EndpointKind::Mx,
+        host: "mx.gateway.example".to_string(),
+        addresses: vec![ip("203.0.113.7")],
+    });
+    let json = serde_json::to_value(&result)?;
+    assert_eq!(json["endpoint_anomalies"][0]["kind"], "mx");
+    assert_eq!(json["endpoint_anomalies"][0]["addresses"][0], "203.0.113.7");
+    Ok(())
+}
+
+#[test]
+fn test_attribution_flags_are_global() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "single",
+        "--domain",
+        "contoso.com",
+        "--attribute-ips",
+        "--data-dir",
+        "/var/lib/sentri",
+    ])?;
+    assert!(cli.attribute_ips);
+    assert_eq!(
+        cli.data_dir.as_deref().and_then(|p| p.to_str()),
+        Some("/var/lib/sentri")
+    );
+    Ok(())
+}
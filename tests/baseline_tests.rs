@@ -0,0 +1,128 @@
+use anyhow::Result;
+use clap::Parser;
+use sentri::baseline::{Baseline, Drift};
+use sentri::cli::{BaselineAction, Cli, Commands};
+use sentri::core::{DomainResult, MdiGeneration};
+
+fn result(domain: &str, federated: &[&str], mdi: Option<&str>) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        tenant: Some("contoso".to_string()),
+        federated_domains: federated.iter().map(|d| d.to_string()).collect(),
+        mdi_instance: mdi.map(str::to_string),
+        mdi_generation: mdi.map(|_| MdiGeneration::Legacy),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_unchanged_scan_has_no_drift() {
+    let results = vec![result(
+        "contoso.com",
+        &["contoso.com", "contoso.net"],
+        Some("contososensorapi.atp.azure.com"),
+    )];
+    let baseline = Baseline::from_results(&results);
+
+    // Federation order is irrelevant
+    let rescan = vec![result(
+        "contoso.com",
+        &["contoso.net", "contoso.com"],
+        Some("contososensorapi.atp.azure.com"),
+    )];
+    assert!(baseline.check(&rescan).is_empty());
+}
+
+#[test]
+fn test_detects_drifts() {
+    let baseline = Baseline::from_results(&[
+        result(
+            "contoso.com",
+            &["contoso.com", "contoso.net"],
+            Some("contososensorapi.atp.azure.com"),
+        ),
+        result("gone.com", &["gone.com"], None),
+    ]);
+
+    let mut changed = result("contoso.com", &["contoso.com", "shadow.com"], None);
+    changed.tenant = Some("fabrikam".to_string());
+    let drifts = baseline.check(&[changed, result("new.com", &[], None)]);
+
+    assert_eq!(
+        drifts,
+        vec![
+            Drift::TenantChanged {
+                domain: "contoso.com".to_string(),
+                expected: Some("contoso".to_string()),
+                actual: Some("fabrikam".to_string()),
+            },
+            Drift::FederationChanged {
+                domain: "contoso.com".to_string(),
+                added: vec!["shadow.com".to_string()],
+                removed: vec!["contoso.net".to_string()],
+            },
+            Drift::MdiChanged {
+                domain: "contoso.com".to_string(),
+                expected: Some("contososensorapi.atp.azure.com".to_string()),
+                actual: None,
+            },
+            Drift::DomainMissing {
+                domain: "gone.com".to_string(),
+                last_seen: baseline.created_at,
+            },
+            Drift::DomainAdded {
+                domain: "new.com".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_failed_scans_are_not_compared() {
+    let baseline = Baseline::from_results(&[result("contoso.com", &["contoso.com"], None)]);
+    let failed = DomainResult {
+        domain: "contoso.com".to_string(),
+        error: Some("timeout".to_string()),
+        ..Default::default()
+    };
+    assert!(baseline.check(&[failed]).is_empty());
+}
+
+#[tokio::test]
+async fn test_baseline_round_trip() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_baseline_{}.json", uuid::Uuid::new_v4()));
+    let baseline = Baseline::from_results(&[result(
+        "contoso.com",
+        &["contoso.com"],
+        Some("contososensorapi.atp.azure.com"),
+    )]);
+
+    baseline.save(&path).await?;
+    let loaded = Baseline::load(&path).await?;
+    assert_eq!(loaded.domains, baseline.domains);
+    assert_eq!(loaded.created_at, baseline.created_at);
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_baseline_subcommand_parsing() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "baseline",
+        "check",
+        "--results-file",
+        "results.jsonl",
+        "--baseline-file",
+        "baseline.json",
+    ])?;
+
+    match &cli.command {
+        Commands::Baseline {
+            action: BaselineAction::Check { baseline_file, .. },
+        } => assert_eq!(baseline_file.to_str(), Some("baseline.json")),
+        _ => panic!("Expected baseline check command"),
+    }
+    Ok(())
+}
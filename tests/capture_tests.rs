@@ -0,0 +1,205 @@
+use anyhow::Result;
+use sentri::capture::{
+    encrypted_response_path, read_index, response_path, CaptureStats, Evidence, ResponseStore,
+    INDEX_FILE,
+};
+use sentri::encryption::StorageKey;
+use sentri::xml::canonical_sha256;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const CONTOSO: &str = "<Envelope><Domain>contoso.com</Domain></Envelope>";
+const FABRIKAM: &str = "<Envelope><Domain>fabrikam.com</Domain></Envelope>";
+
+fn capture_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_capture_{}", uuid::Uuid::new_v4()))
+}
+
+#[tokio::test]
+async fn test_identical_responses_are_stored_once() -> Result<()> {
+    let dir = capture_dir();
+    let store = ResponseStore::open(&dir, None).await?;
+
+    let first = store.capture("contoso.com", CONTOSO).await?;
+    let second = store.capture("contoso.net", CONTOSO).await?;
+    let third = store.capture("fabrikam.com", FABRIKAM).await?;
+
+    assert_eq!(first, second);
+    assert_ne!(first, third);
+    assert_eq!(first.len(), 64);
+    assert_eq!(
+        store.stats(),
+        CaptureStats {
+            captured: 3,
+            deduplicated: 1
+        }
+    );
+
+    // Bodies are kept byte for byte under their hash
+    assert_eq!(
+        std::fs::read_to_string(store.response_path(&first))?,
+        CONTOSO
+    );
+    let stored = walk(&dir.join("responses"));
+    assert_eq!(stored.len(), 2, "{:?}", stored);
+
+    // Every domain keeps its own index entry
+    let index = read_index(&dir, None).await?;
+    let domains: Vec<(&str, &str)> = index
+        .iter()
+        .map(|entry| (entry.domain.as_str(), entry.sha256.as_str()))
+        .collect();
+    assert_eq!(
+        domains,
+        [
+            ("contoso.com", first.as_str()),
+            ("contoso.net", first.as_str()),
+            ("fabrikam.com", third.as_str())
+        ]
+    );
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_index_accumulates_across_runs() -> Result<()> {
+    let dir = capture_dir();
+    let hash = ResponseStore::open(&dir, None)
+        .await?
+        .capture("contoso.com", CONTOSO)
+        .await?;
+
+    let store = ResponseStore::open(&dir, None).await?;
+    store.capture("contoso.com", CONTOSO).await?;
+    assert_eq!(store.stats().deduplicated, 1);
+    assert_eq!(read_index(&dir, None).await?.len(), 2);
+    assert!(response_path(&dir, &hash).starts_with(dir.join("responses").join(&hash[..2])));
+
+    std::fs::write(dir.join(INDEX_FILE), "not json\n")?;
+    let error = read_index(&dir, None).await.unwrap_err();
+    assert!(format!("{:#}", error).contains("line 1"));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_evidence_matches_findings_by_canonical_hash() -> Result<()> {
+    let dir = capture_dir();
+    let store = ResponseStore::open(&dir, None).await?;
+    store.capture("contoso.com", CONTOSO).await?;
+    let stored = store.capture("fabrikam.com", FABRIKAM).await?;
+
+    let mut evidence = Evidence::load(&dir, None).await?;
+    // Findings carry the hash of the canonical form, not of the raw bytes
+    let finding = canonical_sha256(&format!("<?xml version=\"1.0\"?>\n{}", FABRIKAM))?;
+    assert_eq!(
+        evidence.find("FABRIKAM.com", &finding).await?,
+        Some(response_path(&dir, &stored))
+    );
+    assert_eq!(evidence.find("contoso.com", &finding).await?, None);
+    assert_eq!(evidence.find("unknown.com", &finding).await?, None);
+
+    // A stored response that was altered no longer matches
+    std::fs::write(response_path(&dir, &stored), CONTOSO)?;
+    let mut evidence = Evidence::load(&dir, None).await?;
+    assert_eq!(evidence.find("fabrikam.com", &finding).await?, None);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_evidence_skips_unreadable_responses() -> Result<()> {
+    let dir = capture_dir();
+    let store = ResponseStore::open(&dir, None).await?;
+    let corrupt = store.capture("fabrikam.com", CONTOSO).await?;
+    let valid = store.capture("fabrikam.com", FABRIKAM).await?;
+    std::fs::write(response_path(&dir, &corrupt), [0xff, 0xfe, 0x00])?;
+
+    // The corrupt capture does not hide the domain's valid one
+    let finding = canonical_sha256(FABRIKAM)?;
+    let mut evidence = Evidence::load(&dir, None).await?;
+    assert_eq!(
+        evidence.find("fabrikam.com", &finding).await?,
+        Some(response_path(&dir, &valid))
+    );
+    assert_eq!(
+        evidence
+            .find("fabrikam.com", &canonical_sha256(CONTOSO)?)
+            .await?,
+        None
+    );
+
+    // Only a domain none of whose captures can be read is an error
+    std::fs::remove_file(response_path(&dir, &valid))?;
+    let mut evidence = Evidence::load(&dir, None).await?;
+    let error = evidence.find("fabrikam.com", &finding).await.unwrap_err();
+    assert!(format!("{:#}", error).contains("None of the responses captured for fabrikam.com"));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_encrypted_capture_directory() -> Result<()> {
+    let dir = capture_dir();
+    let key = Arc::new(StorageKey::from_base64(&StorageKey::generate())?);
+    let store = ResponseStore::open(&dir, Some(Arc::clone(&key))).await?;
+    store.capture("contoso.com", CONTOSO).await?;
+    let stored = store.capture("fabrikam.com", FABRIKAM).await?;
+    drop(store);
+    let store = ResponseStore::open(&dir, Some(Arc::clone(&key))).await?;
+    store.capture("fabrikam.net", FABRIKAM).await?;
+    assert_eq!(store.stats().deduplicated, 1);
+
+    // Neither domains, bodies nor their hashes are readable on disk
+    let path = encrypted_response_path(&dir, &key, &stored);
+    assert_eq!(store.response_path(&stored), path);
+    let mut files = walk(&dir);
+    files.retain(|file| file.extension().is_some_and(|ext| ext == "enc"));
+    assert_eq!(files.len(), 2, "{:?}", files);
+    for file in walk(&dir) {
+        let content = String::from_utf8_lossy(&std::fs::read(&file)?).into_owned();
+        assert!(!content.contains("fabrikam"), "{}", file.display());
+        assert!(!file.to_string_lossy().contains(&stored));
+    }
+
+    let index = read_index(&dir, Some(&key)).await?;
+    let domains: Vec<&str> = index.iter().map(|entry| entry.domain.as_str()).collect();
+    assert_eq!(domains, ["contoso.com", "fabrikam.com", "fabrikam.net"]);
+    assert!(read_index(&dir, None).await.is_err());
+
+    let finding = canonical_sha256(FABRIKAM)?;
+    let mut evidence = Evidence::load(&dir, Some(Arc::clone(&key))).await?;
+    assert_eq!(evidence.find("fabrikam.com", &finding).await?, Some(path));
+
+    // A directory keeps the form it was created in
+    assert!(ResponseStore::open(&dir, None).await.is_err());
+    let other = Arc::new(StorageKey::from_base64(&StorageKey::generate())?);
+    assert!(ResponseStore::open(&dir, Some(other)).await.is_err());
+    let plain = capture_dir();
+    ResponseStore::open(&plain, None)
+        .await?
+        .capture("contoso.com", CONTOSO)
+        .await?;
+    assert!(ResponseStore::open(&plain, Some(key)).await.is_err());
+
+    std::fs::remove_dir_all(dir)?;
+    std::fs::remove_dir_all(plain)?;
+    Ok(())
+}
+
+fn walk(dir: &std::path::Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(walk(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
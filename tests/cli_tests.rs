@@ -1,7 +1,12 @@
 use anyhow::Result;
-use clap::Parser;
-use sentri::cli::{Cli, Commands};
-use std::path::PathBuf;
+use clap::{CommandFactory, Parser};
+use sentri::cli::{Cli, Commands, ServiceAction};
+use sentri::depth::Depth;
+use sentri::env_profile::EnvProfile;
+use sentri::graph::GraphFormat;
+use sentri::output::OutputFormat;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[test]
 fn test_cli_creation() -> Result<()> {
@@ -47,9 +52,13 @@ fn test_cli_batch_command() -> Result<()> {
             output_file,
             chunk_size,
             rate_limit,
+            ..
         } => {
             // Compare paths as strings for equality check
-            assert_eq!(input_file.to_str(), input_file.to_str());
+            assert_eq!(
+                input_file.as_deref().and_then(|path| path.to_str()),
+                Some("input.txt")
+            );
             // Check output_file is Some variant with correct path
             if let Some(path) = output_file {
                 if let Some(of) = output_file.as_ref() {
@@ -95,6 +104,49 @@ fn test_cli_with_concurrent_requests() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_cli_long_version() {
+    let version = Cli::command().render_long_version();
+    assert!(version.starts_with(&format!("sentri {} (commit ", env!("CARGO_PKG_VERSION"))));
+    assert!(version.contains(sentri::build_info::BUILD_DATE));
+}
+
+#[test]
+fn test_cli_federation_command() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "federation", "--domain", "contoso.com"])?;
+    match &cli.command {
+        Commands::Federation { domain } => assert_eq!(domain, "contoso.com"),
+        _ => panic!("Expected Federation command"),
+    }
+    assert_eq!(cli.format, None);
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--format",
+        "csv",
+        "federation",
+        "-d",
+        "contoso.com",
+    ])?;
+    assert_eq!(cli.format, Some(OutputFormat::Csv));
+    assert!(Cli::try_parse_from(["sentri", "federation"]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_tenant_command() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "tenant", "--name", "contoso"])?;
+    match &cli.command {
+        Commands::Tenant { name } => assert_eq!(name, "contoso"),
+        _ => panic!("Expected Tenant command"),
+    }
+
+    let cli = Cli::try_parse_from(["sentri", "--depth", "fast", "tenant", "-n", "fabrikam"])?;
+    assert!(matches!(&cli.command, Commands::Tenant { name } if name == "fabrikam"));
+    assert!(Cli::try_parse_from(["sentri", "tenant"]).is_err());
+    Ok(())
+}
+
 #[test]
 fn test_cli_with_timeout() -> Result<()> {
     let args = vec![
@@ -119,3 +171,608 @@ fn test_cli_with_timeout() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_cli_with_max_response_size() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "example.com"])?;
+    assert_eq!(cli.max_response_size, 1024 * 1024);
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "single",
+        "--domain",
+        "example.com",
+        "--max-response-size",
+        "65536",
+    ])?;
+    assert_eq!(cli.max_response_size, 65536);
+
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "--max-response-size",
+        "1MB",
+        "single",
+        "--domain",
+        "example.com",
+    ])
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_with_slow_host_threshold() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "example.com"])?;
+    assert_eq!(cli.slow_host_threshold_ms, None);
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--slow-host-threshold-ms",
+        "1500",
+        "single",
+        "--domain",
+        "example.com",
+    ])?;
+    assert_eq!(cli.slow_host_threshold_ms, Some(1500));
+    Ok(())
+}
+
+#[test]
+fn test_cli_with_accept_language() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "example.com"])?;
+    assert_eq!(cli.accept_language, "en-US");
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--accept-language",
+        "de-DE",
+        "single",
+        "--domain",
+        "example.com",
+    ])?;
+    assert_eq!(cli.accept_language, "de-DE");
+    Ok(())
+}
+
+#[test]
+fn test_cli_with_expected_binary_sha256() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "example.com"])?;
+    assert_eq!(cli.expected_binary_sha256, None);
+
+    let hex = "ab".repeat(32);
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "single",
+        "--domain",
+        "example.com",
+        "--expected-binary-sha256",
+        &hex,
+    ])?;
+    assert_eq!(cli.expected_binary_sha256, Some(hex));
+    Ok(())
+}
+
+#[test]
+fn test_cli_with_strict_egress() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "example.com"])?;
+    assert!(!cli.strict_egress);
+    assert!(cli.egress_allow.is_empty());
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--strict-egress",
+        "--egress-allow",
+        "splunk.corp.example,*.intranet.example",
+        "--egress-allow",
+        "proxy.corp.example",
+        "single",
+        "--domain",
+        "example.com",
+    ])?;
+    assert!(cli.strict_egress);
+    assert_eq!(
+        cli.egress_allow,
+        vec![
+            "splunk.corp.example",
+            "*.intranet.example",
+            "proxy.corp.example"
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cli_with_trace_file() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "example.com"])?;
+    assert_eq!(cli.trace_file, None);
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--trace-file",
+        "trace.json",
+    ])?;
+    assert_eq!(cli.trace_file, Some(PathBuf::from("trace.json")));
+    Ok(())
+}
+
+#[test]
+fn test_cli_with_timestamp_bucket() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "example.com"])?;
+    assert_eq!(cli.timestamp_bucket, None);
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--timestamp-bucket",
+        "1h",
+    ])?;
+    assert_eq!(cli.timestamp_bucket, Some(Duration::from_secs(3_600)));
+
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "--timestamp-bucket",
+        "hourly",
+        "single",
+        "--domain",
+        "example.com",
+    ])
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_lookup_and_index() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "lookup",
+        "--results-file",
+        "results.jsonl",
+        "--domain",
+        "contoso.com",
+        "-d",
+        "fabrikam.com",
+    ])?;
+    let Commands::Lookup {
+        results_file,
+        domains,
+    } = &cli.command
+    else {
+        panic!("Expected Lookup command");
+    };
+    assert_eq!(results_file, &PathBuf::from("results.jsonl"));
+    assert_eq!(domains, &["contoso.com", "fabrikam.com"]);
+    assert!(Cli::try_parse_from(["sentri", "lookup", "--results-file", "results.jsonl"]).is_err());
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--output-file",
+        "results.jsonl",
+        "--index",
+    ])?;
+    assert!(matches!(cli.command, Commands::Batch { index: true, .. }));
+    assert!(
+        Cli::try_parse_from(["sentri", "batch", "--input-file", "domains.txt", "--index"]).is_err()
+    );
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--output-file",
+        "results.jsonl",
+        "--append",
+        "--index",
+    ])
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_quiet_ndjson() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "batch", "--input-file", "domains.txt"])?;
+    assert!(!cli.quiet);
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--quiet",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--format",
+        "ndjson",
+    ])?;
+    assert!(cli.quiet);
+    assert_eq!(cli.format, Some(OutputFormat::Jsonl));
+
+    let cli = Cli::try_parse_from(["sentri", "single", "-d", "contoso.com", "-q"])?;
+    assert!(cli.quiet);
+    Ok(())
+}
+
+#[test]
+fn test_cli_global_format() -> Result<()> {
+    Cli::command().debug_assert();
+
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "contoso.com"])?;
+    assert_eq!(cli.format, None);
+
+    let cli = Cli::try_parse_from(["sentri", "--format", "table", "single", "-d", "contoso.com"])?;
+    assert_eq!(cli.format, Some(OutputFormat::Table));
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--format",
+        "csv",
+    ])?;
+    assert_eq!(cli.format, Some(OutputFormat::Csv));
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "lookup",
+        "-r",
+        "results.jsonl",
+        "-d",
+        "contoso.com",
+        "--format",
+        "json",
+    ])?;
+    assert_eq!(cli.format, Some(OutputFormat::Json));
+
+    // Graphs keep their own format flag
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "graph",
+        "--results-file",
+        "results.jsonl",
+        "--graph-format",
+        "graphml",
+    ])?;
+    assert!(matches!(
+        cli.command,
+        Commands::Graph {
+            graph_format: GraphFormat::Graphml,
+            ..
+        }
+    ));
+    let cli = Cli::try_parse_from(["sentri", "graph", "-r", "results.jsonl", "-f", "json"])?;
+    assert!(matches!(
+        cli.command,
+        Commands::Graph {
+            graph_format: GraphFormat::Json,
+            ..
+        }
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_cli_report() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--format",
+        "markdown",
+        "report",
+        "-r",
+        "results.jsonl",
+        "-o",
+        "summary.md",
+    ])?;
+    assert_eq!(cli.format, Some(OutputFormat::Markdown));
+    match cli.command {
+        Commands::Report {
+            results_file,
+            output_file,
+        } => {
+            assert_eq!(results_file, PathBuf::from("results.jsonl"));
+            assert_eq!(output_file, Some(PathBuf::from("summary.md")));
+        }
+        _ => panic!("Expected Report command"),
+    }
+    assert!(Cli::try_parse_from(["sentri", "report"]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_schema() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "schema"])?;
+    assert!(matches!(cli.command, Commands::Schema));
+    Ok(())
+}
+
+#[test]
+fn test_cli_fields() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "single",
+        "--domain",
+        "contoso.com",
+        "--fields",
+        "domain,tenant,mdi_instance",
+    ])?;
+    assert_eq!(cli.fields, vec!["domain", "tenant", "mdi_instance"]);
+    Ok(())
+}
+
+#[test]
+fn test_cli_service_unit() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "service",
+        "unit",
+        "--watchdog",
+        "2m",
+        "--",
+        "serve",
+        "--state-dir",
+        "/var/lib/sentri",
+    ])?;
+    match cli.command {
+        Commands::Service {
+            action:
+                ServiceAction::Unit {
+                    user,
+                    watchdog,
+                    args,
+                },
+        } => {
+            assert_eq!(user, None);
+            assert_eq!(watchdog, Some(Duration::from_secs(120)));
+            assert_eq!(args, ["serve", "--state-dir", "/var/lib/sentri"]);
+        }
+        _ => panic!("Expected Service command"),
+    }
+    assert!(Cli::try_parse_from(["sentri", "service", "unit"]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_dns_timeout_and_attempts() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "contoso.com"])?;
+    assert_eq!(cli.dns_timeout_ms, 5000);
+    assert_eq!(cli.dns_attempts, 2);
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--dns-timeout-ms",
+        "15000",
+        "single",
+        "--domain",
+        "contoso.com",
+        "--dns-attempts",
+        "4",
+    ])?;
+    assert_eq!(cli.dns_timeout_ms, 15000);
+    assert_eq!(cli.dns_attempts, 4);
+
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "--dns-attempts",
+        "0",
+        "single",
+        "-d",
+        "contoso.com"
+    ])
+    .is_err());
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "--dns-timeout-ms",
+        "0",
+        "single",
+        "-d",
+        "contoso.com"
+    ])
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_dns_pipeline() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "contoso.com"])?;
+    assert_eq!(cli.dns_pipeline_workers, None);
+    assert_eq!(cli.dns_pipeline_depth, 64);
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--dns-pipeline-workers",
+        "8",
+        "--dns-pipeline-depth",
+        "128",
+        "batch",
+        "--input-file",
+        "domains.txt",
+    ])?;
+    assert_eq!(cli.dns_pipeline_workers, Some(8));
+    assert_eq!(cli.dns_pipeline_depth, 128);
+
+    // The depth only applies to pipeline workers
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "--dns-pipeline-depth",
+        "128",
+        "single",
+        "-d",
+        "contoso.com"
+    ])
+    .is_err());
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "--dns-pipeline-workers",
+        "0",
+        "single",
+        "-d",
+        "contoso.com"
+    ])
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_mdi_dns_budget() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "contoso.com"])?;
+    assert_eq!(cli.mdi_dns_rate_limit, None);
+    assert_eq!(cli.mdi_dns_concurrency, None);
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--mdi-dns-rate-limit",
+        "600",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--mdi-dns-concurrency",
+        "50",
+    ])?;
+    assert_eq!(cli.mdi_dns_rate_limit, Some(600));
+    assert_eq!(cli.mdi_dns_concurrency, Some(50));
+
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "--mdi-dns-rate-limit",
+        "0",
+        "single",
+        "-d",
+        "contoso.com"
+    ])
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_roaming_retry() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "contoso.com"])?;
+    assert!(!cli.roaming_retry);
+    assert!(cli.roaming_resolvers.is_empty());
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--roaming-retry",
+        "--roaming-resolver",
+        "192.0.2.53,192.0.2.54:5353",
+        "batch",
+        "--input-file",
+        "domains.txt",
+    ])?;
+    assert!(cli.roaming_retry);
+    assert_eq!(
+        cli.roaming_resolvers,
+        ["192.0.2.53:53".parse()?, "192.0.2.54:5353".parse()?]
+    );
+
+    // Roaming resolvers are only used by the roaming retry
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "--roaming-resolver",
+        "192.0.2.53",
+        "single",
+        "-d",
+        "contoso.com"
+    ])
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_batch_output_sqlite() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--output-sqlite",
+        "results.db",
+    ])?;
+    let Commands::Batch { output_sqlite, .. } = &cli.command else {
+        panic!("Expected Batch command");
+    };
+    assert_eq!(output_sqlite.as_deref(), Some(Path::new("results.db")));
+    Ok(())
+}
+
+#[test]
+fn test_cli_batch_max_runtime() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--max-runtime",
+        "8h",
+        "--checkpoint-file",
+        "remaining.txt",
+    ])?;
+    let Commands::Batch {
+        max_runtime,
+        checkpoint_file,
+        ..
+    } = &cli.command
+    else {
+        panic!("Expected Batch command");
+    };
+    assert_eq!(*max_runtime, Some(Duration::from_secs(8 * 3600)));
+    assert_eq!(checkpoint_file.as_deref(), Some(Path::new("remaining.txt")));
+
+    // A checkpoint only makes sense with a run time limit
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--checkpoint-file",
+        "remaining.txt",
+    ])
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_cli_depth() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "contoso.com"])?;
+    assert_eq!(cli.depth, Depth::Standard);
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "d.txt",
+        "--depth",
+        "fast",
+    ])?;
+    assert_eq!(cli.depth, Depth::Fast);
+
+    let cli = Cli::try_parse_from(["sentri", "--depth", "deep", "single", "-d", "contoso.com"])?;
+    assert_eq!(cli.depth, Depth::Deep);
+
+    assert!(
+        Cli::try_parse_from(["sentri", "--depth", "thorough", "single", "-d", "a.com"]).is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cli_env_profile() -> Result<()> {
+    let cli = Cli::try_parse_from(["sentri", "single", "--domain", "example.com"])?;
+    assert_eq!(cli.env_profile, EnvProfile::Off);
+    assert_eq!(cli.cache_size, None);
+    assert!(cli.tuning.is_none());
+
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--env-profile",
+        "auto",
+        "single",
+        "--domain",
+        "example.com",
+    ])?;
+    assert_eq!(cli.env_profile, EnvProfile::Auto);
+    Ok(())
+}
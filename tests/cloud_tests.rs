@@ -0,0 +1,81 @@
+use sentri::output::cloud::is_object_url;
+use sentri::sinks::{object_sink, OutputFormat};
+use std::path::Path;
+
+#[test]
+fn test_object_urls_by_scheme() {
+    for url in [
+        "s3://bucket/results.jsonl",
+        "azblob://container/results.jsonl",
+        "AZ://container/results.jsonl",
+        "abfss://scans@account.dfs.core.windows.net/results.jsonl",
+        "gs://bucket/results.jsonl",
+    ] {
+        assert!(is_object_url(Path::new(url)), "{}", url);
+    }
+    for path in [
+        "results.jsonl",
+        "/tmp/s3/results.jsonl",
+        "file:///tmp/results.jsonl",
+    ] {
+        assert!(!is_object_url(Path::new(path)), "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn test_object_output_rejects_document_formats() {
+    let err = object_sink(OutputFormat::Junit, "s3://bucket/results.xml", None, None)
+        .await
+        .err()
+        .expect("junit is not a line format");
+    assert!(err.to_string().contains("only support"));
+}
+
+#[cfg(not(feature = "object-store"))]
+#[tokio::test]
+async fn test_object_output_without_feature_fails_clearly() {
+    let err = object_sink(OutputFormat::Jsonl, "s3://bucket/results.jsonl", None, None)
+        .await
+        .err()
+        .expect("object store output needs the feature");
+    assert!(err.to_string().contains("object-store feature"));
+}
+
+#[cfg(feature = "object-store")]
+#[tokio::test]
+async fn test_object_output_streams_results() -> anyhow::Result<()> {
+    use sentri::core::DomainResult;
+    use sentri::output::compression::Compression;
+
+    let dir = std::env::temp_dir().join(format!("sentri_cloud_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let result = DomainResult {
+        domain: "contoso.com".to_string(),
+        tenant: Some("contoso".to_string()),
+        ..Default::default()
+    };
+
+    let url = format!("file://{}/results.csv", dir.display());
+    let mut sink = object_sink(OutputFormat::Csv, &url, None, None).await?;
+    sink.write(&result).await?;
+    sink.close().await?;
+    let csv = std::fs::read_to_string(dir.join("results.csv"))?;
+    assert!(csv.starts_with("domain,"));
+    assert!(csv
+        .lines()
+        .nth(1)
+        .unwrap()
+        .starts_with("contoso.com,contoso,"));
+
+    let url = format!("file://{}/results.jsonl.gz", dir.display());
+    let mut sink = object_sink(OutputFormat::Jsonl, &url, None, Some(Compression::Gzip)).await?;
+    sink.write(&result).await?;
+    sink.close().await?;
+    assert_eq!(
+        std::fs::read(dir.join("results.jsonl.gz"))?[..2],
+        [0x1f, 0x8b]
+    );
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
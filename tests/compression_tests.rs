@@ -0,0 +1,159 @@
+use anyhow::Result;
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use clap::Parser;
+use sentri::cli::{Cli, Commands};
+use sentri::core::DomainResult;
+use sentri::output::compression::Compression;
+use sentri::output::fields::FieldSelection;
+use sentri::sinks::{compressed_sink, OutputFormat};
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+async fn read_to_string(mut reader: impl AsyncRead + Unpin) -> Result<String> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content).await?;
+    Ok(content)
+}
+
+async fn decompress(path: &std::path::Path, compression: Compression) -> Result<String> {
+    let file = BufReader::new(tokio::fs::File::open(path).await?);
+    match compression {
+        Compression::Gzip => read_to_string(GzipDecoder::new(file)).await,
+        Compression::Zstd => read_to_string(ZstdDecoder::new(file)).await,
+    }
+}
+
+fn result(domain: &str) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        tenant: Some("contoso".to_string()),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_compressed_output_round_trips() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_compress_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+
+    for compression in [Compression::Gzip, Compression::Zstd] {
+        let path = dir.join(format!("results-{:?}", compression));
+        let mut sink = compressed_sink(OutputFormat::Jsonl, &path, None, compression).await?;
+        for domain in ["contoso.com", "fabrikam.com"] {
+            sink.write(&result(domain)).await?;
+        }
+        sink.flush().await?;
+        sink.close().await?;
+
+        let content = decompress(&path, compression).await?;
+        let domains: Vec<String> = content
+            .lines()
+            .map(|line| Ok(serde_json::from_str::<DomainResult>(line)?.domain))
+            .collect::<Result<_>>()?;
+        assert_eq!(domains, ["contoso.com", "fabrikam.com"], "{compression:?}");
+    }
+
+    // Headers and field selections are compressed as well
+    let path = dir.join("results.csv.gz");
+    let fields = FieldSelection::new(&["domain".to_string(), "tenant".to_string()])?;
+    let mut sink = compressed_sink(OutputFormat::Csv, &path, None, Compression::Gzip).await?;
+    sink.write(&result("contoso.com")).await?;
+    sink.close().await?;
+    let csv = decompress(&path, Compression::Gzip).await?;
+    assert!(csv.starts_with("domain,tenant,mdi,"));
+    assert_eq!(csv.lines().count(), 2);
+
+    let path = dir.join("fields.jsonl.zst");
+    let mut sink = compressed_sink(OutputFormat::Jsonl, &path, fields, Compression::Zstd).await?;
+    sink.write(&result("contoso.com")).await?;
+    sink.close().await?;
+    assert_eq!(
+        decompress(&path, Compression::Zstd).await?,
+        "{\"domain\":\"contoso.com\",\"tenant\":\"contoso\"}\n"
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_document_formats_are_not_compressed() {
+    let path = std::env::temp_dir().join(format!("sentri_compress_{}", uuid::Uuid::new_v4()));
+    for format in [
+        OutputFormat::Junit,
+        OutputFormat::Markdown,
+        OutputFormat::Xlsx,
+    ] {
+        assert!(
+            compressed_sink(format, &path, None, Compression::Gzip)
+                .await
+                .is_err(),
+            "{format:?}"
+        );
+    }
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_compress_flag_requires_output_file() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--output-file",
+        "results.jsonl.zst",
+        "--compress",
+        "zstd",
+    ])?;
+    match cli.command {
+        Commands::Batch { compress, .. } => assert_eq!(compress, Some(Compression::Zstd)),
+        _ => panic!("Expected Batch command"),
+    }
+
+    for args in [
+        &["--compress", "gzip"][..],
+        &[
+            "--output-file",
+            "results.jsonl",
+            "--append",
+            "--compress",
+            "gzip",
+        ],
+        &[
+            "--output-file",
+            "results.jsonl",
+            "--index",
+            "--compress",
+            "gzip",
+        ],
+        &["--output-file", "results.jsonl", "--compress", "brotli"],
+    ] {
+        let mut argv = vec!["sentri", "batch", "--input-file", "domains.txt"];
+        argv.extend_from_slice(args);
+        assert!(Cli::try_parse_from(argv).is_err(), "{args:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_policy_conflicts_with_outputs_ignoring_it() {
+    for args in [
+        &["--output-file", "results.xml.gz", "--compress", "gzip"][..],
+        &["--socket", "/run/sentri.sock"],
+        &["--output-file", "results.jsonl", "--append"],
+        &["--output-file", "results.jsonl", "--index"],
+    ] {
+        let mut argv = vec![
+            "sentri",
+            "--format",
+            "junit",
+            "batch",
+            "--input-file",
+            "domains.txt",
+            "--policy",
+            "policy.toml",
+        ];
+        argv.extend_from_slice(args);
+        assert!(Cli::try_parse_from(argv).is_err(), "{args:?}");
+    }
+}
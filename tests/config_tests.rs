@@ -0,0 +1,319 @@
+use anyhow::Result;
+use clap::{CommandFactory, FromArgMatches};
+use sentri::cli::{Cli, Commands};
+use sentri::config::{
+    apply_tuning, command, env_var_name, try_parse_from, ConfigFile, DnsBudgetSettings,
+    RetrySettings,
+};
+use sentri::depth::{Depth, DnsBudget};
+use sentri::env_profile::{Resources, Tuning};
+use sentri::retry::RetryConfig;
+use sentri::secrets::SecretResolver;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+#[test]
+fn test_env_var_names() {
+    assert_eq!(env_var_name("listen"), "SENTRI_LISTEN");
+    assert_eq!(env_var_name("la-workspace-id"), "SENTRI_LA_WORKSPACE_ID");
+}
+
+#[test]
+fn test_every_option_has_an_env_var() {
+    fn check(command: &clap::Command) {
+        for arg in command.get_arguments() {
+            if let Some(long) = arg.get_long().filter(|l| !["help", "version"].contains(l)) {
+                assert_eq!(
+                    arg.get_env().and_then(|env| env.to_str()),
+                    Some(env_var_name(long).as_str()),
+                    "--{} of {}",
+                    long,
+                    command.get_name()
+                );
+            }
+        }
+        command.get_subcommands().for_each(check);
+    }
+    check(&command());
+}
+
+// All environment manipulation lives in one test, as tests share the process environment
+#[test]
+fn test_env_precedence() -> Result<()> {
+    std::env::set_var("SENTRI_LISTEN", "0.0.0.0:9000");
+    std::env::set_var("SENTRI_RATE_LIMIT", "7");
+    std::env::set_var("SENTRI_TIMEOUT_MS", "1234");
+    std::env::set_var("SENTRI_ATTRIBUTE_IPS", "true");
+    std::env::set_var("SENTRI_DATA_DIR", "/srv/sentri-data");
+
+    // Environment overrides defaults
+    let cli = try_parse_from(["sentri", "serve"])?;
+    assert_eq!(cli.timeout_ms, 1234);
+    assert!(cli.attribute_ips);
+    assert_eq!(cli.data_dir, Some(PathBuf::from("/srv/sentri-data")));
+    match cli.command {
+        Commands::Serve {
+            listen, rate_limit, ..
+        } => {
+            assert_eq!(listen, "0.0.0.0:9000".parse::<SocketAddr>()?);
+            assert_eq!(rate_limit, 7);
+        }
+        _ => panic!("expected serve"),
+    }
+
+    // Flags override the environment
+    let cli = try_parse_from([
+        "sentri",
+        "--timeout-ms",
+        "99",
+        "serve",
+        "--listen",
+        "127.0.0.1:1",
+    ])?;
+    assert_eq!(cli.timeout_ms, 99);
+    match cli.command {
+        Commands::Serve { listen, .. } => {
+            assert_eq!(listen, "127.0.0.1:1".parse::<SocketAddr>()?)
+        }
+        _ => panic!("expected serve"),
+    }
+
+    // Invalid environment values are rejected like invalid flags
+    std::env::set_var("SENTRI_RATE_LIMIT", "lots");
+    assert!(try_parse_from(["sentri", "serve"]).is_err());
+
+    for name in [
+        "SENTRI_LISTEN",
+        "SENTRI_RATE_LIMIT",
+        "SENTRI_TIMEOUT_MS",
+        "SENTRI_ATTRIBUTE_IPS",
+        "SENTRI_DATA_DIR",
+    ] {
+        std::env::remove_var(name);
+    }
+    let cli = try_parse_from(["sentri", "serve"])?;
+    assert_eq!(cli.timeout_ms, 5000);
+    assert!(!cli.attribute_ips);
+
+    // A mounted secret file satisfies options that require the credential
+    let secret = std::env::temp_dir().join(format!("sentri_secret_{}", uuid::Uuid::new_v4()));
+    std::fs::write(&secret, "s3cret\n")?;
+    std::env::set_var("SENTRI_ES_PASSWORD_FILE", &secret);
+    let batch = [
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--es-url",
+        "https://es.example.com",
+        "--es-username",
+        "sentri",
+    ];
+    let mut cli = try_parse_from(batch)?;
+    cli.resolve_secrets(&SecretResolver::default())?;
+    match &cli.command {
+        Commands::Batch { sinks, .. } => assert_eq!(sinks.es_password.as_deref(), Some("s3cret")),
+        _ => panic!("expected batch"),
+    }
+
+    // ...but the flag and the plain variable take precedence
+    let mut cli = try_parse_from(batch.iter().chain(&["--es-password", "flag"]))?;
+    cli.resolve_secrets(&SecretResolver::default())?;
+    match &cli.command {
+        Commands::Batch { sinks, .. } => assert_eq!(sinks.es_password.as_deref(), Some("flag")),
+        _ => panic!("expected batch"),
+    }
+    std::env::set_var("SENTRI_ES_PASSWORD", "env");
+    let cli = try_parse_from(batch)?;
+    match &cli.command {
+        Commands::Batch { sinks, .. } => assert_eq!(sinks.es_password.as_deref(), Some("env")),
+        _ => panic!("expected batch"),
+    }
+
+    std::env::remove_var("SENTRI_ES_PASSWORD");
+    std::env::remove_var("SENTRI_ES_PASSWORD_FILE");
+
+    std::env::set_var("SENTRI_KAFKA_SASL_PASSWORD_FILE", &secret);
+    let mut cli = try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--kafka-brokers",
+        "kafka.internal:9093",
+        "--kafka-topic",
+        "sentri-results",
+        "--kafka-sasl-mechanism",
+        "SCRAM-SHA-512",
+        "--kafka-sasl-username",
+        "sentri",
+    ])?;
+    cli.resolve_secrets(&SecretResolver::default())?;
+    match &cli.command {
+        Commands::Batch { sinks, .. } => {
+            assert_eq!(sinks.kafka_sasl_password.as_deref(), Some("s3cret"))
+        }
+        _ => panic!("expected batch"),
+    }
+    std::env::remove_var("SENTRI_KAFKA_SASL_PASSWORD_FILE");
+
+    // Global credential options are read from files for every subcommand
+    std::env::set_var("SENTRI_STORAGE_KEY_FILE", &secret);
+    let mut cli = try_parse_from([
+        "sentri",
+        "verify-evidence",
+        "--results-file",
+        "r.jsonl",
+        "--capture-dir",
+        "c",
+    ])?;
+    cli.resolve_secrets(&SecretResolver::default())?;
+    assert_eq!(cli.storage_key.as_deref(), Some("s3cret"));
+    std::env::remove_var("SENTRI_STORAGE_KEY_FILE");
+    std::fs::remove_file(secret)?;
+    Ok(())
+}
+
+#[test]
+fn test_config_file_retry_sections() -> Result<()> {
+    let config = ConfigFile::from_toml(
+        r#"
+        [retry.http]
+        max_retries = 5
+        initial_backoff_ms = 250
+        jitter = false
+
+        [retry.dns]
+        max_retries = 0
+        "#,
+    )?;
+
+    let http = config
+        .retry
+        .http
+        .expect("[retry.http]")
+        .apply(RetryConfig::default())?;
+    assert_eq!(
+        http,
+        RetryConfig {
+            max_retries: 5,
+            initial_backoff_ms: 250,
+            add_jitter: false,
+            ..RetryConfig::default()
+        }
+    );
+    let dns = config
+        .retry
+        .dns
+        .expect("[retry.dns]")
+        .apply(RetryConfig::default())?;
+    assert_eq!(dns.max_retries, 0);
+    assert_eq!(dns.max_backoff_ms, RetryConfig::default().max_backoff_ms);
+
+    assert_eq!(ConfigFile::from_toml("")?, ConfigFile::default());
+    Ok(())
+}
+
+#[test]
+fn test_config_file_rejects_invalid_retry() {
+    // Typos are reported rather than silently ignored
+    assert!(ConfigFile::from_toml("[retry.http]\nmax_retry = 5").is_err());
+    assert!(ConfigFile::from_toml("[retry.mdi]\nmax_retries = 5").is_err());
+
+    let settings = RetrySettings {
+        backoff_factor: Some(0.5),
+        ..Default::default()
+    };
+    assert!(settings.apply(RetryConfig::default()).is_err());
+    let settings = RetrySettings {
+        initial_backoff_ms: Some(60_000),
+        max_backoff_ms: Some(1_000),
+        ..Default::default()
+    };
+    assert!(settings.apply(RetryConfig::default()).is_err());
+}
+
+#[test]
+fn test_config_file_mdi_dns_section() -> Result<()> {
+    let config = ConfigFile::from_toml("[mdi_dns]\nqueries_per_minute = 600\n")?;
+    let settings = config.mdi_dns.expect("[mdi_dns]");
+    let budget = settings.apply(Depth::Standard.dns_budget())?;
+    assert_eq!(
+        budget,
+        DnsBudget {
+            queries_per_minute: 600,
+            max_concurrent: Depth::Standard.dns_budget().max_concurrent,
+        }
+    );
+
+    assert!(ConfigFile::from_toml("[mdi_dns]\nrequests_per_minute = 600").is_err());
+    let settings = DnsBudgetSettings {
+        max_concurrent: Some(0),
+        ..Default::default()
+    };
+    assert!(settings.apply(Depth::Deep.dns_budget()).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_config_file_load() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, "[retry.dns]\nmax_backoff_ms = 500\n")?;
+    let config = ConfigFile::load(&path).await?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(
+        config.retry.dns.and_then(|dns| dns.max_backoff_ms),
+        Some(500)
+    );
+
+    let error = ConfigFile::load(&path).await.unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Failed to read configuration file"));
+    Ok(())
+}
+
+#[test]
+fn test_apply_tuning_keeps_explicit_options() -> Result<()> {
+    let tuning = Tuning::for_resources(&Resources {
+        cores: 2,
+        memory_bytes: None,
+        fd_limit: None,
+        ci: true,
+    });
+    let parse = |args: &[&str]| -> Result<Cli> {
+        let matches = Cli::command().try_get_matches_from(args)?;
+        let mut cli = Cli::from_arg_matches(&matches)?;
+        apply_tuning(&mut cli, &matches, tuning);
+        Ok(cli)
+    };
+
+    let cli = parse(&["sentri", "batch", "--input-file", "domains.txt"])?;
+    assert_eq!(cli.tuning, Some(tuning));
+    assert_eq!(cli.concurrent_requests, tuning.concurrent_requests);
+    assert_eq!(cli.cache_size, Some(tuning.cache_size));
+    match cli.command {
+        Commands::Batch { chunk_size, .. } => assert_eq!(chunk_size, tuning.chunk_size),
+        _ => panic!("expected batch"),
+    }
+
+    let cli = parse(&[
+        "sentri",
+        "--concurrent-requests",
+        "7",
+        "--cache-size",
+        "3",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--chunk-size",
+        "9",
+    ])?;
+    assert_eq!(cli.concurrent_requests, 7);
+    assert_eq!(cli.cache_size, Some(3));
+    match cli.command {
+        Commands::Batch { chunk_size, .. } => assert_eq!(chunk_size, 9),
+        _ => panic!("expected batch"),
+    }
+    Ok(())
+}
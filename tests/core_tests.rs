@@ -1,5 +1,11 @@
 use anyhow::Result;
-use sentri::core::{DomainResult, FederationInfo, MdiChecker};
+use sentri::core::{
+    BatchSummary, DomainResult, FederationInfo, MdiChecker, MdiConfidence, MdiGeneration,
+    MDI_SENSOR_ENDPOINTS, RESULT_SCHEMA_VERSION,
+};
+use sentri::error_class::ErrorClass;
+use sentri::sinks::{primary_sink, ResultSink};
+use std::time::Duration;
 
 #[tokio::test]
 async fn test_mdi_checker_creation() {
@@ -22,16 +28,29 @@ async fn test_domain_result_creation() {
     let federated_domains = vec!["federated.com".to_string()];
 
     let result = DomainResult {
+        schema_version: RESULT_SCHEMA_VERSION,
         domain: domain.clone(),
         tenant: tenant.clone(),
         federated_domains: federated_domains.clone(),
         mdi_instance: Some("mdi.test.com".to_string()),
+        mdi_generation: Some(MdiGeneration::Legacy),
+        mdi_confidence: None,
+        endpoint_anomalies: vec![],
+        engagement: None,
+        tags: Default::default(),
         processing_time_ms: 100,
+        response_sha256: None,
+        schema_warnings: vec![],
         error: None,
+        error_class: None,
+        from_cache: false,
+        checked_at: chrono::Utc::now(),
+        completed_at: chrono::Utc::now(),
     };
 
     assert_eq!(result.domain, domain);
     assert_eq!(result.tenant, tenant);
+    assert_eq!(result.mdi_generation, Some(MdiGeneration::Legacy));
     assert!(result.processing_time_ms > 0);
 
     // Test federated domains
@@ -39,6 +58,106 @@ async fn test_domain_result_creation() {
     assert_eq!(result.federated_domains[0], "federated.com");
 }
 
+#[test]
+fn test_mdi_confidence_serialization() -> Result<()> {
+    let result = DomainResult {
+        domain: "fabrikam.com".to_string(),
+        tenant: Some("fabrikam".to_string()),
+        ..Default::default()
+    };
+    let json = serde_json::to_value(&result)?;
+    assert!(json.get("mdi_confidence").is_none());
+
+    let result = DomainResult {
+        mdi_confidence: Some(MdiConfidence::DnssecConfirmed),
+        ..result
+    };
+    let json = serde_json::to_value(&result)?;
+    assert_eq!(json["mdi_confidence"], "dnssec_confirmed");
+    let parsed: DomainResult = serde_json::from_value(json)?;
+    assert_eq!(parsed.mdi_confidence, Some(MdiConfidence::DnssecConfirmed));
+    Ok(())
+}
+
+#[test]
+fn test_mdi_generation_serialization() -> Result<()> {
+    let result = DomainResult {
+        schema_version: RESULT_SCHEMA_VERSION,
+        domain: "contoso.com".to_string(),
+        tenant: Some("contoso".to_string()),
+        mdi_instance: Some("contososensorapi.security.microsoft.com".to_string()),
+        mdi_generation: Some(MdiGeneration::Unified),
+        ..Default::default()
+    };
+
+    let json = serde_json::to_value(&result)?;
+    assert_eq!(json["mdi_generation"], "unified");
+
+    let legacy: DomainResult = serde_json::from_str(
+        r#"{"domain":"a.com","tenant":null,"federated_domains":[],"mdi_instance":null,"processing_time_ms":1,"error":null}"#,
+    )?;
+    assert!(legacy.mdi_generation.is_none());
+    assert!(!legacy.from_cache);
+
+    Ok(())
+}
+
+#[test]
+fn test_legacy_sensor_endpoint_is_probed_first() {
+    assert_eq!(
+        MDI_SENSOR_ENDPOINTS.first(),
+        Some(&(MdiGeneration::Legacy, "sensorapi.atp.azure.com"))
+    );
+    assert_eq!(MdiGeneration::Legacy.to_string(), "legacy");
+}
+
+#[test]
+fn test_cache_provenance_serialization() -> Result<()> {
+    let result = DomainResult {
+        schema_version: RESULT_SCHEMA_VERSION,
+        domain: "contoso.com".to_string(),
+        from_cache: true,
+        ..Default::default()
+    };
+
+    let json = serde_json::to_value(&result)?;
+    assert_eq!(json["from_cache"], true);
+    let round_trip: DomainResult = serde_json::from_value(json)?;
+    assert!(round_trip.from_cache);
+    assert_eq!(round_trip.checked_at, result.checked_at);
+
+    Ok(())
+}
+
+#[test]
+fn test_timestamps_serialize_as_rfc3339() -> Result<()> {
+    let checked_at = chrono::Utc::now();
+    let result = DomainResult {
+        schema_version: RESULT_SCHEMA_VERSION,
+        domain: "contoso.com".to_string(),
+        checked_at,
+        completed_at: checked_at + chrono::Duration::milliseconds(250),
+        ..Default::default()
+    };
+
+    let json = serde_json::to_value(&result)?;
+    for field in ["checked_at", "completed_at"] {
+        let value = json[field].as_str().expect("timestamp is a string");
+        assert!(value.ends_with('Z'));
+        chrono::DateTime::parse_from_rfc3339(value)?;
+    }
+
+    let mut summary = BatchSummary::new();
+    assert!(serde_json::to_value(&summary)?
+        .get("completed_at")
+        .is_none());
+    summary.finish();
+    let completed_at = summary.completed_at.expect("finished batch is stamped");
+    assert!(completed_at >= summary.started_at);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_federation_info_creation() {
     let domains = vec!["domain1.com".to_string(), "domain2.com".to_string()];
@@ -54,12 +173,24 @@ async fn test_federation_info_creation() {
 async fn test_domain_result_without_federation() {
     // Create a domain result without federation information
     let result = DomainResult {
+        schema_version: RESULT_SCHEMA_VERSION,
         domain: "example.com".to_string(),
         tenant: None,
         federated_domains: vec![], // Empty vector for no federated domains
         mdi_instance: None,
+        mdi_generation: None,
+        mdi_confidence: None,
+        endpoint_anomalies: vec![],
+        engagement: None,
+        tags: Default::default(),
         processing_time_ms: 100,
+        response_sha256: None,
+        schema_warnings: vec![],
         error: None,
+        error_class: None,
+        from_cache: false,
+        checked_at: chrono::Utc::now(),
+        completed_at: chrono::Utc::now(),
     };
 
     // Verify the fields reflect a domain without federation
@@ -76,12 +207,24 @@ async fn test_domain_result_with_error() {
     let domain = "error-domain.com".to_string();
 
     let result = DomainResult {
+        schema_version: RESULT_SCHEMA_VERSION,
         domain: domain.clone(),
         tenant: None,
         federated_domains: vec![], // Empty vector for no federated domains
         mdi_instance: None,
+        mdi_generation: None,
+        mdi_confidence: None,
+        endpoint_anomalies: vec![],
+        engagement: None,
+        tags: Default::default(),
         processing_time_ms: 50,
+        response_sha256: None,
+        schema_warnings: vec![],
         error: Some("Connection failed".to_string()),
+        error_class: Some(ErrorClass::Connect),
+        from_cache: false,
+        checked_at: chrono::Utc::now(),
+        completed_at: chrono::Utc::now(),
     };
 
     assert_eq!(result.domain, domain);
@@ -113,3 +256,357 @@ async fn test_read_domains_from_file() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_max_runtime_writes_checkpoint() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let input = dir.join("domains.txt");
+    let output = dir.join("results.jsonl");
+    let checkpoint = dir.join("remaining.txt");
+    // Invalid domains fail validation without any network access
+    std::fs::write(
+        &input,
+        "# maintenance window\n-a-.example\n\nno_tld\n-b-.example\n",
+    )?;
+
+    // Without run time left, no chunk is started and every domain is kept
+    let checker = MdiChecker::new(2, 1000)?.with_max_runtime(Duration::ZERO, checkpoint.clone());
+    let mut sinks: Vec<Box<dyn ResultSink>> = vec![primary_sink(Some(&output)).await?];
+    let summary = checker
+        .process_batch_with_sinks(&input, &mut sinks, 2, 600)
+        .await?;
+    drop(sinks);
+
+    assert_eq!(summary.domains_processed, 0);
+    let truncation = summary.truncated.expect("truncated batch");
+    assert_eq!(truncation.unprocessed, 3);
+    assert_eq!(truncation.max_runtime_ms, 0);
+    assert_eq!(truncation.checkpoint, checkpoint);
+    assert_eq!(
+        std::fs::read_to_string(&checkpoint)?,
+        "-a-.example\nno_tld\n-b-.example\n"
+    );
+    assert_eq!(std::fs::read_to_string(&output)?, "");
+
+    // Resuming from the checkpoint may write the next checkpoint over it
+    let checker = MdiChecker::new(2, 1000)?.with_max_runtime(Duration::ZERO, checkpoint.clone());
+    let mut sinks: Vec<Box<dyn ResultSink>> = vec![primary_sink(Some(&output)).await?];
+    let summary = checker
+        .process_batch_with_sinks(&checkpoint, &mut sinks, 2, 600)
+        .await?;
+    drop(sinks);
+    assert_eq!(summary.truncated.map(|t| t.unprocessed), Some(3));
+    assert_eq!(
+        std::fs::read_to_string(&checkpoint)?,
+        "-a-.example\nno_tld\n-b-.example\n"
+    );
+
+    // A batch finishing within its run time is not truncated
+    std::fs::remove_file(&checkpoint)?;
+    let checker =
+        MdiChecker::new(2, 1000)?.with_max_runtime(Duration::from_secs(3600), checkpoint.clone());
+    let mut sinks: Vec<Box<dyn ResultSink>> = vec![primary_sink(Some(&output)).await?];
+    let summary = checker
+        .process_batch_with_sinks(&input, &mut sinks, 2, 600)
+        .await?;
+    assert_eq!(summary.domains_processed, 3);
+    assert!(summary.truncated.is_none());
+    assert!(!checkpoint.exists());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch_carries_input_tags() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let input = dir.join("domains.csv");
+    let output = dir.join("results.jsonl");
+    let checkpoint = dir.join("remaining.txt");
+    // Invalid domains fail validation without any network access
+    std::fs::write(
+        &input,
+        "domain,tags\n-a-.example,bu=emea,owner=alice\nno_tld,\"owner=Smith, Bob\"\n-b-.example\n",
+    )?;
+
+    let checker = MdiChecker::new(2, 1000)?;
+    let mut sinks: Vec<Box<dyn ResultSink>> = vec![primary_sink(Some(&output)).await?];
+    let summary = checker
+        .process_batch_with_sinks(&input, &mut sinks, 2, 600)
+        .await?;
+    drop(sinks);
+    assert_eq!(summary.domains_processed, 3);
+
+    let mut results: Vec<DomainResult> = std::fs::read_to_string(&output)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    results.sort_by(|a, b| a.domain.cmp(&b.domain));
+    let tags: Vec<String> = results
+        .iter()
+        .map(|result| sentri::tags::format_tags(&result.tags))
+        .collect();
+    assert_eq!(tags, ["bu=emea;owner=alice", "", "owner=Smith, Bob"]);
+
+    // Checkpoints keep the tags for the resumed run
+    let checker = MdiChecker::new(2, 1000)?.with_max_runtime(Duration::ZERO, checkpoint.clone());
+    let mut sinks: Vec<Box<dyn ResultSink>> = vec![primary_sink(Some(&output)).await?];
+    checker
+        .process_batch_with_sinks(&input, &mut sinks, 2, 600)
+        .await?;
+    drop(sinks);
+    assert_eq!(
+        std::fs::read_to_string(&checkpoint)?,
+        "-a-.example,bu=emea,owner=alice\nno_tld,\"owner=Smith, Bob\"\n-b-.example\n"
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_roaming_retry_gives_environmental_failures_a_last_chance() -> Result<()> {
+    use sentri::core::RoamingSummary;
+    use sentri::dns::DnsResolver;
+    use sentri::http::HttpClient;
+    use sentri::retry::RetryConfig;
+
+    let dir = std::env::temp_dir().join(format!("sentri_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let input = dir.join("domains.txt");
+    let output = dir.join("results.jsonl");
+    std::fs::write(&input, "contoso.com\n-a-.example\n")?;
+
+    // Nothing listens on the discard port, so every request fails to connect
+    let unreachable = |url: &str| -> Result<HttpClient> {
+        Ok(HttpClient::builder()
+            .timeout(Duration::from_secs(2))
+            .autodiscover_url(url)
+            .build()?
+            .with_retry_config(RetryConfig {
+                max_retries: 0,
+                ..RetryConfig::default()
+            }))
+    };
+    let checker = MdiChecker::new(2, 1000)?
+        .with_http_client(unreachable(
+            "https://127.0.0.1:9/autodiscover/autodiscover.svc",
+        )?)
+        .with_roaming(
+            unreachable("https://[::1]:9/autodiscover/autodiscover.svc")?,
+            DnsResolver::new()?,
+        );
+    assert!(checker.roaming().is_some());
+
+    let mut sinks: Vec<Box<dyn ResultSink>> = vec![primary_sink(Some(&output)).await?];
+    let summary = checker
+        .process_batch_with_sinks(&input, &mut sinks, 1, 600)
+        .await?;
+    drop(sinks);
+
+    // Only the connection failure is retried; the invalid domain is not
+    assert_eq!(summary.domains_processed, 2);
+    assert_eq!(summary.errors, 2);
+    assert_eq!(
+        summary.roaming,
+        Some(RoamingSummary {
+            retried: 1,
+            recovered: 0
+        })
+    );
+    let results: Vec<DomainResult> = std::fs::read_to_string(&output)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    let domains: Vec<&str> = results.iter().map(|r| r.domain.as_str()).collect();
+    assert_eq!(domains, ["-a-.example", "contoso.com"]);
+    assert_eq!(results[1].error_class, Some(ErrorClass::Connect));
+
+    let result = checker.check_domain_with_roaming("contoso.com").await?;
+    assert_eq!(result.error_class, Some(ErrorClass::Connect));
+
+    // Without roaming, failures are written as they occur
+    let checker = MdiChecker::new(2, 1000)?.with_http_client(unreachable(
+        "https://127.0.0.1:9/autodiscover/autodiscover.svc",
+    )?);
+    assert!(checker.roaming().is_none());
+    let mut sinks: Vec<Box<dyn ResultSink>> = vec![primary_sink(Some(&output)).await?];
+    let summary = checker
+        .process_batch_with_sinks(&input, &mut sinks, 1, 600)
+        .await?;
+    assert_eq!(summary.errors, 2);
+    assert!(summary.roaming.is_none());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+/// Starts a TCP DNS server knowing the tenant `contoso` and its MDI instance
+///
+/// `contoso.onmicrosoft.com` has a mail exchanger and every `contososensorapi`
+/// name an address; all other names are NXDOMAIN.
+async fn start_tenant_server() -> Result<std::net::SocketAddr> {
+    use std::net::Ipv4Addr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use trust_dns_resolver::proto::op::{Message, MessageType, ResponseCode};
+    use trust_dns_resolver::proto::rr::rdata::{A, MX};
+    use trust_dns_resolver::proto::rr::{Name, RData, Record, RecordType};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                loop {
+                    let mut length = [0u8; 2];
+                    if stream.read_exact(&mut length).await.is_err() {
+                        return;
+                    }
+                    let mut buf = vec![0u8; usize::from(u16::from_be_bytes(length))];
+                    if stream.read_exact(&mut buf).await.is_err() {
+                        return;
+                    }
+                    let Ok(query) = Message::from_vec(&buf) else {
+                        return;
+                    };
+                    let mut response = Message::new();
+                    response
+                        .set_id(query.id())
+                        .set_message_type(MessageType::Response)
+                        .set_recursion_desired(true)
+                        .set_recursion_available(true)
+                        .add_queries(query.queries().to_vec());
+                    let question = query.queries()[0].clone();
+                    let name = question.name().to_utf8();
+                    let rdata = match question.query_type() {
+                        RecordType::MX if name == "contoso.onmicrosoft.com." => {
+                            let exchange = Name::from_ascii(
+                                "contoso-onmicrosoft-com.mail.protection.outlook.com.",
+                            )
+                            .unwrap();
+                            Some(RData::MX(MX::new(0, exchange)))
+                        }
+                        RecordType::A if name.starts_with("contososensorapi.") => {
+                            Some(RData::A(A(Ipv4Addr::new(192, 0, 2, 1))))
+                        }
+                        _ => None,
+                    };
+                    match rdata {
+                        Some(rdata) => {
+                            response.add_answer(Record::from_rdata(
+                                question.name().clone(),
+                                60,
+                                rdata,
+                            ));
+                        }
+                        None if name.starts_with("contoso") => {}
+                        None => {
+                            response.set_response_code(ResponseCode::NXDomain);
+                        }
+                    }
+                    let response = response.to_vec().unwrap();
+                    let length = u16::try_from(response.len()).unwrap().to_be_bytes();
+                    if stream.write_all(&length).await.is_err()
+                        || stream.write_all(&response).await.is_err()
+                    {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    Ok(address)
+}
+
+#[tokio::test]
+async fn test_check_tenant_skips_federation() -> Result<()> {
+    use sentri::depth::Depth;
+    use sentri::dns::DnsResolver;
+    use sentri::dns_pool::Strategy;
+    use sentri::http::HttpClient;
+
+    let server = start_tenant_server().await?;
+    // Federation requests would fail to connect, so any result proves they were skipped
+    let checker = MdiChecker::new(2, 1000)?
+        .with_http_client(
+            HttpClient::builder()
+                .autodiscover_url("https://127.0.0.1:9/autodiscover/autodiscover.svc")
+                .build()?,
+        )
+        .with_dns_resolver(
+            DnsResolver::new()?
+                .with_pipelining()
+                .with_upstreams(&[server], Strategy::RoundRobin),
+        );
+
+    let result = checker.check_tenant("Contoso.onmicrosoft.com").await?;
+    assert_eq!(result.domain, "contoso.onmicrosoft.com");
+    assert_eq!(result.tenant.as_deref(), Some("contoso"));
+    assert!(result.federated_domains.is_empty());
+    assert!(result
+        .mdi_instance
+        .unwrap()
+        .starts_with("contososensorapi."));
+    assert_eq!(result.error, None);
+
+    let result = checker.check_tenant("fabrikam").await?;
+    assert_eq!(result.domain, "fabrikam.onmicrosoft.com");
+    assert_eq!(result.tenant, None);
+    assert_eq!(result.mdi_instance, None);
+    assert_eq!(result.error, None);
+
+    let result = checker
+        .clone()
+        .with_depth(Depth::Fast)
+        .check_tenant("contoso")
+        .await?;
+    assert_eq!(result.tenant.as_deref(), Some("contoso"));
+    assert_eq!(result.mdi_instance, None);
+
+    for name in ["contoso.com", "-contoso", ""] {
+        let result = checker.check_tenant(name).await?;
+        assert_eq!(
+            result.error_class,
+            Some(ErrorClass::InvalidDomain),
+            "{}",
+            name
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_federation_makes_no_dns_lookups() -> Result<()> {
+    use sentri::depth::Depth;
+    use sentri::http::HttpClient;
+    use sentri::retry::RetryConfig;
+
+    let checker = MdiChecker::new(1, 1000)?
+        .with_http_client(
+            HttpClient::builder()
+                .timeout(Duration::from_secs(2))
+                .autodiscover_url("https://127.0.0.1:9/autodiscover/autodiscover.svc")
+                .build()?
+                .with_retry_config(RetryConfig {
+                    max_retries: 0,
+                    ..RetryConfig::default()
+                }),
+        )
+        .with_depth(Depth::Deep);
+
+    let result = checker.check_federation("contoso.com").await?;
+    assert_eq!(result.error_class, Some(ErrorClass::Connect));
+    assert!(result.federated_domains.is_empty());
+    let result = checker.check_federation("-a-.example").await?;
+    assert_eq!(result.error_class, Some(ErrorClass::InvalidDomain));
+
+    // Only the federation detector ran, and the checker keeps its depth
+    let summaries = checker.detector_stats().summaries();
+    assert!(summaries
+        .iter()
+        .all(|summary| summary.detector == sentri::stats::Detector::Federation));
+    assert_eq!(checker.depth(), Depth::Deep);
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use sentri::crash::{redact_args, CrashContext, CrashReport, RecentLogs};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::PathBuf;
+use tracing_subscriber::fmt::MakeWriter;
+
+fn support_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_support_{}", uuid::Uuid::new_v4()))
+}
+
+#[test]
+fn test_redact_args() {
+    let args = [
+        "sentri",
+        "batch",
+        "--la-shared-key",
+        "c2VjcmV0",
+        "--smtp-password=hunter2",
+        "--input-file",
+        "domains.txt",
+    ]
+    .map(String::from);
+    let redacted = redact_args(args);
+    assert_eq!(redacted[3], "[REDACTED]");
+    assert_eq!(redacted[4], "--smtp-password=[REDACTED]");
+    assert_eq!(redacted[6], "domains.txt");
+    assert!(!redacted.join(" ").contains("hunter2"));
+}
+
+#[test]
+fn test_recent_logs_keep_last_lines() -> Result<()> {
+    let logs = RecentLogs::new(2);
+    for line in ["first", "second", "\u{1b}[32mthird\u{1b}[0m"] {
+        let mut writer = logs.make_writer();
+        writeln!(writer, "{}", line)?;
+    }
+    assert_eq!(logs.lines(), ["second", "third"]);
+    Ok(())
+}
+
+#[test]
+fn test_write_bundle() -> Result<()> {
+    let dir = support_dir();
+    let logs = RecentLogs::new(10);
+    logs.push("Processing chunk");
+    let context = CrashContext::new(&dir)
+        .with_args(["sentri", "serve", "--storage-key", "c2VjcmV0"].map(String::from))
+        .with_logs(logs);
+    context.record_state("batch", json!({"domains_processed": 500}));
+
+    let bundle =
+        context.write_bundle(&CrashReport::new("boom").with_location("src/core.rs:1:1"))?;
+    assert!(bundle.starts_with(&dir));
+
+    let report: Value = serde_json::from_slice(&std::fs::read(bundle.join("report.json"))?)?;
+    assert_eq!(report["reason"], "boom");
+    assert_eq!(report["location"], "src/core.rs:1:1");
+    assert_eq!(report["version"], env!("CARGO_PKG_VERSION"));
+
+    let config = std::fs::read_to_string(bundle.join("config.json"))?;
+    assert!(config.contains("[REDACTED]"));
+    assert!(!config.contains("c2VjcmV0"));
+
+    let state: Value = serde_json::from_slice(&std::fs::read(bundle.join("state.json"))?)?;
+    assert_eq!(state["batch"]["domains_processed"], 500);
+    assert_eq!(
+        std::fs::read_to_string(bundle.join("recent.log"))?,
+        "Processing chunk"
+    );
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use anyhow::Result;
+use sentri::core::{DomainResult, MdiChecker};
+use sentri::jobs::{JobManager, JobRequest};
+use sentri::rate_limit::RateBudget;
+use sentri::scheduler::Scheduler;
+use sentri::server::{serve, ApiKeys, ServerState};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn state_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_state_{}", uuid::Uuid::new_v4()))
+}
+
+fn result(domain: &str, tenant: Option<&str>, mdi: Option<&str>) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        tenant: tenant.map(str::to_string),
+        mdi_instance: mdi.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+/// Submits a job, cancels it and replaces its results with known content
+async fn job_with_results(jobs: &Arc<JobManager>, results: &[DomainResult]) -> Result<String> {
+    let job = jobs
+        .submit(
+            JobRequest {
+                domains: vec!["contoso.com".to_string(); 10],
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+    jobs.cancel(&job.id).await;
+    for _ in 0..50 {
+        if jobs
+            .get(&job.id)
+            .await
+            .expect("job exists")
+            .state
+            .is_finished()
+        {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let lines: Vec<String> = results
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<_, _>>()?;
+    tokio::fs::write(jobs.results_path(&job.id), lines.join("\n") + "\n").await?;
+    Ok(job.id)
+}
+
+#[tokio::test]
+async fn test_dashboard_and_result_search() -> Result<()> {
+    let dir = state_dir();
+    let checker = MdiChecker::new(1, 1000)?;
+    let budget = Arc::new(RateBudget::new(1, 1));
+    let scheduler = Scheduler::open(checker.clone(), &dir, Arc::clone(&budget)).await?;
+    let jobs = JobManager::new(checker, &dir, budget).await?;
+
+    let keys = ApiKeys::from_toml(&format!(
+        "[[key]]\nname = \"red\"\nkey_sha256 = \"{:x}\"\n",
+        Sha256::digest(b"red-secret")
+    ))?;
+    let state = ServerState::new(scheduler, jobs).with_api_keys(keys);
+    let jobs = Arc::clone(&state.jobs);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(serve(listener, state));
+    let client = reqwest::Client::new();
+
+    // Static assets are served without a key, the API behind them is not
+    let response = client.get(format!("{}/", base)).send().await?;
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()?
+        .starts_with("text/html"));
+    assert!(response.headers().contains_key("content-security-policy"));
+    assert!(response.text().await?.contains("/assets/dashboard.js"));
+    for asset in ["/assets/dashboard.js", "/assets/dashboard.css"] {
+        let response = client.get(format!("{}{}", base, asset)).send().await?;
+        assert_eq!(response.status(), 200);
+    }
+    let response = client.get(format!("{}/results", base)).send().await?;
+    assert_eq!(response.status(), 401);
+
+    // Jobs submitted without a key are not visible to the red key
+    let id = job_with_results(
+        &jobs,
+        &[
+            result(
+                "contoso.com",
+                Some("contoso"),
+                Some("contososensorapi.atp.azure.com"),
+            ),
+            result("fabrikam.com", Some("fabrikam"), None),
+            result("example.org", None, None),
+        ],
+    )
+    .await?;
+    let hits: Value = client
+        .get(format!("{}/results", base))
+        .bearer_auth("red-secret")
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(hits.as_array().map(Vec::len), Some(0));
+
+    server.abort();
+
+    // Without authentication every job is searchable
+    let dir2 = state_dir();
+    let checker = MdiChecker::new(1, 1000)?;
+    let budget = Arc::new(RateBudget::new(1, 1));
+    let scheduler = Scheduler::open(checker.clone(), &dir2, Arc::clone(&budget)).await?;
+    let state = ServerState::new(scheduler, JobManager::new(checker, &dir2, budget).await?);
+    let jobs = Arc::clone(&state.jobs);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(serve(listener, state));
+    let id2 = job_with_results(
+        &jobs,
+        &[
+            result(
+                "contoso.com",
+                Some("contoso"),
+                Some("contososensorapi.atp.azure.com"),
+            ),
+            result("fabrikam.com", Some("fabrikam"), None),
+            result("example.org", None, None),
+        ],
+    )
+    .await?;
+    assert_ne!(id, id2);
+
+    let hits: Value = client
+        .get(format!("{}/results?q=FABRIKAM", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let hits = hits.as_array().expect("array");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["result"]["domain"], "fabrikam.com");
+    assert_eq!(hits[0]["source"], "job");
+    assert_eq!(hits[0]["source_id"], id2.as_str());
+
+    let hits: Value = client
+        .get(format!("{}/results?q=sensorapi", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(hits.as_array().map(Vec::len), Some(1));
+
+    let hits: Value = client
+        .get(format!("{}/results?limit=2", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(hits.as_array().map(Vec::len), Some(2));
+
+    server.abort();
+    std::fs::remove_dir_all(dir)?;
+    std::fs::remove_dir_all(dir2)?;
+    Ok(())
+}
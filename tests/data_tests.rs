@@ -0,0 +1,174 @@
+use anyhow::Result;
+use clap::Parser;
+use sentri::cli::{Cli, Commands};
+use sentri::data::{
+    service_tags_download_url, update_data, Confusables, DataSet, Manifest, PublicSuffixList,
+    UpdateOutcome, CONFUSABLES_FILE, PUBLIC_SUFFIX_FILE,
+};
+use sentri::http::HttpClient;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const PUBLIC_SUFFIXES: &str = "\
+// ===BEGIN ICANN DOMAINS===
+com
+uk
+co.uk
+// Wildcard with an exception
+*.ck
+!www.ck
+// ===END ICANN DOMAINS===
+";
+
+const CONFUSABLES: &str = "\
+\u{feff}# confusables.txt
+0441 ;\t0063 ;\tMA\t# ( с → c ) CYRILLIC SMALL LETTER ES → LATIN SMALL LETTER C
+2460 ;\t0028 0031 0029 ;\tMA\t# ( ① → (1) ) CIRCLED DIGIT ONE → LEFT PARENTHESIS, DIGIT ONE, RIGHT PARENTHESIS
+";
+
+fn temp_dir() -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("sentri_data_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+#[test]
+fn test_registrable_domain() {
+    let list = PublicSuffixList::parse(PUBLIC_SUFFIXES);
+    assert_eq!(list.len(), 5);
+
+    assert_eq!(
+        list.registrable_domain("autodiscover.contoso.com")
+            .as_deref(),
+        Some("contoso.com")
+    );
+    assert_eq!(
+        list.registrable_domain("Mail.Contoso.co.uk.").as_deref(),
+        Some("contoso.co.uk")
+    );
+    assert_eq!(list.registrable_domain("co.uk"), None);
+
+    // Wildcard rules make every child a suffix, except listed exceptions
+    assert_eq!(
+        list.registrable_domain("a.b.example.ck").as_deref(),
+        Some("b.example.ck")
+    );
+    assert_eq!(list.registrable_domain("www.ck").as_deref(), Some("www.ck"));
+
+    // Unknown top-level domains default to a single-label suffix
+    assert_eq!(
+        list.registrable_domain("host.contoso.internal").as_deref(),
+        Some("contoso.internal")
+    );
+}
+
+#[test]
+fn test_confusables_skeleton() {
+    let confusables = Confusables::parse(CONFUSABLES);
+    assert_eq!(confusables.len(), 2);
+    assert_eq!(confusables.skeleton("\u{0441}ontoso"), "contoso");
+    assert_eq!(confusables.skeleton("\u{2460}"), "(1)");
+    assert_eq!(
+        Confusables::bundled().skeleton("\u{0441}\u{043e}nt\u{043e}s\u{043e}.com"),
+        "contoso.com"
+    );
+}
+
+#[test]
+fn test_verify_rejects_unexpected_content() {
+    assert_eq!(
+        DataSet::PublicSuffixList.verify(PUBLIC_SUFFIXES).ok(),
+        Some(5)
+    );
+    assert_eq!(DataSet::Confusables.verify(CONFUSABLES).ok(), Some(2));
+
+    let html = "<html><body>Service unavailable</body></html>";
+    assert!(DataSet::PublicSuffixList.verify(html).is_err());
+    assert!(DataSet::Confusables.verify(html).is_err());
+    assert!(DataSet::ServiceTags.verify(html).is_err());
+}
+
+#[test]
+fn test_service_tags_download_url() {
+    let page = r#"<a href="https://download.microsoft.com/download/7/1/d/71d86715-5596-4529-9b13-da13a5de5b63/ServiceTags_Public_20261012.json" class="mscom-link">"#;
+    assert_eq!(
+        service_tags_download_url(page).as_deref(),
+        Some("https://download.microsoft.com/download/7/1/d/71d86715-5596-4529-9b13-da13a5de5b63/ServiceTags_Public_20261012.json")
+    );
+    assert_eq!(service_tags_download_url("<html></html>"), None);
+}
+
+#[test]
+fn test_load_falls_back_to_bundled_data() -> Result<()> {
+    let dir = temp_dir()?;
+    assert_eq!(
+        PublicSuffixList::load(Some(&dir))?.len(),
+        PublicSuffixList::bundled().len()
+    );
+    assert_eq!(Confusables::load(None)?.len(), Confusables::bundled().len());
+
+    std::fs::write(dir.join(PUBLIC_SUFFIX_FILE), PUBLIC_SUFFIXES)?;
+    std::fs::write(dir.join(CONFUSABLES_FILE), CONFUSABLES)?;
+    assert_eq!(PublicSuffixList::load(Some(&dir))?.len(), 5);
+    assert_eq!(Confusables::load(Some(&dir))?.len(), 2);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_update_keeps_fallback() -> Result<()> {
+    let dir = temp_dir()?;
+    let client = HttpClient::builder()
+        .timeout(Duration::from_secs(2))
+        .http2_prior_knowledge(false)
+        .build()?;
+
+    let outcomes = update_data(
+        &client,
+        &dir,
+        &[DataSet::ServiceTags],
+        Some("http://127.0.0.1:9/ServiceTags_Public.json"),
+    )
+    .await?;
+
+    assert_eq!(outcomes.len(), 1);
+    match &outcomes[0] {
+        UpdateOutcome::Failed {
+            dataset, fallback, ..
+        } => {
+            assert_eq!(*dataset, DataSet::ServiceTags);
+            assert_eq!(fallback, "bundled");
+        }
+        other => panic!("Expected failed update, got {:?}", other),
+    }
+
+    // Nothing was installed, but the manifest is written
+    assert!(Manifest::load(&dir).await?.files.is_empty());
+    assert!(!dir.join(DataSet::ServiceTags.file_name()).exists());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_update_data_parsing() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "--data-dir",
+        "/var/lib/sentri",
+        "update-data",
+        "--only",
+        "service-tags",
+        "--only",
+        "confusables",
+    ])?;
+
+    match &cli.command {
+        Commands::UpdateData { only, .. } => {
+            assert_eq!(only, &vec![DataSet::ServiceTags, DataSet::Confusables])
+        }
+        _ => panic!("Expected update-data command"),
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use sentri::core::MdiChecker;
+use sentri::depth::Depth;
+
+#[test]
+fn test_depth_detectors() {
+    assert!(!Depth::Fast.checks_mdi());
+    assert!(Depth::Standard.checks_mdi());
+    assert!(Depth::Deep.checks_mdi());
+
+    assert!(!Depth::Fast.attributes_endpoints());
+    assert!(!Depth::Standard.attributes_endpoints());
+    assert!(Depth::Deep.attributes_endpoints());
+}
+
+#[test]
+fn test_depth_dns_budgets() {
+    // Only deep spends more DNS queries than the resolver default allows
+    assert_eq!(Depth::Fast.dns_budget(), Depth::Standard.dns_budget());
+    let standard = Depth::Standard.dns_budget();
+    let deep = Depth::Deep.dns_budget();
+    assert_eq!(standard.queries_per_minute, 100);
+    assert!(deep.queries_per_minute > standard.queries_per_minute);
+    assert!(deep.max_concurrent > standard.max_concurrent);
+}
+
+#[test]
+fn test_checker_depth() -> Result<()> {
+    let checker = MdiChecker::new(5, 1000)?;
+    assert_eq!(checker.depth(), Depth::Standard);
+    let checker = checker.with_depth(Depth::Fast);
+    assert_eq!(checker.depth(), Depth::Fast);
+    assert_eq!(checker.clone().depth(), Depth::Fast);
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use sentri::dns::DnsResolver;
+use sentri::dns_override::{DnsOverrides, Override};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const OVERRIDES: &str = "
+# Lab endpoints
+192.0.2.10    autodiscover-s.outlook.com lab.example.com
+2001:db8::10  autodiscover-s.outlook.com
+
+NXDOMAIN      contoso-corp.mail.protection.outlook.com  # gone
+";
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().unwrap()
+}
+
+#[test]
+fn test_parse_combines_addresses_of_a_name() -> Result<()> {
+    let overrides = DnsOverrides::parse(OVERRIDES)?;
+    assert_eq!(overrides.len(), 3);
+    assert_eq!(
+        overrides.lookup("autodiscover-s.outlook.com"),
+        Some(&Override::Addresses(vec![
+            ip("192.0.2.10"),
+            ip("2001:db8::10")
+        ]))
+    );
+    assert_eq!(
+        overrides.lookup("Contoso-Corp.mail.protection.outlook.com."),
+        Some(&Override::NxDomain)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_rejects_invalid_lines() {
+    let error = DnsOverrides::parse("192.0.2.10 a.example.com\n192.0.2.300 b.example.com")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Line 2"), "{}", error);
+    assert!(DnsOverrides::parse("192.0.2.10").is_err());
+    assert!(DnsOverrides::parse("192.0.2.10 a.example.com\nNXDOMAIN a.example.com").is_err());
+}
+
+#[test]
+fn test_load_reports_missing_file() {
+    let path = std::env::temp_dir().join(format!("sentri_hosts_{}", uuid::Uuid::new_v4()));
+    let error = DnsOverrides::load(&path).unwrap_err();
+    assert!(format!("{:#}", error).contains("Failed to read DNS overrides"));
+}
+
+#[tokio::test]
+async fn test_resolver_answers_from_overrides() -> Result<()> {
+    let overrides = Arc::new(DnsOverrides::parse(OVERRIDES)?);
+    let resolver = DnsResolver::new()?.with_overrides(overrides);
+
+    assert_eq!(
+        resolver.resolve("LAB.example.com").await?,
+        [ip("192.0.2.10")]
+    );
+    let error = resolver
+        .resolve("contoso-corp.mail.protection.outlook.com")
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("NXDOMAIN"));
+    assert!(resolver
+        .resolve_mx("contoso-corp.mail.protection.outlook.com")
+        .await?
+        .is_empty());
+    assert!(resolver
+        .resolve_txt("contoso-corp.mail.protection.outlook.com")
+        .await?
+        .is_empty());
+
+    // Overridden names never reach an upstream
+    assert!(resolver
+        .upstream_stats()
+        .iter()
+        .all(|stats| stats.queries == 0));
+    Ok(())
+}
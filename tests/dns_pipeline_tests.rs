@@ -0,0 +1,138 @@
+use anyhow::Result;
+use sentri::dns::DnsResolver;
+use sentri::dns_override::DnsOverrides;
+use sentri::dns_pipeline::{LookupPool, PipelineConfig};
+use sentri::dns_pool::Strategy;
+use sentri::rate_limit::RateLimiter;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use trust_dns_resolver::proto::op::{Message, MessageType};
+use trust_dns_resolver::proto::rr::rdata::A;
+use trust_dns_resolver::proto::rr::{RData, Record};
+
+/// Starts a TCP-only DNS server answering every query with 192.0.2.1
+///
+/// Returns the address and the number of connections accepted so far.
+async fn start_tcp_server() -> Result<(SocketAddr, Arc<AtomicUsize>)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = Arc::clone(&connections);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            accepted.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                loop {
+                    let mut length = [0u8; 2];
+                    if stream.read_exact(&mut length).await.is_err() {
+                        return;
+                    }
+                    let mut buf = vec![0u8; usize::from(u16::from_be_bytes(length))];
+                    if stream.read_exact(&mut buf).await.is_err() {
+                        return;
+                    }
+                    let Ok(query) = Message::from_vec(&buf) else {
+                        return;
+                    };
+                    let mut response = Message::new();
+                    response
+                        .set_id(query.id())
+                        .set_message_type(MessageType::Response)
+                        .set_recursion_desired(true)
+                        .set_recursion_available(true)
+                        .add_queries(query.queries().to_vec());
+                    if let Some(question) = query.queries().first() {
+                        response.add_answer(Record::from_rdata(
+                            question.name().clone(),
+                            60,
+                            RData::A(A(Ipv4Addr::new(192, 0, 2, 1))),
+                        ));
+                    }
+                    let response = response.to_vec().unwrap();
+                    let length = u16::try_from(response.len()).unwrap().to_be_bytes();
+                    if stream.write_all(&length).await.is_err()
+                        || stream.write_all(&response).await.is_err()
+                    {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    Ok((address, connections))
+}
+
+#[tokio::test]
+async fn test_pool_pipelines_lookups_over_one_connection() -> Result<()> {
+    let (server, connections) = start_tcp_server().await?;
+    let resolver = DnsResolver::new()?
+        .with_pipelining()
+        .with_upstreams(&[server], Strategy::RoundRobin);
+    assert!(resolver.is_pipelined());
+    let config = PipelineConfig {
+        workers: 2,
+        depth: 16,
+    };
+    let pool = Arc::new(LookupPool::spawn(Arc::new(resolver), config));
+
+    let lookups = (0..40).map(|i| {
+        let pool = Arc::clone(&pool);
+        tokio::spawn(async move { pool.resolve(&format!("tenant{}.example.com", i)).await })
+    });
+    for lookup in lookups {
+        assert_eq!(lookup.await??, [IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]);
+    }
+
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+    let stats = pool.stats();
+    assert_eq!(stats.lookups, 40);
+    assert!(stats.batches >= 3);
+    assert!(stats.largest_batch <= 16);
+    assert!(stats.average_batch() >= 1.0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pool_returns_resolver_errors() -> Result<()> {
+    let overrides = DnsOverrides::parse("NXDOMAIN blocked.example\n192.0.2.10 lab.example")?;
+    let resolver = DnsResolver::new()?
+        .with_pipelining()
+        .with_overrides(Arc::new(overrides));
+    let pool = LookupPool::spawn(
+        Arc::new(resolver),
+        PipelineConfig {
+            workers: 0,
+            depth: 0,
+        },
+    );
+
+    let error = pool.resolve("blocked.example").await.unwrap_err();
+    assert!(error.to_string().contains("NXDOMAIN"));
+    assert_eq!(
+        pool.resolve("lab.example").await?,
+        [IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10))]
+    );
+    // Zero workers and depth are raised to one
+    assert_eq!(pool.stats().largest_batch, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pool_draws_from_given_limiter() -> Result<()> {
+    let (server, _) = start_tcp_server().await?;
+    let resolver = DnsResolver::new()?
+        .with_pipelining()
+        .with_upstreams(&[server], Strategy::RoundRobin);
+    let pool = LookupPool::spawn(Arc::new(resolver), PipelineConfig::default());
+    let limiter = Arc::new(RateLimiter::new(10, 60_000, 2));
+
+    for i in 0..3 {
+        let name = format!("tenant{}.example.com", i);
+        pool.resolve_limited(&name, Arc::clone(&limiter)).await?;
+    }
+    assert_eq!(limiter.usage().await.tokens_available, 7);
+    Ok(())
+}
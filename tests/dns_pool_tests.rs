@@ -0,0 +1,96 @@
+use sentri::dns::DnsResolver;
+use sentri::dns_pool::{Balancer, Strategy, COOLDOWN, FAILURE_THRESHOLD, PROBE_EVERY};
+use std::time::{Duration, Instant};
+
+fn fail(balancer: &Balancer, index: usize, now: Instant) {
+    for _ in 0..FAILURE_THRESHOLD {
+        balancer.record_failure(index, now);
+    }
+}
+
+#[test]
+fn test_round_robin_skips_unhealthy_upstreams() {
+    let balancer = Balancer::new(Strategy::RoundRobin, 3);
+    let now = Instant::now();
+    let picks: Vec<usize> = (0..6).map(|_| balancer.select(now)).collect();
+    assert_eq!(picks, [0, 1, 2, 0, 1, 2]);
+
+    fail(&balancer, 1, now);
+    assert!(!balancer.stats()[1].healthy);
+    assert!((0..6).all(|_| balancer.select(now) != 1));
+
+    // Back in rotation once the cooldown has passed
+    let later = now + COOLDOWN;
+    assert!((0..3).any(|_| balancer.select(later) == 1));
+}
+
+#[test]
+fn test_failover_uses_first_healthy_upstream() {
+    let balancer = Balancer::new(Strategy::Failover, 3);
+    let now = Instant::now();
+    assert_eq!(balancer.select(now), 0);
+
+    balancer.record_failure(0, now);
+    assert_eq!(balancer.select(now), 0);
+    fail(&balancer, 0, now);
+    assert_eq!(balancer.select(now), 1);
+
+    // A success resets the failure streak
+    balancer.record_failure(1, now);
+    balancer.record_success(1, Duration::from_millis(5));
+    balancer.record_failure(1, now);
+    assert_eq!(balancer.select(now), 1);
+}
+
+#[test]
+fn test_fastest_prefers_low_latency_and_probes() {
+    let balancer = Balancer::new(Strategy::Fastest, 2);
+    let now = Instant::now();
+    balancer.record_success(0, Duration::from_millis(80));
+    balancer.record_success(1, Duration::from_millis(10));
+
+    let picks: Vec<usize> = (0..PROBE_EVERY * 2).map(|_| balancer.select(now)).collect();
+    let probes = picks.iter().filter(|&&pick| pick == 0).count();
+    assert!((1..=2).contains(&probes), "picks: {:?}", picks);
+    assert_eq!(picks[0], 1);
+}
+
+#[test]
+fn test_all_upstreams_down_uses_first_to_recover() {
+    let balancer = Balancer::new(Strategy::RoundRobin, 2);
+    let now = Instant::now();
+    fail(&balancer, 1, now);
+    fail(&balancer, 0, now + Duration::from_secs(1));
+    assert_eq!(balancer.select(now + Duration::from_secs(2)), 1);
+}
+
+#[test]
+fn test_stats_track_queries_and_latency() {
+    let balancer = Balancer::new(Strategy::RoundRobin, 1);
+    balancer.record_success(0, Duration::from_millis(10));
+    balancer.record_success(0, Duration::from_millis(20));
+    balancer.record_failure(0, Instant::now());
+
+    let stats = &balancer.stats()[0];
+    assert_eq!(stats.queries, 3);
+    assert_eq!(stats.failures, 1);
+    let average = stats.average_latency_ms.expect("latency measured");
+    assert!(average > 10.0 && average < 20.0);
+    assert!(stats.healthy);
+}
+
+#[test]
+fn test_resolver_upstreams() -> anyhow::Result<()> {
+    let resolver = DnsResolver::new()?;
+    assert_eq!(resolver.upstream_stats()[0].upstream, "system");
+
+    let upstreams = ["192.0.2.1:53".parse()?, "192.0.2.2:5353".parse()?];
+    let resolver = resolver.with_upstreams(&upstreams, Strategy::Failover);
+    let names: Vec<String> = resolver
+        .upstream_stats()
+        .into_iter()
+        .map(|stats| stats.upstream)
+        .collect();
+    assert_eq!(names, ["192.0.2.1:53", "192.0.2.2:5353"]);
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use anyhow::Result;
+use sentri::dns::DnsResolver;
+use sentri::dns_pool::Strategy;
+use sentri::dns_privacy::{parse_ecs, Ecs, PrivacyConfig, EPHEMERAL_PORTS};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use trust_dns_resolver::proto::op::{Message, MessageType};
+use trust_dns_resolver::proto::rr::rdata::opt::{EdnsCode, EdnsOption};
+use trust_dns_resolver::proto::rr::rdata::A;
+use trust_dns_resolver::proto::rr::{RData, Record};
+
+/// A query as it reached the server
+struct Received {
+    source: SocketAddr,
+    query: Message,
+}
+
+/// Starts a DNS server answering every query with 192.0.2.1
+async fn start_server() -> Result<(SocketAddr, mpsc::UnboundedReceiver<Received>)> {
+    let socket = UdpSocket::bind("127.0.0.1:0").await?;
+    let address = socket.local_addr()?;
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 4096];
+        while let Ok((len, source)) = socket.recv_from(&mut buf).await {
+            let Ok(query) = Message::from_vec(&buf[..len]) else {
+                continue;
+            };
+            let mut response = Message::new();
+            response
+                .set_id(query.id())
+                .set_message_type(MessageType::Response)
+                .set_recursion_desired(true)
+                .set_recursion_available(true)
+                .add_queries(query.queries().to_vec());
+            if let Some(question) = query.queries().first() {
+                response.add_answer(Record::from_rdata(
+                    question.name().clone(),
+                    60,
+                    RData::A(A(Ipv4Addr::new(192, 0, 2, 1))),
+                ));
+            }
+            let _ = socket.send_to(&response.to_vec().unwrap(), source).await;
+            let _ = sender.send(Received { source, query });
+        }
+    });
+    Ok((address, receiver))
+}
+
+fn resolver(server: SocketAddr, privacy: PrivacyConfig) -> Result<DnsResolver> {
+    Ok(DnsResolver::new()?
+        .with_upstreams(&[server], Strategy::RoundRobin)
+        .with_privacy(privacy))
+}
+
+fn client_subnet(query: &Message) -> Option<&EdnsOption> {
+    query.extensions().as_ref()?.option(EdnsCode::Subnet)
+}
+
+#[test]
+fn test_parse_ecs_clears_host_bits() {
+    assert_eq!(parse_ecs("OFF").unwrap(), Ecs::Off);
+    assert_eq!(
+        parse_ecs("203.0.113.200/25").unwrap(),
+        Ecs::Subnet {
+            address: IpAddr::V4(Ipv4Addr::new(203, 0, 113, 128)),
+            prefix: 25,
+        }
+    );
+    assert!(parse_ecs("on").is_err());
+    assert!(parse_ecs("2001:db8::/129").is_err());
+}
+
+#[tokio::test]
+async fn test_queries_without_ecs_setting_are_unchanged() -> Result<()> {
+    let (server, mut received) = start_server().await?;
+    let resolver = resolver(server, PrivacyConfig::default())?;
+
+    let ips = resolver.resolve("plain.example.com").await?;
+    assert_eq!(ips, [IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]);
+    let query = received.recv().await.unwrap().query;
+    assert!(client_subnet(&query).is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ecs_off_sends_zero_prefix() -> Result<()> {
+    let (server, mut received) = start_server().await?;
+    let privacy = PrivacyConfig {
+        ecs: Some(Ecs::Off),
+        bind_address: None,
+    };
+    let resolver = resolver(server, privacy)?;
+
+    resolver.resolve("private.example.com").await?;
+    let query = received.recv().await.unwrap().query;
+    let expected = EdnsOption::Subnet("0.0.0.0/0".parse().unwrap());
+    assert_eq!(client_subnet(&query), Some(&expected));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ecs_subnet_is_forwarded() -> Result<()> {
+    let (server, mut received) = start_server().await?;
+    let privacy = PrivacyConfig {
+        ecs: Some(parse_ecs("198.51.100.77/24")?),
+        bind_address: None,
+    };
+    let resolver = resolver(server, privacy)?;
+
+    let records = resolver.resolve("subnet.example.com").await?;
+    assert_eq!(records.len(), 1);
+    let query = received.recv().await.unwrap().query;
+    let expected = EdnsOption::Subnet("198.51.100.0/24".parse().unwrap());
+    assert_eq!(client_subnet(&query), Some(&expected));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_source_ports_are_randomized() -> Result<()> {
+    let (server, mut received) = start_server().await?;
+    let privacy = PrivacyConfig {
+        ecs: None,
+        bind_address: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+    };
+    let resolver = resolver(server, privacy)?;
+
+    let mut sources = Vec::new();
+    let mut ids = Vec::new();
+    for i in 0..4 {
+        resolver.resolve(&format!("host{}.example.com", i)).await?;
+        let received = received.recv().await.unwrap();
+        sources.push(received.source);
+        ids.push(received.query.id());
+    }
+
+    assert!(sources
+        .iter()
+        .all(|source| source.ip() == IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    assert!(sources
+        .iter()
+        .all(|source| EPHEMERAL_PORTS.contains(&source.port())));
+    sources.dedup();
+    ids.dedup();
+    assert!(sources.len() > 1 && ids.len() > 1);
+
+    let stats = resolver.source_ports();
+    assert_eq!(stats.refused, 0);
+    assert!(stats.sockets >= 4);
+    assert!(stats
+        .recent_ports
+        .iter()
+        .all(|port| EPHEMERAL_PORTS.contains(port)));
+    Ok(())
+}
@@ -1,8 +1,9 @@
 use anyhow::Result;
-use sentri::dns::DnsResolver;
+use sentri::dns::{config_or_default, system_config, DnsResolver};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use tokio::sync::oneshot;
 use tokio::time::{sleep, Duration};
+use trust_dns_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
 
 // This is a mock test to verify retry behavior
 // We don't actually perform network calls in unit tests
@@ -66,3 +67,104 @@ async fn test_dns_resolver_with_mock_concept() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_query_timeout_and_attempts() -> Result<()> {
+    use sentri::dns::{DEFAULT_DNS_ATTEMPTS, DEFAULT_DNS_TIMEOUT_MS};
+    use sentri::dns_pool::Strategy;
+    use sentri::retry::RetryConfig;
+
+    let resolver = DnsResolver::new()?;
+    assert_eq!(
+        resolver.query_timeout(),
+        Duration::from_millis(DEFAULT_DNS_TIMEOUT_MS)
+    );
+    assert_eq!(resolver.attempts(), DEFAULT_DNS_ATTEMPTS);
+    assert_eq!(resolver.with_attempts(0).attempts(), 1);
+
+    // An upstream that accepts queries but never answers them
+    let udp = std::net::UdpSocket::bind("127.0.0.1:0")?;
+    let address = udp.local_addr()?;
+    let _tcp = std::net::TcpListener::bind(address)?;
+
+    let resolver = DnsResolver::new()?
+        .with_query_timeout(Duration::from_millis(200))
+        .with_attempts(1)
+        .with_upstreams(&[address], Strategy::default())
+        .with_retry_config(RetryConfig {
+            max_retries: 0,
+            initial_backoff_ms: 10,
+            backoff_factor: 1.0,
+            max_backoff_ms: 10,
+            add_jitter: false,
+        });
+    assert_eq!(resolver.query_timeout(), Duration::from_millis(200));
+
+    let started = std::time::Instant::now();
+    assert!(resolver.resolve("unanswered.example").await.is_err());
+    assert!(
+        started.elapsed() < Duration::from_millis(DEFAULT_DNS_TIMEOUT_MS),
+        "lookup took {:?}",
+        started.elapsed()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dnssec_negatives() -> Result<()> {
+    use sentri::dns_override::DnsOverrides;
+    use sentri::dns_pool::Strategy;
+    use std::sync::Arc;
+
+    let resolver = DnsResolver::new()?;
+    assert!(!resolver.validates_negatives());
+    assert!(!resolver.prove_nonexistent("contosomissing.example").await);
+    if !cfg!(feature = "dnssec") {
+        assert!(resolver.with_dnssec_negatives().is_err());
+        return Ok(());
+    }
+
+    // An upstream that never answers proves nothing
+    let udp = std::net::UdpSocket::bind("127.0.0.1:0")?;
+    let address = udp.local_addr()?;
+    let _tcp = std::net::TcpListener::bind(address)?;
+    let overrides = DnsOverrides::parse("NXDOMAIN blocked.example")?;
+    let resolver = DnsResolver::new()?
+        .with_query_timeout(Duration::from_millis(200))
+        .with_attempts(1)
+        .with_upstreams(&[address], Strategy::default())
+        .with_overrides(Arc::new(overrides))
+        .with_dnssec_negatives()?;
+    assert!(resolver.validates_negatives());
+    assert!(!resolver.prove_nonexistent("unanswered.example").await);
+
+    // Neither does an override
+    assert!(!resolver.prove_nonexistent("blocked.example").await);
+    Ok(())
+}
+
+#[test]
+fn test_system_config_and_default_fallback() {
+    // Name servers read from the system are used as they are
+    let servers =
+        NameServerConfigGroup::from_ips_clear(&[IpAddr::V4(Ipv4Addr::new(10, 0, 0, 53))], 53, true);
+    let system = ResolverConfig::from_parts(None, vec![], servers);
+    assert_eq!(
+        config_or_default(Ok::<_, std::io::Error>((
+            system.clone(),
+            ResolverOpts::default()
+        ))),
+        system
+    );
+
+    // A system configuration that cannot be read falls back to the default
+    assert_eq!(
+        config_or_default(Err(std::io::Error::other("resolv.conf is unreadable"))),
+        ResolverConfig::default()
+    );
+
+    match trust_dns_resolver::system_conf::read_system_conf() {
+        Ok((config, _)) => assert_eq!(system_config(), config),
+        Err(_) => assert_eq!(system_config(), ResolverConfig::default()),
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use sentri::core::MdiChecker;
+use sentri::dns::DnsResolver;
+use sentri::egress::{
+    allow_host, allow_url, allowed_hosts, ensure_url_allowed, is_allowed, is_strict_egress,
+    set_strict_egress, EgressError,
+};
+use sentri::error_class::ErrorClass;
+use sentri::http::HttpClient;
+use sentri::sinks::elasticsearch::{ElasticsearchAuth, ElasticsearchSink};
+use std::time::{Duration, Instant};
+
+// Strict egress is process-wide, so every test in this binary runs with it
+
+#[test]
+fn test_default_allowlist() {
+    set_strict_egress(true);
+    assert!(is_strict_egress());
+
+    assert!(is_allowed("autodiscover-s.outlook.com"));
+    assert!(is_allowed("Login.MicrosoftOnline.com."));
+    assert!(is_allowed("contososensorapi.atp.azure.com"));
+    assert!(is_allowed("contososensorapi.security.microsoft.com"));
+
+    assert!(!is_allowed(""));
+    assert!(!is_allowed("outlook.com"));
+    assert!(!is_allowed("sensorapi.atp.azure.com"));
+    assert!(!is_allowed("login.microsoftonline.com.attacker.example"));
+    assert!(ensure_url_allowed("not a url", "GET not a url").is_err());
+}
+
+#[test]
+fn test_allow_host_patterns() {
+    set_strict_egress(true);
+    allow_host("Splunk.Corp.Example");
+    allow_host("*.intranet.example");
+    allow_url("https://[2001:db8::1]:8088/services");
+
+    assert!(is_allowed("splunk.corp.example"));
+    assert!(!is_allowed("corp.example"));
+    assert!(is_allowed("hec.intranet.example"));
+    assert!(is_allowed("a.b.intranet.example"));
+    assert!(!is_allowed("intranet.example"));
+    assert!(is_allowed("2001:db8::1"));
+    assert!(ensure_url_allowed("https://hec.intranet.example:8088/", "POST").is_ok());
+    assert!(allowed_hosts().contains(&"*.intranet.example".to_string()));
+}
+
+#[test]
+fn test_configured_sinks_are_allowed() -> Result<()> {
+    set_strict_egress(true);
+    assert!(!is_allowed("elastic.sinks.example"));
+    ElasticsearchSink::new(
+        "https://elastic.sinks.example:9200",
+        "sentri",
+        ElasticsearchAuth::None,
+        Duration::from_secs(5),
+    )?;
+    assert!(is_allowed("elastic.sinks.example"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_http_requests_outside_allowlist_fail_immediately() -> Result<()> {
+    set_strict_egress(true);
+    let client = HttpClient::new(Duration::from_secs(30))?;
+
+    let started = Instant::now();
+    let error = client
+        .get("https://telemetry.example/collect")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<EgressError>(),
+        Some(&EgressError {
+            host: "telemetry.example".to_string(),
+            operation: "GET https://telemetry.example/collect".to_string(),
+        })
+    );
+    assert_eq!(ErrorClass::classify(&error), ErrorClass::EgressBlocked);
+    assert_eq!(
+        ErrorClass::from_message(&format!("{:#}", error)),
+        ErrorClass::EgressBlocked
+    );
+    assert!(started.elapsed() < Duration::from_secs(1));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dns_lookups_outside_allowlist_fail() -> Result<()> {
+    set_strict_egress(true);
+    let resolver = DnsResolver::new()?;
+
+    for error in [
+        resolver.resolve("telemetry.example").await.unwrap_err(),
+        resolver.resolve_mx("contoso.example").await.unwrap_err(),
+        resolver.resolve_txt("contoso.example").await.unwrap_err(),
+    ] {
+        assert!(error.downcast_ref::<EgressError>().is_some(), "{:#}", error);
+    }
+    assert!(resolver
+        .upstream_stats()
+        .iter()
+        .all(|stats| stats.queries == 0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_refusals_name_the_blocked_host_in_results() -> Result<()> {
+    set_strict_egress(true);
+    let checker = MdiChecker::new(1, 1000)?.with_http_client(
+        HttpClient::builder()
+            .autodiscover_url("https://autodiscover.blocked.example/autodiscover/autodiscover.svc")
+            .build()?,
+    );
+
+    let result = checker.check_domain("contoso.com").await?;
+    let error = result.error.as_deref().unwrap_or_default();
+    assert!(
+        error
+            .contains("Destination autodiscover.blocked.example is not allowed by --strict-egress"),
+        "{error}"
+    );
+    assert_eq!(result.error_class, Some(ErrorClass::EgressBlocked));
+    Ok(())
+}
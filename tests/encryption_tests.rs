@@ -0,0 +1,283 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sentri::core::{DomainResult, MdiChecker};
+use sentri::encryption::{LineDecoder, LineEncryptor, StorageKey, ENCRYPTED_LINE_PREFIX};
+use sentri::jobs::{JobManager, JobRequest};
+use sentri::rate_limit::RateBudget;
+use sentri::scheduler::Scheduler;
+use sentri::server::{serve, AuditLog, AuditRecord, ServerState, AUDIT_LOG_FILE};
+use sentri::sinks::{JsonlFileSink, ResultSink};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn state_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_state_{}", uuid::Uuid::new_v4()))
+}
+
+fn result(domain: &str) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        tenant: Some("contoso".to_string()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_key_parsing() {
+    let key = StorageKey::generate();
+    assert!(StorageKey::from_base64(&key).is_ok());
+    assert!(StorageKey::from_base64(&format!("{}\n", key)).is_ok());
+    assert!(StorageKey::from_base64("c2hvcnQ=").is_err());
+    assert!(StorageKey::from_base64("not base64!").is_err());
+    assert_eq!(
+        format!("{:?}", StorageKey::from_base64(&key).unwrap()),
+        "StorageKey(..)"
+    );
+}
+
+fn new_key() -> Result<Arc<StorageKey>> {
+    Ok(Arc::new(StorageKey::from_base64(&StorageKey::generate())?))
+}
+
+fn decode_all(key: Option<&StorageKey>, lines: &[String]) -> Result<Vec<String>> {
+    let mut decoder = LineDecoder::new();
+    let mut plain = Vec::new();
+    for line in lines {
+        plain.extend(decoder.decode(key, line)?);
+    }
+    Ok(plain)
+}
+
+#[test]
+fn test_line_round_trip() -> Result<()> {
+    let key = new_key()?;
+    let other = new_key()?;
+    let line = r#"{"domain":"contoso.com"}"#;
+
+    let mut encryptor = LineEncryptor::new(Arc::clone(&key));
+    let file = vec![
+        encryptor.header(),
+        encryptor.encrypt(line)?,
+        encryptor.encrypt(line)?,
+    ];
+    assert!(file[1].starts_with(ENCRYPTED_LINE_PREFIX));
+    assert!(!file[1].contains("contoso"));
+    // Random nonces: the same line never encrypts the same way twice
+    assert_ne!(file[1], file[2]);
+
+    assert_eq!(decode_all(Some(&key), &file)?, [line, line]);
+    assert!(decode_all(Some(&other), &file).is_err());
+    assert!(decode_all(None, &file).is_err());
+
+    // Tampering is detected
+    let mut tampered = file.clone();
+    let end = tampered[1].len();
+    tampered[1].replace_range(end - 4.., "AAAA");
+    assert!(decode_all(Some(&key), &tampered).is_err());
+
+    // Plaintext written before encryption was enabled stays readable
+    let plain = vec![line.to_string()];
+    assert_eq!(decode_all(Some(&key), &plain)?, [line]);
+    assert_eq!(decode_all(None, &plain)?, [line]);
+    Ok(())
+}
+
+#[test]
+fn test_lines_are_bound_to_file_and_position() -> Result<()> {
+    let key = new_key()?;
+    let mut encryptor = LineEncryptor::new(Arc::clone(&key));
+    let file = vec![
+        encryptor.header(),
+        encryptor.encrypt("first")?,
+        encryptor.encrypt("second")?,
+        encryptor.encrypt("third")?,
+    ];
+
+    // Reordered lines
+    let reordered = vec![
+        file[0].clone(),
+        file[2].clone(),
+        file[1].clone(),
+        file[3].clone(),
+    ];
+    assert!(decode_all(Some(&key), &reordered).is_err());
+
+    // A line removed from the middle
+    let removed = vec![file[0].clone(), file[1].clone(), file[3].clone()];
+    assert!(decode_all(Some(&key), &removed).is_err());
+
+    // A line moved into another file encrypted with the same key
+    let other = LineEncryptor::new(Arc::clone(&key));
+    let transplanted = vec![other.header(), file[1].clone()];
+    assert!(decode_all(Some(&key), &transplanted).is_err());
+
+    // Encrypted lines without their header, and plaintext among encrypted lines
+    assert!(decode_all(Some(&key), &file[1..]).is_err());
+    let mixed = vec![file[0].clone(), file[1].clone(), "plain".to_string()];
+    assert!(decode_all(Some(&key), &mixed).is_err());
+
+    // Appending continues the numbering
+    let content = file.join("\n");
+    let mut resumed = LineEncryptor::resume(Arc::clone(&key), &content)?.expect("encrypted");
+    let mut appended = file.clone();
+    appended.push(resumed.encrypt("fourth")?);
+    assert_eq!(
+        decode_all(Some(&key), &appended)?,
+        ["first", "second", "third", "fourth"]
+    );
+    assert!(LineEncryptor::resume(new_key()?, &content).is_err());
+    assert!(LineEncryptor::resume(Arc::clone(&key), "plain\n").is_err());
+    assert!(LineEncryptor::resume(key, "")?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_encrypted_jsonl_sink() -> Result<()> {
+    let dir = state_dir();
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("results.jsonl");
+    let key = new_key()?;
+
+    let mut sink = JsonlFileSink::create(&path)
+        .await?
+        .with_encryption(Arc::clone(&key))
+        .await?;
+    sink.write(&result("contoso.com")).await?;
+    sink.write(&result("fabrikam.com")).await?;
+    sink.close().await?;
+
+    let content = std::fs::read_to_string(&path)?;
+    assert!(!content.contains("contoso"));
+    let lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let domains: Vec<String> = decode_all(Some(&key), &lines)?
+        .iter()
+        .map(|plain| Ok(serde_json::from_str::<DomainResult>(plain)?.domain))
+        .collect::<Result<_>>()?;
+    assert_eq!(domains, ["contoso.com", "fabrikam.com"]);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_encrypted_audit_log() -> Result<()> {
+    let dir = state_dir();
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(AUDIT_LOG_FILE);
+    let key = new_key()?;
+    let record = |status: u16, timestamp: DateTime<Utc>| AuditRecord {
+        timestamp,
+        key: Some("red-team".to_string()),
+        remote_addr: None,
+        method: "GET".to_string(),
+        path: "/jobs".to_string(),
+        status,
+        engagement: None,
+        integrity: None,
+        change: None,
+    };
+    let old = Utc::now() - chrono::Duration::days(30);
+
+    let log = AuditLog::open(&path, Some(Arc::clone(&key))).await?;
+    log.record(&record(200, old)).await?;
+    log.record(&record(201, Utc::now())).await?;
+    drop(log);
+    // Appending after a restart continues the file
+    let log = AuditLog::open(&path, Some(Arc::clone(&key))).await?;
+    log.record(&record(202, Utc::now())).await?;
+
+    let read = |key: &Arc<StorageKey>| -> Result<Vec<u16>> {
+        let lines: Vec<String> = std::fs::read_to_string(&path)?
+            .lines()
+            .map(str::to_string)
+            .collect();
+        decode_all(Some(key), &lines)?
+            .iter()
+            .map(|line| Ok(serde_json::from_str::<AuditRecord>(line)?.status))
+            .collect()
+    };
+    assert!(!std::fs::read_to_string(&path)?.contains("red-team"));
+    assert_eq!(read(&key)?, [200, 201, 202]);
+
+    // Purging rewrites the log encrypted, and recording continues after it
+    assert_eq!(log.purge(Utc::now() - chrono::Duration::days(1)).await?, 1);
+    log.record(&record(203, Utc::now())).await?;
+    assert_eq!(read(&key)?, [201, 202, 203]);
+    assert!(!std::fs::read_to_string(&path)?.contains("red-team"));
+
+    // Neither a missing nor a different key can append
+    assert!(AuditLog::open(&path, None).await.is_err());
+    assert!(AuditLog::open(&path, Some(new_key()?)).await.is_err());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_decrypts_stored_results() -> Result<()> {
+    let dir = state_dir();
+    let key = Arc::new(StorageKey::from_base64(&StorageKey::generate())?);
+    let checker = MdiChecker::new(1, 1000)?;
+    let budget = Arc::new(RateBudget::new(1, 1));
+    let scheduler = Scheduler::open(checker.clone(), &dir, Arc::clone(&budget))
+        .await?
+        .with_storage_key(Arc::clone(&key));
+    let jobs = JobManager::new(checker, &dir, budget)
+        .await?
+        .with_storage_key(Arc::clone(&key));
+    let state = ServerState::new(scheduler, jobs);
+    let jobs = Arc::clone(&state.jobs);
+
+    // Cancel a job and replace its results with known, encrypted content
+    let job = jobs
+        .submit(
+            JobRequest {
+                domains: vec!["contoso.com".to_string(); 10],
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+    jobs.cancel(&job.id).await;
+    for _ in 0..50 {
+        if jobs.get(&job.id).await.expect("job").state.is_finished() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let mut sink = JsonlFileSink::create(&jobs.results_path(&job.id))
+        .await?
+        .with_encryption(Arc::clone(&key))
+        .await?;
+    sink.write(&result("contoso.com")).await?;
+    sink.close().await?;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(serve(listener, state));
+    let client = reqwest::Client::new();
+
+    let body = client
+        .get(format!("{}/jobs/{}/results", base, job.id))
+        .send()
+        .await?
+        .text()
+        .await?;
+    let stored: DomainResult = serde_json::from_str(body.trim_end())?;
+    assert_eq!(stored.domain, "contoso.com");
+
+    let hits: Vec<Value> = client
+        .get(format!("{}/results?q=contoso", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["result"]["domain"], "contoso.com");
+
+    server.abort();
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
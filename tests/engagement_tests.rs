@@ -0,0 +1,134 @@
+use anyhow::Result;
+use sentri::core::{DomainResult, MdiChecker};
+use sentri::engagement::{Engagement, MAX_FIELD_LEN};
+use sentri::jobs::JobManager;
+use sentri::rate_limit::RateBudget;
+use sentri::scheduler::Scheduler;
+use sentri::server::{serve, AuditLog, AuditRecord, ServerState, AUDIT_LOG_FILE};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn state_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_state_{}", uuid::Uuid::new_v4()))
+}
+
+fn engagement(id: &str, operator: &str) -> Engagement {
+    Engagement {
+        engagement_id: Some(id.to_string()),
+        operator: Some(operator.to_string()),
+    }
+}
+
+#[test]
+fn test_engagement_validation() {
+    assert!(Engagement::new(None, None).unwrap().is_none());
+    assert!(Engagement::new(Some("ENG-1".into()), None)
+        .unwrap()
+        .is_some());
+    assert!(Engagement::new(Some("  ".into()), None).is_err());
+    assert!(Engagement::new(None, Some("alice\r\nforged".into())).is_err());
+    assert!(Engagement::new(Some("x".repeat(MAX_FIELD_LEN + 1)), None).is_err());
+    assert!(Engagement::new(Some("x".repeat(MAX_FIELD_LEN)), None).is_ok());
+}
+
+#[test]
+fn test_engagement_defaults() {
+    let defaults = engagement("ENG-1", "red-team");
+    let own = Engagement {
+        engagement_id: Some("ENG-2".to_string()),
+        operator: None,
+    };
+    assert_eq!(own.or(Some(&defaults)), engagement("ENG-2", "red-team"));
+    assert_eq!(Engagement::default().or(None), Engagement::default());
+}
+
+#[test]
+fn test_result_serialization() -> Result<()> {
+    let mut result = DomainResult {
+        domain: "contoso.com".to_string(),
+        ..Default::default()
+    };
+    assert!(serde_json::to_value(&result)?.get("engagement").is_none());
+
+    result.engagement = Some(engagement("ENG-1", "alice"));
+    assert_eq!(
+        serde_json::to_value(&result)?["engagement"],
+        json!({"engagement_id": "ENG-1", "operator": "alice"})
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_jobs_and_audit_record_engagement() -> Result<()> {
+    let dir = state_dir();
+    let checker = MdiChecker::new(1, 1000)?.with_engagement(engagement("ENG-1", "red-team"));
+    let budget = Arc::new(RateBudget::new(1, 1));
+    let scheduler = Scheduler::open(checker.clone(), &dir, Arc::clone(&budget)).await?;
+    let jobs = JobManager::new(checker, &dir, budget).await?;
+    let state = ServerState::new(scheduler, jobs)
+        .with_audit_log(AuditLog::open(&dir.join(AUDIT_LOG_FILE), None).await?);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(serve(listener, state));
+    let client = reqwest::Client::new();
+
+    // Server defaults apply to a job that names no engagement
+    let job: Value = client
+        .post(format!("{}/jobs", base))
+        .json(&json!({"domains": ["contoso.com"]}))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(
+        job["engagement"],
+        json!({"engagement_id": "ENG-1", "operator": "red-team"})
+    );
+    client
+        .delete(format!("{}/jobs/{}", base, job["id"].as_str().unwrap()))
+        .send()
+        .await?;
+
+    // The body overrides the headers, which override the server defaults
+    let job: Value = client
+        .post(format!("{}/jobs", base))
+        .header("X-Engagement-Id", "ENG-2")
+        .header("X-Operator", "alice")
+        .json(&json!({"domains": ["contoso.com"], "operator": "bob"}))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(
+        job["engagement"],
+        json!({"engagement_id": "ENG-2", "operator": "bob"})
+    );
+    client
+        .delete(format!("{}/jobs/{}", base, job["id"].as_str().unwrap()))
+        .send()
+        .await?;
+
+    let response = client
+        .get(format!("{}/jobs", base))
+        .header("X-Operator", "")
+        .send()
+        .await?;
+    assert_eq!(response.status(), 400);
+
+    let audit = std::fs::read_to_string(dir.join(AUDIT_LOG_FILE))?;
+    let records: Vec<AuditRecord> = audit
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(records.len(), 5);
+    assert_eq!(records[0].engagement, Some(engagement("ENG-1", "red-team")));
+    assert_eq!(records[2].engagement, Some(engagement("ENG-2", "alice")));
+    assert_eq!(records[4].status, 400);
+    assert_eq!(records[4].engagement, None);
+
+    server.abort();
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
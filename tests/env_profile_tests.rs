@@ -0,0 +1,98 @@
+use sentri::env_profile::{parse_meminfo, Environment, Resources, Tuning};
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+fn resources(cores: usize, memory_gib: u64) -> Resources {
+    Resources {
+        cores,
+        memory_bytes: Some(memory_gib * GIB),
+        fd_limit: Some(65_536),
+        ci: false,
+    }
+}
+
+#[test]
+fn test_environment_classification() {
+    assert_eq!(resources(4, 16).environment(), Environment::Laptop);
+    assert_eq!(resources(16, 8).environment(), Environment::Laptop);
+    assert_eq!(resources(16, 64).environment(), Environment::Server);
+    // Unknown memory is never taken for a server
+    let unknown = Resources {
+        memory_bytes: None,
+        ..resources(64, 0)
+    };
+    assert_eq!(unknown.environment(), Environment::Laptop);
+    let ci = Resources {
+        ci: true,
+        ..resources(16, 64)
+    };
+    assert_eq!(ci.environment(), Environment::Ci);
+}
+
+#[test]
+fn test_tuning_scales_with_environment() {
+    let ci = Tuning::for_resources(&Resources {
+        ci: true,
+        ..resources(2, 7)
+    });
+    let laptop = Tuning::for_resources(&resources(4, 16));
+    let server = Tuning::for_resources(&resources(32, 128));
+
+    assert_eq!(ci.environment, Environment::Ci);
+    assert_eq!(ci.worker_threads, 2);
+    assert_eq!(laptop.worker_threads, 6);
+    assert_eq!(server.worker_threads, 32);
+    for field in [
+        |t: &Tuning| t.concurrent_requests,
+        |t: &Tuning| t.chunk_size,
+        |t: &Tuning| t.cache_size,
+    ] {
+        assert!(field(&ci) < field(&laptop), "ci below laptop");
+        assert!(field(&laptop) < field(&server), "laptop below server");
+    }
+}
+
+#[test]
+fn test_tuning_clamped_to_resources() {
+    // Few descriptors limit concurrency, never below one check
+    let tuning = Tuning::for_resources(&Resources {
+        fd_limit: Some(128),
+        ..resources(32, 128)
+    });
+    assert_eq!(tuning.concurrent_requests, 16);
+    let tuning = Tuning::for_resources(&Resources {
+        fd_limit: Some(10),
+        ..resources(32, 128)
+    });
+    assert_eq!(tuning.concurrent_requests, 1);
+
+    // Little memory bounds the cache
+    let tuning = Tuning::for_resources(&resources(4, 1));
+    assert_eq!(tuning.cache_size, 8_192);
+
+    // A single core still gets a worker
+    let tuning = Tuning::for_resources(&Resources {
+        ci: true,
+        ..resources(0, 4)
+    });
+    assert_eq!(tuning.worker_threads, 1);
+}
+
+#[test]
+fn test_parse_meminfo() {
+    assert_eq!(
+        parse_meminfo("MemTotal:        8000000 kB\nSwapTotal: 0 kB\n"),
+        Some(8_000_000 * 1024)
+    );
+    assert_eq!(parse_meminfo("MemTotal: lots"), None);
+    assert_eq!(parse_meminfo(""), None);
+}
+
+#[test]
+fn test_detected_resources() {
+    let resources = Resources::detect();
+    assert!(resources.cores >= 1);
+    let tuning = Tuning::for_resources(&resources);
+    assert!(tuning.worker_threads >= 1);
+    assert!(tuning.concurrent_requests >= 1);
+}
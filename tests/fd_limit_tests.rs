@@ -0,0 +1,47 @@
+use sentri::fd_limit::{
+    clamp_concurrency, current, max_concurrency, preflight, required_fds, FdLimit, RESERVED_FDS,
+    SOCKETS_PER_CHECK,
+};
+
+#[test]
+fn test_required_fds_round_trip() {
+    for concurrency in [1, 48, 100, 5_000] {
+        let required = required_fds(concurrency);
+        assert_eq!(
+            required,
+            concurrency as u64 * SOCKETS_PER_CHECK + RESERVED_FDS
+        );
+        assert_eq!(max_concurrency(required), concurrency);
+        assert_eq!(max_concurrency(required - 1), (concurrency - 1).max(1));
+    }
+}
+
+#[test]
+fn test_clamp_concurrency() {
+    let limit = FdLimit {
+        soft: 1024,
+        hard: 1024,
+    };
+    assert_eq!(clamp_concurrency(10, limit), 10);
+    assert_eq!(clamp_concurrency(240, limit), 240);
+    assert_eq!(clamp_concurrency(241, limit), 240);
+
+    // A limit below the reserve still allows one check
+    let limit = FdLimit { soft: 32, hard: 32 };
+    assert_eq!(clamp_concurrency(100, limit), 1);
+}
+
+#[test]
+fn test_preflight_against_process_limit() {
+    let Some(limit) = current() else {
+        return;
+    };
+    assert!(limit.soft <= limit.hard);
+
+    // Concurrency within the limit is left alone
+    assert_eq!(preflight(1, false), 1);
+
+    // Concurrency the hard limit cannot serve is always clamped
+    let clamped = preflight(usize::MAX / 8, false);
+    assert_eq!(clamped, max_concurrency(limit.soft));
+}
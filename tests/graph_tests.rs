@@ -0,0 +1,99 @@
+use sentri::core::DomainResult;
+use sentri::graph::{EdgeKind, Graph, GraphFormat, NodeKind};
+
+fn result(json: &str) -> DomainResult {
+    serde_json::from_str(json).unwrap()
+}
+
+fn results() -> Vec<DomainResult> {
+    vec![
+        result(
+            r#"{"domain": "contoso.com", "tenant": "contoso", "federated_domains": ["contoso.com", "Fabrikam.com."],
+                "mdi_instance": "contososensorapi.atp.azure.com", "processing_time_ms": 10, "error": null}"#,
+        ),
+        result(
+            r#"{"domain": "fabrikam.com", "tenant": "contoso", "federated_domains": ["contoso.com"],
+                "mdi_instance": null, "processing_time_ms": 10, "error": null}"#,
+        ),
+        result(
+            r#"{"domain": "broken.example", "tenant": "ignored", "federated_domains": [],
+                "mdi_instance": null, "processing_time_ms": 10, "error": "timeout"}"#,
+        ),
+    ]
+}
+
+#[test]
+fn test_graph_merges_shared_nodes() {
+    let graph = Graph::from_results(&results());
+    let ids: Vec<&str> = graph.nodes().iter().map(|node| node.id.as_str()).collect();
+    assert_eq!(
+        ids,
+        [
+            "domain:broken.example",
+            "domain:contoso.com",
+            "domain:fabrikam.com",
+            "mdi_instance:contososensorapi.atp.azure.com",
+            "tenant:contoso",
+        ]
+    );
+    assert_eq!(graph.nodes()[4].kind, NodeKind::Tenant);
+
+    let edges: Vec<(&str, &str, EdgeKind)> = graph
+        .edges()
+        .iter()
+        .map(|edge| (edge.source.as_str(), edge.target.as_str(), edge.kind))
+        .collect();
+    assert_eq!(
+        edges,
+        [
+            (
+                "domain:contoso.com",
+                "domain:fabrikam.com",
+                EdgeKind::Federation
+            ),
+            ("domain:contoso.com", "tenant:contoso", EdgeKind::Tenant),
+            (
+                "domain:fabrikam.com",
+                "domain:contoso.com",
+                EdgeKind::Federation
+            ),
+            ("domain:fabrikam.com", "tenant:contoso", EdgeKind::Tenant),
+            (
+                "tenant:contoso",
+                "mdi_instance:contososensorapi.atp.azure.com",
+                EdgeKind::Mdi
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_json_graph_format() {
+    let graph = Graph::from_results(&results());
+    let json: serde_json::Value = serde_json::from_str(&graph.render(GraphFormat::Json)).unwrap();
+    assert_eq!(json["graph"]["directed"], true);
+    assert_eq!(
+        json["graph"]["nodes"]["tenant:contoso"]["metadata"]["kind"],
+        "tenant"
+    );
+    assert_eq!(json["graph"]["edges"].as_array().unwrap().len(), 5);
+    assert_eq!(json["graph"]["edges"][4]["relation"], "mdi");
+}
+
+#[test]
+fn test_graphml_and_dot_escape_values() {
+    let hostile = result(
+        r#"{"domain": "evil.example", "tenant": "a\"b<c>&d", "federated_domains": [],
+            "mdi_instance": null, "processing_time_ms": 10, "error": null}"#,
+    );
+    let graph = Graph::from_results(&[hostile]);
+
+    let graphml = graph.render(GraphFormat::Graphml);
+    assert!(graphml.contains("<node id=\"tenant:a&quot;b&lt;c&gt;&amp;d\">"));
+    assert!(graphml.contains("<data key=\"label\">a\"b&lt;c&gt;&amp;d</data>"));
+    assert!(graphml.contains("edgedefault=\"directed\""));
+
+    let dot = graph.render(GraphFormat::Dot);
+    assert!(dot.starts_with("digraph sentri {"));
+    assert!(dot.contains("\"domain:evil.example\" -> \"tenant:a\\\"b<c>&d\" [label=tenant];"));
+}
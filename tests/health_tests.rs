@@ -0,0 +1,62 @@
+use anyhow::Result;
+use sentri::core::MdiChecker;
+use sentri::jobs::JobManager;
+use sentri::rate_limit::RateBudget;
+use sentri::scheduler::Scheduler;
+use sentri::server::health::{CheckStatus, HealthReport};
+use sentri::server::{serve, ApiKeys, ServerState};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_health_probes() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_state_{}", uuid::Uuid::new_v4()));
+    let checker = MdiChecker::new(1, 1000)?;
+    let budget = Arc::new(RateBudget::new(1, 1));
+    let scheduler = Scheduler::open(checker.clone(), &dir, Arc::clone(&budget)).await?;
+    let jobs = JobManager::new(checker, &dir, Arc::clone(&budget)).await?;
+    let keys = ApiKeys::from_toml(&format!(
+        "[[key]]\nname = \"red\"\nkey_sha256 = \"{}\"\n",
+        "0".repeat(64)
+    ))?;
+    let state = ServerState::new(scheduler, jobs).with_api_keys(keys);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(serve(listener, state));
+    let client = reqwest::Client::new();
+
+    // Probes need no API key
+    let response = client.get(format!("{}/healthz", base)).send().await?;
+    assert_eq!(response.status(), 200);
+    let report: HealthReport = response.json().await?;
+    assert_eq!(report.status, CheckStatus::Pass);
+    let components: Vec<&str> = report.checks.iter().map(|c| c.component.as_str()).collect();
+    assert_eq!(components, ["state_dir", "rate_limit"]);
+
+    // Spending the only token of the shared budget degrades, but does not fail
+    assert!(budget.shared().try_take().await);
+    let report: HealthReport = client
+        .get(format!("{}/healthz", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(report.status, CheckStatus::Warn);
+    assert_eq!(report.checks[1].status, CheckStatus::Warn);
+
+    // External dependencies may be unreachable from the test environment;
+    // either way every component is reported and failures answer 503
+    let response = client.get(format!("{}/readyz", base)).send().await?;
+    let code = response.status().as_u16();
+    let report: HealthReport = response.json().await?;
+    let components: Vec<&str> = report.checks.iter().map(|c| c.component.as_str()).collect();
+    assert_eq!(
+        components,
+        ["state_dir", "rate_limit", "autodiscover", "dns"]
+    );
+    assert_eq!(code == 503, report.status == CheckStatus::Fail);
+
+    server.abort();
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -1,9 +1,14 @@
 use anyhow::Result;
-use sentri::http::HttpClient;
+use sentri::error_class::ErrorClass;
+use sentri::http::{
+    HttpClient, ResponseTooLarge, DEFAULT_ACCEPT_LANGUAGE, DEFAULT_MAX_DECOMPRESSED_SIZE,
+    DEFAULT_MAX_RESPONSE_SIZE,
+};
 // Import modules directly as they are exported in lib.rs
 use reqwest::tls::Version;
 use sentri::rate_limit::RateLimiter;
 use sentri::retry::RetryConfig;
+use sentri::xml::parse_soap_fault;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::test;
@@ -116,6 +121,28 @@ async fn test_http_client_security_features() -> Result<()> {
     Ok(())
 }
 
+#[test]
+async fn test_http_client_compression_settings() -> Result<()> {
+    assert_eq!(DEFAULT_MAX_DECOMPRESSED_SIZE, 32 * 1024 * 1024);
+
+    // Compressed transfer with a small decompression cap
+    let client = HttpClient::builder()
+        .timeout(Duration::from_secs(2))
+        .compression(true)
+        .max_decompressed_size(64 * 1024)
+        .build()?;
+    assert!(client.post_soap_request("test").await.is_err());
+
+    // Identity transfer only
+    let identity_client = HttpClient::builder()
+        .timeout(Duration::from_secs(2))
+        .compression(false)
+        .build()?;
+    assert!(identity_client.post_soap_request("test").await.is_err());
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_http_client_connection_pooling() -> Result<()> {
     // Create client with default connection pooling settings
@@ -180,3 +207,95 @@ async fn test_http_client_with_custom_request() -> Result<()> {
 
     Ok(())
 }
+
+const BUSY_FAULT: &str = r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+  <s:Body>
+    <s:Fault>
+      <faultcode>s:Server</faultcode>
+      <faultstring>The server cannot service this request right now.</faultstring>
+      <detail>
+        <e:ResponseCode xmlns:e="http://schemas.microsoft.com/exchange/services/2006/errors">ErrorServerBusy</e:ResponseCode>
+      </detail>
+    </s:Fault>
+  </s:Body>
+</s:Envelope>"#;
+
+const INVALID_DOMAIN_FAULT: &str = r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+  <s:Body>
+    <s:Fault>
+      <faultcode>s:Client</faultcode>
+      <faultstring>Invalid domain</faultstring>
+      <detail>
+        <e:ResponseCode xmlns:e="http://schemas.microsoft.com/exchange/services/2006/errors">ErrorInvalidDomain</e:ResponseCode>
+      </detail>
+    </s:Fault>
+  </s:Body>
+</s:Envelope>"#;
+
+#[tokio::test]
+async fn test_soap_faults_drive_retries() -> Result<()> {
+    let client = HttpClient::new(Duration::from_secs(5))?;
+    let failed = |fault: &str| {
+        anyhow::Error::new(parse_soap_fault(fault).expect("fault"))
+            .context("HTTP request failed with status: 500 Internal Server Error")
+    };
+
+    let busy = failed(BUSY_FAULT);
+    assert!(client.is_retriable_error(&busy));
+    assert_eq!(ErrorClass::classify(&busy), ErrorClass::RateLimited);
+
+    let invalid = failed(INVALID_DOMAIN_FAULT);
+    assert!(!client.is_retriable_error(&invalid));
+    assert_eq!(ErrorClass::classify(&invalid), ErrorClass::InvalidDomain);
+    Ok(())
+}
+
+#[test]
+async fn test_response_too_large_is_an_invalid_response() -> Result<()> {
+    let announced = anyhow::Error::new(ResponseTooLarge {
+        limit: 1024,
+        content_length: Some(4096),
+    })
+    .context("SOAP response rejected");
+    assert_eq!(
+        announced.root_cause().to_string(),
+        "Response body of 4096 bytes exceeds the limit of 1024 bytes"
+    );
+    assert_eq!(
+        ErrorClass::classify(&announced),
+        ErrorClass::InvalidResponse
+    );
+
+    let streamed = ResponseTooLarge {
+        limit: 1024,
+        content_length: None,
+    };
+    assert_eq!(
+        ErrorClass::from_message(&streamed.to_string()),
+        ErrorClass::InvalidResponse
+    );
+
+    // The limit is configurable per client
+    let client = HttpClient::builder()
+        .timeout(Duration::from_secs(2))
+        .max_response_size(DEFAULT_MAX_RESPONSE_SIZE / 2)
+        .build()?;
+    assert!(!client.is_retriable_error(&announced));
+    Ok(())
+}
+
+#[test]
+async fn test_accept_language_is_validated() -> Result<()> {
+    assert_eq!(DEFAULT_ACCEPT_LANGUAGE, "en-US");
+    HttpClient::builder()
+        .accept_language("de-DE, en;q=0.5")
+        .build()?;
+
+    let err = HttpClient::builder()
+        .accept_language("en-US\nX-Injected: 1")
+        .build()
+        .err()
+        .expect("invalid header value rejected");
+    assert!(err.to_string().contains("Invalid Accept-Language"));
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use anyhow::Result;
+use sentri::core::MdiChecker;
+use sentri::engagement::Engagement;
+use sentri::jobs::{JobEvent, JobManager, JobRequest, JobState};
+use sentri::rate_limit::RateBudget;
+use sentri::scheduler::Scheduler;
+use sentri::server::{serve, ServerState};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn state_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_state_{}", uuid::Uuid::new_v4()))
+}
+
+fn domains(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|i| format!("domain{}.example.com", i))
+        .collect()
+}
+
+async fn manager(dir: &Path) -> Result<Arc<JobManager>> {
+    // A tiny budget keeps the job busy long enough to observe and cancel it
+    let budget = Arc::new(RateBudget::new(1, 1));
+    Ok(Arc::new(
+        JobManager::new(MdiChecker::new(1, 1000)?, dir, budget).await?,
+    ))
+}
+
+#[test]
+fn test_job_state_is_finished() {
+    assert!(!JobState::Queued.is_finished());
+    assert!(!JobState::Running.is_finished());
+    assert!(JobState::Completed.is_finished());
+    assert!(JobState::Cancelled.is_finished());
+    assert!(JobState::Failed.is_finished());
+}
+
+#[tokio::test]
+async fn test_submit_validates_domains() -> Result<()> {
+    let dir = state_dir();
+    let jobs = manager(&dir).await?;
+
+    assert!(jobs.submit(JobRequest::default(), None).await.is_err());
+    assert!(jobs
+        .submit(
+            JobRequest {
+                domains: vec!["not a domain".to_string()],
+                ..Default::default()
+            },
+            None
+        )
+        .await
+        .is_err());
+    assert!(jobs
+        .submit(
+            JobRequest {
+                domains: domains(1),
+                engagement: Engagement {
+                    engagement_id: Some("ENG\n1".to_string()),
+                    operator: None,
+                },
+            },
+            None
+        )
+        .await
+        .is_err());
+    assert!(jobs.list().await.is_empty());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancel_job() -> Result<()> {
+    let dir = state_dir();
+    let jobs = manager(&dir).await?;
+
+    let job = jobs
+        .submit(
+            JobRequest {
+                domains: domains(50),
+                ..Default::default()
+            },
+            Some("red".to_string()),
+        )
+        .await?;
+    assert_eq!(job.total, 50);
+    assert_eq!(job.owner.as_deref(), Some("red"));
+    assert_eq!(jobs.cancel(&job.id).await, Some(true));
+    assert_eq!(jobs.cancel("missing").await, None);
+
+    let mut status = jobs.get(&job.id).await.expect("job exists");
+    for _ in 0..50 {
+        if status.state.is_finished() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        status = jobs.get(&job.id).await.expect("job exists");
+    }
+    assert_eq!(status.state, JobState::Cancelled);
+    assert!(status.finished_at.is_some());
+    assert!(status.progress.domains_processed < 50);
+
+    // Finished jobs cannot be cancelled again
+    assert_eq!(jobs.cancel(&job.id).await, Some(false));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_subscribe_to_job_events() -> Result<()> {
+    let dir = state_dir();
+    let jobs = manager(&dir).await?;
+
+    let job = jobs
+        .submit(
+            JobRequest {
+                domains: domains(50),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+    let (status, receiver) = jobs.subscribe(&job.id).await.expect("job exists");
+    assert!(!status.state.is_finished());
+    let mut receiver = receiver.expect("running job has a receiver");
+    jobs.cancel(&job.id).await;
+
+    let finished = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let JobEvent::Finished(status) = receiver.recv().await? {
+                return Ok::<_, anyhow::Error>(status);
+            }
+        }
+    })
+    .await??;
+    assert_eq!(finished.state, JobState::Cancelled);
+
+    // Finished jobs have nothing left to stream
+    let (status, receiver) = jobs.subscribe(&job.id).await.expect("job exists");
+    assert_eq!(status.state, JobState::Cancelled);
+    assert!(receiver.is_none());
+    assert!(jobs.subscribe("missing").await.is_none());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_job_api() -> Result<()> {
+    let dir = state_dir();
+    let checker = MdiChecker::new(1, 1000)?;
+    let budget = Arc::new(RateBudget::new(1, 1));
+    let scheduler = Scheduler::open(checker.clone(), &dir, Arc::clone(&budget)).await?;
+    let jobs = JobManager::new(checker, &dir, budget).await?;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(serve(listener, ServerState::new(scheduler, jobs)));
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/jobs", base))
+        .json(&json!({ "domains": domains(20) }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 202);
+    let job: Value = response.json().await?;
+    let id = job["id"].as_str().expect("id").to_string();
+    assert_eq!(job["total"], 20);
+
+    let response = client
+        .post(format!("{}/jobs", base))
+        .json(&json!({ "domains": [] }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 400);
+
+    let status: Value = client
+        .get(format!("{}/jobs/{}", base, id))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert!(status["progress"]["domains_processed"].is_number());
+
+    let response = client
+        .delete(format!("{}/jobs/{}", base, id))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 202);
+
+    let response = client
+        .get(format!("{}/jobs/{}/results", base, id))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+
+    // Once the cancellation lands, the event stream reports the final status and closes
+    for _ in 0..50 {
+        let status: Value = client
+            .get(format!("{}/jobs/{}", base, id))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if status["state"] == "cancelled" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let response = client
+        .get(format!("{}/jobs/{}/events", base, id))
+        .send()
+        .await?;
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let body = response.text().await?;
+    assert!(body.starts_with("event: finished\n"));
+    assert!(body.contains("\"state\":\"cancelled\""));
+
+    let listed: Value = client
+        .get(format!("{}/jobs", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(listed.as_array().map(Vec::len), Some(1));
+
+    let response = client
+        .get(format!("{}/jobs/unknown/results", base))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 404);
+
+    server.abort();
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,232 @@
+use clap::Parser;
+use sentri::cli::{Cli, Commands};
+
+#[cfg(feature = "kafka")]
+mod producer {
+    use anyhow::Result;
+    use rdkafka::consumer::{BaseConsumer, Consumer};
+    use rdkafka::mocking::MockCluster;
+    use rdkafka::producer::DefaultProducerContext;
+    use rdkafka::types::{RDKafkaApiKey, RDKafkaRespErr};
+    use rdkafka::{ClientConfig, Message};
+    use sentri::core::DomainResult;
+    use sentri::sinks::{KafkaSink, ResultSink};
+    use std::time::{Duration, Instant};
+
+    /// Message read back from the cluster: partition, key and value
+    type Received = (i32, String, String);
+
+    fn cluster(
+        topic: &str,
+        partitions: i32,
+    ) -> Result<MockCluster<'static, DefaultProducerContext>> {
+        let cluster = MockCluster::new(3)?;
+        cluster.create_topic(topic, partitions, 1)?;
+        Ok(cluster)
+    }
+
+    /// Reads `count` messages of `topic` from the beginning
+    fn consume(brokers: &str, topic: &str, count: usize) -> Result<Vec<Received>> {
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("group.id", "sentri-tests")
+            .set("auto.offset.reset", "earliest")
+            .create()?;
+        consumer.subscribe(&[topic])?;
+        let deadline = Instant::now() + Duration::from_secs(30);
+        let mut received = Vec::new();
+        while received.len() < count && Instant::now() < deadline {
+            if let Some(message) = consumer.poll(Duration::from_millis(100)) {
+                let message = message?;
+                let text = |bytes: Option<&[u8]>| {
+                    String::from_utf8_lossy(bytes.unwrap_or_default()).into_owned()
+                };
+                received.push((
+                    message.partition(),
+                    text(message.key()),
+                    text(message.payload()),
+                ));
+            }
+        }
+        // Anything beyond `count` would be a duplicate
+        if let Some(message) = consumer.poll(Duration::from_millis(500)) {
+            let message = message?;
+            received.push((message.partition(), String::new(), String::new()));
+        }
+        Ok(received)
+    }
+
+    fn result(domain: &str) -> DomainResult {
+        DomainResult {
+            domain: domain.to_string(),
+            tenant: Some("contoso".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_kafka_sink_produces_results_by_key() -> Result<()> {
+        let cluster = cluster("sentri.results", 12)?;
+        let brokers = cluster.bootstrap_servers();
+        let mut sink = KafkaSink::new(
+            std::slice::from_ref(&brokers),
+            "sentri.results",
+            Duration::from_secs(10),
+        )
+        .with_batch_size(2);
+
+        // murmur2 partitions of the Java client's default partitioner
+        let domains = ["contoso.com", "21", "foobar", "fabrikam.com", "adatum.com"];
+        for domain in domains {
+            sink.write(&result(domain)).await?;
+        }
+        sink.close().await?;
+
+        let mut received = consume(&brokers, "sentri.results", domains.len())?;
+        received.sort_by(|a, b| a.1.cmp(&b.1));
+        let mut expected = domains.to_vec();
+        expected.sort();
+        assert_eq!(
+            received.iter().map(|m| m.1.as_str()).collect::<Vec<_>>(),
+            expected
+        );
+        for (partition, key, value) in &received {
+            let result: DomainResult = serde_json::from_str(value)?;
+            assert_eq!(&result.domain, key);
+            assert_eq!(result.tenant.as_deref(), Some("contoso"));
+            match key.as_str() {
+                "21" => assert_eq!(*partition, 0),
+                "foobar" => assert_eq!(*partition, 6),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_deliveries_are_retried_without_duplicates() -> Result<()> {
+        let cluster = cluster("sentri.results", 3)?;
+        let brokers = cluster.bootstrap_servers();
+        let mut sink = KafkaSink::new(
+            std::slice::from_ref(&brokers),
+            "sentri.results",
+            Duration::from_secs(10),
+        );
+
+        let domains = [
+            "contoso.com",
+            "fabrikam.com",
+            "woodgrovebank.com",
+            "tailspintoys.com",
+            "adatum.com",
+        ];
+        for domain in domains {
+            sink.write(&result(domain)).await?;
+        }
+        cluster.request_errors(
+            RDKafkaApiKey::Produce,
+            &[RDKafkaRespErr::RD_KAFKA_RESP_ERR_MSG_SIZE_TOO_LARGE],
+        );
+        let error = sink.flush().await.unwrap_err();
+        assert!(format!("{:#}", error).contains("results to Kafka topic sentri.results"));
+
+        sink.flush().await?;
+        let mut received: Vec<String> = consume(&brokers, "sentri.results", domains.len())?
+            .into_iter()
+            .map(|(_, key, _)| key)
+            .collect();
+        received.sort();
+        let mut expected = domains.to_vec();
+        expected.sort();
+        assert_eq!(received, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_kafka_sink_reports_unavailable_topic() -> Result<()> {
+        let cluster = cluster("sentri.results", 1)?;
+        cluster.topic_error(
+            "sentri.results",
+            RDKafkaRespErr::RD_KAFKA_RESP_ERR_TOPIC_AUTHORIZATION_FAILED,
+        )?;
+        let mut sink = KafkaSink::new(
+            &[cluster.bootstrap_servers()],
+            "sentri.results",
+            Duration::from_secs(5),
+        );
+        sink.write(&result("contoso.com")).await?;
+
+        let error = format!("{:#}", sink.flush().await.unwrap_err());
+        assert!(
+            error.contains("Kafka topic sentri.results is unavailable"),
+            "{}",
+            error
+        );
+        assert!(error.contains("authorization"), "{}", error);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_kafka_args_parsing() {
+    let cli = Cli::parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--kafka-brokers",
+        "kafka-1:9093,kafka-2:9093",
+        "--kafka-topic",
+        "sentri.results",
+        "--kafka-tls",
+        "--kafka-sasl-mechanism",
+        "SCRAM-SHA-512",
+        "--kafka-sasl-username",
+        "sentri",
+        "--kafka-sasl-password",
+        "secret",
+    ]);
+    let Commands::Batch { sinks, .. } = cli.command else {
+        panic!("expected batch command");
+    };
+    assert_eq!(sinks.kafka_brokers, ["kafka-1:9093", "kafka-2:9093"]);
+    assert!(sinks.kafka_tls);
+    assert_eq!(sinks.kafka_sasl_mechanism.as_deref(), Some("SCRAM-SHA-512"));
+    #[cfg(feature = "kafka")]
+    assert!(
+        sentri::sinks::KafkaSink::from_args(&sinks, std::time::Duration::from_secs(1)).is_some()
+    );
+    #[cfg(not(feature = "kafka"))]
+    {
+        let error = sentri::sinks::build_sinks(&sinks, std::time::Duration::from_secs(1))
+            .err()
+            .expect("kafka without the feature");
+        assert!(error.to_string().contains("kafka feature"));
+    }
+
+    let missing_topic = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--kafka-brokers",
+        "kafka-1:9092",
+    ]);
+    assert!(missing_topic.is_err());
+
+    let missing_password = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--kafka-brokers",
+        "kafka-1:9092",
+        "--kafka-topic",
+        "sentri.results",
+        "--kafka-sasl-mechanism",
+        "PLAIN",
+        "--kafka-sasl-username",
+        "sentri",
+    ]);
+    assert!(missing_password.is_err());
+}
@@ -0,0 +1,95 @@
+use sentri::latency::{LatencyConfig, LatencyTracker, DEFAULT_MIN_SAMPLES, DEFAULT_WINDOW};
+use std::time::Duration;
+
+fn tracker(min_samples: usize, penalty: Duration) -> LatencyTracker {
+    LatencyTracker::new(LatencyConfig {
+        window: 20,
+        min_samples,
+        penalty,
+        ..LatencyConfig::new(Duration::from_millis(500))
+    })
+}
+
+#[test]
+fn test_default_config() {
+    let config = LatencyConfig::new(Duration::from_secs(2));
+    assert_eq!(config.threshold, Duration::from_secs(2));
+    assert_eq!(config.window, DEFAULT_WINDOW);
+    assert_eq!(config.min_samples, DEFAULT_MIN_SAMPLES);
+    assert_eq!(config.penalty, Duration::from_secs(300));
+}
+
+#[test]
+fn test_host_flagged_once_p95_exceeds_threshold() {
+    let tracker = tracker(20, Duration::from_secs(60));
+
+    // 19 fast and 1 slow request: the p95 is still fast
+    for _ in 0..19 {
+        assert!(tracker
+            .record("autodiscover.example", Duration::from_millis(100))
+            .is_none());
+    }
+    assert!(tracker
+        .record("autodiscover.example", Duration::from_secs(3))
+        .is_none());
+    assert_eq!(
+        tracker.p95("autodiscover.example"),
+        Some(Duration::from_millis(100))
+    );
+    assert!(tracker.time_box("autodiscover.example").is_none());
+    assert!(tracker.findings().is_empty());
+
+    // The window slides: a second slow request reaches the 95th percentile
+    let finding = tracker
+        .record("Autodiscover.Example", Duration::from_secs(3))
+        .expect("host flagged");
+    assert_eq!(finding.host, "autodiscover.example");
+    assert_eq!(finding.p95_ms, 3000);
+    assert_eq!(finding.threshold_ms, 500);
+    assert_eq!(finding.samples, 20);
+    assert_eq!(finding.times_flagged, 1);
+
+    assert_eq!(
+        tracker.time_box("AUTODISCOVER.example"),
+        Some(Duration::from_millis(500))
+    );
+    assert!(tracker.time_box("other.example").is_none());
+    assert_eq!(tracker.findings(), vec![finding]);
+}
+
+#[test]
+fn test_p95_below_threshold_is_not_flagged() {
+    let tracker = tracker(5, Duration::from_secs(60));
+    for _ in 0..50 {
+        assert!(tracker
+            .record("fast.example", Duration::from_millis(500))
+            .is_none());
+    }
+    assert!(tracker.time_box("fast.example").is_none());
+}
+
+#[test]
+fn test_penalty_expires_and_host_can_be_flagged_again() {
+    let tracker = tracker(2, Duration::from_millis(50));
+    tracker.record("slow.example", Duration::from_secs(1));
+    assert!(tracker
+        .record("slow.example", Duration::from_secs(1))
+        .is_some());
+    // Flagged hosts are not flagged again during their penalty
+    assert!(tracker
+        .record("slow.example", Duration::from_secs(1))
+        .is_none());
+
+    std::thread::sleep(Duration::from_millis(80));
+    assert!(tracker.time_box("slow.example").is_none());
+    // The window starts over after the penalty
+    assert_eq!(tracker.p95("slow.example"), None);
+
+    tracker.record("slow.example", Duration::from_secs(2));
+    let finding = tracker
+        .record("slow.example", Duration::from_secs(2))
+        .expect("host flagged again");
+    assert_eq!(finding.times_flagged, 2);
+    assert_eq!(finding.p95_ms, 2000);
+    assert_eq!(tracker.findings().len(), 1);
+}
@@ -0,0 +1,38 @@
+use sentri::logging::{LogSampler, DEFAULT_EVERY, DEFAULT_FIRST};
+
+#[test]
+fn test_first_occurrences_are_logged() {
+    let sampler = LogSampler::new(3, 5);
+    let logged: Vec<Option<u64>> = (0..4).map(|_| sampler.sample("dns.retriable")).collect();
+    assert_eq!(logged, [Some(1), Some(2), Some(3), None]);
+    assert_eq!(sampler.suppressed(), vec![("dns.retriable", 1)]);
+}
+
+#[test]
+fn test_then_one_in_every() {
+    let sampler = LogSampler::new(3, 5);
+    let logged: Vec<u64> = (0..20)
+        .filter_map(|_| sampler.sample("http.server_error"))
+        .collect();
+    assert_eq!(logged, [1, 2, 3, 8, 13, 18]);
+    assert_eq!(sampler.occurrences("http.server_error"), 20);
+    assert_eq!(sampler.suppressed(), vec![("http.server_error", 14)]);
+}
+
+#[test]
+fn test_classes_are_counted_separately() {
+    let sampler = LogSampler::default();
+    for _ in 0..DEFAULT_FIRST + DEFAULT_EVERY {
+        sampler.sample("http.rate_limited");
+    }
+    assert_eq!(sampler.sample("dns.unknown"), Some(1));
+    assert_eq!(
+        sampler.occurrences("http.rate_limited"),
+        DEFAULT_FIRST + DEFAULT_EVERY
+    );
+    assert_eq!(sampler.occurrences("core.federation_failed"), 0);
+    assert_eq!(
+        sampler.suppressed(),
+        vec![("http.rate_limited", DEFAULT_EVERY - 1)]
+    );
+}
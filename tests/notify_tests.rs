@@ -0,0 +1,170 @@
+use anyhow::Result;
+use chrono::Utc;
+use clap::Parser;
+use sentri::cli::{Cli, Commands};
+use sentri::core::{BatchSummary, DomainResult, Truncation};
+use sentri::latency::SlowHost;
+use sentri::notify::{render_html, render_text, report_subject, EmailConfig, RunOutcome};
+use sentri::stats::{Detector, DetectorStats};
+use std::time::Duration;
+
+fn sample_summary() -> BatchSummary {
+    let mut summary = BatchSummary::new();
+    summary.record(&DomainResult {
+        domain: "contoso.com".to_string(),
+        tenant: Some("contoso".to_string()),
+        mdi_instance: Some("contososensorapi.atp.azure.com".to_string()),
+        ..Default::default()
+    });
+    summary.record(&DomainResult {
+        domain: "broken.example".to_string(),
+        error: Some("timeout".to_string()),
+        ..Default::default()
+    });
+    summary.finish();
+    summary
+}
+
+#[test]
+fn test_batch_summary_counts() {
+    let summary = sample_summary();
+
+    assert_eq!(summary.domains_processed, 2);
+    assert_eq!(summary.tenants_found, 1);
+    assert_eq!(summary.mdi_instances, 1);
+    assert_eq!(summary.errors, 1);
+}
+
+#[test]
+fn test_render_completed_report() {
+    let outcome = RunOutcome::Completed(sample_summary());
+
+    assert_eq!(
+        report_subject(&outcome),
+        "[sentri] Batch completed: 2 domains, 1 with MDI"
+    );
+
+    let text = render_text(&outcome);
+    assert!(text.contains("Domains processed:  2"));
+    assert!(text.contains("Errors:             1"));
+
+    let html = render_html(&outcome);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<th>MDI instances</th><td>1</td>"));
+}
+
+#[test]
+fn test_render_truncated_report() {
+    let mut summary = sample_summary();
+    summary.truncated = Some(Truncation {
+        max_runtime_ms: 28_800_000,
+        unprocessed: 1200,
+        checkpoint: "results.jsonl.remaining".into(),
+    });
+    let outcome = RunOutcome::Completed(summary);
+
+    assert_eq!(
+        report_subject(&outcome),
+        "[sentri] Batch truncated: 2 domains, 1 with MDI, 1200 unprocessed"
+    );
+    let line =
+        "Stopped after 28800000 ms; 1200 domains unprocessed, listed in results.jsonl.remaining";
+    assert!(render_text(&outcome).contains(line));
+    assert!(render_html(&outcome).contains(&format!("<th>Truncated</th><td>{}</td>", line)));
+}
+
+#[test]
+fn test_render_detector_report() {
+    let stats = DetectorStats::new();
+    stats.record(Detector::Federation, true, Duration::from_millis(150));
+    stats.record(Detector::Federation, false, Duration::from_millis(5000));
+    let mut summary = sample_summary();
+    summary.detectors = stats.summaries();
+    let outcome = RunOutcome::Completed(summary);
+
+    let line = "1 ok, 1 failed; p50 150 ms, p95 5000 ms, p99 5000 ms, max 5000 ms";
+    assert!(render_text(&outcome).contains(&format!("  federation  {}", line)));
+    assert!(
+        render_html(&outcome).contains(&format!("<th>Detector federation</th><td>{}</td>", line))
+    );
+}
+
+#[test]
+fn test_render_failed_report_escapes_error() {
+    let outcome = RunOutcome::Failed("<script>alert(1)</script>".to_string());
+
+    assert_eq!(report_subject(&outcome), "[sentri] Batch failed");
+    assert!(render_text(&outcome).contains("<script>"));
+
+    let html = render_html(&outcome);
+    assert!(!html.contains("<script>"));
+    assert!(html.contains("&lt;script&gt;"));
+}
+
+#[test]
+fn test_email_config_from_args() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--email-report",
+        "ops@example.com,soc@example.com",
+        "--smtp-host",
+        "smtp.example.com",
+        "--email-attach-html",
+    ])?;
+
+    let Commands::Batch { notify, .. } = &cli.command else {
+        panic!("Expected Batch command");
+    };
+
+    let config = EmailConfig::from_args(notify).expect("email should be configured");
+    assert_eq!(
+        config.recipients,
+        vec!["ops@example.com", "soc@example.com"]
+    );
+    assert_eq!(config.smtp_host, "smtp.example.com");
+    assert!(config.attach_html);
+    assert!(!config.implicit_tls);
+
+    Ok(())
+}
+
+#[test]
+fn test_email_report_requires_smtp_host() {
+    let result = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--email-report",
+        "ops@example.com",
+    ]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_reports_list_slow_hosts() {
+    let mut summary = sample_summary();
+    summary.slow_hosts.push(SlowHost {
+        host: "autodiscover-s.outlook.com".to_string(),
+        p95_ms: 2400,
+        threshold_ms: 1000,
+        samples: 50,
+        flagged_at: Utc::now(),
+        times_flagged: 2,
+    });
+    let outcome = RunOutcome::Completed(summary);
+
+    let line =
+        "autodiscover-s.outlook.com (p95 2400 ms over 50 requests, threshold 1000 ms, flagged 2x)";
+    let text = render_text(&outcome);
+    assert!(text.contains("Slow hosts:"));
+    assert!(text.contains(line));
+    assert!(render_html(&outcome).contains(&format!("<th>Slow host</th><td>{}</td>", line)));
+
+    // Summaries without slow hosts keep their JSON shape
+    let json = serde_json::to_value(sample_summary()).unwrap();
+    assert!(json.get("slow_hosts").is_none());
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use sentri::core::MdiChecker;
+use sentri::dns::DnsResolver;
+use sentri::dns_override::DnsOverrides;
+use sentri::http::HttpClient;
+use sentri::offline::{is_offline, set_offline, OfflineError};
+use sentri::stats::Detector;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Offline mode is process-wide, so every test in this binary runs offline
+
+#[tokio::test]
+async fn test_http_requests_fail_immediately() -> Result<()> {
+    set_offline(true);
+    assert!(is_offline());
+    let client = HttpClient::new(Duration::from_secs(30))?;
+
+    let started = Instant::now();
+    let error = client.get("https://example.com/data").await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<OfflineError>(),
+        Some(&OfflineError {
+            operation: "GET https://example.com/data".to_string()
+        })
+    );
+    let error = client.post_soap_request("<soap/>").await.unwrap_err();
+    assert!(error.downcast_ref::<OfflineError>().is_some());
+    assert!(format!("{:#}", error).contains("disabled by --offline"));
+    assert!(client.probe_autodiscover().await.is_err());
+    assert!(started.elapsed() < Duration::from_secs(1));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dns_queries_fail_but_overrides_answer() -> Result<()> {
+    set_offline(true);
+    let overrides = DnsOverrides::parse("192.0.2.10 lab.example.com")?;
+    let resolver = DnsResolver::new()?.with_overrides(Arc::new(overrides));
+
+    assert_eq!(resolver.resolve("lab.example.com").await?.len(), 1);
+    for error in [
+        resolver.resolve("example.com").await.unwrap_err(),
+        resolver.resolve_mx("example.com").await.unwrap_err(),
+        resolver.resolve_txt("example.com").await.unwrap_err(),
+    ] {
+        assert!(
+            error.downcast_ref::<OfflineError>().is_some(),
+            "{:#}",
+            error
+        );
+    }
+    assert!(resolver
+        .upstream_stats()
+        .iter()
+        .all(|stats| stats.queries == 0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_federation_counts_as_detector_failure() -> Result<()> {
+    set_offline(true);
+    let checker = MdiChecker::new(1, 1000)?;
+    assert!(checker.detector_stats().summaries().is_empty());
+
+    let result = checker.check_domain("contoso.com").await?;
+    let error = result.error.as_deref().unwrap_or_default();
+    assert!(
+        error.starts_with("Failed to send SOAP request: "),
+        "{error}"
+    );
+    assert!(error.contains("disabled by --offline"), "{error}");
+    let summaries = checker.detector_stats().summaries();
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].detector, Detector::Federation);
+    assert_eq!((summaries[0].successes, summaries[0].failures), (0, 1));
+    Ok(())
+}
@@ -0,0 +1,314 @@
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use sentri::core::{DomainResult, MdiGeneration};
+use sentri::output::fields::{FieldSelection, FieldsFormatter};
+use sentri::output::{formatter, CsvFormatter, Formatter, OutputFormat, TableFormatter};
+use sentri::sinks::{fields_sink, format_sink, FormattedSink, ResultSink};
+
+fn mdi_result() -> DomainResult {
+    DomainResult {
+        domain: "contoso.com".to_string(),
+        tenant: Some("contoso".to_string()),
+        federated_domains: vec!["contoso.com".to_string(), "contoso.de".to_string()],
+        mdi_instance: Some("contososensorapi.atp.azure.com".to_string()),
+        mdi_generation: Some(MdiGeneration::Legacy),
+        processing_time_ms: 123,
+        checked_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+        ..Default::default()
+    }
+}
+
+fn failed_result() -> DomainResult {
+    DomainResult {
+        domain: "broken.com".to_string(),
+        error: Some("Request failed: \"timeout\",\nretrying".to_string()),
+        checked_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_json_formats_round_trip() -> Result<()> {
+    let result = mdi_result();
+
+    let pretty = formatter(OutputFormat::Json).unwrap().format(&result)?;
+    assert!(pretty.contains('\n'));
+    let compact = formatter(OutputFormat::Jsonl).unwrap().format(&result)?;
+    assert!(!compact.contains('\n'));
+
+    for rendered in [pretty, compact] {
+        let parsed: DomainResult = serde_json::from_str(&rendered)?;
+        assert_eq!(parsed.domain, result.domain);
+        assert_eq!(parsed.mdi_instance, result.mdi_instance);
+    }
+    assert!(formatter(OutputFormat::Json).unwrap().header().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_csv_rows_are_quoted() -> Result<()> {
+    assert_eq!(
+        CsvFormatter.header().unwrap(),
+        "domain,tenant,mdi,mdi_instance,mdi_generation,federated_domains,processing_time_ms,error,error_class,checked_at,tags"
+    );
+    assert_eq!(
+        CsvFormatter.format(&mdi_result())?,
+        "contoso.com,contoso,true,contososensorapi.atp.azure.com,legacy,contoso.com;contoso.de,123,,,2024-05-01T12:00:00+00:00,"
+    );
+    assert_eq!(
+        CsvFormatter.format(&failed_result())?,
+        "broken.com,,false,,,,0,\"Request failed: \"\"timeout\"\", retrying\",,2024-05-01T12:00:00+00:00,"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_table_columns_line_up() -> Result<()> {
+    let header = TableFormatter.header().unwrap();
+    let row = TableFormatter.format(&mdi_result())?;
+    let failed = TableFormatter.format(&failed_result())?;
+
+    for column in [
+        "TENANT",
+        "MDI",
+        "INSTANCE",
+        "GENERATION",
+        "TIME",
+        "TAGS",
+        "ERROR",
+    ] {
+        let start = header.find(column).unwrap();
+        assert_ne!(row.as_bytes()[start], b' ', "{column} in {row:?}");
+        assert_eq!(row.as_bytes()[start - 1], b' ', "{column} in {row:?}");
+        assert_ne!(failed.as_bytes()[start], b' ', "{column} in {failed:?}");
+    }
+    assert!(row.starts_with("contoso.com "));
+    assert!(row.contains(" yes "));
+    assert!(row.ends_with(&format!(" 123ms   -{}-", " ".repeat(25))));
+    assert!(failed.contains(" no "));
+    assert!(failed.ends_with("Request failed: \"timeout\", retrying"));
+    Ok(())
+}
+
+#[test]
+fn test_table_header_and_rows_share_column_offsets() -> Result<()> {
+    let header = TableFormatter.header().unwrap();
+    let offset = |column: &str| header.find(column).unwrap();
+
+    let mut result = mdi_result();
+    result.tags.insert("bu".to_string(), "emea".to_string());
+    result.error = Some("partial answer".to_string());
+    let row = TableFormatter.format(&result)?;
+    for (column, cell) in [
+        ("DOMAIN", "contoso.com"),
+        ("TENANT", "contoso "),
+        ("MDI", "yes"),
+        ("INSTANCE", "contososensorapi.atp.azure.com"),
+        ("GENERATION", "legacy"),
+        ("TIME", "123ms"),
+        ("TAGS", "bu=emea"),
+        ("ERROR", "partial answer"),
+    ] {
+        assert_eq!(row.find(cell), Some(offset(column)), "{column} in {row:?}");
+    }
+
+    result.tenant = Some("a-very-long-tenant-name".to_string());
+    let row = TableFormatter.format(&result)?;
+    assert_eq!(row.find(" yes "), Some(offset("TENANT") + 23 + 1));
+    for (column, cell) in [("GENERATION", "legacy"), ("TAGS", "bu=emea")] {
+        assert_eq!(row.find(cell), Some(offset(column)), "{column} in {row:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_cef_events() -> Result<()> {
+    let cef = formatter(OutputFormat::Cef).unwrap();
+    assert!(cef.header().is_none());
+
+    let result = mdi_result();
+    assert_eq!(
+        cef.format(&result)?,
+        format!(
+            "CEF:0|sentri|sentri|{}|mdi-instance|MDI instance found|3|rt=1714564800000 end={} \
+             dhost=contoso.com cs1Label=tenant cs1=contoso cs2Label=mdiInstance \
+             cs2=contososensorapi.atp.azure.com cs3Label=mdiGeneration cs3=legacy \
+             cs4Label=federatedDomains cs4=contoso.com;contoso.de \
+             cn1Label=processingTimeMs cn1=123",
+            env!("CARGO_PKG_VERSION"),
+            result.completed_at.timestamp_millis()
+        )
+    );
+
+    // Separators in values cannot forge fields or events
+    let failed = DomainResult {
+        error: Some("bad=value|x\\y\r\nCEF:0|forged".to_string()),
+        ..failed_result()
+    };
+    let line = cef.format(&failed)?;
+    assert!(line.contains("|check-failed|Domain check failed|4|"));
+    assert!(line.ends_with(r"msg=bad\=value|x\\y\nCEF:0|forged"));
+    assert!(!line.contains(['\r', '\n']));
+    Ok(())
+}
+
+#[test]
+fn test_leef_events() -> Result<()> {
+    let leef = formatter(OutputFormat::Leef).unwrap();
+
+    let line = leef.format(&mdi_result())?;
+    let (header, attributes) = line.split_at(line.find("cat=").unwrap());
+    assert_eq!(
+        header,
+        format!(
+            "LEEF:1.0|sentri|sentri|{}|mdi-instance|",
+            env!("CARGO_PKG_VERSION")
+        )
+    );
+    let attributes: Vec<&str> = attributes.split('\t').collect();
+    assert_eq!(
+        attributes,
+        [
+            "cat=mdi-instance",
+            "sev=3",
+            "devTime=May 01 2024 12:00:00.000 UTC",
+            "devTimeFormat=MMM dd yyyy HH:mm:ss.SSS z",
+            "domain=contoso.com",
+            "tenant=contoso",
+            "mdiInstance=contososensorapi.atp.azure.com",
+            "mdiGeneration=legacy",
+            "federatedDomains=contoso.com;contoso.de",
+            "processingTimeMs=123",
+        ]
+    );
+
+    let line = leef.format(&failed_result())?;
+    assert!(line.contains("|check-failed|cat=check-failed\tsev=4\t"));
+    assert!(line.ends_with("\terror=Request failed: \"timeout\", retrying"));
+    Ok(())
+}
+
+#[test]
+fn test_line_formats_carry_tags() -> Result<()> {
+    let mut result = mdi_result();
+    result.tags.insert("bu".to_string(), "emea".to_string());
+    result
+        .tags
+        .insert("owner".to_string(), "Smith, Bob".to_string());
+
+    let csv = CsvFormatter.format(&result)?;
+    assert!(csv.ends_with(",\"bu=emea;owner=Smith, Bob\""), "{csv}");
+    let table = TableFormatter.format(&result)?;
+    assert!(
+        table.ends_with(" 123ms   bu=emea;owner=Smith, Bob  -"),
+        "{table}"
+    );
+    let cef = formatter(OutputFormat::Cef).unwrap().format(&result)?;
+    assert!(
+        cef.contains(" flexString1Label=tags flexString1=bu\\=emea;owner\\=Smith, Bob "),
+        "{cef}"
+    );
+    let leef = formatter(OutputFormat::Leef).unwrap().format(&result)?;
+    assert!(leef.contains("\ttags=bu=emea;owner=Smith, Bob\t"), "{leef}");
+
+    let json = formatter(OutputFormat::Jsonl).unwrap().format(&result)?;
+    let parsed: DomainResult = serde_json::from_str(&json)?;
+    assert_eq!(parsed.tags, result.tags);
+    Ok(())
+}
+
+#[test]
+fn test_document_formats_have_no_formatter() {
+    assert!(formatter(OutputFormat::Junit).is_none());
+    assert!(formatter(OutputFormat::Grepable).is_none());
+    assert!(formatter(OutputFormat::Markdown).is_none());
+    assert!(formatter(OutputFormat::Dot).is_none());
+    assert!(formatter(OutputFormat::Graphml).is_none());
+    for format in [
+        OutputFormat::Json,
+        OutputFormat::Jsonl,
+        OutputFormat::Csv,
+        OutputFormat::Table,
+    ] {
+        assert!(formatter(format).is_some(), "{format:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_formatted_sink_writes_header_and_rows() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_output_{}.csv", uuid::Uuid::new_v4()));
+
+    let mut sink = format_sink(OutputFormat::Csv, Some(&path), None).await?;
+    sink.write(&mdi_result()).await?;
+    sink.write(&failed_result()).await?;
+    sink.close().await?;
+
+    let content = std::fs::read_to_string(&path)?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("domain,"));
+    assert!(lines[1].starts_with("contoso.com,"));
+    assert!(lines[2].starts_with("broken.com,"));
+
+    // An empty run still gets the header
+    let mut sink = FormattedSink::create(Box::new(TableFormatter), Some(&path)).await?;
+    sink.close().await?;
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        format!("{}\n", TableFormatter.header().unwrap())
+    );
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+fn fields(names: &[&str]) -> FieldSelection {
+    let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    FieldSelection::new(&names).unwrap().unwrap()
+}
+
+#[test]
+fn test_field_selection() -> Result<()> {
+    let jsonl = FieldsFormatter::new(
+        OutputFormat::Jsonl,
+        fields(&["domain", "mdi_instance", "response_sha256"]),
+    )?;
+    assert_eq!(
+        jsonl.format(&mdi_result())?,
+        r#"{"domain":"contoso.com","mdi_instance":"contososensorapi.atp.azure.com","response_sha256":null}"#
+    );
+
+    let json = FieldsFormatter::new(OutputFormat::Json, fields(&["tenant"]))?;
+    assert_eq!(
+        json.format(&mdi_result())?,
+        "{\n  \"tenant\": \"contoso\"\n}"
+    );
+
+    assert!(FieldsFormatter::new(OutputFormat::Csv, fields(&["domain"])).is_err());
+    let twice = ["domain".to_string(), "domain".to_string()];
+    assert!(FieldSelection::new(&twice).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fields_sink_writes_selection() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_output_{}.jsonl", uuid::Uuid::new_v4()));
+
+    let mut sink = fields_sink(
+        OutputFormat::Jsonl,
+        Some(&path),
+        fields(&["domain", "error"]),
+    )
+    .await?;
+    sink.write(&mdi_result()).await?;
+    sink.write(&failed_result()).await?;
+    sink.close().await?;
+
+    let content = std::fs::read_to_string(&path)?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], r#"{"domain":"contoso.com","error":null}"#);
+    assert!(lines[1].starts_with(r#"{"domain":"broken.com","error":"Request failed"#));
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use chrono::Utc;
+use sentri::core::MdiChecker;
+use sentri::ownership::{OwnershipStore, TXT_PREFIX};
+use std::collections::HashSet;
+
+#[test]
+fn test_issue_generates_fresh_tokens() -> Result<()> {
+    let mut store = OwnershipStore::default();
+    let first = store.issue("contoso.com", Utc::now())?.clone();
+    assert_eq!(first.token.len(), 32);
+    assert!(first.token.chars().all(|c| c.is_ascii_hexdigit()));
+    assert!(first.txt_value().starts_with(TXT_PREFIX));
+    assert!(first.verified_at.is_none());
+
+    let second = store.issue("contoso.com", Utc::now())?.clone();
+    assert_ne!(first.token, second.token);
+    assert!(store.issue("not a domain", Utc::now()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_verification_requires_published_token() -> Result<()> {
+    let mut store = OwnershipStore::default();
+    let record = store.issue("contoso.com", Utc::now())?.clone();
+
+    let unrelated = vec!["v=spf1 include:spf.protection.outlook.com -all".to_string()];
+    assert!(!store.record_verification("contoso.com", &unrelated, Utc::now()));
+    assert!(!store.is_verified("contoso.com"));
+
+    // Records for domains without an issued token never verify
+    assert!(!store.record_verification("fabrikam.com", &[record.txt_value()], Utc::now()));
+
+    let published = vec![unrelated[0].clone(), record.txt_value()];
+    assert!(store.record_verification("Contoso.com", &published, Utc::now()));
+    assert!(store.is_verified("contoso.com"));
+    assert_eq!(
+        store.verified_domains(),
+        HashSet::from(["contoso.com".to_string()])
+    );
+
+    // Reissuing revokes the verification
+    store.issue("contoso.com", Utc::now())?;
+    assert!(!store.is_verified("contoso.com"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_store_round_trip() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_ownership_{}.json", uuid::Uuid::new_v4()));
+    assert!(OwnershipStore::load(&path).await?.domains.is_empty());
+
+    let mut store = OwnershipStore::default();
+    let record = store.issue("contoso.com", Utc::now())?.clone();
+    store.record_verification("contoso.com", &[record.txt_value()], Utc::now());
+    store.issue("fabrikam.com", Utc::now())?;
+    store.save(&path).await?;
+
+    let loaded = OwnershipStore::load(&path).await?;
+    assert_eq!(loaded.domains, store.domains);
+    assert!(loaded.is_verified("contoso.com"));
+    assert!(!loaded.is_verified("fabrikam.com"));
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_checker_gates_intrusive_detectors() -> Result<()> {
+    let open = MdiChecker::new(1, 1000)?;
+    assert!(open.may_probe("fabrikam.com"));
+
+    let gated = open.with_verified_domains(HashSet::from(["contoso.com".to_string()]));
+    assert!(gated.may_probe("contoso.com"));
+    assert!(gated.may_probe("CONTOSO.com"));
+    assert!(!gated.may_probe("fabrikam.com"));
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use anyhow::Result;
+#[cfg(feature = "parquet")]
+use sentri::core::DomainResult;
+use sentri::output::OutputFormat;
+use sentri::sinks::format_sink;
+
+#[cfg(feature = "parquet")]
+fn result(domain: &str) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        tenant: Some("contoso".to_string()),
+        federated_domains: vec![domain.to_string(), "contoso.onmicrosoft.com".to_string()],
+        ..Default::default()
+    }
+}
+
+#[cfg(feature = "parquet")]
+#[tokio::test]
+async fn test_parquet_file_round_trip() -> Result<()> {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Int64Type, TimestampMicrosecondType};
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let path = std::env::temp_dir().join(format!("sentri_{}.parquet", uuid::Uuid::new_v4()));
+    let mut tagged = result("contoso.com");
+    tagged.mdi_instance = Some("contosocorp.atp.azure.com".to_string());
+    tagged.processing_time_ms = 42;
+    tagged.tags.insert("bu".to_string(), "emea".to_string());
+    tagged
+        .tags
+        .insert("owner".to_string(), "secops".to_string());
+    let mut failed = result("contoso.de");
+    failed.tenant = None;
+    failed.federated_domains.clear();
+    failed.error = Some("connection refused".to_string());
+
+    let mut sink = format_sink(OutputFormat::Parquet, Some(&path), None).await?;
+    sink.write(&tagged).await?;
+    sink.write(&failed).await?;
+    sink.close().await?;
+
+    let builder = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path)?)?;
+    let created_by = builder
+        .metadata()
+        .file_metadata()
+        .created_by()
+        .unwrap_or_default();
+    assert!(created_by.starts_with("sentri version"), "{created_by}");
+    let batches = builder.build()?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), 2);
+
+    let column = |name: &str| batch.column_by_name(name).unwrap().clone();
+    let domain = column("domain");
+    let domain = domain.as_string::<i32>();
+    assert_eq!(domain.value(0), "contoso.com");
+    assert_eq!(domain.value(1), "contoso.de");
+    let tenant = column("tenant");
+    assert_eq!(tenant.as_string::<i32>().value(0), "contoso");
+    assert!(tenant.is_null(1));
+    let mdi = column("mdi");
+    assert!(mdi.as_boolean().value(0));
+    assert!(!mdi.as_boolean().value(1));
+    assert_eq!(
+        column("processing_time_ms")
+            .as_primitive::<Int64Type>()
+            .value(0),
+        42
+    );
+    assert_eq!(
+        column("error").as_string::<i32>().value(1),
+        "connection refused"
+    );
+    assert_eq!(
+        column("checked_at")
+            .as_primitive::<TimestampMicrosecondType>()
+            .value(0),
+        tagged.checked_at.timestamp_micros()
+    );
+
+    let federated_domains = column("federated_domains");
+    let federated_domains = federated_domains.as_list::<i32>();
+    let first = federated_domains.value(0);
+    let first = first.as_string::<i32>();
+    assert_eq!(
+        first.iter().flatten().collect::<Vec<_>>(),
+        ["contoso.com", "contoso.onmicrosoft.com"]
+    );
+    assert!(federated_domains.value(1).is_empty());
+
+    let tags = column("tags");
+    let tags = tags.as_map();
+    let keys = tags.value(0).column(0).clone();
+    let values = tags.value(0).column(1).clone();
+    assert_eq!(
+        keys.as_string::<i32>().iter().flatten().collect::<Vec<_>>(),
+        ["bu", "owner"]
+    );
+    assert_eq!(
+        values
+            .as_string::<i32>()
+            .iter()
+            .flatten()
+            .collect::<Vec<_>>(),
+        ["emea", "secops"]
+    );
+    assert!(tags.value(1).is_empty());
+
+    std::fs::remove_file(&path)?;
+    assert!(format_sink(OutputFormat::Parquet, None, None)
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_parquet_row_groups() -> Result<()> {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use sentri::output::parquet::{ParquetWriter, ROW_GROUP_ROWS};
+
+    let results: Vec<_> = (0..=ROW_GROUP_ROWS)
+        .map(|i| result(&format!("d{i}.example")))
+        .collect();
+    let path = std::env::temp_dir().join(format!("sentri_{}.parquet", uuid::Uuid::new_v4()));
+    let mut writer = ParquetWriter::new(std::fs::File::create(&path)?)?;
+    writer.write(&results)?;
+    assert_eq!(writer.rows(), ROW_GROUP_ROWS + 1);
+    writer.finish()?;
+
+    let reader = SerializedFileReader::new(std::fs::File::open(&path)?)?;
+    let metadata = reader.metadata();
+    assert_eq!(metadata.num_row_groups(), 2);
+    assert_eq!(metadata.row_group(0).num_rows(), ROW_GROUP_ROWS as i64);
+    assert_eq!(metadata.row_group(1).num_rows(), 1);
+    assert_eq!(
+        metadata.file_metadata().num_rows(),
+        ROW_GROUP_ROWS as i64 + 1
+    );
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet"))]
+#[tokio::test]
+async fn test_parquet_requires_feature() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_{}.parquet", uuid::Uuid::new_v4()));
+    let error = format_sink(OutputFormat::Parquet, Some(&path), None)
+        .await
+        .err()
+        .expect("parquet output without the feature");
+    assert!(error.to_string().contains("parquet feature"));
+    assert!(!path.exists());
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use anyhow::Result;
+use sentri::core::{DomainResult, MdiGeneration};
+use sentri::policy::rego::{deny_entry_to_violation, DEFAULT_DENY_RULE};
+use sentri::policy::{read_results, Check, Policy, RegoPolicy};
+
+fn covered(domain: &str, tenant: &str) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        tenant: Some(tenant.to_string()),
+        federated_domains: vec![domain.to_string()],
+        mdi_instance: Some(format!("{}sensorapi.atp.azure.com", tenant)),
+        mdi_generation: Some(MdiGeneration::Legacy),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_policy_parsing() -> Result<()> {
+    let policy = Policy::from_toml(
+        r#"
+        [[rule]]
+        name = "coverage"
+        check = "require_mdi"
+        domains = ["contoso.com", "*.contoso.net"]
+
+        [[rule]]
+        name = "single-tenant"
+        check = "allowed_tenants"
+        tenants = ["contoso"]
+
+        [[rule]]
+        name = "unified"
+        check = "require_generation"
+        generation = "unified"
+        "#,
+    )?;
+
+    assert_eq!(policy.rules.len(), 3);
+    assert_eq!(policy.rules[0].check, Check::RequireMdi);
+    assert_eq!(policy.rules[0].domains.len(), 2);
+    assert_eq!(
+        policy.rules[1].check,
+        Check::AllowedTenants {
+            tenants: vec!["contoso".to_string()]
+        }
+    );
+    assert_eq!(
+        policy.rules[2].check,
+        Check::RequireGeneration {
+            generation: MdiGeneration::Unified
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unknown_check_is_rejected() {
+    let result = Policy::from_toml(
+        r#"
+        [[rule]]
+        name = "bogus"
+        check = "require_magic"
+        "#,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_require_mdi_and_missing_domains() -> Result<()> {
+    let policy = Policy::from_toml(
+        r#"
+        [[rule]]
+        name = "coverage"
+        check = "require_mdi"
+        domains = ["contoso.com", "fabrikam.com", "missing.com"]
+        "#,
+    )?;
+
+    let results = vec![
+        covered("contoso.com", "contoso"),
+        DomainResult {
+            domain: "fabrikam.com".to_string(),
+            tenant: Some("fabrikam".to_string()),
+            ..Default::default()
+        },
+        DomainResult {
+            domain: "unscoped.com".to_string(),
+            ..Default::default()
+        },
+    ];
+
+    let violations = policy.evaluate(&results);
+    assert_eq!(violations.len(), 2);
+    assert_eq!(violations[0].domain, "fabrikam.com");
+    assert_eq!(violations[0].message, "No MDI instance detected");
+    assert_eq!(violations[1].domain, "missing.com");
+    assert_eq!(violations[1].message, "Domain missing from results");
+    Ok(())
+}
+
+#[test]
+fn test_federation_outside_tenant() -> Result<()> {
+    let policy = Policy::from_toml(
+        r#"
+        [[rule]]
+        name = "single-tenant"
+        check = "allowed_tenants"
+        tenants = ["Contoso"]
+
+        [[rule]]
+        name = "known-federation"
+        check = "allowed_federated_domains"
+        patterns = ["contoso.com", "*.contoso.com"]
+        "#,
+    )?;
+
+    let mut foreign = covered("eu.contoso.com", "fabrikam");
+    foreign.federated_domains.push("fabrikam.com".to_string());
+
+    let violations = policy.evaluate(&[covered("contoso.com", "contoso"), foreign]);
+    assert_eq!(violations.len(), 2);
+    assert_eq!(violations[0].rule, "single-tenant");
+    assert!(violations[0].message.contains("fabrikam"));
+    assert_eq!(violations[1].rule, "known-federation");
+    assert!(violations[1].message.contains("fabrikam.com"));
+    Ok(())
+}
+
+#[test]
+fn test_scan_errors_only_fail_no_scan_errors_rule() -> Result<()> {
+    let policy = Policy::from_toml(
+        r#"
+        [[rule]]
+        name = "coverage"
+        check = "require_mdi"
+
+        [[rule]]
+        name = "clean-scan"
+        check = "no_scan_errors"
+        "#,
+    )?;
+
+    let failed = DomainResult {
+        domain: "flaky.com".to_string(),
+        error: Some("timeout".to_string()),
+        ..Default::default()
+    };
+
+    let violations = policy.check_result(&failed);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].rule, "clean-scan");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_results_jsonl() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_policy_{}.jsonl", uuid::Uuid::new_v4()));
+    let content = format!(
+        "{}\n\n{}\n",
+        serde_json::to_string(&covered("contoso.com", "contoso"))?,
+        serde_json::to_string(&covered("fabrikam.com", "fabrikam"))?
+    );
+    std::fs::write(&path, content)?;
+
+    let results = read_results(&path).await?;
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1].domain, "fabrikam.com");
+
+    std::fs::write(&path, "not json\n")?;
+    let err = read_results(&path).await.unwrap_err();
+    assert!(err.to_string().contains("line 1"));
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_deny_entry_conversion() {
+    let plain = deny_entry_to_violation(&serde_json::json!("no MDI"), "deny", "contoso.com");
+    assert_eq!(plain.rule, "deny");
+    assert_eq!(plain.message, "no MDI");
+
+    let structured = deny_entry_to_violation(
+        &serde_json::json!({"rule": "coverage", "msg": "no MDI"}),
+        "deny",
+        "contoso.com",
+    );
+    assert_eq!(structured.rule, "coverage");
+    assert_eq!(structured.domain, "contoso.com");
+}
+
+#[cfg(not(feature = "rego"))]
+#[test]
+fn test_rego_without_feature_fails_clearly() {
+    let err = RegoPolicy::load(&[], DEFAULT_DENY_RULE)
+        .err()
+        .expect("loading must fail without the rego feature");
+    assert!(err.to_string().contains("rego feature"));
+}
+
+#[cfg(feature = "rego")]
+#[test]
+fn test_rego_policy_evaluation() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_policy_{}.rego", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        r#"
+package sentri
+
+deny contains msg if {
+    input.error == null
+    input.mdi_instance == null
+    msg := sprintf("%s has no MDI instance", [input.domain])
+}
+
+deny contains {"rule": "tenant", "msg": "unexpected tenant"} if {
+    input.tenant == "fabrikam"
+}
+"#,
+    )?;
+
+    let mut policy = RegoPolicy::load(std::slice::from_ref(&path), DEFAULT_DENY_RULE)?;
+    let violations = policy.evaluate(&[
+        covered("contoso.com", "contoso"),
+        DomainResult {
+            domain: "uncovered.com".to_string(),
+            ..Default::default()
+        },
+        covered("fabrikam.com", "fabrikam"),
+    ])?;
+
+    assert_eq!(violations.len(), 2);
+    assert_eq!(violations[0].rule, "deny");
+    assert_eq!(violations[0].message, "uncovered.com has no MDI instance");
+    assert_eq!(violations[1].rule, "tenant");
+    assert_eq!(violations[1].domain, "fabrikam.com");
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
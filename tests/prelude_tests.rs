@@ -0,0 +1,46 @@
+// Names every item of the prelude, so removing or renaming one fails here
+// before it breaks a library user
+use sentri::prelude::*;
+
+#[test]
+fn test_prelude_result_types() {
+    let result = DomainResult {
+        domain: "contoso.com".to_string(),
+        tenant: Some("contoso".to_string()),
+        mdi_instance: Some("contososensorapi.atp.azure.com".to_string()),
+        mdi_generation: Some(MdiGeneration::Legacy),
+        mdi_confidence: None::<MdiConfidence>,
+        tags: Tags::new(),
+        ..Default::default()
+    };
+    assert_eq!(result.schema_version, RESULT_SCHEMA_VERSION);
+    assert_eq!(ErrorClass::of(&result), None);
+
+    let mut summary = BatchSummary::new();
+    summary.record(&result);
+    summary.finish();
+    assert_eq!(summary.mdi_instances, 1);
+
+    let _: Option<FederationInfo> = None;
+    let _: Option<SoapFault> = None;
+    assert!(validate_domain("contoso.com").is_ok());
+    let _ = DomainValidator::new();
+    let _ = XmlParser::new();
+}
+
+#[cfg(all(feature = "http", feature = "dns", feature = "sanitize-html"))]
+#[test]
+fn test_prelude_scanner_types() -> anyhow::Result<()> {
+    use std::time::Duration;
+
+    let checker = MdiChecker::new(1, 1000)?
+        .with_http_client(HttpClient::new(Duration::from_secs(1))?)
+        .with_dns_resolver(DnsResolver::new()?)
+        .with_depth(Depth::default());
+    assert_eq!(checker.depth(), Depth::default());
+
+    let _: Option<Box<dyn ResultSink>> = None;
+    let _: Option<Box<dyn Formatter>> = None;
+    assert_eq!(OutputFormat::default(), OutputFormat::Jsonl);
+    Ok(())
+}
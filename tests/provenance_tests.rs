@@ -0,0 +1,71 @@
+use anyhow::Result;
+use sentri::provenance::{parse_expected_sha256, sha256_file, verify_binary};
+use sentri::server::AuditRecord;
+use std::path::PathBuf;
+
+/// SHA-256 of "sentri"
+const SENTRI_SHA256: &str = "563f77ba16279d08ca5e70eb14f470de6c72b0eeb697447dc53f84bc3bb9e934";
+
+/// SHA-256 of a different release
+const OTHER_SHA256: &str = "1a5a9e8b0f6fa4f0b5b9a1c8d3b1f5c0c5e5f5b3b8a9e2e0d6b5c8e4f3a2b1c0";
+
+fn binary_with(contents: &[u8]) -> Result<PathBuf> {
+    let path = std::env::temp_dir().join(format!("sentri_binary_{}", uuid::Uuid::new_v4()));
+    std::fs::write(&path, contents)?;
+    Ok(path)
+}
+
+#[test]
+fn test_parse_expected_sha256() {
+    let hex = "0123456789abcdef".repeat(4);
+    assert_eq!(parse_expected_sha256(&hex).unwrap(), hex);
+    assert_eq!(parse_expected_sha256(&hex.to_uppercase()).unwrap(), hex);
+    assert_eq!(
+        parse_expected_sha256(&format!(" sha256:{hex}\n")).unwrap(),
+        hex
+    );
+
+    assert!(parse_expected_sha256("").is_err());
+    assert!(parse_expected_sha256(&hex[..63]).is_err());
+    assert!(parse_expected_sha256(&format!("{}zz", &hex[..62])).is_err());
+}
+
+#[test]
+fn test_verify_binary() -> Result<()> {
+    let path = binary_with(b"sentri")?;
+    let sha256 = sha256_file(&path)?;
+    assert_eq!(sha256, SENTRI_SHA256);
+
+    let report = verify_binary(&path, &sha256.to_uppercase())?;
+    assert!(report.verified);
+    assert_eq!(report.binary, path);
+    assert_eq!(report.sha256, sha256);
+    assert_eq!(report.expected, sha256);
+    assert_eq!(report.version, env!("CARGO_PKG_VERSION"));
+
+    let report = verify_binary(&path, OTHER_SHA256)?;
+    assert!(!report.verified);
+    assert_eq!(report.sha256, sha256);
+
+    assert!(verify_binary(&path, "not-a-hash").is_err());
+    std::fs::remove_file(&path)?;
+    assert!(verify_binary(&path, &sha256).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_integrity_audit_record() -> Result<()> {
+    let path = binary_with(b"sentri")?;
+    let report = verify_binary(&path, OTHER_SHA256)?;
+    std::fs::remove_file(&path)?;
+
+    let record = AuditRecord::integrity(report.clone());
+    assert_eq!(record.method, "STARTUP");
+    assert_eq!(record.status, 412);
+    assert_eq!(record.timestamp, report.checked_at);
+
+    let line = serde_json::to_string(&record)?;
+    let parsed: AuditRecord = serde_json::from_str(&line)?;
+    assert_eq!(parsed.integrity, Some(report));
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use rand::Rng;
+use sentri::random::{rng, seed, seeded_rng, set_seed, RETRY_JITTER_STREAM};
+
+fn draw(mut rng: impl Rng) -> Vec<u64> {
+    (0..8).map(|_| rng.gen()).collect()
+}
+
+#[test]
+fn test_seeded_streams_are_reproducible() {
+    assert_eq!(
+        draw(seeded_rng(42, RETRY_JITTER_STREAM)),
+        draw(seeded_rng(42, RETRY_JITTER_STREAM))
+    );
+    assert_ne!(
+        draw(seeded_rng(42, RETRY_JITTER_STREAM)),
+        draw(seeded_rng(43, RETRY_JITTER_STREAM))
+    );
+    assert_ne!(
+        draw(seeded_rng(42, RETRY_JITTER_STREAM)),
+        draw(seeded_rng(42, "input.shuffle"))
+    );
+}
+
+#[test]
+fn test_fixed_seed_applies_to_every_stream() {
+    set_seed(42).unwrap();
+    assert_eq!(seed(), Some(42));
+    assert!(set_seed(42).is_ok());
+    assert!(set_seed(7).is_err());
+
+    assert_eq!(
+        draw(rng(RETRY_JITTER_STREAM)),
+        draw(seeded_rng(42, RETRY_JITTER_STREAM))
+    );
+}
@@ -133,6 +133,35 @@ async fn test_rate_limiter_update_config() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_rate_limiter_update_config_lowers_concurrency_in_use() -> Result<()> {
+    let limiter = RateLimiter::new(100, 60_000, 3);
+    let first = limiter.acquire().await?;
+    let second = limiter.acquire().await?;
+
+    // Two permits stay in use while the limit drops to one
+    limiter.update_config(100, 60_000, 1).await?;
+    assert_eq!(limiter.usage().await.permits_available, 0);
+
+    drop(first);
+    assert!(
+        timeout(Duration::from_millis(100), limiter.acquire())
+            .await
+            .is_err(),
+        "A returned permit must be withdrawn while above the new limit"
+    );
+
+    drop(second);
+    let third = timeout(Duration::from_millis(100), limiter.acquire()).await;
+    assert!(
+        third.is_ok(),
+        "Should acquire once usage is below the limit"
+    );
+    assert_eq!(limiter.usage().await.permits_available, 0);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_microsoft_api_limiter_config() {
     // Microsoft API limiter should be configured with appropriate values
@@ -216,3 +245,53 @@ async fn test_integration_with_dns_resolver() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_nested_limiter_respects_parent_budget() -> Result<()> {
+    let parent = Arc::new(RateLimiter::new(2, 1000, 5));
+    let team_a = RateLimiter::new(5, 1000, 5).with_parent(Arc::clone(&parent));
+    let team_b = RateLimiter::new(5, 1000, 5).with_parent(Arc::clone(&parent));
+
+    drop(team_a.acquire().await?);
+    drop(team_b.acquire().await?);
+
+    // Both children still have tokens, but the shared budget is spent
+    let start = Instant::now();
+    drop(team_a.acquire().await?);
+    assert!(
+        start.elapsed().as_millis() >= 800,
+        "Parent budget was not enforced: {:?}",
+        start.elapsed()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_try_take_does_not_wait() {
+    let limiter = RateLimiter::new(2, 60_000, 1);
+    assert!(limiter.try_take().await);
+    assert!(limiter.try_take().await);
+    assert!(!limiter.try_take().await);
+}
+
+#[tokio::test]
+async fn test_usage_reports_saturation() -> Result<()> {
+    let limiter = RateLimiter::new(2, 60_000, 1);
+    let usage = limiter.usage().await;
+    assert_eq!(usage.tokens_available, 2);
+    assert_eq!(usage.capacity, 2);
+    assert!(!usage.is_saturated());
+
+    // A running request holds the only slot
+    let guard = limiter.acquire().await?;
+    let usage = limiter.usage().await;
+    assert_eq!(usage.tokens_available, 1);
+    assert_eq!(usage.permits_available, 0);
+    assert!(usage.is_saturated());
+
+    drop(guard);
+    assert!(limiter.try_take().await);
+    assert!(limiter.usage().await.is_saturated());
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use clap::Parser;
+use sentri::cli::{Cli, Commands};
+use sentri::rate_limit::RateLimiter;
+use sentri::rate_report::{RateRecorder, RateReport};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_report_counts_requests_per_refill_period() -> Result<()> {
+    let recorder = Arc::new(RateRecorder::new());
+    let limiter = RateLimiter::new(3, 200, 2).with_recorder(recorder.clone());
+
+    // Seven requests drain the bucket twice, so they span three periods
+    for _ in 0..7 {
+        limiter.acquire().await?;
+    }
+    let held = (limiter.acquire().await?, limiter.acquire().await?);
+
+    let report = recorder.report().expect("started recorder");
+    assert_eq!(report.requests, 9);
+    assert_eq!((report.limit, report.max_concurrent), (3, 2));
+    assert_eq!(report.window_ms, 200);
+    assert_eq!(report.max_per_window, 3);
+    assert_eq!(report.p99_per_window, 3);
+    assert_eq!(report.peak_concurrent, 2);
+    assert!(report.bursts.is_empty());
+    assert!(report.compliant);
+    let counts: Vec<u64> = report.windows.iter().map(|w| w.requests).collect();
+    assert_eq!(counts[..3], [3, 3, 3]);
+    assert!(report.windows.windows(2).all(|w| w[0].start < w[1].start));
+    drop(held);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_write_report_as_json() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_rate_{}.json", uuid::Uuid::new_v4()));
+    let recorder = Arc::new(RateRecorder::new());
+    let limiter = RateLimiter::new(30, 60_000, 5).with_recorder(recorder.clone());
+    limiter.acquire().await?;
+
+    let written = recorder.write(&path).await?;
+    let read: RateReport = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    assert_eq!(read, written);
+    assert_eq!(read.windows.len(), 1);
+    assert_eq!(read.windows[0].requests, 1);
+    assert_eq!(read.mean_per_window, 1.0);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unattached_recorder_has_no_report() {
+    let recorder = RateRecorder::new();
+    assert!(recorder.report().is_none());
+    let path = std::env::temp_dir().join("sentri_rate_unattached.json");
+    assert!(recorder.write(&path).await.is_err());
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_parse_rate_report_flag() {
+    let cli = Cli::parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--rate-report",
+        "rate.json",
+    ]);
+    match cli.command {
+        Commands::Batch { rate_report, .. } => {
+            assert_eq!(rate_report, Some(PathBuf::from("rate.json")))
+        }
+        _ => panic!("expected batch command"),
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use sentri::config::ConfigFile;
+use sentri::rate_limit::RateLimiter;
+use sentri::reload::LiveConfig;
+use sentri::scan_window::{ScanWindow, ScanWindows};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_scan_window_parsing() {
+    let window: ScanWindow = "09:30-17:00".parse().unwrap();
+    assert_eq!(window.to_string(), "09:30-17:00");
+
+    assert!("9-17".parse::<ScanWindow>().is_err());
+    assert!("09:00".parse::<ScanWindow>().is_err());
+    assert!("25:00-06:00".parse::<ScanWindow>().is_err());
+    assert!("06:00-06:00".parse::<ScanWindow>().is_err());
+}
+
+#[test]
+fn test_scan_windows_wrap_around_midnight() {
+    let windows = ScanWindows {
+        allowed: vec![
+            "22:00-06:00".parse().unwrap(),
+            "12:00-13:00".parse().unwrap(),
+        ],
+    };
+    let at = |hour, minute| Utc.with_ymd_and_hms(2024, 5, 1, hour, minute, 0).unwrap();
+
+    assert!(windows.is_open(at(23, 0)));
+    assert!(windows.is_open(at(2, 0)));
+    assert!(windows.is_open(at(12, 30)));
+    assert!(!windows.is_open(at(13, 0)));
+    assert_eq!(
+        windows.until_open(at(7, 0)),
+        Some(Duration::from_secs(5 * 60 * 60))
+    );
+    assert_eq!(
+        windows.until_open(at(14, 0)),
+        Some(Duration::from_secs(8 * 60 * 60))
+    );
+}
+
+#[test]
+fn test_config_file_sections() {
+    let config = ConfigFile::from_toml(
+        "[rate_limit]\nrequests_per_minute = 30\n\n[windows]\nallowed = [\"22:00-06:00\"]\n",
+    )
+    .unwrap();
+    assert_eq!(config.rate_limit.unwrap().requests_per_minute, Some(30));
+    assert_eq!(config.windows.allowed.len(), 1);
+
+    assert!(ConfigFile::from_toml("[windows]\nallowed = [\"late\"]").is_err());
+    assert!(ConfigFile::from_toml("[rate_limit]\nburst = 5").is_err());
+}
+
+#[tokio::test]
+async fn test_reload_applies_rate_limit() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_{}.toml", uuid::Uuid::new_v4()));
+    tokio::fs::write(&path, "[rate_limit]\nrequests_per_minute = 30\n").await?;
+
+    let limiter = Arc::new(RateLimiter::new(100, 60_000, 10));
+    let live = LiveConfig::new(
+        Some(&path),
+        ConfigFile::load(&path).await?,
+        Arc::clone(&limiter),
+        100,
+        10,
+    );
+    live.apply().await?;
+    assert_eq!(limiter.usage().await.capacity, 30);
+    assert!(!live.reload().await?, "Unchanged file is not a change");
+
+    tokio::fs::write(&path, "[rate_limit]\nmax_concurrent = 4\n").await?;
+    assert!(live.reload().await?);
+    let usage = limiter.usage().await;
+    assert_eq!(
+        usage.capacity, 100,
+        "Omitted keys fall back to the CLI values"
+    );
+    assert_eq!(usage.permits_available, 4);
+
+    // An invalid file keeps the previous configuration
+    tokio::fs::write(&path, "[rate_limit]\nmax_concurrent = 0\n").await?;
+    assert!(live.reload().await.is_err());
+    assert_eq!(
+        live.current().rate_limit.as_ref().unwrap().max_concurrent,
+        Some(4)
+    );
+    assert_eq!(limiter.usage().await.permits_available, 4);
+
+    tokio::fs::remove_file(&path).await?;
+    Ok(())
+}
@@ -0,0 +1,320 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
+use sentri::cli::{Cli, Commands};
+use sentri::core::{DomainResult, MdiChecker};
+use sentri::error_class::ErrorClass;
+use sentri::offline::OfflineError;
+use sentri::policy::read_results;
+use sentri::rescan::{carried_over, domains_to_rescan, domains_to_retry, RescanFilter};
+use sentri::sinks::{JsonlFileSink, ResultSink};
+use std::sync::{Arc, Mutex};
+
+fn result(domain: &str, mdi: bool, error: bool) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        tenant: Some("contoso".to_string()),
+        mdi_instance: mdi.then(|| "contososensorapi.atp.azure.com".to_string()),
+        error: error.then(|| "Request timed out".to_string()),
+        ..Default::default()
+    }
+}
+
+fn previous_run() -> Vec<DomainResult> {
+    vec![
+        result("contoso.com", true, false),
+        result("fabrikam.com", false, false),
+        result("timeout.example", false, true),
+        result("TIMEOUT.example", false, true),
+        result("northwind.com", false, true),
+    ]
+}
+
+#[test]
+fn test_filters_select_domains_once_in_order() {
+    let results = previous_run();
+    assert_eq!(
+        domains_to_rescan(&results, RescanFilter::All),
+        [
+            "contoso.com",
+            "fabrikam.com",
+            "timeout.example",
+            "northwind.com"
+        ]
+    );
+    assert_eq!(
+        domains_to_rescan(&results, RescanFilter::Errors),
+        ["timeout.example", "northwind.com"]
+    );
+    assert_eq!(
+        domains_to_rescan(&results, RescanFilter::NoMdi),
+        ["fabrikam.com"]
+    );
+    assert_eq!(
+        domains_to_rescan(&results, RescanFilter::Mdi),
+        ["contoso.com"]
+    );
+}
+
+#[test]
+fn test_cli_takes_domains_from_results() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--from-results",
+        "old.jsonl",
+        "--only",
+        "errors",
+    ])?;
+    match cli.command {
+        Commands::Batch {
+            input_file,
+            from_results,
+            only,
+            ..
+        } => {
+            assert_eq!(input_file, None);
+            assert_eq!(from_results.as_deref(), Some("old.jsonl".as_ref()));
+            assert_eq!(only, Some(RescanFilter::Errors));
+        }
+        _ => panic!("Expected Batch command"),
+    }
+
+    // One source of domains is required, and only one
+    assert!(Cli::try_parse_from(["sentri", "batch"]).is_err());
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--from-results",
+        "old.jsonl",
+    ])
+    .is_err());
+    assert!(
+        Cli::try_parse_from(["sentri", "batch", "-i", "domains.txt", "--only", "errors"]).is_err()
+    );
+    Ok(())
+}
+
+struct CollectingSink(Arc<Mutex<Vec<String>>>);
+
+#[async_trait]
+impl ResultSink for CollectingSink {
+    fn name(&self) -> &str {
+        "collect"
+    }
+
+    async fn write(&mut self, result: &DomainResult) -> Result<()> {
+        self.0.lock().unwrap().push(result.domain.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_process_domains_streams_every_domain() -> Result<()> {
+    let checker = MdiChecker::new(2, 1000)?;
+    let written = Arc::new(Mutex::new(Vec::new()));
+    let mut sinks: Vec<Box<dyn ResultSink>> = vec![Box::new(CollectingSink(written.clone()))];
+
+    // Invalid domains fail validation without any network access
+    let domains = vec!["-bad-.example".to_string(), "no_tld".to_string()];
+    let summary = checker
+        .process_domains_with_sinks(domains, &mut sinks, 1, 600)
+        .await?;
+
+    assert_eq!(summary.domains_processed, 2);
+    assert_eq!(summary.errors, 2);
+    assert_eq!(written.lock().unwrap().len(), 2);
+    let mut written = written.lock().unwrap().clone();
+    written.sort();
+    assert_eq!(written, ["-bad-.example", "no_tld"]);
+    Ok(())
+}
+
+fn failed(domain: &str, error: &str, class: Option<ErrorClass>) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        error: Some(error.to_string()),
+        error_class: class,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_classify_uses_error_types_then_messages() {
+    let offline = anyhow::Error::new(OfflineError {
+        operation: "GET https://example.com".to_string(),
+    })
+    .context("Failed to send SOAP request");
+    assert_eq!(ErrorClass::classify(&offline), ErrorClass::Offline);
+
+    let status = anyhow::anyhow!("HTTP request failed with status: 429 Too Many Requests")
+        .context("Failed to send SOAP request");
+    assert_eq!(ErrorClass::classify(&status), ErrorClass::RateLimited);
+
+    for (message, class) in [
+        ("operation timed out", ErrorClass::Timeout),
+        (
+            "DNS resolution failed for a.example: NXDOMAIN",
+            ErrorClass::Dns,
+        ),
+        (
+            "HTTP request failed with status: 503 Service Unavailable",
+            ErrorClass::HttpStatus,
+        ),
+        ("Missing SOAP envelope", ErrorClass::InvalidResponse),
+        (
+            "SOAP fault Server: Server busy (ErrorServerBusy)",
+            ErrorClass::RateLimited,
+        ),
+        (
+            "SOAP fault Client: Invalid domain",
+            ErrorClass::InvalidDomain,
+        ),
+        (
+            "SOAP fault Server: Le serveur est occupé",
+            ErrorClass::RateLimited,
+        ),
+        (
+            "SOAP fault Client: Dominio no válido",
+            ErrorClass::InvalidDomain,
+        ),
+        (
+            "SOAP fault Server: Internal error (ErrorInternalServerError)",
+            ErrorClass::SoapFault,
+        ),
+        (
+            "Suspicious domain detected: rate-limit.example",
+            ErrorClass::InvalidDomain,
+        ),
+        ("something else", ErrorClass::Other),
+    ] {
+        assert_eq!(ErrorClass::from_message(message), class, "{}", message);
+    }
+}
+
+#[test]
+fn test_error_class_round_trips_and_is_omitted_on_success() -> Result<()> {
+    let result = failed(
+        "contoso.com",
+        "Failed to send SOAP request",
+        Some(ErrorClass::Timeout),
+    );
+    let json = serde_json::to_value(&result)?;
+    assert_eq!(json["error_class"], "timeout");
+    let round_trip: DomainResult = serde_json::from_value(json)?;
+    assert_eq!(round_trip.error_class, Some(ErrorClass::Timeout));
+
+    let json = serde_json::to_value(DomainResult::default())?;
+    assert!(json.get("error_class").is_none());
+    Ok(())
+}
+
+#[test]
+fn test_retry_selects_classes_and_carries_over_the_rest() {
+    let results = vec![
+        result("contoso.com", true, false),
+        // Recorded class wins over the message
+        failed(
+            "slow.example",
+            "Failed to send SOAP request",
+            Some(ErrorClass::Timeout),
+        ),
+        // Older records without a class are classified by message
+        failed("busy.example", "Rate limiting error: closed", None),
+        failed("bad_domain", "Invalid domain format: bad_domain", None),
+        failed(
+            "gone.example",
+            "DNS resolution failed",
+            Some(ErrorClass::Dns),
+        ),
+    ];
+
+    let domains = domains_to_retry(&results, &[ErrorClass::Timeout, ErrorClass::RateLimited]);
+    assert_eq!(domains, ["slow.example", "busy.example"]);
+
+    let kept: Vec<String> = carried_over(results, &domains)
+        .into_iter()
+        .map(|result| result.domain)
+        .collect();
+    assert_eq!(kept, ["contoso.com", "bad_domain", "gone.example"]);
+}
+
+#[test]
+fn test_cli_parses_retry_classes() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--from-results",
+        "old.jsonl",
+        "--retry-classes",
+        "timeout,rate_limited",
+    ])?;
+    match cli.command {
+        Commands::Batch { retry_classes, .. } => {
+            assert_eq!(
+                retry_classes,
+                [ErrorClass::Timeout, ErrorClass::RateLimited]
+            );
+        }
+        _ => panic!("Expected Batch command"),
+    }
+
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--from-results",
+        "old.jsonl",
+        "--retry-classes",
+        "timeout",
+        "--only",
+        "errors",
+    ])
+    .is_err());
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--from-results",
+        "old.jsonl",
+        "--retry-classes",
+        "flaky",
+    ])
+    .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_carried_over_results_are_not_escaped_again() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_retry_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let input = dir.join("old.jsonl");
+    let output = dir.join("new.jsonl");
+
+    let mut kept = result("att.com", true, false);
+    kept.tenant = Some("AT&amp;T &lt;&quot;us&quot;&gt;".to_string());
+    let retried = failed(
+        "slow.example",
+        "Request timed out",
+        Some(ErrorClass::Timeout),
+    );
+    let lines: Vec<String> = [&kept, &retried]
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<serde_json::Result<_>>()?;
+    std::fs::write(&input, lines.join("\n"))?;
+
+    let results = read_results(&input).await?;
+    let domains = domains_to_retry(&results, &[ErrorClass::Timeout]);
+    assert_eq!(domains, ["slow.example"]);
+    let carried = carried_over(results, &domains);
+    let mut sinks: Vec<Box<dyn ResultSink>> = vec![Box::new(JsonlFileSink::create(&output).await?)];
+    MdiChecker::write_sanitized_results(&carried, &mut sinks).await?;
+    sinks[0].close().await?;
+
+    let merged = read_results(&output).await?;
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].tenant, kept.tenant);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
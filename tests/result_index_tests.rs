@@ -0,0 +1,126 @@
+use anyhow::Result;
+use sentri::core::DomainResult;
+use sentri::result_index::{find_results, index_path, read_result_at, ResultIndex};
+use sentri::sinks::{JsonlFileSink, ResultSink};
+use std::path::{Path, PathBuf};
+
+fn results_path() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_results_{}.jsonl", uuid::Uuid::new_v4()))
+}
+
+async fn write_results(path: &Path, domains: &[&str], index: bool) -> Result<()> {
+    let mut sink = JsonlFileSink::create(path).await?;
+    if index {
+        sink = sink.with_index().await?;
+    }
+    for domain in domains {
+        sink.write(&DomainResult {
+            domain: domain.to_string(),
+            tenant: Some(domain.split('.').next().unwrap().to_string()),
+            ..Default::default()
+        })
+        .await?;
+    }
+    sink.close().await
+}
+
+fn cleanup(path: &Path) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(index_path(path));
+}
+
+#[tokio::test]
+async fn test_index_points_at_every_line() -> Result<()> {
+    let path = results_path();
+    write_results(
+        &path,
+        &["contoso.com", "Fabrikam.com", "tailspin.example"],
+        true,
+    )
+    .await?;
+
+    let content = std::fs::read_to_string(&path)?;
+    let index = ResultIndex::load(&path).await?.expect("index written");
+    assert_eq!(index.len(), 3);
+
+    for line in content.lines() {
+        let result: DomainResult = serde_json::from_str(line)?;
+        let entry = index.get(&result.domain).unwrap();
+        let start = entry.offset as usize;
+        assert_eq!(&content[start..start + entry.length as usize], line);
+        assert_eq!(read_result_at(&path, entry).await?.domain, result.domain);
+    }
+    assert!(index.get("FABRIKAM.COM").is_some());
+    assert!(index.get("unknown.example").is_none());
+
+    cleanup(&path);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_find_results_with_and_without_index() -> Result<()> {
+    let domains = ["contoso.com", "fabrikam.com"];
+    let wanted = vec![
+        "fabrikam.com".to_string(),
+        "missing.example".to_string(),
+        "CONTOSO.COM".to_string(),
+    ];
+
+    for index in [true, false] {
+        let path = results_path();
+        write_results(&path, &domains, index).await?;
+        assert_eq!(ResultIndex::load(&path).await?.is_some(), index);
+
+        let found = find_results(&path, &wanted).await?;
+        let summary: Vec<(&str, Option<&str>)> = found
+            .iter()
+            .map(|(domain, result)| {
+                (
+                    domain.as_str(),
+                    result.as_ref().and_then(|r| r.tenant.as_deref()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("fabrikam.com", Some("fabrikam")),
+                ("missing.example", None),
+                ("CONTOSO.COM", Some("contoso")),
+            ]
+        );
+        cleanup(&path);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stale_index_is_reported() -> Result<()> {
+    let path = results_path();
+    write_results(&path, &["contoso.com", "fabrikam.com"], true).await?;
+    let index = ResultIndex::load(&path).await?.unwrap();
+
+    // Rewrite the results without the index
+    write_results(&path, &["fabrikam.com", "contoso.com"], false).await?;
+    let error = find_results(&path, &["contoso.com".to_string()])
+        .await
+        .unwrap_err();
+    assert!(
+        format!("{:#}", error).contains("Stale index"),
+        "{:#}",
+        error
+    );
+
+    std::fs::write(&path, "")?;
+    let error = read_result_at(&path, index.get("fabrikam.com").unwrap())
+        .await
+        .unwrap_err();
+    assert!(
+        format!("{:#}", error).contains("Stale index"),
+        "{:#}",
+        error
+    );
+
+    cleanup(&path);
+    Ok(())
+}
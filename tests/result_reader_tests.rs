@@ -0,0 +1,131 @@
+use anyhow::Result;
+use sentri::core::DomainResult;
+use sentri::result_reader::{
+    parse_results, read_results_mapped, stream_results, MappedFile, CHUNK_SIZE,
+};
+use std::path::PathBuf;
+
+fn temp_path() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_reader_{}.jsonl", uuid::Uuid::new_v4()))
+}
+
+/// JSONL spanning several parser chunks
+fn large_results(count: usize) -> Result<String> {
+    let mut content = String::new();
+    for i in 0..count {
+        let result = DomainResult {
+            domain: format!("domain{i}.example"),
+            tenant: Some(format!("tenant{i}")),
+            federated_domains: vec![format!("domain{i}.example"); 4],
+            ..Default::default()
+        };
+        content.push_str(&serde_json::to_string(&result)?);
+        content.push('\n');
+    }
+    assert!(content.len() > 2 * CHUNK_SIZE);
+    Ok(content)
+}
+
+#[test]
+fn test_parallel_parse_keeps_file_order() -> Result<()> {
+    let content = large_results(20_000)?;
+    let path = temp_path();
+    std::fs::write(&path, &content)?;
+
+    let results = read_results_mapped(&path)?.collect::<Result<Vec<_>>>()?;
+    assert_eq!(results.len(), 20_000);
+    for (i, result) in results.iter().enumerate() {
+        assert_eq!(result.domain, format!("domain{i}.example"));
+    }
+
+    let mapped = MappedFile::open(&path)?;
+    assert_eq!(&mapped[..], content.as_bytes());
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_errors_report_the_line_of_the_whole_file() -> Result<()> {
+    let mut content = large_results(20_000)?;
+    content.push_str("\nnot json\n");
+
+    let mut results = parse_results(content.as_bytes());
+    for i in 0..20_000 {
+        assert_eq!(
+            results.next().unwrap()?.domain,
+            format!("domain{i}.example")
+        );
+    }
+    let error = results.next().unwrap().unwrap_err();
+    assert_eq!(error.to_string(), "Invalid result on line 20002");
+    assert!(results.next().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_blank_lines_and_empty_files() -> Result<()> {
+    let line = |domain: &str| {
+        let result = DomainResult {
+            domain: domain.to_string(),
+            ..Default::default()
+        };
+        serde_json::to_string(&result).unwrap()
+    };
+    let content = format!("\n{}\r\n   \n{}", line("contoso.com"), line("fabrikam.com"));
+    let results = parse_results(content.as_bytes()).collect::<Result<Vec<_>>>()?;
+    let domains: Vec<&str> = results.iter().map(|r| r.domain.as_str()).collect();
+    assert_eq!(domains, ["contoso.com", "fabrikam.com"]);
+
+    let path = temp_path();
+    std::fs::write(&path, "")?;
+    assert!(read_results_mapped(&path)?.next().is_none());
+    std::fs::remove_file(&path)?;
+
+    let error = read_results_mapped(&path).err().unwrap();
+    assert!(error.to_string().starts_with("Failed to open results file"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stream_results() -> Result<()> {
+    let content = large_results(20_000)?;
+    let path = temp_path();
+    std::fs::write(&path, &content)?;
+
+    let mut stream = stream_results(&path);
+    let mut count = 0;
+    while let Some(result) = stream.next().await {
+        assert_eq!(result?.domain, format!("domain{count}.example"));
+        count += 1;
+    }
+    assert_eq!(count, 20_000);
+
+    std::fs::remove_file(&path)?;
+    let mut stream = stream_results(&path);
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(error.to_string().starts_with("Failed to open results file"));
+    assert!(stream.next().await.is_none());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_mapped_files_are_not_truncated() -> Result<()> {
+    use sentri::result_reader::truncate_results_file;
+
+    let path = temp_path();
+    std::fs::write(&path, "{}\n")?;
+    let writer = std::fs::OpenOptions::new().write(true).open(&path)?;
+
+    let mapped = MappedFile::open(&path)?;
+    let error = truncate_results_file(&writer, &path).unwrap_err();
+    assert!(error.to_string().contains("is being read"), "{error}");
+    assert_eq!(&mapped[..], b"{}\n");
+
+    drop(mapped);
+    truncate_results_file(&writer, &path)?;
+    assert_eq!(std::fs::metadata(&path)?.len(), 0);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
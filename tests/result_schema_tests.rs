@@ -0,0 +1,92 @@
+use clap::ValueEnum;
+use sentri::attribution::{EndpointAnomaly, EndpointKind};
+use sentri::core::{DomainResult, MdiGeneration, RESULT_SCHEMA_VERSION};
+use sentri::engagement::Engagement;
+use sentri::error_class::ErrorClass;
+use sentri::result_schema::domain_result_schema;
+use sentri::xml_schema::{SchemaViolation, SchemaWarning};
+use serde_json::Value;
+
+fn full_result() -> DomainResult {
+    DomainResult {
+        domain: "contoso.com".to_string(),
+        tenant: Some("contoso".to_string()),
+        federated_domains: vec!["contoso.com".to_string()],
+        mdi_instance: Some("contososensorapi.atp.azure.com".to_string()),
+        mdi_generation: Some(MdiGeneration::Legacy),
+        endpoint_anomalies: vec![EndpointAnomaly {
+            domain: "contoso.com".to_string(),
+            kind: EndpointKind::Mx,
+            host: "mx.contoso.com".to_string(),
+            addresses: vec!["192.0.2.1".parse().unwrap()],
+        }],
+        engagement: Some(Engagement {
+            engagement_id: Some("ENG-1".to_string()),
+            operator: Some("alice".to_string()),
+        }),
+        response_sha256: Some("ab".repeat(32)),
+        schema_warnings: vec![SchemaWarning {
+            violation: SchemaViolation::Malformed,
+            path: "/".to_string(),
+            message: "unexpected end".to_string(),
+        }],
+        error: Some("timeout".to_string()),
+        error_class: Some(ErrorClass::Timeout),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_schema_covers_every_field() {
+    let schema = domain_result_schema();
+    let properties = schema["properties"].as_object().unwrap();
+    let result = serde_json::to_value(full_result()).unwrap();
+    let minimal = serde_json::to_value(DomainResult::default()).unwrap();
+
+    for field in result.as_object().unwrap().keys() {
+        assert!(
+            properties.contains_key(field),
+            "{} missing from schema",
+            field
+        );
+    }
+    for field in schema["required"].as_array().unwrap() {
+        let field = field.as_str().unwrap();
+        assert!(
+            properties.contains_key(field),
+            "{} has no definition",
+            field
+        );
+        assert!(
+            minimal.get(field).is_some(),
+            "{} is not always written",
+            field
+        );
+    }
+}
+
+#[test]
+fn test_schema_lists_every_error_class() {
+    let schema = domain_result_schema();
+    let allowed = schema["properties"]["error_class"]["enum"]
+        .as_array()
+        .unwrap();
+    for class in ErrorClass::value_variants() {
+        let value = serde_json::to_value(class).unwrap();
+        assert!(allowed.contains(&value), "{} missing from schema", value);
+    }
+}
+
+#[test]
+fn test_results_carry_schema_version() {
+    let result = serde_json::to_value(DomainResult::default()).unwrap();
+    assert_eq!(result["schema_version"], Value::from(RESULT_SCHEMA_VERSION));
+
+    // Results written before versioning are the first version
+    let old: DomainResult = serde_json::from_str(
+        r#"{"domain":"contoso.com","tenant":null,"federated_domains":[],"mdi_instance":null,
+            "mdi_generation":null,"processing_time_ms":5,"error":null}"#,
+    )
+    .unwrap();
+    assert_eq!(old.schema_version, 1);
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sentri::retention::{cutoff, parse_age, purge, purge_results, RetentionPolicy};
+use sentri::server::{AuditLog, AuditRecord, AUDIT_LOG_FILE};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const DAY: Duration = Duration::from_secs(86_400);
+
+fn state_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_state_{}", uuid::Uuid::new_v4()))
+}
+
+/// Writes a file whose last modification lies `age` in the past
+fn write_aged(path: &Path, age: Duration) -> Result<()> {
+    std::fs::create_dir_all(path.parent().expect("parent"))?;
+    std::fs::write(path, "{}\n")?;
+    std::fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(SystemTime::now() - age)?;
+    Ok(())
+}
+
+fn audit_record(timestamp: DateTime<Utc>, path: &str) -> AuditRecord {
+    AuditRecord {
+        timestamp,
+        key: None,
+        remote_addr: None,
+        method: "GET".to_string(),
+        path: path.to_string(),
+        status: 200,
+        engagement: None,
+        integrity: None,
+        change: None,
+    }
+}
+
+#[test]
+fn test_parse_age() {
+    assert_eq!(parse_age("90d").unwrap(), 90 * DAY);
+    assert_eq!(parse_age("2w").unwrap(), 14 * DAY);
+    assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+    assert_eq!(parse_age("30m").unwrap(), Duration::from_secs(1800));
+    assert_eq!(parse_age("45s").unwrap(), Duration::from_secs(45));
+    for invalid in ["", "d", "90", "90y", "-1d", "1.5d"] {
+        assert!(parse_age(invalid).is_err(), "{:?} accepted", invalid);
+    }
+}
+
+#[tokio::test]
+async fn test_purge_results() -> Result<()> {
+    let dir = state_dir();
+    let old_job = dir.join("jobs").join("old.jsonl");
+    let new_job = dir.join("jobs").join("new.jsonl");
+    let old_run = dir
+        .join("results")
+        .join("nightly")
+        .join("20240101T020000Z.jsonl");
+    let other = dir.join("jobs").join("notes.txt");
+    write_aged(&old_job, 100 * DAY)?;
+    write_aged(&new_job, DAY)?;
+    write_aged(&old_run, 100 * DAY)?;
+    write_aged(&other, 100 * DAY)?;
+
+    let cutoff = cutoff(Utc::now(), 90 * DAY);
+    let report = purge_results(&dir, cutoff, true).await?;
+    assert_eq!(report.result_files, 2);
+    assert_eq!(report.result_bytes, 6);
+    assert!(old_job.exists() && old_run.exists());
+
+    let report = purge_results(&dir, cutoff, false).await?;
+    assert_eq!(report.result_files, 2);
+    assert!(!old_job.exists() && !old_run.exists());
+    assert!(new_job.exists() && other.exists());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_purge_audit_records() -> Result<()> {
+    let dir = state_dir();
+    std::fs::create_dir_all(&dir)?;
+    let now = Utc::now();
+    let log = AuditLog::open(&dir.join(AUDIT_LOG_FILE), None).await?;
+    log.record(&audit_record(now - chrono::Duration::days(400), "/old"))
+        .await?;
+    log.record(&audit_record(now, "/recent")).await?;
+
+    let policy = RetentionPolicy {
+        results: None,
+        audit: Some(365 * DAY),
+    };
+    let report = purge(&dir, &policy, now, true, None).await?;
+    assert_eq!(report.audit_records, 1);
+    assert_eq!(report.result_files, 0);
+
+    // A running server purges through its open log and keeps appending
+    assert_eq!(log.purge(cutoff(now, 365 * DAY)).await?, 1);
+    log.record(&audit_record(now, "/after")).await?;
+    let content = std::fs::read_to_string(dir.join(AUDIT_LOG_FILE))?;
+    let paths: Vec<String> = content
+        .lines()
+        .map(|line| Ok(serde_json::from_str::<AuditRecord>(line)?.path))
+        .collect::<Result<_>>()?;
+    assert_eq!(paths, ["/recent", "/after"]);
+    assert_eq!(
+        purge(&dir, &policy, now, false, None).await?.audit_records,
+        0
+    );
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use anyhow::Result;
+use sentri::config::ConfigFile;
+use sentri::core::{DomainResult, MdiGeneration};
+use sentri::sinks::routing::Route;
+use sentri::sinks::{ResultSink, RoutingSink};
+use std::time::Duration;
+
+fn result(domain: &str, bu: &str, mdi: bool) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        mdi_instance: mdi.then(|| format!("{}sensorapi.security.microsoft.com", domain)),
+        mdi_generation: mdi.then_some(MdiGeneration::Unified),
+        tags: [("bu".to_string(), bu.to_string())].into(),
+        ..Default::default()
+    }
+}
+
+fn domains(content: &str) -> Result<Vec<String>> {
+    content
+        .lines()
+        .map(|line| Ok(serde_json::from_str::<DomainResult>(line)?.domain))
+        .collect()
+}
+
+#[test]
+fn test_routes_are_read_from_config() -> Result<()> {
+    let config = ConfigFile::from_toml(
+        r#"
+        [[route]]
+        name = "emea-gaps"
+        tags = { bu = "emea" }
+        mdi = false
+        webhook = "https://hooks.example.com/services/emea"
+
+        [[route]]
+        name = "unified"
+        domains = ["*.contoso.com"]
+        fields = { mdi_generation = "unified" }
+        file = "unified.jsonl"
+        "#,
+    )?;
+    assert_eq!(config.routes.len(), 2);
+    assert_eq!(config.routes[0].mdi, Some(false));
+    assert_eq!(config.routes[0].tags["bu"], "emea");
+    assert_eq!(config.routes[1].fields["mdi_generation"], "unified");
+
+    assert!(ConfigFile::from_toml("[[route]]\nname = \"x\"\nfile = \"a\"\nbu = \"emea\"").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_route_conditions() {
+    let route = Route {
+        name: "contoso-unified".to_string(),
+        domains: vec!["*.contoso.com".to_string()],
+        fields: [("mdi_generation".to_string(), "unified".into())].into(),
+        failed: Some(false),
+        ..Default::default()
+    };
+    assert!(route.matches(&result("eu.contoso.com", "emea", true)));
+    assert!(!route.matches(&result("eu.contoso.com", "emea", false)));
+    assert!(!route.matches(&result("fabrikam.com", "emea", true)));
+
+    let mut failed = result("eu.contoso.com", "emea", true);
+    failed.error = Some("timeout".to_string());
+    assert!(!route.matches(&failed));
+
+    // A route without conditions takes everything
+    let everything = Route {
+        name: "all".to_string(),
+        ..Default::default()
+    };
+    assert!(everything.matches(&failed));
+}
+
+#[tokio::test]
+async fn test_invalid_routes_are_rejected() {
+    let timeout = Duration::from_secs(1);
+    let no_destination = Route {
+        name: "nowhere".to_string(),
+        ..Default::default()
+    };
+    let both = Route {
+        name: "both".to_string(),
+        webhook: Some("https://hooks.example.com".to_string()),
+        file: Some("routed.jsonl".into()),
+        ..Default::default()
+    };
+    let unknown_field = Route {
+        name: "typo".to_string(),
+        fields: [("tennant".to_string(), "contoso".into())].into(),
+        webhook: Some("https://hooks.example.com".to_string()),
+        ..Default::default()
+    };
+    for route in [no_destination, both, unknown_field] {
+        let error = RoutingSink::from_routes(std::slice::from_ref(&route), timeout)
+            .await
+            .err()
+            .expect("invalid route");
+        assert!(error.to_string().contains(&route.name), "{error}");
+    }
+}
+
+#[tokio::test]
+async fn test_results_go_to_every_matching_route() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_routing_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let routes = [
+        Route {
+            name: "emea-gaps".to_string(),
+            tags: [("bu".to_string(), "emea".to_string())].into(),
+            mdi: Some(false),
+            file: Some(dir.join("emea-gaps.jsonl")),
+            ..Default::default()
+        },
+        Route {
+            name: "all".to_string(),
+            file: Some(dir.join("all.jsonl")),
+            ..Default::default()
+        },
+    ];
+
+    let mut sink = RoutingSink::from_routes(&routes, Duration::from_secs(1)).await?;
+    for result in [
+        result("contoso.com", "emea", false),
+        result("fabrikam.com", "emea", true),
+        result("northwind.com", "amer", false),
+    ] {
+        sink.write(&result).await?;
+    }
+    sink.close().await?;
+
+    assert_eq!(
+        domains(&std::fs::read_to_string(dir.join("emea-gaps.jsonl"))?)?,
+        ["contoso.com"]
+    );
+    assert_eq!(
+        domains(&std::fs::read_to_string(dir.join("all.jsonl"))?)?,
+        ["contoso.com", "fabrikam.com", "northwind.com"]
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
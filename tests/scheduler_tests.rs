@@ -0,0 +1,174 @@
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use sentri::core::MdiChecker;
+use sentri::jobs::JobManager;
+use sentri::rate_limit::RateBudget;
+use sentri::scheduler::{parse_cron, ScheduleRequest, ScheduledScan, Scheduler, SCHEDULES_FILE};
+use sentri::server::{serve, ServerState};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn state_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_state_{}", uuid::Uuid::new_v4()))
+}
+
+fn request(cron: &str, domains: &[&str]) -> ScheduleRequest {
+    ScheduleRequest {
+        name: "nightly".to_string(),
+        cron: cron.to_string(),
+        domains: domains.iter().map(|d| d.to_string()).collect(),
+        enabled: true,
+    }
+}
+
+#[test]
+fn test_parse_cron() {
+    assert!(parse_cron("*/15 * * * *").is_ok());
+    assert!(parse_cron("30 0 6 * * Mon-Fri").is_ok());
+    assert!(parse_cron("* * *").is_err());
+    assert!(parse_cron("61 * * * *").is_err());
+}
+
+#[test]
+fn test_schedule_from_request() -> Result<()> {
+    let now = Utc.with_ymd_and_hms(2026, 10, 16, 1, 30, 0).unwrap();
+    let schedule = ScheduledScan::from_request(request("0 2 * * *", &["contoso.com"]), now)?;
+
+    assert_eq!(
+        schedule.next_run,
+        Some(Utc.with_ymd_and_hms(2026, 10, 16, 2, 0, 0).unwrap())
+    );
+    assert!(!schedule.is_due(now));
+    assert!(schedule.is_due(Utc.with_ymd_and_hms(2026, 10, 16, 2, 0, 0).unwrap()));
+
+    let mut disabled = request("0 2 * * *", &["contoso.com"]);
+    disabled.enabled = false;
+    let disabled = ScheduledScan::from_request(disabled, now)?;
+    assert_eq!(disabled.next_run, None);
+    assert!(!disabled.is_due(Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap()));
+
+    assert!(ScheduledScan::from_request(request("0 2 * * *", &[]), now).is_err());
+    assert!(ScheduledScan::from_request(request("0 2 * * *", &["not a domain"]), now).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_schedules_are_persisted() -> Result<()> {
+    let dir = state_dir();
+    let checker = MdiChecker::new(5, 5000)?;
+
+    let scheduler =
+        Scheduler::open(checker.clone(), &dir, Arc::new(RateBudget::new(50, 5))).await?;
+    let schedule = ScheduledScan::from_request(request("0 2 * * *", &["contoso.com"]), Utc::now())?;
+    scheduler.add(schedule.clone()).await?;
+    assert!(dir.join(SCHEDULES_FILE).exists());
+
+    let reopened = Scheduler::open(checker, &dir, Arc::new(RateBudget::new(50, 5))).await?;
+    let loaded = reopened
+        .get(&schedule.id)
+        .await
+        .expect("schedule persisted");
+    assert_eq!(loaded.domains, vec!["contoso.com".to_string()]);
+    assert_eq!(loaded.next_run, schedule.next_run);
+
+    assert!(reopened.remove(&schedule.id).await?);
+    assert!(!reopened.remove(&schedule.id).await?);
+    assert!(reopened.list().await.is_empty());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reschedule_after_clock_jump() -> Result<()> {
+    let dir = state_dir();
+    let scheduler = Scheduler::open(
+        MdiChecker::new(5, 5000)?,
+        &dir,
+        Arc::new(RateBudget::new(50, 5)),
+    )
+    .await?;
+    let now = Utc.with_ymd_and_hms(2026, 10, 16, 1, 30, 0).unwrap();
+    let schedule = ScheduledScan::from_request(request("0 2 * * *", &["contoso.com"]), now)?;
+    scheduler.add(schedule.clone()).await?;
+
+    // The clock was set back a day: the next run follows the new time
+    scheduler
+        .reschedule_all(Utc.with_ymd_and_hms(2026, 10, 15, 3, 0, 0).unwrap())
+        .await?;
+    let rescheduled = scheduler.get(&schedule.id).await.expect("schedule exists");
+    assert_eq!(
+        rescheduled.next_run,
+        Some(Utc.with_ymd_and_hms(2026, 10, 16, 2, 0, 0).unwrap())
+    );
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_schedule_api() -> Result<()> {
+    let dir = state_dir();
+    let checker = MdiChecker::new(5, 5000)?;
+    let budget = Arc::new(RateBudget::new(50, 5));
+    let scheduler = Scheduler::open(checker.clone(), &dir, Arc::clone(&budget)).await?;
+    let jobs = JobManager::new(checker, &dir, budget).await?;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(serve(listener, ServerState::new(scheduler, jobs)));
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/schedules", base))
+        .json(&json!({"name": "weekly", "cron": "0 3 * * Sun", "domains": ["contoso.com"]}))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 201);
+    let created: Value = response.json().await?;
+    let id = created["id"].as_str().expect("id").to_string();
+    assert_eq!(created["enabled"], true);
+    assert!(created["next_run"].is_string());
+
+    let response = client
+        .post(format!("{}/schedules", base))
+        .json(&json!({"name": "broken", "cron": "whenever", "domains": ["contoso.com"]}))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 400);
+    let error: Value = response.json().await?;
+    assert!(error["error"].as_str().unwrap().contains("Cron expression"));
+
+    let listed: Value = client
+        .get(format!("{}/schedules", base))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(listed.as_array().map(Vec::len), Some(1));
+
+    let fetched: Value = client
+        .get(format!("{}/schedules/{}", base, id))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(fetched["name"], "weekly");
+    assert!(fetched["last_run"].is_null());
+
+    let response = client
+        .delete(format!("{}/schedules/{}", base, id))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 204);
+
+    let response = client
+        .get(format!("{}/schedules/{}", base, id))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 404);
+
+    server.abort();
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use sentri::secrets::{read_secret_file, SecretProvider, SecretResolver};
+
+struct StaticProvider;
+
+impl SecretProvider for StaticProvider {
+    fn scheme(&self) -> &str {
+        "vault"
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String> {
+        match reference {
+            "secret/es#password" => Ok("from-vault".to_string()),
+            _ => anyhow::bail!("No secret at {}", reference),
+        }
+    }
+}
+
+#[test]
+fn test_plain_values_pass_through() -> Result<()> {
+    let resolver = SecretResolver::default();
+    assert_eq!(resolver.resolve("hunter2")?, "hunter2");
+    // Unknown schemes are part of the secret
+    assert_eq!(resolver.resolve("abc:def")?, "abc:def");
+    assert_eq!(resolver.resolve("literal:file:abc")?, "file:abc");
+    Ok(())
+}
+
+#[test]
+fn test_file_references() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_secret_{}", uuid::Uuid::new_v4()));
+    std::fs::write(&path, "s3cret\n")?;
+
+    let resolver = SecretResolver::default();
+    assert_eq!(
+        resolver.resolve(&format!("file:{}", path.display()))?,
+        "s3cret"
+    );
+    assert_eq!(read_secret_file(&path)?, "s3cret");
+
+    std::fs::remove_file(&path)?;
+    assert!(resolver
+        .resolve(&format!("file:{}", path.display()))
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_env_references() -> Result<()> {
+    std::env::set_var("SENTRI_TEST_SECRET_REF", "from-env");
+    let resolver = SecretResolver::default();
+    assert_eq!(resolver.resolve("env:SENTRI_TEST_SECRET_REF")?, "from-env");
+    assert!(resolver.resolve("env:SENTRI_TEST_SECRET_UNSET").is_err());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_command_references() -> Result<()> {
+    let resolver = SecretResolver::default();
+    assert_eq!(resolver.resolve("cmd:echo from-command")?, "from-command");
+    // No shell: metacharacters are passed through as arguments
+    assert_eq!(resolver.resolve("cmd:echo $HOME;true")?, "$HOME;true");
+    assert!(resolver.resolve("cmd:false").is_err());
+    assert!(resolver.resolve("cmd:").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_custom_provider() -> Result<()> {
+    let resolver = SecretResolver::default().with_provider(StaticProvider);
+    assert_eq!(resolver.resolve("vault:secret/es#password")?, "from-vault");
+    let error = resolver.resolve("vault:secret/missing").unwrap_err();
+    assert!(format!("{:#}", error).contains("secret/missing"));
+
+    let mut value = Some("vault:secret/es#password".to_string());
+    resolver.resolve_option(&mut value)?;
+    assert_eq!(value.as_deref(), Some("from-vault"));
+    Ok(())
+}
@@ -0,0 +1,376 @@
+use anyhow::Result;
+use sentri::core::MdiChecker;
+use sentri::jobs::JobManager;
+use sentri::rate_limit::RateBudget;
+use sentri::scheduler::Scheduler;
+use sentri::server::{serve, ApiKeys, AuditLog, AuditRecord, Caller, Scope, ServerState};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+fn digest(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+fn key_file(requests_per_minute: u64) -> String {
+    format!(
+        r#"
+[[key]]
+name = "red"
+key_sha256 = "{}"
+requests_per_minute = {}
+
+[[key]]
+name = "blue"
+key_sha256 = "{}"
+rate_limit = 5
+"#,
+        digest("red-secret"),
+        requests_per_minute,
+        digest("blue-secret").to_uppercase()
+    )
+}
+
+#[test]
+fn test_key_file_parsing() -> Result<()> {
+    let keys = ApiKeys::from_toml(&key_file(10))?;
+    assert_eq!(keys.keys().len(), 2);
+    assert_eq!(keys.keys()[0].rate_limit, 20);
+    assert_eq!(keys.keys()[1].rate_limit, 5);
+    assert_eq!(keys.keys()[1].requests_per_minute, 120);
+    assert_eq!(keys.keys()[0].scopes, vec![Scope::Read, Scope::Scan]);
+
+    assert_eq!(
+        keys.authenticate("red-secret").map(|k| k.name.as_str()),
+        Some("red")
+    );
+    // Digests are compared case-insensitively
+    assert_eq!(
+        keys.authenticate("blue-secret").map(|k| k.name.as_str()),
+        Some("blue")
+    );
+    assert!(keys.authenticate("guess").is_none());
+
+    assert!(ApiKeys::from_toml("").is_err());
+    assert!(ApiKeys::from_toml("[[key]]\nname = \"x\"\nkey_sha256 = \"abc\"\n").is_err());
+    let duplicate = format!(
+        "[[key]]\nname = \"x\"\nkey_sha256 = \"{0}\"\n[[key]]\nname = \"x\"\nkey_sha256 = \"{0}\"\n",
+        digest("a")
+    );
+    assert!(ApiKeys::from_toml(&duplicate).is_err());
+    let unscoped = format!(
+        "[[key]]\nname = \"x\"\nkey_sha256 = \"{}\"\nscopes = []\n",
+        digest("a")
+    );
+    assert!(ApiKeys::from_toml(&unscoped).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_quota_is_per_key() -> Result<()> {
+    let keys = ApiKeys::from_toml(&key_file(1))?;
+    assert!(keys.take_quota("red").await);
+    assert!(!keys.take_quota("red").await);
+    assert!(keys.take_quota("blue").await);
+    assert!(!keys.take_quota("unknown").await);
+    Ok(())
+}
+
+#[test]
+fn test_caller_access() {
+    let anonymous = Caller::anonymous();
+    let red = Caller {
+        key: Some("red".to_string()),
+        scopes: vec![Scope::Read],
+    };
+    assert!(anonymous.can_access(Some("red")));
+    assert!(anonymous.can_access(None));
+    assert!(red.can_access(Some("red")));
+    assert!(!red.can_access(Some("blue")));
+    assert!(!red.can_access(None));
+
+    assert!(anonymous.require(Scope::Scan).is_ok());
+    assert!(red.require(Scope::Read).is_ok());
+    assert!(red.require(Scope::Scan).is_err());
+}
+
+#[tokio::test]
+async fn test_authenticated_api() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_state_{}", uuid::Uuid::new_v4()));
+    let keys = ApiKeys::from_toml(&key_file(4))?;
+    let checker = MdiChecker::new(5, 5000)?;
+    let budget = Arc::new(
+        RateBudget::new(50, 5)
+            .with_owner("red", 20)
+            .with_owner("blue", 5),
+    );
+    let scheduler = Scheduler::open(checker.clone(), &dir, Arc::clone(&budget)).await?;
+    let jobs = JobManager::new(checker, &dir, budget).await?;
+    let audit_path = dir.join("audit.jsonl");
+    let state = ServerState::new(scheduler, jobs)
+        .with_api_keys(keys)
+        .with_audit_log(AuditLog::open(&audit_path, None).await?);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(serve(listener, state));
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{}/schedules", base)).send().await?;
+    assert_eq!(response.status(), 401);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+
+    let created: Value = client
+        .post(format!("{}/schedules", base))
+        .bearer_auth("red-secret")
+        .json(&json!({"name": "red nightly", "cron": "0 2 * * *", "domains": ["contoso.com"]}))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(created["owner"], "red");
+    let id = created["id"].as_str().expect("id");
+
+    // Other keys neither see nor reach the schedule
+    let listed: Value = client
+        .get(format!("{}/schedules", base))
+        .header("X-API-Key", "blue-secret")
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(listed, json!([]));
+    let response = client
+        .delete(format!("{}/schedules/{}", base, id))
+        .header("X-API-Key", "blue-secret")
+        .send()
+        .await?;
+    assert_eq!(response.status(), 404);
+
+    // Red has used 1 of 4 requests; the fifth is rejected
+    for _ in 0..3 {
+        let response = client
+            .get(format!("{}/schedules/{}", base, id))
+            .bearer_auth("red-secret")
+            .send()
+            .await?;
+        assert_eq!(response.status(), 200);
+    }
+    let response = client
+        .get(format!("{}/schedules", base))
+        .bearer_auth("red-secret")
+        .send()
+        .await?;
+    assert_eq!(response.status(), 429);
+    assert_eq!(response.headers()["retry-after"], "60");
+
+    server.abort();
+
+    let records: Vec<AuditRecord> = std::fs::read_to_string(&audit_path)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(records.len(), 8);
+    assert_eq!(records[0].key, None);
+    assert_eq!(records[0].status, 401);
+    assert_eq!(records[1].key.as_deref(), Some("red"));
+    assert_eq!(records[1].method, "POST");
+    assert_eq!(records[1].status, 201);
+    assert!(records[1].remote_addr.is_some());
+    assert_eq!(records[7].status, 429);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_scope_matrix() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_state_{}", uuid::Uuid::new_v4()));
+    let keys = ApiKeys::from_toml(&format!(
+        r#"
+[[key]]
+name = "reader"
+key_sha256 = "{}"
+scopes = ["read"]
+
+[[key]]
+name = "scanner"
+key_sha256 = "{}"
+scopes = ["scan"]
+
+[[key]]
+name = "full"
+key_sha256 = "{}"
+
+[[key]]
+name = "admin"
+key_sha256 = "{}"
+scopes = ["admin"]
+"#,
+        digest("reader-secret"),
+        digest("scanner-secret"),
+        digest("full-secret"),
+        digest("admin-secret")
+    ))?;
+    let checker = MdiChecker::new(1, 1000)?;
+    let budget = Arc::new(RateBudget::new(1, 1));
+    let scheduler = Scheduler::open(checker.clone(), &dir, Arc::clone(&budget)).await?;
+    let jobs = JobManager::new(checker, &dir, budget).await?;
+    let state = ServerState::new(scheduler, jobs).with_api_keys(keys);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(serve(listener, state));
+    let client = reqwest::Client::new();
+
+    let schedule = json!({"name": "n", "cron": "0 2 * * *", "domains": ["contoso.com"]});
+    let job = json!({"domains": ["contoso.com"]});
+    let limits = json!({"max_concurrent": 1});
+    // (method, path, body, required scope, status once authorized)
+    let matrix: Vec<(&str, &str, Option<&Value>, Scope, u16)> = vec![
+        ("GET", "/schedules", None, Scope::Read, 200),
+        ("GET", "/schedules/missing", None, Scope::Read, 404),
+        ("POST", "/schedules", Some(&schedule), Scope::Scan, 201),
+        ("POST", "/schedules/missing/run", None, Scope::Scan, 404),
+        ("DELETE", "/schedules/missing", None, Scope::Scan, 404),
+        ("GET", "/jobs", None, Scope::Read, 200),
+        ("GET", "/jobs/missing", None, Scope::Read, 404),
+        ("GET", "/jobs/missing/results", None, Scope::Read, 404),
+        ("GET", "/jobs/missing/events", None, Scope::Read, 404),
+        ("POST", "/jobs", Some(&job), Scope::Scan, 202),
+        ("DELETE", "/jobs/missing", None, Scope::Scan, 404),
+        ("GET", "/results", None, Scope::Read, 200),
+        ("GET", "/version", None, Scope::Read, 200),
+        ("GET", "/admin/limits", None, Scope::Admin, 200),
+        ("PUT", "/admin/limits", Some(&limits), Scope::Admin, 200),
+    ];
+
+    for (key, scopes) in [
+        ("reader-secret", vec![Scope::Read]),
+        ("scanner-secret", vec![Scope::Scan]),
+        ("full-secret", vec![Scope::Read, Scope::Scan]),
+        ("admin-secret", vec![Scope::Admin]),
+    ] {
+        for (method, path, body, scope, allowed) in &matrix {
+            let mut request = client
+                .request(method.parse()?, format!("{}{}", base, path))
+                .bearer_auth(key);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            let status = request.send().await?.status().as_u16();
+            let expected = if scopes.contains(scope) {
+                *allowed
+            } else {
+                403
+            };
+            assert_eq!(status, expected, "{} {} with {}", method, path, key);
+        }
+    }
+
+    let version: Value = client
+        .get(format!("{}/version", base))
+        .bearer_auth("reader-secret")
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+    assert!(version["build_date"].is_string());
+
+    server.abort();
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_admin_limits() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_state_{}", uuid::Uuid::new_v4()));
+    let keys = ApiKeys::from_toml(&format!(
+        "{}\n[[key]]\nname = \"ops\"\nkey_sha256 = \"{}\"\nscopes = [\"read\", \"admin\"]\n",
+        key_file(100),
+        digest("ops-secret")
+    ))?;
+    let checker = MdiChecker::new(5, 5000)?;
+    let budget = Arc::new(RateBudget::new(50, 5).with_owner("red", 20));
+    let scheduler = Scheduler::open(checker.clone(), &dir, Arc::clone(&budget)).await?;
+    let jobs = JobManager::new(checker, &dir, Arc::clone(&budget)).await?;
+    let audit_path = dir.join("audit.jsonl");
+    let state = ServerState::new(scheduler, jobs)
+        .with_api_keys(keys)
+        .with_audit_log(AuditLog::open(&audit_path, None).await?);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(serve(listener, state));
+    let client = reqwest::Client::new();
+
+    let limits: Value = client
+        .get(format!("{}/admin/limits", base))
+        .bearer_auth("ops-secret")
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(limits["shared"]["requests_per_minute"], 50);
+    assert_eq!(limits["shared"]["max_concurrent"], 5);
+    assert_eq!(limits["keys"]["red"]["requests_per_minute"], 20);
+
+    let updated: Value = client
+        .put(format!("{}/admin/limits", base))
+        .bearer_auth("ops-secret")
+        .json(&json!({"requests_per_minute": 10, "max_concurrent": 2}))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(updated["requests_per_minute"], 10);
+    assert_eq!(updated["max_concurrent"], 2);
+    let usage = budget.shared().usage().await;
+    assert_eq!((usage.capacity, usage.max_concurrent), (10, 2));
+
+    let response = client
+        .put(format!("{}/admin/limits", base))
+        .bearer_auth("ops-secret")
+        .json(&json!({"key": "red", "requests_per_minute": 5}))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(budget.limiter_for(Some("red")).usage().await.capacity, 5);
+
+    for (body, status) in [
+        (json!({"key": "nobody", "max_concurrent": 1}), 404),
+        (json!({"max_concurrent": 0}), 400),
+        (json!({}), 400),
+    ] {
+        let response = client
+            .put(format!("{}/admin/limits", base))
+            .bearer_auth("ops-secret")
+            .json(&body)
+            .send()
+            .await?;
+        assert_eq!(response.status(), status, "{}", body);
+    }
+
+    server.abort();
+
+    let records: Vec<AuditRecord> = std::fs::read_to_string(&audit_path)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert!(records[0].change.is_none());
+    assert_eq!(records[1].key.as_deref(), Some("ops"));
+    assert_eq!(
+        records[1].change,
+        Some(json!({
+            "limits": "shared",
+            "before": {"requests_per_minute": 50, "max_concurrent": 5},
+            "after": {"requests_per_minute": 10, "max_concurrent": 2},
+        }))
+    );
+    assert_eq!(records[2].change.as_ref().unwrap()["limits"], "red");
+    assert!(records[3].change.is_none());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
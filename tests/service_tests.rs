@@ -0,0 +1,76 @@
+use sentri::service::{progress, unit_file, Notifier};
+
+#[test]
+fn test_inactive_notifier_is_silent() {
+    let notifier = Notifier::default();
+    assert!(!notifier.is_active());
+    assert_eq!(notifier.watchdog_interval(), None);
+    assert_eq!(notifier.arm_watchdog(), None);
+    notifier.ready();
+    notifier.status("idle");
+    progress();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_notifier_sends_datagrams() -> anyhow::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+    use std::time::Duration;
+
+    let path = std::env::temp_dir().join(format!("sentri_notify_{}", uuid::Uuid::new_v4()));
+    let socket = UnixDatagram::bind(&path)?;
+    socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+    std::env::set_var("NOTIFY_SOCKET", &path);
+    std::env::set_var("WATCHDOG_USEC", "10000000");
+
+    // Inherited from a parent process
+    std::env::set_var("WATCHDOG_PID", (std::process::id() + 1).to_string());
+    let notifier = Notifier::from_env();
+    assert!(notifier.is_active());
+    assert_eq!(notifier.watchdog_interval(), None);
+
+    let mut buf = [0u8; 256];
+    notifier.ready();
+    let len = socket.recv(&mut buf)?;
+    assert_eq!(&buf[..len], b"READY=1\n");
+    notifier.status("Listening on\n127.0.0.1:8080");
+    let len = socket.recv(&mut buf)?;
+    assert_eq!(&buf[..len], b"STATUS=Listening on 127.0.0.1:8080\n");
+
+    // Pinged as scans progress, at most every half interval
+    std::env::set_var("WATCHDOG_PID", std::process::id().to_string());
+    assert_eq!(notifier.arm_watchdog(), Some(Duration::from_secs(5)));
+    let len = socket.recv(&mut buf)?;
+    assert_eq!(&buf[..len], b"WATCHDOG=1\n");
+    progress();
+    socket.set_nonblocking(true)?;
+    assert!(socket.recv(&mut buf).is_err());
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_unit_file() {
+    let args: Vec<String> = [
+        "serve",
+        "--state-dir",
+        "/var/lib/sentri",
+        "--operator",
+        "Blue Team 100%",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    let unit = unit_file("/usr/local/bin/sentri", &args, Some("sentri"), None);
+    assert!(unit.starts_with("[Unit]\n"));
+    assert!(unit.contains("Type=notify\n"));
+    assert!(unit.contains(
+        "ExecStart=/usr/local/bin/sentri serve --state-dir /var/lib/sentri --operator \"Blue Team 100%%\"\n"
+    ));
+    assert!(unit.contains("ExecReload=/bin/kill -HUP $MAINPID\n"));
+    assert!(unit.contains("User=sentri\n"));
+    assert!(!unit.contains("DynamicUser"));
+    assert!(!unit.contains("WatchdogSec"));
+    assert!(unit.ends_with("WantedBy=multi-user.target\n"));
+}
@@ -0,0 +1,808 @@
+use anyhow::Result;
+use clap::Parser;
+use sentri::cli::{Cli, Commands};
+use sentri::core::{DomainResult, MdiGeneration};
+use sentri::graph::{Graph, GraphFormat};
+use sentri::sinks::elasticsearch::{bulk_body, check_bulk_response};
+use sentri::sinks::log_analytics::shared_key_signature;
+use sentri::sinks::splunk::{check_hec_response, event_body};
+use sentri::sinks::tenant_report::TenantReportSink;
+use sentri::sinks::{
+    format_sink, BucketedSink, ElasticsearchAuth, ElasticsearchSink, FanOutSink, GrepableSink,
+    JsonlFileSink, JunitSink, LogAnalyticsAuth, OutputFormat, ResultSink, SharedFileSink,
+    SocketSink, SplunkHecSink,
+};
+use std::time::Duration;
+
+#[test]
+fn test_shared_key_signature_known_vector() -> Result<()> {
+    let header = shared_key_signature(
+        "workspace-id",
+        "c2VjcmV0LWtleQ==",
+        "Mon, 04 Apr 2016 08:00:00 GMT",
+        42,
+    )?;
+
+    assert_eq!(
+        header,
+        "SharedKey workspace-id:32fBoxs+kHJMhtOtAMXzEDISmtMqof0ciFz3hLzbQfI="
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_shared_key_signature_rejects_invalid_key() {
+    let result = shared_key_signature("workspace-id", "not base64!", "date", 1);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_log_analytics_auth_from_args() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--la-workspace-id",
+        "ws",
+        "--la-shared-key",
+        "c2VjcmV0",
+    ])?;
+
+    let Commands::Batch { sinks, .. } = &cli.command else {
+        panic!("Expected Batch command");
+    };
+
+    match LogAnalyticsAuth::from_args(sinks) {
+        Some(LogAnalyticsAuth::SharedKey {
+            workspace_id,
+            log_type,
+            ..
+        }) => {
+            assert_eq!(workspace_id, "ws");
+            assert_eq!(log_type, "SentriMdi");
+        }
+        other => panic!("Expected shared key auth, got {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_log_analytics_requires_shared_key() {
+    let result = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--la-workspace-id",
+        "ws",
+    ]);
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_jsonl_file_sink_writes_one_line_per_result() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_sink_{}.jsonl", uuid::Uuid::new_v4()));
+
+    let mut sink = JsonlFileSink::create(&path).await?;
+    for domain in ["a.com", "b.com"] {
+        sink.write(&DomainResult {
+            domain: domain.to_string(),
+            ..Default::default()
+        })
+        .await?;
+    }
+    sink.flush().await?;
+
+    let content = std::fs::read_to_string(&path)?;
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("\"domain\":\"a.com\""));
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_shared_file_sinks_never_interleave_lines() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_shared_{}.jsonl", uuid::Uuid::new_v4()));
+    // A writer that died mid-line left a partial record behind
+    std::fs::write(&path, "{\"domain\":\"trunc")?;
+
+    let writers = (0..4).map(|writer| {
+        let path = path.clone();
+        tokio::spawn(async move {
+            // Every writer opens the file on its own, as separate processes would
+            let mut sink = SharedFileSink::open(&path).await?;
+            for chunk in 0..25 {
+                for index in 0..20 {
+                    sink.write(&DomainResult {
+                        domain: format!("w{}-c{}-{}.example.com", writer, chunk, index),
+                        federated_domains: vec!["x".repeat(200)],
+                        ..Default::default()
+                    })
+                    .await?;
+                }
+                sink.flush().await?;
+            }
+            sink.close().await
+        })
+    });
+    for writer in futures::future::join_all(writers).await {
+        writer??;
+    }
+
+    let content = std::fs::read_to_string(&path)?;
+    let mut lines = content.lines();
+    assert_eq!(lines.next(), Some("{\"domain\":\"trunc"));
+    let results = lines
+        .map(serde_json::from_str::<DomainResult>)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(results.len(), 4 * 25 * 20);
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_elasticsearch_bulk_body_format() -> Result<()> {
+    let results = vec![
+        DomainResult {
+            domain: "a.com".to_string(),
+            ..Default::default()
+        },
+        DomainResult {
+            domain: "b.com".to_string(),
+            ..Default::default()
+        },
+    ];
+
+    let body = bulk_body("sentri", &results)?;
+    let lines: Vec<serde_json::Value> = body
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0]["index"]["_index"], "sentri");
+    assert_eq!(lines[0]["index"]["_id"], "a.com");
+    assert_eq!(lines[3]["domain"], "b.com");
+    assert!(body.ends_with('\n'));
+
+    Ok(())
+}
+
+#[test]
+fn test_elasticsearch_bulk_response_errors() {
+    assert!(check_bulk_response(r#"{"took":3,"errors":false,"items":[]}"#).is_ok());
+
+    let failed = r#"{"took":3,"errors":true,"items":[
+        {"index":{"_id":"a.com","status":201}},
+        {"index":{"_id":"b.com","status":400,"error":{"type":"mapper_parsing_exception","reason":"failed to parse"}}}
+    ]}"#;
+    let err = check_bulk_response(failed).unwrap_err().to_string();
+    assert!(err.contains("1 of 2"));
+    assert!(err.contains("failed to parse"));
+}
+
+#[test]
+fn test_elasticsearch_sink_from_args() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--es-url",
+        "https://elastic.example.com:9200/",
+        "--es-index",
+        "scans-%Y",
+        "--es-username",
+        "elastic",
+        "--es-password",
+        "changeme",
+    ])?;
+
+    let Commands::Batch { sinks, .. } = &cli.command else {
+        panic!("Expected Batch command");
+    };
+
+    let sink = ElasticsearchSink::from_args(sinks, Duration::from_secs(1))?
+        .expect("Elasticsearch sink should be configured");
+    assert_eq!(sink.name(), "elasticsearch");
+    assert_eq!(
+        sink.resolve_index(),
+        chrono::Utc::now().format("scans-%Y").to_string()
+    );
+
+    let plain = ElasticsearchSink::new(
+        "https://elastic.example.com",
+        "static-index",
+        ElasticsearchAuth::None,
+        Duration::from_secs(1),
+    )?;
+    assert_eq!(plain.resolve_index(), "static-index");
+
+    Ok(())
+}
+
+#[test]
+fn test_splunk_event_body_format() -> Result<()> {
+    let results = vec![
+        DomainResult {
+            domain: "a.com".to_string(),
+            checked_at: "2024-05-01T12:00:00.250Z".parse()?,
+            ..Default::default()
+        },
+        DomainResult {
+            domain: "b.com".to_string(),
+            ..Default::default()
+        },
+    ];
+
+    let body = event_body(&results, Some("security"), "sentri:result")?;
+    let events: Vec<serde_json::Value> = body
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["time"], 1714564800.25);
+    assert_eq!(events[0]["host"], "a.com");
+    assert_eq!(events[0]["index"], "security");
+    assert_eq!(events[0]["sourcetype"], "sentri:result");
+    assert_eq!(events[1]["event"]["domain"], "b.com");
+
+    let body = event_body(&results[..1], None, "custom")?;
+    assert!(!body.contains("\"index\""));
+    Ok(())
+}
+
+#[test]
+fn test_splunk_hec_response_errors() {
+    assert!(check_hec_response(r#"{"text":"Success","code":0}"#).is_ok());
+
+    let err = check_hec_response(r#"{"text":"Incorrect index","code":7,"invalid-event-number":0}"#)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        err,
+        "Splunk HEC rejected the events: Incorrect index (code 7)"
+    );
+    assert!(check_hec_response("<html>").is_err());
+}
+
+#[test]
+fn test_splunk_sink_from_args() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--splunk-hec-url",
+        "https://splunk.example.com:8088/",
+        "--splunk-token",
+        "00000000-0000-0000-0000-000000000000",
+        "--splunk-index",
+        "security",
+    ])?;
+    let Commands::Batch { sinks, .. } = &cli.command else {
+        panic!("Expected Batch command");
+    };
+
+    let sink = SplunkHecSink::from_args(sinks, Duration::from_secs(1))?
+        .expect("Splunk sink should be configured");
+    assert_eq!(sink.name(), "splunk-hec");
+    assert_eq!(
+        sink.url(),
+        "https://splunk.example.com:8088/services/collector/event"
+    );
+
+    // Full endpoint URLs are kept as given
+    let raw = SplunkHecSink::new(
+        "https://hec.example.com/services/collector/event/1.0",
+        "token",
+        Duration::from_secs(1),
+    )?;
+    assert_eq!(
+        raw.url(),
+        "https://hec.example.com/services/collector/event/1.0"
+    );
+
+    // A token without a collector is rejected
+    assert!(Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--splunk-token",
+        "token",
+    ])
+    .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_junit_sink_report() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_junit_{}.xml", uuid::Uuid::new_v4()));
+
+    let mut sink = JunitSink::new(Some(&path));
+    sink.write(&DomainResult {
+        domain: "covered.com".to_string(),
+        mdi_instance: Some("coveredsensorapi.atp.azure.com".to_string()),
+        processing_time_ms: 1500,
+        ..Default::default()
+    })
+    .await?;
+    sink.write(&DomainResult {
+        domain: "uncovered.com".to_string(),
+        tags: [("owner".to_string(), "<alice>".to_string())].into(),
+        ..Default::default()
+    })
+    .await?;
+    sink.write(&DomainResult {
+        domain: "broken.com".to_string(),
+        error: Some("timeout <5s>".to_string()),
+        ..Default::default()
+    })
+    .await?;
+    sink.close().await?;
+
+    let report = std::fs::read_to_string(&path)?;
+    assert!(report.contains(r#"tests="3" failures="1" errors="1""#));
+    assert!(
+        report.contains(r#"<testcase classname="sentri.mdi" name="covered.com" time="1.500"/>"#)
+    );
+    assert!(report.contains("No MDI instance detected for uncovered.com"));
+    assert!(report.contains(r#"<property name="tag.owner" value="&lt;alice&gt;"/>"#));
+    assert!(report.contains(r#"<error message="timeout &lt;5s&gt;""#));
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_junit_sink_custom_check() -> Result<()> {
+    let mut sink = JunitSink::new(None).with_check(Box::new(|result: &DomainResult| {
+        (result.tenant.is_none()).then(|| "tenant not identified".to_string())
+    }));
+    sink.write(&DomainResult {
+        domain: "example.com".to_string(),
+        tenant: Some("example".to_string()),
+        ..Default::default()
+    })
+    .await?;
+
+    assert!(sink.render().contains(r#"failures="0" errors="0""#));
+    Ok(())
+}
+
+#[test]
+fn test_format_flag_parsing() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--format",
+        "junit",
+    ])?;
+
+    assert!(matches!(cli.command, Commands::Batch { .. }));
+    assert_eq!(cli.format, Some(OutputFormat::Junit));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_grepable_sink_writes_one_line_per_domain() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_grepable_{}", uuid::Uuid::new_v4()));
+
+    let mut sink = GrepableSink::create(Some(&path)).await?;
+    sink.write(&DomainResult {
+        domain: "contoso.com".to_string(),
+        tenant: Some("contoso".to_string()),
+        federated_domains: vec!["contoso.com".to_string(), "fabrikam.com".to_string()],
+        mdi_instance: Some("contososensorapi.atp.azure.com".to_string()),
+        tags: [
+            ("bu".to_string(), "emea".to_string()),
+            ("owner".to_string(), "alice".to_string()),
+        ]
+        .into(),
+        ..Default::default()
+    })
+    .await?;
+    sink.write(&DomainResult {
+        domain: "broken.com".to_string(),
+        error: Some("timeout\nafter 5s".to_string()),
+        ..Default::default()
+    })
+    .await?;
+    sink.close().await?;
+
+    let output = std::fs::read_to_string(&path)?;
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("# sentri "));
+    assert_eq!(
+        lines[1],
+        "Domain: contoso.com\tTenant: contoso\tMDI: yes\tInstance: contososensorapi.atp.azure.com\tFederated: 2\tTags: bu=emea;owner=alice\tError: -"
+    );
+    assert_eq!(
+        lines[2],
+        "Domain: broken.com\tTenant: -\tMDI: no\tInstance: -\tFederated: 0\tTags: -\tError: timeout after 5s"
+    );
+    assert_eq!(lines[3], "# sentri done: 2 domains, 1 with MDI, 1 errors");
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_markdown_sink_summarizes_tenants() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_{}.md", uuid::Uuid::new_v4()));
+
+    let mut sink = format_sink(OutputFormat::Markdown, Some(&path), None).await?;
+    for domain in ["contoso.com", "contoso.de"] {
+        sink.write(&DomainResult {
+            domain: domain.to_string(),
+            tenant: Some("contoso".to_string()),
+            federated_domains: vec![
+                domain.to_string(),
+                "contoso.onmicrosoft.com".to_string(),
+                "contoso.mail.onmicrosoft.com".to_string(),
+            ],
+            mdi_instance: Some("contososensorapi.atp.azure.com".to_string()),
+            mdi_generation: Some(MdiGeneration::Legacy),
+            ..Default::default()
+        })
+        .await?;
+    }
+    sink.write(&DomainResult {
+        domain: "fabrikam.com".to_string(),
+        tenant: Some("fab|rikam".to_string()),
+        ..Default::default()
+    })
+    .await?;
+    sink.write(&DomainResult {
+        domain: "broken.com".to_string(),
+        error: Some("timeout\nafter 5s".to_string()),
+        ..Default::default()
+    })
+    .await?;
+    sink.close().await?;
+
+    let markdown = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(path)?;
+    assert!(markdown.starts_with("# Sentri MDI Discovery Summary\n"));
+    assert!(markdown.contains("| 4 | 2 | 1 | 1 |"));
+    assert!(markdown.contains(
+        "| contoso | 4 | contoso.com, contoso.de | yes | contososensorapi.atp.azure.com (legacy) | 2 custom, 1 onmicrosoft, 1 mail routing |"
+    ));
+    // Pipes cannot break out of their cell
+    assert!(markdown.contains("| fab\\|rikam | 1 | fabrikam.com | no | - | 1 custom |"));
+    assert!(markdown.contains("## Failed Checks\n\n- broken.com: timeout after 5s\n"));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_graph_formats_connect_tenants_to_domains() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_graph_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let results = [
+        DomainResult {
+            domain: "contoso.com".to_string(),
+            tenant: Some("contoso".to_string()),
+            federated_domains: vec!["contoso.com".to_string(), "fabrikam.com".to_string()],
+            mdi_instance: Some("contososensorapi.atp.azure.com".to_string()),
+            ..Default::default()
+        },
+        DomainResult {
+            domain: "broken.com".to_string(),
+            error: Some("timeout".to_string()),
+            ..Default::default()
+        },
+    ];
+
+    for (format, name) in [
+        (OutputFormat::Dot, "graph.dot"),
+        (OutputFormat::Graphml, "graph.graphml"),
+    ] {
+        let path = dir.join(name);
+        let mut sink = format_sink(format, Some(&path), None).await?;
+        for result in &results {
+            sink.write(result).await?;
+        }
+        sink.close().await?;
+        // Same graph as `sentri graph` over the results file
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            Graph::from_results(&results).render(match format {
+                OutputFormat::Dot => GraphFormat::Dot,
+                _ => GraphFormat::Graphml,
+            })
+        );
+    }
+
+    let dot = std::fs::read_to_string(dir.join("graph.dot"))?;
+    assert!(dot.contains(r#""domain:fabrikam.com" -> "tenant:contoso" [label=tenant];"#));
+    assert!(dot.contains(r#""domain:broken.com" [label="broken.com""#));
+    let graphml = std::fs::read_to_string(dir.join("graph.graphml"))?;
+    assert!(graphml.contains(
+        r#"<edge source="tenant:contoso" target="mdi_instance:contososensorapi.atp.azure.com">"#
+    ));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_socket_sink_streams_ndjson() -> Result<()> {
+    use tokio::io::AsyncBufReadExt;
+
+    let path = std::env::temp_dir().join(format!("sentri_{}.sock", uuid::Uuid::new_v4()));
+    let listener = tokio::net::UnixListener::bind(&path)?;
+
+    let mut sink = SocketSink::connect(&path).await?;
+    let (stream, _) = listener.accept().await?;
+    for domain in ["a.com", "b.com"] {
+        sink.write(&DomainResult {
+            domain: domain.to_string(),
+            ..Default::default()
+        })
+        .await?;
+    }
+    sink.flush().await?;
+    sink.close().await?;
+
+    let mut lines = tokio::io::BufReader::new(stream).lines();
+    let mut domains = Vec::new();
+    while let Some(line) = lines.next_line().await? {
+        let result: DomainResult = serde_json::from_str(&line)?;
+        domains.push(result.domain);
+    }
+    assert_eq!(domains, ["a.com", "b.com"]);
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_socket_sink_rejects_regular_files() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_socket_{}", uuid::Uuid::new_v4()));
+    assert!(SocketSink::connect(&path).await.is_err());
+
+    std::fs::write(&path, "")?;
+    let error = SocketSink::connect(&path).await.err().unwrap();
+    assert!(format!("{:#}", error).contains("Not a Unix socket or named pipe"));
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bucketed_sink_rounds_timestamps() -> Result<()> {
+    use chrono::{TimeZone, Utc};
+
+    let path = std::env::temp_dir().join(format!("sentri_bucketed_{}.jsonl", uuid::Uuid::new_v4()));
+    let inner = Box::new(JsonlFileSink::create(&path).await?);
+    let mut sink = BucketedSink::new(inner, Duration::from_secs(3_600));
+    assert_eq!(sink.name(), "jsonl-file");
+
+    sink.write(&DomainResult {
+        domain: "contoso.com".to_string(),
+        processing_time_ms: 1250,
+        checked_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 34, 56).unwrap(),
+        completed_at: Utc.with_ymd_and_hms(2024, 5, 1, 13, 0, 1).unwrap(),
+        ..Default::default()
+    })
+    .await?;
+    sink.close().await?;
+
+    let content = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
+    let written: DomainResult = serde_json::from_str(content.trim())?;
+    assert_eq!(written.domain, "contoso.com");
+    assert_eq!(written.processing_time_ms, 1250);
+    assert_eq!(
+        written.checked_at,
+        Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap()
+    );
+    assert_eq!(
+        written.completed_at,
+        Utc.with_ymd_and_hms(2024, 5, 1, 13, 0, 0).unwrap()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tenant_report_sink_writes_one_line_per_tenant() -> Result<()> {
+    use sentri::core::MdiGeneration;
+    use sentri::sinks::tenant_report::TenantSummary;
+
+    let result = |domain: &str, tenant: Option<&str>, mdi: Option<&str>| DomainResult {
+        domain: domain.to_string(),
+        tenant: tenant.map(String::from),
+        federated_domains: tenant
+            .map(|tenant| vec![domain.to_string(), format!("{tenant}.onmicrosoft.com")])
+            .unwrap_or_default(),
+        mdi_instance: mdi.map(String::from),
+        mdi_generation: mdi.map(|_| MdiGeneration::Legacy),
+        ..Default::default()
+    };
+
+    let path = std::env::temp_dir().join(format!("sentri_tenants_{}.jsonl", uuid::Uuid::new_v4()));
+    let mut sink = TenantReportSink::new(&path);
+    assert_eq!(sink.name(), "tenant-report");
+    for result in [
+        result("fabrikam.com", Some("fabrikam"), None),
+        result("contoso.de", Some("contoso"), None),
+        result(
+            "Contoso.com",
+            Some("Contoso"),
+            Some("contososensorapi.atp.azure.com"),
+        ),
+        result("failed.example", None, None),
+    ] {
+        sink.write(&result).await?;
+    }
+    sink.flush().await?;
+    assert!(!path.exists());
+    sink.close().await?;
+
+    let content = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
+    let tenants: Vec<TenantSummary> = content
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(
+        tenants,
+        vec![
+            TenantSummary {
+                tenant: "contoso".to_string(),
+                domain_count: 3,
+                scanned_domains: vec!["contoso.com".to_string(), "contoso.de".to_string()],
+                mdi: true,
+                mdi_instance: Some("contososensorapi.atp.azure.com".to_string()),
+                mdi_generation: Some(MdiGeneration::Legacy),
+            },
+            TenantSummary {
+                tenant: "fabrikam".to_string(),
+                domain_count: 2,
+                scanned_domains: vec!["fabrikam.com".to_string()],
+                mdi: false,
+                mdi_instance: None,
+                mdi_generation: None,
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_batch_tenant_report_option() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--tenant-report",
+        "tenants.jsonl",
+    ])?;
+    let Commands::Batch { tenant_report, .. } = &cli.command else {
+        panic!("Expected Batch command");
+    };
+    assert_eq!(tenant_report.as_deref(), Some("tenants.jsonl".as_ref()));
+    Ok(())
+}
+
+/// Target failing every write, counting how often it was closed
+struct FailingSink(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+#[async_trait::async_trait]
+impl ResultSink for FailingSink {
+    fn name(&self) -> &str {
+        "failing"
+    }
+
+    async fn write(&mut self, _result: &DomainResult) -> Result<()> {
+        anyhow::bail!("disk full")
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        anyhow::bail!("disk full")
+    }
+}
+
+#[tokio::test]
+async fn test_fan_out_sink_writes_every_target() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_fanout_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let (jsonl, csv) = (dir.join("results.jsonl"), dir.join("results.csv"));
+
+    let mut sink = FanOutSink::new(vec![
+        format_sink(OutputFormat::Jsonl, Some(&jsonl), None).await?,
+        format_sink(OutputFormat::Csv, Some(&csv), None).await?,
+    ]);
+    assert_eq!(sink.len(), 2);
+    for domain in ["contoso.com", "fabrikam.com"] {
+        sink.write(&DomainResult {
+            domain: domain.to_string(),
+            ..Default::default()
+        })
+        .await?;
+    }
+    sink.close().await?;
+
+    let jsonl = std::fs::read_to_string(&jsonl)?;
+    let csv = std::fs::read_to_string(&csv)?;
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(jsonl.lines().count(), 2);
+    let csv: Vec<&str> = csv.lines().collect();
+    assert_eq!(csv.len(), 3);
+    assert!(csv[0].starts_with("domain,"));
+    assert!(csv[2].starts_with("fabrikam.com,"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fan_out_sink_names_failing_target() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_fanout_{}.jsonl", uuid::Uuid::new_v4()));
+    let closed = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let mut sink = FanOutSink::new(vec![
+        Box::new(FailingSink(closed.clone())),
+        Box::new(JsonlFileSink::create(&path).await?),
+    ]);
+
+    let error = sink.write(&DomainResult::default()).await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Failed to write result to failing output"
+    );
+
+    // The other targets are still closed, keeping what they received
+    let error = sink.close().await.unwrap_err();
+    assert_eq!(error.to_string(), "Failed to close failing output");
+    assert_eq!(closed.load(std::sync::atomic::Ordering::SeqCst), 1);
+    assert!(path.exists());
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_batch_multiple_outputs() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--output",
+        "results.jsonl",
+        "--output-csv",
+        "results.csv",
+        "--output-parquet",
+        "results.parquet",
+        "--output-sqlite",
+        "results.db",
+    ])?;
+    let Commands::Batch {
+        output_file,
+        output_csv,
+        output_parquet,
+        output_sqlite,
+        ..
+    } = &cli.command
+    else {
+        panic!("Expected Batch command");
+    };
+    assert_eq!(output_file.as_deref(), Some("results.jsonl".as_ref()));
+    assert_eq!(output_csv.as_deref(), Some("results.csv".as_ref()));
+    assert_eq!(output_parquet.as_deref(), Some("results.parquet".as_ref()));
+    assert_eq!(output_sqlite.as_deref(), Some("results.db".as_ref()));
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use sentri::sinks::sqlite_sink;
+use std::path::PathBuf;
+
+fn database_path() -> PathBuf {
+    std::env::temp_dir().join(format!("sentri_{}.db", uuid::Uuid::new_v4()))
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_rescans_upsert_rows() -> anyhow::Result<()> {
+    use sentri::core::DomainResult;
+    use sentri::sinks::sqlite::read_sqlite_results;
+
+    let path = database_path();
+    let result = |domain: &str, tenant: Option<&str>, error: Option<&str>| DomainResult {
+        domain: domain.to_string(),
+        tenant: tenant.map(str::to_string),
+        federated_domains: vec![domain.to_string()],
+        error: error.map(str::to_string),
+        ..Default::default()
+    };
+
+    let mut sink = sqlite_sink(&path, None)?;
+    sink.write(&result("fabrikam.com", None, Some("timeout")))
+        .await?;
+    sink.write(&result("contoso.com", Some("contoso"), None))
+        .await?;
+    sink.close().await?;
+    drop(sink);
+
+    // A re-scan replaces fabrikam.com and leaves contoso.com alone
+    let mut sink = sqlite_sink(&path, None)?;
+    sink.write(&result("fabrikam.com", Some("fabrikam"), None))
+        .await?;
+    sink.close().await?;
+    drop(sink);
+
+    let results = read_sqlite_results(&path, None)?;
+    let rows: Vec<(&str, Option<&str>, Option<&str>)> = results
+        .iter()
+        .map(|r| (r.domain.as_str(), r.tenant.as_deref(), r.error.as_deref()))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("contoso.com", Some("contoso"), None),
+            ("fabrikam.com", Some("fabrikam"), None),
+        ]
+    );
+    assert_eq!(results[0].federated_domains, ["contoso.com"]);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_uncommitted_chunk_is_discarded() -> anyhow::Result<()> {
+    use sentri::core::DomainResult;
+    use sentri::sinks::sqlite::read_sqlite_results;
+
+    let path = database_path();
+    let mut sink = sqlite_sink(&path, None)?;
+    sink.write(&DomainResult {
+        domain: "contoso.com".to_string(),
+        ..Default::default()
+    })
+    .await?;
+    sink.flush().await?;
+    sink.write(&DomainResult {
+        domain: "fabrikam.com".to_string(),
+        ..Default::default()
+    })
+    .await?;
+    // Dropped mid-chunk, e.g. by an interrupted batch
+    drop(sink);
+
+    let domains: Vec<String> = read_sqlite_results(&path, None)?
+        .into_iter()
+        .map(|r| r.domain)
+        .collect();
+    assert_eq!(domains, ["contoso.com"]);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_encrypted_store() -> anyhow::Result<()> {
+    use sentri::core::DomainResult;
+    use sentri::encryption::StorageKey;
+    use sentri::sinks::sqlite::read_sqlite_results;
+    use std::sync::Arc;
+
+    let path = database_path();
+    let key = Arc::new(StorageKey::from_base64(&StorageKey::generate())?);
+    let result = |domain: &str, tenant: &str| DomainResult {
+        domain: domain.to_string(),
+        tenant: Some(tenant.to_string()),
+        ..Default::default()
+    };
+
+    let mut sink = sqlite_sink(&path, Some(Arc::clone(&key)))?;
+    sink.write(&result("fabrikam.com", "old")).await?;
+    sink.write(&result("contoso.com", "contoso")).await?;
+    sink.close().await?;
+    drop(sink);
+    let mut sink = sqlite_sink(&path, Some(Arc::clone(&key)))?;
+    sink.write(&result("fabrikam.com", "fabrikam")).await?;
+    sink.close().await?;
+    drop(sink);
+
+    let rows: Vec<(String, Option<String>)> = read_sqlite_results(&path, Some(Arc::clone(&key)))?
+        .into_iter()
+        .map(|r| (r.domain, r.tenant))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("contoso.com".to_string(), Some("contoso".to_string())),
+            ("fabrikam.com".to_string(), Some("fabrikam".to_string())),
+        ]
+    );
+    let content = String::from_utf8_lossy(&std::fs::read(&path)?).into_owned();
+    assert!(!content.contains("contoso"));
+
+    // Rows cannot be swapped between domains
+    {
+        let connection = rusqlite::Connection::open(&path)?;
+        connection.execute(
+            "UPDATE encrypted_results SET result = (SELECT result FROM encrypted_results WHERE id = ?1) WHERE id = ?2",
+            [key.keyed_id("contoso.com"), key.keyed_id("fabrikam.com")],
+        )?;
+    }
+    assert!(read_sqlite_results(&path, Some(Arc::clone(&key))).is_err());
+
+    // A database keeps the form it was created in
+    let other = Arc::new(StorageKey::from_base64(&StorageKey::generate())?);
+    assert!(sqlite_sink(&path, None).is_err());
+    assert!(sqlite_sink(&path, Some(other)).is_err());
+    assert!(read_sqlite_results(&path, None).is_err());
+    let plain = database_path();
+    let mut sink = sqlite_sink(&plain, None)?;
+    sink.write(&result("contoso.com", "contoso")).await?;
+    sink.close().await?;
+    drop(sink);
+    assert!(sqlite_sink(&plain, Some(key)).is_err());
+
+    std::fs::remove_file(&path)?;
+    std::fs::remove_file(&plain)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+#[test]
+fn test_sqlite_requires_feature() {
+    let path = database_path();
+    let error = sqlite_sink(&path, None)
+        .err()
+        .expect("sqlite without the feature");
+    assert!(error.to_string().contains("sqlite feature"));
+    assert!(!path.exists());
+}
@@ -0,0 +1,52 @@
+use sentri::core::BatchSummary;
+use sentri::stats::{Detector, DetectorStats};
+use std::time::Duration;
+
+#[test]
+fn test_detector_percentiles() {
+    let stats = DetectorStats::new();
+    for ms in 1..=100 {
+        stats.record(Detector::Mdi, ms % 10 != 0, Duration::from_millis(ms));
+    }
+
+    let summaries = stats.summaries();
+    assert_eq!(summaries.len(), 1);
+    let mdi = &summaries[0];
+    assert_eq!(mdi.detector, Detector::Mdi);
+    assert_eq!((mdi.successes, mdi.failures), (90, 10));
+    assert_eq!(mdi.p50_ms, 50);
+    assert_eq!(mdi.p95_ms, 95);
+    assert_eq!(mdi.p99_ms, 99);
+    assert_eq!(mdi.max_ms, 100);
+}
+
+#[test]
+fn test_detectors_in_pipeline_order() {
+    let stats = DetectorStats::new();
+    stats.record(Detector::Endpoints, true, Duration::from_millis(40));
+    stats.record(Detector::Federation, true, Duration::from_millis(300));
+    stats.record(Detector::Tenant, true, Duration::ZERO);
+
+    let detectors: Vec<Detector> = stats.summaries().iter().map(|s| s.detector).collect();
+    assert_eq!(
+        detectors,
+        [Detector::Federation, Detector::Tenant, Detector::Endpoints]
+    );
+}
+
+#[test]
+fn test_summary_serializes_detectors() -> anyhow::Result<()> {
+    let stats = DetectorStats::new();
+    stats.record(Detector::Federation, true, Duration::from_millis(120));
+    let mut summary = BatchSummary::new();
+    summary.detectors = stats.summaries();
+
+    let json = serde_json::to_value(&summary)?;
+    assert_eq!(json["detectors"][0]["detector"], "federation");
+    assert_eq!(json["detectors"][0]["p95_ms"], 120);
+
+    // Summaries without detector runs leave the field out
+    let json = serde_json::to_value(BatchSummary::new())?;
+    assert!(json.get("detectors").is_none());
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use sentri::tags::{format_tags, parse_input_line, InputLine, Tags, CUSTOMER_TAG};
+
+fn tags(pairs: &[(&str, &str)]) -> Tags {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_parse_input_line() {
+    assert_eq!(
+        parse_input_line("  contoso.com  "),
+        Some(InputLine::new("contoso.com"))
+    );
+    assert_eq!(
+        parse_input_line("contoso.com, bu = emea ,owner=alice,=x,empty="),
+        Some(InputLine {
+            domain: "contoso.com".to_string(),
+            tags: tags(&[("bu", "emea"), ("owner", "alice")]),
+        })
+    );
+    // Quotes may open mid-column and contain commas and escaped quotes
+    assert_eq!(
+        parse_input_line(r#"contoso.com,owner="Smith, Bob","note=say ""hi""""#)
+            .unwrap()
+            .tags,
+        tags(&[("note", "say \"hi\""), ("owner", "Smith, Bob")])
+    );
+    assert_eq!(parse_input_line("Domain,Customer"), None);
+    assert_eq!(parse_input_line(""), None);
+    assert_eq!(parse_input_line("# contoso.com,bu=emea"), None);
+    assert_eq!(parse_input_line(",bu=emea"), None);
+}
+
+#[test]
+fn test_bare_column_is_customer() {
+    let line = parse_input_line("contoso.com,Acme Corp,ignored,bu=emea").unwrap();
+    assert_eq!(
+        line.tags,
+        tags(&[("bu", "emea"), (CUSTOMER_TAG, "Acme Corp")])
+    );
+
+    // An explicit customer tag wins over the shorthand
+    let line = parse_input_line("contoso.com,Acme Corp,customer=Globex").unwrap();
+    assert_eq!(line.tags[CUSTOMER_TAG], "Globex");
+}
+
+#[test]
+fn test_input_line_round_trip() {
+    for line in [
+        "contoso.com",
+        "contoso.com,bu=emea,owner=alice",
+        "contoso.com,\"owner=Smith, Bob\"",
+        "contoso.com,\"note=say \"\"hi\"\"\"",
+    ] {
+        let parsed = parse_input_line(line).unwrap();
+        assert_eq!(parsed.to_string(), line);
+        assert_eq!(parse_input_line(&parsed.to_string()), Some(parsed));
+    }
+}
+
+#[test]
+fn test_format_tags() {
+    assert_eq!(format_tags(&Tags::new()), "");
+    assert_eq!(
+        format_tags(&tags(&[("owner", "alice"), ("bu", "emea")])),
+        "bu=emea;owner=alice"
+    );
+}
@@ -0,0 +1,59 @@
+use chrono::{TimeZone, Utc};
+use sentri::time::{bucket_timestamp, Stopwatch, SKEW_TOLERANCE};
+use std::time::Duration;
+
+#[test]
+fn test_stopwatch_is_anchored_to_wall_clock() {
+    let stopwatch = Stopwatch::start();
+    std::thread::sleep(Duration::from_millis(20));
+
+    assert!(stopwatch.elapsed_ms() >= 20);
+    let elapsed = stopwatch.now() - stopwatch.started_at();
+    assert!(elapsed.num_milliseconds() >= 20);
+}
+
+#[test]
+fn test_clock_jump_detection() {
+    let stopwatch = Stopwatch::start();
+    let now = stopwatch.now();
+    assert!(!stopwatch.clock_jumped(now));
+
+    let tolerance = chrono::Duration::from_std(SKEW_TOLERANCE).unwrap();
+    let forward = now + tolerance * 2;
+    assert!(stopwatch.clock_jumped(forward));
+    assert!(stopwatch.skew(forward) > chrono::Duration::zero());
+
+    let back = now - chrono::Duration::hours(1);
+    assert!(stopwatch.clock_jumped(back));
+    assert!(stopwatch.skew(back) < chrono::Duration::zero());
+}
+
+#[test]
+fn test_bucket_timestamp() {
+    let at = |h, m, s| Utc.with_ymd_and_hms(2024, 5, 1, h, m, s).unwrap();
+    let hour = Duration::from_secs(3_600);
+
+    assert_eq!(bucket_timestamp(at(12, 34, 56), hour), at(12, 0, 0));
+    assert_eq!(bucket_timestamp(at(12, 0, 0), hour), at(12, 0, 0));
+    assert_eq!(
+        bucket_timestamp(at(12, 34, 56), Duration::from_secs(15 * 60)),
+        at(12, 30, 0)
+    );
+    assert_eq!(
+        bucket_timestamp(at(23, 59, 59), Duration::from_secs(86_400)),
+        at(0, 0, 0)
+    );
+    let precise = at(12, 34, 56) + chrono::Duration::milliseconds(789);
+    assert_eq!(bucket_timestamp(precise, Duration::ZERO), precise);
+    assert_eq!(
+        bucket_timestamp(precise, Duration::from_secs(1)),
+        at(12, 34, 56)
+    );
+
+    // Timestamps before the epoch round down, not towards zero
+    let before_epoch = Utc.with_ymd_and_hms(1969, 12, 31, 23, 30, 0).unwrap();
+    assert_eq!(
+        bucket_timestamp(before_epoch, hour),
+        Utc.with_ymd_and_hms(1969, 12, 31, 23, 0, 0).unwrap()
+    );
+}
@@ -0,0 +1,163 @@
+use anyhow::Result;
+use sentri::core::MdiChecker;
+use sentri::trace::{span, TraceRecorder};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Complete events of `trace` as (track, name, start, duration)
+fn spans(trace: &Value) -> Vec<(u64, String, u64, u64)> {
+    trace["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|event| event["ph"] == "X")
+        .map(|event| {
+            (
+                event["tid"].as_u64().unwrap(),
+                event["name"].as_str().unwrap().to_string(),
+                event["ts"].as_u64().unwrap(),
+                event["dur"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+/// Track names of `trace` by track
+fn tracks(trace: &Value) -> Vec<(u64, String)> {
+    trace["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|event| event["ph"] == "M" && event["name"] == "thread_name")
+        .map(|event| {
+            (
+                event["tid"].as_u64().unwrap(),
+                event["args"]["name"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_spans_nest_on_the_domain_track() {
+    let recorder = Arc::new(TraceRecorder::new());
+    recorder
+        .domain("contoso.com")
+        .scope(async {
+            let _check = span("check_domain");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            let mut attempt = span("http_attempt");
+            attempt.arg("attempt", 2);
+            attempt.arg("status", 503);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        })
+        .await;
+
+    let trace = recorder.to_json();
+    assert_eq!(trace["displayTimeUnit"], "ms");
+    assert_eq!(tracks(&trace), vec![(1, "contoso.com".to_string())]);
+
+    let spans = spans(&trace);
+    assert_eq!(spans.len(), 2);
+    // Inner spans end, and are recorded, first
+    let (track, name, start, duration) = &spans[0];
+    let (outer_track, outer_name, outer_start, outer_duration) = &spans[1];
+    assert_eq!(
+        (name.as_str(), outer_name.as_str()),
+        ("http_attempt", "check_domain")
+    );
+    assert_eq!(track, outer_track);
+    assert!(start >= outer_start);
+    assert!(start + duration <= outer_start + outer_duration);
+    assert!(*duration >= 5_000);
+
+    let attempt = &trace["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|event| event["name"] == "http_attempt")
+        .unwrap()["args"];
+    assert_eq!(attempt["attempt"], 2);
+    assert_eq!(attempt["status"], 503);
+}
+
+#[tokio::test]
+async fn test_spans_outside_a_traced_check_are_not_recorded() {
+    let recorder = Arc::new(TraceRecorder::new());
+    {
+        let mut untraced = span("dns_lookup");
+        untraced.arg("name", "contoso.com");
+    }
+    assert_eq!(recorder.spans(), 0);
+}
+
+#[tokio::test]
+async fn test_concurrent_domains_get_their_own_tracks() {
+    let recorder = Arc::new(TraceRecorder::new());
+    let check = |domain: &'static str| {
+        recorder.domain(domain).scope(async {
+            let _check = span("check_domain");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            let _mdi = span("mdi");
+        })
+    };
+    tokio::join!(check("contoso.com"), check("fabrikam.com"));
+
+    let trace = recorder.to_json();
+    let tracks = tracks(&trace);
+    assert_eq!(tracks.len(), 2);
+    for (track, _) in &tracks {
+        let names: Vec<String> = spans(&trace)
+            .into_iter()
+            .filter(|(tid, ..)| tid == track)
+            .map(|(_, name, ..)| name)
+            .collect();
+        assert_eq!(names, vec!["mdi", "check_domain"]);
+    }
+}
+
+#[tokio::test]
+async fn test_checker_traces_domain_checks() -> Result<()> {
+    let recorder = Arc::new(TraceRecorder::new());
+    let checker = MdiChecker::new(1, 1_000)?.with_trace(Arc::clone(&recorder));
+
+    // Rejected by validation, so no request leaves the host
+    let result = checker.check_domain("not a domain").await?;
+    assert!(result.error.is_some());
+
+    let trace = recorder.to_json();
+    let names: Vec<String> = spans(&trace)
+        .into_iter()
+        .map(|(_, name, ..)| name)
+        .collect();
+    assert_eq!(names, vec!["validation", "check_domain"]);
+    let check = trace["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|event| event["name"] == "check_domain")
+        .unwrap();
+    assert_eq!(check["args"]["error_class"], "invalid_domain");
+    assert_eq!(check["args"]["mdi"], false);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_write_trace_file() -> Result<()> {
+    let recorder = Arc::new(TraceRecorder::new());
+    recorder
+        .domain("contoso.com")
+        .scope(async {
+            let _check = span("check_domain");
+        })
+        .await;
+
+    let path = std::env::temp_dir().join(format!("sentri_trace_{}.json", uuid::Uuid::new_v4()));
+    recorder.write(&path).await?;
+    let written: Value = serde_json::from_slice(&tokio::fs::read(&path).await?)?;
+    tokio::fs::remove_file(&path).await?;
+    assert_eq!(written, recorder.to_json());
+    assert_eq!(spans(&written).len(), 1);
+    Ok(())
+}
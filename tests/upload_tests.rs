@@ -0,0 +1,110 @@
+use anyhow::Result;
+use clap::Parser;
+use sentri::cli::{Cli, Commands};
+use sentri::upload::{destination_url, upload_file, ServerSideEncryption};
+use std::path::Path;
+
+#[test]
+fn test_destination_url_prefix_and_key() {
+    assert_eq!(
+        destination_url("az://container/runs/", Path::new("out/results.jsonl")),
+        "az://container/runs/results.jsonl"
+    );
+    assert_eq!(
+        destination_url("s3://bucket/fixed.jsonl", Path::new("out/results.jsonl")),
+        "s3://bucket/fixed.jsonl"
+    );
+}
+
+#[test]
+fn test_server_side_encryption_options() {
+    assert!(ServerSideEncryption::Default
+        .config_options(Some("ignored"))
+        .is_empty());
+
+    assert_eq!(
+        ServerSideEncryption::Aes256.config_options(None),
+        vec![(
+            "aws_server_side_encryption".to_string(),
+            "AES256".to_string()
+        )]
+    );
+
+    let dsse = ServerSideEncryption::DsseKms.config_options(Some("arn:aws:kms:key"));
+    assert_eq!(dsse.len(), 2);
+    assert_eq!(dsse[0].1, "aws:kms:dsse");
+    assert_eq!(dsse[1].1, "arn:aws:kms:key");
+}
+
+#[test]
+fn test_upload_requires_output_file() {
+    let result = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--upload-to",
+        "s3://bucket/",
+    ]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_upload_args_parsing() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "batch",
+        "--input-file",
+        "domains.txt",
+        "--output-file",
+        "results.jsonl",
+        "--upload-to",
+        "s3://bucket/",
+        "--upload-sse",
+        "kms",
+    ])?;
+
+    let Commands::Batch { upload, .. } = &cli.command else {
+        panic!("Expected Batch command");
+    };
+    assert_eq!(upload.upload_to.as_deref(), Some("s3://bucket/"));
+    assert_eq!(upload.upload_sse, ServerSideEncryption::Kms);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "object-store"))]
+#[tokio::test]
+async fn test_upload_without_feature_fails_clearly() {
+    let err = upload_file(
+        Path::new("results.jsonl"),
+        "s3://bucket/",
+        ServerSideEncryption::Default,
+        None,
+    )
+    .await
+    .unwrap_err();
+
+    assert!(err.to_string().contains("object-store feature"));
+}
+
+#[cfg(feature = "object-store")]
+#[tokio::test]
+async fn test_upload_to_local_object_store() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("sentri_upload_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let source = dir.join("results.jsonl");
+    std::fs::write(&source, "{\"domain\":\"example.com\"}\n")?;
+
+    let destination = format!("file://{}/uploaded/", dir.display());
+    let url = upload_file(&source, &destination, ServerSideEncryption::Default, None).await?;
+
+    assert!(url.ends_with("uploaded/results.jsonl"));
+    assert_eq!(
+        std::fs::read_to_string(dir.join("uploaded/results.jsonl"))?,
+        "{\"domain\":\"example.com\"}\n"
+    );
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,245 @@
+use anyhow::Result;
+use chrono::Utc;
+use clap::Parser;
+use sentri::alert::{OpsgenieAlerter, PagerDutyAlerter, OPSGENIE_ALERTS_URL};
+use sentri::cli::{Cli, Commands};
+use sentri::core::DomainResult;
+use sentri::watch::{detect_changes, TombstoneReason, WatchEvent, WatchHistory};
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn result(domain: &str, mdi: Option<&str>, federated: &[&str]) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        tenant: Some("contoso".to_string()),
+        federated_domains: federated.iter().map(|d| d.to_string()).collect(),
+        mdi_instance: mdi.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+fn baseline(results: &[DomainResult]) -> HashMap<String, DomainResult> {
+    results
+        .iter()
+        .map(|r| (r.domain.clone(), r.clone()))
+        .collect()
+}
+
+#[test]
+fn test_first_cycle_only_establishes_baseline() {
+    let current = vec![result("contoso.com", None, &["contoso.com", "other.com"])];
+    assert!(detect_changes(&HashMap::new(), &current).is_empty());
+}
+
+#[test]
+fn test_detects_lost_mdi_instance() {
+    let previous = baseline(&[result(
+        "contoso.com",
+        Some("contososensorapi.atp.azure.com"),
+        &["contoso.com"],
+    )]);
+    let current = vec![result("contoso.com", None, &["contoso.com"])];
+
+    assert_eq!(
+        detect_changes(&previous, &current),
+        vec![WatchEvent::MdiLost {
+            domain: "contoso.com".to_string(),
+            previous_instance: "contososensorapi.atp.azure.com".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_failed_scan_is_not_reported_as_lost_mdi() {
+    let previous = baseline(&[result(
+        "contoso.com",
+        Some("contososensorapi.atp.azure.com"),
+        &["contoso.com"],
+    )]);
+    let current = vec![DomainResult {
+        domain: "contoso.com".to_string(),
+        error: Some("timeout".to_string()),
+        ..Default::default()
+    }];
+
+    assert!(detect_changes(&previous, &current).is_empty());
+}
+
+#[test]
+fn test_detects_new_unknown_federated_domain() {
+    let previous = baseline(&[
+        result("contoso.com", None, &["contoso.com"]),
+        result("fabrikam.com", None, &["fabrikam.com"]),
+    ]);
+    // fabrikam.com is monitored, so only the unknown domain is reported
+    let current = vec![
+        result(
+            "contoso.com",
+            None,
+            &["contoso.com", "fabrikam.com", "shadow-it.com"],
+        ),
+        result("fabrikam.com", None, &["fabrikam.com"]),
+    ];
+
+    let events = detect_changes(&previous, &current);
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].dedup_key(),
+        "sentri:new-federated-domain:shadow-it.com"
+    );
+    assert_eq!(events[0].domain(), "contoso.com");
+}
+
+fn failed(domain: &str) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        error: Some("timeout".to_string()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_tombstones_removed_domains() {
+    let mut history = WatchHistory::new(3);
+    let first = Utc::now();
+    let cycle = vec![
+        result("contoso.com", None, &["contoso.com"]),
+        result("fabrikam.com", None, &["fabrikam.com"]),
+    ];
+    assert!(history.record(&cycle, first).is_empty());
+
+    let later = first + chrono::Duration::hours(1);
+    let events = history.record(&cycle[..1], later);
+    assert_eq!(
+        events,
+        vec![WatchEvent::Tombstone {
+            domain: "fabrikam.com".to_string(),
+            reason: TombstoneReason::RemovedFromInput,
+            last_seen: first,
+        }]
+    );
+    assert!(!history.previous().contains_key("fabrikam.com"));
+    // Tombstones are emitted once
+    assert!(history.record(&cycle[..1], later).is_empty());
+}
+
+#[test]
+fn test_tombstones_domains_that_keep_failing() {
+    let mut history = WatchHistory::new(2);
+    let first = Utc::now();
+    history.record(&[result("contoso.com", None, &[])], first);
+
+    let later = first + chrono::Duration::hours(1);
+    assert!(history.record(&[failed("contoso.com")], later).is_empty());
+    // The last good result stays available while failures are transient
+    assert!(history.previous().contains_key("contoso.com"));
+
+    let events = history.record(&[failed("contoso.com")], later);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind(), "tombstone");
+    assert_eq!(events[0].dedup_key(), "sentri:tombstone:contoso.com");
+    assert!(history.record(&[failed("contoso.com")], later).is_empty());
+
+    // A recovery in between resets the count; never-seen domains have no tombstone
+    let mut history = WatchHistory::new(2);
+    history.record(&[result("contoso.com", None, &[])], first);
+    history.record(&[failed("contoso.com"), failed("new.com")], later);
+    history.record(&[result("contoso.com", None, &[])], later);
+    assert!(history
+        .record(&[failed("contoso.com"), failed("new.com")], later)
+        .is_empty());
+}
+
+#[test]
+fn test_tombstone_serialization() -> Result<()> {
+    let last_seen = "2024-05-01T12:00:00Z".parse()?;
+    let event = WatchEvent::Tombstone {
+        domain: "contoso.com".to_string(),
+        reason: TombstoneReason::StoppedResolving,
+        last_seen,
+    };
+    let json = serde_json::to_value(&event)?;
+    assert_eq!(json["event"], "tombstone");
+    assert_eq!(json["reason"], "stopped_resolving");
+    assert_eq!(json["last_seen"], "2024-05-01T12:00:00Z");
+    assert_eq!(event.severity(), "info");
+    Ok(())
+}
+
+#[test]
+fn test_event_serialization() -> Result<()> {
+    let event = WatchEvent::NewFederatedDomain {
+        domain: "contoso.com".to_string(),
+        federated_domain: "shadow-it.com".to_string(),
+    };
+    let json = serde_json::to_value(&event)?;
+    assert_eq!(json["event"], "new_federated_domain");
+    assert_eq!(json["federated_domain"], "shadow-it.com");
+    Ok(())
+}
+
+#[test]
+fn test_pagerduty_payload() -> Result<()> {
+    let alerter = PagerDutyAlerter::new("routing-key", Duration::from_secs(1))?;
+    let payload = alerter.payload(&WatchEvent::MdiLost {
+        domain: "contoso.com".to_string(),
+        previous_instance: "contososensorapi.atp.azure.com".to_string(),
+    });
+
+    assert_eq!(payload["routing_key"], "routing-key");
+    assert_eq!(payload["event_action"], "trigger");
+    assert_eq!(payload["dedup_key"], "sentri:mdi-lost:contoso.com");
+    assert_eq!(payload["payload"]["severity"], "critical");
+    assert_eq!(payload["payload"]["source"], "contoso.com");
+    assert_eq!(payload["payload"]["custom_details"]["event"], "mdi_lost");
+    Ok(())
+}
+
+#[test]
+fn test_opsgenie_payload() -> Result<()> {
+    let alerter = OpsgenieAlerter::new("api-key", OPSGENIE_ALERTS_URL, Duration::from_secs(1))?;
+    let payload = alerter.payload(&WatchEvent::NewFederatedDomain {
+        domain: "contoso.com".to_string(),
+        federated_domain: "shadow-it.com".to_string(),
+    });
+
+    assert_eq!(
+        payload["alias"],
+        "sentri:new-federated-domain:shadow-it.com"
+    );
+    assert_eq!(payload["priority"], "P3");
+    assert_eq!(payload["entity"], "contoso.com");
+    Ok(())
+}
+
+#[test]
+fn test_watch_args_parsing() -> Result<()> {
+    let cli = Cli::try_parse_from([
+        "sentri",
+        "watch",
+        "--input-file",
+        "monitored.txt",
+        "--interval-secs",
+        "600",
+        "--opsgenie-api-key",
+        "key",
+    ])?;
+
+    let Commands::Watch {
+        interval_secs,
+        max_iterations,
+        tombstone_after,
+        alerts,
+        ..
+    } = &cli.command
+    else {
+        panic!("Expected Watch command");
+    };
+    assert_eq!(*interval_secs, 600);
+    assert_eq!(*max_iterations, None);
+    assert_eq!(*tombstone_after, 3);
+    assert_eq!(alerts.opsgenie_api_key.as_deref(), Some("key"));
+    assert_eq!(alerts.opsgenie_url, OPSGENIE_ALERTS_URL);
+    assert!(alerts.pagerduty_routing_key.is_none());
+    Ok(())
+}
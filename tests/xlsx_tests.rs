@@ -0,0 +1,78 @@
+use anyhow::Result;
+#[cfg(feature = "xlsx")]
+use sentri::core::DomainResult;
+use sentri::output::OutputFormat;
+use sentri::sinks::format_sink;
+
+#[cfg(feature = "xlsx")]
+fn result(domain: &str, tenant: Option<&str>) -> DomainResult {
+    DomainResult {
+        domain: domain.to_string(),
+        tenant: tenant.map(String::from),
+        federated_domains: vec![domain.to_string()],
+        ..Default::default()
+    }
+}
+
+#[cfg(feature = "xlsx")]
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(feature = "xlsx")]
+#[tokio::test]
+async fn test_xlsx_workbook_layout() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_{}.xlsx", uuid::Uuid::new_v4()));
+    let mut sink = format_sink(OutputFormat::Xlsx, Some(&path), None).await?;
+    assert_eq!(sink.name(), "xlsx");
+    assert!(path.exists());
+    for result in [
+        result("contoso.com", Some("contoso")),
+        result("contoso.de", Some("contoso")),
+        result("failed.example", None),
+    ] {
+        sink.write(&result).await?;
+    }
+    sink.flush().await?;
+    sink.close().await?;
+
+    let bytes = std::fs::read(&path)?;
+    std::fs::remove_file(&path)?;
+    // A zip archive with one part per sheet; names are stored uncompressed
+    assert!(bytes.starts_with(b"PK\x03\x04"));
+    assert!(contains(&bytes, b"xl/worksheets/sheet1.xml"));
+    assert!(contains(&bytes, b"xl/worksheets/sheet2.xml"));
+    assert!(!contains(&bytes, b"xl/worksheets/sheet3.xml"));
+
+    assert!(format_sink(OutputFormat::Xlsx, None, None).await.is_err());
+    Ok(())
+}
+
+#[cfg(feature = "xlsx")]
+#[test]
+fn test_xlsx_writer_counts_rows() -> Result<()> {
+    use sentri::output::xlsx::XlsxWriter;
+
+    let mut writer = XlsxWriter::new()?;
+    for i in 0..100 {
+        writer.push(&result(&format!("d{i}.example"), Some("contoso")))?;
+    }
+    assert_eq!(writer.rows(), 100);
+    assert!(!writer.finish()?.is_empty());
+    Ok(())
+}
+
+#[cfg(not(feature = "xlsx"))]
+#[tokio::test]
+async fn test_xlsx_requires_feature() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("sentri_{}.xlsx", uuid::Uuid::new_v4()));
+    let error = format_sink(OutputFormat::Xlsx, Some(&path), None)
+        .await
+        .err()
+        .expect("xlsx output without the feature");
+    assert!(error.to_string().contains("xlsx feature"));
+    assert!(!path.exists());
+    Ok(())
+}
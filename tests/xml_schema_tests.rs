@@ -0,0 +1,183 @@
+use anyhow::Result;
+use sentri::xml_schema::{validate_federation_response, SchemaViolation, SchemaWarning};
+
+/// Federation response as returned by autodiscover-s.outlook.com
+const RESPONSE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" xmlns:a="http://www.w3.org/2005/08/addressing">
+  <s:Header>
+    <a:Action s:mustUnderstand="1">http://schemas.microsoft.com/exchange/2010/Autodiscover/Autodiscover/GetFederationInformationResponse</a:Action>
+    <h:ServerVersionInfo xmlns:h="http://schemas.microsoft.com/exchange/2010/Autodiscover" xmlns:i="http://www.w3.org/2001/XMLSchema-instance">
+      <h:MajorVersion>15</h:MajorVersion>
+    </h:ServerVersionInfo>
+  </s:Header>
+  <s:Body>
+    <GetFederationInformationResponseMessage xmlns="http://schemas.microsoft.com/exchange/2010/Autodiscover">
+      <Response xmlns:i="http://www.w3.org/2001/XMLSchema-instance">
+        <ErrorCode>NoError</ErrorCode>
+        <ErrorMessage/>
+        <ApplicationUri>outlook.com</ApplicationUri>
+        <Domains>
+          <Domain>contoso.com</Domain>
+          <Domain>contoso.onmicrosoft.com</Domain>
+        </Domains>
+        <TokenIssuers>
+          <TokenIssuer>
+            <Endpoint>https://login.microsoftonline.com/extSTS.srf</Endpoint>
+            <Uri>urn:federation:MicrosoftOnline</Uri>
+          </TokenIssuer>
+        </TokenIssuers>
+      </Response>
+    </GetFederationInformationResponseMessage>
+  </s:Body>
+</s:Envelope>"#;
+
+const RESPONSE_PATH: &str = "/Envelope/Body/GetFederationInformationResponseMessage/Response";
+
+fn violations(xml: &str) -> Vec<(SchemaViolation, String)> {
+    validate_federation_response(xml)
+        .into_iter()
+        .map(|warning| (warning.violation, warning.path))
+        .collect()
+}
+
+#[test]
+fn test_genuine_response_conforms() {
+    assert_eq!(validate_federation_response(RESPONSE), []);
+}
+
+#[test]
+fn test_injected_and_reordered_elements_are_reported() {
+    let injected = RESPONSE.replace(
+        "<ApplicationUri>",
+        "<Banner>Inspected by proxy</Banner><ApplicationUri>",
+    );
+    assert_eq!(
+        violations(&injected),
+        [(
+            SchemaViolation::UnexpectedElement,
+            format!("{}/Banner", RESPONSE_PATH)
+        )]
+    );
+
+    let reordered = RESPONSE
+        .replace("<ErrorCode>NoError</ErrorCode>", "")
+        .replace(
+            "</TokenIssuers>",
+            "</TokenIssuers><ErrorCode>NoError</ErrorCode>",
+        );
+    assert_eq!(
+        violations(&reordered),
+        [(
+            SchemaViolation::OutOfOrder,
+            format!("{}/ErrorCode", RESPONSE_PATH)
+        )]
+    );
+
+    let duplicated = RESPONSE.replace(
+        "<ApplicationUri>outlook.com</ApplicationUri>",
+        "<ApplicationUri>outlook.com</ApplicationUri><ApplicationUri>evil.example</ApplicationUri>",
+    );
+    assert_eq!(
+        violations(&duplicated),
+        [(
+            SchemaViolation::TooManyElements,
+            format!("{}/ApplicationUri", RESPONSE_PATH)
+        )]
+    );
+}
+
+#[test]
+fn test_namespaces_and_values_are_checked() {
+    // Same local names in another namespace do not conform
+    let rewritten = RESPONSE.replace(
+        r#"<GetFederationInformationResponseMessage xmlns="http://schemas.microsoft.com/exchange/2010/Autodiscover">"#,
+        r#"<GetFederationInformationResponseMessage xmlns="urn:proxy">"#,
+    );
+    assert_eq!(
+        violations(&rewritten),
+        [
+            (
+                SchemaViolation::UnexpectedElement,
+                "/Envelope/Body/GetFederationInformationResponseMessage".to_string()
+            ),
+            (
+                SchemaViolation::MissingElement,
+                "/Envelope/Body".to_string()
+            ),
+        ]
+    );
+
+    let invalid = RESPONSE
+        .replace("NoError", "Maybe")
+        .replace("<Domain>contoso.com</Domain>", "<Domain> </Domain>");
+    assert_eq!(
+        violations(&invalid),
+        [
+            (
+                SchemaViolation::InvalidValue,
+                format!("{}/ErrorCode", RESPONSE_PATH)
+            ),
+            (
+                SchemaViolation::InvalidValue,
+                format!("{}/Domains/Domain", RESPONSE_PATH)
+            ),
+        ]
+    );
+
+    let text = RESPONSE.replace("<Domains>", "<Domains>stray");
+    assert_eq!(
+        violations(&text),
+        [(
+            SchemaViolation::UnexpectedText,
+            format!("{}/Domains", RESPONSE_PATH)
+        )]
+    );
+}
+
+#[test]
+fn test_missing_and_malformed_responses() {
+    let empty = RESPONSE.replace(
+        &RESPONSE[RESPONSE.find("<Response ").unwrap()..RESPONSE.find("</Response>").unwrap() + 11],
+        "",
+    );
+    assert_eq!(
+        violations(&empty),
+        [(
+            SchemaViolation::MissingElement,
+            "/Envelope/Body/GetFederationInformationResponseMessage".to_string()
+        )]
+    );
+
+    let html = "<html><body>Access denied</body></html>";
+    assert_eq!(
+        violations(html),
+        [(SchemaViolation::UnexpectedElement, "/html".to_string())]
+    );
+
+    let truncated = &RESPONSE[..RESPONSE.find("</Domains>").unwrap()];
+    assert_eq!(
+        violations(truncated),
+        [(
+            SchemaViolation::Malformed,
+            format!("{}/Domains", RESPONSE_PATH)
+        )]
+    );
+}
+
+#[test]
+fn test_warnings_serialize_as_structured_records() -> Result<()> {
+    let warning = SchemaWarning {
+        violation: SchemaViolation::OutOfOrder,
+        path: "/Envelope/Body".to_string(),
+        message: "ErrorCode must precede Domains".to_string(),
+    };
+    assert_eq!(
+        serde_json::to_value(&warning)?,
+        serde_json::json!({
+            "violation": "out_of_order",
+            "path": "/Envelope/Body",
+            "message": "ErrorCode must precede Domains"
+        })
+    );
+    Ok(())
+}
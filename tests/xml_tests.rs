@@ -1,5 +1,9 @@
 use anyhow::Result;
-use sentri::xml::XmlParser;
+use sentri::error_class::ErrorClass;
+use sentri::xml::{
+    canonical_sha256, canonicalize, decode_response, normalize_fault_message, parse_soap_fault,
+    SoapFault, XmlParser, INVALID_DOMAIN_ERROR, SERVER_BUSY_ERROR,
+};
 
 #[test]
 fn test_xml_parser_creation() {
@@ -169,3 +173,241 @@ fn test_parse_federation_response_with_invalid_domains() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_canonical_hash_ignores_serialization_details() -> Result<()> {
+    let compact = r#"<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/" xmlns:a="urn:a"><soap:Body><Domain>contoso.com</Domain><Empty/></soap:Body></soap:Envelope>"#;
+    let pretty = r#"<?xml version="1.0" encoding="utf-8"?>
+<!-- reformatted by a proxy -->
+<soap:Envelope xmlns:a='urn:a' xmlns:soap='http://schemas.xmlsoap.org/soap/envelope/'>
+  <soap:Body>
+    <Domain>contoso.com</Domain>
+    <Empty></Empty>
+  </soap:Body>
+</soap:Envelope>
+"#;
+    assert_eq!(canonicalize(compact)?, canonicalize(pretty)?);
+    assert_eq!(canonical_sha256(compact)?, canonical_sha256(pretty)?);
+    assert_eq!(canonical_sha256(compact)?.len(), 64);
+
+    // Any change of content changes the hash
+    let tampered = compact.replace("contoso.com", "fabrikam.com");
+    assert_ne!(canonical_sha256(compact)?, canonical_sha256(&tampered)?);
+
+    assert!(canonicalize("<a><b></a>").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse_soap_fault_soap11_with_detail() {
+    let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+  <s:Body>
+    <s:Fault>
+      <faultcode xmlns:a="http://schemas.microsoft.com/exchange/services/2006/types">a:ErrorServerBusy</faultcode>
+      <faultstring xml:lang="en-US">The server cannot service this request right now. Try again later.</faultstring>
+      <detail>
+        <e:ResponseCode xmlns:e="http://schemas.microsoft.com/exchange/services/2006/errors">ErrorServerBusy</e:ResponseCode>
+        <e:Message xmlns:e="http://schemas.microsoft.com/exchange/services/2006/errors">The server cannot service this request right now.</e:Message>
+      </detail>
+    </s:Fault>
+  </s:Body>
+</s:Envelope>"#;
+
+    let fault = parse_soap_fault(xml).expect("fault");
+    assert_eq!(fault.code, "ErrorServerBusy");
+    assert_eq!(
+        fault.reason,
+        "The server cannot service this request right now. Try again later."
+    );
+    assert_eq!(fault.detail_code.as_deref(), Some("ErrorServerBusy"));
+    assert!(fault.is_server_busy());
+    assert!(fault.is_retriable());
+}
+
+#[test]
+fn test_parse_soap_fault_soap12_with_subcode() {
+    let xml = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope">
+  <s:Body>
+    <s:Fault>
+      <s:Code>
+        <s:Value>s:Sender</s:Value>
+        <s:Subcode><s:Value>a:InvalidDomain</s:Value></s:Subcode>
+      </s:Code>
+      <s:Reason><s:Text xml:lang="en-US">The domain is not valid.</s:Text></s:Reason>
+    </s:Fault>
+  </s:Body>
+</s:Envelope>"#;
+
+    let fault = parse_soap_fault(xml).expect("fault");
+    assert_eq!(fault.code, "Sender");
+    assert_eq!(fault.reason, "The domain is not valid.");
+    assert_eq!(fault.detail_code.as_deref(), Some("InvalidDomain"));
+    assert!(fault.is_invalid_domain());
+    assert!(!fault.is_retriable());
+    assert_eq!(
+        fault.to_string(),
+        "SOAP fault Sender: The domain is not valid. (InvalidDomain)"
+    );
+}
+
+#[test]
+fn test_parse_soap_fault_ignores_other_documents() {
+    assert!(parse_soap_fault("").is_none());
+    assert!(parse_soap_fault("<s:Envelope><s:Body><s:Fault>").is_none());
+    // A Fault element outside the SOAP body is not a fault
+    assert!(parse_soap_fault("<Envelope><Header><Fault/></Header><Body/></Envelope>").is_none());
+    assert!(parse_soap_fault(
+        r#"<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/"><soap:Body>
+            <GetFederationInformationResponse><Response><Domain>contoso.com</Domain></Response></GetFederationInformationResponse>
+        </soap:Body></soap:Envelope>"#
+    )
+    .is_none());
+}
+
+#[test]
+fn test_parse_federation_response_surfaces_soap_fault() {
+    let xml = r#"<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+  <soap:Body>
+    <soap:Fault>
+      <faultcode>soap:Server</faultcode>
+      <faultstring>Server busy</faultstring>
+    </soap:Fault>
+  </soap:Body>
+</soap:Envelope>"#;
+
+    let err = XmlParser::new().parse_federation_response(xml).unwrap_err();
+    let fault = err.downcast_ref::<SoapFault>().expect("SOAP fault error");
+    assert_eq!(fault.code, "Server");
+    assert!(fault.is_retriable());
+    assert_eq!(ErrorClass::classify(&err), ErrorClass::RateLimited);
+}
+
+const FEDERATION_RESPONSE: &str = r#"<?xml version="1.0" encoding="utf-16"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+  <soap:Body>
+    <GetFederationInformationResponse xmlns="http://schemas.microsoft.com/exchange/2010/Autodiscover">
+      <Response>
+        <Domain>contoso.com</Domain>
+        <Domain>contoso.onmicrosoft.com</Domain>
+      </Response>
+    </GetFederationInformationResponse>
+  </soap:Body>
+</soap:Envelope>"#;
+
+fn utf16(text: &str, big_endian: bool, bom: bool) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let units = bom
+        .then_some(0xFEFF_u16)
+        .into_iter()
+        .chain(text.encode_utf16());
+    for unit in units {
+        if big_endian {
+            bytes.extend(unit.to_be_bytes());
+        } else {
+            bytes.extend(unit.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+#[test]
+fn test_decode_response_transcodes_utf16() -> Result<()> {
+    let parser = XmlParser::new();
+    for (big_endian, bom) in [(false, true), (true, true), (false, false), (true, false)] {
+        let body = utf16(FEDERATION_RESPONSE, big_endian, bom);
+        let text = decode_response(&body, None)?;
+        assert_eq!(
+            text, FEDERATION_RESPONSE,
+            "big_endian={big_endian} bom={bom}"
+        );
+        assert_eq!(parser.parse_federation_response(&text)?.domains.len(), 2);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decode_response_uses_charset_then_declaration() -> Result<()> {
+    let latin1 = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>caf\xe9</a>";
+    assert_eq!(
+        decode_response(latin1, None)?,
+        "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>caf\u{e9}</a>"
+    );
+    assert_eq!(
+        decode_response(
+            b"<a>caf\xe9</a>",
+            Some("text/xml; charset=\"windows-1252\"")
+        )?,
+        "<a>caf\u{e9}</a>"
+    );
+
+    // A UTF-16 declaration kept on a body re-encoded to UTF-8 is ignored
+    let text = decode_response(FEDERATION_RESPONSE.as_bytes(), Some("text/xml"))?;
+    assert_eq!(text, FEDERATION_RESPONSE);
+
+    let mut bom = b"\xEF\xBB\xBF".to_vec();
+    bom.extend(FEDERATION_RESPONSE.as_bytes());
+    assert_eq!(decode_response(&bom, None)?, FEDERATION_RESPONSE);
+    Ok(())
+}
+
+#[test]
+fn test_decode_response_unescapes_entity_encoded_body() -> Result<()> {
+    let escaped = "\n&lt;a b=&quot;1&quot;&gt;contoso.com&lt;/a&gt;";
+    assert_eq!(
+        decode_response(escaped.as_bytes(), None)?,
+        r#"<a b="1">contoso.com</a>"#
+    );
+    Ok(())
+}
+
+#[test]
+fn test_decode_response_rejects_invalid_bytes() {
+    let err = decode_response(b"<a>caf\xe9</a>", None).unwrap_err();
+    assert!(err.to_string().contains("not valid UTF-8"), "{err}");
+
+    let err = decode_response(b"\xFF\xFE<\x00\x00\xD8", None).unwrap_err();
+    assert!(err.to_string().contains("UTF-16LE"), "{err}");
+}
+
+#[test]
+fn test_parse_federation_response_skips_byte_order_mark() -> Result<()> {
+    let xml = format!("\u{feff}{}", FEDERATION_RESPONSE);
+    let info = XmlParser::new().parse_federation_response(&xml)?;
+    assert_eq!(info.domains.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_localized_faults_normalize_to_error_codes() {
+    let fault = |reason: &str| SoapFault {
+        code: "Server".to_string(),
+        reason: reason.to_string(),
+        detail_code: None,
+    };
+
+    let busy = fault("Der Server ist ausgelastet. Versuchen Sie es später erneut.");
+    assert!(busy.is_server_busy());
+    assert!(busy.is_retriable());
+    assert_eq!(busy.error_code(), Some(SERVER_BUSY_ERROR));
+    assert!(busy.to_string().ends_with("(ErrorServerBusy)"));
+
+    let invalid = fault("Domaine non valide : contoso.example");
+    assert!(invalid.is_invalid_domain());
+    assert!(!invalid.is_retriable());
+    assert_eq!(invalid.error_code(), Some(INVALID_DOMAIN_ERROR));
+
+    // Case is ignored beyond ASCII
+    assert_eq!(
+        normalize_fault_message("UNGÜLTIGE DOMÄNE"),
+        Some(INVALID_DOMAIN_ERROR)
+    );
+
+    // The detail code wins over the message
+    let detailed = SoapFault {
+        detail_code: Some("ErrorInternalServerError".to_string()),
+        ..fault("Interner Serverfehler")
+    };
+    assert_eq!(detailed.error_code(), Some("ErrorInternalServerError"));
+    assert!(!detailed.is_retriable());
+}